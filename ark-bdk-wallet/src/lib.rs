@@ -3,10 +3,12 @@ use ark_client::error::Error;
 use ark_client::error::ErrorContext;
 use ark_client::wallet::Balance;
 use ark_client::wallet::BoardingWallet;
+use ark_client::wallet::ClientStore;
 use ark_client::wallet::OnchainWallet;
 use ark_client::wallet::Persistence;
 use ark_core::BoardingOutput;
 use bdk_esplora::EsploraAsyncExt;
+use bdk_wallet::CreateParams;
 use bdk_wallet::KeychainKind;
 use bdk_wallet::SignOptions;
 use bdk_wallet::TxOrdering;
@@ -14,6 +16,7 @@ use bdk_wallet::Wallet as BdkWallet;
 use bitcoin::bip32::Xpriv;
 use bitcoin::key::Keypair;
 use bitcoin::key::Secp256k1;
+use bitcoin::psbt;
 use bitcoin::secp256k1::schnorr::Signature;
 use bitcoin::secp256k1::All;
 use bitcoin::secp256k1::Message;
@@ -21,7 +24,11 @@ use bitcoin::Address;
 use bitcoin::Amount;
 use bitcoin::FeeRate;
 use bitcoin::Network;
+use bitcoin::OutPoint;
 use bitcoin::Psbt;
+use bitcoin::Transaction;
+use bitcoin::Weight;
+use bitcoin::Witness;
 use bitcoin::XOnlyPublicKey;
 use jiff::Timestamp;
 use std::collections::BTreeSet;
@@ -32,6 +39,18 @@ use std::sync::RwLock;
 #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
 mod utils;
 
+/// The weight of the empty witness spending a pay-to-anchor output: a single byte for the number
+/// of witness elements.
+const ANCHOR_SATISFACTION_WEIGHT: Weight = Weight::from_wu(1);
+
+/// The number of consecutive unused addresses after which a full scan stops looking for more,
+/// unless configured otherwise with [`Wallet::with_scan_options`].
+const DEFAULT_STOP_GAP: usize = 5;
+
+/// The number of requests sent to the Esplora server in parallel during a full scan, unless
+/// configured otherwise with [`Wallet::with_scan_options`].
+const DEFAULT_PARALLEL_REQUESTS: usize = 5;
+
 pub struct Wallet<DB>
 where
     DB: Persistence,
@@ -43,6 +62,8 @@ where
     client: esplora_client::AsyncClient,
     #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
     client: esplora_client::AsyncClient<WebSleeper>,
+    stop_gap: usize,
+    parallel_requests: usize,
     db: DB,
 }
 
@@ -50,6 +71,7 @@ impl<DB> Wallet<DB>
 where
     DB: Persistence,
 {
+    /// A wallet whose on-chain funds are held by BIP84 descriptors derived from `kp`.
     pub fn new(
         kp: Keypair,
         secp: Secp256k1<All>,
@@ -61,9 +83,47 @@ where
         let xprv = Xpriv::new_master(network, key.as_ref())?;
         let external = bdk_wallet::template::Bip84(xprv, KeychainKind::External);
         let change = bdk_wallet::template::Bip84(xprv, KeychainKind::Internal);
-        let wallet = BdkWallet::create(external, change)
-            .network(network)
-            .create_wallet_no_persist()?;
+
+        Self::with_params(
+            kp,
+            secp,
+            BdkWallet::create(external, change).network(network),
+            esplora_url,
+            db,
+        )
+    }
+
+    /// A wallet whose on-chain funds are held by the descriptors `external_descriptor` and
+    /// `internal_descriptor`, the latter receiving change.
+    ///
+    /// The descriptors must include private keys for the wallet to sign on-chain transactions.
+    /// Boarding outputs and VTXOs are still owned by `kp`.
+    pub fn from_descriptors(
+        kp: Keypair,
+        secp: Secp256k1<All>,
+        network: Network,
+        external_descriptor: String,
+        internal_descriptor: String,
+        esplora_url: &str,
+        db: DB,
+    ) -> Result<Self> {
+        Self::with_params(
+            kp,
+            secp,
+            BdkWallet::create(external_descriptor, internal_descriptor).network(network),
+            esplora_url,
+            db,
+        )
+    }
+
+    fn with_params(
+        kp: Keypair,
+        secp: Secp256k1<All>,
+        params: CreateParams,
+        esplora_url: &str,
+        db: DB,
+    ) -> Result<Self> {
+        let wallet = params.create_wallet_no_persist()?;
 
         #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
         let client = esplora_client::Builder::new(esplora_url).build_async_with_sleeper()?;
@@ -77,16 +137,38 @@ where
             secp,
             inner: Arc::new(RwLock::new(wallet)),
             client,
+            stop_gap: DEFAULT_STOP_GAP,
+            parallel_requests: DEFAULT_PARALLEL_REQUESTS,
             db,
         })
     }
+
+    /// Stop a full scan after `stop_gap` consecutive unused addresses, sending up to
+    /// `parallel_requests` requests to the Esplora server at a time.
+    pub fn with_scan_options(mut self, stop_gap: usize, parallel_requests: usize) -> Self {
+        self.stop_gap = stop_gap;
+        self.parallel_requests = parallel_requests;
+        self
+    }
+
+    /// The external and internal descriptors of the wallet, without private keys.
+    ///
+    /// They can be backed up, or imported into another wallet to watch our on-chain funds.
+    pub fn public_descriptors(&self) -> (String, String) {
+        let wallet = self.inner.read().expect("read lock");
+
+        (
+            wallet.public_descriptor(KeychainKind::External).to_string(),
+            wallet.public_descriptor(KeychainKind::Internal).to_string(),
+        )
+    }
 }
 
 impl<DB> OnchainWallet for Wallet<DB>
 where
     DB: Persistence + Send + Sync,
 {
-    fn get_onchain_address(&self) -> Result<Address, Error> {
+    async fn get_onchain_address(&self) -> Result<Address, Error> {
         let info = self
             .inner
             .write()
@@ -119,10 +201,9 @@ where
             .try_into()
             .map_err(Error::wallet)?;
 
-        // TODO: Use smarter constants or make it configurable.
         let update = self
             .client
-            .full_scan(request, 5, 5)
+            .full_scan(request, self.stop_gap, self.parallel_requests)
             .await
             .map_err(Error::wallet)
             .context("Failed syncing wallet")?;
@@ -136,7 +217,7 @@ where
         Ok(())
     }
 
-    fn balance(&self) -> Result<Balance, Error> {
+    async fn balance(&self) -> Result<Balance, Error> {
         let balance = self.inner.read().expect("read lock").balance();
 
         Ok(Balance {
@@ -147,7 +228,7 @@ where
         })
     }
 
-    fn prepare_send_to_address(
+    async fn prepare_send_to_address(
         &self,
         address: Address,
         amount: Amount,
@@ -164,7 +245,7 @@ where
         Ok(psbt)
     }
 
-    fn sign(&self, psbt: &mut Psbt) -> Result<bool, Error> {
+    async fn sign(&self, psbt: &mut Psbt) -> Result<bool, Error> {
         let finalized = self
             .inner
             .read()
@@ -174,12 +255,85 @@ where
 
         Ok(finalized)
     }
+
+    async fn prepare_cpfp(&self, parent: &Transaction, fee_rate: FeeRate) -> Result<Psbt, Error> {
+        let wallet = &mut self.inner.write().expect("write lock");
+
+        let parent_txid = parent.compute_txid();
+        let own_outpoints = parent
+            .output
+            .iter()
+            .enumerate()
+            .filter(|(_, o)| wallet.is_mine(o.script_pubkey.clone()))
+            .map(|(vout, _)| OutPoint::new(parent_txid, vout as u32))
+            .collect::<Vec<_>>();
+
+        if own_outpoints.is_empty() {
+            return Err(Error::wallet(format!(
+                "no output of transaction {parent_txid} belongs to the wallet"
+            )));
+        }
+
+        let drain_address = wallet.next_unused_address(KeychainKind::Internal);
+
+        let mut b = wallet.build_tx();
+        b.add_utxos(&own_outpoints).map_err(Error::wallet)?;
+        b.manually_selected_only();
+        b.drain_to(drain_address.script_pubkey());
+        b.fee_rate(fee_rate);
+
+        let psbt = b.finish().map_err(Error::wallet)?;
+
+        Ok(psbt)
+    }
+
+    async fn prepare_anchor_cpfp(
+        &self,
+        parent: &Transaction,
+        anchor_vout: u32,
+        fee_rate: FeeRate,
+    ) -> Result<Psbt, Error> {
+        let wallet = &mut self.inner.write().expect("write lock");
+
+        let parent_txid = parent.compute_txid();
+        let anchor = parent.output.get(anchor_vout as usize).ok_or_else(|| {
+            Error::wallet(format!(
+                "transaction {parent_txid} has no output at index {anchor_vout}"
+            ))
+        })?;
+
+        // Anyone can spend the anchor with an empty witness, so we finalize it right away.
+        let anchor_input = psbt::Input {
+            witness_utxo: Some(anchor.clone()),
+            non_witness_utxo: Some(parent.clone()),
+            final_script_witness: Some(Witness::new()),
+            ..Default::default()
+        };
+
+        let drain_address = wallet.next_unused_address(KeychainKind::Internal);
+
+        let mut b = wallet.build_tx();
+        b.add_foreign_utxo(
+            OutPoint::new(parent_txid, anchor_vout),
+            anchor_input,
+            ANCHOR_SATISFACTION_WEIGHT,
+        )
+        .map_err(Error::wallet)?;
+        b.drain_to(drain_address.script_pubkey());
+        b.fee_rate(fee_rate);
+
+        let psbt = b.finish().map_err(Error::wallet)?;
+
+        Ok(psbt)
+    }
 }
 
 impl<DB> BoardingWallet for Wallet<DB>
 where
-    DB: Persistence,
+    DB: Persistence + ClientStore + Send + Sync,
 {
+    type Store = DB;
+
     fn new_boarding_output(
         &self,
         server_pk: XOnlyPublicKey,
@@ -210,7 +364,11 @@ where
         self.db.load_boarding_outputs()
     }
 
-    fn sign_for_pk(&self, pk: &XOnlyPublicKey, msg: &Message) -> Result<Signature, Error> {
+    fn store(&self) -> &DB {
+        &self.db
+    }
+
+    async fn sign_for_pk(&self, pk: &XOnlyPublicKey, msg: &Message) -> Result<Signature, Error> {
         let key = self
             .db
             .sk_for_pk(pk)
@@ -222,6 +380,16 @@ where
 
         Ok(sig)
     }
+
+    fn unlock_keys(&self, passphrase: &str) -> Result<(), Error> {
+        self.db
+            .unlock(passphrase)
+            .context("Failed unlocking secret keys")
+    }
+
+    fn lock_keys(&self) -> Result<(), Error> {
+        self.db.lock().context("Failed locking secret keys")
+    }
 }
 
 #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
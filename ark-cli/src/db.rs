@@ -0,0 +1,752 @@
+//! The wallet database of the CLI, in SQLite.
+//!
+//! Only what is needed across invocations is written to disk: the settings of the wallet, its
+//! boarding outputs, the transactions of unilateral exits, the forfeit transactions we signed and
+//! the out-of-round payments we claimed.
+//! Everything else the client persists is kept in memory for the duration of a single command:
+//! it is either fetched again from the Ark server, or not used by the CLI.
+
+use anyhow::Context;
+use anyhow::Result;
+use ark_client::config::ClientConfig;
+use ark_client::contacts::Contact;
+use ark_client::wallet::ClientStore;
+use ark_client::wallet::ExitTx;
+use ark_client::wallet::ExitTxStatus;
+use ark_client::wallet::ForfeitRecord;
+use ark_client::wallet::Persistence;
+use ark_client::wallet::VtxoExit;
+use ark_client::wallet::VtxoOrigin;
+use ark_client::wallet::VtxoRiskStatus;
+use ark_client::wallet::WalletBirthday;
+use ark_client::watch_only::WatchedAddress;
+use ark_client::Error;
+use ark_core::receipt::PaymentReceipt;
+use ark_core::server;
+use ark_core::server::ListVtxo;
+use ark_core::server::VtxoOutPoint;
+use ark_core::ArkAddress;
+use ark_core::ArkTransaction;
+use ark_core::BoardingOutput;
+use ark_core::Direction;
+use bitcoin::consensus;
+use bitcoin::key::Secp256k1;
+use bitcoin::secp256k1::SecretKey;
+use bitcoin::Network;
+use bitcoin::OutPoint;
+use bitcoin::Sequence;
+use bitcoin::Txid;
+use bitcoin::XOnlyPublicKey;
+use rusqlite::params;
+use rusqlite::Connection;
+use rusqlite::OptionalExtension;
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::sync::RwLock;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS wallet (
+    id INTEGER PRIMARY KEY CHECK (id = 0),
+    secret_key TEXT NOT NULL,
+    network TEXT NOT NULL,
+    ark_server_url TEXT NOT NULL,
+    esplora_url TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS boarding_outputs (
+    address TEXT PRIMARY KEY,
+    secret_key TEXT NOT NULL,
+    server_pk TEXT NOT NULL,
+    descriptor TEXT NOT NULL,
+    exit_delay INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS exit_txs (
+    txid TEXT PRIMARY KEY,
+    tx BLOB NOT NULL,
+    status TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS forfeits (
+    vtxo_outpoint TEXT PRIMARY KEY,
+    connector_outpoint TEXT NOT NULL,
+    forfeit_txid TEXT NOT NULL,
+    round_txid TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS birthday (
+    id INTEGER PRIMARY KEY CHECK (id = 0),
+    timestamp INTEGER NOT NULL,
+    height INTEGER
+);
+CREATE TABLE IF NOT EXISTS claimed_deliveries (
+    outpoint TEXT PRIMARY KEY
+);
+CREATE TABLE IF NOT EXISTS deliveries_initialized (
+    id INTEGER PRIMARY KEY CHECK (id = 0)
+);
+";
+
+/// What `ark-cli init` stores, to connect to the Ark server on every other command.
+pub struct WalletSettings {
+    pub secret_key: SecretKey,
+    pub network: Network,
+    pub ark_server_url: String,
+    pub esplora_url: String,
+}
+
+pub struct SqliteDb {
+    conn: Mutex<Connection>,
+    cache: Cache,
+}
+
+/// The state of the client which is not written to disk.
+#[derive(Default)]
+struct Cache {
+    vtxo_origins: RwLock<HashMap<OutPoint, VtxoOrigin>>,
+    server_info: RwLock<Option<server::Info>>,
+    vtxo_lists: RwLock<HashMap<String, (ListVtxo, i64)>>,
+    vtxo_risk_statuses: RwLock<HashMap<OutPoint, VtxoRiskStatus>>,
+    config: RwLock<Option<ClientConfig>>,
+    imported_vtxos: RwLock<HashMap<String, Vec<VtxoOutPoint>>>,
+    contacts: RwLock<HashMap<String, Contact>>,
+    receipts: RwLock<HashMap<OutPoint, PaymentReceipt>>,
+    processed_events: RwLock<Vec<String>>,
+    vtxo_exits: RwLock<HashMap<OutPoint, VtxoExit>>,
+    watched_addresses: RwLock<HashMap<String, WatchedAddress>>,
+    archived_transactions: RwLock<HashMap<(Txid, bool), ArkTransaction>>,
+}
+
+impl SqliteDb {
+    /// Open the database at `path`, creating it if it does not exist.
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("failed to open database {}", path.display()))?;
+        conn.execute_batch(SCHEMA)
+            .context("failed to create database schema")?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+            cache: Cache::default(),
+        })
+    }
+
+    pub fn save_settings(&self, settings: &WalletSettings) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO wallet (id, secret_key, network, ark_server_url, esplora_url) \
+             VALUES (0, ?1, ?2, ?3, ?4)",
+            params![
+                settings.secret_key.display_secret().to_string(),
+                settings.network.to_string(),
+                settings.ark_server_url,
+                settings.esplora_url,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn load_settings(&self) -> Result<Option<WalletSettings>> {
+        let row = self
+            .conn()
+            .query_row(
+                "SELECT secret_key, network, ark_server_url, esplora_url FROM wallet",
+                [],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                    ))
+                },
+            )
+            .optional()?;
+
+        let Some((secret_key, network, ark_server_url, esplora_url)) = row else {
+            return Ok(None);
+        };
+
+        Ok(Some(WalletSettings {
+            secret_key: SecretKey::from_str(&secret_key)?,
+            network: Network::from_str(&network)?,
+            ark_server_url,
+            esplora_url,
+        }))
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().expect("lock not poisoned")
+    }
+}
+
+impl Persistence for SqliteDb {
+    fn save_boarding_output(
+        &self,
+        sk: SecretKey,
+        boarding_output: BoardingOutput,
+    ) -> Result<(), Error> {
+        // The descriptor of the boarding output no longer contains the `USER` placeholder, so
+        // passing it to `BoardingOutput::new` as the template yields the same boarding output.
+        self.conn()
+            .execute(
+                "INSERT OR IGNORE INTO boarding_outputs \
+                 (address, secret_key, server_pk, descriptor, exit_delay) \
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    boarding_output.address().to_string(),
+                    sk.display_secret().to_string(),
+                    boarding_output.server_pk().to_string(),
+                    boarding_output.ark_descriptor(),
+                    boarding_output.exit_delay().to_consensus_u32(),
+                ],
+            )
+            .map_err(Error::wallet)?;
+
+        Ok(())
+    }
+
+    fn load_boarding_outputs(&self) -> Result<Vec<BoardingOutput>, Error> {
+        Ok(self
+            .load_boarding_outputs_with_keys()?
+            .into_iter()
+            .map(|(_, boarding_output)| boarding_output)
+            .collect())
+    }
+
+    fn sk_for_pk(&self, pk: &XOnlyPublicKey) -> Result<SecretKey, Error> {
+        self.load_boarding_outputs_with_keys()?
+            .into_iter()
+            .find_map(|(sk, b)| (b.owner_pk() == *pk).then_some(sk))
+            .ok_or_else(|| Error::wallet(format!("no secret key for public key {pk}")))
+    }
+}
+
+impl ClientStore for SqliteDb {
+    fn save_vtxo_origin(&self, outpoint: OutPoint, origin: VtxoOrigin) -> Result<(), Error> {
+        self.cache
+            .vtxo_origins
+            .write()
+            .expect("lock not poisoned")
+            .insert(outpoint, origin);
+
+        Ok(())
+    }
+
+    fn load_vtxo_origin(&self, outpoint: &OutPoint) -> Result<Option<VtxoOrigin>, Error> {
+        Ok(self
+            .cache
+            .vtxo_origins
+            .read()
+            .expect("lock not poisoned")
+            .get(outpoint)
+            .cloned())
+    }
+
+    fn save_server_info(&self, info: server::Info) -> Result<(), Error> {
+        *self.cache.server_info.write().expect("lock not poisoned") = Some(info);
+
+        Ok(())
+    }
+
+    fn load_server_info(&self) -> Result<Option<server::Info>, Error> {
+        Ok(self
+            .cache
+            .server_info
+            .read()
+            .expect("lock not poisoned")
+            .clone())
+    }
+
+    fn save_vtxo_list(
+        &self,
+        address: ArkAddress,
+        vtxos: ListVtxo,
+        updated_at: i64,
+    ) -> Result<(), Error> {
+        self.cache
+            .vtxo_lists
+            .write()
+            .expect("lock not poisoned")
+            .insert(address.encode(), (vtxos, updated_at));
+
+        Ok(())
+    }
+
+    fn load_vtxo_list(&self, address: &ArkAddress) -> Result<Option<(ListVtxo, i64)>, Error> {
+        Ok(self
+            .cache
+            .vtxo_lists
+            .read()
+            .expect("lock not poisoned")
+            .get(&address.encode())
+            .cloned())
+    }
+
+    fn save_vtxo_risk_status(
+        &self,
+        outpoint: OutPoint,
+        status: VtxoRiskStatus,
+    ) -> Result<(), Error> {
+        self.cache
+            .vtxo_risk_statuses
+            .write()
+            .expect("lock not poisoned")
+            .insert(outpoint, status);
+
+        Ok(())
+    }
+
+    fn load_vtxo_risk_statuses(&self) -> Result<Vec<(OutPoint, VtxoRiskStatus)>, Error> {
+        Ok(self
+            .cache
+            .vtxo_risk_statuses
+            .read()
+            .expect("lock not poisoned")
+            .iter()
+            .map(|(outpoint, status)| (*outpoint, status.clone()))
+            .collect())
+    }
+
+    fn save_forfeit(&self, forfeit: ForfeitRecord) -> Result<(), Error> {
+        self.conn()
+            .execute(
+                "INSERT OR REPLACE INTO forfeits \
+                 (vtxo_outpoint, connector_outpoint, forfeit_txid, round_txid) \
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    forfeit.vtxo_outpoint.to_string(),
+                    forfeit.connector_outpoint.to_string(),
+                    forfeit.forfeit_txid.to_string(),
+                    forfeit.round_txid.to_string(),
+                ],
+            )
+            .map_err(Error::wallet)?;
+
+        Ok(())
+    }
+
+    fn load_forfeits(&self) -> Result<Vec<ForfeitRecord>, Error> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare(
+                "SELECT vtxo_outpoint, connector_outpoint, forfeit_txid, round_txid FROM forfeits",
+            )
+            .map_err(Error::wallet)?;
+
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            })
+            .map_err(Error::wallet)?;
+
+        rows.map(|row| {
+            let (vtxo_outpoint, connector_outpoint, forfeit_txid, round_txid) =
+                row.map_err(Error::wallet)?;
+
+            Ok(ForfeitRecord {
+                vtxo_outpoint: OutPoint::from_str(&vtxo_outpoint).map_err(Error::wallet)?,
+                connector_outpoint: OutPoint::from_str(&connector_outpoint)
+                    .map_err(Error::wallet)?,
+                forfeit_txid: Txid::from_str(&forfeit_txid).map_err(Error::wallet)?,
+                round_txid: Txid::from_str(&round_txid).map_err(Error::wallet)?,
+            })
+        })
+        .collect()
+    }
+
+    fn save_birthday(&self, birthday: WalletBirthday) -> Result<(), Error> {
+        self.conn()
+            .execute(
+                "INSERT OR REPLACE INTO birthday (id, timestamp, height) VALUES (0, ?1, ?2)",
+                params![birthday.timestamp as i64, birthday.height],
+            )
+            .map_err(Error::wallet)?;
+
+        Ok(())
+    }
+
+    fn load_birthday(&self) -> Result<Option<WalletBirthday>, Error> {
+        self.conn()
+            .query_row("SELECT timestamp, height FROM birthday", [], |row| {
+                Ok(WalletBirthday {
+                    timestamp: row.get::<_, i64>(0)? as u64,
+                    height: row.get(1)?,
+                })
+            })
+            .optional()
+            .map_err(Error::wallet)
+    }
+
+    fn save_config(&self, config: ClientConfig) -> Result<(), Error> {
+        *self.cache.config.write().expect("lock not poisoned") = Some(config);
+
+        Ok(())
+    }
+
+    fn load_config(&self) -> Result<Option<ClientConfig>, Error> {
+        Ok(self.cache.config.read().expect("lock not poisoned").clone())
+    }
+
+    fn save_claimed_delivery(&self, outpoint: OutPoint) -> Result<(), Error> {
+        self.conn()
+            .execute(
+                "INSERT OR IGNORE INTO claimed_deliveries (outpoint) VALUES (?1)",
+                params![outpoint.to_string()],
+            )
+            .map_err(Error::wallet)?;
+
+        Ok(())
+    }
+
+    fn load_claimed_deliveries(&self) -> Result<Vec<OutPoint>, Error> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare("SELECT outpoint FROM claimed_deliveries")
+            .map_err(Error::wallet)?;
+
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(Error::wallet)?;
+
+        rows.map(|row| {
+            let outpoint = row.map_err(Error::wallet)?;
+
+            OutPoint::from_str(&outpoint).map_err(Error::wallet)
+        })
+        .collect()
+    }
+
+    fn save_deliveries_initialized(&self) -> Result<(), Error> {
+        self.conn()
+            .execute(
+                "INSERT OR IGNORE INTO deliveries_initialized (id) VALUES (0)",
+                [],
+            )
+            .map_err(Error::wallet)?;
+
+        Ok(())
+    }
+
+    fn load_deliveries_initialized(&self) -> Result<bool, Error> {
+        self.conn()
+            .query_row("SELECT id FROM deliveries_initialized", [], |_| Ok(()))
+            .optional()
+            .map(|row| row.is_some())
+            .map_err(Error::wallet)
+    }
+
+    fn save_exit_tx(&self, exit_tx: ExitTx) -> Result<(), Error> {
+        let status = match exit_tx.status {
+            ExitTxStatus::Pending => "pending",
+            ExitTxStatus::Broadcast => "broadcast",
+            ExitTxStatus::Confirmed => "confirmed",
+        };
+
+        self.conn()
+            .execute(
+                "INSERT OR REPLACE INTO exit_txs (txid, tx, status) VALUES (?1, ?2, ?3)",
+                params![
+                    exit_tx.tx.compute_txid().to_string(),
+                    consensus::serialize(&exit_tx.tx),
+                    status,
+                ],
+            )
+            .map_err(Error::wallet)?;
+
+        Ok(())
+    }
+
+    fn load_exit_txs(&self) -> Result<Vec<ExitTx>, Error> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare("SELECT tx, status FROM exit_txs")
+            .map_err(Error::wallet)?;
+
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(Error::wallet)?;
+
+        rows.map(|row| {
+            let (tx, status) = row.map_err(Error::wallet)?;
+
+            let status = match status.as_str() {
+                "pending" => ExitTxStatus::Pending,
+                "broadcast" => ExitTxStatus::Broadcast,
+                "confirmed" => ExitTxStatus::Confirmed,
+                status => {
+                    return Err(Error::wallet(format!("unknown exit TX status {status}")));
+                }
+            };
+
+            Ok(ExitTx {
+                tx: consensus::deserialize(&tx).map_err(Error::wallet)?,
+                status,
+            })
+        })
+        .collect()
+    }
+
+    fn delete_exit_tx(&self, txid: Txid) -> Result<(), Error> {
+        self.conn()
+            .execute(
+                "DELETE FROM exit_txs WHERE txid = ?1",
+                params![txid.to_string()],
+            )
+            .map_err(Error::wallet)?;
+
+        Ok(())
+    }
+
+    fn save_imported_vtxo(&self, address: ArkAddress, vtxo: VtxoOutPoint) -> Result<(), Error> {
+        self.cache
+            .imported_vtxos
+            .write()
+            .expect("lock not poisoned")
+            .entry(address.encode())
+            .or_default()
+            .push(vtxo);
+
+        Ok(())
+    }
+
+    fn load_imported_vtxos(&self, address: &ArkAddress) -> Result<Vec<VtxoOutPoint>, Error> {
+        Ok(self
+            .cache
+            .imported_vtxos
+            .read()
+            .expect("lock not poisoned")
+            .get(&address.encode())
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn delete_imported_vtxo(&self, address: &ArkAddress, outpoint: OutPoint) -> Result<(), Error> {
+        if let Some(vtxos) = self
+            .cache
+            .imported_vtxos
+            .write()
+            .expect("lock not poisoned")
+            .get_mut(&address.encode())
+        {
+            vtxos.retain(|vtxo| vtxo.outpoint != outpoint);
+        }
+
+        Ok(())
+    }
+
+    fn save_contact(&self, contact: Contact) -> Result<(), Error> {
+        self.cache
+            .contacts
+            .write()
+            .expect("lock not poisoned")
+            .insert(contact.name.clone(), contact);
+
+        Ok(())
+    }
+
+    fn load_contacts(&self) -> Result<Vec<Contact>, Error> {
+        Ok(self
+            .cache
+            .contacts
+            .read()
+            .expect("lock not poisoned")
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    fn delete_contact(&self, name: &str) -> Result<(), Error> {
+        self.cache
+            .contacts
+            .write()
+            .expect("lock not poisoned")
+            .remove(name);
+
+        Ok(())
+    }
+
+    fn save_receipt(&self, receipt: PaymentReceipt) -> Result<(), Error> {
+        self.cache
+            .receipts
+            .write()
+            .expect("lock not poisoned")
+            .insert(receipt.vtxo_outpoint, receipt);
+
+        Ok(())
+    }
+
+    fn load_receipts(&self) -> Result<Vec<PaymentReceipt>, Error> {
+        Ok(self
+            .cache
+            .receipts
+            .read()
+            .expect("lock not poisoned")
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    fn save_processed_event(&self, event_id: String) -> Result<(), Error> {
+        self.cache
+            .processed_events
+            .write()
+            .expect("lock not poisoned")
+            .push(event_id);
+
+        Ok(())
+    }
+
+    fn load_processed_events(&self) -> Result<Vec<String>, Error> {
+        Ok(self
+            .cache
+            .processed_events
+            .read()
+            .expect("lock not poisoned")
+            .clone())
+    }
+
+    fn prune_processed_events(&self, keep: usize) -> Result<(), Error> {
+        let mut processed_events = self
+            .cache
+            .processed_events
+            .write()
+            .expect("lock not poisoned");
+
+        let n_pruned = processed_events.len().saturating_sub(keep);
+        processed_events.drain(..n_pruned);
+
+        Ok(())
+    }
+
+    fn save_vtxo_exit(&self, exit: VtxoExit) -> Result<(), Error> {
+        self.cache
+            .vtxo_exits
+            .write()
+            .expect("lock not poisoned")
+            .insert(exit.vtxo_outpoint, exit);
+
+        Ok(())
+    }
+
+    fn load_vtxo_exits(&self) -> Result<Vec<VtxoExit>, Error> {
+        Ok(self
+            .cache
+            .vtxo_exits
+            .read()
+            .expect("lock not poisoned")
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    fn save_watched_address(&self, watched: WatchedAddress) -> Result<(), Error> {
+        self.cache
+            .watched_addresses
+            .write()
+            .expect("lock not poisoned")
+            .insert(watched.address.encode(), watched);
+
+        Ok(())
+    }
+
+    fn load_watched_addresses(&self) -> Result<Vec<WatchedAddress>, Error> {
+        Ok(self
+            .cache
+            .watched_addresses
+            .read()
+            .expect("lock not poisoned")
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    fn delete_watched_address(&self, address: &ArkAddress) -> Result<(), Error> {
+        self.cache
+            .watched_addresses
+            .write()
+            .expect("lock not poisoned")
+            .remove(&address.encode());
+
+        Ok(())
+    }
+
+    fn save_archived_transaction(&self, tx: ArkTransaction) -> Result<(), Error> {
+        let is_incoming = tx.amount().direction == Direction::Incoming;
+
+        self.cache
+            .archived_transactions
+            .write()
+            .expect("lock not poisoned")
+            .insert((tx.txid(), is_incoming), tx);
+
+        Ok(())
+    }
+
+    fn load_archived_transactions(&self) -> Result<Vec<ArkTransaction>, Error> {
+        Ok(self
+            .cache
+            .archived_transactions
+            .read()
+            .expect("lock not poisoned")
+            .values()
+            .cloned()
+            .collect())
+    }
+}
+
+impl SqliteDb {
+    fn load_boarding_outputs_with_keys(&self) -> Result<Vec<(SecretKey, BoardingOutput)>, Error> {
+        let conn = self.conn();
+
+        // Boarding outputs are on the network of the wallet.
+        let network = conn
+            .query_row("SELECT network FROM wallet", [], |row| {
+                row.get::<_, String>(0)
+            })
+            .map_err(Error::wallet)?;
+        let network = Network::from_str(&network).map_err(Error::wallet)?;
+
+        let mut stmt = conn
+            .prepare("SELECT secret_key, server_pk, descriptor, exit_delay FROM boarding_outputs")
+            .map_err(Error::wallet)?;
+
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, u32>(3)?,
+                ))
+            })
+            .map_err(Error::wallet)?;
+
+        let secp = Secp256k1::new();
+
+        rows.map(|row| {
+            let (sk, server_pk, descriptor, exit_delay) = row.map_err(Error::wallet)?;
+
+            let sk = SecretKey::from_str(&sk).map_err(Error::wallet)?;
+            let (owner_pk, _) = sk.x_only_public_key(&secp);
+            let server_pk = XOnlyPublicKey::from_str(&server_pk).map_err(Error::wallet)?;
+
+            let boarding_output = BoardingOutput::new(
+                &secp,
+                server_pk,
+                owner_pk,
+                &descriptor,
+                Sequence::from_consensus(exit_delay),
+                network,
+            );
+
+            Ok((sk, boarding_output))
+        })
+        .collect()
+    }
+}
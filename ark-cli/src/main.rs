@@ -0,0 +1,243 @@
+#![allow(clippy::print_stdout)]
+
+//! A reference Ark wallet in your terminal, built on `ark-client`.
+//!
+//! The wallet talks to the Ark server over gRPC, watches the blockchain via Esplora and keeps its
+//! state in SQLite (see [`db`]).
+
+use crate::db::SqliteDb;
+use crate::db::WalletSettings;
+use anyhow::bail;
+use anyhow::Result;
+use ark_client::esplora::EsploraBlockchain;
+use ark_client::Client;
+use ark_client::OfflineClient;
+use ark_core::ArkAddress;
+use ark_core::ArkTransaction;
+use ark_core::Direction;
+use bitcoin::key::Keypair;
+use bitcoin::key::Secp256k1;
+use bitcoin::secp256k1::SecretKey;
+use bitcoin::Amount;
+use bitcoin::Network;
+use clap::Parser;
+use clap::Subcommand;
+use rand::thread_rng;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+mod db;
+
+type CliClient = Client<EsploraBlockchain, ark_bdk_wallet::Wallet<SqliteDb>>;
+
+#[derive(Parser)]
+#[command(name = "ark-cli")]
+#[command(about = "A reference Ark wallet built on ark-client")]
+struct Cli {
+    /// Path to the wallet database.
+    #[arg(short, long, default_value = "ark-cli.sqlite")]
+    db: PathBuf,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Create a new wallet.
+    Init {
+        /// The Bitcoin network of the Ark server.
+        #[arg(long, default_value = "regtest")]
+        network: Network,
+        /// The URL of the Ark server.
+        #[arg(long, default_value = "http://localhost:7070")]
+        ark_server: String,
+        /// The URL of the Esplora server.
+        #[arg(long, default_value = "http://localhost:3000")]
+        esplora: String,
+        /// Import this secret key, in hex, instead of generating a new one.
+        #[arg(long)]
+        secret_key: Option<SecretKey>,
+    },
+    /// Show the off-chain balance.
+    Balance,
+    /// Show the Ark address and the boarding address.
+    Address,
+    /// Lift confirmed boarding outputs and pending VTXOs into the Ark.
+    Board,
+    /// Send coins to an Ark address.
+    Send {
+        /// Where to send the coins to.
+        address: ArkAddressCli,
+        /// How many sats to send.
+        amount: u64,
+    },
+    /// Show the transaction history.
+    History,
+    /// Publish our VTXOs on chain, or resume an exit which was interrupted.
+    Exit,
+}
+
+#[derive(Clone)]
+struct ArkAddressCli(ArkAddress);
+
+impl FromStr for ArkAddressCli {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let address = ArkAddress::decode(s)?;
+
+        Ok(Self(address))
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    init_tracing();
+
+    let cli = Cli::parse();
+
+    let db = SqliteDb::open(&cli.db)?;
+
+    if let Commands::Init {
+        network,
+        ark_server,
+        esplora,
+        secret_key,
+    } = cli.command
+    {
+        if db.load_settings()?.is_some() {
+            bail!("a wallet already exists in {}", cli.db.display());
+        }
+
+        db.save_settings(&WalletSettings {
+            secret_key: secret_key.unwrap_or_else(|| SecretKey::new(&mut thread_rng())),
+            network,
+            ark_server_url: ark_server,
+            esplora_url: esplora,
+        })?;
+
+        println!("Created a {network} wallet in {}", cli.db.display());
+
+        return Ok(());
+    }
+
+    let client = connect(db).await?;
+
+    run(&client, cli.command).await.map_err(anyhow::Error::msg)
+}
+
+async fn connect(db: SqliteDb) -> Result<CliClient> {
+    let Some(settings) = db.load_settings()? else {
+        bail!("no wallet found, create one with `ark-cli init`");
+    };
+
+    let secp = Secp256k1::new();
+    let kp = Keypair::from_secret_key(&secp, &settings.secret_key);
+
+    let blockchain = EsploraBlockchain::new(&settings.esplora_url).map_err(anyhow::Error::msg)?;
+    let wallet =
+        ark_bdk_wallet::Wallet::new(kp, secp, settings.network, &settings.esplora_url, db)?;
+
+    let client = OfflineClient::new(
+        "ark-cli".to_string(),
+        kp,
+        Arc::new(blockchain),
+        Arc::new(wallet),
+        settings.ark_server_url,
+    )
+    .connect()
+    .await
+    .map_err(anyhow::Error::msg)?;
+
+    Ok(client)
+}
+
+async fn run(client: &CliClient, command: Commands) -> Result<(), ark_client::Error> {
+    match command {
+        Commands::Init { .. } => unreachable!("handled before connecting"),
+        Commands::Balance => {
+            let balance = client.offchain_balance().await?;
+
+            println!(
+                "Off-chain balance: confirmed: {}, pending: {}, total: {}",
+                balance.confirmed(),
+                balance.pending(),
+                balance.total()
+            );
+        }
+        Commands::Address => {
+            let (offchain_address, _) = client.get_offchain_address();
+            let boarding_address = client.get_boarding_address()?;
+
+            println!("Send VTXOs to this Ark address: {offchain_address}");
+            println!("Send coins to this on-chain address to board them: {boarding_address}");
+        }
+        Commands::Board => {
+            client.board(&mut thread_rng()).await?;
+
+            println!("Boarded our boarding outputs and pending VTXOs");
+        }
+        Commands::Send { address, amount } => {
+            let amount = Amount::from_sat(amount);
+            let psbt = client.send_vtxo(address.0, amount).await?;
+
+            let txid = psbt.unsigned_tx.compute_txid();
+            println!("Sent {amount} to {} in transaction {txid}", address.0);
+        }
+        Commands::History => {
+            for tx in client.transaction_history().await? {
+                println!("{}", format_transaction(&tx));
+            }
+        }
+        Commands::Exit => {
+            client.commit_vtxos_on_chain().await?;
+
+            for exit_tx in client.exit_progress()? {
+                println!("{}: {:?}", exit_tx.tx.compute_txid(), exit_tx.status);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn format_transaction(tx: &ArkTransaction) -> String {
+    let kind = match tx {
+        ArkTransaction::Boarding { .. } => "boarding",
+        ArkTransaction::Round { .. } => "round",
+        ArkTransaction::Redeem {
+            is_settled: true, ..
+        } => "redeem",
+        ArkTransaction::Redeem {
+            is_settled: false, ..
+        } => "redeem (pending)",
+    };
+
+    let amount = tx.amount();
+    let sign = match amount.direction {
+        Direction::Incoming => '+',
+        Direction::Outgoing => '-',
+    };
+
+    let time = match tx {
+        ArkTransaction::Boarding {
+            confirmed_at: None, ..
+        } => "unconfirmed".to_string(),
+        tx => jiff::Timestamp::from_second(tx.created_at())
+            .map(|t| t.to_string())
+            .unwrap_or_else(|_| tx.created_at().to_string()),
+    };
+
+    format!("{time} {kind} {sign}{} {}", amount.net, tx.txid())
+}
+
+fn init_tracing() {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn")),
+        )
+        .init()
+}
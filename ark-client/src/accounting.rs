@@ -0,0 +1,30 @@
+//! Books of the client, kept in double-entry form for financial integrators.
+//!
+//! Only available with the `accounting` feature. See [`Client::ledger`].
+
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use ark_core::accounting::Ledger;
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Every balance change of [`Client::transaction_history`] as debits and credits, with the
+    /// running balance of each account.
+    ///
+    /// Use [`Ledger::to_csv`] to export the books.
+    pub async fn ledger(&self) -> Result<Ledger, Error> {
+        let history = self.transaction_history().await?;
+
+        let ledger = Ledger::from_history(&history)?;
+
+        Ok(ledger)
+    }
+}
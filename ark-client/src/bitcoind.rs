@@ -0,0 +1,994 @@
+//! A [`Blockchain`] backed by the JSON-RPC interface of a Bitcoin Core node, for users who run a
+//! full node and no indexer.
+//!
+//! Only available with the `bitcoind` feature.
+//!
+//! Bitcoin Core only indexes what it needs to validate blocks, so:
+//!
+//! - [`Blockchain::find_outpoints`] scans the UTXO set with `scantxoutset`. It only finds
+//!   confirmed, unspent outputs.
+//! - Looking up confirmed transactions requires the node to run with `-txindex`.
+//! - [`Blockchain::get_output_status`] searches the blocks after the output was confirmed for the
+//!   transaction spending it.
+
+use crate::error::ErrorContext;
+use crate::tx_broadcast::BroadcastError;
+use crate::Blockchain;
+use crate::Error;
+use crate::ExplorerUtxo;
+use crate::SpendStatus;
+use bitcoin::consensus::encode::deserialize_hex;
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::Address;
+use bitcoin::Amount;
+use bitcoin::OutPoint;
+use bitcoin::Transaction;
+use bitcoin::Txid;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::StatusCode;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// The error code returned by Bitcoin Core for unknown transactions.
+const RPC_INVALID_ADDRESS_OR_KEY: i64 = -5;
+
+/// How to authenticate with the RPC server of Bitcoin Core.
+#[derive(Clone)]
+pub enum BitcoindAuth {
+    /// Read the credentials from the cookie file which Bitcoin Core writes on startup, e.g.
+    /// `~/.bitcoin/.cookie`.
+    ///
+    /// The file is read on every request, since it changes whenever Bitcoin Core restarts.
+    CookieFile(PathBuf),
+    /// The credentials set with `-rpcuser` and `-rpcpassword`, or `-rpcauth`.
+    UserPass { user: String, password: String },
+}
+
+impl fmt::Debug for BitcoindAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CookieFile(path) => f.debug_tuple("CookieFile").field(path).finish(),
+            Self::UserPass { user, .. } => f
+                .debug_struct("UserPass")
+                .field("user", user)
+                .field("password", &"<redacted>")
+                .finish(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BitcoindBlockchain {
+    client: reqwest::Client,
+    url: String,
+    auth: BitcoindAuth,
+    /// Bitcoin Core only runs one `scantxoutset` at a time.
+    scan_lock: Arc<Mutex<()>>,
+}
+
+impl BitcoindBlockchain {
+    /// Use the RPC server of Bitcoin Core at `url`, e.g. `http://127.0.0.1:8332`.
+    pub fn new(url: impl Into<String>, auth: BitcoindAuth) -> Result<Self, Error> {
+        let client = reqwest::Client::builder()
+            .build()
+            .map_err(|e| Error::ad_hoc(format!("failed to build bitcoind client: {e}")))?;
+
+        Ok(Self {
+            client,
+            url: url.into(),
+            auth,
+            scan_lock: Arc::new(Mutex::new(())),
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The verbose form of the transaction identified by `txid`, if Bitcoin Core knows it.
+    async fn get_verbose_transaction(&self, txid: &Txid) -> Result<Option<Value>, Error> {
+        match self
+            .call("getrawtransaction", json!([txid.to_string(), true]))
+            .await?
+        {
+            Ok(tx) => Ok(Some(tx)),
+            Err(error) if error.code == RPC_INVALID_ADDRESS_OR_KEY => Ok(None),
+            Err(error) => Err(error.into_error("getrawtransaction")),
+        }
+    }
+
+    /// The timestamp of the block at `height`.
+    async fn get_block_time(&self, height: u64) -> Result<u64, Error> {
+        self.get_block_header_field(height, "time").await
+    }
+
+    /// The field `field` of the verbose header of the block at `height`, e.g. its `time`.
+    async fn get_block_header_field(&self, height: u64, field: &str) -> Result<u64, Error> {
+        let block_hash = self
+            .request("getblockhash", json!([height]))
+            .await
+            .with_context(|| format!("failed to get block hash at height {height}"))?;
+
+        let header = self
+            .request("getblockheader", json!([block_hash]))
+            .await
+            .with_context(|| format!("failed to get block header at height {height}"))?;
+
+        header
+            .get(field)
+            .and_then(Value::as_u64)
+            .ok_or_else(|| Error::ad_hoc(format!("missing {field} in block header: {header}")))
+    }
+
+    /// Search the blocks from the one with hash `block_hash` to the tip for the transaction
+    /// spending `outpoint`.
+    async fn find_spending_tx_from(
+        &self,
+        outpoint: OutPoint,
+        mut block_hash: String,
+    ) -> Result<Option<Txid>, Error> {
+        loop {
+            let block = self
+                .request("getblock", json!([block_hash, 2]))
+                .await
+                .with_context(|| format!("failed to get block {block_hash}"))?;
+
+            for tx in block
+                .get("tx")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                let spends_outpoint = tx
+                    .get("vin")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .any(|input| parse_outpoint(input).ok() == Some(outpoint));
+
+                if spends_outpoint {
+                    return parse_txid(tx, "txid").map(Some);
+                }
+            }
+
+            match block.get("nextblockhash").and_then(Value::as_str) {
+                Some(next) => block_hash = next.to_string(),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Make a request which Bitcoin Core is expected to answer successfully.
+    async fn request(&self, method: &str, params: Value) -> Result<Value, Error> {
+        self.call(method, params)
+            .await?
+            .map_err(|error| error.into_error(method))
+    }
+
+    /// Make a request, returning the error if Bitcoin Core answers with one.
+    async fn call(&self, method: &str, params: Value) -> Result<Result<Value, RpcError>, Error> {
+        let body = json!({
+            "jsonrpc": "1.0",
+            "id": method,
+            "method": method,
+            "params": params,
+        });
+
+        let (user, password) = match &self.auth {
+            BitcoindAuth::CookieFile(path) => {
+                let cookie = std::fs::read_to_string(path).map_err(|e| {
+                    Error::ad_hoc(format!(
+                        "failed to read bitcoind cookie file {}: {e}",
+                        path.display()
+                    ))
+                })?;
+
+                let (user, password) = cookie.trim().split_once(':').ok_or_else(|| {
+                    Error::ad_hoc(format!("invalid bitcoind cookie file {}", path.display()))
+                })?;
+
+                (user.to_string(), password.to_string())
+            }
+            BitcoindAuth::UserPass { user, password } => (user.clone(), password.clone()),
+        };
+
+        let response = self
+            .client
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/json")
+            .basic_auth(user, Some(password))
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(|e| Error::ad_hoc(format!("failed to reach bitcoind at {}: {e}", self.url)))?;
+
+        if response.status() == StatusCode::UNAUTHORIZED {
+            return Err(Error::ad_hoc(format!(
+                "bitcoind at {} rejected our RPC credentials",
+                self.url
+            )));
+        }
+
+        // Errors come with a non-success status, but still carry a JSON-RPC response.
+        let response = response
+            .text()
+            .await
+            .map_err(|e| Error::ad_hoc(format!("failed to read response to {method}: {e}")))?;
+        let mut response: Value = serde_json::from_str(&response).map_err(|e| {
+            Error::ad_hoc(format!("invalid response from bitcoind to {method}: {e}"))
+        })?;
+
+        if let Some(error) = response.get("error").filter(|error| !error.is_null()) {
+            return Ok(Err(RpcError {
+                code: error
+                    .get("code")
+                    .and_then(Value::as_i64)
+                    .unwrap_or_default(),
+                message: match error.get("message").and_then(Value::as_str) {
+                    Some(message) => message.to_string(),
+                    None => error.to_string(),
+                },
+            }));
+        }
+
+        Ok(Ok(response["result"].take()))
+    }
+}
+
+impl Blockchain for BitcoindBlockchain {
+    /// The confirmed, unspent outputs paying `address`.
+    async fn find_outpoints(&self, address: &Address) -> Result<Vec<ExplorerUtxo>, Error> {
+        let scan = {
+            let _scan_lock = self.scan_lock.lock().await;
+
+            self.request(
+                "scantxoutset",
+                json!(["start", [format!("addr({address})")]]),
+            )
+            .await
+            .with_context(|| format!("failed to scan UTXO set for address {address}"))?
+        };
+
+        let unspents = scan
+            .get("unspents")
+            .and_then(Value::as_array)
+            .ok_or_else(|| Error::ad_hoc(format!("missing unspents in scan result: {scan}")))?;
+
+        let mut block_times = HashMap::new();
+        let mut outputs = Vec::new();
+        for unspent in unspents {
+            let outpoint = parse_outpoint(unspent)?;
+
+            let amount = unspent
+                .get("amount")
+                .and_then(Value::as_f64)
+                .and_then(|amount| Amount::from_btc(amount).ok())
+                .ok_or_else(|| {
+                    Error::ad_hoc(format!("invalid amount in scan result: {unspent}"))
+                })?;
+
+            let height = unspent
+                .get("height")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| {
+                    Error::ad_hoc(format!("missing height in scan result: {unspent}"))
+                })?;
+
+            let block_time = match block_times.get(&height) {
+                Some(block_time) => *block_time,
+                None => {
+                    let block_time = self.get_block_time(height).await?;
+                    block_times.insert(height, block_time);
+
+                    block_time
+                }
+            };
+
+            outputs.push(ExplorerUtxo {
+                outpoint,
+                amount,
+                confirmation_blocktime: Some(block_time),
+                confirmation_height: Some(height as u32),
+                is_spent: false,
+            });
+        }
+
+        Ok(outputs)
+    }
+
+    async fn find_tx(&self, txid: &Txid) -> Result<Option<Transaction>, Error> {
+        let tx = match self
+            .call("getrawtransaction", json!([txid.to_string()]))
+            .await
+            .with_context(|| format!("failed to get transaction {txid}"))?
+        {
+            Ok(tx) => tx,
+            Err(error) if error.code == RPC_INVALID_ADDRESS_OR_KEY => return Ok(None),
+            Err(error) => {
+                return Err(error.into_error("getrawtransaction"))
+                    .with_context(|| format!("failed to get transaction {txid}"))
+            }
+        };
+
+        let tx = tx
+            .as_str()
+            .ok_or_else(|| Error::ad_hoc("expected hex transaction in bitcoind response"))?;
+
+        deserialize_hex(tx)
+            .map(Some)
+            .map_err(|e| Error::ad_hoc(format!("invalid transaction {txid} from bitcoind: {e}")))
+    }
+
+    async fn get_output_status(&self, txid: &Txid, vout: u32) -> Result<SpendStatus, Error> {
+        let outpoint = OutPoint::new(*txid, vout);
+        let context = || format!("failed to get status of output {outpoint}");
+
+        let txout = self
+            .request("gettxout", json!([txid.to_string(), vout, true]))
+            .await
+            .with_context(context)?;
+        if !txout.is_null() {
+            return Ok(SpendStatus { spend_txid: None });
+        }
+
+        // Only supported from Bitcoin Core 24 onwards, so errors are ignored.
+        if let Ok(spending) = self
+            .call(
+                "gettxspendingprevout",
+                json!([[{ "txid": txid.to_string(), "vout": vout }]]),
+            )
+            .await
+            .with_context(context)?
+        {
+            let spend_txid = spending
+                .get(0)
+                .and_then(|spending| spending.get("spendingtxid"))
+                .and_then(Value::as_str)
+                .and_then(|spend_txid| spend_txid.parse().ok());
+
+            if spend_txid.is_some() {
+                return Ok(SpendStatus { spend_txid });
+            }
+        }
+
+        // The output was spent in a block, which must come after the one confirming the output.
+        let block_hash = self
+            .get_verbose_transaction(txid)
+            .await
+            .with_context(context)?
+            .and_then(|tx| {
+                tx.get("blockhash")
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+            });
+
+        let spend_txid = match block_hash {
+            Some(block_hash) => self
+                .find_spending_tx_from(outpoint, block_hash)
+                .await
+                .with_context(context)?,
+            None => None,
+        };
+
+        Ok(SpendStatus { spend_txid })
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> Result<(), Error> {
+        let response = self
+            .call("sendrawtransaction", json!([serialize_hex(tx)]))
+            .await
+            .with_context(|| format!("failed to broadcast transaction {}", tx.compute_txid()))?;
+
+        match response {
+            Ok(_) => Ok(()),
+            Err(error) => Err(Error::broadcast(BroadcastError::from_reason(error.message))),
+        }
+    }
+
+    async fn get_confirmations(&self, txid: &Txid) -> Result<u32, Error> {
+        let tx = self
+            .get_verbose_transaction(txid)
+            .await
+            .with_context(|| format!("failed to get status of transaction {txid}"))?;
+
+        // Unconfirmed transactions have no confirmations field.
+        let confirmations = tx
+            .and_then(|tx| tx.get("confirmations").and_then(Value::as_u64))
+            .unwrap_or_default();
+
+        Ok(confirmations as u32)
+    }
+
+    async fn get_tip_height(&self) -> Result<u32, Error> {
+        let height = self
+            .request("getblockcount", json!([]))
+            .await
+            .context("failed to get blockchain tip")?;
+
+        height
+            .as_u64()
+            .and_then(|height| u32::try_from(height).ok())
+            .ok_or_else(|| Error::ad_hoc(format!("invalid block count: {height}")))
+    }
+    async fn get_median_time_past(&self, height: u32) -> Result<u64, Error> {
+        self.get_block_header_field(height as u64, "mediantime")
+            .await
+    }
+}
+
+/// An error returned by the RPC server of Bitcoin Core.
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn into_error(self, method: &str) -> Error {
+        Error::ad_hoc(format!(
+            "bitcoind failed to answer {method}: {} (code {})",
+            self.message, self.code
+        ))
+    }
+}
+
+fn parse_outpoint(value: &Value) -> Result<OutPoint, Error> {
+    let txid = parse_txid(value, "txid")?;
+    let vout = value
+        .get("vout")
+        .and_then(Value::as_u64)
+        .and_then(|vout| u32::try_from(vout).ok())
+        .ok_or_else(|| Error::ad_hoc(format!("missing vout in bitcoind response: {value}")))?;
+
+    Ok(OutPoint::new(txid, vout))
+}
+
+fn parse_txid(value: &Value, field: &str) -> Result<Txid, Error> {
+    value
+        .get(field)
+        .and_then(Value::as_str)
+        .and_then(|txid| txid.parse().ok())
+        .ok_or_else(|| Error::ad_hoc(format!("missing {field} in bitcoind response: {value}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::hashes::Hash;
+    use bitcoin::transaction;
+    use bitcoin::Network;
+    use bitcoin::ScriptBuf;
+    use bitcoin::TxIn;
+    use bitcoin::TxOut;
+    use std::io::BufRead;
+    use std::io::BufReader;
+    use std::io::Read;
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::net::TcpStream;
+
+    /// The credentials which the node accepts, `ark:secret` in base64.
+    const AUTHORIZATION: &str = "Basic YXJrOnNlY3JldA==";
+
+    /// The timestamp of the block at height 100.
+    const TIME_100: u64 = 1_700_000_000;
+
+    const BLOCK_100: &str = "0000000000000000000000000000000000000000000000000000000000000100";
+    const BLOCK_101: &str = "0000000000000000000000000000000000000000000000000000000000000101";
+
+    /// A request and the response recorded from Bitcoin Core, i.e. its `result` or `error`,
+    /// without the rest of the JSON-RPC envelope. A string is sent as the raw response body.
+    type Recorded = (&'static str, Value, Value);
+
+    /// Serve `recorded` responses on a local port, as the RPC server of Bitcoin Core would.
+    /// Returns its URL.
+    fn bitcoind(recorded: Vec<Recorded>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let recorded = Arc::new(recorded);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let recorded = recorded.clone();
+                std::thread::spawn(move || serve(stream.unwrap(), &recorded));
+            }
+        });
+
+        url
+    }
+
+    fn serve(stream: TcpStream, recorded: &[Recorded]) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+
+        loop {
+            let mut request_line = String::new();
+            if reader.read_line(&mut request_line).unwrap() == 0 {
+                return;
+            }
+
+            let mut content_length = 0;
+            let mut authorization = None;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+
+                let header = header.trim_end();
+                if header.is_empty() {
+                    break;
+                }
+
+                let (name, value) = header.split_once(':').unwrap();
+                match name.to_lowercase().as_str() {
+                    "content-length" => content_length = value.trim().parse().unwrap(),
+                    "authorization" => authorization = Some(value.trim().to_string()),
+                    _ => {}
+                }
+            }
+
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            let (status, body) = if authorization.as_deref() == Some(AUTHORIZATION) {
+                respond(&serde_json::from_slice(&body).unwrap(), recorded)
+            } else {
+                ("401 Unauthorized", String::new())
+            };
+
+            write!(
+                writer,
+                "HTTP/1.1 {status}\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+        }
+    }
+
+    fn respond(request: &Value, recorded: &[Recorded]) -> (&'static str, String) {
+        let method = request["method"].as_str().unwrap();
+        let params = &request["params"];
+
+        let recorded = recorded
+            .iter()
+            .find(|(m, p, _)| *m == method && p == params)
+            .map(|(_, _, response)| response.clone())
+            .unwrap_or_else(
+                || json!({ "error": { "code": -32601, "message": "Method not found" } }),
+            );
+
+        if let Some(raw) = recorded.as_str() {
+            return ("200 OK", raw.to_string());
+        }
+
+        let mut response = json!({ "result": null, "error": null, "id": request["id"] });
+        for field in ["result", "error"] {
+            if let Some(value) = recorded.get(field) {
+                response[field] = value.clone();
+            }
+        }
+
+        // Bitcoin Core answers errors with a failure status, but still with a JSON-RPC response.
+        let status = match response["error"]["code"].as_i64() {
+            None => "200 OK",
+            Some(-32601) => "404 Not Found",
+            Some(_) => "500 Internal Server Error",
+        };
+
+        (status, response.to_string())
+    }
+
+    fn client(url: String) -> BitcoindBlockchain {
+        BitcoindBlockchain::new(
+            url,
+            BitcoindAuth::UserPass {
+                user: "ark".to_string(),
+                password: "secret".to_string(),
+            },
+        )
+        .unwrap()
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn txid(n: u8) -> Txid {
+        Txid::from_byte_array([n; 32])
+    }
+
+    fn address() -> Address {
+        Address::p2wsh(&ScriptBuf::from_bytes(vec![0x51]), Network::Regtest)
+    }
+
+    fn not_found() -> Value {
+        json!({
+            "error": {
+                "code": RPC_INVALID_ADDRESS_OR_KEY,
+                "message": "No such mempool or blockchain transaction. \
+                            Use gettransaction for wallet transactions.",
+            }
+        })
+    }
+
+    /// The blocks 100 and 101, the latter of which spends `spent`.
+    fn blocks(spent: OutPoint, spend_txid: Txid) -> Vec<Recorded> {
+        vec![
+            ("getblockhash", json!([100]), json!({ "result": BLOCK_100 })),
+            (
+                "getblockheader",
+                json!([BLOCK_100]),
+                json!({
+                    "result": {
+                        "hash": BLOCK_100,
+                        "height": 100,
+                        "time": TIME_100,
+                        "mediantime": TIME_100 - 600,
+                    }
+                }),
+            ),
+            (
+                "getblock",
+                json!([BLOCK_100, 2]),
+                json!({
+                    "result": {
+                        "hash": BLOCK_100,
+                        "tx": [{ "txid": spent.txid.to_string(), "vin": [{ "coinbase": "0164" }] }],
+                        "nextblockhash": BLOCK_101,
+                    }
+                }),
+            ),
+            (
+                "getblock",
+                json!([BLOCK_101, 2]),
+                json!({
+                    "result": {
+                        "hash": BLOCK_101,
+                        "tx": [
+                            { "txid": txid(9).to_string(), "vin": [{ "coinbase": "0165" }] },
+                            {
+                                "txid": spend_txid.to_string(),
+                                "vin": [{ "txid": spent.txid.to_string(), "vout": spent.vout }],
+                            },
+                        ],
+                    }
+                }),
+            ),
+        ]
+    }
+
+    #[test]
+    fn unspent_outputs_are_found_in_the_utxo_set() {
+        let url = bitcoind(
+            [
+                vec![(
+                    "scantxoutset",
+                    json!(["start", [format!("addr({})", address())]]),
+                    json!({
+                        "result": {
+                            "success": true,
+                            "height": 105,
+                            "unspents": [
+                                {
+                                    "txid": txid(1).to_string(),
+                                    "vout": 0,
+                                    "amount": 0.00001,
+                                    "height": 100,
+                                },
+                                {
+                                    "txid": txid(1).to_string(),
+                                    "vout": 2,
+                                    "amount": 0.00003,
+                                    "height": 100,
+                                },
+                            ],
+                            "total_amount": 0.00004,
+                        }
+                    }),
+                )],
+                blocks(OutPoint::new(txid(1), 0), txid(2)),
+            ]
+            .concat(),
+        );
+
+        let outputs = block_on(client(url).find_outpoints(&address()))
+            .unwrap()
+            .into_iter()
+            .map(|output| {
+                (
+                    output.outpoint,
+                    output.amount.to_sat(),
+                    output.confirmation_blocktime,
+                    output.confirmation_height,
+                    output.is_spent,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            outputs,
+            vec![
+                (
+                    OutPoint::new(txid(1), 0),
+                    1_000,
+                    Some(TIME_100),
+                    Some(100),
+                    false
+                ),
+                (
+                    OutPoint::new(txid(1), 2),
+                    3_000,
+                    Some(TIME_100),
+                    Some(100),
+                    false
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn transactions_are_looked_up_by_txid() {
+        let tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: vec![TxOut {
+                value: Amount::from_sat(1_000),
+                script_pubkey: address().script_pubkey(),
+            }],
+        };
+        let known = tx.compute_txid();
+
+        let url = bitcoind(vec![
+            (
+                "getrawtransaction",
+                json!([known.to_string()]),
+                json!({ "result": serialize_hex(&tx) }),
+            ),
+            (
+                "getrawtransaction",
+                json!([txid(1).to_string()]),
+                not_found(),
+            ),
+            (
+                "getrawtransaction",
+                json!([txid(2).to_string()]),
+                json!({
+                    "error": { "code": -8, "message": "parameter 1 must be hexadecimal string" }
+                }),
+            ),
+        ]);
+        let bitcoind = client(url);
+
+        assert_eq!(block_on(bitcoind.find_tx(&known)).unwrap(), Some(tx));
+        assert_eq!(block_on(bitcoind.find_tx(&txid(1))).unwrap(), None);
+        assert!(block_on(bitcoind.find_tx(&txid(2))).is_err());
+    }
+
+    #[test]
+    fn unspent_output_has_no_spending_transaction() {
+        let url = bitcoind(vec![(
+            "gettxout",
+            json!([txid(1).to_string(), 0, true]),
+            json!({ "result": { "bestblock": BLOCK_101, "confirmations": 6, "value": 0.00001 } }),
+        )]);
+
+        let status = block_on(client(url).get_output_status(&txid(1), 0)).unwrap();
+
+        assert_eq!(status.spend_txid, None);
+    }
+
+    #[test]
+    fn spending_transaction_is_found_through_the_mempool_index() {
+        let url = bitcoind(vec![
+            (
+                "gettxout",
+                json!([txid(1).to_string(), 0, true]),
+                json!({ "result": null }),
+            ),
+            (
+                "gettxspendingprevout",
+                json!([[{ "txid": txid(1).to_string(), "vout": 0 }]]),
+                json!({
+                    "result": [{
+                        "txid": txid(1).to_string(),
+                        "vout": 0,
+                        "spendingtxid": txid(2).to_string(),
+                    }]
+                }),
+            ),
+        ]);
+
+        let status = block_on(client(url).get_output_status(&txid(1), 0)).unwrap();
+
+        assert_eq!(status.spend_txid, Some(txid(2)));
+    }
+
+    #[test]
+    fn spending_transaction_is_found_in_later_blocks() {
+        let spent = OutPoint::new(txid(1), 1);
+
+        // Nodes older than Bitcoin Core 24 do not know `gettxspendingprevout`, and confirmed
+        // spends are not in the mempool anyway.
+        let url = bitcoind(
+            [
+                vec![
+                    (
+                        "gettxout",
+                        json!([txid(1).to_string(), 1, true]),
+                        json!({ "result": null }),
+                    ),
+                    (
+                        "getrawtransaction",
+                        json!([txid(1).to_string(), true]),
+                        json!({
+                            "result": {
+                                "txid": txid(1).to_string(),
+                                "blockhash": BLOCK_100,
+                                "confirmations": 6,
+                            }
+                        }),
+                    ),
+                ],
+                blocks(spent, txid(2)),
+            ]
+            .concat(),
+        );
+
+        let status = block_on(client(url).get_output_status(&txid(1), 1)).unwrap();
+
+        assert_eq!(status.spend_txid, Some(txid(2)));
+    }
+
+    #[test]
+    fn confirmations_are_taken_from_the_verbose_transaction() {
+        let url = bitcoind(vec![
+            (
+                "getrawtransaction",
+                json!([txid(1).to_string(), true]),
+                json!({
+                    "result": {
+                        "txid": txid(1).to_string(),
+                        "blockhash": BLOCK_100,
+                        "confirmations": 6,
+                    }
+                }),
+            ),
+            // In the mempool.
+            (
+                "getrawtransaction",
+                json!([txid(2).to_string(), true]),
+                json!({ "result": { "txid": txid(2).to_string() } }),
+            ),
+            (
+                "getrawtransaction",
+                json!([txid(3).to_string(), true]),
+                not_found(),
+            ),
+            ("getblockcount", json!([]), json!({ "result": 105 })),
+        ]);
+        let bitcoind = client(url);
+
+        assert_eq!(block_on(bitcoind.get_confirmations(&txid(1))).unwrap(), 6);
+        assert_eq!(block_on(bitcoind.get_confirmations(&txid(2))).unwrap(), 0);
+        assert_eq!(block_on(bitcoind.get_confirmations(&txid(3))).unwrap(), 0);
+        assert_eq!(block_on(bitcoind.get_tip_height()).unwrap(), 105);
+    }
+
+    #[test]
+    fn median_time_past_is_taken_from_the_block_header() {
+        let url = bitcoind(blocks(OutPoint::new(txid(1), 0), txid(2)));
+
+        let median_time_past = block_on(client(url).get_median_time_past(100)).unwrap();
+
+        assert_eq!(median_time_past, TIME_100 - 600);
+    }
+
+    #[test]
+    fn rejected_broadcast_is_classified() {
+        let tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: Vec::new(),
+        };
+
+        let url = bitcoind(vec![(
+            "sendrawtransaction",
+            json!([serialize_hex(&tx)]),
+            json!({ "error": { "code": -26, "message": "min relay fee not met, 100 < 141" } }),
+        )]);
+
+        let error = block_on(client(url).broadcast(&tx)).unwrap_err();
+
+        assert!(matches!(
+            error.broadcast_error(),
+            Some(BroadcastError::InsufficientFee { .. })
+        ));
+    }
+
+    #[test]
+    fn credentials_are_read_from_the_cookie_file() {
+        let url = bitcoind(vec![("getblockcount", json!([]), json!({ "result": 105 }))]);
+
+        let cookie = std::env::temp_dir().join(format!(".cookie-{}", txid(1)));
+        std::fs::write(&cookie, "ark:secret\n").unwrap();
+
+        let bitcoind =
+            BitcoindBlockchain::new(url.clone(), BitcoindAuth::CookieFile(cookie.clone())).unwrap();
+        let height = block_on(bitcoind.get_tip_height());
+
+        std::fs::remove_file(&cookie).unwrap();
+        assert_eq!(height.unwrap(), 105);
+
+        // The cookie file is gone once the node shuts down.
+        assert!(block_on(bitcoind.get_tip_height()).is_err());
+    }
+
+    #[test]
+    fn wrong_credentials_are_rejected() {
+        let url = bitcoind(vec![("getblockcount", json!([]), json!({ "result": 105 }))]);
+
+        let bitcoind = BitcoindBlockchain::new(
+            url,
+            BitcoindAuth::UserPass {
+                user: "ark".to_string(),
+                password: "wrong".to_string(),
+            },
+        )
+        .unwrap();
+
+        assert!(block_on(bitcoind.get_tip_height()).is_err());
+    }
+
+    #[test]
+    fn malformed_responses_are_rejected() {
+        let scan = |unspents: Value| {
+            let url = bitcoind(vec![(
+                "scantxoutset",
+                json!(["start", [format!("addr({})", address())]]),
+                json!({ "result": { "success": true, "unspents": unspents } }),
+            )]);
+
+            block_on(client(url).find_outpoints(&address()))
+        };
+
+        // No height, and a negative amount.
+        assert!(
+            scan(json!([{ "txid": txid(1).to_string(), "vout": 0, "amount": 0.00001 }])).is_err()
+        );
+        assert!(scan(
+            json!([{ "txid": txid(1).to_string(), "vout": 0, "amount": -1, "height": 100 }])
+        )
+        .is_err());
+        assert!(scan(json!(null)).is_err());
+
+        let url = bitcoind(vec![
+            (
+                "getblockcount",
+                json!([]),
+                json!("<html>Bad gateway</html>"),
+            ),
+            ("getblockhash", json!([100]), json!({ "result": BLOCK_100 })),
+            (
+                "getblockheader",
+                json!([BLOCK_100]),
+                json!({ "result": { "hash": BLOCK_100 } }),
+            ),
+        ]);
+        let bitcoind = client(url);
+
+        // Not JSON, and a header without a median time.
+        assert!(block_on(bitcoind.get_tip_height()).is_err());
+        assert!(block_on(bitcoind.get_median_time_past(100)).is_err());
+    }
+}
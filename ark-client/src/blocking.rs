@@ -0,0 +1,173 @@
+//! Synchronous facades over the main operations of the [`Client`], for embedders without an async
+//! runtime, e.g. GUI frameworks and FFI hosts.
+//!
+//! Only available with the `blocking` feature. See [`BlockingClient`].
+
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use crate::OffChainBalance;
+use crate::OfflineClient;
+use ark_core::server::ListVtxo;
+use ark_core::ArkAddress;
+use ark_core::ArkTransaction;
+use bitcoin::Address;
+use bitcoin::Amount;
+use bitcoin::Psbt;
+use bitcoin::Txid;
+use rand::CryptoRng;
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+/// How long an operation of a [`BlockingClient`] may take, unless configured otherwise with
+/// [`BlockingClient::with_timeout`].
+///
+/// Boarding and off-boarding wait for a round, so they may need more than this.
+pub const DEFAULT_BLOCKING_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A [`Client`] whose operations block the calling thread until they complete or time out.
+///
+/// The client is driven by a single-threaded runtime owned by the `BlockingClient`, so it must not
+/// be used from within an async runtime. Operations which are not mirrored here can be run with
+/// [`BlockingClient::block_on`].
+pub struct BlockingClient<
+    B,
+    W,
+    #[cfg(feature = "grpc")] T = ark_grpc::Client,
+    #[cfg(not(feature = "grpc"))] T,
+> {
+    client: Client<B, W, T>,
+    runtime: Runtime,
+    timeout: Duration,
+}
+
+impl<B, W, T> BlockingClient<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Connect `offline_client` to the Ark server, see [`OfflineClient::connect`].
+    ///
+    /// Fails if connecting takes longer than [`DEFAULT_BLOCKING_TIMEOUT`].
+    pub fn connect(offline_client: OfflineClient<B, W, T>) -> Result<Self, Error> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| Error::ad_hoc(format!("failed to start runtime: {e}")))?;
+
+        let client =
+            block_on_with_timeout(&runtime, DEFAULT_BLOCKING_TIMEOUT, offline_client.connect())??;
+
+        Ok(Self {
+            client,
+            runtime,
+            timeout: DEFAULT_BLOCKING_TIMEOUT,
+        })
+    }
+
+    /// Fail operations which take longer than `timeout`.
+    ///
+    /// An operation which times out is cancelled. In particular, a payment may or may not have
+    /// reached the Ark server by then.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The underlying client, e.g. for operations which do not need to wait.
+    pub fn client(&self) -> &Client<B, W, T> {
+        &self.client
+    }
+
+    /// Run `future` to completion on the runtime of the client, failing if it takes longer than
+    /// the timeout of the client.
+    ///
+    /// For operations which are not mirrored by the `BlockingClient`, e.g.
+    /// `blocking.block_on(blocking.client().list_spendable_outpoints())`.
+    pub fn block_on<F>(&self, future: F) -> Result<F::Output, Error>
+    where
+        F: Future,
+    {
+        block_on_with_timeout(&self.runtime, self.timeout, future)
+    }
+
+    pub fn get_offchain_address(&self) -> ArkAddress {
+        let (address, _) = self.client.get_offchain_address();
+
+        address
+    }
+
+    pub fn get_boarding_address(&self) -> Result<Address, Error> {
+        self.client.get_boarding_address()
+    }
+
+    /// See [`Client::offchain_balance`].
+    pub fn offchain_balance(&self) -> Result<OffChainBalance, Error> {
+        self.block_on(self.client.offchain_balance())?
+    }
+
+    /// See [`Client::list_vtxos`].
+    pub fn list_vtxos(&self) -> Result<ListVtxo, Error> {
+        self.block_on(self.client.list_vtxos())?
+    }
+
+    /// See [`Client::transaction_history`].
+    pub fn transaction_history(&self) -> Result<Vec<ArkTransaction>, Error> {
+        self.block_on(self.client.transaction_history())?
+    }
+
+    /// See [`Client::send_vtxo`].
+    pub fn send_vtxo(&self, address: ArkAddress, amount: Amount) -> Result<Psbt, Error> {
+        self.block_on(self.client.send_vtxo(address, amount))?
+    }
+
+    /// See [`Client::send_vtxos`].
+    pub fn send_vtxos(&self, outputs: Vec<(ArkAddress, Amount)>) -> Result<Psbt, Error> {
+        self.block_on(self.client.send_vtxos(outputs))?
+    }
+
+    /// See [`Client::board`].
+    pub fn board<R>(&self, rng: &mut R) -> Result<(), Error>
+    where
+        R: Rng + CryptoRng + Clone,
+    {
+        self.block_on(self.client.board(rng))?
+    }
+
+    /// See [`Client::off_board`].
+    pub fn off_board<R>(
+        &self,
+        rng: &mut R,
+        to_address: Address,
+        to_amount: Amount,
+    ) -> Result<Txid, Error>
+    where
+        R: Rng + CryptoRng + Clone,
+    {
+        self.block_on(self.client.off_board(rng, to_address, to_amount))?
+    }
+
+    /// See [`Client::send_on_chain`].
+    pub fn send_on_chain(&self, to_address: Address, to_amount: Amount) -> Result<Txid, Error> {
+        self.block_on(self.client.send_on_chain(to_address, to_amount))?
+    }
+}
+
+fn block_on_with_timeout<F>(
+    runtime: &Runtime,
+    timeout: Duration,
+    future: F,
+) -> Result<F::Output, Error>
+where
+    F: Future,
+{
+    runtime
+        .block_on(tokio::time::timeout(timeout, future))
+        .map_err(|_| Error::ad_hoc(format!("operation timed out after {timeout:?}")))
+}
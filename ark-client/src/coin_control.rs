@@ -0,0 +1,89 @@
+//! Let the user pick which VTXOs to spend, instead of relying on automatic coin selection.
+//!
+//! List candidates with [`Client::list_spendable_outpoints`] and spend a selection with
+//! [`Client::send_vtxo_with_inputs`].
+
+use crate::error::ErrorContext;
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use ark_core::ArkAddress;
+use bitcoin::Amount;
+use bitcoin::OutPoint;
+use bitcoin::Txid;
+
+/// One of our spendable VTXOs, with what a user needs to decide whether to spend it.
+#[derive(Debug, Clone)]
+pub struct SpendableOutpoint {
+    pub outpoint: OutPoint,
+    pub amount: Amount,
+    /// The address of ours which owns the VTXO.
+    pub address: ArkAddress,
+    /// The round which created the VTXO or, for a pending VTXO, the round of the VTXOs it was
+    /// paid with.
+    pub round_txid: Txid,
+    /// The UNIX timestamp at which the VTXO was created.
+    pub created_at: i64,
+    /// The UNIX timestamp at which the VTXO expires, or 0 if unknown.
+    pub expire_at: i64,
+    /// See [`ark_core::server::VtxoOutPoint::sweepable_after`].
+    pub sweepable_after: Option<i64>,
+    /// Whether the VTXO was created out of round and is yet to be settled.
+    pub is_pending: bool,
+    /// Whether the VTXO is set aside by a reservation, see [`Client::reserve`]. Reserved VTXOs
+    /// cannot be selected until the reservation is released.
+    pub is_reserved: bool,
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Every VTXO which can be spent with [`Client::send_vtxo_with_inputs`], soonest to expire
+    /// first, including reserved VTXOs.
+    ///
+    /// VTXOs flagged by the [`crate::risk::RiskOracle`] or held for manual review are left out.
+    pub async fn list_spendable_outpoints(&self) -> Result<Vec<SpendableOutpoint>, Error> {
+        let reserved = &self.reservations.reserved_outpoints();
+
+        let spendable_vtxos = self
+            .spendable_vtxos()
+            .await
+            .context("failed to get spendable VTXOs")?;
+
+        let mut outpoints = spendable_vtxos
+            .into_iter()
+            .flat_map(|(vtxos, vtxo)| {
+                let address = vtxo.to_ark_address();
+
+                vtxos.into_iter().map(move |v| SpendableOutpoint {
+                    outpoint: v.outpoint,
+                    amount: v.amount,
+                    address,
+                    round_txid: v.round_txid,
+                    created_at: v.created_at,
+                    expire_at: v.expire_at,
+                    sweepable_after: v.sweepable_after,
+                    is_pending: v.is_pending,
+                    is_reserved: reserved.contains(&v.outpoint),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // VTXOs whose expiry is unknown go last.
+        outpoints.sort_by_key(|outpoint| {
+            (
+                outpoint.expire_at <= 0,
+                outpoint.expire_at,
+                outpoint.outpoint,
+            )
+        });
+
+        Ok(outpoints)
+    }
+}
@@ -1,3 +1,4 @@
+use crate::transport::NetworkTransport;
 use crate::wallet::BoardingWallet;
 use crate::wallet::OnchainWallet;
 use crate::Blockchain;
@@ -6,8 +7,6 @@ use crate::Error;
 use crate::ExplorerUtxo;
 use ark_core::unilateral_exit;
 use bitcoin::Amount;
-use jiff::SignedDuration;
-use jiff::Timestamp;
 
 /// Select boarding outputs and VTXOs to be used as inputs in on-chain transactions, exiting the Ark
 /// ecosystem.
@@ -19,8 +18,8 @@ use jiff::Timestamp;
 /// https://github.com/bitcoindevkit/coin-select.
 ///
 /// TODO: Part of this logic needs to be extracted into `ark-core`.
-pub async fn coin_select_for_onchain<B, W>(
-    client: &Client<B, W>,
+pub async fn coin_select_for_onchain<B, W, T>(
+    client: &Client<B, W, T>,
     target_amount: Amount,
 ) -> Result<
     (
@@ -32,10 +31,12 @@ pub async fn coin_select_for_onchain<B, W>(
 where
     B: Blockchain,
     W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
 {
     let boarding_outputs = client.inner.wallet.get_boarding_outputs()?;
 
-    let now = Timestamp::now();
+    // Only fetched once we find a confirmed output.
+    let mut chain_tip = None;
 
     let mut selected_boarding_outputs = Vec::new();
     let mut selected_amount = Amount::ZERO;
@@ -45,32 +46,32 @@ where
             return Ok((selected_boarding_outputs, Vec::new()));
         }
 
-        let outpoints = client
-            .blockchain()
-            .find_outpoints(boarding_output.address())
-            .await?;
+        let outpoints = client.find_our_outpoints(boarding_output.address()).await?;
 
         for o in outpoints.iter() {
             // Find outpoints for each boarding output.
-            if let ExplorerUtxo {
-                outpoint,
-                amount,
-                confirmation_blocktime: Some(confirmation_blocktime),
-                is_spent: false,
-            } = o
-            {
-                let exit_delay_duration: SignedDuration = boarding_output
-                    .exit_delay_duration()
-                    .try_into()
-                    .map_err(Error::ad_hoc)?;
-                let spendable_at = Timestamp::new(*confirmation_blocktime as i64, 0)
-                    .map_err(Error::ad_hoc)?
-                    + exit_delay_duration;
+            if o.is_spent {
+                continue;
+            }
+
+            if let Some(confirmed) = client.confirmed_in(o).await? {
+                let ExplorerUtxo {
+                    outpoint, amount, ..
+                } = o;
+                let tip = match chain_tip {
+                    Some(tip) => tip,
+                    None => *chain_tip.insert(client.chain_tip().await?),
+                };
 
                 // For each confirmed outpoint, check if they can already be spent unilaterally
                 // using the exit path.
-                if spendable_at <= now {
-                    tracing::debug!(?outpoint, %amount, ?boarding_output, "Selected boarding output");
+                if boarding_output.can_be_claimed_unilaterally_by_owner(confirmed, tip)? {
+                    tracing::debug!(
+                        ?outpoint,
+                        amount = %client.inner.privacy.amount(*amount),
+                        address = %client.inner.privacy.address(boarding_output.address()),
+                        "Selected boarding output"
+                    );
 
                     selected_boarding_outputs.push(unilateral_exit::OnChainInput::new(
                         boarding_output.clone(),
@@ -90,24 +91,32 @@ where
             return Ok((selected_boarding_outputs, selected_vtxo_outputs));
         }
 
-        let outpoints = client.blockchain().find_outpoints(vtxo.address()).await?;
+        let outpoints = client.find_our_outpoints(vtxo.address()).await?;
 
         for o in outpoints.iter() {
             // Find outpoints for each VTXO.
-            if let ExplorerUtxo {
-                outpoint,
-                amount,
-                confirmation_blocktime: Some(confirmation_blocktime),
-                is_spent: false,
-            } = o
-            {
+            if o.is_spent {
+                continue;
+            }
+
+            if let Some(confirmed) = client.confirmed_in(o).await? {
+                let ExplorerUtxo {
+                    outpoint, amount, ..
+                } = o;
+                let tip = match chain_tip {
+                    Some(tip) => tip,
+                    None => *chain_tip.insert(client.chain_tip().await?),
+                };
+
                 // For each confirmed outpoint, check if they can already be spent unilaterally
                 // using the exit path.
-                if vtxo.can_be_claimed_unilaterally_by_owner(
-                    now.as_duration().try_into().map_err(Error::ad_hoc)?,
-                    std::time::Duration::from_secs(*confirmation_blocktime),
-                ) {
-                    tracing::debug!(?outpoint, %amount, ?vtxo, "Selected VTXO");
+                if vtxo.can_be_claimed_unilaterally_by_owner(confirmed, tip) {
+                    tracing::debug!(
+                        ?outpoint,
+                        amount = %client.inner.privacy.amount(*amount),
+                        address = %client.inner.privacy.address(vtxo.to_ark_address()),
+                        "Selected VTXO"
+                    );
 
                     selected_vtxo_outputs.push(unilateral_exit::VtxoInput::new(
                         vtxo.clone(),
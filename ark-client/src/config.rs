@@ -0,0 +1,188 @@
+//! Settings of the client which can be persisted and changed while it is running.
+
+use crate::notifications::ClientEvent;
+use crate::privacy::PrivacyConfig;
+use crate::round::AutoBoardPolicy;
+use crate::round::DustSweepPolicy;
+use crate::round::RoundConfig;
+use crate::round::RoundRetryPolicy;
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::ClientStore;
+use crate::wallet::OnchainWallet;
+use crate::AddressTypePolicy;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use crate::OfflineClient;
+use ark_core::coin_select::CoinSelectionStrategy;
+use ark_core::redeem::ChangePolicy;
+use bitcoin::Amount;
+use bitcoin::FeeRate;
+
+/// The settings of a [`Client`], as set via the `with_*` methods of [`OfflineClient`].
+///
+/// Hooks such as the risk oracle, round middleware and external signer are not included, since
+/// they cannot be persisted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientConfig {
+    /// The URL of the Ark server.
+    ///
+    /// Changes only take effect the next time the client connects, since the current server info
+    /// would no longer apply.
+    pub server_url: String,
+    pub min_round_confirmations: u32,
+    pub onchain_fee_rate: FeeRate,
+    pub address_type_policy: AddressTypePolicy,
+    pub round_retry_policy: RoundRetryPolicy,
+    pub round_config: RoundConfig,
+    pub dust_sweep_policy: Option<DustSweepPolicy>,
+    pub auto_board_policy: Option<AutoBoardPolicy>,
+    pub manual_review: bool,
+    pub refund_rejected_vtxos: bool,
+    pub privacy: PrivacyConfig,
+    pub change_policy: ChangePolicy,
+    pub coin_selection_strategy: CoinSelectionStrategy,
+}
+
+impl ClientConfig {
+    pub fn validate(&self) -> Result<(), Error> {
+        if !(self.server_url.starts_with("http://") || self.server_url.starts_with("https://")) {
+            return Err(Error::ad_hoc(format!(
+                "invalid Ark server URL {}: must use http or https",
+                self.server_url
+            )));
+        }
+
+        if self.onchain_fee_rate < FeeRate::BROADCAST_MIN {
+            return Err(Error::ad_hoc(format!(
+                "on-chain fee rate {} is below the minimum relay fee rate",
+                self.onchain_fee_rate
+            )));
+        }
+
+        if let Some(dust_sweep_policy) = self.dust_sweep_policy {
+            if dust_sweep_policy.threshold == Amount::ZERO {
+                return Err(Error::ad_hoc("dust sweep threshold must be positive"));
+            }
+        }
+
+        self.address_type_policy.validate()?;
+        self.round_retry_policy.validate()?;
+        self.round_config.validate()?;
+
+        Ok(())
+    }
+}
+
+/// Published as [`ClientEvent::ConfigChanged`] whenever the configuration of a [`Client`] is
+/// updated via [`Client::update_config`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigChanged {
+    pub old: ClientConfig,
+    pub new: ClientConfig,
+}
+
+impl<B, W, T> OfflineClient<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    pub fn config(&self) -> ClientConfig {
+        ClientConfig {
+            server_url: self.network_client.url().to_string(),
+            min_round_confirmations: self.min_round_confirmations,
+            onchain_fee_rate: self.onchain_fee_rate,
+            address_type_policy: self.address_type_policy.clone(),
+            round_retry_policy: self.round_retry_policy,
+            round_config: self.round_config,
+            dust_sweep_policy: self.dust_sweep_policy,
+            auto_board_policy: self.auto_board_policy,
+            manual_review: self.manual_review,
+            refund_rejected_vtxos: self.refund_rejected_vtxos,
+            privacy: self.privacy,
+            change_policy: self.change_policy,
+            coin_selection_strategy: self.coin_selection_strategy,
+        }
+    }
+
+    /// Apply the configuration persisted by [`Client::update_config`], if any.
+    ///
+    /// Persisted settings take precedence over those passed to the `with_*` methods, since they
+    /// reflect changes made at runtime.
+    pub(crate) fn load_config(&mut self) -> Result<(), Error> {
+        let config = match self.wallet.store().load_config()? {
+            Some(config) => config,
+            None => return Ok(()),
+        };
+
+        if config.server_url != self.network_client.url() {
+            self.network_client.set_url(config.server_url.clone());
+        }
+
+        self.apply_config(config);
+
+        Ok(())
+    }
+
+    fn apply_config(&mut self, config: ClientConfig) {
+        self.min_round_confirmations = config.min_round_confirmations;
+        self.onchain_fee_rate = config.onchain_fee_rate;
+        self.address_type_policy = config.address_type_policy;
+        self.round_retry_policy = config.round_retry_policy;
+        self.round_config = config.round_config;
+        self.dust_sweep_policy = config.dust_sweep_policy;
+        self.auto_board_policy = config.auto_board_policy;
+        self.manual_review = config.manual_review;
+        self.refund_rejected_vtxos = config.refund_rejected_vtxos;
+        self.privacy = config.privacy;
+        self.change_policy = config.change_policy;
+        self.coin_selection_strategy = config.coin_selection_strategy;
+    }
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// The current configuration of the client.
+    ///
+    /// The server URL is the one we are connected to, even if a different one was persisted.
+    pub fn config(&self) -> ClientConfig {
+        self.inner.config()
+    }
+
+    /// Validate, persist and apply `config`, publishing [`ClientEvent::ConfigChanged`].
+    ///
+    /// Every setting is applied immediately, except for [`ClientConfig::server_url`], which is
+    /// used the next time the client connects.
+    pub fn update_config(&mut self, config: ClientConfig) -> Result<(), Error> {
+        config.validate()?;
+
+        let old = self.config();
+        if old == config {
+            return Ok(());
+        }
+
+        self.inner.wallet.store().save_config(config.clone())?;
+
+        if config.server_url != old.server_url {
+            tracing::info!(
+                server_url = config.server_url,
+                "Ark server URL changed, will be used on next connect"
+            );
+        }
+
+        self.inner.apply_config(config.clone());
+
+        self.publish(ClientEvent::ConfigChanged(Box::new(ConfigChanged {
+            old,
+            new: config,
+        })));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+//! Verify the transactions we build against the script interpreter of Bitcoin Core, as bundled by
+//! `libbitcoinconsensus`, before we release them.
+//!
+//! With the `consensus-verify` feature, every transaction the client broadcasts (unilateral exits,
+//! spends of boarding outputs and exited VTXOs, CPFP children) is checked with
+//! [`verify_transaction`] first, and is not broadcast if any of its inputs fails verification.
+//! Forfeit transactions are not checked, as they are only complete once the Ark server signs them.
+
+use crate::error::ErrorContext;
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use bitcoin::Transaction;
+use bitcoin::TxOut;
+
+/// Check that every input of `tx` validly spends the corresponding output in `prevouts`, with all
+/// the rules of Bitcoin Core enforced, including Taproot.
+///
+/// `prevouts` must be in the same order as the inputs of `tx`.
+pub fn verify_transaction(tx: &Transaction, prevouts: &[TxOut]) -> Result<(), Error> {
+    let txid = tx.compute_txid();
+
+    if prevouts.len() != tx.input.len() {
+        return Err(Error::ad_hoc(format!(
+            "cannot verify transaction {txid}: got {} previous outputs for {} inputs",
+            prevouts.len(),
+            tx.input.len()
+        )));
+    }
+
+    let serialized_tx = bitcoin::consensus::serialize(tx);
+
+    let spent_outputs = prevouts
+        .iter()
+        .map(|prevout| bitcoinconsensus::Utxo {
+            script_pubkey: prevout.script_pubkey.as_bytes().as_ptr(),
+            script_pubkey_len: prevout.script_pubkey.len() as u32,
+            value: prevout.value.to_sat() as i64,
+        })
+        .collect::<Vec<_>>();
+
+    for (i, prevout) in prevouts.iter().enumerate() {
+        bitcoinconsensus::verify(
+            prevout.script_pubkey.as_bytes(),
+            prevout.value.to_sat(),
+            serialized_tx.as_slice(),
+            Some(&spent_outputs),
+            i,
+        )
+        .map_err(|e| {
+            Error::ad_hoc(format!(
+                "input {i} of transaction {txid} failed consensus verification: {e}"
+            ))
+        })?;
+    }
+
+    Ok(())
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Like [`verify_transaction`], but looking up the outputs spent by `tx` with the
+    /// [`Blockchain`] backend.
+    pub async fn verify_transaction(&self, tx: &Transaction) -> Result<(), Error> {
+        let mut prevouts = Vec::with_capacity(tx.input.len());
+        for input in tx.input.iter() {
+            let outpoint = input.previous_output;
+
+            let parent = self
+                .blockchain()
+                .find_tx(&outpoint.txid)
+                .await
+                .with_context(|| format!("failed to look up output {outpoint}"))?
+                .ok_or_else(|| Error::ad_hoc(format!("output {outpoint} not found")))?;
+
+            let prevout = parent
+                .output
+                .get(outpoint.vout as usize)
+                .cloned()
+                .ok_or_else(|| Error::ad_hoc(format!("output {outpoint} not found")))?;
+
+            prevouts.push(prevout);
+        }
+
+        verify_transaction(tx, &prevouts)
+    }
+}
@@ -0,0 +1,197 @@
+//! An address book, so that payments and history can refer to counterparties by name.
+
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::ClientStore;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use crate::PaymentOutcome;
+use ark_core::ArkAddress;
+use ark_core::ArkTransaction;
+use bitcoin::Address;
+use bitcoin::Amount;
+use jiff::Timestamp;
+
+/// Where to pay a [`Contact`].
+#[derive(Debug, Clone)]
+pub enum ContactAddress {
+    Ark(ArkAddress),
+    OnChain(Address),
+    /// A Lightning address such as `alice@example.com`.
+    ///
+    /// Lightning addresses can be stored, but not paid by this client yet.
+    Lightning(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Contact {
+    pub name: String,
+    pub address: ContactAddress,
+    /// The UNIX timestamp in seconds at which we last paid the contact, if ever.
+    pub last_used_at: Option<i64>,
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Add a contact to the address book, replacing the contact with the same name.
+    ///
+    /// Ark addresses must belong to our Ark server and on-chain addresses to its network.
+    pub fn add_contact(&self, name: String, address: ContactAddress) -> Result<Contact, Error> {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return Err(Error::ad_hoc("contact name cannot be empty"));
+        }
+
+        match &address {
+            ContactAddress::Ark(address) => self.check_recipient_server(address)?,
+            ContactAddress::OnChain(address) => {
+                let network = self.server_info.network;
+                if !address.as_unchecked().is_valid_for_network(network) {
+                    return Err(Error::ad_hoc(format!(
+                        "address {address} is not valid for network {network}"
+                    )));
+                }
+            }
+            ContactAddress::Lightning(address) => check_lightning_address(address)?,
+        }
+
+        let last_used_at = self
+            .contact(&name)?
+            .and_then(|contact| contact.last_used_at);
+
+        let contact = Contact {
+            name,
+            address,
+            last_used_at,
+        };
+
+        self.inner.wallet.store().save_contact(contact.clone())?;
+
+        Ok(contact)
+    }
+
+    pub fn remove_contact(&self, name: &str) -> Result<(), Error> {
+        if self.contact(name)?.is_none() {
+            return Err(Error::ad_hoc(format!("contact {name} not found")));
+        }
+
+        self.inner.wallet.store().delete_contact(name)
+    }
+
+    pub fn contact(&self, name: &str) -> Result<Option<Contact>, Error> {
+        let contact = self
+            .inner
+            .wallet
+            .store()
+            .load_contacts()?
+            .into_iter()
+            .find(|contact| contact.name == name);
+
+        Ok(contact)
+    }
+
+    /// All contacts, sorted by name.
+    pub fn contacts(&self) -> Result<Vec<Contact>, Error> {
+        let mut contacts = self.inner.wallet.store().load_contacts()?;
+        contacts.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(contacts)
+    }
+
+    /// Send `amount` to the Ark address of the contact named `name`, see
+    /// [`Client::send_vtxo_with_outcome`].
+    pub async fn send_vtxo_to_contact(
+        &self,
+        name: &str,
+        amount: Amount,
+    ) -> Result<PaymentOutcome, Error> {
+        let mut contact = self
+            .contact(name)?
+            .ok_or_else(|| Error::ad_hoc(format!("contact {name} not found")))?;
+
+        let address = match contact.address {
+            ContactAddress::Ark(address) => address,
+            ContactAddress::OnChain(_) | ContactAddress::Lightning(_) => {
+                return Err(Error::ad_hoc(format!(
+                    "contact {name} does not have an Ark address"
+                )));
+            }
+        };
+
+        let outcome = self.send_vtxo_with_outcome(address, amount).await?;
+
+        contact.last_used_at = Some(Timestamp::now().as_second());
+        if let Err(e) = self.inner.wallet.store().save_contact(contact) {
+            tracing::warn!(name, "Failed to record payment to contact: {e}");
+        }
+
+        Ok(outcome)
+    }
+
+    /// Like [`Client::transaction_history`], with the name of the counterparty of every
+    /// transaction that pays or is paid by one of our contacts.
+    pub async fn transaction_history_with_contacts(
+        &self,
+    ) -> Result<Vec<(ArkTransaction, Option<String>)>, Error> {
+        let contacts = self.inner.wallet.store().load_contacts()?;
+        let network = self.server_info.network;
+        let (server, _) = self.server_info.pk.x_only_public_key();
+
+        let history = self
+            .transaction_history()
+            .await?
+            .into_iter()
+            .map(|tx| {
+                let name = tx
+                    .counterparty_address(network, server)
+                    .and_then(|counterparty| {
+                        contacts.iter().find(|contact| match &contact.address {
+                            ContactAddress::Ark(address) => {
+                                address.to_p2tr_script_pubkey()
+                                    == counterparty.to_p2tr_script_pubkey()
+                            }
+                            ContactAddress::OnChain(_) | ContactAddress::Lightning(_) => false,
+                        })
+                    })
+                    .map(|contact| contact.name.clone());
+
+                (tx, name)
+            })
+            .collect();
+
+        Ok(history)
+    }
+}
+
+/// Check that `address` looks like a Lightning address, i.e. `user@domain`.
+fn check_lightning_address(address: &str) -> Result<(), Error> {
+    let is_valid = match address.split_once('@') {
+        Some((user, domain)) => {
+            !user.is_empty()
+                && user
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.+".contains(c))
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && domain
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-.".contains(c))
+        }
+        None => false,
+    };
+
+    if !is_valid {
+        return Err(Error::ad_hoc(format!(
+            "invalid Lightning address {address}"
+        )));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+//! Trace the VTXOs and rounds which a VTXO derives from.
+
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::OnchainWallet;
+use crate::wallet::VtxoOrigin;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use ark_core::server::ListVtxo;
+use bitcoin::Amount;
+use bitcoin::OutPoint;
+use bitcoin::Txid;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+
+/// The transaction which created a VTXO.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CustodyCreation {
+    /// The VTXO is a leaf of the VTXO tree of the round with this TXID.
+    Round { round_txid: Txid },
+    /// The VTXO is an output of the out-of-round transaction with this TXID.
+    Redeem { redeem_txid: Txid },
+}
+
+/// A VTXO in the chain of custody returned by [`Client::chain_of_custody`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustodyLink {
+    pub outpoint: OutPoint,
+    /// The value of the VTXO, if known.
+    pub amount: Option<Amount>,
+    /// Whether the VTXO was locked by one of our addresses.
+    ///
+    /// The history of other parties' VTXOs is unknown, so the chain stops at them.
+    pub is_ours: bool,
+    /// The transaction which created the VTXO, if known.
+    pub created_by: Option<CustodyCreation>,
+    /// The origin recorded locally for the VTXO, see [`Client::vtxo_origin`].
+    pub origin: Option<VtxoOrigin>,
+    /// The VTXOs spent to create this one, as far as they are known. Each of them has its own
+    /// [`CustodyLink`].
+    ///
+    /// For VTXOs created in a round, only the VTXOs that we settled in that round are known.
+    /// Boarding outputs are not included.
+    pub inputs: Vec<OutPoint>,
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Walk back from the VTXO with outpoint `vtxo_outpoint` through the rounds and out-of-round
+    /// transactions it derives from.
+    ///
+    /// The first link is the VTXO itself, followed by its ancestors in breadth-first order.
+    pub async fn chain_of_custody(
+        &self,
+        vtxo_outpoint: OutPoint,
+    ) -> Result<Vec<CustodyLink>, Error> {
+        let ListVtxo { spendable, spent } = self.list_vtxos().await?;
+
+        let ours = spendable
+            .iter()
+            .chain(spent.iter())
+            .map(|vtxo| (vtxo.outpoint, vtxo))
+            .collect::<HashMap<_, _>>();
+
+        if !ours.contains_key(&vtxo_outpoint) {
+            return Err(Error::ad_hoc(format!("VTXO {vtxo_outpoint} not found")));
+        }
+
+        // The amounts of VTXOs belonging to other parties, learnt from the redeem transactions
+        // which spent them.
+        let mut foreign_amounts = HashMap::new();
+
+        let mut links = Vec::new();
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::from([vtxo_outpoint]);
+        while let Some(outpoint) = queue.pop_front() {
+            if !visited.insert(outpoint) {
+                continue;
+            }
+
+            let vtxo = match ours.get(&outpoint) {
+                Some(vtxo) => vtxo,
+                None => {
+                    links.push(CustodyLink {
+                        outpoint,
+                        amount: foreign_amounts.get(&outpoint).copied(),
+                        is_ours: false,
+                        created_by: None,
+                        origin: None,
+                        inputs: Vec::new(),
+                    });
+
+                    continue;
+                }
+            };
+
+            let (created_by, inputs) = match vtxo.redeem_tx.as_ref() {
+                Some(redeem_tx) => {
+                    let redeem_txid = redeem_tx.unsigned_tx.compute_txid();
+
+                    let inputs = redeem_tx
+                        .unsigned_tx
+                        .input
+                        .iter()
+                        .zip(redeem_tx.inputs.iter())
+                        .map(|(input, psbt_input)| {
+                            if let Some(prevout) = psbt_input.witness_utxo.as_ref() {
+                                foreign_amounts.insert(input.previous_output, prevout.value);
+                            }
+
+                            input.previous_output
+                        })
+                        .collect::<Vec<_>>();
+
+                    (CustodyCreation::Redeem { redeem_txid }, inputs)
+                }
+                None => {
+                    let round_txid = vtxo.round_txid;
+
+                    let inputs = spent
+                        .iter()
+                        .filter(|v| v.spent_by == Some(round_txid))
+                        .map(|v| v.outpoint)
+                        .collect::<Vec<_>>();
+
+                    (CustodyCreation::Round { round_txid }, inputs)
+                }
+            };
+
+            queue.extend(inputs.iter().copied());
+
+            links.push(CustodyLink {
+                outpoint,
+                amount: Some(vtxo.amount),
+                is_ours: true,
+                created_by: Some(created_by),
+                origin: self.vtxo_origin(&outpoint)?,
+                inputs,
+            });
+        }
+
+        Ok(links)
+    }
+}
@@ -0,0 +1,190 @@
+//! Claim the out-of-round payments which the Ark server held for us while we were offline.
+//!
+//! Out-of-round payments do not require the recipient to be online: the Ark server keeps the
+//! cosigned redeem transaction and reports the new VTXO the next time the recipient asks for its
+//! VTXOs. Claiming such a delivery means validating the redeem transaction before treating the
+//! VTXO as received.
+
+use crate::notifications::ClientEvent;
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::ClientStore;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use ark_core::redeem::verify_redeem_transaction;
+use ark_core::server::VtxoOutPoint;
+use bitcoin::Amount;
+use bitcoin::OutPoint;
+use bitcoin::Txid;
+use std::collections::HashSet;
+
+/// Published as [`ClientEvent::PaymentClaimed`] for every out-of-round payment claimed with
+/// [`Client::claim_pending_deliveries`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VtxoReceived {
+    pub outpoint: OutPoint,
+    pub amount: Amount,
+    pub redeem_txid: Txid,
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Claim the out-of-round payments made to us which we have not seen yet.
+    ///
+    /// This is done by [`crate::OfflineClient::connect`], but can be called at any time to pick up
+    /// new payments.
+    ///
+    /// The first call only records the VTXOs we already hold as claimed, without announcing them:
+    /// they were received before deliveries were tracked, e.g. by an older version of this crate.
+    ///
+    /// The redeem transaction of every payment must be fully signed, including by the Ark server,
+    /// and must pay the reported amount to one of our addresses. Payments which fail this check
+    /// are left unclaimed and checked again on the next call. The others go through the usual
+    /// screening (see [`crate::risk::RiskOracle`] and [`crate::OfflineClient::with_manual_review`])
+    /// and are announced via [`Client::subscribe`].
+    pub async fn claim_pending_deliveries(&self) -> Result<Vec<VtxoReceived>, Error> {
+        let store = self.inner.wallet.store();
+
+        let vtxos = self.list_vtxos().await?;
+
+        if !store.load_deliveries_initialized()? {
+            let mut n_vtxos = 0;
+            for vtxo in vtxos.spendable.iter() {
+                if vtxo.redeem_tx.is_some() {
+                    store.save_claimed_delivery(vtxo.outpoint)?;
+                    n_vtxos += 1;
+                }
+            }
+            store.save_deliveries_initialized()?;
+
+            tracing::debug!(n_vtxos, "Started tracking out-of-round payments");
+
+            return Ok(Vec::new());
+        }
+
+        let claimed = store
+            .load_claimed_deliveries()?
+            .into_iter()
+            .collect::<HashSet<_>>();
+
+        // A redeem transaction which spends one of our VTXOs is one of our own payments.
+        let own_vtxos = vtxos
+            .spent
+            .iter()
+            .chain(vtxos.spendable.iter())
+            .map(|vtxo| vtxo.outpoint)
+            .collect::<HashSet<_>>();
+
+        let mut pending = Vec::new();
+        for vtxo in vtxos.spendable.into_iter() {
+            let Some(redeem_tx) = vtxo.redeem_tx.as_ref() else {
+                continue;
+            };
+
+            // Change outputs of our own payments are not deliveries.
+            let is_own_payment = redeem_tx
+                .unsigned_tx
+                .input
+                .iter()
+                .any(|input| own_vtxos.contains(&input.previous_output));
+            if is_own_payment
+                || claimed.contains(&vtxo.outpoint)
+                || self.vtxo_origin(&vtxo.outpoint)?.is_some()
+            {
+                continue;
+            }
+
+            pending.push(vtxo);
+        }
+
+        let mut received = Vec::new();
+        let mut valid = Vec::new();
+        for vtxo in pending.into_iter() {
+            let outpoint = vtxo.outpoint;
+
+            match self.validate_delivery(&vtxo) {
+                Ok(redeem_txid) => {
+                    tracing::info!(
+                        %outpoint,
+                        amount = %self.inner.privacy.amount(vtxo.amount),
+                        "Claimed out-of-round payment"
+                    );
+
+                    received.push(VtxoReceived {
+                        outpoint,
+                        amount: vtxo.amount,
+                        redeem_txid,
+                    });
+                    valid.push(vtxo);
+
+                    store.save_claimed_delivery(outpoint)?;
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        %outpoint,
+                        "Not claiming invalid out-of-round payment, will check it again: {e}"
+                    );
+                }
+            }
+        }
+
+        // Persist the outcome of screening the new VTXOs.
+        self.screen_vtxos(valid).await?;
+
+        for event in received.iter() {
+            self.publish(ClientEvent::PaymentClaimed(*event));
+        }
+
+        Ok(received)
+    }
+
+    /// Check that the redeem transaction which created `vtxo` is final and pays us, returning its
+    /// TXID.
+    fn validate_delivery(&self, vtxo: &VtxoOutPoint) -> Result<Txid, Error> {
+        let redeem_psbt = vtxo
+            .redeem_tx
+            .as_ref()
+            .ok_or_else(|| Error::ad_hoc("VTXO was not created out of round"))?;
+        let redeem_txid = redeem_psbt.unsigned_tx.compute_txid();
+
+        if vtxo.outpoint.txid != redeem_txid {
+            return Err(Error::ad_hoc(format!(
+                "VTXO is not an output of its redeem transaction {redeem_txid}"
+            )));
+        }
+
+        let server = self.server_info.pk.x_only_public_key().0;
+        verify_redeem_transaction(redeem_psbt, server)?;
+
+        let output = redeem_psbt
+            .unsigned_tx
+            .output
+            .get(vtxo.outpoint.vout as usize)
+            .ok_or_else(|| Error::ad_hoc("VTXO not found in redeem transaction"))?;
+
+        if output.value != vtxo.amount {
+            return Err(Error::ad_hoc(format!(
+                "redeem transaction pays {}, but the Ark server reported {}",
+                output.value, vtxo.amount
+            )));
+        }
+
+        let is_ours = self
+            .get_offchain_addresses()
+            .iter()
+            .any(|(address, _)| address.to_p2tr_script_pubkey() == output.script_pubkey);
+        if !is_ours {
+            return Err(Error::ad_hoc(
+                "redeem transaction does not pay one of our addresses",
+            ));
+        }
+
+        Ok(redeem_txid)
+    }
+}
@@ -0,0 +1,250 @@
+//! Rotate our offchain address for every payment, deriving the keys from an extended private key.
+//!
+//! See [`OfflineClient::with_offchain_keychain`] and
+//! [`OfflineClient::with_change_address_strategy`].
+
+use crate::error::ErrorContext;
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use crate::OfflineClient;
+use ark_core::default_vtxo::DefaultVtxo;
+use ark_core::derivation::GapLimitScan;
+use ark_core::derivation::KeychainKind;
+use ark_core::derivation::OffchainKeychain;
+use ark_core::ArkAddress;
+use bitcoin::bip32::Xpriv;
+use bitcoin::key::Keypair;
+use std::sync::Mutex;
+
+/// The keychain deriving our offchain addresses, and how many of them we have handed out.
+pub(crate) struct OffchainKeys {
+    keychain: OffchainKeychain,
+    /// The number of derived external addresses that we watch, i.e. indices `0..revealed`.
+    ///
+    /// Not persisted: call [`Client::discover_offchain_addresses`] after connecting to find the
+    /// addresses used in earlier sessions.
+    revealed: Mutex<u32>,
+    /// Like `revealed`, for the internal addresses receiving our change.
+    change_revealed: Mutex<u32>,
+}
+
+impl OffchainKeys {
+    fn revealed(&self, kind: KeychainKind) -> &Mutex<u32> {
+        match kind {
+            KeychainKind::External => &self.revealed,
+            KeychainKind::Internal => &self.change_revealed,
+        }
+    }
+}
+
+/// Where the change of our payments goes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChangeAddressStrategy {
+    /// The address of the client keypair, see [`Client::get_offchain_address`].
+    #[default]
+    ClientAddress,
+    /// A fresh address for every change output, derived from the internal branch of the offchain
+    /// keychain (see [`KeychainKind::Internal`]).
+    ///
+    /// These addresses are never handed out, so the addresses we receive payments on only ever
+    /// hold what others sent us. Requires [`OfflineClient::with_offchain_keychain`].
+    Internal,
+}
+
+impl<B, W, T> OfflineClient<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Derive additional offchain addresses from `xpriv`, so that a fresh address can be handed out
+    /// for every payment with [`Client::new_offchain_address`].
+    ///
+    /// The address of the client keypair remains watched, and keeps being used for change unless
+    /// configured otherwise with [`OfflineClient::with_change_address_strategy`].
+    pub fn with_offchain_keychain(mut self, xpriv: Xpriv) -> Self {
+        self.offchain_keys = Some(OffchainKeys {
+            keychain: OffchainKeychain::new(xpriv),
+            revealed: Mutex::new(0),
+            change_revealed: Mutex::new(0),
+        });
+        self
+    }
+
+    /// Send the change of our payments according to `change_address_strategy`.
+    ///
+    /// Defaults to [`ChangeAddressStrategy::ClientAddress`].
+    pub fn with_change_address_strategy(
+        mut self,
+        change_address_strategy: ChangeAddressStrategy,
+    ) -> Self {
+        self.change_address_strategy = change_address_strategy;
+        self
+    }
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Derive an offchain address which has not been handed out before.
+    ///
+    /// Fails if the client was not built with [`OfflineClient::with_offchain_keychain`].
+    pub fn new_offchain_address(&self) -> Result<(ArkAddress, DefaultVtxo), Error> {
+        let offchain_keys = self.offchain_keys()?;
+
+        self.reveal_offchain_address(offchain_keys, KeychainKind::External)
+    }
+
+    /// The address to send the change of a payment to, according to the
+    /// [`ChangeAddressStrategy`] of the client.
+    pub(crate) fn change_address(&self) -> Result<ArkAddress, Error> {
+        match self.inner.change_address_strategy {
+            ChangeAddressStrategy::ClientAddress => {
+                let (address, _) = self.get_offchain_address();
+
+                Ok(address)
+            }
+            ChangeAddressStrategy::Internal => {
+                let offchain_keys = self
+                    .offchain_keys()
+                    .context("cannot derive internal change address")?;
+
+                let (address, _) =
+                    self.reveal_offchain_address(offchain_keys, KeychainKind::Internal)?;
+
+                Ok(address)
+            }
+        }
+    }
+
+    fn reveal_offchain_address(
+        &self,
+        offchain_keys: &OffchainKeys,
+        kind: KeychainKind,
+    ) -> Result<(ArkAddress, DefaultVtxo), Error> {
+        let mut revealed = offchain_keys
+            .revealed(kind)
+            .lock()
+            .expect("lock not poisoned");
+        let (_, vtxo) = self.derive_offchain_address(&offchain_keys.keychain, kind, *revealed)?;
+        *revealed += 1;
+
+        Ok((vtxo.to_ark_address(), vtxo))
+    }
+
+    /// Find the derived offchain addresses which have ever held VTXOs, stopping after `gap_limit`
+    /// consecutive addresses without any, e.g. [`ark_core::derivation::DEFAULT_GAP_LIMIT`].
+    ///
+    /// Both the external and the internal branch of the keychain are scanned. Every address up to
+    /// the last used one of each branch is watched from then on. Returns the number of watched
+    /// derived addresses.
+    pub async fn discover_offchain_addresses(&self, gap_limit: u32) -> Result<u32, Error> {
+        let offchain_keys = self.offchain_keys()?;
+
+        let mut watched = 0;
+        for kind in [KeychainKind::External, KeychainKind::Internal] {
+            let mut scan = GapLimitScan::new(gap_limit);
+            while let Some(index) = scan.next_index() {
+                let (_, vtxo) =
+                    self.derive_offchain_address(&offchain_keys.keychain, kind, index)?;
+
+                let list = self
+                    .network_client()
+                    .list_vtxos(&vtxo.to_ark_address())
+                    .await?;
+
+                scan.record(!list.spendable.is_empty() || !list.spent.is_empty());
+            }
+
+            let discovered = scan.last_used().map_or(0, |index| index + 1);
+
+            let mut revealed = offchain_keys
+                .revealed(kind)
+                .lock()
+                .expect("lock not poisoned");
+            *revealed = (*revealed).max(discovered);
+
+            tracing::info!(
+                ?kind,
+                discovered,
+                watched = *revealed,
+                "Discovered offchain addresses"
+            );
+
+            watched += *revealed;
+        }
+
+        Ok(watched)
+    }
+
+    /// The keypair and VTXO of every derived offchain address that we watch.
+    pub(crate) fn derived_offchain_addresses(&self) -> Vec<(Keypair, DefaultVtxo)> {
+        let offchain_keys = match self.inner.offchain_keys.as_ref() {
+            Some(offchain_keys) => offchain_keys,
+            None => return Vec::new(),
+        };
+
+        [KeychainKind::External, KeychainKind::Internal]
+            .into_iter()
+            .flat_map(|kind| {
+                let revealed = *offchain_keys
+                    .revealed(kind)
+                    .lock()
+                    .expect("lock not poisoned");
+
+                (0..revealed).filter_map(move |index| {
+                    self.derive_offchain_address(&offchain_keys.keychain, kind, index)
+                        .inspect_err(|e| {
+                            tracing::warn!(?kind, index, "Failed to derive offchain address: {e}")
+                        })
+                        .ok()
+                })
+            })
+            .collect()
+    }
+
+    /// Every keypair which may own our VTXOs.
+    pub(crate) fn keypairs(&self) -> Vec<Keypair> {
+        std::iter::once(*self.kp())
+            .chain(
+                self.derived_offchain_addresses()
+                    .into_iter()
+                    .map(|(kp, _)| kp),
+            )
+            .collect()
+    }
+
+    fn derive_offchain_address(
+        &self,
+        keychain: &OffchainKeychain,
+        kind: KeychainKind,
+        index: u32,
+    ) -> Result<(Keypair, DefaultVtxo), Error> {
+        let (server, _) = self.server_info.pk.x_only_public_key();
+
+        let derived = keychain.default_vtxo(
+            self.secp(),
+            kind,
+            index,
+            server,
+            self.server_info.unilateral_exit_delay,
+            self.server_info.network,
+        )?;
+
+        Ok(derived)
+    }
+
+    fn offchain_keys(&self) -> Result<&OffchainKeys, Error> {
+        self.inner
+            .offchain_keys
+            .as_ref()
+            .ok_or_else(|| Error::ad_hoc("client has no offchain keychain"))
+    }
+}
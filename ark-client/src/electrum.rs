@@ -0,0 +1,1093 @@
+//! A [`Blockchain`] backed by an [Electrum](https://electrum-protocol.readthedocs.io) server.
+//!
+//! Only available with the `electrum` feature.
+//!
+//! Use an `ssl://` URL to connect over TLS, and [`ElectrumBlockchain::with_socks5_proxy`] to
+//! connect through Tor.
+
+use crate::error::ErrorContext;
+use crate::tx_broadcast::BroadcastError;
+use crate::wallet::WalletBirthday;
+use crate::Blockchain;
+use crate::Error;
+use crate::ExplorerUtxo;
+use crate::SpendStatus;
+use ark_core::exit_delay::median_time_past;
+use ark_core::exit_delay::MEDIAN_TIME_SPAN;
+use bitcoin::block::Header;
+use bitcoin::consensus::deserialize;
+use bitcoin::consensus::encode::deserialize_hex;
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::hashes::sha256;
+use bitcoin::hashes::Hash;
+use bitcoin::hex::DisplayHex;
+use bitcoin::hex::FromHex;
+use bitcoin::Address;
+use bitcoin::OutPoint;
+use bitcoin::Script;
+use bitcoin::Transaction;
+use bitcoin::Txid;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tokio_socks::tcp::Socks5Stream;
+
+/// The version of the Electrum protocol we speak.
+const PROTOCOL_VERSION: &str = "1.4";
+
+/// The name we give to the Electrum server when negotiating the protocol version.
+const CLIENT_NAME: &str = "ark-client";
+
+/// The default for [`ElectrumBlockchain::with_timeout`].
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Clone)]
+pub struct ElectrumBlockchain {
+    url: String,
+    host: String,
+    port: u16,
+    use_tls: bool,
+    accept_invalid_certs: bool,
+    socks5_proxy: Option<String>,
+    timeout: Duration,
+    /// Established on the first request, and again on the next request after it breaks.
+    connection: Arc<Mutex<Option<Connection>>>,
+}
+
+impl ElectrumBlockchain {
+    /// Use the Electrum server at `url`, e.g. `ssl://electrum.blockstream.info:50002`.
+    ///
+    /// The scheme is `tcp://` for plaintext connections and `ssl://` for TLS. The server is not
+    /// contacted until the first request.
+    pub fn new(url: &str) -> Result<Self, Error> {
+        let invalid_url = || {
+            Error::ad_hoc(format!(
+                "invalid Electrum URL {url}: expected tcp://<host>:<port> or ssl://<host>:<port>"
+            ))
+        };
+
+        let (use_tls, address) = match url.split_once("://") {
+            Some(("ssl", address)) => (true, address),
+            Some(("tcp", address)) => (false, address),
+            _ => return Err(invalid_url()),
+        };
+
+        let (host, port) = address.rsplit_once(':').ok_or_else(invalid_url)?;
+        let port = port.parse().map_err(|_| invalid_url())?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(invalid_url());
+        }
+
+        Ok(Self {
+            url: url.to_string(),
+            host: host.to_string(),
+            port,
+            use_tls,
+            accept_invalid_certs: false,
+            socks5_proxy: None,
+            timeout: DEFAULT_TIMEOUT,
+            connection: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Connect through the SOCKS5 proxy at `proxy`, e.g. `127.0.0.1:9050` for a local Tor daemon.
+    ///
+    /// The host of the Electrum server is resolved by the proxy, so `.onion` servers can be used.
+    pub fn with_socks5_proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.socks5_proxy = Some(proxy.into());
+        self
+    }
+
+    /// Accept TLS certificates which are self-signed or otherwise invalid, as used by many
+    /// Electrum servers.
+    ///
+    /// The connection can then be intercepted, so only do this for servers reached through Tor or
+    /// another trusted channel.
+    pub fn with_accept_invalid_certs(mut self, accept_invalid_certs: bool) -> Self {
+        self.accept_invalid_certs = accept_invalid_certs;
+        self
+    }
+
+    /// Fail requests which take longer than `timeout`, including connecting.
+    ///
+    /// Defaults to 30 seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The outputs paying `address`. Outputs confirmed before `start_timestamp` may be left out.
+    async fn find_outpoints_confirmed_since(
+        &self,
+        address: &Address,
+        start_timestamp: Option<u64>,
+    ) -> Result<Vec<ExplorerUtxo>, Error> {
+        let script_pubkey = address.script_pubkey();
+
+        let history = self
+            .get_history(&script_pubkey)
+            .await
+            .with_context(|| format!("failed to get transactions of address {address}"))?;
+        let unspent = self
+            .list_unspent(&script_pubkey)
+            .await
+            .with_context(|| format!("failed to get unspent outputs of address {address}"))?;
+
+        let mut block_times = HashMap::new();
+        let mut outputs = Vec::new();
+        for entry in history {
+            let confirmation_blocktime = match entry.height {
+                Some(height) => match block_times.get(&height) {
+                    Some(block_time) => Some(*block_time),
+                    None => {
+                        let block_time = self.get_block_time(height).await?;
+                        block_times.insert(height, block_time);
+
+                        Some(block_time)
+                    }
+                },
+                None => None,
+            };
+
+            if matches!(
+                (confirmation_blocktime, start_timestamp),
+                (Some(block_time), Some(start)) if block_time < start
+            ) {
+                continue;
+            }
+
+            let tx = self.get_transaction(&entry.txid).await?.ok_or_else(|| {
+                Error::ad_hoc(format!(
+                    "Electrum server does not know transaction {} of address {address}",
+                    entry.txid
+                ))
+            })?;
+
+            outputs.extend(
+                tx.output
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, output)| output.script_pubkey == script_pubkey)
+                    .map(|(vout, output)| {
+                        let outpoint = OutPoint::new(entry.txid, vout as u32);
+
+                        ExplorerUtxo {
+                            outpoint,
+                            amount: output.value,
+                            confirmation_blocktime,
+                            confirmation_height: entry.height,
+                            is_spent: !unspent.contains(&outpoint),
+                        }
+                    }),
+            );
+        }
+
+        Ok(outputs)
+    }
+
+    /// The transactions which pay to or spend from `script`.
+    async fn get_history(&self, script: &Script) -> Result<Vec<HistoryEntry>, Error> {
+        let history = self
+            .request(
+                "blockchain.scripthash.get_history",
+                json!([script_hash(script)]),
+            )
+            .await?;
+
+        as_array(&history)?
+            .iter()
+            .map(|entry| {
+                Ok(HistoryEntry {
+                    txid: parse_txid(entry)?,
+                    // Unconfirmed transactions have a height of 0, or -1 if they have unconfirmed
+                    // inputs.
+                    height: u32::try_from(as_i64(entry, "height")?)
+                        .ok()
+                        .filter(|height| *height > 0),
+                })
+            })
+            .collect()
+    }
+
+    /// The unspent outputs paying to `script`, including unconfirmed ones.
+    async fn list_unspent(&self, script: &Script) -> Result<HashSet<OutPoint>, Error> {
+        let unspent = self
+            .request(
+                "blockchain.scripthash.listunspent",
+                json!([script_hash(script)]),
+            )
+            .await?;
+
+        as_array(&unspent)?
+            .iter()
+            .map(|output| {
+                let vout = u32::try_from(as_i64(output, "tx_pos")?)
+                    .map_err(|_| Error::ad_hoc("invalid tx_pos in Electrum response"))?;
+
+                Ok(OutPoint::new(parse_txid(output)?, vout))
+            })
+            .collect()
+    }
+
+    async fn get_transaction(&self, txid: &Txid) -> Result<Option<Transaction>, Error> {
+        // Electrum servers report unknown transactions as errors.
+        let tx = match self
+            .call("blockchain.transaction.get", json!([txid.to_string()]))
+            .await?
+        {
+            Ok(tx) => tx,
+            Err(_) => return Ok(None),
+        };
+
+        let tx = tx
+            .as_str()
+            .ok_or_else(|| Error::ad_hoc("expected hex transaction in Electrum response"))?;
+
+        deserialize_hex(tx)
+            .map(Some)
+            .map_err(|e| Error::ad_hoc(format!("invalid transaction {txid} from Electrum: {e}")))
+    }
+
+    /// The timestamp of the block at `height`.
+    async fn get_block_time(&self, height: u32) -> Result<u64, Error> {
+        let header = self
+            .request("blockchain.block.header", json!([height]))
+            .await
+            .with_context(|| format!("failed to get block header at height {height}"))?;
+
+        let header = header
+            .as_str()
+            .ok_or_else(|| Error::ad_hoc("expected hex block header in Electrum response"))?;
+        let header = deserialize_hex::<Header>(header)
+            .map_err(|e| Error::ad_hoc(format!("invalid block header from Electrum: {e}")))?;
+
+        Ok(header.time as u64)
+    }
+
+    /// Make a request which the Electrum server is expected to answer successfully.
+    async fn request(&self, method: &str, params: Value) -> Result<Value, Error> {
+        self.call(method, params).await?.map_err(|message| {
+            Error::ad_hoc(format!(
+                "Electrum server failed to answer {method}: {message}"
+            ))
+        })
+    }
+
+    /// Make a request, returning the error message if the Electrum server answers with an error.
+    ///
+    /// The connection is dropped if it breaks, so that the next request reconnects.
+    async fn call(&self, method: &str, params: Value) -> Result<Result<Value, String>, Error> {
+        let mut connection = self.connection.lock().await;
+
+        let response = tokio::time::timeout(self.timeout, async {
+            if connection.is_none() {
+                *connection = Some(self.connect().await?);
+            }
+
+            connection
+                .as_mut()
+                .expect("connected above")
+                .call(method, params)
+                .await
+        })
+        .await
+        .unwrap_or_else(|_| {
+            Err(Error::ad_hoc(format!(
+                "Electrum request {method} timed out after {:?}",
+                self.timeout
+            )))
+        });
+
+        if response.is_err() {
+            *connection = None;
+        }
+
+        response
+    }
+
+    async fn connect(&self) -> Result<Connection, Error> {
+        let target = (self.host.as_str(), self.port);
+
+        let tcp = match &self.socks5_proxy {
+            Some(proxy) => Socks5Stream::connect(proxy.as_str(), target)
+                .await
+                .map_err(|e| {
+                    Error::ad_hoc(format!(
+                        "failed to connect to Electrum server {} through proxy {proxy}: {e}",
+                        self.url
+                    ))
+                })?
+                .into_inner(),
+            None => TcpStream::connect(target).await.map_err(|e| {
+                Error::ad_hoc(format!(
+                    "failed to connect to Electrum server {}: {e}",
+                    self.url
+                ))
+            })?,
+        };
+
+        let stream: Box<dyn AsyncStream> = if self.use_tls {
+            let connector = native_tls::TlsConnector::builder()
+                .danger_accept_invalid_certs(self.accept_invalid_certs)
+                .build()
+                .map_err(|e| Error::ad_hoc(format!("failed to set up TLS: {e}")))?;
+
+            let tls = tokio_native_tls::TlsConnector::from(connector)
+                .connect(&self.host, tcp)
+                .await
+                .map_err(|e| {
+                    Error::ad_hoc(format!(
+                        "TLS handshake with Electrum server {} failed: {e}",
+                        self.url
+                    ))
+                })?;
+
+            Box::new(tls)
+        } else {
+            Box::new(tcp)
+        };
+
+        let mut connection = Connection {
+            stream: BufReader::new(stream),
+            next_id: 0,
+        };
+
+        connection
+            .call("server.version", json!([CLIENT_NAME, PROTOCOL_VERSION]))
+            .await?
+            .map_err(|message| {
+                Error::ad_hoc(format!(
+                    "Electrum server {} does not support protocol version {PROTOCOL_VERSION}: \
+                     {message}",
+                    self.url
+                ))
+            })?;
+
+        Ok(connection)
+    }
+}
+
+impl fmt::Debug for ElectrumBlockchain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ElectrumBlockchain")
+            .field("url", &self.url)
+            .field("accept_invalid_certs", &self.accept_invalid_certs)
+            .field("socks5_proxy", &self.socks5_proxy)
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Blockchain for ElectrumBlockchain {
+    async fn find_outpoints(&self, address: &Address) -> Result<Vec<ExplorerUtxo>, Error> {
+        self.find_outpoints_confirmed_since(address, None).await
+    }
+
+    async fn find_tx(&self, txid: &Txid) -> Result<Option<Transaction>, Error> {
+        self.get_transaction(txid)
+            .await
+            .with_context(|| format!("failed to get transaction {txid}"))
+    }
+
+    async fn get_output_status(&self, txid: &Txid, vout: u32) -> Result<SpendStatus, Error> {
+        let unspent = SpendStatus { spend_txid: None };
+
+        let tx = match self.find_tx(txid).await? {
+            Some(tx) => tx,
+            None => return Ok(unspent),
+        };
+        let output = tx
+            .output
+            .get(vout as usize)
+            .ok_or_else(|| Error::ad_hoc(format!("transaction {txid} has no output {vout}")))?;
+
+        let outpoint = OutPoint::new(*txid, vout);
+        let context = || format!("failed to get status of output {outpoint}");
+
+        if self
+            .list_unspent(&output.script_pubkey)
+            .await
+            .with_context(context)?
+            .contains(&outpoint)
+        {
+            return Ok(unspent);
+        }
+
+        // Electrum servers cannot look up the transaction spending an output, but it must be in
+        // the history of the output's script.
+        let history = self
+            .get_history(&output.script_pubkey)
+            .await
+            .with_context(context)?;
+        for entry in history.iter().filter(|entry| entry.txid != *txid) {
+            let candidate = self
+                .get_transaction(&entry.txid)
+                .await
+                .with_context(context)?;
+
+            let is_spender = candidate.is_some_and(|candidate| {
+                candidate
+                    .input
+                    .iter()
+                    .any(|input| input.previous_output == outpoint)
+            });
+            if is_spender {
+                return Ok(SpendStatus {
+                    spend_txid: Some(entry.txid),
+                });
+            }
+        }
+
+        Ok(unspent)
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> Result<(), Error> {
+        let response = self
+            .call(
+                "blockchain.transaction.broadcast",
+                json!([serialize_hex(tx)]),
+            )
+            .await
+            .with_context(|| format!("failed to broadcast transaction {}", tx.compute_txid()))?;
+
+        match response {
+            Ok(_) => Ok(()),
+            // Electrum servers relay the reason given by Bitcoin Core for rejecting the
+            // transaction.
+            Err(message) => Err(Error::broadcast(BroadcastError::from_reason(message))),
+        }
+    }
+
+    async fn find_outpoints_since(
+        &self,
+        address: &Address,
+        birthday: WalletBirthday,
+    ) -> Result<Vec<ExplorerUtxo>, Error> {
+        self.find_outpoints_confirmed_since(address, Some(birthday.scan_start_timestamp()))
+            .await
+    }
+
+    async fn get_confirmations(&self, txid: &Txid) -> Result<u32, Error> {
+        let tx = match self.find_tx(txid).await? {
+            Some(tx) => tx,
+            None => return Ok(0),
+        };
+
+        // Electrum servers only know the height of a transaction through the history of the
+        // scripts it pays to.
+        let output = match tx
+            .output
+            .iter()
+            .find(|output| !output.script_pubkey.is_op_return())
+        {
+            Some(output) => output,
+            None => return Ok(0),
+        };
+
+        let height = self
+            .get_history(&output.script_pubkey)
+            .await
+            .with_context(|| format!("failed to get status of transaction {txid}"))?
+            .into_iter()
+            .find(|entry| entry.txid == *txid)
+            .and_then(|entry| entry.height);
+
+        let height = match height {
+            Some(height) => height,
+            None => return Ok(0),
+        };
+
+        let tip = self.get_tip_height().await?;
+
+        Ok(tip.saturating_sub(height) + 1)
+    }
+
+    async fn get_tip_height(&self) -> Result<u32, Error> {
+        // This also subscribes to new blocks, but the notifications are ignored.
+        let tip = self
+            .request("blockchain.headers.subscribe", json!([]))
+            .await
+            .context("failed to get blockchain tip")?;
+
+        u32::try_from(as_i64(&tip, "height")?)
+            .map_err(|_| Error::ad_hoc("invalid tip height in Electrum response"))
+    }
+    async fn get_median_time_past(&self, height: u32) -> Result<u64, Error> {
+        let start_height = height.saturating_sub(MEDIAN_TIME_SPAN as u32 - 1);
+        let count = height - start_height + 1;
+
+        let headers = self
+            .request("blockchain.block.headers", json!([start_height, count]))
+            .await
+            .with_context(|| format!("failed to get block headers up to height {height}"))?;
+
+        let headers = headers
+            .get("hex")
+            .and_then(Value::as_str)
+            .ok_or_else(|| Error::ad_hoc("expected hex block headers in Electrum response"))?;
+        let headers = Vec::<u8>::from_hex(headers)
+            .map_err(|e| Error::ad_hoc(format!("invalid block headers from Electrum: {e}")))?;
+
+        // The headers are concatenated, each of them taking 80 bytes.
+        let timestamps = headers
+            .chunks(Header::SIZE)
+            .map(|header| {
+                deserialize::<Header>(header)
+                    .map(|header| header.time as u64)
+                    .map_err(|e| Error::ad_hoc(format!("invalid block header from Electrum: {e}")))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        median_time_past(&timestamps)
+            .map(|median_time_past| median_time_past.as_secs())
+            .ok_or_else(|| Error::ad_hoc(format!("no block header at height {height}")))
+    }
+}
+
+struct HistoryEntry {
+    txid: Txid,
+    /// The height of the block confirming the transaction, if it is confirmed.
+    height: Option<u32>,
+}
+
+trait AsyncStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<S> AsyncStream for S where S: AsyncRead + AsyncWrite + Unpin + Send {}
+
+/// A connection to an Electrum server, speaking newline-delimited JSON-RPC.
+struct Connection {
+    stream: BufReader<Box<dyn AsyncStream>>,
+    next_id: u64,
+}
+
+impl Connection {
+    async fn call(&mut self, method: &str, params: Value) -> Result<Result<Value, String>, Error> {
+        self.next_id += 1;
+        let id = self.next_id;
+
+        let mut request = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        })
+        .to_string()
+        .into_bytes();
+        request.push(b'\n');
+
+        self.stream
+            .write_all(&request)
+            .await
+            .map_err(connection_error)?;
+        self.stream.flush().await.map_err(connection_error)?;
+
+        loop {
+            let mut line = String::new();
+            let read = self
+                .stream
+                .read_line(&mut line)
+                .await
+                .map_err(connection_error)?;
+            if read == 0 {
+                return Err(Error::ad_hoc("Electrum server closed the connection"));
+            }
+
+            let mut response: Value = serde_json::from_str(&line)
+                .map_err(|e| Error::ad_hoc(format!("invalid JSON from Electrum server: {e}")))?;
+
+            // Skip notifications for subscriptions, which have no ID.
+            if response.get("id").and_then(Value::as_u64) != Some(id) {
+                continue;
+            }
+
+            if let Some(error) = response.get("error").filter(|error| !error.is_null()) {
+                let message = match error.get("message").and_then(Value::as_str) {
+                    Some(message) => message.to_string(),
+                    None => error.to_string(),
+                };
+
+                return Ok(Err(message));
+            }
+
+            return Ok(Ok(response["result"].take()));
+        }
+    }
+}
+
+/// The key under which Electrum servers index `script`: its SHA256 hash, byte-reversed.
+fn script_hash(script: &Script) -> String {
+    let mut hash = sha256::Hash::hash(script.as_bytes()).to_byte_array();
+    hash.reverse();
+
+    hash.to_lower_hex_string()
+}
+
+fn as_array(value: &Value) -> Result<&Vec<Value>, Error> {
+    value
+        .as_array()
+        .ok_or_else(|| Error::ad_hoc(format!("expected array in Electrum response: {value}")))
+}
+
+fn as_i64(value: &Value, field: &str) -> Result<i64, Error> {
+    value
+        .get(field)
+        .and_then(Value::as_i64)
+        .ok_or_else(|| Error::ad_hoc(format!("missing {field} in Electrum response: {value}")))
+}
+
+fn parse_txid(value: &Value) -> Result<Txid, Error> {
+    value
+        .get("tx_hash")
+        .and_then(Value::as_str)
+        .and_then(|txid| txid.parse().ok())
+        .ok_or_else(|| Error::ad_hoc(format!("missing tx_hash in Electrum response: {value}")))
+}
+
+fn connection_error(error: std::io::Error) -> Error {
+    Error::ad_hoc(format!("Electrum connection failed: {error}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::block;
+    use bitcoin::transaction;
+    use bitcoin::Amount;
+    use bitcoin::BlockHash;
+    use bitcoin::CompactTarget;
+    use bitcoin::Network;
+    use bitcoin::ScriptBuf;
+    use bitcoin::TxIn;
+    use bitcoin::TxMerkleNode;
+    use bitcoin::TxOut;
+    use tokio::io::AsyncBufReadExt;
+    use tokio::net::TcpListener;
+    use tokio::runtime::Runtime;
+
+    /// The timestamps of the blocks at heights 100 and 101.
+    const TIME_100: u32 = 1_700_000_000;
+    const TIME_101: u32 = 1_700_000_600;
+
+    /// A request and the response recorded from an Electrum server, without the JSON-RPC envelope.
+    type Recorded = (&'static str, Value, Value);
+
+    /// Serve `recorded` responses on a local port, as an Electrum server would, and connect to it.
+    fn electrum(recorded: Vec<Recorded>) -> (Runtime, ElectrumBlockchain) {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        let listener = runtime.block_on(TcpListener::bind("127.0.0.1:0")).unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let recorded = Arc::new(recorded);
+        runtime.spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(serve(stream, recorded.clone()));
+            }
+        });
+
+        let electrum = ElectrumBlockchain::new(&format!("tcp://127.0.0.1:{port}")).unwrap();
+
+        (runtime, electrum)
+    }
+
+    async fn serve(stream: TcpStream, recorded: Arc<Vec<Recorded>>) {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+
+        while let Some(line) = lines.next_line().await.unwrap() {
+            let request: Value = serde_json::from_str(&line).unwrap();
+            let method = request["method"].as_str().unwrap();
+            let params = &request["params"];
+
+            let mut response = match method {
+                "server.version" => json!({ "result": ["ElectrumX 1.16.0", "1.4"] }),
+                _ => recorded
+                    .iter()
+                    .find(|(m, p, _)| *m == method && p == params)
+                    .map(|(_, _, response)| response.clone())
+                    .unwrap_or_else(|| {
+                        json!({
+                            "error": {
+                                "code": -32600,
+                                "message": format!("unexpected request {method} {params}"),
+                            }
+                        })
+                    }),
+            };
+            response["jsonrpc"] = json!("2.0");
+            response["id"] = request["id"].clone();
+
+            // Notifications of our subscriptions may arrive before the response.
+            let notification = json!({
+                "jsonrpc": "2.0",
+                "method": "blockchain.headers.subscribe",
+                "params": [{ "height": 106, "hex": "" }],
+            });
+
+            writer
+                .write_all(format!("{notification}\n{response}\n").as_bytes())
+                .await
+                .unwrap();
+        }
+    }
+
+    fn address() -> Address {
+        Address::p2wsh(&ScriptBuf::from_bytes(vec![0x51]), Network::Regtest)
+    }
+
+    fn other_script() -> ScriptBuf {
+        Address::p2wsh(&ScriptBuf::from_bytes(vec![0x52]), Network::Regtest).script_pubkey()
+    }
+
+    fn tx(inputs: Vec<OutPoint>, outputs: Vec<(ScriptBuf, u64)>) -> Transaction {
+        Transaction {
+            version: transaction::Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: inputs
+                .into_iter()
+                .map(|previous_output| TxIn {
+                    previous_output,
+                    ..Default::default()
+                })
+                .collect(),
+            output: outputs
+                .into_iter()
+                .map(|(script_pubkey, value)| TxOut {
+                    value: Amount::from_sat(value),
+                    script_pubkey,
+                })
+                .collect(),
+        }
+    }
+
+    fn header(time: u32) -> Header {
+        Header {
+            version: block::Version::TWO,
+            prev_blockhash: BlockHash::all_zeros(),
+            merkle_root: TxMerkleNode::all_zeros(),
+            time,
+            bits: CompactTarget::from_consensus(0x207fffff),
+            nonce: 0,
+        }
+    }
+
+    /// A wallet which received two outputs in block 100, spent the first of them in block 101 and
+    /// received another output which is still unconfirmed.
+    struct Wallet {
+        funding: Transaction,
+        spend: Transaction,
+        payment: Transaction,
+    }
+
+    impl Wallet {
+        fn new() -> Self {
+            let script = address().script_pubkey();
+
+            let funding = tx(
+                vec![OutPoint::new(Txid::all_zeros(), 0)],
+                vec![
+                    (script.clone(), 1_000),
+                    (other_script(), 2_000),
+                    (script.clone(), 3_000),
+                ],
+            );
+            let spend = tx(
+                vec![OutPoint::new(funding.compute_txid(), 0)],
+                vec![(other_script(), 900)],
+            );
+            let payment = tx(
+                vec![OutPoint::new(Txid::all_zeros(), 1)],
+                vec![(script, 4_000)],
+            );
+
+            Self {
+                funding,
+                spend,
+                payment,
+            }
+        }
+
+        fn recorded(&self) -> Vec<Recorded> {
+            let script_hash = script_hash(&address().script_pubkey());
+            let funding = self.funding.compute_txid().to_string();
+            let spend = self.spend.compute_txid().to_string();
+            let payment = self.payment.compute_txid().to_string();
+
+            let mut recorded = vec![
+                (
+                    "blockchain.scripthash.get_history",
+                    json!([script_hash]),
+                    json!({
+                        "result": [
+                            { "tx_hash": funding, "height": 100 },
+                            { "tx_hash": spend, "height": 101 },
+                            { "tx_hash": payment, "height": 0, "fee": 141 },
+                        ]
+                    }),
+                ),
+                (
+                    "blockchain.scripthash.listunspent",
+                    json!([script_hash]),
+                    json!({
+                        "result": [
+                            { "tx_hash": funding, "tx_pos": 2, "height": 100, "value": 3_000 },
+                            { "tx_hash": payment, "tx_pos": 0, "height": 0, "value": 4_000 },
+                        ]
+                    }),
+                ),
+                (
+                    "blockchain.block.header",
+                    json!([100]),
+                    json!({ "result": serialize_hex(&header(TIME_100)) }),
+                ),
+                (
+                    "blockchain.block.header",
+                    json!([101]),
+                    json!({ "result": serialize_hex(&header(TIME_101)) }),
+                ),
+                (
+                    "blockchain.headers.subscribe",
+                    json!([]),
+                    json!({
+                        "result": { "height": 105, "hex": serialize_hex(&header(TIME_101)) }
+                    }),
+                ),
+            ];
+
+            for tx in [&self.funding, &self.spend, &self.payment] {
+                recorded.push((
+                    "blockchain.transaction.get",
+                    json!([tx.compute_txid().to_string()]),
+                    json!({ "result": serialize_hex(tx) }),
+                ));
+            }
+
+            recorded
+        }
+    }
+
+    #[test]
+    fn script_hash_matches_electrum_protocol() {
+        // The example of the Electrum protocol documentation, for the P2PKH address
+        // 1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa.
+        let script =
+            ScriptBuf::from_hex("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac").unwrap();
+
+        assert_eq!(
+            script_hash(&script),
+            "8b01df4e368ea28f8dc0423bcf7a4923e3a12d307c875e47a0cfbf90b5c39161"
+        );
+    }
+
+    #[test]
+    fn outputs_are_found_with_their_confirmation_and_spend_status() {
+        let wallet = Wallet::new();
+        let (runtime, electrum) = electrum(wallet.recorded());
+
+        let mut outputs = runtime
+            .block_on(electrum.find_outpoints(&address()))
+            .unwrap()
+            .into_iter()
+            .map(|output| {
+                (
+                    output.outpoint,
+                    output.amount.to_sat(),
+                    output.confirmation_blocktime,
+                    output.confirmation_height,
+                    output.is_spent,
+                )
+            })
+            .collect::<Vec<_>>();
+        outputs.sort_by_key(|(_, amount, ..)| *amount);
+
+        let funding = wallet.funding.compute_txid();
+        let payment = wallet.payment.compute_txid();
+        let confirmed = Some(TIME_100 as u64);
+        assert_eq!(
+            outputs,
+            vec![
+                (OutPoint::new(funding, 0), 1_000, confirmed, Some(100), true),
+                (
+                    OutPoint::new(funding, 2),
+                    3_000,
+                    confirmed,
+                    Some(100),
+                    false
+                ),
+                (OutPoint::new(payment, 0), 4_000, None, None, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn outputs_confirmed_before_the_birthday_are_skipped() {
+        let wallet = Wallet::new();
+        let (runtime, electrum) = electrum(wallet.recorded());
+
+        let birthday = WalletBirthday {
+            // Past the margin kept for inaccurate block timestamps.
+            timestamp: TIME_101 as u64 + 3 * 60 * 60,
+            height: None,
+        };
+        let outputs = runtime
+            .block_on(electrum.find_outpoints_since(&address(), birthday))
+            .unwrap();
+
+        let outpoints = outputs
+            .iter()
+            .map(|output| output.outpoint)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outpoints,
+            vec![OutPoint::new(wallet.payment.compute_txid(), 0)]
+        );
+    }
+
+    #[test]
+    fn spending_transaction_is_found_in_the_history() {
+        let wallet = Wallet::new();
+        let (runtime, electrum) = electrum(wallet.recorded());
+
+        let funding = wallet.funding.compute_txid();
+
+        let spent = runtime
+            .block_on(electrum.get_output_status(&funding, 0))
+            .unwrap();
+        assert_eq!(spent.spend_txid, Some(wallet.spend.compute_txid()));
+
+        let unspent = runtime
+            .block_on(electrum.get_output_status(&funding, 2))
+            .unwrap();
+        assert_eq!(unspent.spend_txid, None);
+
+        // Unknown transactions are reported as errors by Electrum servers.
+        let unknown = runtime
+            .block_on(electrum.get_output_status(&Txid::all_zeros(), 0))
+            .unwrap();
+        assert_eq!(unknown.spend_txid, None);
+
+        assert!(runtime
+            .block_on(electrum.get_output_status(&funding, 3))
+            .is_err());
+    }
+
+    #[test]
+    fn confirmations_are_counted_from_the_tip() {
+        let wallet = Wallet::new();
+        let (runtime, electrum) = electrum(wallet.recorded());
+
+        let confirmations = |txid: Txid| runtime.block_on(electrum.get_confirmations(&txid));
+
+        assert_eq!(confirmations(wallet.funding.compute_txid()).unwrap(), 6);
+        assert_eq!(confirmations(wallet.payment.compute_txid()).unwrap(), 0);
+        assert_eq!(confirmations(Txid::all_zeros()).unwrap(), 0);
+    }
+
+    #[test]
+    fn median_time_past_is_taken_from_the_headers() {
+        // The timestamps of blocks 0 to 10, which need not be increasing.
+        let times = [50, 0, 10, 20, 30, 40, 100, 60, 70, 90, 80];
+        let headers = times
+            .iter()
+            .map(|time| serialize_hex(&header(TIME_100 + time)))
+            .collect::<String>();
+
+        let (runtime, electrum) = electrum(vec![(
+            "blockchain.block.headers",
+            json!([0, 11]),
+            json!({ "result": { "count": 11, "hex": headers, "max": 2016 } }),
+        )]);
+
+        let median_time_past = runtime.block_on(electrum.get_median_time_past(10)).unwrap();
+
+        assert_eq!(median_time_past, TIME_100 as u64 + 50);
+    }
+
+    #[test]
+    fn rejected_broadcast_is_classified() {
+        let wallet = Wallet::new();
+        let (runtime, electrum) = electrum(vec![
+            (
+                "blockchain.transaction.broadcast",
+                json!([serialize_hex(&wallet.funding)]),
+                json!({ "result": wallet.funding.compute_txid().to_string() }),
+            ),
+            (
+                "blockchain.transaction.broadcast",
+                json!([serialize_hex(&wallet.spend)]),
+                json!({
+                    "error": {
+                        "code": 1,
+                        "message": "the transaction was rejected by network rules.\n\n\
+                                    bad-txns-inputs-missingorspent",
+                    }
+                }),
+            ),
+        ]);
+
+        runtime
+            .block_on(electrum.broadcast(&wallet.funding))
+            .unwrap();
+
+        let error = runtime
+            .block_on(electrum.broadcast(&wallet.spend))
+            .unwrap_err();
+        assert!(matches!(
+            error.broadcast_error(),
+            Some(BroadcastError::MissingOrSpentInputs { .. })
+        ));
+    }
+
+    #[test]
+    fn malformed_responses_are_rejected() {
+        let script_hash = script_hash(&address().script_pubkey());
+
+        let (runtime, electrum) = electrum(vec![
+            (
+                "blockchain.scripthash.get_history",
+                json!([script_hash]),
+                json!({ "result": [{ "tx_hash": Txid::all_zeros().to_string() }] }),
+            ),
+            (
+                "blockchain.scripthash.listunspent",
+                json!([script_hash]),
+                json!({ "result": { "tx_hash": Txid::all_zeros().to_string(), "tx_pos": 0 } }),
+            ),
+            (
+                "blockchain.headers.subscribe",
+                json!([]),
+                json!({ "result": { "height": -1 } }),
+            ),
+        ]);
+
+        let script = address().script_pubkey();
+
+        // No height.
+        assert!(runtime.block_on(electrum.get_history(&script)).is_err());
+        // Not an array.
+        assert!(runtime.block_on(electrum.list_unspent(&script)).is_err());
+        // Negative height.
+        assert!(runtime.block_on(electrum.get_tip_height()).is_err());
+    }
+}
@@ -1,3 +1,8 @@
+use crate::tx_broadcast::BroadcastError;
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::OutPoint;
+use bitcoin::Txid;
+use bitcoin::XOnlyPublicKey;
 use std::error::Error as StdError;
 use std::fmt;
 
@@ -20,12 +25,30 @@ enum Kind {
     AdHoc(AdHocError),
     /// An error related to interactions with the Ark server.
     ArkServer(ArkServerError),
+    /// A round we registered for was aborted by the Ark server.
+    RoundFailed(RoundFailedError),
+    /// We withdrew from a round we registered for, see [`crate::round_handle::RoundHandle`].
+    RoundWithdrawn(RoundWithdrawnError),
+    /// We lost track of a round we were signing after the connection to the Ark server dropped.
+    RoundAbandoned(RoundAbandonedError),
+    /// A phase of a round took longer than allowed by the [`crate::round::RoundConfig`].
+    RoundTimedOut(RoundTimedOutError),
     /// An error from [`ark_core`].
     Core(CoreError),
     /// An error related to coin selection of VTXOs and boarding outputs.
     CoinSelect(CoinSelectError),
     /// An error related to actions within the wallet.
     Wallet(WalletError),
+    /// An address belongs to a different Ark server than the one we are connected to.
+    ServerMismatch(ServerMismatchError),
+    /// The public key of the Ark server changed since we cached its info.
+    ServerKeyChanged(ServerKeyChangedError),
+    /// A transaction was rejected by a [`crate::Blockchain`] backend.
+    Broadcast(BroadcastError),
+    /// A boarding output we were about to register for a round was already spent.
+    BoardingOutputSpent(BoardingOutputSpentError),
+    /// Our secret keys are encrypted at rest and must be unlocked before we can sign.
+    KeysLocked,
 }
 
 #[derive(Debug)]
@@ -36,6 +59,28 @@ struct AdHocError {
 #[derive(Debug)]
 struct ArkServerError {
     source: Source,
+    /// Whether the request may succeed if it is made again.
+    transient: bool,
+}
+
+#[derive(Debug)]
+struct RoundFailedError {
+    source: Source,
+}
+
+#[derive(Debug)]
+struct RoundWithdrawnError {
+    source: Source,
+}
+
+#[derive(Debug)]
+struct RoundAbandonedError {
+    source: Source,
+}
+
+#[derive(Debug)]
+struct RoundTimedOutError {
+    source: Source,
 }
 
 #[derive(Debug)]
@@ -53,6 +98,24 @@ struct WalletError {
     source: Source,
 }
 
+#[derive(Debug)]
+struct ServerMismatchError {
+    ours: XOnlyPublicKey,
+    theirs: XOnlyPublicKey,
+}
+
+#[derive(Debug)]
+struct ServerKeyChangedError {
+    cached: PublicKey,
+    live: PublicKey,
+}
+
+#[derive(Debug)]
+struct BoardingOutputSpentError {
+    outpoint: OutPoint,
+    spend_txid: Txid,
+}
+
 impl Error {
     fn new(kind: Kind) -> Self {
         Self {
@@ -66,9 +129,48 @@ impl Error {
         }))
     }
 
-    pub(crate) fn ark_server(source: impl Into<Source>) -> Self {
+    /// An error returned by a [`crate::NetworkTransport`] because a request to the Ark server
+    /// failed, e.g. because the Ark server rejected it.
+    pub fn ark_server(source: impl Into<Source>) -> Self {
         Error::new(Kind::ArkServer(ArkServerError {
             source: source.into(),
+            transient: false,
+        }))
+    }
+
+    /// Like [`Error::ark_server`], but for a request which may succeed if it is made again, e.g.
+    /// because the connection dropped.
+    ///
+    /// [`crate::NetworkTransport`] implementations must use this for such failures, so that the
+    /// client retries them, see [`Error::is_transient`].
+    pub fn ark_server_transient(source: impl Into<Source>) -> Self {
+        Error::new(Kind::ArkServer(ArkServerError {
+            source: source.into(),
+            transient: true,
+        }))
+    }
+
+    pub(crate) fn round_failed(source: impl Into<Source>) -> Self {
+        Error::new(Kind::RoundFailed(RoundFailedError {
+            source: source.into(),
+        }))
+    }
+
+    pub(crate) fn round_withdrawn(source: impl Into<Source>) -> Self {
+        Error::new(Kind::RoundWithdrawn(RoundWithdrawnError {
+            source: source.into(),
+        }))
+    }
+
+    pub(crate) fn round_abandoned(source: impl Into<Source>) -> Self {
+        Error::new(Kind::RoundAbandoned(RoundAbandonedError {
+            source: source.into(),
+        }))
+    }
+
+    pub(crate) fn round_timed_out(source: impl Into<Source>) -> Self {
+        Error::new(Kind::RoundTimedOut(RoundTimedOutError {
+            source: source.into(),
         }))
     }
 
@@ -83,6 +185,251 @@ impl Error {
             source: source.into(),
         }))
     }
+
+    pub(crate) fn server_mismatch(ours: XOnlyPublicKey, theirs: XOnlyPublicKey) -> Self {
+        Error::new(Kind::ServerMismatch(ServerMismatchError { ours, theirs }))
+    }
+
+    pub(crate) fn server_key_changed(cached: PublicKey, live: PublicKey) -> Self {
+        Error::new(Kind::ServerKeyChanged(ServerKeyChangedError {
+            cached,
+            live,
+        }))
+    }
+
+    pub(crate) fn boarding_output_spent(outpoint: OutPoint, spend_txid: Txid) -> Self {
+        Error::new(Kind::BoardingOutputSpent(BoardingOutputSpentError {
+            outpoint,
+            spend_txid,
+        }))
+    }
+
+    /// Our secret keys are locked, for [`crate::wallet::Persistence`] implementations which
+    /// encrypt them at rest, see [`crate::wallet::Persistence::unlock`].
+    pub fn keys_locked() -> Self {
+        Error::new(Kind::KeysLocked)
+    }
+
+    /// A transaction was rejected by a [`crate::Blockchain`] backend, see
+    /// [`crate::Blockchain::broadcast`].
+    pub fn broadcast(error: BroadcastError) -> Self {
+        Error::new(Kind::Broadcast(error))
+    }
+}
+
+impl Error {
+    /// Whether this error, or any of its causes, is due to the Ark server aborting a round we
+    /// registered for.
+    ///
+    /// Such a failure is not our fault (e.g. another participant misbehaved), so it is safe to
+    /// register again for the next round.
+    pub fn is_round_failed(&self) -> bool {
+        let mut err = self;
+        loop {
+            if let Kind::RoundFailed(_) = err.inner.kind {
+                return true;
+            }
+
+            err = match err.inner.cause.as_ref() {
+                None => return false,
+                Some(err) => err,
+            };
+        }
+    }
+
+    /// Whether this error, or any of its causes, is due to us withdrawing from a round we
+    /// registered for, see [`crate::round_handle::RoundHandle::withdraw`].
+    pub fn is_round_withdrawn(&self) -> bool {
+        let mut err = self;
+        loop {
+            if let Kind::RoundWithdrawn(_) = err.inner.kind {
+                return true;
+            }
+
+            err = match err.inner.cause.as_ref() {
+                None => return false,
+                Some(err) => err,
+            };
+        }
+    }
+
+    /// Whether this error, or any of its causes, is due to us abandoning a round after the
+    /// connection to the Ark server dropped while we were signing it, and we could not resume.
+    ///
+    /// If we had already submitted our signed forfeit transactions, the round may still have
+    /// completed without us hearing about it: check our VTXOs before registering again.
+    pub fn is_round_abandoned(&self) -> bool {
+        let mut err = self;
+        loop {
+            if let Kind::RoundAbandoned(_) = err.inner.kind {
+                return true;
+            }
+
+            err = match err.inner.cause.as_ref() {
+                None => return false,
+                Some(err) => err,
+            };
+        }
+    }
+
+    /// Whether this error, or any of its causes, is due to a round we joined taking longer than
+    /// allowed by the [`crate::round::RoundConfig`] of the client.
+    pub fn is_round_timed_out(&self) -> bool {
+        let mut err = self;
+        loop {
+            if let Kind::RoundTimedOut(_) = err.inner.kind {
+                return true;
+            }
+
+            err = match err.inner.cause.as_ref() {
+                None => return false,
+                Some(err) => err,
+            };
+        }
+    }
+
+    /// Whether this error, or any of its causes, is due to paying an address of a different Ark
+    /// server than the one we are connected to.
+    ///
+    /// VTXOs can only be sent to addresses of the same Ark server. Paying another Ark server
+    /// requires a swap, e.g. via Lightning.
+    pub fn is_server_mismatch(&self) -> bool {
+        let mut err = self;
+        loop {
+            if let Kind::ServerMismatch(_) = err.inner.kind {
+                return true;
+            }
+
+            err = match err.inner.cause.as_ref() {
+                None => return false,
+                Some(err) => err,
+            };
+        }
+    }
+
+    /// Whether this error, or any of its causes, is due to the public key of the Ark server having
+    /// changed since we cached its info.
+    ///
+    /// See [`crate::OfflineClient::with_server_key_reset`].
+    pub fn is_server_key_changed(&self) -> bool {
+        let mut err = self;
+        loop {
+            if let Kind::ServerKeyChanged(_) = err.inner.kind {
+                return true;
+            }
+
+            err = match err.inner.cause.as_ref() {
+                None => return false,
+                Some(err) => err,
+            };
+        }
+    }
+
+    /// Whether this error, or any of its causes, is due to a request to the Ark server which
+    /// failed for a transient reason, e.g. a dropped connection.
+    ///
+    /// Such requests are retried according to the [`crate::retry::RetryPolicy`] of the client.
+    /// Errors built with [`Error::ark_server_transient`] are transient, as are the transient
+    /// errors of the gRPC transport.
+    pub fn is_transient(&self) -> bool {
+        let mut err = self;
+        loop {
+            if let Kind::ArkServer(ArkServerError {
+                transient,
+                ref source,
+            }) = err.inner.kind
+            {
+                if transient {
+                    return true;
+                }
+
+                #[cfg(all(
+                    feature = "grpc",
+                    not(all(target_arch = "wasm32", target_os = "unknown"))
+                ))]
+                if source
+                    .downcast_ref::<ark_grpc::Error>()
+                    .is_some_and(ark_grpc::Error::is_transient)
+                {
+                    return true;
+                }
+
+                #[cfg(not(all(
+                    feature = "grpc",
+                    not(all(target_arch = "wasm32", target_os = "unknown"))
+                )))]
+                let _ = source;
+            }
+
+            err = match err.inner.cause.as_ref() {
+                None => return false,
+                Some(err) => err,
+            };
+        }
+    }
+
+    /// The message of this error and of each of its causes, outermost first.
+    pub fn chain(&self) -> Vec<String> {
+        let mut chain = Vec::new();
+
+        let mut err = self;
+        loop {
+            chain.push(err.inner.kind.to_string());
+
+            err = match err.inner.cause.as_ref() {
+                None => return chain,
+                Some(err) => err,
+            };
+        }
+    }
+
+    /// The reason why a transaction was rejected, if this error, or any of its causes, is due to
+    /// a failed broadcast.
+    pub fn broadcast_error(&self) -> Option<&BroadcastError> {
+        let mut err = self;
+        loop {
+            if let Kind::Broadcast(ref broadcast_error) = err.inner.kind {
+                return Some(broadcast_error);
+            }
+
+            err = err.inner.cause.as_ref()?;
+        }
+    }
+
+    /// Whether this error, or any of its causes, is due to our secret keys being locked, see
+    /// [`crate::Client::unlock_keys`].
+    ///
+    /// Unlock the keys with the passphrase of the user and try again.
+    pub fn is_keys_locked(&self) -> bool {
+        let mut err = self;
+        loop {
+            if let Kind::KeysLocked = err.inner.kind {
+                return true;
+            }
+
+            err = match err.inner.cause.as_ref() {
+                None => return false,
+                Some(err) => err,
+            };
+        }
+    }
+
+    /// The outpoint of the boarding output and the TXID of the transaction which spent it, if
+    /// this error, or any of its causes, is due to a boarding output being spent before we could
+    /// register it for a round.
+    ///
+    /// This happens when the same boarding key is used by several devices, one of which boarded
+    /// the output first.
+    pub fn spent_boarding_output(&self) -> Option<(OutPoint, Txid)> {
+        let mut err = self;
+        loop {
+            if let Kind::BoardingOutputSpent(ref spent) = err.inner.kind {
+                return Some((spent.outpoint, spent.spend_txid));
+            }
+
+            err = err.inner.cause.as_ref()?;
+        }
+    }
 }
 
 impl fmt::Display for Error {
@@ -105,9 +452,18 @@ impl fmt::Display for Kind {
         match *self {
             Kind::AdHoc(ref err) => err.fmt(f),
             Kind::ArkServer(ref err) => err.fmt(f),
+            Kind::RoundFailed(ref err) => err.fmt(f),
+            Kind::RoundWithdrawn(ref err) => err.fmt(f),
+            Kind::RoundAbandoned(ref err) => err.fmt(f),
+            Kind::RoundTimedOut(ref err) => err.fmt(f),
             Kind::Core(ref err) => err.fmt(f),
             Kind::CoinSelect(ref err) => err.fmt(f),
             Kind::Wallet(ref err) => err.fmt(f),
+            Kind::ServerMismatch(ref err) => err.fmt(f),
+            Kind::ServerKeyChanged(ref err) => err.fmt(f),
+            Kind::Broadcast(ref err) => err.fmt(f),
+            Kind::BoardingOutputSpent(ref err) => err.fmt(f),
+            Kind::KeysLocked => write!(f, "secret keys are locked"),
         }
     }
 }
@@ -124,6 +480,30 @@ impl fmt::Display for ArkServerError {
     }
 }
 
+impl fmt::Display for RoundFailedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.source.fmt(f)
+    }
+}
+
+impl fmt::Display for RoundWithdrawnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.source.fmt(f)
+    }
+}
+
+impl fmt::Display for RoundAbandonedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.source.fmt(f)
+    }
+}
+
+impl fmt::Display for RoundTimedOutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.source.fmt(f)
+    }
+}
+
 impl fmt::Display for CoreError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.source.fmt(f)
@@ -142,6 +522,37 @@ impl fmt::Display for WalletError {
     }
 }
 
+impl fmt::Display for ServerMismatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "address belongs to Ark server {}, but we are connected to Ark server {}",
+            self.theirs, self.ours
+        )
+    }
+}
+
+impl fmt::Display for ServerKeyChangedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "public key of Ark server changed from {} to {}",
+            self.cached, self.live
+        )
+    }
+}
+
+impl fmt::Display for BoardingOutputSpentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "boarding output {} was already spent by transaction {}, possibly by another device \
+             using the same key",
+            self.outpoint, self.spend_txid
+        )
+    }
+}
+
 impl From<ark_core::Error> for Error {
     fn from(value: ark_core::Error) -> Self {
         Self::new(Kind::Core(CoreError { source: value }))
@@ -234,6 +645,10 @@ impl<T> ErrorContext for Result<T, Error> {
     }
 }
 
+#[cfg(all(
+    feature = "grpc",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
 impl From<ark_grpc::Error> for Error {
     fn from(value: ark_grpc::Error) -> Self {
         Self::ark_server(value)
@@ -0,0 +1,304 @@
+//! A [`Blockchain`] backed by an [Esplora](https://github.com/Blockstream/esplora) server.
+//!
+//! Only available with the `esplora` feature.
+
+use crate::error::ErrorContext;
+use crate::fee_estimator::FeeEstimator;
+use crate::tx_broadcast::BroadcastError;
+use crate::wallet::WalletBirthday;
+use crate::Blockchain;
+use crate::Error;
+use crate::ExplorerUtxo;
+use crate::SpendStatus;
+use ark_core::exit_delay::median_time_past;
+use ark_core::exit_delay::MEDIAN_TIME_SPAN;
+use bitcoin::Address;
+use bitcoin::Amount;
+use bitcoin::FeeRate;
+use bitcoin::OutPoint;
+use bitcoin::Transaction;
+use bitcoin::Txid;
+use esplora_client::AsyncClient;
+use esplora_client::Builder;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use futures::StreamExt;
+use futures::TryStreamExt;
+
+/// The number of confirmed transactions returned by Esplora per page of address history.
+const CONFIRMED_TXS_PER_PAGE: usize = 25;
+
+/// The default for [`EsploraBlockchain::with_max_concurrent_requests`].
+const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct EsploraBlockchain {
+    client: AsyncClient,
+    /// The maximum number of requests in flight when looking up several addresses or outputs.
+    max_concurrent_requests: usize,
+}
+
+impl EsploraBlockchain {
+    /// Use the Esplora server at `base_url`, e.g. `https://mempool.space/api`.
+    pub fn new(base_url: &str) -> Result<Self, Error> {
+        Self::from_builder(Builder::new(base_url))
+    }
+
+    /// Use the Esplora server configured by `builder`, to set a timeout, a proxy or the number of
+    /// retries.
+    pub fn from_builder(builder: Builder) -> Result<Self, Error> {
+        let client = builder
+            .build_async()
+            .map_err(|e| Error::ad_hoc(format!("failed to build Esplora client: {e}")))?;
+
+        Ok(Self {
+            client,
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+        })
+    }
+
+    /// Make at most `max_concurrent_requests` requests at a time when looking up several
+    /// addresses or outputs.
+    ///
+    /// Defaults to 8. Public Esplora servers rate-limit aggressive clients.
+    pub fn with_max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.max_concurrent_requests = max_concurrent_requests.max(1);
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        self.client.url()
+    }
+
+    /// Like [`Blockchain::find_outpoints`], for several addresses at once.
+    ///
+    /// The outpoints of every address are returned in the order of `addresses`.
+    pub async fn find_outpoints_batch(
+        &self,
+        addresses: &[Address],
+    ) -> Result<Vec<Vec<ExplorerUtxo>>, Error> {
+        futures::stream::iter(addresses)
+            .map(|address| self.find_outpoints_confirmed_since(address, None))
+            .buffered(self.max_concurrent_requests)
+            .try_collect()
+            .await
+    }
+
+    /// The outputs paying `address`. Outputs confirmed before `start_timestamp` may be left out.
+    async fn find_outpoints_confirmed_since(
+        &self,
+        address: &Address,
+        start_timestamp: Option<u64>,
+    ) -> Result<Vec<ExplorerUtxo>, Error> {
+        let script_pubkey = address.script_pubkey();
+
+        // Transactions come newest first: the mempool and a page of confirmed transactions, then
+        // further pages of confirmed transactions.
+        let mut txs = Vec::new();
+        let mut last_seen = None;
+        loop {
+            let page = self
+                .client
+                .get_address_txs(address, last_seen)
+                .await
+                .map_err(esplora_error)
+                .with_context(|| format!("failed to get transactions of address {address}"))?;
+
+            let confirmed = page.iter().filter(|tx| tx.status.confirmed).count();
+            let is_before_start = page.iter().any(|tx| {
+                matches!(
+                    (tx.status.block_time, start_timestamp),
+                    (Some(block_time), Some(start)) if block_time < start
+                )
+            });
+
+            last_seen = page
+                .iter()
+                .rev()
+                .find(|tx| tx.status.confirmed)
+                .map(|tx| tx.txid);
+            txs.extend(page);
+
+            if confirmed < CONFIRMED_TXS_PER_PAGE || is_before_start {
+                break;
+            }
+        }
+
+        let outputs = txs
+            .iter()
+            .flat_map(|tx| {
+                tx.vout
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, vout)| vout.scriptpubkey == script_pubkey)
+                    .map(|(vout, output)| ExplorerUtxo {
+                        outpoint: OutPoint::new(tx.txid, vout as u32),
+                        amount: Amount::from_sat(output.value),
+                        confirmation_blocktime: tx.status.block_time,
+                        confirmation_height: tx.status.block_height,
+                        // Filled in below.
+                        is_spent: false,
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        futures::stream::iter(outputs)
+            .map(|output| async move {
+                let spend_status = self
+                    .get_output_status(&output.outpoint.txid, output.outpoint.vout)
+                    .await?;
+
+                Ok(ExplorerUtxo {
+                    is_spent: spend_status.spend_txid.is_some(),
+                    ..output
+                })
+            })
+            .buffered(self.max_concurrent_requests)
+            .try_collect()
+            .await
+    }
+
+    async fn fetch_fee_rate(&self, target_blocks: u16) -> Result<FeeRate, Error> {
+        let estimates = self
+            .client
+            .get_fee_estimates()
+            .await
+            .map_err(esplora_error)
+            .context("failed to get fee estimates")?;
+
+        let sat_per_vb = esplora_client::convert_fee_rate(target_blocks as usize, estimates)
+            .ok_or_else(|| {
+                Error::ad_hoc(format!(
+                    "no fee estimate for target of {target_blocks} blocks"
+                ))
+            })?;
+
+        // 1 vbyte is 4 weight units, so 1 sat/vB is 250 sat/kwu.
+        Ok(FeeRate::from_sat_per_kwu((sat_per_vb * 250.0).ceil() as u64))
+    }
+}
+
+impl Blockchain for EsploraBlockchain {
+    async fn find_outpoints(&self, address: &Address) -> Result<Vec<ExplorerUtxo>, Error> {
+        self.find_outpoints_confirmed_since(address, None).await
+    }
+
+    async fn find_tx(&self, txid: &Txid) -> Result<Option<Transaction>, Error> {
+        self.client
+            .get_tx(txid)
+            .await
+            .map_err(esplora_error)
+            .with_context(|| format!("failed to get transaction {txid}"))
+    }
+
+    async fn get_output_status(&self, txid: &Txid, vout: u32) -> Result<SpendStatus, Error> {
+        let status = self
+            .client
+            .get_output_status(txid, vout as u64)
+            .await
+            .map_err(esplora_error)
+            .with_context(|| format!("failed to get status of output {txid}:{vout}"))?;
+
+        Ok(SpendStatus {
+            spend_txid: status.and_then(|status| status.txid),
+        })
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> Result<(), Error> {
+        match self.client.broadcast(tx).await {
+            Ok(()) => Ok(()),
+            // Esplora relays the reason given by Bitcoin Core for rejecting the transaction.
+            Err(esplora_client::Error::HttpResponse {
+                status: 400,
+                message,
+            }) => Err(Error::broadcast(BroadcastError::from_reason(message))),
+            Err(e) => Err(esplora_error(e))
+                .with_context(|| format!("failed to broadcast transaction {}", tx.compute_txid())),
+        }
+    }
+
+    async fn find_outpoints_since(
+        &self,
+        address: &Address,
+        birthday: WalletBirthday,
+    ) -> Result<Vec<ExplorerUtxo>, Error> {
+        self.find_outpoints_confirmed_since(address, Some(birthday.scan_start_timestamp()))
+            .await
+    }
+
+    async fn get_confirmations(&self, txid: &Txid) -> Result<u32, Error> {
+        let status = match self.client.get_tx_status(txid).await {
+            Ok(status) => status,
+            Err(esplora_client::Error::HttpResponse { status: 404, .. }) => return Ok(0),
+            Err(e) => {
+                return Err(esplora_error(e))
+                    .with_context(|| format!("failed to get status of transaction {txid}"))
+            }
+        };
+
+        let block_height = match status.block_height {
+            Some(block_height) => block_height,
+            None => return Ok(0),
+        };
+
+        let tip = self
+            .client
+            .get_height()
+            .await
+            .map_err(esplora_error)
+            .context("failed to get blockchain tip")?;
+
+        Ok(tip.saturating_sub(block_height) + 1)
+    }
+
+    async fn get_tip_height(&self) -> Result<u32, Error> {
+        self.client
+            .get_height()
+            .await
+            .map_err(esplora_error)
+            .context("failed to get blockchain tip")
+    }
+    async fn get_median_time_past(&self, height: u32) -> Result<u64, Error> {
+        let lowest_height = height.saturating_sub(MEDIAN_TIME_SPAN as u32 - 1);
+
+        // Esplora returns the summaries of a few blocks at a time, from the requested height down.
+        let mut timestamps = Vec::new();
+        let mut next_height = Some(height);
+        while let Some(from_height) = next_height {
+            let blocks = self
+                .client
+                .get_blocks(Some(from_height))
+                .await
+                .map_err(esplora_error)
+                .with_context(|| format!("failed to get blocks from height {from_height}"))?;
+
+            timestamps.extend(
+                blocks
+                    .iter()
+                    .filter(|block| (lowest_height..=height).contains(&block.time.height))
+                    .map(|block| block.time.timestamp),
+            );
+
+            next_height = blocks
+                .iter()
+                .map(|block| block.time.height)
+                .min()
+                .filter(|min_height| *min_height > lowest_height && *min_height <= from_height)
+                .map(|min_height| min_height - 1);
+        }
+
+        median_time_past(&timestamps)
+            .map(|median_time_past| median_time_past.as_secs())
+            .ok_or_else(|| Error::ad_hoc(format!("no block at height {height}")))
+    }
+}
+
+impl FeeEstimator for EsploraBlockchain {
+    fn estimate_fee_rate(&self, target_blocks: u16) -> BoxFuture<'_, Result<FeeRate, Error>> {
+        self.fetch_fee_rate(target_blocks).boxed()
+    }
+}
+
+fn esplora_error(error: esplora_client::Error) -> Error {
+    Error::ad_hoc(error.to_string())
+}
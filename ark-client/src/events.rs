@@ -0,0 +1,493 @@
+//! Streams of events published by the Ark server, handing each event to the application once.
+//!
+//! The Ark server replays events when a stream is reopened, e.g. after a disconnection, and its
+//! events carry no sequence number. Instead, the ID of every event handed out is persisted (see
+//! [`ClientStore::save_processed_event`]) and replayed events are skipped. Only the IDs of the
+//! [`MAX_PROCESSED_EVENTS`] most recent events are kept: the Ark server only replays recent events,
+//! so older IDs are pruned (see [`ClientStore::prune_processed_events`]).
+//!
+//! If the Ark server cannot stream transactions, [`Client::transaction_events`] polls for our
+//! VTXOs instead and derives the events from how they changed, see [`EventPollingConfig`].
+
+use crate::reservation::Reservations;
+use crate::transport::box_stream;
+use crate::transport::MaybeSend;
+use crate::transport::MaybeSync;
+use crate::transport::NetworkStream;
+use crate::transport::NetworkTransport;
+use crate::utils::sleep;
+use crate::wallet::BoardingWallet;
+use crate::wallet::ClientStore;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use ark_core::server::RedeemTransaction;
+use ark_core::server::RoundStreamEvent;
+use ark_core::server::RoundTransaction;
+use ark_core::server::TransactionEvent;
+use ark_core::server::VtxoOutPoint;
+use ark_core::ArkAddress;
+use bitcoin::Txid;
+use futures::future;
+use futures::stream;
+use futures::StreamExt;
+use futures::TryStreamExt;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often [`Client::transaction_events`] polls for our VTXOs when the Ark server cannot stream
+/// transactions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventPollingConfig {
+    /// How long to wait between two polls while an operation is pending, i.e. while VTXOs are
+    /// reserved, some of our VTXOs are not settled in a round yet or the last poll found new
+    /// transactions.
+    pub active_interval: Duration,
+    /// How long to wait between two polls otherwise.
+    pub idle_interval: Duration,
+}
+
+impl Default for EventPollingConfig {
+    fn default() -> Self {
+        Self {
+            active_interval: Duration::from_secs(2),
+            idle_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// A step of a round of the Ark server, see [`Client::round_events`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RoundEvent {
+    /// The round started: its participants must sign the VTXO tree of the round transaction.
+    Started { round_id: String, round_txid: Txid },
+    /// The participants submitted their nonces, and are now signing the VTXO tree.
+    Signing { round_id: String },
+    /// The VTXO tree is signed, and the participants must submit their forfeit transactions.
+    Finalization { round_id: String, round_txid: Txid },
+    /// The round transaction was broadcast.
+    Finalized { round_id: String, round_txid: Txid },
+    /// The round failed, e.g. because a participant did not sign in time.
+    Failed { round_id: String, reason: String },
+}
+
+impl RoundEvent {
+    pub fn round_id(&self) -> &str {
+        match self {
+            RoundEvent::Started { round_id, .. }
+            | RoundEvent::Signing { round_id }
+            | RoundEvent::Finalization { round_id, .. }
+            | RoundEvent::Finalized { round_id, .. }
+            | RoundEvent::Failed { round_id, .. } => round_id,
+        }
+    }
+
+    fn id(&self) -> String {
+        let step = match self {
+            RoundEvent::Started { .. } => "started",
+            RoundEvent::Signing { .. } => "signing",
+            RoundEvent::Finalization { .. } => "finalization",
+            RoundEvent::Finalized { .. } => "finalized",
+            RoundEvent::Failed { .. } => "failed",
+        };
+
+        format!("round:{}:{step}", self.round_id())
+    }
+}
+
+impl From<RoundStreamEvent> for RoundEvent {
+    fn from(value: RoundStreamEvent) -> Self {
+        match value {
+            RoundStreamEvent::RoundSigning(e) => RoundEvent::Started {
+                round_id: e.id,
+                round_txid: e.unsigned_round_tx.unsigned_tx.compute_txid(),
+            },
+            RoundStreamEvent::RoundSigningNoncesGenerated(e) => {
+                RoundEvent::Signing { round_id: e.id }
+            }
+            RoundStreamEvent::RoundFinalization(e) => RoundEvent::Finalization {
+                round_id: e.id,
+                round_txid: e.round_tx.unsigned_tx.compute_txid(),
+            },
+            RoundStreamEvent::RoundFinalized(e) => RoundEvent::Finalized {
+                round_id: e.id,
+                round_txid: e.round_txid,
+            },
+            RoundStreamEvent::RoundFailed(e) => RoundEvent::Failed {
+                round_id: e.id,
+                reason: e.reason,
+            },
+        }
+    }
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet + MaybeSend + MaybeSync + 'static,
+    T: NetworkTransport,
+{
+    /// The round and out-of-round transactions published by the Ark server from now on.
+    ///
+    /// Transactions already yielded by an earlier stream, including one opened before a restart,
+    /// are skipped. A transaction counts as processed once it is yielded, so it is not yielded
+    /// again even if the application fails to handle it.
+    ///
+    /// The stream ends for good after the first error: open a new one to resume.
+    ///
+    /// If the Ark server cannot stream transactions, our VTXOs are polled for instead, as
+    /// configured with [`crate::OfflineClient::with_event_polling`]. Polled events only involve
+    /// our own VTXOs, and never list claimed boarding outputs.
+    pub async fn transaction_events(
+        &self,
+    ) -> Result<NetworkStream<'static, Result<TransactionEvent, Error>>, Error> {
+        let stream = match self.network_client().get_transaction_stream().await? {
+            Some(stream) => stream,
+            None => {
+                tracing::info!("Ark server cannot stream transactions, polling for them instead");
+
+                self.poll_transaction_events()
+            }
+        };
+
+        deduplicate(stream, self.inner.wallet.clone(), transaction_event_id)
+    }
+
+    /// The progress of the rounds of the Ark server from now on, e.g. to show which step the
+    /// round we joined is at, or to react to it failing.
+    ///
+    /// Events are deduplicated like [`Client::transaction_events`]. Joining a round, e.g. with
+    /// [`Client::board`], uses a stream of its own, so it is not affected by this one.
+    ///
+    /// Round events cannot be polled for, so this fails if the Ark server cannot stream them.
+    pub async fn round_events(
+        &self,
+    ) -> Result<NetworkStream<'static, Result<RoundEvent, Error>>, Error> {
+        let stream = self
+            .network_client()
+            .get_event_stream()
+            .await?
+            .map_ok(RoundEvent::from);
+        let stream = box_stream(stream);
+
+        deduplicate(stream, self.inner.wallet.clone(), RoundEvent::id)
+    }
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// The transactions involving our VTXOs from now on, found by polling for our VTXOs.
+    fn poll_transaction_events(&self) -> NetworkStream<'static, Result<TransactionEvent, Error>> {
+        let poller = TransactionPoller {
+            network_client: self.network_client().clone(),
+            addresses: self
+                .get_offchain_addresses()
+                .into_iter()
+                .map(|(address, _)| address)
+                .collect(),
+            reservations: self.reservations.clone(),
+            config: self.inner.event_polling,
+            seen: None,
+            round_txids: HashMap::new(),
+            is_active: false,
+        };
+
+        let stream = stream::unfold(poller, |mut poller| async move {
+            let events = loop {
+                // The first poll only records the transactions which already exist.
+                if poller.seen.is_some() {
+                    let interval = if poller.is_active || poller.reservations.is_active() {
+                        poller.config.active_interval
+                    } else {
+                        poller.config.idle_interval
+                    };
+
+                    sleep(interval).await;
+                }
+
+                match poller.poll().await {
+                    Ok(events) if events.is_empty() => {}
+                    Ok(events) => break events.into_iter().map(Ok).collect::<Vec<_>>(),
+                    Err(e) => break vec![Err(e)],
+                }
+            };
+
+            Some((stream::iter(events), poller))
+        })
+        .flatten();
+
+        box_stream(stream)
+    }
+}
+
+/// Derives [`TransactionEvent`]s from the changes to our VTXOs between two polls.
+struct TransactionPoller<T> {
+    network_client: T,
+    addresses: Vec<ArkAddress>,
+    reservations: Arc<Reservations>,
+    config: EventPollingConfig,
+    /// The transactions found so far, or `None` before the first poll.
+    seen: Option<HashSet<Txid>>,
+    /// Whether each transaction spending our VTXOs without creating any is a round transaction.
+    round_txids: HashMap<Txid, bool>,
+    /// Whether an operation was pending at the last poll.
+    is_active: bool,
+}
+
+impl<T> TransactionPoller<T>
+where
+    T: NetworkTransport,
+{
+    /// The transactions which appeared since the last poll, oldest first.
+    ///
+    /// The first poll only records the transactions which already exist.
+    async fn poll(&mut self) -> Result<Vec<TransactionEvent>, Error> {
+        let mut vtxos = Vec::new();
+        for address in self.addresses.iter() {
+            let list = self.network_client.list_vtxos(address).await?;
+
+            vtxos.extend(list.spendable);
+            vtxos.extend(list.spent);
+        }
+
+        // The transactions which created or spent our VTXOs, by TXID.
+        let mut txs = HashMap::<Txid, PolledTransaction>::new();
+        for vtxo in vtxos.iter() {
+            let (txid, is_round) = if vtxo.is_pending || vtxo.redeem_tx.is_some() {
+                (vtxo.outpoint.txid, false)
+            } else {
+                (vtxo.round_txid, true)
+            };
+
+            let tx = txs.entry(txid).or_default();
+            tx.is_round = Some(is_round);
+            tx.created_at = tx.created_at.max(vtxo.created_at);
+            tx.spendable_vtxos.push(vtxo.clone());
+        }
+        for vtxo in vtxos.iter() {
+            if let Some(spent_by) = vtxo.spent_by {
+                let tx = txs.entry(spent_by).or_default();
+                tx.created_at = tx.created_at.max(vtxo.created_at);
+                tx.spent_vtxos.push(vtxo.clone());
+            }
+        }
+
+        let seen = match self.seen.as_mut() {
+            Some(seen) => seen,
+            None => {
+                self.seen = Some(txs.into_keys().collect());
+                return Ok(Vec::new());
+            }
+        };
+
+        let mut new_txs = txs
+            .into_iter()
+            .filter(|(txid, _)| !seen.contains(txid))
+            .collect::<Vec<_>>();
+        new_txs.sort_by_key(|(_, tx)| tx.created_at);
+
+        let mut events = Vec::with_capacity(new_txs.len());
+        for (txid, tx) in new_txs {
+            // A transaction which spent our VTXOs without creating any for us, e.g. a payment of
+            // all our VTXOs or an off-board, may be a round transaction or a redeem transaction.
+            let is_round = match tx.is_round {
+                Some(is_round) => is_round,
+                None => match self.round_txids.get(&txid) {
+                    Some(is_round) => *is_round,
+                    None => {
+                        let is_round = self
+                            .network_client
+                            .get_round(txid.to_string())
+                            .await?
+                            .is_some();
+                        self.round_txids.insert(txid, is_round);
+
+                        is_round
+                    }
+                },
+            };
+
+            seen.insert(txid);
+
+            events.push(if is_round {
+                TransactionEvent::Round(RoundTransaction {
+                    txid,
+                    spent_vtxos: tx.spent_vtxos,
+                    spendable_vtxos: tx.spendable_vtxos,
+                    claimed_boarding_utxos: Vec::new(),
+                })
+            } else {
+                TransactionEvent::Redeem(RedeemTransaction {
+                    txid,
+                    spent_vtxos: tx.spent_vtxos,
+                    spendable_vtxos: tx.spendable_vtxos,
+                })
+            });
+        }
+
+        self.is_active = !events.is_empty() || vtxos.iter().any(|vtxo| vtxo.is_pending);
+
+        Ok(events)
+    }
+}
+
+#[derive(Default)]
+struct PolledTransaction {
+    /// Whether the transaction is a round transaction, if known from the VTXOs it created.
+    is_round: Option<bool>,
+    created_at: i64,
+    spent_vtxos: Vec<VtxoOutPoint>,
+    spendable_vtxos: Vec<VtxoOutPoint>,
+}
+
+/// How many processed event IDs are remembered to skip replayed events.
+pub const MAX_PROCESSED_EVENTS: usize = 10_000;
+
+/// How many IDs beyond [`MAX_PROCESSED_EVENTS`] are processed before the oldest ones are pruned,
+/// so that persistence is not pruned after every event.
+const PRUNE_PROCESSED_EVENTS_EVERY: usize = 1_000;
+
+/// The IDs of the most recently processed events, oldest first.
+struct ProcessedEvents {
+    ids: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl ProcessedEvents {
+    fn new(ids: Vec<String>) -> Self {
+        let mut processed = Self {
+            ids: HashSet::new(),
+            order: VecDeque::new(),
+        };
+
+        for id in ids {
+            processed.insert(id);
+        }
+
+        processed
+    }
+
+    fn contains(&self, id: &str) -> bool {
+        self.ids.contains(id)
+    }
+
+    fn insert(&mut self, id: String) {
+        if self.ids.insert(id.clone()) {
+            self.order.push_back(id);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Forget all but the `keep` most recently processed events.
+    fn prune(&mut self, keep: usize) {
+        while self.order.len() > keep {
+            if let Some(id) = self.order.pop_front() {
+                self.ids.remove(&id);
+            }
+        }
+    }
+}
+
+/// Forget the oldest processed events if there are too many, both in `processed` and in `wallet`.
+fn prune_processed_events<W>(processed: &mut ProcessedEvents, wallet: &W) -> Result<(), Error>
+where
+    W: BoardingWallet,
+{
+    if processed.len() <= MAX_PROCESSED_EVENTS + PRUNE_PROCESSED_EVENTS_EVERY {
+        return Ok(());
+    }
+
+    wallet
+        .store()
+        .prune_processed_events(MAX_PROCESSED_EVENTS)?;
+    processed.prune(MAX_PROCESSED_EVENTS);
+
+    Ok(())
+}
+
+/// Skip the events of `stream` whose ID, as given by `event_id`, was already processed.
+pub(crate) fn deduplicate<E, W>(
+    stream: NetworkStream<'static, Result<E, Error>>,
+    wallet: Arc<W>,
+    event_id: fn(&E) -> String,
+) -> Result<NetworkStream<'static, Result<E, Error>>, Error>
+where
+    E: MaybeSend + 'static,
+    W: BoardingWallet + MaybeSend + MaybeSync + 'static,
+{
+    let mut processed = ProcessedEvents::new(wallet.store().load_processed_events()?);
+    prune_processed_events(&mut processed, wallet.as_ref())?;
+
+    let stream = stream
+        // The underlying streams keep yielding the same error once disconnected.
+        .scan(false, |failed, event| {
+            if *failed {
+                return future::ready(None);
+            }
+
+            *failed = event.is_err();
+
+            future::ready(Some(event))
+        })
+        .try_filter_map(move |event| {
+            let id = event_id(&event);
+
+            let event = if processed.contains(&id) {
+                tracing::debug!(event_id = id, "Skipping replayed server event");
+
+                Ok(None)
+            } else {
+                wallet
+                    .store()
+                    .save_processed_event(id.clone())
+                    .and_then(|()| {
+                        processed.insert(id);
+
+                        prune_processed_events(&mut processed, wallet.as_ref())
+                    })
+                    .map(|()| Some(event))
+            };
+
+            future::ready(event)
+        });
+
+    Ok(box_stream(stream))
+}
+
+fn transaction_event_id(event: &TransactionEvent) -> String {
+    match event {
+        TransactionEvent::Round(round) => format!("round_tx:{}", round.txid),
+        TransactionEvent::Redeem(redeem) => format!("redeem_tx:{}", redeem.txid),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pruning_forgets_the_oldest_processed_events() {
+        let mut processed = ProcessedEvents::new(vec!["a".into(), "b".into(), "a".into()]);
+        processed.insert("c".into());
+
+        assert_eq!(processed.len(), 3);
+
+        processed.prune(2);
+
+        assert!(!processed.contains("a"));
+        assert!(processed.contains("b"));
+        assert!(processed.contains("c"));
+        assert_eq!(processed.len(), 2);
+    }
+}
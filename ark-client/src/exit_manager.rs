@@ -0,0 +1,312 @@
+//! Drive the unilateral exit of VTXOs from start to finish, surviving restarts.
+//!
+//! See [`ExitManager`].
+
+use crate::error::ErrorContext;
+use crate::transport::NetworkTransport;
+use crate::utils::sleep;
+use crate::wallet::BoardingWallet;
+use crate::wallet::ClientStore;
+use crate::wallet::OnchainWallet;
+use crate::wallet::VtxoExit;
+use crate::wallet::VtxoExitStatus;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use crate::ExplorerUtxo;
+use ark_core::default_vtxo::DefaultVtxo;
+use ark_core::exit_delay::BlockTime;
+use ark_core::unilateral_exit;
+use ark_core::unilateral_exit::batched_unilateral_exit_transaction_sighashes;
+use ark_core::unilateral_exit::create_batched_unilateral_exit_transaction_with;
+use ark_core::unilateral_exit::estimate_unilateral_exit_tx_fee;
+use bitcoin::Address;
+use bitcoin::OutPoint;
+use bitcoin::Txid;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// Takes VTXOs all the way out of the Ark, without the collaboration of the Ark server.
+///
+/// The exit of every VTXO goes through the [`VtxoExitStatus`]es in order:
+///
+/// 1. The transactions of the VTXO tree leading to the VTXO are broadcast, parents first, until the
+///    VTXO itself is confirmed (see [`Client::commit_vtxos_on_chain`]).
+/// 2. We wait for the exit delay of the VTXO to pass.
+/// 3. The VTXO is swept to the destination given when starting the exit.
+/// 4. We wait for the sweeping transaction to confirm.
+///
+/// Every step is persisted (see [`ClientStore::save_vtxo_exit`]), so a new `ExitManager` picks
+/// up where the previous one left off, e.g. after a restart.
+pub struct ExitManager<'a, B, W, T> {
+    client: &'a Client<B, W, T>,
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    pub fn exit_manager(&self) -> ExitManager<'_, B, W, T> {
+        ExitManager { client: self }
+    }
+}
+
+impl<B, W, T> ExitManager<'_, B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Start the exit of the VTXOs in `vtxos`, sweeping each of them to `destination`.
+    ///
+    /// Nothing is published until [`ExitManager::advance`] is called.
+    pub async fn start(&self, vtxos: &[OutPoint], destination: Address) -> Result<(), Error> {
+        self.client
+            .inner
+            .address_type_policy
+            .check_destination(&destination)?;
+
+        let exiting = self
+            .exits()?
+            .into_iter()
+            .map(|exit| exit.vtxo_outpoint)
+            .collect::<HashSet<_>>();
+
+        let spendable = self
+            .client
+            .available_vtxos()
+            .await
+            .context("failed to get spendable VTXOs")?
+            .into_iter()
+            .flat_map(|(vtxos, _)| vtxos.into_iter().map(|vtxo| vtxo.outpoint))
+            .collect::<HashSet<_>>();
+
+        for outpoint in vtxos.iter() {
+            if exiting.contains(outpoint) {
+                return Err(Error::ad_hoc(format!(
+                    "exit of VTXO {outpoint} already started"
+                )));
+            }
+
+            if !spendable.contains(outpoint) {
+                return Err(Error::ad_hoc(format!(
+                    "VTXO {outpoint} is not one of our spendable VTXOs"
+                )));
+            }
+        }
+
+        for outpoint in vtxos.iter() {
+            self.client.inner.wallet.store().save_vtxo_exit(VtxoExit {
+                vtxo_outpoint: *outpoint,
+                destination: destination.clone(),
+                status: VtxoExitStatus::Committing,
+            })?;
+
+            tracing::info!(vtxo_outpoint = %outpoint, "Started unilateral exit of VTXO");
+        }
+
+        Ok(())
+    }
+
+    /// Every exit started with [`ExitManager::start`], including completed ones.
+    pub fn exits(&self) -> Result<Vec<VtxoExit>, Error> {
+        self.client.inner.wallet.store().load_vtxo_exits()
+    }
+
+    /// Take every exit as far as it can go right now, returning where they stand.
+    pub async fn advance(&self) -> Result<Vec<VtxoExit>, Error> {
+        let exits = self.exits()?;
+
+        let committing = exits
+            .iter()
+            .filter(|exit| exit.status == VtxoExitStatus::Committing)
+            .map(|exit| exit.vtxo_outpoint)
+            .collect::<HashSet<_>>();
+        if !committing.is_empty() {
+            self.client
+                .commit_selected_vtxos_on_chain(Some(&committing))
+                .await
+                .context("failed to publish VTXO tree transactions")?;
+        }
+
+        for exit in exits.into_iter() {
+            let status = self.next_status(&exit).await.with_context(|| {
+                format!("failed to advance exit of VTXO {}", exit.vtxo_outpoint)
+            })?;
+
+            if status != exit.status {
+                tracing::info!(
+                    vtxo_outpoint = %exit.vtxo_outpoint,
+                    ?status,
+                    "Unilateral exit of VTXO progressed"
+                );
+
+                self.client
+                    .inner
+                    .wallet
+                    .store()
+                    .save_vtxo_exit(VtxoExit { status, ..exit })?;
+            }
+        }
+
+        self.exits()
+    }
+
+    /// Call [`ExitManager::advance`] every `poll_interval` until every exit is complete.
+    ///
+    /// Failures are logged and retried at the next poll.
+    pub async fn run(&self, poll_interval: Duration) -> Vec<VtxoExit> {
+        loop {
+            match self.advance().await {
+                Ok(exits)
+                    if exits
+                        .iter()
+                        .all(|exit| matches!(exit.status, VtxoExitStatus::Completed { .. })) =>
+                {
+                    return exits;
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Failed to advance unilateral exits: {e}"),
+            }
+
+            sleep(poll_interval).await;
+        }
+    }
+
+    async fn next_status(&self, exit: &VtxoExit) -> Result<VtxoExitStatus, Error> {
+        let blockchain = self.client.blockchain();
+
+        match exit.status {
+            VtxoExitStatus::Committing | VtxoExitStatus::WaitingForExitDelay => {
+                let (vtxo, utxo, confirmed) =
+                    match self.find_confirmed_vtxo(exit.vtxo_outpoint).await? {
+                        Some(published) => published,
+                        None => return Ok(VtxoExitStatus::Committing),
+                    };
+
+                let tip = self.client.chain_tip().await?;
+                if !vtxo.can_be_claimed_unilaterally_by_owner(confirmed, tip) {
+                    return Ok(VtxoExitStatus::WaitingForExitDelay);
+                }
+
+                let txid = self.sweep(vtxo, &utxo, &exit.destination).await?;
+
+                Ok(VtxoExitStatus::Swept { txid })
+            }
+            VtxoExitStatus::Swept { txid } => {
+                // The sweeping transaction may have been dropped, in which case we sweep again.
+                if blockchain.find_tx(&txid).await?.is_none() {
+                    return Ok(VtxoExitStatus::WaitingForExitDelay);
+                }
+
+                if blockchain.get_confirmations(&txid).await? > 0 {
+                    Ok(VtxoExitStatus::Completed { txid })
+                } else {
+                    Ok(exit.status)
+                }
+            }
+            VtxoExitStatus::Completed { .. } => Ok(exit.status),
+        }
+    }
+
+    /// The VTXO at `outpoint` and its output, if the VTXO is confirmed on chain and not spent.
+    async fn find_confirmed_vtxo(
+        &self,
+        outpoint: OutPoint,
+    ) -> Result<Option<(DefaultVtxo, ExplorerUtxo, BlockTime)>, Error> {
+        for (_, vtxo) in self.client.get_offchain_addresses() {
+            let utxos = self.client.find_our_outpoints(vtxo.address()).await?;
+
+            let utxo = match utxos.into_iter().find(|utxo| utxo.outpoint == outpoint) {
+                Some(utxo) => utxo,
+                None => continue,
+            };
+
+            if utxo.is_spent {
+                return Ok(None);
+            }
+
+            return match self.client.confirmed_in(&utxo).await? {
+                Some(confirmed) => Ok(Some((vtxo, utxo, confirmed))),
+                None => Ok(None),
+            };
+        }
+
+        Ok(None)
+    }
+
+    /// Spend the whole VTXO in `utxo` to `destination`, returning the TXID of the broadcast
+    /// transaction.
+    async fn sweep(
+        &self,
+        vtxo: DefaultVtxo,
+        utxo: &ExplorerUtxo,
+        destination: &Address,
+    ) -> Result<Txid, Error> {
+        let fee_rate = self.client.onchain_fee_rate().await;
+        let vtxo_inputs = [unilateral_exit::VtxoInput::new(
+            vtxo,
+            utxo.amount,
+            utxo.outpoint,
+        )];
+
+        // The transaction is built with room for a change output. Since we leave no change, that
+        // output ends up below dust and is left out.
+        let output_scripts = [destination.script_pubkey(), destination.script_pubkey()];
+        let fee = estimate_unilateral_exit_tx_fee(fee_rate, &[], &vtxo_inputs, &output_scripts)
+            .map_err(Error::from)?;
+
+        let amount = utxo
+            .amount
+            .checked_sub(fee)
+            .filter(|amount| *amount >= destination.script_pubkey().minimal_non_dust())
+            .ok_or_else(|| {
+                Error::coin_select(format!(
+                    "VTXO {} worth {} cannot pay the fee of {fee} to sweep it",
+                    utxo.outpoint, utxo.amount
+                ))
+            })?;
+
+        let outputs = [(destination.clone(), amount)];
+
+        let sighashes = batched_unilateral_exit_transaction_sighashes(
+            &outputs,
+            destination.clone(),
+            &[],
+            &vtxo_inputs,
+            fee_rate,
+        )
+        .map_err(Error::from)?;
+        let sigs = self
+            .client
+            .sign_sighashes(sighashes)
+            .await
+            .context("failed to sign transaction sweeping exited VTXO")?;
+
+        let tx = create_batched_unilateral_exit_transaction_with(
+            sigs.sign_for_pk_fn(),
+            &outputs,
+            destination.clone(),
+            &[],
+            &vtxo_inputs,
+            fee_rate,
+        )
+        .map_err(Error::from)?;
+
+        let txid = tx.compute_txid();
+
+        tracing::info!(
+            %txid,
+            vtxo_outpoint = %utxo.outpoint,
+            destination = %self.client.inner.privacy.address(destination),
+            amount = %self.client.inner.privacy.amount(amount),
+            "Broadcasting transaction sweeping exited VTXO"
+        );
+
+        self.client.broadcast_tx(&tx).await?;
+
+        Ok(txid)
+    }
+}
@@ -0,0 +1,252 @@
+use crate::error::ErrorContext;
+use crate::fee_estimator::FeeEstimator;
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::ClientStore;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use ark_core::unilateral_exit::find_anchor_output;
+use bitcoin::Amount;
+use bitcoin::FeeRate;
+use bitcoin::Transaction;
+use bitcoin::Txid;
+
+/// The expected size of a CPFP child transaction produced by the [`OnchainWallet`], in vbytes.
+///
+/// We assume a single P2WPKH input and a single P2WPKH output, which is what a BIP84 wallet will
+/// produce in the common case. If the child ends up being bigger, the effective package fee rate
+/// will be slightly lower than requested.
+const CPFP_CHILD_VSIZE: u64 = 110;
+
+/// The expected size of a CPFP child transaction spending a pay-to-anchor output, in vbytes.
+///
+/// Besides the anchor, we assume a single P2WPKH input from the [`OnchainWallet`] to pay the fee
+/// and a single P2WPKH change output.
+const ANCHOR_CPFP_CHILD_VSIZE: u64 = 150;
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Accelerate the confirmation of a transaction funding one of our boarding outputs, using
+    /// child-pays-for-parent (CPFP).
+    ///
+    /// The boarding output itself cannot be spent by us before its exit delay has passed, so the
+    /// child transaction spends any _other_ output of the funding transaction which belongs to
+    /// our [`OnchainWallet`] (typically the change output, if the boarding output was funded from
+    /// the same wallet).
+    ///
+    /// Returns `None` if the funding transaction is already confirmed, or if the fee rate it pays
+    /// already meets `target_fee_rate`. Otherwise, returns the TXID of the broadcast child
+    /// transaction.
+    pub async fn bump_boarding_funding_tx(
+        &self,
+        funding_txid: Txid,
+        target_fee_rate: FeeRate,
+    ) -> Result<Option<Txid>, Error> {
+        let funding_tx = self
+            .blockchain()
+            .find_tx(&funding_txid)
+            .await?
+            .ok_or_else(|| {
+                Error::ad_hoc(format!("could not find funding transaction {funding_txid}"))
+            })?;
+
+        let boarding_outputs = self.inner.wallet.get_boarding_outputs()?;
+        let boarding_output = boarding_outputs
+            .iter()
+            .find(|b| {
+                funding_tx
+                    .output
+                    .iter()
+                    .any(|o| o.script_pubkey == b.script_pubkey())
+            })
+            .ok_or_else(|| {
+                Error::ad_hoc(format!(
+                    "transaction {funding_txid} does not fund any of our boarding outputs"
+                ))
+            })?;
+
+        let is_confirmed = self
+            .find_our_outpoints(boarding_output.address())
+            .await?
+            .iter()
+            .any(|o| o.outpoint.txid == funding_txid && o.confirmation_blocktime.is_some());
+
+        if is_confirmed {
+            tracing::debug!(%funding_txid, "Boarding funding transaction already confirmed");
+            return Ok(None);
+        }
+
+        self.bump_via_cpfp(&funding_tx, target_fee_rate)
+            .await
+            .context("failed to bump boarding funding transaction")
+    }
+
+    /// Accelerate the confirmation of a transaction published by
+    /// [`Client::commit_vtxos_on_chain`], using child-pays-for-parent (CPFP).
+    ///
+    /// The transactions of the VTXO tree are pre-signed with a fixed fee, which may not be enough
+    /// when fees rise. The child transaction spends the anchor output of the transaction and is
+    /// funded by our [`OnchainWallet`], paying enough for both transactions to confirm within
+    /// `target_blocks` blocks according to `fee_estimator`.
+    ///
+    /// Returns `None` if the transaction is already confirmed, or if the fee rate it pays already
+    /// meets the estimate. Otherwise, returns the TXID of the broadcast child transaction.
+    pub async fn bump_exit_tx<E>(
+        &self,
+        txid: Txid,
+        fee_estimator: &E,
+        target_blocks: u16,
+    ) -> Result<Option<Txid>, Error>
+    where
+        E: FeeEstimator + ?Sized,
+    {
+        let exit_tx = self
+            .inner
+            .wallet
+            .store()
+            .load_exit_txs()?
+            .into_iter()
+            .find(|exit_tx| exit_tx.tx.compute_txid() == txid)
+            .ok_or_else(|| Error::ad_hoc(format!("{txid} is not a unilateral exit transaction")))?;
+
+        if self.blockchain().get_confirmations(&txid).await? > 0 {
+            tracing::debug!(%txid, "Exit transaction already confirmed");
+            return Ok(None);
+        }
+
+        if self.blockchain().find_tx(&txid).await?.is_none() {
+            return Err(Error::ad_hoc(format!(
+                "exit transaction {txid} must be broadcast before it can be bumped"
+            )));
+        }
+
+        let target_fee_rate = fee_estimator
+            .estimate_fee_rate(target_blocks)
+            .await
+            .context("failed to estimate fee rate")?;
+
+        self.bump_via_cpfp(&exit_tx.tx, target_fee_rate)
+            .await
+            .with_context(|| format!("failed to bump exit transaction {txid}"))
+    }
+
+    /// Broadcast a child of `parent` so that they pay `target_fee_rate` together.
+    ///
+    /// The child spends the anchor output of `parent` if it has one, or else every output of
+    /// `parent` which belongs to our [`OnchainWallet`].
+    async fn bump_via_cpfp(
+        &self,
+        parent: &Transaction,
+        target_fee_rate: FeeRate,
+    ) -> Result<Option<Txid>, Error> {
+        let parent_txid = parent.compute_txid();
+
+        let anchor_vout = find_anchor_output(parent);
+        let child_vsize = match anchor_vout {
+            Some(_) => ANCHOR_CPFP_CHILD_VSIZE,
+            None => CPFP_CHILD_VSIZE,
+        };
+
+        let parent_fee = self
+            .transaction_fee(parent)
+            .await
+            .context("failed to compute fee of parent transaction")?;
+        let parent_vsize = parent.vsize() as u64;
+
+        let target_package_fee = target_fee_rate
+            .fee_vb(parent_vsize + child_vsize)
+            .ok_or_else(|| Error::ad_hoc("target package fee overflow"))?;
+
+        let target_parent_fee = target_fee_rate
+            .fee_vb(parent_vsize)
+            .ok_or_else(|| Error::ad_hoc("target parent fee overflow"))?;
+
+        if parent_fee >= target_parent_fee {
+            tracing::debug!(
+                %parent_txid,
+                %parent_fee,
+                %target_fee_rate,
+                "Transaction already pays enough fees"
+            );
+            return Ok(None);
+        }
+
+        let child_fee = target_package_fee - parent_fee;
+        let child_fee_rate = FeeRate::from_sat_per_vb(child_fee.to_sat().div_ceil(child_vsize))
+            .ok_or_else(|| Error::ad_hoc("child fee rate overflow"))?;
+
+        tracing::info!(
+            %parent_txid,
+            %parent_fee,
+            %target_fee_rate,
+            %child_fee_rate,
+            ?anchor_vout,
+            "Bumping transaction via CPFP"
+        );
+
+        let mut psbt = match anchor_vout {
+            Some(anchor_vout) => {
+                self.inner
+                    .wallet
+                    .prepare_anchor_cpfp(parent, anchor_vout, child_fee_rate)
+                    .await
+            }
+            None => self.inner.wallet.prepare_cpfp(parent, child_fee_rate).await,
+        }
+        .context("failed to prepare CPFP transaction")?;
+
+        let finalized = self.inner.wallet.sign(&mut psbt).await?;
+        if !finalized {
+            return Err(Error::wallet("failed to finalize CPFP transaction"));
+        }
+
+        let child_tx = psbt.extract_tx().map_err(Error::wallet)?;
+        let child_txid = child_tx.compute_txid();
+
+        self.broadcast_tx(&child_tx)
+            .await
+            .with_context(|| format!("failed to broadcast CPFP transaction {child_txid}"))?;
+
+        Ok(Some(child_txid))
+    }
+
+    /// The fee paid by `tx`, computed by looking up all its previous outputs.
+    async fn transaction_fee(&self, tx: &Transaction) -> Result<Amount, Error> {
+        let mut input_amount = Amount::ZERO;
+        for input in tx.input.iter() {
+            let previous_output = input.previous_output;
+
+            let previous_tx = self
+                .blockchain()
+                .find_tx(&previous_output.txid)
+                .await?
+                .ok_or_else(|| {
+                    Error::ad_hoc(format!(
+                        "could not find previous transaction {}",
+                        previous_output.txid
+                    ))
+                })?;
+
+            let txout = previous_tx
+                .output
+                .get(previous_output.vout as usize)
+                .ok_or_else(|| {
+                    Error::ad_hoc(format!("missing previous output {previous_output}"))
+                })?;
+
+            input_amount += txout.value;
+        }
+
+        let output_amount: Amount = tx.output.iter().map(|o| o.value).sum();
+
+        input_amount
+            .checked_sub(output_amount)
+            .ok_or_else(|| Error::ad_hoc("transaction outputs exceed inputs"))
+    }
+}
@@ -0,0 +1,74 @@
+//! Estimate the fee rate needed for a transaction to confirm in time.
+
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use bitcoin::FeeRate;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+
+/// The number of blocks within which we want on-chain transactions to confirm, unless configured
+/// otherwise with [`crate::OfflineClient::with_fee_estimator`].
+pub const DEFAULT_FEE_ESTIMATE_TARGET_BLOCKS: u16 = 6;
+
+/// Estimates the fee rate at which transactions currently confirm.
+///
+/// Set one with [`crate::OfflineClient::with_fee_estimator`] to pay estimated fees when spending
+/// boarding outputs and VTXOs on-chain, or pass one to [`crate::Client::bump_exit_tx`] to bump the
+/// fee of a stuck transaction.
+///
+/// With the `esplora` feature, `esplora::EsploraBlockchain` is a `FeeEstimator`. This includes
+/// mempool.space, whose API is Esplora-compatible.
+pub trait FeeEstimator: Send + Sync {
+    /// The fee rate at which a transaction is expected to confirm within `target_blocks` blocks.
+    fn estimate_fee_rate(&self, target_blocks: u16) -> BoxFuture<'_, Result<FeeRate, Error>>;
+}
+
+/// A [`FeeEstimator`] which always returns the same fee rate, whatever the target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedFeeEstimator(pub FeeRate);
+
+impl FeeEstimator for FixedFeeEstimator {
+    fn estimate_fee_rate(&self, _: u16) -> BoxFuture<'_, Result<FeeRate, Error>> {
+        futures::future::ready(Ok(self.0)).boxed()
+    }
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// The fee rate to pay when spending boarding outputs and VTXOs on-chain.
+    ///
+    /// See [`crate::OfflineClient::with_fee_estimator`].
+    pub(crate) async fn onchain_fee_rate(&self) -> FeeRate {
+        let min_fee_rate = self.inner.onchain_fee_rate;
+
+        let fee_estimator = match &self.inner.fee_estimator {
+            Some(fee_estimator) => fee_estimator,
+            None => return min_fee_rate,
+        };
+
+        let target_blocks = self.inner.fee_estimate_target_blocks;
+        match fee_estimator.estimate_fee_rate(target_blocks).await {
+            Ok(fee_rate) => {
+                tracing::debug!(%fee_rate, target_blocks, "Estimated on-chain fee rate");
+
+                fee_rate.max(min_fee_rate)
+            }
+            Err(e) => {
+                tracing::warn!(
+                    fee_rate = %min_fee_rate,
+                    "Failed to estimate on-chain fee rate, falling back to minimum: {e}"
+                );
+
+                min_fee_rate
+            }
+        }
+    }
+}
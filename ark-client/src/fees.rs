@@ -0,0 +1,65 @@
+//! Quote the fees charged by the Ark server before performing an operation.
+//!
+//! The Ark server may publish a fee schedule in [`ark_core::server::Info::fees`]. If it does not,
+//! which is the case for every transport shipped with this crate, quotes use our own estimate
+//! instead, see [`FeeSchedule::estimate`].
+
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use ark_core::fees::FeeSchedule;
+use bitcoin::Amount;
+
+/// An operation settled in a round, for which to quote fees with [`Client::quote_fees`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeOperation {
+    /// Settle `n_inputs` boarding outputs and VTXOs into a single VTXO, see [`Client::board`].
+    Board { n_inputs: usize },
+    /// Settle `n_vtxos` VTXOs near expiry into new VTXOs, see
+    /// [`Client::refresh_vtxos_near_expiry`].
+    Refresh { n_vtxos: usize },
+    /// Send the value of `n_inputs` boarding outputs and VTXOs on-chain, keeping the change in a
+    /// VTXO, see [`Client::off_board`].
+    SendOnChain { n_inputs: usize },
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// The fee that the Ark server is expected to charge for `operation`.
+    ///
+    /// The quote follows the fee schedule published by the Ark server, or our own estimate at our
+    /// on-chain fee rate if it does not publish one, see [`Client::round_fee_schedule`].
+    ///
+    /// When joining a round, the fee actually charged is checked against this quote before we sign
+    /// anything, see
+    /// [`RoundConfig::fee_tolerance`](crate::round::RoundConfig::fee_tolerance).
+    pub async fn quote_fees(&self, operation: FeeOperation) -> Amount {
+        let fees = self.round_fee_schedule().await;
+
+        match operation {
+            FeeOperation::Board { n_inputs } => fees.round_fee(n_inputs, 1, 0),
+            FeeOperation::Refresh { n_vtxos } => fees.round_fee(n_vtxos, 1, 0),
+            // We quote for a change output, even if there may not be one.
+            FeeOperation::SendOnChain { n_inputs } => fees.round_fee(n_inputs, 1, 1),
+        }
+    }
+
+    /// The fee schedule that rounds are expected to follow.
+    ///
+    /// This is the schedule published by the Ark server if there is one. Otherwise, it is our own
+    /// [estimate](FeeSchedule::estimate) at the fee rate we pay on-chain, see
+    /// [`crate::OfflineClient::with_onchain_fee_rate`] and
+    /// [`crate::OfflineClient::with_fee_estimator`].
+    pub async fn round_fee_schedule(&self) -> FeeSchedule {
+        match self.server_info.fees {
+            Some(fees) => fees,
+            None => FeeSchedule::estimate(self.onchain_fee_rate().await),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::ClientStore;
+use crate::wallet::ForfeitRecord;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use bitcoin::Txid;
+
+/// The state of the connector output backing one of our signed forfeit transactions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectorStatus {
+    /// The connector is not on-chain yet. The Ark server can only claim the forfeited VTXO after
+    /// publishing the connector.
+    NotPublished,
+    /// The connector is on-chain and unspent. The Ark server can claim the forfeited VTXO if it is
+    /// published.
+    Unspent,
+    /// The connector was spent by our forfeit transaction, i.e. the Ark server claimed the
+    /// forfeited VTXO.
+    Claimed,
+    /// The connector was spent by a transaction other than our forfeit transaction.
+    ///
+    /// The Ark server can no longer use our forfeit transaction, so the forfeited VTXO is not
+    /// protected against being claimed some other way if it ends up on-chain.
+    SpentAbnormally { spend_txid: Txid },
+}
+
+/// A signed forfeit transaction, together with the current status of its connector output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ForfeitSafety {
+    pub forfeit: ForfeitRecord,
+    pub connector_status: ConnectorStatus,
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Check the connector outputs of all the forfeit transactions that we have signed.
+    ///
+    /// Connectors which were spent by anything other than our forfeit transaction are logged as
+    /// warnings, since that changes what can happen to the forfeited VTXOs if they are published.
+    pub async fn check_forfeit_safety(&self) -> Result<Vec<ForfeitSafety>, Error> {
+        let forfeits = self.inner.wallet.store().load_forfeits()?;
+
+        let mut report = Vec::with_capacity(forfeits.len());
+        for forfeit in forfeits.into_iter() {
+            let connector_outpoint = forfeit.connector_outpoint;
+
+            let connector_status = match self.blockchain().find_tx(&connector_outpoint.txid).await?
+            {
+                None => ConnectorStatus::NotPublished,
+                Some(_) => {
+                    let status = self
+                        .blockchain()
+                        .get_output_status(&connector_outpoint.txid, connector_outpoint.vout)
+                        .await?;
+
+                    match status.spend_txid {
+                        None => ConnectorStatus::Unspent,
+                        Some(spend_txid) if spend_txid == forfeit.forfeit_txid => {
+                            ConnectorStatus::Claimed
+                        }
+                        Some(spend_txid) => {
+                            tracing::warn!(
+                                %connector_outpoint,
+                                %spend_txid,
+                                forfeit_txid = %forfeit.forfeit_txid,
+                                vtxo_outpoint = %forfeit.vtxo_outpoint,
+                                "Connector spent by unexpected transaction"
+                            );
+
+                            ConnectorStatus::SpentAbnormally { spend_txid }
+                        }
+                    }
+                }
+            };
+
+            report.push(ForfeitSafety {
+                forfeit,
+                connector_status,
+            });
+        }
+
+        Ok(report)
+    }
+}
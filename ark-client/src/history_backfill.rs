@@ -0,0 +1,106 @@
+use crate::error::ErrorContext;
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::ClientStore;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use ark_core::generate_incoming_vtxo_transaction_history;
+use ark_core::generate_outgoing_vtxo_transaction_history;
+use ark_core::ArkTransaction;
+use bitcoin::Txid;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Reconstruct our transaction history from our VTXOs and the rounds that created or settled
+    /// them, archiving it so that [`Client::transaction_history`] keeps showing it even once the
+    /// Ark server no longer reports the VTXOs involved.
+    ///
+    /// Call this after restoring a wallet from its seed. It is safe to call again: archived
+    /// transactions are updated in place.
+    ///
+    /// Returns the transactions which were not archived yet.
+    #[tracing::instrument(skip_all)]
+    pub async fn backfill_history(&self) -> Result<Vec<ArkTransaction>, Error> {
+        let mut vtxos = self.list_vtxos().await.context("failed to list VTXOs")?;
+
+        let round_txids = vtxos
+            .spendable
+            .iter()
+            .chain(vtxos.spent.iter())
+            .filter(|vtxo| !vtxo.is_pending)
+            .map(|vtxo| vtxo.round_txid)
+            .collect::<BTreeSet<Txid>>();
+
+        let mut round_ends = HashMap::new();
+        for round_txid in round_txids.iter() {
+            match self
+                .get_round(round_txid.to_string())
+                .await
+                .with_context(|| format!("failed to get round {round_txid}"))?
+            {
+                Some(round) => {
+                    round_ends.insert(*round_txid, round.end);
+                }
+                None => tracing::debug!(%round_txid, "Round unknown to the Ark server"),
+            }
+        }
+
+        // VTXOs from old rounds may lack a creation timestamp, so we date them by their round.
+        for vtxo in vtxos.spendable.iter_mut().chain(vtxos.spent.iter_mut()) {
+            if vtxo.created_at <= 0 {
+                if let Some(round_end) = round_ends.get(&vtxo.round_txid) {
+                    vtxo.created_at = *round_end;
+                }
+            }
+        }
+
+        let (_, boarding_round_txids) = self.boarding_transaction_history().await?;
+
+        let incoming_transactions = generate_incoming_vtxo_transaction_history(
+            &vtxos.spent,
+            &vtxos.spendable,
+            &boarding_round_txids,
+        )?;
+        let outgoing_transactions =
+            generate_outgoing_vtxo_transaction_history(&vtxos.spent, &vtxos.spendable)?;
+
+        let archived = self.inner.wallet.store().load_archived_transactions()?;
+
+        let mut backfilled = Vec::new();
+        for tx in incoming_transactions
+            .into_iter()
+            .chain(outgoing_transactions)
+        {
+            let is_archived = archived.iter().any(|archived| {
+                archived.txid() == tx.txid() && archived.amount().direction == tx.amount().direction
+            });
+
+            self.inner
+                .wallet
+                .store()
+                .save_archived_transaction(tx.clone())?;
+
+            if !is_archived {
+                backfilled.push(tx);
+            }
+        }
+
+        backfilled.sort_by_key(|tx| tx.created_at());
+
+        tracing::info!(
+            n_rounds = round_txids.len(),
+            n_transactions = backfilled.len(),
+            "Backfilled transaction history"
+        );
+
+        Ok(backfilled)
+    }
+}
@@ -0,0 +1,153 @@
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::ClientStore;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use ark_core::payment_proof::PaymentProof;
+use ark_core::server::ListVtxo;
+use ark_core::server::VtxoOutPoint;
+use ark_core::ArkAddress;
+use bitcoin::hex::DisplayHex;
+use jiff::Timestamp;
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Take ownership of a VTXO which was handed to us out-of-band, e.g. as a [`PaymentProof`]
+    /// produced by the sender with [`Client::payment_proof`].
+    ///
+    /// The proof must be fully signed and lead to a VTXO locked by one of our addresses. The Ark
+    /// server must know the round, the round transaction must be confirmed and the VTXO must not
+    /// be spent according to the Ark server.
+    ///
+    /// The VTXO is persisted and included in our balance and coin selection until the Ark server
+    /// reports it itself, or until it expires with the VTXO tree of its round.
+    pub async fn import_vtxo(&self, proof: PaymentProof) -> Result<VtxoOutPoint, Error> {
+        let output = proof.verify(
+            self.server_info.pk.x_only_public_key().0,
+            self.server_info.vtxo_tree_expiry,
+        )?;
+        let outpoint = proof.vtxo_outpoint;
+        let round_txid = proof.round_txid();
+
+        let address = self
+            .get_offchain_addresses()
+            .into_iter()
+            .map(|(address, _)| address)
+            .find(|address| address.to_p2tr_script_pubkey() == output.script_pubkey)
+            .ok_or_else(|| Error::ad_hoc(format!("VTXO {outpoint} does not pay to us")))?;
+
+        let round = self
+            .get_round(round_txid.to_string())
+            .await?
+            .ok_or_else(|| Error::ad_hoc(format!("Ark server does not know round {round_txid}")))?;
+        if round.round_tx.unsigned_tx.compute_txid() != round_txid {
+            return Err(Error::ad_hoc(format!(
+                "Ark server reports a different transaction for round {round_txid}"
+            )));
+        }
+
+        // The VTXO expires with the VTXO tree of its round.
+        let expire_at = round.sweepable_after(self.server_info.vtxo_tree_expiry)?;
+        if expire_at <= Timestamp::now().as_second() {
+            return Err(Error::ad_hoc(format!(
+                "VTXO {outpoint} expired at {expire_at}"
+            )));
+        }
+
+        if self.blockchain().get_confirmations(&round_txid).await? == 0 {
+            return Err(Error::ad_hoc(format!(
+                "round transaction {round_txid} is not confirmed"
+            )));
+        }
+
+        let listed = self.network_client().list_vtxos(&address).await?;
+        if let Some(vtxo) = listed.spent.iter().find(|vtxo| vtxo.outpoint == outpoint) {
+            return Err(Error::ad_hoc(format!(
+                "VTXO {outpoint} was already spent by {:?}",
+                vtxo.spent_by
+            )));
+        }
+
+        // The output key is the part of the script after `OP_1 OP_PUSHBYTES_32`.
+        let pubkey = output.script_pubkey.as_bytes()[2..].to_lower_hex_string();
+
+        let vtxo = VtxoOutPoint {
+            outpoint,
+            spent: false,
+            round_txid,
+            spent_by: None,
+            expire_at,
+            sweepable_after: Some(expire_at),
+            swept: false,
+            is_pending: proof.redeem_tx.is_some(),
+            redeem_tx: proof.redeem_tx,
+            amount: output.value,
+            pubkey,
+            created_at: Timestamp::now().as_second(),
+        };
+
+        self.inner
+            .wallet
+            .store()
+            .save_imported_vtxo(address, vtxo.clone())?;
+
+        tracing::info!(
+            %outpoint,
+            amount = %self.inner.privacy.amount(vtxo.amount),
+            "Imported VTXO"
+        );
+
+        // Persist the outcome of screening the imported VTXO.
+        self.screen_vtxos(vec![vtxo.clone()]).await?;
+
+        Ok(vtxo)
+    }
+
+    /// Add the VTXOs of `address` imported with [`Client::import_vtxo`] which are missing from
+    /// `vtxos`, as reported by the Ark server.
+    ///
+    /// Imported VTXOs which the Ark server now reports, e.g. as spent or swept, are forgotten: the
+    /// Ark server knows better from then on. So are those which expired.
+    pub(crate) fn add_imported_vtxos(
+        &self,
+        address: &ArkAddress,
+        vtxos: &mut ListVtxo,
+    ) -> Result<(), Error> {
+        let now = Timestamp::now().as_second();
+
+        for imported in self.inner.wallet.store().load_imported_vtxos(address)? {
+            let is_listed = vtxos
+                .spendable
+                .iter()
+                .chain(vtxos.spent.iter())
+                .any(|vtxo| vtxo.outpoint == imported.outpoint);
+            let is_expired = imported.expire_at > 0 && imported.expire_at <= now;
+
+            if is_listed || is_expired {
+                tracing::debug!(
+                    outpoint = %imported.outpoint,
+                    is_listed,
+                    is_expired,
+                    "Forgetting imported VTXO"
+                );
+
+                self.inner
+                    .wallet
+                    .store()
+                    .delete_imported_vtxo(address, imported.outpoint)?;
+
+                continue;
+            }
+
+            vtxos.spendable.push(imported);
+        }
+
+        Ok(())
+    }
+}
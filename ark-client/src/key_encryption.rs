@@ -0,0 +1,634 @@
+//! Keep our secret keys encrypted at rest, behind a passphrase.
+//!
+//! Wrap a store implementing both [`Persistence`] and [`EncryptedKeyStore`] in an
+//! [`EncryptedPersistence`]. The rest of the state of the client, see [`ClientStore`], is kept by
+//! the wrapped store in plain text. Secret keys are then encrypted with XChaCha20-Poly1305, under a key
+//! derived from the passphrase with Argon2id, and can only be used while the store is unlocked,
+//! see [`crate::Client::unlock_keys`].
+//!
+//! Only available with the `key-encryption` feature.
+
+use crate::config::ClientConfig;
+use crate::contacts::Contact;
+use crate::wallet::ClientStore;
+use crate::wallet::ExitTx;
+use crate::wallet::ForfeitRecord;
+use crate::wallet::Persistence;
+use crate::wallet::VtxoExit;
+use crate::wallet::VtxoOrigin;
+use crate::wallet::VtxoRiskStatus;
+use crate::wallet::WalletBirthday;
+use crate::watch_only::WatchedAddress;
+use crate::Error;
+use argon2::Argon2;
+use ark_core::receipt::PaymentReceipt;
+use ark_core::server;
+use ark_core::server::ListVtxo;
+use ark_core::server::VtxoOutPoint;
+use ark_core::ArkAddress;
+use ark_core::ArkTransaction;
+use ark_core::BoardingOutput;
+use bitcoin::secp256k1::SecretKey;
+use bitcoin::OutPoint;
+use bitcoin::Txid;
+use bitcoin::XOnlyPublicKey;
+use chacha20poly1305::aead::Aead;
+use chacha20poly1305::aead::KeyInit;
+use chacha20poly1305::aead::Payload;
+use chacha20poly1305::Key;
+use chacha20poly1305::XChaCha20Poly1305;
+use chacha20poly1305::XNonce;
+use rand::RngCore;
+use std::sync::RwLock;
+use zeroize::Zeroizing;
+
+/// The version of the serialization of an [`EncryptedSecretKey`], in case we ever need to change
+/// the algorithms or their parameters.
+const FORMAT_VERSION: u8 = 1;
+
+const SALT_LEN: usize = 16;
+
+const NONCE_LEN: usize = 24;
+
+/// A secret key encrypted with a passphrase.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedSecretKey {
+    salt: [u8; SALT_LEN],
+    nonce: [u8; NONCE_LEN],
+    ciphertext: Vec<u8>,
+}
+
+impl EncryptedSecretKey {
+    /// Encrypt `sk`, the secret key of `pk`, with a key derived from `passphrase`.
+    pub fn encrypt(sk: &SecretKey, pk: &XOnlyPublicKey, passphrase: &str) -> Result<Self, Error> {
+        let key = PassphraseKey::derive(passphrase, random_salt())?;
+
+        Self::encrypt_with(sk, pk, &key)
+    }
+
+    /// Decrypt the secret key of `pk`.
+    ///
+    /// Fails if `passphrase` is wrong or this is not the secret key of `pk`.
+    pub fn decrypt(&self, pk: &XOnlyPublicKey, passphrase: &str) -> Result<SecretKey, Error> {
+        let key = PassphraseKey::derive(passphrase, self.salt)?;
+
+        self.decrypt_with(pk, &key)
+    }
+
+    fn encrypt_with(
+        sk: &SecretKey,
+        pk: &XOnlyPublicKey,
+        key: &PassphraseKey,
+    ) -> Result<Self, Error> {
+        let mut nonce = [0; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let plaintext = Zeroizing::new(sk.secret_bytes());
+
+        // Binding the ciphertext to `pk` prevents swapping the encrypted keys of two public keys.
+        let ciphertext = key
+            .cipher()
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext.as_ref(),
+                    aad: &pk.serialize(),
+                },
+            )
+            .map_err(|e| Error::ad_hoc(format!("failed to encrypt secret key of {pk}: {e}")))?;
+
+        Ok(Self {
+            salt: key.salt,
+            nonce,
+            ciphertext,
+        })
+    }
+
+    /// Fails if `key` was not derived from the right passphrase, with the salt of this key.
+    fn decrypt_with(&self, pk: &XOnlyPublicKey, key: &PassphraseKey) -> Result<SecretKey, Error> {
+        let plaintext = key
+            .cipher()
+            .decrypt(
+                XNonce::from_slice(&self.nonce),
+                Payload {
+                    msg: &self.ciphertext,
+                    aad: &pk.serialize(),
+                },
+            )
+            .map_err(|_| {
+                Error::ad_hoc(format!(
+                    "failed to decrypt secret key of {pk}: wrong passphrase"
+                ))
+            })?;
+        let plaintext = Zeroizing::new(plaintext);
+
+        SecretKey::from_slice(&plaintext)
+            .map_err(|e| Error::ad_hoc(format!("invalid secret key of {pk}: {e}")))
+    }
+
+    /// Serialize the encrypted key for storage, see [`EncryptedSecretKey::from_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + SALT_LEN + NONCE_LEN + self.ciphertext.len());
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&self.salt);
+        bytes.extend_from_slice(&self.nonce);
+        bytes.extend_from_slice(&self.ciphertext);
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let (version, bytes) = bytes
+            .split_first()
+            .ok_or_else(|| Error::ad_hoc("empty encrypted secret key"))?;
+
+        if *version != FORMAT_VERSION {
+            return Err(Error::ad_hoc(format!(
+                "unsupported encrypted secret key version {version}"
+            )));
+        }
+
+        if bytes.len() <= SALT_LEN + NONCE_LEN {
+            return Err(Error::ad_hoc("encrypted secret key too short"));
+        }
+
+        let (salt, bytes) = bytes.split_at(SALT_LEN);
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+
+        Ok(Self {
+            salt: salt.try_into().expect("salt length"),
+            nonce: nonce.try_into().expect("nonce length"),
+            ciphertext: ciphertext.to_vec(),
+        })
+    }
+}
+
+/// Where an [`EncryptedPersistence`] keeps our encrypted secret keys.
+pub trait EncryptedKeyStore {
+    /// Like [`Persistence::save_boarding_output`], but with the secret key encrypted.
+    fn save_encrypted_boarding_output(
+        &self,
+        sk: EncryptedSecretKey,
+        boarding_output: BoardingOutput,
+    ) -> Result<(), Error>;
+
+    fn load_encrypted_sk(&self, pk: &XOnlyPublicKey) -> Result<Option<EncryptedSecretKey>, Error>;
+}
+
+/// A [`Persistence`] which keeps our secret keys encrypted in an [`EncryptedKeyStore`], and
+/// everything else in plain text.
+///
+/// It starts locked: secret keys can be neither saved nor used until [`Persistence::unlock`] is
+/// called with the passphrase. The passphrase itself is not kept: the keys derived from it are,
+/// until [`Persistence::lock`] is called, and they are zeroized when dropped. If no secret key has
+/// been saved yet, any passphrase is accepted and used for every key saved from then on.
+///
+/// Only the secret keys saved through [`Persistence`], i.e. those of our boarding outputs, are
+/// covered. The identity keypair [`crate::OfflineClient::kp`], which signs for our VTXOs, is never
+/// stored here and cannot be locked.
+pub struct EncryptedPersistence<P> {
+    inner: P,
+    /// The keys derived from the passphrase, one per salt of our encrypted secret keys. New secret
+    /// keys are encrypted with the first one. Empty while locked.
+    keys: RwLock<Vec<PassphraseKey>>,
+}
+
+impl<P> EncryptedPersistence<P>
+where
+    P: Persistence + EncryptedKeyStore,
+{
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            keys: RwLock::new(Vec::new()),
+        }
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.keys.read().expect("lock not poisoned").is_empty()
+    }
+}
+
+impl<P> Persistence for EncryptedPersistence<P>
+where
+    P: Persistence + EncryptedKeyStore,
+{
+    fn save_boarding_output(
+        &self,
+        sk: SecretKey,
+        boarding_output: BoardingOutput,
+    ) -> Result<(), Error> {
+        let keys = self.keys.read().expect("lock not poisoned");
+        let key = keys.first().ok_or_else(Error::keys_locked)?;
+
+        let sk = EncryptedSecretKey::encrypt_with(&sk, &boarding_output.owner_pk(), key)?;
+
+        self.inner
+            .save_encrypted_boarding_output(sk, boarding_output)
+    }
+
+    fn load_boarding_outputs(&self) -> Result<Vec<BoardingOutput>, Error> {
+        self.inner.load_boarding_outputs()
+    }
+
+    fn sk_for_pk(&self, pk: &XOnlyPublicKey) -> Result<SecretKey, Error> {
+        let keys = self.keys.read().expect("lock not poisoned");
+        if keys.is_empty() {
+            return Err(Error::keys_locked());
+        }
+
+        let sk = self
+            .inner
+            .load_encrypted_sk(pk)?
+            .ok_or_else(|| Error::ad_hoc(format!("no secret key for {pk}")))?;
+
+        let key = keys.iter().find(|key| key.salt == sk.salt).ok_or_else(|| {
+            Error::ad_hoc(format!(
+                "secret key of {pk} was saved after unlocking: lock and unlock again"
+            ))
+        })?;
+
+        sk.decrypt_with(pk, key)
+    }
+
+    fn unlock(&self, passphrase: &str) -> Result<(), Error> {
+        // Derive the key for every salt of our secret keys once, checking the passphrase against
+        // one secret key per salt.
+        let mut keys = Vec::<PassphraseKey>::new();
+        for boarding_output in self.inner.load_boarding_outputs()? {
+            let pk = boarding_output.owner_pk();
+            let Some(sk) = self.inner.load_encrypted_sk(&pk)? else {
+                continue;
+            };
+
+            if keys.iter().any(|key| key.salt == sk.salt) {
+                continue;
+            }
+
+            let key = PassphraseKey::derive(passphrase, sk.salt)?;
+            sk.decrypt_with(&pk, &key)?;
+
+            keys.push(key);
+        }
+
+        if keys.is_empty() {
+            keys.push(PassphraseKey::derive(passphrase, random_salt())?);
+        }
+
+        *self.keys.write().expect("lock not poisoned") = keys;
+
+        tracing::debug!("Unlocked secret keys");
+
+        Ok(())
+    }
+
+    fn lock(&self) -> Result<(), Error> {
+        self.keys.write().expect("lock not poisoned").clear();
+
+        tracing::debug!("Locked secret keys");
+
+        Ok(())
+    }
+}
+
+impl<P> ClientStore for EncryptedPersistence<P>
+where
+    P: ClientStore,
+{
+    fn save_vtxo_origin(&self, outpoint: OutPoint, origin: VtxoOrigin) -> Result<(), Error> {
+        self.inner.save_vtxo_origin(outpoint, origin)
+    }
+
+    fn load_vtxo_origin(&self, outpoint: &OutPoint) -> Result<Option<VtxoOrigin>, Error> {
+        self.inner.load_vtxo_origin(outpoint)
+    }
+
+    fn save_server_info(&self, info: server::Info) -> Result<(), Error> {
+        self.inner.save_server_info(info)
+    }
+
+    fn load_server_info(&self) -> Result<Option<server::Info>, Error> {
+        self.inner.load_server_info()
+    }
+
+    fn save_vtxo_list(
+        &self,
+        address: ArkAddress,
+        vtxos: ListVtxo,
+        updated_at: i64,
+    ) -> Result<(), Error> {
+        self.inner.save_vtxo_list(address, vtxos, updated_at)
+    }
+
+    fn load_vtxo_list(&self, address: &ArkAddress) -> Result<Option<(ListVtxo, i64)>, Error> {
+        self.inner.load_vtxo_list(address)
+    }
+
+    fn save_vtxo_risk_status(
+        &self,
+        outpoint: OutPoint,
+        status: VtxoRiskStatus,
+    ) -> Result<(), Error> {
+        self.inner.save_vtxo_risk_status(outpoint, status)
+    }
+
+    fn load_vtxo_risk_statuses(&self) -> Result<Vec<(OutPoint, VtxoRiskStatus)>, Error> {
+        self.inner.load_vtxo_risk_statuses()
+    }
+
+    fn save_forfeit(&self, forfeit: ForfeitRecord) -> Result<(), Error> {
+        self.inner.save_forfeit(forfeit)
+    }
+
+    fn load_forfeits(&self) -> Result<Vec<ForfeitRecord>, Error> {
+        self.inner.load_forfeits()
+    }
+
+    fn save_birthday(&self, birthday: WalletBirthday) -> Result<(), Error> {
+        self.inner.save_birthday(birthday)
+    }
+
+    fn load_birthday(&self) -> Result<Option<WalletBirthday>, Error> {
+        self.inner.load_birthday()
+    }
+
+    fn save_config(&self, config: ClientConfig) -> Result<(), Error> {
+        self.inner.save_config(config)
+    }
+
+    fn load_config(&self) -> Result<Option<ClientConfig>, Error> {
+        self.inner.load_config()
+    }
+
+    fn save_claimed_delivery(&self, outpoint: OutPoint) -> Result<(), Error> {
+        self.inner.save_claimed_delivery(outpoint)
+    }
+
+    fn load_claimed_deliveries(&self) -> Result<Vec<OutPoint>, Error> {
+        self.inner.load_claimed_deliveries()
+    }
+
+    fn save_deliveries_initialized(&self) -> Result<(), Error> {
+        self.inner.save_deliveries_initialized()
+    }
+
+    fn load_deliveries_initialized(&self) -> Result<bool, Error> {
+        self.inner.load_deliveries_initialized()
+    }
+
+    fn save_exit_tx(&self, exit_tx: ExitTx) -> Result<(), Error> {
+        self.inner.save_exit_tx(exit_tx)
+    }
+
+    fn load_exit_txs(&self) -> Result<Vec<ExitTx>, Error> {
+        self.inner.load_exit_txs()
+    }
+
+    fn delete_exit_tx(&self, txid: Txid) -> Result<(), Error> {
+        self.inner.delete_exit_tx(txid)
+    }
+
+    fn save_imported_vtxo(&self, address: ArkAddress, vtxo: VtxoOutPoint) -> Result<(), Error> {
+        self.inner.save_imported_vtxo(address, vtxo)
+    }
+
+    fn load_imported_vtxos(&self, address: &ArkAddress) -> Result<Vec<VtxoOutPoint>, Error> {
+        self.inner.load_imported_vtxos(address)
+    }
+
+    fn delete_imported_vtxo(&self, address: &ArkAddress, outpoint: OutPoint) -> Result<(), Error> {
+        self.inner.delete_imported_vtxo(address, outpoint)
+    }
+
+    fn save_contact(&self, contact: Contact) -> Result<(), Error> {
+        self.inner.save_contact(contact)
+    }
+
+    fn load_contacts(&self) -> Result<Vec<Contact>, Error> {
+        self.inner.load_contacts()
+    }
+
+    fn delete_contact(&self, name: &str) -> Result<(), Error> {
+        self.inner.delete_contact(name)
+    }
+
+    fn save_receipt(&self, receipt: PaymentReceipt) -> Result<(), Error> {
+        self.inner.save_receipt(receipt)
+    }
+
+    fn load_receipts(&self) -> Result<Vec<PaymentReceipt>, Error> {
+        self.inner.load_receipts()
+    }
+
+    fn save_processed_event(&self, event_id: String) -> Result<(), Error> {
+        self.inner.save_processed_event(event_id)
+    }
+
+    fn load_processed_events(&self) -> Result<Vec<String>, Error> {
+        self.inner.load_processed_events()
+    }
+
+    fn prune_processed_events(&self, keep: usize) -> Result<(), Error> {
+        self.inner.prune_processed_events(keep)
+    }
+
+    fn save_vtxo_exit(&self, exit: VtxoExit) -> Result<(), Error> {
+        self.inner.save_vtxo_exit(exit)
+    }
+
+    fn load_vtxo_exits(&self) -> Result<Vec<VtxoExit>, Error> {
+        self.inner.load_vtxo_exits()
+    }
+
+    fn save_watched_address(&self, watched: WatchedAddress) -> Result<(), Error> {
+        self.inner.save_watched_address(watched)
+    }
+
+    fn load_watched_addresses(&self) -> Result<Vec<WatchedAddress>, Error> {
+        self.inner.load_watched_addresses()
+    }
+
+    fn delete_watched_address(&self, address: &ArkAddress) -> Result<(), Error> {
+        self.inner.delete_watched_address(address)
+    }
+
+    fn save_archived_transaction(&self, tx: ArkTransaction) -> Result<(), Error> {
+        self.inner.save_archived_transaction(tx)
+    }
+
+    fn load_archived_transactions(&self) -> Result<Vec<ArkTransaction>, Error> {
+        self.inner.load_archived_transactions()
+    }
+}
+
+/// A key derived from a passphrase with Argon2id, zeroized on drop.
+struct PassphraseKey {
+    salt: [u8; SALT_LEN],
+    key: Zeroizing<[u8; 32]>,
+}
+
+impl PassphraseKey {
+    fn derive(passphrase: &str, salt: [u8; SALT_LEN]) -> Result<Self, Error> {
+        let mut key = Zeroizing::new([0; 32]);
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut())
+            .map_err(|e| Error::ad_hoc(format!("failed to derive key from passphrase: {e}")))?;
+
+        Ok(Self { salt, key })
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(Key::from_slice(self.key.as_ref()))
+    }
+}
+
+fn random_salt() -> [u8; SALT_LEN] {
+    let mut salt = [0; SALT_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+
+    salt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::key::Keypair;
+    use bitcoin::key::Secp256k1;
+    use bitcoin::Network;
+
+    const BOARDING_DESCRIPTOR_TEMPLATE: &str =
+        "tr(UNSPENDABLE_KEY,{and(pk(USER),pk(SERVER)),and(older(TIMEOUT),pk(USER))})";
+
+    /// Keeps encrypted secret keys in memory.
+    #[derive(Default)]
+    struct KeyStore {
+        keys: RwLock<Vec<(EncryptedSecretKey, BoardingOutput)>>,
+    }
+
+    impl Persistence for KeyStore {
+        fn save_boarding_output(&self, _: SecretKey, _: BoardingOutput) -> Result<(), Error> {
+            unreachable!("secret keys are saved encrypted")
+        }
+
+        fn load_boarding_outputs(&self) -> Result<Vec<BoardingOutput>, Error> {
+            let keys = self.keys.read().unwrap();
+
+            Ok(keys.iter().map(|(_, b)| b.clone()).collect())
+        }
+
+        fn sk_for_pk(&self, _: &XOnlyPublicKey) -> Result<SecretKey, Error> {
+            unreachable!("secret keys are loaded encrypted")
+        }
+    }
+
+    impl EncryptedKeyStore for KeyStore {
+        fn save_encrypted_boarding_output(
+            &self,
+            sk: EncryptedSecretKey,
+            boarding_output: BoardingOutput,
+        ) -> Result<(), Error> {
+            self.keys.write().unwrap().push((sk, boarding_output));
+
+            Ok(())
+        }
+
+        fn load_encrypted_sk(
+            &self,
+            pk: &XOnlyPublicKey,
+        ) -> Result<Option<EncryptedSecretKey>, Error> {
+            let keys = self.keys.read().unwrap();
+
+            Ok(keys
+                .iter()
+                .find(|(_, b)| b.owner_pk() == *pk)
+                .map(|(sk, _)| sk.clone()))
+        }
+    }
+
+    fn boarding_output(kp: &Keypair) -> BoardingOutput {
+        let secp = Secp256k1::new();
+        let server = Keypair::new(&secp, &mut rand::thread_rng());
+
+        BoardingOutput::new(
+            &secp,
+            server.x_only_public_key().0,
+            kp.x_only_public_key().0,
+            BOARDING_DESCRIPTOR_TEMPLATE,
+            bitcoin::Sequence::from_height(144),
+            Network::Regtest,
+        )
+    }
+
+    #[test]
+    fn encrypted_secret_key_roundtrip() {
+        let kp = Keypair::new(&Secp256k1::new(), &mut rand::thread_rng());
+        let (pk, _) = kp.x_only_public_key();
+
+        let encrypted = EncryptedSecretKey::encrypt(&kp.secret_key(), &pk, "passphrase").unwrap();
+        let encrypted = EncryptedSecretKey::from_bytes(&encrypted.to_bytes()).unwrap();
+
+        let sk = encrypted.decrypt(&pk, "passphrase").unwrap();
+
+        assert_eq!(sk, kp.secret_key());
+    }
+
+    #[test]
+    fn wrong_passphrase_is_rejected() {
+        let kp = Keypair::new(&Secp256k1::new(), &mut rand::thread_rng());
+        let (pk, _) = kp.x_only_public_key();
+
+        let encrypted = EncryptedSecretKey::encrypt(&kp.secret_key(), &pk, "passphrase").unwrap();
+
+        assert!(encrypted.decrypt(&pk, "wrong passphrase").is_err());
+    }
+
+    #[test]
+    fn malformed_encrypted_secret_key_is_rejected() {
+        let kp = Keypair::new(&Secp256k1::new(), &mut rand::thread_rng());
+        let (pk, _) = kp.x_only_public_key();
+
+        let bytes = EncryptedSecretKey::encrypt(&kp.secret_key(), &pk, "passphrase")
+            .unwrap()
+            .to_bytes();
+
+        let mut bad_version = bytes.clone();
+        bad_version[0] = FORMAT_VERSION + 1;
+        assert!(EncryptedSecretKey::from_bytes(&bad_version).is_err());
+
+        assert!(EncryptedSecretKey::from_bytes(&[]).is_err());
+        assert!(EncryptedSecretKey::from_bytes(&bytes[..1 + SALT_LEN + NONCE_LEN]).is_err());
+    }
+
+    #[test]
+    fn encrypted_persistence_locks_secret_keys() {
+        let kp = Keypair::new(&Secp256k1::new(), &mut rand::thread_rng());
+        let (pk, _) = kp.x_only_public_key();
+
+        let persistence = EncryptedPersistence::new(KeyStore::default());
+        assert!(persistence.is_locked());
+
+        let err = persistence
+            .save_boarding_output(kp.secret_key(), boarding_output(&kp))
+            .unwrap_err();
+        assert!(err.is_keys_locked());
+
+        persistence.unlock("passphrase").unwrap();
+        persistence
+            .save_boarding_output(kp.secret_key(), boarding_output(&kp))
+            .unwrap();
+        assert_eq!(persistence.sk_for_pk(&pk).unwrap(), kp.secret_key());
+
+        persistence.lock().unwrap();
+        assert!(persistence.sk_for_pk(&pk).unwrap_err().is_keys_locked());
+
+        assert!(persistence.unlock("wrong passphrase").is_err());
+        assert!(persistence.is_locked());
+
+        persistence.unlock("passphrase").unwrap();
+        assert_eq!(persistence.sk_for_pk(&pk).unwrap(), kp.secret_key());
+    }
+}
@@ -1,42 +1,139 @@
+use crate::derivation::OffchainKeys;
+use crate::error::ErrorContext;
+use crate::events::EventPollingConfig;
+use crate::fee_estimator::FeeEstimator;
+use crate::fee_estimator::DEFAULT_FEE_ESTIMATE_TARGET_BLOCKS;
+use crate::middleware::RoundMiddleware;
+use crate::notifications::ClientEvent;
+use crate::notifications::ObservedWallet;
+use crate::notifications::CLIENT_EVENTS_CAPACITY;
+use crate::operation::OperationJournal;
+use crate::privacy::PrivacyConfig;
+use crate::reservation::Reservations;
+use crate::retry::RetryPolicy;
+use crate::retry::RetryingTransport;
+use crate::risk::RiskOracle;
+use crate::round::AutoBoardPolicy;
+use crate::round::DustSweepPolicy;
+use crate::round::RoundConfig;
+use crate::round::RoundRetryPolicy;
+use crate::signer::ArkSigner;
+use crate::transport::NetworkTransport;
 use crate::wallet::BoardingWallet;
+use crate::wallet::ClientStore;
+use crate::wallet::ExternalSigner;
 use crate::wallet::OnchainWallet;
+use crate::wallet::WalletBirthday;
+use ark_core::coin_select::CoinSelectionStrategy;
 use ark_core::default_vtxo::DefaultVtxo;
+use ark_core::exit_delay::BlockTime;
 use ark_core::generate_incoming_vtxo_transaction_history;
 use ark_core::generate_outgoing_vtxo_transaction_history;
+use ark_core::payment_proof::payment_proof;
+use ark_core::payment_proof::PaymentProof;
+use ark_core::redeem::ChangePolicy;
+use ark_core::round::VtxoTreeLimits;
 use ark_core::server;
 use ark_core::server::ListVtxo;
+use ark_core::server::Maintenance;
 use ark_core::server::Round;
+use ark_core::server::ServerPolicy;
 use ark_core::server::VtxoOutPoint;
+use ark_core::topology::round_topology;
+use ark_core::topology::RoundTopology;
 use ark_core::ArkAddress;
 use ark_core::ArkTransaction;
+use ark_core::TransactionAmount;
 use bitcoin::key::Keypair;
 use bitcoin::key::Secp256k1;
 use bitcoin::secp256k1::All;
 use bitcoin::Address;
 use bitcoin::Amount;
+use bitcoin::FeeRate;
 use bitcoin::OutPoint;
 use bitcoin::Transaction;
 use bitcoin::Txid;
 use futures::Future;
 use jiff::Timestamp;
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use tokio::sync::broadcast;
 
+#[cfg(feature = "bitcoind")]
+pub mod bitcoind;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod coin_control;
+pub mod config;
+#[cfg(feature = "consensus-verify")]
+pub mod consensus_verify;
+pub mod contacts;
+pub mod custody;
+pub mod delivery;
+#[cfg(feature = "electrum")]
+pub mod electrum;
 pub mod error;
+#[cfg(feature = "esplora")]
+pub mod esplora;
+pub mod events;
+pub mod exit_manager;
+pub mod fee_estimator;
+pub mod fees;
+pub mod forfeit_monitor;
+#[cfg(feature = "key-encryption")]
+pub mod key_encryption;
+pub mod maintenance;
+pub mod middleware;
+pub mod multi_blockchain;
+pub mod notifications;
+pub mod operation;
+pub mod prelude;
+pub mod privacy;
+pub mod reconcile;
+pub mod reservation;
+pub mod retry;
+pub mod risk;
 pub mod round;
+pub mod round_handle;
+pub mod signer;
+#[cfg(feature = "support-bundle")]
+pub mod support_bundle;
+pub mod transport;
+pub mod tx_broadcast;
+pub mod vtxo_refresher;
 pub mod wallet;
+pub mod watch_only;
 
+#[cfg(feature = "accounting")]
+mod accounting;
 mod coin_select;
+mod derivation;
+mod fee_bump;
+mod history_backfill;
+mod import_vtxo;
+mod receipt;
 mod send_vtxo;
+#[cfg(feature = "status-server")]
+mod status_server;
 mod unilateral_exit;
 mod utils;
+mod vtxo_origin;
 
+pub use derivation::ChangeAddressStrategy;
 pub use error::Error;
+pub use send_vtxo::PaymentOutcome;
+pub use unilateral_exit::AddressTypePolicy;
 
 /// A client to interact with Ark Server
 ///
 /// ## Example
 ///
-/// ```rust
+/// With the `grpc` feature:
+///
+#[cfg_attr(feature = "grpc", doc = "```rust")]
+#[cfg_attr(not(feature = "grpc"), doc = "```ignore")]
 /// # use std::future::Future;
 /// # use std::str::FromStr;
 /// # use ark_client::{Blockchain, Client, Error, ExplorerUtxo, SpendStatus};
@@ -44,9 +141,10 @@ pub use error::Error;
 /// # use bitcoin::key::Keypair;
 /// # use bitcoin::secp256k1::{Message, SecretKey};
 /// # use std::sync::Arc;
-/// # use bitcoin::{Address, Amount, FeeRate, Network, Psbt, Transaction, Txid, XOnlyPublicKey};
+/// # use bitcoin::{Address, Amount, FeeRate, Network, OutPoint, Psbt, Transaction, Txid, XOnlyPublicKey};
 /// # use bitcoin::secp256k1::schnorr::Signature;
-/// # use ark_client::wallet::{Balance, BoardingWallet, OnchainWallet, Persistence};
+/// # use ark_client::wallet::{Balance, BoardingWallet, ClientStore, ExitTx, ForfeitRecord, OnchainWallet, Persistence, VtxoRiskStatus};
+/// # use ark_core::server;
 /// # use ark_core::BoardingOutput;
 ///
 /// struct MyBlockchain {}
@@ -72,13 +170,25 @@ pub use error::Error;
 /// #     async fn broadcast(&self, tx: &Transaction) -> Result<(), Error> {
 /// #         unimplemented!()
 /// #     }
+/// #
+/// #     async fn get_confirmations(&self, txid: &Txid) -> Result<u32, Error> {
+/// #         unimplemented!()
+/// #     }
+/// #
+/// #     async fn get_tip_height(&self) -> Result<u32, Error> {
+/// #         unimplemented!()
+/// #     }
+/// #
+/// #     async fn get_median_time_past(&self, height: u32) -> Result<u64, Error> {
+/// #         unimplemented!()
+/// #     }
 /// # }
 ///
 /// struct MyWallet {}
 ///
 /// # impl OnchainWallet for MyWallet where {
 /// #
-/// #     fn get_onchain_address(&self) -> Result<Address, Error> {
+/// #     async fn get_onchain_address(&self) -> Result<Address, Error> {
 /// #         unimplemented!("You can implement this function using your preferred client library such as bdk")
 /// #     }
 /// #
@@ -86,15 +196,23 @@ pub use error::Error;
 /// #         unimplemented!()
 /// #     }
 /// #
-/// #     fn balance(&self) -> Result<Balance, Error> {
+/// #     async fn balance(&self) -> Result<Balance, Error> {
 /// #         unimplemented!()
 /// #     }
 /// #
-/// #     fn prepare_send_to_address(&self, address: Address, amount: Amount, fee_rate: FeeRate) -> Result<Psbt, Error> {
+/// #     async fn prepare_send_to_address(&self, address: Address, amount: Amount, fee_rate: FeeRate) -> Result<Psbt, Error> {
 /// #         unimplemented!()
 /// #     }
 /// #
-/// #     fn sign(&self, psbt: &mut Psbt) -> Result<bool, Error> {
+/// #     async fn sign(&self, psbt: &mut Psbt) -> Result<bool, Error> {
+/// #         unimplemented!()
+/// #     }
+/// #
+/// #     async fn prepare_cpfp(&self, parent: &Transaction, fee_rate: FeeRate) -> Result<Psbt, Error> {
+/// #         unimplemented!()
+/// #     }
+/// #
+/// #     async fn prepare_anchor_cpfp(&self, parent: &Transaction, anchor_vout: u32, fee_rate: FeeRate) -> Result<Psbt, Error> {
 /// #         unimplemented!()
 /// #     }
 /// # }
@@ -118,12 +236,54 @@ pub use error::Error;
 /// #     fn sk_for_pk(&self, pk: &XOnlyPublicKey) -> Result<SecretKey, Error> {
 /// #         unimplemented!()
 /// #     }
+/// #
+/// # }
+/// #
+/// # impl ClientStore for InMemoryDb {
+/// #
+/// #     fn save_server_info(&self, info: server::Info) -> Result<(), Error> {
+/// #         unimplemented!()
+/// #     }
+/// #
+/// #     fn load_server_info(&self) -> Result<Option<server::Info>, Error> {
+/// #         unimplemented!()
+/// #     }
+/// #
+/// #     fn save_vtxo_risk_status(&self, outpoint: OutPoint, status: VtxoRiskStatus) -> Result<(), Error> {
+/// #         unimplemented!()
+/// #     }
+/// #
+/// #     fn load_vtxo_risk_statuses(&self) -> Result<Vec<(OutPoint, VtxoRiskStatus)>, Error> {
+/// #         unimplemented!()
+/// #     }
+/// #
+/// #     fn save_forfeit(&self, forfeit: ForfeitRecord) -> Result<(), Error> {
+/// #         unimplemented!()
+/// #     }
+/// #
+/// #     fn load_forfeits(&self) -> Result<Vec<ForfeitRecord>, Error> {
+/// #         unimplemented!()
+/// #     }
+/// #
+/// #     fn save_exit_tx(&self, exit_tx: ExitTx) -> Result<(), Error> {
+/// #         unimplemented!()
+/// #     }
+/// #
+/// #     fn load_exit_txs(&self) -> Result<Vec<ExitTx>, Error> {
+/// #         unimplemented!()
+/// #     }
+/// #
+/// #     fn delete_exit_tx(&self, txid: Txid) -> Result<(), Error> {
+/// #         unimplemented!()
+/// #     }
 /// # }
 /// #
 /// #
 /// # impl BoardingWallet for MyWallet
 /// # where
 /// # {
+/// #     type Store = InMemoryDb;
+/// #
 /// #     fn new_boarding_output(
 /// #         &self,
 /// #         server_pk: XOnlyPublicKey,
@@ -138,7 +298,11 @@ pub use error::Error;
 /// #         unimplemented!()
 /// #     }
 /// #
-/// #     fn sign_for_pk(&self, pk: &XOnlyPublicKey, msg: &Message) -> Result<Signature, Error> {
+/// #     fn store(&self) -> &InMemoryDb {
+/// #         unimplemented!()
+/// #     }
+/// #
+/// #     async fn sign_for_pk(&self, pk: &XOnlyPublicKey, msg: &Message) -> Result<Signature, Error> {
 /// #         unimplemented!()
 /// #     }
 /// # }
@@ -169,22 +333,105 @@ pub use error::Error;
 ///     Ok(client)
 /// }
 /// ```
-pub struct OfflineClient<B, W> {
-    // TODO: We could introduce a generic interface so that consumers can use either GRPC or REST.
-    network_client: ark_grpc::Client,
+pub struct OfflineClient<
+    B,
+    W,
+    #[cfg(all(
+        feature = "grpc",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))] T = ark_grpc::Client,
+    #[cfg(not(all(
+        feature = "grpc",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    )))] T,
+> {
+    network_client: RetryingTransport<T>,
     pub name: String,
+    /// The identity keypair of the client, which owns our VTXOs and signs for them, unless an
+    /// [`ArkSigner`] is set with [`OfflineClient::with_ark_signer`].
+    ///
+    /// It is kept in memory in plain text for as long as the client lives. Only the secret keys of
+    /// the wallet, i.e. those of our boarding outputs, can be encrypted at rest and locked with
+    /// [`Client::lock_keys`]: protecting this keypair is up to the application.
     pub kp: Keypair,
     blockchain: Arc<B>,
     secp: Secp256k1<All>,
     wallet: Arc<W>,
+    /// The number of onchain confirmations that the round transaction backing a VTXO must have
+    /// before the VTXO is considered confirmed.
+    ///
+    /// Defaults to 0, meaning that VTXOs are considered confirmed as soon as they are part of a
+    /// round.
+    min_round_confirmations: u32,
+    risk_oracle: Option<Arc<dyn RiskOracle>>,
+    round_middleware: Vec<Arc<dyn RoundMiddleware>>,
+    external_signer: Option<Arc<dyn ExternalSigner>>,
+    ark_signer: Option<Arc<dyn ArkSigner>>,
+    /// The fee rate paid by transactions which spend boarding outputs and VTXOs on-chain.
+    ///
+    /// If `fee_estimator` is set, this is the minimum fee rate paid instead.
+    onchain_fee_rate: FeeRate,
+    fee_estimator: Option<Arc<dyn FeeEstimator>>,
+    /// The number of blocks within which we want on-chain transactions to confirm, when estimating
+    /// their fee rate with `fee_estimator`.
+    fee_estimate_target_blocks: u16,
+    address_type_policy: AddressTypePolicy,
+    round_retry_policy: RoundRetryPolicy,
+    round_config: RoundConfig,
+    dust_sweep_policy: Option<DustSweepPolicy>,
+    auto_board_policy: Option<AutoBoardPolicy>,
+    /// Whether received VTXOs must be accepted manually before they can be spent.
+    manual_review: bool,
+    /// Whether VTXOs rejected after review are sent back to their senders, see
+    /// [`Client::refund_rejected_vtxos`].
+    refund_rejected_vtxos: bool,
+    /// How addresses and amounts are written to logs.
+    privacy: PrivacyConfig,
+    /// What to do with change which is too small to be worth a VTXO when sending VTXOs.
+    change_policy: ChangePolicy,
+    /// The order in which VTXOs are selected to pay for a payment.
+    coin_selection_strategy: CoinSelectionStrategy,
+    birthday: Option<WalletBirthday>,
+    offchain_keys: Option<OffchainKeys>,
+    change_address_strategy: ChangeAddressStrategy,
+    /// How transaction events are polled for if the Ark server cannot stream them.
+    event_polling: EventPollingConfig,
+    /// The limits on the VTXO trees that we cosign.
+    vtxo_tree_limits: VtxoTreeLimits,
+    /// What we expect of the Ark server, checked whenever we get its info.
+    server_policy: ServerPolicy,
+    /// Whether to adopt the Ark server's public key even if it differs from the cached one.
+    reset_server_key: bool,
+    client_events: broadcast::Sender<ClientEvent>,
 }
 
 /// A client to interact with Ark server
 ///
 /// See [`OfflineClient`] docs for details.
-pub struct Client<B, W> {
-    inner: OfflineClient<B, W>,
+pub struct Client<
+    B,
+    W,
+    #[cfg(all(
+        feature = "grpc",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))] T = ark_grpc::Client,
+    #[cfg(not(all(
+        feature = "grpc",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    )))] T,
+> {
+    inner: OfflineClient<B, W, T>,
     pub server_info: server::Info,
+    /// Whether `server_info` was fetched from the Ark server, as opposed to loaded from the cache.
+    server_info_is_live: bool,
+    /// Whether `server_info` was fetched from the Ark server or checked against it.
+    server_info_checked: AtomicBool,
+    reservations: Arc<Reservations>,
+    operation_journal: OperationJournal,
+    observed: ObservedWallet,
+    /// Downtime of the Ark server, as announced by its operator through
+    /// [`Client::set_maintenance`].
+    maintenance: Option<Maintenance>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -192,6 +439,8 @@ pub struct ExplorerUtxo {
     pub outpoint: OutPoint,
     pub amount: Amount,
     pub confirmation_blocktime: Option<u64>,
+    /// The height of the block which confirmed the output, if it is confirmed.
+    pub confirmation_height: Option<u32>,
     pub is_spent: bool,
 }
 
@@ -200,10 +449,57 @@ pub struct SpendStatus {
     pub spend_txid: Option<Txid>,
 }
 
+/// Whether data was fetched from the Ark server or loaded from the cache because the server was
+/// unreachable.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DataFreshness {
+    #[default]
+    Live,
+    /// The data was cached at this UNIX timestamp.
+    Cached { updated_at: i64 },
+}
+
+impl DataFreshness {
+    /// Combine the freshness of two pieces of data, keeping the stalest.
+    fn merge(self, other: DataFreshness) -> DataFreshness {
+        match (self, other) {
+            (DataFreshness::Live, other) | (other, DataFreshness::Live) => other,
+            (DataFreshness::Cached { updated_at: a }, DataFreshness::Cached { updated_at: b }) => {
+                DataFreshness::Cached {
+                    updated_at: a.min(b),
+                }
+            }
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Default)]
 pub struct OffChainBalance {
     pending: Amount,
+    awaiting_confirmations: Amount,
     confirmed: Amount,
+    flagged: Amount,
+    pending_review: Amount,
+    rejected: Amount,
+    recoverable: Amount,
+    sweep_warning: Option<SweepWarning>,
+    freshness: DataFreshness,
+}
+
+/// How long before the Ark server can sweep them that VTXOs are reported in a [`SweepWarning`].
+pub const SWEEP_WARNING_WINDOW: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+
+/// Some of our VTXOs can soon be swept by the Ark server, see
+/// [`VtxoOutPoint::sweepable_after`].
+///
+/// They should be refreshed before then, e.g. with [`Client::refresh_vtxos_near_expiry`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SweepWarning {
+    /// The amount held in VTXOs which can be swept within [`SWEEP_WARNING_WINDOW`], or which can
+    /// already be swept.
+    pub amount: Amount,
+    /// The UNIX timestamp after which the first of these VTXOs can be swept.
+    pub earliest_sweepable_after: i64,
 }
 
 impl OffChainBalance {
@@ -211,15 +507,66 @@ impl OffChainBalance {
         self.pending
     }
 
+    /// The amount held in VTXOs whose round transaction does not yet have the number of onchain
+    /// confirmations required by [`OfflineClient::with_min_round_confirmations`].
+    pub fn awaiting_confirmations(&self) -> Amount {
+        self.awaiting_confirmations
+    }
+
     pub fn confirmed(&self) -> Amount {
         self.confirmed
     }
 
+    /// The amount held in VTXOs flagged by the [`RiskOracle`], which cannot be spent until they
+    /// are released.
+    pub fn flagged(&self) -> Amount {
+        self.flagged
+    }
+
+    /// The amount held in received VTXOs awaiting manual review, which cannot be spent until they
+    /// are accepted.
+    pub fn pending_review(&self) -> Amount {
+        self.pending_review
+    }
+
+    /// The amount held in VTXOs rejected after manual review, which will never be spent.
+    pub fn rejected(&self) -> Amount {
+        self.rejected
+    }
+
+    /// The amount held in VTXOs that expired and were swept by the Ark server, which can be
+    /// reclaimed with [`Client::recover_swept_vtxos`].
+    pub fn recoverable(&self) -> Amount {
+        self.recoverable
+    }
+
+    /// Set if some of the VTXOs counted in this balance can soon be swept by the Ark server.
+    pub fn sweep_warning(&self) -> Option<SweepWarning> {
+        self.sweep_warning
+    }
+
     pub fn total(&self) -> Amount {
-        self.pending + self.confirmed
+        self.pending
+            + self.awaiting_confirmations
+            + self.confirmed
+            + self.flagged
+            + self.pending_review
+            + self.rejected
+            + self.recoverable
+    }
+
+    pub fn freshness(&self) -> DataFreshness {
+        self.freshness
     }
 }
 
+/// Our VTXOs, grouped by the [`DefaultVtxo`] they belong to.
+struct OffchainVtxos {
+    spendable: Vec<(Vec<VtxoOutPoint>, DefaultVtxo)>,
+    recoverable: Vec<(Vec<VtxoOutPoint>, DefaultVtxo)>,
+    freshness: DataFreshness,
+}
+
 pub trait Blockchain {
     fn find_outpoints(
         &self,
@@ -237,9 +584,54 @@ pub trait Blockchain {
         vout: u32,
     ) -> impl Future<Output = Result<SpendStatus, Error>> + Send;
 
+    /// Publish `tx`.
+    ///
+    /// If the backend rejects the transaction, return [`Error::broadcast`] with the reason for
+    /// the rejection, classified with [`tx_broadcast::BroadcastError::from_reason`]. This lets the
+    /// client treat transactions which were already published as broadcast, and rebuild
+    /// transactions which do not pay enough fees.
     fn broadcast(&self, tx: &Transaction) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Like [`Blockchain::find_outpoints`], but outpoints confirmed before the
+    /// [`WalletBirthday::scan_start_timestamp`] of `birthday` may be left out.
+    ///
+    /// Backends which can limit their scans, e.g. by no longer paging through older transactions,
+    /// should override this to speed up restoring a wallet. By default, all outpoints are found.
+    fn find_outpoints_since(
+        &self,
+        address: &Address,
+        birthday: WalletBirthday,
+    ) -> impl Future<Output = Result<Vec<ExplorerUtxo>, Error>> + Send {
+        let _ = birthday;
+
+        self.find_outpoints(address)
+    }
+
+    /// The number of onchain confirmations of the transaction identified by `txid`.
+    ///
+    /// Must return 0 if the transaction is unconfirmed or unknown.
+    fn get_confirmations(&self, txid: &Txid) -> impl Future<Output = Result<u32, Error>> + Send;
+
+    /// The height of the tip of the blockchain.
+    ///
+    /// Used to check whether block-based exit delays have elapsed.
+    fn get_tip_height(&self) -> impl Future<Output = Result<u32, Error>> + Send;
+
+    /// The median time past of the block at `height`, in UNIX seconds: the median of the
+    /// timestamps of that block and of the 10 blocks before it, see
+    /// [`ark_core::exit_delay::median_time_past`].
+    ///
+    /// Used to check whether time-based exit delays have elapsed, which BIP68 measures in median
+    /// time past rather than in block timestamps.
+    fn get_median_time_past(&self, height: u32) -> impl Future<Output = Result<u64, Error>> + Send;
 }
 
+/// The gRPC transport is not available in WASM or without the `grpc` feature, where a transport
+/// must be passed to [`OfflineClient::new_with_transport`] instead.
+#[cfg(all(
+    feature = "grpc",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
 impl<B, W> OfflineClient<B, W>
 where
     B: Blockchain,
@@ -252,51 +644,501 @@ where
         wallet: Arc<W>,
         ark_server_url: String,
     ) -> Self {
-        let secp = Secp256k1::new();
-
         let network_client = ark_grpc::Client::new(ark_server_url);
 
+        Self::new_with_transport(name, kp, blockchain, wallet, network_client)
+    }
+}
+
+impl<B, W, T> OfflineClient<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Like [`OfflineClient::new`], but talking to the Ark server via `network_client` instead of
+    /// gRPC.
+    pub fn new_with_transport(
+        name: String,
+        kp: Keypair,
+        blockchain: Arc<B>,
+        wallet: Arc<W>,
+        network_client: T,
+    ) -> Self {
+        let secp = Secp256k1::new();
+
         Self {
-            network_client,
+            network_client: RetryingTransport::new(network_client, RetryPolicy::default()),
             name,
             kp,
             blockchain,
             secp,
             wallet,
+            min_round_confirmations: 0,
+            risk_oracle: None,
+            round_middleware: Vec::new(),
+            external_signer: None,
+            ark_signer: None,
+            onchain_fee_rate: FeeRate::BROADCAST_MIN,
+            fee_estimator: None,
+            fee_estimate_target_blocks: DEFAULT_FEE_ESTIMATE_TARGET_BLOCKS,
+            address_type_policy: AddressTypePolicy::default(),
+            round_retry_policy: RoundRetryPolicy::default(),
+            round_config: RoundConfig::default(),
+            dust_sweep_policy: None,
+            auto_board_policy: None,
+            manual_review: false,
+            refund_rejected_vtxos: false,
+            privacy: PrivacyConfig::default(),
+            change_policy: ChangePolicy::default(),
+            coin_selection_strategy: CoinSelectionStrategy::default(),
+            birthday: None,
+            offchain_keys: None,
+            change_address_strategy: ChangeAddressStrategy::default(),
+            event_polling: EventPollingConfig::default(),
+            vtxo_tree_limits: VtxoTreeLimits::default(),
+            server_policy: ServerPolicy::default(),
+            reset_server_key: false,
+            client_events: broadcast::channel(CLIENT_EVENTS_CAPACITY).0,
         }
     }
 
-    pub async fn connect(mut self) -> Result<Client<B, W>, Error> {
-        self.network_client.connect().await?;
-        let server_info = self.network_client.get_info().await?;
+    /// Only count VTXOs as confirmed once their round transaction has at least
+    /// `min_round_confirmations` onchain confirmations.
+    ///
+    /// VTXOs which do not meet this requirement are reported separately, via
+    /// [`OffChainBalance::awaiting_confirmations`].
+    pub fn with_min_round_confirmations(mut self, min_round_confirmations: u32) -> Self {
+        self.min_round_confirmations = min_round_confirmations;
+        self
+    }
 
-        tracing::debug!(
-            name = self.name,
-            ark_server_url = ?self.network_client,
-            "Connected to Ark server"
-        );
+    /// Screen our VTXOs with `risk_oracle` before spending them.
+    ///
+    /// See [`RiskOracle`] for details.
+    pub fn with_risk_oracle(mut self, risk_oracle: Arc<dyn RiskOracle>) -> Self {
+        self.risk_oracle = Some(risk_oracle);
+        self
+    }
+
+    /// Sign the round transaction inputs spending our boarding outputs with `external_signer`,
+    /// instead of [`BoardingWallet::sign_for_pk`].
+    ///
+    /// The signatures are verified before they are submitted to the Ark server.
+    pub fn with_external_signer(mut self, external_signer: Arc<dyn ExternalSigner>) -> Self {
+        self.external_signer = Some(external_signer);
+        self
+    }
+
+    /// Sign for our VTXOs with `ark_signer`, e.g. a hardware wallet, instead of with `kp`.
+    ///
+    /// The default offchain address is then owned by the key of `ark_signer`. See
+    /// [`crate::signer`] for what it signs.
+    pub fn with_ark_signer(mut self, ark_signer: Arc<dyn ArkSigner>) -> Self {
+        self.ark_signer = Some(ark_signer);
+        self
+    }
+
+    /// Poll for transaction events as configured by `event_polling` if the Ark server cannot
+    /// stream them, see [`Client::transaction_events`].
+    pub fn with_event_polling(mut self, event_polling: EventPollingConfig) -> Self {
+        self.event_polling = event_polling;
+        self
+    }
+
+    /// Refuse to cosign VTXO trees which exceed `vtxo_tree_limits`.
+    ///
+    /// Defaults to [`VtxoTreeLimits::default`]. See [`ark_core::round::verify_vtxo_tree`] for
+    /// everything which is checked before cosigning a VTXO tree.
+    pub fn with_vtxo_tree_limits(mut self, vtxo_tree_limits: VtxoTreeLimits) -> Self {
+        self.vtxo_tree_limits = vtxo_tree_limits;
+        self
+    }
+
+    /// Retry requests to the Ark server which fail for transient reasons according to
+    /// `retry_policy`.
+    ///
+    /// Defaults to [`RetryPolicy::default`]. See [`crate::retry`] for details.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.network_client.set_policy(retry_policy);
+        self
+    }
+
+    /// Refuse to use an Ark server whose info does not meet `server_policy`, e.g. because it runs
+    /// on another network or uses a different public key than the pinned one.
+    ///
+    /// The policy is checked by [`OfflineClient::connect`], [`OfflineClient::connect_lazy`] and
+    /// [`Client::ensure_server_info`], against both fetched and cached server info.
+    pub fn with_server_policy(mut self, server_policy: ServerPolicy) -> Self {
+        self.server_policy = server_policy;
+        self
+    }
+
+    /// Accept a new public key of the Ark server, replacing the cached one.
+    ///
+    /// By default, connecting fails with an error for which [`Error::is_server_key_changed`] holds
+    /// if the Ark server's key changed since it was cached: our VTXOs and boarding outputs are
+    /// locked with the old key, so the change is either an attack or a new Ark server altogether.
+    /// Only reset the key once the operator of the Ark server confirmed the change.
+    pub fn with_server_key_reset(mut self) -> Self {
+        self.reset_server_key = true;
+        self
+    }
+
+    /// Call `round_middleware` at every step of the rounds we join.
+    ///
+    /// Can be called several times, in which case the middleware is called in the order in which
+    /// it was added. See [`RoundMiddleware`] for details.
+    pub fn with_round_middleware(mut self, round_middleware: Arc<dyn RoundMiddleware>) -> Self {
+        self.round_middleware.push(round_middleware);
+        self
+    }
+
+    /// Pay `onchain_fee_rate` when spending boarding outputs and VTXOs on-chain.
+    ///
+    /// Defaults to [`FeeRate::BROADCAST_MIN`]. With [`OfflineClient::with_fee_estimator`], this is
+    /// the minimum fee rate paid instead.
+    pub fn with_onchain_fee_rate(mut self, onchain_fee_rate: FeeRate) -> Self {
+        self.onchain_fee_rate = onchain_fee_rate;
+        self
+    }
+
+    /// Pay the fee rate estimated by `fee_estimator` for confirmation within `target_blocks`
+    /// blocks when spending boarding outputs and VTXOs on-chain, e.g. when sending on-chain or
+    /// sweeping exited VTXOs.
+    ///
+    /// If the estimate is lower than the fee rate set with
+    /// [`OfflineClient::with_onchain_fee_rate`], or if it cannot be obtained, that fee rate is
+    /// paid instead.
+    pub fn with_fee_estimator(
+        mut self,
+        fee_estimator: Arc<dyn FeeEstimator>,
+        target_blocks: u16,
+    ) -> Self {
+        self.fee_estimator = Some(fee_estimator);
+        self.fee_estimate_target_blocks = target_blocks;
+        self
+    }
+
+    /// Restrict the address types used when spending boarding outputs and VTXOs on-chain.
+    pub fn with_address_type_policy(mut self, address_type_policy: AddressTypePolicy) -> Self {
+        self.address_type_policy = address_type_policy;
+        self
+    }
+
+    /// Control how we re-register for the next round when a round we joined is aborted by the Ark
+    /// server, when boarding or off-boarding.
+    pub fn with_round_retry_policy(mut self, round_retry_policy: RoundRetryPolicy) -> Self {
+        self.round_retry_policy = round_retry_policy;
+        self
+    }
+
+    /// Limit how long each phase of the rounds we join may take and what we register for each of
+    /// them, see [`RoundConfig`].
+    pub fn with_round_config(mut self, round_config: RoundConfig) -> Self {
+        self.round_config = round_config;
+        self
+    }
+
+    /// Hold every VTXO we receive for review, excluding it from the spendable balance until it is
+    /// accepted with [`Client::accept_vtxo`] or rejected with [`Client::reject_vtxo`].
+    ///
+    /// VTXOs created by our own operations, such as boarding or change outputs, are not held. VTXOs
+    /// which were never screened before, e.g. because they were received before enabling review,
+    /// are held too.
+    pub fn with_manual_review(mut self) -> Self {
+        self.manual_review = true;
+        self
+    }
+
+    /// Send the VTXOs rejected with [`Client::reject_vtxo`] back to their senders, via
+    /// [`Client::refund_rejected_vtxos`].
+    ///
+    /// By default, rejected VTXOs are only excluded from spending.
+    pub fn with_rejected_vtxo_refunds(mut self) -> Self {
+        self.refund_rejected_vtxos = true;
+        self
+    }
+
+    /// Consolidate small VTXOs during the Ark server's market hour, via
+    /// [`Client::sweep_small_vtxos`].
+    ///
+    /// By default, small VTXOs are not swept.
+    pub fn with_dust_sweep_policy(mut self, dust_sweep_policy: DustSweepPolicy) -> Self {
+        self.dust_sweep_policy = Some(dust_sweep_policy);
+        self
+    }
+
+    /// Board confirmed deposits without an explicit request, via [`Client::auto_board`].
+    ///
+    /// By default, boarding outputs are only boarded by [`Client::board`].
+    pub fn with_auto_board_policy(mut self, auto_board_policy: AutoBoardPolicy) -> Self {
+        self.auto_board_policy = Some(auto_board_policy);
+        self
+    }
+
+    /// Only search the blockchain for our outputs from `birthday` onwards.
+    ///
+    /// Use [`WalletBirthday::now`] when creating a new wallet, or the creation date of the wallet
+    /// when restoring it. The birthday is persisted on connect, so it only needs to be set once.
+    /// Without a birthday, the [`Blockchain`] may have to scan from the genesis block.
+    pub fn with_birthday(mut self, birthday: WalletBirthday) -> Self {
+        self.birthday = Some(birthday);
+        self
+    }
+
+    /// Redact addresses and amounts in logs, e.g. with [`PrivacyConfig::strict`].
+    ///
+    /// By default, they are logged in full.
+    pub fn with_privacy_config(mut self, privacy: PrivacyConfig) -> Self {
+        self.privacy = privacy;
+        self
+    }
+
+    /// Avoid creating change VTXOs below `change_policy.min_change` in [`Client::send_vtxo`].
+    ///
+    /// Change below the Ark server's dust limit is always considered too small. By default, such
+    /// change goes towards the fee.
+    pub fn with_change_policy(mut self, change_policy: ChangePolicy) -> Self {
+        self.change_policy = change_policy;
+        self
+    }
+
+    /// Select the VTXOs to spend in a payment according to `coin_selection_strategy`.
+    ///
+    /// By default, the VTXOs which expire soonest are spent first.
+    pub fn with_coin_selection_strategy(
+        mut self,
+        coin_selection_strategy: CoinSelectionStrategy,
+    ) -> Self {
+        self.coin_selection_strategy = coin_selection_strategy;
+        self
+    }
+
+    /// Connect to the Ark server and fetch its configuration.
+    ///
+    /// The server info is validated and cached, so that addresses can later be derived without
+    /// contacting the Ark server (see [`OfflineClient::get_offchain_address`]).
+    ///
+    /// If the Ark server is unreachable but we have connected to it before, the client is built
+    /// from the cached server info instead. Such a client can still produce addresses and report
+    /// cached balances and history, but any operation which needs the server will fail. Only
+    /// transient failures, see [`Error::is_transient`], count as the Ark server being unreachable:
+    /// any other error, e.g. invalid server info, is returned.
+    ///
+    /// Once connected, the out-of-round payments we received while offline are claimed, see
+    /// [`Client::claim_pending_deliveries`].
+    pub async fn connect(mut self) -> Result<Client<B, W, T>, Error> {
+        self.load_birthday()?;
+        self.load_config()?;
+        self.network_client.policy().validate()?;
+
+        let (server_info, server_info_is_live) = match self.fetch_server_info().await {
+            Ok(server_info) => {
+                tracing::debug!(
+                    name = self.name,
+                    ark_server_url = self.network_client.url(),
+                    "Connected to Ark server"
+                );
+
+                self.check_server_policy(&server_info)?;
+                self.check_server_key(self.load_server_info()?.as_ref(), &server_info)?;
+
+                if let Err(e) = self.wallet.store().save_server_info(server_info.clone()) {
+                    tracing::warn!("Failed to cache server info: {e}");
+                }
+
+                (server_info, true)
+            }
+            Err(e) if e.is_transient() => match self.load_server_info()? {
+                Some(server_info) => {
+                    tracing::warn!(
+                        name = self.name,
+                        ark_server_url = self.network_client.url(),
+                        "Ark server unreachable, using cached server info: {e}"
+                    );
+
+                    self.check_server_policy(&server_info)?;
+
+                    // Let the connection be established once the server is reachable again.
+                    self.network_client.connect_lazy()?;
+
+                    (server_info, false)
+                }
+                None => return Err(e),
+            },
+            Err(e) => return Err(e),
+        };
+
+        let client = Client {
+            inner: self,
+            server_info,
+            server_info_checked: AtomicBool::new(server_info_is_live),
+            server_info_is_live,
+            reservations: Arc::default(),
+            operation_journal: OperationJournal::default(),
+            observed: ObservedWallet::default(),
+            maintenance: None,
+        };
+
+        if client.server_info_is_live {
+            if let Err(e) = client.claim_pending_deliveries().await {
+                tracing::warn!("Failed to claim pending out-of-round payments: {e}");
+            }
+        }
+
+        Ok(client)
+    }
+
+    /// Build a [`Client`] without waiting for the Ark server, if we have connected to it before.
+    ///
+    /// The connection is established on the first request, and the client uses the cached server
+    /// info until [`Client::ensure_server_info`] is called. The first operation which needs the
+    /// Ark server, e.g. a payment or a round, checks the cached server info against it first, and
+    /// fails if our addresses would change. If there is no cached server info, it
+    /// is fetched from the Ark server like in [`OfflineClient::connect`].
+    pub async fn connect_lazy(mut self) -> Result<Client<B, W, T>, Error> {
+        self.load_birthday()?;
+        self.load_config()?;
+        self.network_client.policy().validate()?;
+
+        self.network_client.connect_lazy()?;
+
+        let (server_info, server_info_is_live) = match self.load_server_info()? {
+            Some(server_info) => {
+                self.check_server_policy(&server_info)?;
+
+                (server_info, false)
+            }
+            None => {
+                let server_info = self.network_client.get_info().await?;
+                server_info.validate()?;
+                self.check_server_policy(&server_info)?;
+
+                if let Err(e) = self.wallet.store().save_server_info(server_info.clone()) {
+                    tracing::warn!("Failed to cache server info: {e}");
+                }
+
+                (server_info, true)
+            }
+        };
 
         Ok(Client {
             inner: self,
             server_info,
+            server_info_checked: AtomicBool::new(server_info_is_live),
+            server_info_is_live,
+            reservations: Arc::default(),
+            operation_journal: OperationJournal::default(),
+            observed: ObservedWallet::default(),
+            maintenance: None,
         })
     }
-}
 
-impl<B, W> Client<B, W>
-where
-    B: Blockchain,
-    W: BoardingWallet + OnchainWallet,
-{
-    // At the moment we are always generating the same address.
-    pub fn get_offchain_address(&self) -> (ArkAddress, DefaultVtxo) {
-        let server_info = &self.server_info;
+    /// Persist the configured [`WalletBirthday`], or load the persisted one.
+    fn load_birthday(&mut self) -> Result<(), Error> {
+        match self.birthday {
+            Some(birthday) => self.wallet.store().save_birthday(birthday)?,
+            None => self.birthday = self.wallet.store().load_birthday()?,
+        }
+
+        Ok(())
+    }
+
+    async fn fetch_server_info(&mut self) -> Result<server::Info, Error> {
+        self.network_client.connect().await?;
+        let server_info = self.network_client.get_info().await?;
+        server_info.validate()?;
+
+        Ok(server_info)
+    }
+
+    fn check_server_policy(&self, server_info: &server::Info) -> Result<(), Error> {
+        self.server_policy
+            .check(server_info)
+            .map_err(Error::from)
+            .context("Ark server rejected by the server policy")
+    }
+
+    /// Fail if the public key of the Ark server changed since we cached its info, unless the key
+    /// is being reset with [`OfflineClient::with_server_key_reset`].
+    fn check_server_key(
+        &self,
+        cached: Option<&server::Info>,
+        live: &server::Info,
+    ) -> Result<(), Error> {
+        match cached {
+            Some(cached) if cached.pk != live.pk => {
+                if self.reset_server_key {
+                    tracing::warn!(
+                        cached_pk = %cached.pk,
+                        pk = %live.pk,
+                        "Resetting public key of Ark server"
+                    );
+
+                    return Ok(());
+                }
+
+                Err(Error::server_key_changed(cached.pk, live.pk))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The server info cached the last time we connected to the Ark server.
+    ///
+    /// Fails if we have never connected to the Ark server.
+    pub fn cached_server_info(&self) -> Result<server::Info, Error> {
+        self.load_server_info()?.ok_or_else(|| {
+            Error::ad_hoc("no cached server info: must connect to the Ark server at least once")
+        })
+    }
+
+    /// Derive our offchain address from the cached server info, without contacting the Ark
+    /// server.
+    ///
+    /// See [`OfflineClient::cached_server_info`].
+    pub fn get_offchain_address(&self) -> Result<(ArkAddress, DefaultVtxo), Error> {
+        let server_info = self.cached_server_info()?;
 
+        Ok(self.offchain_address(&server_info))
+    }
+
+    /// Derive our boarding address from the cached server info, without contacting the Ark
+    /// server.
+    ///
+    /// See [`OfflineClient::cached_server_info`].
+    pub fn get_boarding_address(&self) -> Result<Address, Error> {
+        let server_info = self.cached_server_info()?;
+
+        self.boarding_address(&server_info)
+    }
+
+    /// Load the cached server info, ignoring it if it cannot be used to derive addresses.
+    fn load_server_info(&self) -> Result<Option<server::Info>, Error> {
+        let server_info = match self.wallet.store().load_server_info()? {
+            Some(server_info) => server_info,
+            None => return Ok(None),
+        };
+
+        if let Err(e) = server_info.validate() {
+            tracing::warn!("Ignoring invalid cached server info: {e}");
+
+            return Ok(None);
+        }
+
+        Ok(Some(server_info))
+    }
+
+    fn offchain_address(&self, server_info: &server::Info) -> (ArkAddress, DefaultVtxo) {
         let (server, _) = server_info.pk.x_only_public_key();
-        let (owner, _) = self.inner.kp.public_key().x_only_public_key();
+        let owner = match &self.ark_signer {
+            Some(ark_signer) => ark_signer.x_only_public_key(),
+            None => self.kp.public_key().x_only_public_key().0,
+        };
 
         let default_vtxo = DefaultVtxo::new(
-            self.secp(),
+            &self.secp,
             server,
             owner,
             server_info.unilateral_exit_delay,
@@ -308,16 +1150,8 @@ where
         (ark_address, default_vtxo)
     }
 
-    pub fn get_offchain_addresses(&self) -> Vec<(ArkAddress, DefaultVtxo)> {
-        let address = self.get_offchain_address();
-
-        vec![address]
-    }
-
-    // At the moment we are always generating the same address.
-    pub fn get_boarding_address(&self) -> Result<Address, Error> {
-        let server_info = &self.server_info;
-        let boarding_output = self.inner.wallet.new_boarding_output(
+    fn boarding_address(&self, server_info: &server::Info) -> Result<Address, Error> {
+        let boarding_output = self.wallet.new_boarding_output(
             server_info.pk.x_only_public_key().0,
             server_info.unilateral_exit_delay,
             &server_info.boarding_descriptor_template,
@@ -326,6 +1160,112 @@ where
 
         Ok(boarding_output.address().clone())
     }
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Make sure that [`Client::server_info`] was fetched from the Ark server, rather than loaded
+    /// from the cache.
+    ///
+    /// Operations which need the Ark server check the cached server info on first use by
+    /// themselves. Call this to also adopt changes which do not affect our addresses, e.g. to the
+    /// fees, on a client built with [`OfflineClient::connect_lazy`], or after the Ark server was
+    /// unreachable during [`OfflineClient::connect`].
+    ///
+    /// Fails if the public key of the Ark server changed since it was cached, see
+    /// [`OfflineClient::with_server_key_reset`].
+    pub async fn ensure_server_info(&mut self) -> Result<&server::Info, Error> {
+        if !self.server_info_is_live {
+            let server_info = self.fetch_live_server_info().await?;
+
+            if let Err(e) = self
+                .inner
+                .wallet
+                .store()
+                .save_server_info(server_info.clone())
+            {
+                tracing::warn!("Failed to cache server info: {e}");
+            }
+
+            self.server_info = server_info;
+            self.server_info_is_live = true;
+            self.server_info_checked.store(true, Ordering::Relaxed);
+        }
+
+        Ok(&self.server_info)
+    }
+
+    /// Check that the cached [`Client::server_info`] still matches the Ark server, the first time
+    /// an operation needs the Ark server.
+    ///
+    /// Fails if the public key of the Ark server changed, or if any other parameter which our
+    /// addresses depend on changed: the new server info must then be adopted with
+    /// [`Client::ensure_server_info`].
+    pub(crate) async fn check_server_info(&self) -> Result<(), Error> {
+        if self.server_info_checked.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let live = self.fetch_live_server_info().await?;
+        let cached = &self.server_info;
+
+        let unchanged = live.pk == cached.pk
+            && live.network == cached.network
+            && live.unilateral_exit_delay == cached.unilateral_exit_delay
+            && live.vtxo_tree_expiry == cached.vtxo_tree_expiry
+            && live.boarding_descriptor_template == cached.boarding_descriptor_template
+            && live.forfeit_address == cached.forfeit_address;
+        if !unchanged {
+            return Err(Error::ad_hoc(
+                "Ark server info changed since it was cached: call `Client::ensure_server_info`",
+            ));
+        }
+
+        self.server_info_checked.store(true, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// The server info according to the Ark server, checked against the server policy and the
+    /// cached public key.
+    async fn fetch_live_server_info(&self) -> Result<server::Info, Error> {
+        let server_info = self.network_client().get_info().await?;
+        server_info.validate()?;
+        self.inner.check_server_policy(&server_info)?;
+        self.inner
+            .check_server_key(Some(&self.server_info), &server_info)?;
+
+        Ok(server_info)
+    }
+
+    // At the moment we are always generating the same address.
+    pub fn get_offchain_address(&self) -> (ArkAddress, DefaultVtxo) {
+        self.inner.offchain_address(&self.server_info)
+    }
+
+    /// Our offchain address, plus every derived address that we watch.
+    ///
+    /// See [`OfflineClient::with_offchain_keychain`].
+    pub fn get_offchain_addresses(&self) -> Vec<(ArkAddress, DefaultVtxo)> {
+        let address = self.get_offchain_address();
+
+        std::iter::once(address)
+            .chain(
+                self.derived_offchain_addresses()
+                    .into_iter()
+                    .map(|(_, vtxo)| (vtxo.to_ark_address(), vtxo)),
+            )
+            .collect()
+    }
+
+    // At the moment we are always generating the same address.
+    pub fn get_boarding_address(&self) -> Result<Address, Error> {
+        self.inner.boarding_address(&self.server_info)
+    }
 
     pub fn get_boarding_addresses(&self) -> Result<Vec<Address>, Error> {
         let address = self.get_boarding_address()?;
@@ -333,6 +1273,39 @@ where
         Ok(vec![address])
     }
 
+    /// Decrypt our secret keys with `passphrase`, if the wallet keeps them encrypted at rest.
+    ///
+    /// Until then, every operation which needs to sign fails with an error for which
+    /// [`Error::is_keys_locked`] holds. Fails if `passphrase` is wrong or the wallet does not
+    /// encrypt its keys.
+    pub fn unlock_keys(&self, passphrase: &str) -> Result<(), Error> {
+        self.inner.wallet.unlock_keys(passphrase)
+    }
+
+    /// Forget the passphrase given to [`Client::unlock_keys`], e.g. when the user walks away.
+    ///
+    /// This only locks the secret keys of the wallet, i.e. those of our boarding outputs. The
+    /// identity keypair [`OfflineClient::kp`], which signs for our VTXOs, stays usable.
+    pub fn lock_keys(&self) -> Result<(), Error> {
+        self.inner.wallet.lock_keys()
+    }
+
+    /// An estimate of how long it will take for the next round to start, based on the scheduling
+    /// information published by the Ark server.
+    ///
+    /// During maintenance set with [`Client::set_maintenance`], it is the time until rounds are
+    /// expected to resume, if known.
+    ///
+    /// This is an upper bound: the next round may start sooner.
+    pub fn next_round_eta(&self) -> std::time::Duration {
+        let now = Timestamp::now().as_second();
+
+        self.maintenance
+            .as_ref()
+            .and_then(|maintenance| maintenance.time_until_end(now))
+            .unwrap_or_else(|| self.server_info.next_round_eta(now))
+    }
+
     pub async fn list_vtxos(&self) -> Result<ListVtxo, Error> {
         let addresses = self.get_offchain_addresses();
 
@@ -343,6 +1316,8 @@ where
 
         for (address, _) in addresses.into_iter() {
             let mut list = self.network_client().list_vtxos(&address).await?;
+            self.add_imported_vtxos(&address, &mut list)?;
+
             vtxos.spendable.append(&mut list.spendable);
             vtxos.spent.append(&mut list.spent);
         }
@@ -356,31 +1331,145 @@ where
         Ok(round)
     }
 
+    /// The VTXO tree of the round with TXID `round_txid`, with our VTXOs marked.
+    ///
+    /// Returns `None` if the Ark server does not know the round.
+    pub async fn round_topology(&self, round_txid: Txid) -> Result<Option<RoundTopology>, Error> {
+        let round = match self.get_round(round_txid.to_string()).await? {
+            Some(round) => round,
+            None => return Ok(None),
+        };
+
+        let our_scripts = self
+            .get_offchain_addresses()
+            .into_iter()
+            .map(|(_, vtxo)| vtxo.script_pubkey())
+            .collect::<Vec<_>>();
+
+        Ok(Some(round_topology(&round, &our_scripts)))
+    }
+
+    /// A [`PaymentProof`] that the VTXO with outpoint `vtxo_outpoint` was created in a round, which
+    /// can be handed to a third party as a receipt.
+    ///
+    /// The proof relies on the Ark server returning the signed VTXO tree of the round; use
+    /// [`PaymentProof::verify`] to check that it is complete.
+    pub async fn payment_proof(&self, vtxo_outpoint: OutPoint) -> Result<PaymentProof, Error> {
+        let ListVtxo { spendable, spent } = self.list_vtxos().await?;
+
+        let vtxo = spendable
+            .into_iter()
+            .chain(spent)
+            .find(|vtxo| vtxo.outpoint == vtxo_outpoint)
+            .ok_or_else(|| Error::ad_hoc(format!("VTXO {vtxo_outpoint} not found")))?;
+
+        let round = self
+            .get_round(vtxo.round_txid.to_string())
+            .await?
+            .ok_or_else(|| Error::ad_hoc(format!("round {} not found", vtxo.round_txid)))?;
+
+        let proof = payment_proof(&round, &vtxo)?;
+
+        Ok(proof)
+    }
+
+    /// Our VTXOs which can currently be spent, excluding those flagged by the [`RiskOracle`] and
+    /// those held for manual review.
+    ///
+    /// This includes the VTXOs reserved with [`Client::reserve`] or registered for a round, see
+    /// [`Client::available_vtxos`] for those which no other operation holds.
     pub async fn spendable_vtxos(&self) -> Result<Vec<(Vec<VtxoOutPoint>, DefaultVtxo)>, Error> {
+        let OffchainVtxos { spendable, .. } = self.fetch_offchain_vtxos().await?;
+
+        let mut unflagged = Vec::new();
+        for (vtxos, vtxo) in spendable.into_iter() {
+            let screened = self.screen_vtxos(vtxos).await?;
+            unflagged.push((screened.spendable, vtxo));
+        }
+
+        Ok(unflagged)
+    }
+
+    /// Like [`Client::spendable_vtxos`], but excluding the VTXOs reserved with
+    /// [`Client::reserve`] or registered for a round, i.e. those which a new operation may spend.
+    pub async fn available_vtxos(&self) -> Result<Vec<(Vec<VtxoOutPoint>, DefaultVtxo)>, Error> {
+        let reserved = self.reservations.reserved_outpoints();
+
+        let unreserved = self
+            .spendable_vtxos()
+            .await?
+            .into_iter()
+            .map(|(vtxos, vtxo)| {
+                let vtxos = vtxos
+                    .into_iter()
+                    .filter(|v| !reserved.contains(&v.outpoint))
+                    .collect();
+
+                (vtxos, vtxo)
+            })
+            .collect();
+
+        Ok(unreserved)
+    }
+
+    /// Our VTXOs which expired and were swept by the Ark server, but which can still be recovered
+    /// with [`Client::recover_swept_vtxos`].
+    pub async fn recoverable_vtxos(&self) -> Result<Vec<(Vec<VtxoOutPoint>, DefaultVtxo)>, Error> {
+        let OffchainVtxos { recoverable, .. } = self.fetch_offchain_vtxos().await?;
+
+        Ok(recoverable)
+    }
+
+    async fn fetch_offchain_vtxos(&self) -> Result<OffchainVtxos, Error> {
         let addresses = self.get_offchain_addresses();
 
         let now = Timestamp::now();
 
         let mut spendable = vec![];
+        let mut recoverable = vec![];
+        let mut freshness = DataFreshness::Live;
+        let mut rounds = HashMap::new();
+        let mut tip = None;
         for (address, vtxo) in addresses.into_iter() {
-            let vtxos = self.network_client().list_vtxos(&address).await?;
-            let explorer_utxos = self.blockchain().find_outpoints(vtxo.address()).await?;
+            let (vtxos, address_freshness) = self.list_vtxos_or_cached(&address).await?;
+            freshness = freshness.merge(address_freshness);
+
+            let explorer_utxos = self.find_our_outpoints(vtxo.address()).await?;
+
+            // We only need the tip of the blockchain if some VTXO was published on-chain.
+            if tip.is_none()
+                && explorer_utxos
+                    .iter()
+                    .any(|u| u.confirmation_height.is_some())
+            {
+                tip = Some(self.chain_tip().await?);
+            }
 
             let mut vtxo_outpoints = Vec::new();
+            let mut recoverable_outpoints = Vec::new();
             for vtxo_outpoint in vtxos.spendable {
-                match explorer_utxos
+                // Expired VTXOs can no longer be spent, but the server lets us reclaim their value
+                // in a later round.
+                let is_expired =
+                    vtxo_outpoint.expire_at > 0 && vtxo_outpoint.expire_at <= now.as_second();
+                if vtxo_outpoint.swept || is_expired {
+                    recoverable_outpoints.push(vtxo_outpoint);
+                    continue;
+                }
+
+                let confirmed = match explorer_utxos
                     .iter()
                     .find(|explorer_utxo| explorer_utxo.outpoint == vtxo_outpoint.outpoint)
                 {
+                    Some(explorer_utxo) => self.confirmed_in(explorer_utxo).await?,
+                    None => None,
+                };
+
+                match confirmed.zip(tip) {
                     // Include VTXOs that have been confirmed on the blockchain, but whose
                     // exit path is still _inactive_.
-                    Some(ExplorerUtxo {
-                        confirmation_blocktime: Some(confirmation_blocktime),
-                        ..
-                    }) if !vtxo.can_be_claimed_unilaterally_by_owner(
-                        now.as_duration().try_into().map_err(Error::ad_hoc)?,
-                        std::time::Duration::from_secs(*confirmation_blocktime),
-                    ) =>
+                    Some((confirmed, tip))
+                        if !vtxo.can_be_claimed_unilaterally_by_owner(confirmed, tip) =>
                     {
                         vtxo_outpoints.push(vtxo_outpoint);
                     }
@@ -392,38 +1481,229 @@ where
                 }
             }
 
-            spendable.push((vtxo_outpoints, vtxo));
+            self.fill_sweepable_after(&mut vtxo_outpoints, &mut rounds)
+                .await;
+
+            spendable.push((vtxo_outpoints, vtxo.clone()));
+            recoverable.push((recoverable_outpoints, vtxo));
         }
 
-        Ok(spendable)
+        // Cached VTXOs may be out of date, so they are not compared with what we saw before.
+        if freshness == DataFreshness::Live {
+            let all_spendable = spendable
+                .iter()
+                .flat_map(|(vtxos, _)| vtxos.clone())
+                .collect::<Vec<_>>();
+            let all_recoverable = recoverable
+                .iter()
+                .flat_map(|(vtxos, _)| vtxos.clone())
+                .collect::<Vec<_>>();
+
+            self.observe_vtxos(&all_spendable, &all_recoverable);
+        }
+
+        Ok(OffchainVtxos {
+            spendable,
+            recoverable,
+            freshness,
+        })
     }
 
+    /// Work out [`VtxoOutPoint::sweepable_after`] from the lifetime of the round, for the VTXOs
+    /// whose expiry the Ark server did not report.
+    ///
+    /// `rounds` caches the result per round. Failures are logged, leaving the field empty.
+    async fn fill_sweepable_after(
+        &self,
+        vtxos: &mut [VtxoOutPoint],
+        rounds: &mut HashMap<Txid, Option<i64>>,
+    ) {
+        for vtxo in vtxos.iter_mut() {
+            if vtxo.sweepable_after.is_some() || vtxo.is_pending {
+                continue;
+            }
+
+            let round_txid = vtxo.round_txid;
+            let sweepable_after = match rounds.get(&round_txid) {
+                Some(sweepable_after) => *sweepable_after,
+                None => {
+                    let sweepable_after = match self.get_round(round_txid.to_string()).await {
+                        Ok(Some(round)) => {
+                            match round.sweepable_after(self.server_info.vtxo_tree_expiry) {
+                                Ok(sweepable_after) => Some(sweepable_after),
+                                Err(e) => {
+                                    tracing::warn!(
+                                        %round_txid,
+                                        "Failed to compute sweep time of round: {e}"
+                                    );
+                                    None
+                                }
+                            }
+                        }
+                        Ok(None) => None,
+                        Err(e) => {
+                            tracing::warn!(%round_txid, "Failed to get round: {e}");
+                            None
+                        }
+                    };
+
+                    rounds.insert(round_txid, sweepable_after);
+
+                    sweepable_after
+                }
+            };
+
+            vtxo.sweepable_after = sweepable_after;
+        }
+    }
+
+    /// The balance of our VTXOs.
+    ///
+    /// If the Ark server is unreachable, the balance is computed from cached VTXOs, which is
+    /// reflected in [`OffChainBalance::freshness`].
     pub async fn offchain_balance(&self) -> Result<OffChainBalance, Error> {
-        let list = self.spendable_vtxos().await?;
-        let sum =
-            list.iter()
+        let OffchainVtxos {
+            spendable,
+            recoverable,
+            freshness,
+        } = self.fetch_offchain_vtxos().await?;
+
+        let mut round_confirmations = HashMap::new();
+        let mut balance = OffChainBalance {
+            recoverable: recoverable
+                .iter()
                 .flat_map(|(vtxos, _)| vtxos)
-                .fold(OffChainBalance::default(), |acc, x| match x.is_pending {
-                    true => OffChainBalance {
-                        pending: acc.pending + x.amount,
-                        ..acc
-                    },
-                    false => OffChainBalance {
-                        confirmed: acc.confirmed + x.amount,
-                        ..acc
-                    },
-                });
+                .fold(Amount::ZERO, |acc, x| acc + x.amount),
+            freshness,
+            ..OffChainBalance::default()
+        };
+        let vtxos = spendable.into_iter().flat_map(|(vtxos, _)| vtxos).collect();
+        let screened = self.screen_vtxos(vtxos).await?;
+
+        let sum = |vtxos: &[VtxoOutPoint]| vtxos.iter().fold(Amount::ZERO, |acc, x| acc + x.amount);
+        balance.flagged = sum(&screened.flagged);
+        balance.pending_review = sum(&screened.pending_review);
+        balance.rejected = sum(&screened.rejected);
+
+        let warn_before = Timestamp::now().as_second() + SWEEP_WARNING_WINDOW.as_secs() as i64;
+        for vtxo in screened.spendable.iter() {
+            match (vtxo.sweepable_after, balance.sweep_warning.as_mut()) {
+                (Some(sweepable_after), _) if sweepable_after > warn_before => {}
+                (Some(sweepable_after), Some(warning)) => {
+                    warning.amount += vtxo.amount;
+                    warning.earliest_sweepable_after =
+                        warning.earliest_sweepable_after.min(sweepable_after);
+                }
+                (Some(sweepable_after), None) => {
+                    balance.sweep_warning = Some(SweepWarning {
+                        amount: vtxo.amount,
+                        earliest_sweepable_after: sweepable_after,
+                    });
+                }
+                (None, _) => {}
+            }
 
-        Ok(sum)
+            if vtxo.is_pending {
+                balance.pending += vtxo.amount;
+                continue;
+            }
+
+            if !self
+                .has_enough_round_confirmations(vtxo, &mut round_confirmations)
+                .await?
+            {
+                balance.awaiting_confirmations += vtxo.amount;
+                continue;
+            }
+
+            balance.confirmed += vtxo.amount;
+        }
+
+        if let Some(warning) = balance.sweep_warning {
+            tracing::warn!(
+                amount = %self.inner.privacy.amount(warning.amount),
+                earliest_sweepable_after = warning.earliest_sweepable_after,
+                "VTXOs can soon be swept by the Ark server, refresh them"
+            );
+        }
+
+        Ok(balance)
     }
 
     pub async fn transaction_history(&self) -> Result<Vec<ArkTransaction>, Error> {
+        let (txs, _) = self.transaction_history_with_freshness().await?;
+
+        Ok(txs)
+    }
+
+    /// Like [`Client::transaction_history`], but falls back to cached VTXOs if the Ark server is
+    /// unreachable, indicating so via the returned [`DataFreshness`].
+    pub async fn transaction_history_with_freshness(
+        &self,
+    ) -> Result<(Vec<ArkTransaction>, DataFreshness), Error> {
+        let (boarding_transactions, boarding_round_transactions) =
+            self.boarding_transaction_history().await?;
+
+        let mut vtxos = ListVtxo {
+            spendable: Vec::new(),
+            spent: Vec::new(),
+        };
+        let mut freshness = DataFreshness::Live;
+        for (address, _) in self.get_offchain_addresses().into_iter() {
+            let (mut list, address_freshness) = self.list_vtxos_or_cached(&address).await?;
+            vtxos.spendable.append(&mut list.spendable);
+            vtxos.spent.append(&mut list.spent);
+            freshness = freshness.merge(address_freshness);
+        }
+
+        let incoming_transactions = generate_incoming_vtxo_transaction_history(
+            &vtxos.spent,
+            &vtxos.spendable,
+            &boarding_round_transactions,
+        )?;
+
+        let outgoing_transactions =
+            generate_outgoing_vtxo_transaction_history(&vtxos.spent, &vtxos.spendable)?;
+
+        let mut txs = [
+            boarding_transactions,
+            incoming_transactions,
+            outgoing_transactions,
+        ]
+        .concat();
+
+        // Transactions which the Ark server no longer reports, see [`Client::backfill_history`].
+        let archived_transactions = self
+            .inner
+            .wallet
+            .store()
+            .load_archived_transactions()?
+            .into_iter()
+            .filter(|archived| {
+                !txs.iter().any(|tx| {
+                    tx.txid() == archived.txid()
+                        && tx.amount().direction == archived.amount().direction
+                })
+            })
+            .collect::<Vec<_>>();
+        txs.extend(archived_transactions);
+
+        txs.sort_by_key(|a| a.created_at());
+
+        Ok((txs, freshness))
+    }
+
+    /// Our boarding transactions, and the TXIDs of the rounds in which we boarded them.
+    async fn boarding_transaction_history(
+        &self,
+    ) -> Result<(Vec<ArkTransaction>, Vec<Txid>), Error> {
         let mut boarding_transactions = Vec::new();
         let mut boarding_round_transactions = Vec::new();
+        let mut confirmed_boarding_outputs = Vec::new();
 
         let boarding_addresses = self.get_boarding_addresses()?;
         for boarding_address in boarding_addresses.iter() {
-            let outpoints = self.blockchain().find_outpoints(boarding_address).await?;
+            let outpoints = self.find_our_outpoints(boarding_address).await?;
 
             for ExplorerUtxo {
                 outpoint,
@@ -434,9 +1714,13 @@ where
             {
                 let confirmed_at = confirmation_blocktime.map(|t| t as i64);
 
+                if confirmed_at.is_some() {
+                    confirmed_boarding_outputs.push((*outpoint, *amount));
+                }
+
                 boarding_transactions.push(ArkTransaction::Boarding {
                     txid: outpoint.txid,
-                    amount: *amount,
+                    amount: TransactionAmount::incoming(*amount),
                     confirmed_at,
                 });
 
@@ -451,30 +1735,84 @@ where
             }
         }
 
-        let vtxos = self.list_vtxos().await?;
+        self.observe_confirmed_boarding_outputs(&confirmed_boarding_outputs);
 
-        let incoming_transactions = generate_incoming_vtxo_transaction_history(
-            &vtxos.spent,
-            &vtxos.spendable,
-            &boarding_round_transactions,
-        )?;
+        Ok((boarding_transactions, boarding_round_transactions))
+    }
 
-        let outgoing_transactions =
-            generate_outgoing_vtxo_transaction_history(&vtxos.spent, &vtxos.spendable)?;
+    /// List the VTXOs of `address` according to the Ark server, caching the result.
+    ///
+    /// If the Ark server cannot be reached, we fall back to the cached VTXOs, if any.
+    async fn list_vtxos_or_cached(
+        &self,
+        address: &ArkAddress,
+    ) -> Result<(ListVtxo, DataFreshness), Error> {
+        let (mut vtxos, freshness) = match self.network_client().list_vtxos(address).await {
+            Ok(vtxos) => {
+                let now = Timestamp::now().as_second();
+                if let Err(e) =
+                    self.inner
+                        .wallet
+                        .store()
+                        .save_vtxo_list(*address, vtxos.clone(), now)
+                {
+                    tracing::warn!(
+                        address = %self.inner.privacy.address(address),
+                        "Failed to cache VTXOs: {e}"
+                    );
+                }
 
-        let mut txs = [
-            boarding_transactions,
-            incoming_transactions,
-            outgoing_transactions,
-        ]
-        .concat();
+                (vtxos, DataFreshness::Live)
+            }
+            Err(e) => match self.inner.wallet.store().load_vtxo_list(address)? {
+                Some((vtxos, updated_at)) => {
+                    tracing::warn!(
+                        address = %self.inner.privacy.address(address),
+                        updated_at,
+                        "Ark server unreachable, using cached VTXOs: {e}"
+                    );
 
-        txs.sort_by_key(|a| a.created_at());
+                    (vtxos, DataFreshness::Cached { updated_at })
+                }
+                None => return Err(e),
+            },
+        };
 
-        Ok(txs)
+        self.add_imported_vtxos(address, &mut vtxos)?;
+
+        Ok((vtxos, freshness))
+    }
+
+    /// Whether the round transaction of `vtxo` has at least as many confirmations as required by
+    /// the client configuration.
+    ///
+    /// Confirmations are cached per round TXID in `cache`, to avoid querying the blockchain
+    /// repeatedly for VTXOs from the same round.
+    async fn has_enough_round_confirmations(
+        &self,
+        vtxo: &VtxoOutPoint,
+        cache: &mut HashMap<Txid, u32>,
+    ) -> Result<bool, Error> {
+        let min_round_confirmations = self.inner.min_round_confirmations;
+        if min_round_confirmations == 0 {
+            return Ok(true);
+        }
+
+        let round_txid = vtxo.round_txid;
+        let confirmations = match cache.get(&round_txid) {
+            Some(confirmations) => *confirmations,
+            None => {
+                let confirmations = self.blockchain().get_confirmations(&round_txid).await?;
+                cache.insert(round_txid, confirmations);
+
+                confirmations
+            }
+        };
+
+        Ok(confirmations >= min_round_confirmations)
     }
 
-    fn network_client(&self) -> ark_grpc::Client {
+    fn network_client(&self) -> RetryingTransport<T> {
         self.inner.network_client.clone()
     }
 
@@ -489,4 +1827,49 @@ where
     fn blockchain(&self) -> &B {
         &self.inner.blockchain
     }
+
+    /// The tip of the blockchain, with its median time past.
+    pub(crate) async fn chain_tip(&self) -> Result<BlockTime, Error> {
+        let height = self.blockchain().get_tip_height().await?;
+        let median_time_past = self.blockchain().get_median_time_past(height).await?;
+
+        Ok(BlockTime::new(
+            height,
+            std::time::Duration::from_secs(median_time_past),
+        ))
+    }
+
+    /// Where `utxo` was confirmed, if it is confirmed: the height of its block and the median
+    /// time past of the block before it, from which time-based exit delays count.
+    pub(crate) async fn confirmed_in(
+        &self,
+        utxo: &ExplorerUtxo,
+    ) -> Result<Option<BlockTime>, Error> {
+        let height = match utxo.confirmation_height {
+            Some(height) => height,
+            None => return Ok(None),
+        };
+
+        let median_time_past = self
+            .blockchain()
+            .get_median_time_past(height.saturating_sub(1))
+            .await?;
+
+        Ok(Some(BlockTime::new(
+            height,
+            std::time::Duration::from_secs(median_time_past),
+        )))
+    }
+
+    /// Find the outpoints of one of our addresses, skipping blocks before our [`WalletBirthday`].
+    async fn find_our_outpoints(&self, address: &Address) -> Result<Vec<ExplorerUtxo>, Error> {
+        match self.inner.birthday {
+            Some(birthday) => {
+                self.blockchain()
+                    .find_outpoints_since(address, birthday)
+                    .await
+            }
+            None => self.blockchain().find_outpoints(address).await,
+        }
+    }
 }
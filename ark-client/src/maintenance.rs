@@ -0,0 +1,103 @@
+//! React to downtime announced by the operator of the Ark server.
+//!
+//! The Ark server does not announce maintenance through its API, so the application passes on
+//! what the operator publishes, e.g. on a status page, with [`Client::set_maintenance`].
+//!
+//! Nothing is detected automatically: the client neither parses maintenance from the Ark server
+//! nor notices that rounds have stopped. Unless the application calls
+//! [`Client::set_maintenance`], the routines below are never paused and no
+//! [`ClientEvent::Maintenance`] is published.
+//!
+//! No rounds take place during maintenance, so the routines which settle our VTXOs in rounds
+//! ([`Client::recover_swept_vtxos`], [`Client::refresh_vtxos_near_expiry`] and
+//! [`Client::sweep_small_vtxos`]) do nothing until it is over, instead of failing.
+
+use crate::notifications::ClientEvent;
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use ark_core::server::Maintenance;
+use jiff::Timestamp;
+
+/// Published as [`ClientEvent::Maintenance`] when the maintenance set with
+/// [`Client::set_maintenance`] changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MaintenanceEvent {
+    /// Maintenance was announced, or the announced one changed.
+    ///
+    /// [`Maintenance::end_time`] is when rounds are expected to resume, if known.
+    Announced(Maintenance),
+    /// Maintenance is no longer announced.
+    Ended,
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Set the maintenance announced by the operator of the Ark server, or clear it with `None`,
+    /// notifying subscribers of [`Client::subscribe`] if anything changed.
+    ///
+    /// Our VTXO maintenance routines pause while it is active and resume once it is over. This is
+    /// the only way the client learns about maintenance, see the [module
+    /// documentation](crate::maintenance).
+    pub fn set_maintenance(&mut self, maintenance: Option<Maintenance>) {
+        if maintenance == self.maintenance {
+            return;
+        }
+
+        let event = match &maintenance {
+            Some(maintenance) => {
+                tracing::warn!(
+                    start_time = maintenance.start_time,
+                    end_time = ?maintenance.end_time,
+                    reason = ?maintenance.reason,
+                    "Ark server maintenance announced"
+                );
+
+                MaintenanceEvent::Announced(maintenance.clone())
+            }
+            None => {
+                tracing::info!("Ark server maintenance is over");
+
+                MaintenanceEvent::Ended
+            }
+        };
+
+        self.maintenance = maintenance;
+
+        self.publish(ClientEvent::Maintenance(event));
+    }
+
+    /// The maintenance set with [`Client::set_maintenance`], if any.
+    pub fn maintenance(&self) -> Option<&Maintenance> {
+        self.maintenance.as_ref()
+    }
+
+    /// Whether rounds are halted for maintenance at the UNIX timestamp `now` in seconds.
+    pub(crate) fn is_under_maintenance(&self, now: i64) -> bool {
+        self.maintenance
+            .as_ref()
+            .is_some_and(|maintenance| maintenance.is_active(now))
+    }
+
+    /// Whether rounds are halted for maintenance, in which case our VTXO maintenance routines
+    /// should not run.
+    pub(crate) fn is_paused_for_maintenance(&self) -> bool {
+        let now = Timestamp::now().as_second();
+        if !self.is_under_maintenance(now) {
+            return false;
+        }
+
+        tracing::info!(
+            end_time = ?self.maintenance.as_ref().and_then(|m| m.end_time),
+            "Ark server is under maintenance, pausing VTXO maintenance"
+        );
+
+        true
+    }
+}
@@ -0,0 +1,44 @@
+use crate::Error;
+use ark_core::server::RoundInput;
+use ark_core::server::RoundOutput;
+use ark_core::server::TxTree;
+use bitcoin::Psbt;
+use bitcoin::Txid;
+
+/// Hooks into the lifecycle of every round that the client joins, e.g. to enforce custom policy or
+/// to log round activity.
+///
+/// Every method has a no-op default, so implementors only need to override the points they care
+/// about. Returning an error from a `before_*` hook stops the client from participating in the
+/// round; such errors are not retried.
+pub trait RoundMiddleware: Send + Sync {
+    /// Called before registering `inputs` and `outputs` for the next round.
+    fn before_registration(
+        &self,
+        inputs: &[RoundInput],
+        outputs: &[RoundOutput],
+    ) -> Result<(), Error> {
+        let _ = (inputs, outputs);
+
+        Ok(())
+    }
+
+    /// Called once the Ark server presents the round transaction and the VTXO tree, before we
+    /// commit to signing them.
+    fn before_signing(
+        &self,
+        round_id: &str,
+        unsigned_round_tx: &Psbt,
+        vtxo_tree: &TxTree,
+    ) -> Result<(), Error> {
+        let _ = (round_id, unsigned_round_tx, vtxo_tree);
+
+        Ok(())
+    }
+
+    /// Called after the round with ID `round_id` was finalized in the round transaction with TXID
+    /// `round_txid`.
+    fn after_finalization(&self, round_id: &str, round_txid: Txid) {
+        let _ = (round_id, round_txid);
+    }
+}
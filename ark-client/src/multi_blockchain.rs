@@ -0,0 +1,513 @@
+//! Spread [`Blockchain`] requests across several providers, failing over between them.
+//!
+//! Lookups and broadcasts are distributed separately, so that e.g. a self-hosted node can handle
+//! all broadcasts while a public explorer takes most of the lookups. Providers need not be of the
+//! same type: an Esplora server can back up a Bitcoin Core node.
+
+use crate::error::ErrorContext;
+use crate::Blockchain;
+use crate::Error;
+use crate::ExplorerUtxo;
+use crate::SpendStatus;
+use crate::WalletBirthday;
+use bitcoin::Address;
+use bitcoin::Transaction;
+use bitcoin::Txid;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use std::future::Future;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// The kind of [`Blockchain`] request, each with its own weights.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RequestKind {
+    Lookup,
+    Broadcast,
+}
+
+/// A [`Blockchain`] whose futures are boxed, so that providers of different types can be stored
+/// together.
+trait DynBlockchain: Send + Sync {
+    fn find_outpoints<'a>(
+        &'a self,
+        address: &'a Address,
+    ) -> BoxFuture<'a, Result<Vec<ExplorerUtxo>, Error>>;
+
+    fn find_tx<'a>(&'a self, txid: &'a Txid) -> BoxFuture<'a, Result<Option<Transaction>, Error>>;
+
+    fn get_output_status<'a>(
+        &'a self,
+        txid: &'a Txid,
+        vout: u32,
+    ) -> BoxFuture<'a, Result<SpendStatus, Error>>;
+
+    fn broadcast<'a>(&'a self, tx: &'a Transaction) -> BoxFuture<'a, Result<(), Error>>;
+
+    fn find_outpoints_since<'a>(
+        &'a self,
+        address: &'a Address,
+        birthday: WalletBirthday,
+    ) -> BoxFuture<'a, Result<Vec<ExplorerUtxo>, Error>>;
+
+    fn get_confirmations<'a>(&'a self, txid: &'a Txid) -> BoxFuture<'a, Result<u32, Error>>;
+
+    fn get_tip_height(&self) -> BoxFuture<'_, Result<u32, Error>>;
+
+    fn get_median_time_past(&self, height: u32) -> BoxFuture<'_, Result<u64, Error>>;
+}
+
+impl<B> DynBlockchain for B
+where
+    B: Blockchain + Send + Sync,
+{
+    fn find_outpoints<'a>(
+        &'a self,
+        address: &'a Address,
+    ) -> BoxFuture<'a, Result<Vec<ExplorerUtxo>, Error>> {
+        Blockchain::find_outpoints(self, address).boxed()
+    }
+
+    fn find_tx<'a>(&'a self, txid: &'a Txid) -> BoxFuture<'a, Result<Option<Transaction>, Error>> {
+        Blockchain::find_tx(self, txid).boxed()
+    }
+
+    fn get_output_status<'a>(
+        &'a self,
+        txid: &'a Txid,
+        vout: u32,
+    ) -> BoxFuture<'a, Result<SpendStatus, Error>> {
+        Blockchain::get_output_status(self, txid, vout).boxed()
+    }
+
+    fn broadcast<'a>(&'a self, tx: &'a Transaction) -> BoxFuture<'a, Result<(), Error>> {
+        Blockchain::broadcast(self, tx).boxed()
+    }
+
+    fn find_outpoints_since<'a>(
+        &'a self,
+        address: &'a Address,
+        birthday: WalletBirthday,
+    ) -> BoxFuture<'a, Result<Vec<ExplorerUtxo>, Error>> {
+        Blockchain::find_outpoints_since(self, address, birthday).boxed()
+    }
+
+    fn get_confirmations<'a>(&'a self, txid: &'a Txid) -> BoxFuture<'a, Result<u32, Error>> {
+        Blockchain::get_confirmations(self, txid).boxed()
+    }
+
+    fn get_tip_height(&self) -> BoxFuture<'_, Result<u32, Error>> {
+        Blockchain::get_tip_height(self).boxed()
+    }
+    fn get_median_time_past(&self, height: u32) -> BoxFuture<'_, Result<u64, Error>> {
+        Blockchain::get_median_time_past(self, height).boxed()
+    }
+}
+
+struct Provider {
+    blockchain: Arc<dyn DynBlockchain>,
+    lookup_weight: u32,
+    broadcast_weight: u32,
+}
+
+impl Provider {
+    fn weight(&self, kind: RequestKind) -> u32 {
+        match kind {
+            RequestKind::Lookup => self.lookup_weight,
+            RequestKind::Broadcast => self.broadcast_weight,
+        }
+    }
+}
+
+/// A [`Blockchain`] backed by several providers, possibly of different types.
+///
+/// Requests are assigned to providers by weighted round-robin: a provider with weight 2 gets
+/// twice as many requests as a provider with weight 1, and a provider with weight 0 is never
+/// used for that kind of request. If the chosen provider fails, the request is retried with the
+/// other providers.
+pub struct MultiBlockchain {
+    providers: Vec<Provider>,
+    lookup_counter: AtomicUsize,
+    broadcast_counter: AtomicUsize,
+    /// Whether the spend status of outputs must be confirmed by every lookup provider.
+    cross_verify_spend_status: bool,
+}
+
+impl MultiBlockchain {
+    pub fn new() -> Self {
+        Self {
+            providers: Vec::new(),
+            lookup_counter: AtomicUsize::new(0),
+            broadcast_counter: AtomicUsize::new(0),
+            cross_verify_spend_status: false,
+        }
+    }
+
+    /// Add a provider, used for lookups and broadcasts according to `lookup_weight` and
+    /// `broadcast_weight` respectively.
+    pub fn with_provider<B>(self, blockchain: B, lookup_weight: u32, broadcast_weight: u32) -> Self
+    where
+        B: Blockchain + Send + Sync + 'static,
+    {
+        self.with_shared_provider(Arc::new(blockchain), lookup_weight, broadcast_weight)
+    }
+
+    /// Like [`MultiBlockchain::with_provider`], for a provider which is also used elsewhere.
+    pub fn with_shared_provider<B>(
+        mut self,
+        blockchain: Arc<B>,
+        lookup_weight: u32,
+        broadcast_weight: u32,
+    ) -> Self
+    where
+        B: Blockchain + Send + Sync + 'static,
+    {
+        self.providers.push(Provider {
+            blockchain,
+            lookup_weight,
+            broadcast_weight,
+        });
+        self
+    }
+
+    /// Ask every lookup provider for the spend status of an output, and fail unless they all
+    /// answer and agree.
+    ///
+    /// The spend status decides whether we react to a VTXO or boarding output being spent, so a
+    /// single faulty or malicious provider should not be trusted with it. This trades availability
+    /// for assurance: if any lookup provider is down, spend statuses cannot be checked.
+    pub fn with_cross_verified_spend_status(mut self) -> Self {
+        self.cross_verify_spend_status = true;
+        self
+    }
+
+    /// The indices of the providers to try for a request of kind `kind`, in order.
+    ///
+    /// The first provider is chosen by weighted round-robin. The other providers with a non-zero
+    /// weight follow, as fallbacks.
+    fn providers_in_order(&self, kind: RequestKind) -> Vec<usize> {
+        let total_weight = self
+            .providers
+            .iter()
+            .map(|provider| provider.weight(kind) as usize)
+            .sum::<usize>();
+
+        if total_weight == 0 {
+            return Vec::new();
+        }
+
+        let counter = match kind {
+            RequestKind::Lookup => &self.lookup_counter,
+            RequestKind::Broadcast => &self.broadcast_counter,
+        };
+        let mut slot = counter.fetch_add(1, Ordering::Relaxed) % total_weight;
+
+        let mut first = 0;
+        for (i, provider) in self.providers.iter().enumerate() {
+            let weight = provider.weight(kind) as usize;
+
+            if slot < weight {
+                first = i;
+                break;
+            }
+
+            slot -= weight;
+        }
+
+        (first..self.providers.len())
+            .chain(0..first)
+            .filter(|i| self.providers[*i].weight(kind) > 0)
+            .collect()
+    }
+
+    async fn with_failover<'a, T, F, Fut>(
+        &'a self,
+        kind: RequestKind,
+        request: &str,
+        f: F,
+    ) -> Result<T, Error>
+    where
+        F: Fn(&'a dyn DynBlockchain) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let mut last_error = None;
+        for i in self.providers_in_order(kind) {
+            match f(self.providers[i].blockchain.as_ref()).await {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    tracing::warn!(provider = i, "Blockchain provider failed to {request}: {e}");
+
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        match last_error {
+            Some(e) => Err(e.context(format!("all Blockchain providers failed to {request}"))),
+            None => Err(Error::ad_hoc(format!(
+                "no Blockchain provider configured to {request}"
+            ))),
+        }
+    }
+
+    async fn cross_verified_output_status(
+        &self,
+        txid: &Txid,
+        vout: u32,
+    ) -> Result<SpendStatus, Error> {
+        let mut agreed: Option<SpendStatus> = None;
+        for (i, provider) in self.providers.iter().enumerate() {
+            if provider.lookup_weight == 0 {
+                continue;
+            }
+
+            let status = provider
+                .blockchain
+                .get_output_status(txid, vout)
+                .await
+                .with_context(|| {
+                    format!("Blockchain provider {i} failed to get status of output {txid}:{vout}")
+                })?;
+
+            match agreed {
+                None => agreed = Some(status),
+                Some(agreed) if agreed.spend_txid != status.spend_txid => {
+                    return Err(Error::ad_hoc(format!(
+                        "Blockchain providers disagree on the status of output {txid}:{vout}: \
+                         spent by {:?} according to one, {:?} according to provider {i}",
+                        agreed.spend_txid, status.spend_txid
+                    )));
+                }
+                Some(_) => {}
+            }
+        }
+
+        agreed.ok_or_else(|| Error::ad_hoc("no Blockchain provider configured for lookups"))
+    }
+}
+
+impl Default for MultiBlockchain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Blockchain for MultiBlockchain {
+    async fn find_outpoints(&self, address: &Address) -> Result<Vec<ExplorerUtxo>, Error> {
+        self.with_failover(RequestKind::Lookup, "find outpoints", |blockchain| {
+            blockchain.find_outpoints(address)
+        })
+        .await
+    }
+
+    async fn find_tx(&self, txid: &Txid) -> Result<Option<Transaction>, Error> {
+        self.with_failover(RequestKind::Lookup, "find transaction", |blockchain| {
+            blockchain.find_tx(txid)
+        })
+        .await
+    }
+
+    async fn get_output_status(&self, txid: &Txid, vout: u32) -> Result<SpendStatus, Error> {
+        if self.cross_verify_spend_status {
+            return self.cross_verified_output_status(txid, vout).await;
+        }
+
+        self.with_failover(RequestKind::Lookup, "get output status", |blockchain| {
+            blockchain.get_output_status(txid, vout)
+        })
+        .await
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> Result<(), Error> {
+        self.with_failover(
+            RequestKind::Broadcast,
+            "broadcast transaction",
+            |blockchain| blockchain.broadcast(tx),
+        )
+        .await
+    }
+
+    async fn find_outpoints_since(
+        &self,
+        address: &Address,
+        birthday: WalletBirthday,
+    ) -> Result<Vec<ExplorerUtxo>, Error> {
+        self.with_failover(RequestKind::Lookup, "find outpoints", |blockchain| {
+            blockchain.find_outpoints_since(address, birthday)
+        })
+        .await
+    }
+
+    async fn get_confirmations(&self, txid: &Txid) -> Result<u32, Error> {
+        self.with_failover(RequestKind::Lookup, "get confirmations", |blockchain| {
+            blockchain.get_confirmations(txid)
+        })
+        .await
+    }
+
+    async fn get_tip_height(&self) -> Result<u32, Error> {
+        self.with_failover(RequestKind::Lookup, "get tip height", |blockchain| {
+            blockchain.get_tip_height()
+        })
+        .await
+    }
+    async fn get_median_time_past(&self, height: u32) -> Result<u64, Error> {
+        self.with_failover(RequestKind::Lookup, "get median time past", |blockchain| {
+            blockchain.get_median_time_past(height)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    /// A provider which reports its `height` as the tip height, or fails if it is down.
+    struct Node {
+        height: u32,
+        down: bool,
+    }
+
+    /// A provider of a different type than [`Node`].
+    struct Explorer {
+        height: u32,
+    }
+
+    impl Blockchain for Node {
+        async fn find_outpoints(&self, _: &Address) -> Result<Vec<ExplorerUtxo>, Error> {
+            unimplemented!()
+        }
+
+        async fn find_tx(&self, _: &Txid) -> Result<Option<Transaction>, Error> {
+            unimplemented!()
+        }
+
+        async fn get_output_status(&self, _: &Txid, _: u32) -> Result<SpendStatus, Error> {
+            unimplemented!()
+        }
+
+        async fn broadcast(&self, _: &Transaction) -> Result<(), Error> {
+            unimplemented!()
+        }
+
+        async fn get_confirmations(&self, _: &Txid) -> Result<u32, Error> {
+            unimplemented!()
+        }
+
+        async fn get_tip_height(&self) -> Result<u32, Error> {
+            if self.down {
+                return Err(Error::ad_hoc("node is down"));
+            }
+
+            Ok(self.height)
+        }
+
+        async fn get_median_time_past(&self, _: u32) -> Result<u64, Error> {
+            unimplemented!()
+        }
+    }
+
+    impl Blockchain for Explorer {
+        async fn find_outpoints(&self, _: &Address) -> Result<Vec<ExplorerUtxo>, Error> {
+            unimplemented!()
+        }
+
+        async fn find_tx(&self, _: &Txid) -> Result<Option<Transaction>, Error> {
+            unimplemented!()
+        }
+
+        async fn get_output_status(&self, _: &Txid, _: u32) -> Result<SpendStatus, Error> {
+            unimplemented!()
+        }
+
+        async fn broadcast(&self, _: &Transaction) -> Result<(), Error> {
+            unimplemented!()
+        }
+
+        async fn get_confirmations(&self, _: &Txid) -> Result<u32, Error> {
+            unimplemented!()
+        }
+
+        async fn get_tip_height(&self) -> Result<u32, Error> {
+            Ok(self.height)
+        }
+
+        async fn get_median_time_past(&self, _: u32) -> Result<u64, Error> {
+            unimplemented!()
+        }
+    }
+
+    fn tip_heights(blockchain: &MultiBlockchain, n: usize) -> Vec<u32> {
+        (0..n)
+            .map(|_| block_on(Blockchain::get_tip_height(blockchain)).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn requests_are_spread_by_weight() {
+        let blockchain = MultiBlockchain::new()
+            .with_provider(
+                Node {
+                    height: 1,
+                    down: false,
+                },
+                2,
+                1,
+            )
+            .with_provider(Explorer { height: 2 }, 1, 0)
+            .with_provider(
+                Node {
+                    height: 3,
+                    down: false,
+                },
+                0,
+                1,
+            );
+
+        assert_eq!(tip_heights(&blockchain, 6), [1, 1, 2, 1, 1, 2]);
+        assert_eq!(
+            blockchain.providers_in_order(RequestKind::Broadcast),
+            [0, 2]
+        );
+        assert_eq!(
+            blockchain.providers_in_order(RequestKind::Broadcast),
+            [2, 0]
+        );
+    }
+
+    #[test]
+    fn failed_requests_fail_over_to_the_other_providers() {
+        let blockchain = MultiBlockchain::new()
+            .with_provider(
+                Node {
+                    height: 1,
+                    down: true,
+                },
+                1,
+                1,
+            )
+            .with_provider(Explorer { height: 2 }, 1, 1);
+
+        assert_eq!(tip_heights(&blockchain, 2), [2, 2]);
+    }
+
+    #[test]
+    fn requests_fail_if_every_provider_fails() {
+        let blockchain = MultiBlockchain::new()
+            .with_provider(
+                Node {
+                    height: 1,
+                    down: true,
+                },
+                1,
+                1,
+            )
+            .with_provider(Explorer { height: 2 }, 0, 1);
+
+        assert!(block_on(Blockchain::get_tip_height(&blockchain)).is_err());
+        assert!(block_on(Blockchain::get_tip_height(&MultiBlockchain::new())).is_err());
+    }
+}
@@ -0,0 +1,405 @@
+//! A single stream of what happens to the wallet, so that wallet UIs can react to changes instead
+//! of polling for our VTXOs and diffing them.
+//!
+//! Events about our VTXOs and boarding outputs are derived from how they changed since the client
+//! last fetched them. [`Client::run_wallet_watcher`] fetches them whenever the Ark server reports
+//! a transaction, and periodically otherwise, to keep these events flowing. Fetching them for any
+//! other reason, e.g. in [`Client::offchain_balance`], reports changes too.
+
+use crate::config::ConfigChanged;
+use crate::delivery::VtxoReceived;
+use crate::maintenance::MaintenanceEvent;
+use crate::risk::VtxoRefunded;
+use crate::round::AutoBoarded;
+use crate::transport::NetworkTransport;
+use crate::utils::sleep;
+use crate::wallet::BoardingWallet;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use crate::OfflineClient;
+use ark_core::server::VtxoOutPoint;
+use bitcoin::Amount;
+use bitcoin::OutPoint;
+use bitcoin::Txid;
+use futures::future;
+use futures::future::Either;
+use futures::stream;
+use futures::Stream;
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::pin::pin;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+/// The number of [`ClientEvent`]s buffered for slow subscribers.
+pub(crate) const CLIENT_EVENTS_CAPACITY: usize = 128;
+
+/// Something that happened to the wallet, see [`Client::subscribe`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientEvent {
+    /// A VTXO was added to our wallet, e.g. by a payment or a round we took part in.
+    VtxoReceived { outpoint: OutPoint, amount: Amount },
+    /// One of our VTXOs is gone, e.g. because we spent it or forfeited it in a round.
+    VtxoSpent { outpoint: OutPoint, amount: Amount },
+    /// An out-of-round payment was claimed with [`Client::claim_pending_deliveries`].
+    PaymentClaimed(VtxoReceived),
+    /// A transaction paying one of our boarding addresses was confirmed, so the output can now be
+    /// boarded.
+    BoardingOutputConfirmed { outpoint: OutPoint, amount: Amount },
+    /// A rejected VTXO was sent back to its sender by [`Client::refund_rejected_vtxos`].
+    VtxoRefunded(Box<VtxoRefunded>),
+    /// Confirmed deposits were boarded by [`Client::auto_board`].
+    AutoBoarded(AutoBoarded),
+    /// We registered for the next round.
+    RoundRegistered,
+    /// We are taking part in a round, which has just started.
+    RoundJoined { round_id: String },
+    /// We signed the VTXO tree of a round we take part in.
+    RoundSigned { round_id: String },
+    /// We signed our forfeit transactions for a round we take part in.
+    RoundForfeitsSigned { round_id: String },
+    /// The round transaction of a round we took part in was broadcast.
+    RoundFinalized { round_id: String, round_txid: Txid },
+    /// A round we took part in failed.
+    RoundFailed { round_id: String, reason: String },
+    /// A transaction of a unilateral exit was broadcast.
+    ExitBroadcast { txid: Txid },
+    /// A transaction of a unilateral exit was rejected for good, so it was dropped from the exit
+    /// together with the transactions spending it.
+    ExitTxRejected { txid: Txid },
+    /// The total of our spendable VTXOs changed.
+    BalanceChanged { previous: Amount, current: Amount },
+    /// The maintenance set with [`Client::set_maintenance`] changed.
+    Maintenance(MaintenanceEvent),
+    /// The configuration was updated with [`Client::update_config`].
+    ConfigChanged(Box<ConfigChanged>),
+}
+
+/// What the client last saw of our VTXOs and boarding outputs, to tell what changed since.
+///
+/// Nothing is reported for the first observation, which only records what already exists.
+#[derive(Default)]
+pub(crate) struct ObservedWallet {
+    /// Our spendable and recoverable VTXOs, and the total of the spendable ones.
+    vtxos: Mutex<Option<(HashMap<OutPoint, Amount>, Amount)>>,
+    confirmed_boarding_outputs: Mutex<Option<HashSet<OutPoint>>>,
+}
+
+impl ObservedWallet {
+    /// Record our current VTXOs, returning the events describing how they changed.
+    ///
+    /// `spendable` must be all our spendable VTXOs, and `recoverable` all our swept or expired
+    /// ones, so that VTXOs which expire are not reported as spent.
+    fn observe_vtxos(
+        &self,
+        spendable: &[VtxoOutPoint],
+        recoverable: &[VtxoOutPoint],
+    ) -> Vec<ClientEvent> {
+        let vtxos = spendable
+            .iter()
+            .chain(recoverable)
+            .map(|vtxo| (vtxo.outpoint, vtxo.amount))
+            .collect::<HashMap<_, _>>();
+        let balance = spendable.iter().map(|vtxo| vtxo.amount).sum::<Amount>();
+
+        let previous = self
+            .vtxos
+            .lock()
+            .expect("lock not poisoned")
+            .replace((vtxos.clone(), balance));
+
+        let Some((previous_vtxos, previous_balance)) = previous else {
+            return Vec::new();
+        };
+
+        let mut events = Vec::new();
+
+        for (outpoint, amount) in vtxos.iter() {
+            if !previous_vtxos.contains_key(outpoint) {
+                events.push(ClientEvent::VtxoReceived {
+                    outpoint: *outpoint,
+                    amount: *amount,
+                });
+            }
+        }
+
+        for (outpoint, amount) in previous_vtxos.iter() {
+            if !vtxos.contains_key(outpoint) {
+                events.push(ClientEvent::VtxoSpent {
+                    outpoint: *outpoint,
+                    amount: *amount,
+                });
+            }
+        }
+
+        if balance != previous_balance {
+            events.push(ClientEvent::BalanceChanged {
+                previous: previous_balance,
+                current: balance,
+            });
+        }
+
+        events
+    }
+
+    /// Record our confirmed boarding outputs, returning an event for each one confirmed since
+    /// they were last observed.
+    ///
+    /// `confirmed` must be all our confirmed boarding outputs, whether spent or not.
+    fn observe_confirmed_boarding_outputs(
+        &self,
+        confirmed: &[(OutPoint, Amount)],
+    ) -> Vec<ClientEvent> {
+        let mut observed = self
+            .confirmed_boarding_outputs
+            .lock()
+            .expect("lock not poisoned");
+
+        let Some(known) = observed.as_mut() else {
+            *observed = Some(confirmed.iter().map(|(outpoint, _)| *outpoint).collect());
+            return Vec::new();
+        };
+
+        confirmed
+            .iter()
+            .filter(|(outpoint, _)| known.insert(*outpoint))
+            .map(|(outpoint, amount)| ClientEvent::BoardingOutputConfirmed {
+                outpoint: *outpoint,
+                amount: *amount,
+            })
+            .collect()
+    }
+}
+
+impl<B, W, T> OfflineClient<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Receive every [`ClientEvent`] from now on.
+    ///
+    /// Subscribe before calling [`OfflineClient::connect`] to be notified of the events which
+    /// happen while connecting, e.g. out-of-round payments claimed.
+    pub fn subscribe(&self) -> impl Stream<Item = ClientEvent> + Send + 'static {
+        receiver_stream(self.client_events.subscribe())
+    }
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Receive every [`ClientEvent`] from now on.
+    ///
+    /// If a subscriber falls behind by more than a few dozen events, the oldest ones are skipped.
+    /// A wallet UI can then refresh its state with [`Client::offchain_balance`].
+    pub fn subscribe(&self) -> impl Stream<Item = ClientEvent> + Send + 'static {
+        receiver_stream(self.inner.client_events.subscribe())
+    }
+
+    pub(crate) fn publish(&self, event: ClientEvent) {
+        // Sending only fails if there are no subscribers.
+        let _ = self.inner.client_events.send(event);
+    }
+
+    /// Keep the [`ClientEvent`]s about our VTXOs and boarding outputs flowing.
+    ///
+    /// Our VTXOs and boarding outputs are fetched whenever the Ark server reports a transaction,
+    /// and every `check_interval` otherwise, e.g. to notice boarding outputs being confirmed, or
+    /// if the Ark server cannot stream transactions. Failures are logged and retried at the next
+    /// check.
+    ///
+    /// Unlike [`Client::transaction_events`], this does not mark any transaction as processed.
+    ///
+    /// This never returns: run it alongside the rest of the application, e.g. with
+    /// `tokio::task::spawn_local` or `tokio::select!`, and drop it to stop watching.
+    pub async fn run_wallet_watcher(&self, check_interval: Duration) {
+        tracing::info!(?check_interval, "Watching the wallet for changes");
+
+        let mut can_stream = true;
+        let mut transactions = None;
+        loop {
+            if let Err(e) = self.observe_wallet().await {
+                tracing::warn!("Failed to check the wallet for changes: {e}");
+            }
+
+            if transactions.is_none() && can_stream {
+                match self.network_client().get_transaction_stream().await {
+                    Ok(Some(stream)) => transactions = Some(stream),
+                    Ok(None) => {
+                        tracing::info!("Ark server cannot stream transactions, polling instead");
+                        can_stream = false;
+                    }
+                    Err(e) => tracing::warn!("Failed to subscribe to transactions: {e}"),
+                }
+            }
+
+            let Some(stream) = transactions.as_mut() else {
+                sleep(check_interval).await;
+                continue;
+            };
+
+            match future::select(stream.next(), pin!(sleep(check_interval))).await {
+                Either::Left((Some(Ok(_)), _)) | Either::Right(_) => {}
+                Either::Left((Some(Err(e)), _)) => {
+                    tracing::warn!("Transaction stream failed, resubscribing: {e}");
+                    transactions = None;
+                }
+                Either::Left((None, _)) => transactions = None,
+            }
+        }
+    }
+
+    /// Fetch our VTXOs and boarding outputs, which reports how they changed.
+    async fn observe_wallet(&self) -> Result<(), Error> {
+        self.fetch_offchain_vtxos().await?;
+        self.boarding_transaction_history().await?;
+
+        Ok(())
+    }
+
+    /// Report how our VTXOs changed since they were last observed, see
+    /// [`ObservedWallet::observe_vtxos`].
+    pub(crate) fn observe_vtxos(&self, spendable: &[VtxoOutPoint], recoverable: &[VtxoOutPoint]) {
+        for event in self.observed.observe_vtxos(spendable, recoverable) {
+            self.publish(event);
+        }
+    }
+
+    /// Report the boarding outputs which were confirmed since they were last observed, see
+    /// [`ObservedWallet::observe_confirmed_boarding_outputs`].
+    pub(crate) fn observe_confirmed_boarding_outputs(&self, confirmed: &[(OutPoint, Amount)]) {
+        for event in self.observed.observe_confirmed_boarding_outputs(confirmed) {
+            self.publish(event);
+        }
+    }
+}
+
+fn receiver_stream(
+    receiver: broadcast::Receiver<ClientEvent>,
+) -> impl Stream<Item = ClientEvent> + Send + 'static {
+    stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => return Some((event, receiver)),
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "Client event subscriber fell behind");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::Hash;
+
+    fn vtxo(vout: u32, amount: u64) -> VtxoOutPoint {
+        VtxoOutPoint {
+            outpoint: OutPoint::new(Txid::all_zeros(), vout),
+            spent: false,
+            round_txid: Txid::all_zeros(),
+            spent_by: None,
+            expire_at: 0,
+            sweepable_after: None,
+            swept: false,
+            is_pending: false,
+            redeem_tx: None,
+            amount: Amount::from_sat(amount),
+            pubkey: String::new(),
+            created_at: 0,
+        }
+    }
+
+    fn sorted(mut events: Vec<ClientEvent>) -> Vec<ClientEvent> {
+        events.sort_by_key(|event| format!("{event:?}"));
+        events
+    }
+
+    #[test]
+    fn first_observation_reports_nothing() {
+        let observed = ObservedWallet::default();
+
+        assert!(observed
+            .observe_vtxos(&[vtxo(0, 1_000)], &[vtxo(1, 500)])
+            .is_empty());
+        assert!(observed
+            .observe_confirmed_boarding_outputs(&[(vtxo(2, 0).outpoint, Amount::ONE_SAT)])
+            .is_empty());
+    }
+
+    #[test]
+    fn vtxo_changes_are_reported_once() {
+        let observed = ObservedWallet::default();
+        observed.observe_vtxos(&[vtxo(0, 1_000), vtxo(1, 2_000)], &[]);
+
+        let events = observed.observe_vtxos(&[vtxo(1, 2_000), vtxo(2, 500)], &[]);
+
+        assert_eq!(
+            sorted(events),
+            sorted(vec![
+                ClientEvent::VtxoReceived {
+                    outpoint: vtxo(2, 0).outpoint,
+                    amount: Amount::from_sat(500),
+                },
+                ClientEvent::VtxoSpent {
+                    outpoint: vtxo(0, 0).outpoint,
+                    amount: Amount::from_sat(1_000),
+                },
+                ClientEvent::BalanceChanged {
+                    previous: Amount::from_sat(3_000),
+                    current: Amount::from_sat(2_500),
+                },
+            ])
+        );
+        assert!(observed
+            .observe_vtxos(&[vtxo(1, 2_000), vtxo(2, 500)], &[])
+            .is_empty());
+    }
+
+    #[test]
+    fn expired_vtxos_are_not_reported_as_spent() {
+        let observed = ObservedWallet::default();
+        observed.observe_vtxos(&[vtxo(0, 1_000), vtxo(1, 2_000)], &[]);
+
+        let events = observed.observe_vtxos(&[vtxo(1, 2_000)], &[vtxo(0, 1_000)]);
+
+        assert_eq!(
+            events,
+            [ClientEvent::BalanceChanged {
+                previous: Amount::from_sat(3_000),
+                current: Amount::from_sat(2_000),
+            }]
+        );
+    }
+
+    #[test]
+    fn boarding_outputs_are_reported_once_confirmed() {
+        let observed = ObservedWallet::default();
+        let first = (vtxo(0, 0).outpoint, Amount::from_sat(10_000));
+        let second = (vtxo(1, 0).outpoint, Amount::from_sat(20_000));
+
+        observed.observe_confirmed_boarding_outputs(&[first]);
+
+        assert_eq!(
+            observed.observe_confirmed_boarding_outputs(&[first, second]),
+            [ClientEvent::BoardingOutputConfirmed {
+                outpoint: second.0,
+                amount: second.1,
+            }]
+        );
+        assert!(observed
+            .observe_confirmed_boarding_outputs(&[first, second])
+            .is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+//! Identify the operations performed by the client, such as sending, boarding, exiting and
+//! refreshing VTXOs.
+
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use jiff::Timestamp;
+use rand::Rng;
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use ulid::Ulid;
+
+/// The number of finished operations kept by [`crate::Client::operation_journal`].
+pub const OPERATION_JOURNAL_CAPACITY: usize = 64;
+
+/// Ties together everything related to a single client operation.
+///
+/// Every operation runs in a tracing span with an `operation_id` field, and the ID is attached to
+/// the [`crate::wallet::VtxoOrigin`] of the VTXOs it creates and to the outcome it returns, so
+/// that logs, persisted state and user reports can be matched up.
+///
+/// IDs are [ULIDs](https://github.com/ulid/spec), so they sort by the time the operation started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OperationId(Ulid);
+
+impl OperationId {
+    /// Generate an ID for a new operation and record it in the current tracing span.
+    pub(crate) fn start() -> Self {
+        let operation_id = Self(new_ulid());
+
+        tracing::Span::current().record("operation_id", tracing::field::display(operation_id));
+
+        operation_id
+    }
+
+    /// The UNIX timestamp in milliseconds at which the operation started.
+    pub fn timestamp_ms(&self) -> u64 {
+        self.0.timestamp_ms()
+    }
+}
+
+impl fmt::Display for OperationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for OperationId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ulid = Ulid::from_string(s)
+            .map_err(|e| Error::ad_hoc(format!("invalid operation ID {s}: {e}")))?;
+
+        Ok(Self(ulid))
+    }
+}
+
+/// A new ULID for the current time.
+///
+/// Built by hand rather than with `Ulid::new`, which needs the `std` feature of `ulid` and with it
+/// a random number generator which does not build for WASM.
+pub(crate) fn new_ulid() -> Ulid {
+    let timestamp_ms = Timestamp::now().as_millisecond() as u64;
+
+    Ulid::from_parts(timestamp_ms, rand::thread_rng().gen())
+}
+
+/// A finished operation, as recorded in the journal of a client, see
+/// [`crate::Client::operation_journal`].
+#[derive(Debug, Clone)]
+pub struct OperationRecord {
+    pub operation_id: OperationId,
+    /// The method of the client which started the operation, e.g. `send_vtxos`.
+    pub name: &'static str,
+    /// The UNIX timestamp in milliseconds at which the operation finished.
+    pub finished_at_ms: u64,
+    /// If the operation failed, the message of the error and of each of its causes, outermost
+    /// first.
+    pub error: Option<Vec<String>>,
+}
+
+/// The last [`OPERATION_JOURNAL_CAPACITY`] operations finished by a client, oldest first.
+///
+/// The journal is only kept in memory.
+#[derive(Default)]
+pub(crate) struct OperationJournal(Mutex<VecDeque<OperationRecord>>);
+
+impl OperationJournal {
+    pub fn record<T>(
+        &self,
+        operation_id: OperationId,
+        name: &'static str,
+        outcome: &Result<T, Error>,
+    ) {
+        let record = OperationRecord {
+            operation_id,
+            name,
+            finished_at_ms: Timestamp::now().as_millisecond() as u64,
+            error: outcome.as_ref().err().map(Error::chain),
+        };
+
+        let mut records = self.0.lock().expect("lock not poisoned");
+        if records.len() == OPERATION_JOURNAL_CAPACITY {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    pub fn records(&self) -> Vec<OperationRecord> {
+        self.0
+            .lock()
+            .expect("lock not poisoned")
+            .iter()
+            .cloned()
+            .collect()
+    }
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// The last [`OPERATION_JOURNAL_CAPACITY`] operations finished since the client connected,
+    /// oldest first, with the errors of those which failed.
+    ///
+    /// Periodic operations, such as [`Client::sweep_small_vtxos`], are left out when they had
+    /// nothing to do.
+    pub fn operation_journal(&self) -> Vec<OperationRecord> {
+        self.operation_journal.records()
+    }
+
+    /// Record the `outcome` of the operation `operation_id` in the journal.
+    pub(crate) fn record_operation<O>(
+        &self,
+        operation_id: OperationId,
+        name: &'static str,
+        outcome: Result<O, Error>,
+    ) -> Result<O, Error> {
+        self.operation_journal.record(operation_id, name, &outcome);
+
+        outcome
+    }
+}
@@ -0,0 +1,144 @@
+//! The stable surface of the client: what most applications need to hold a wallet, check its
+//! balance and history, and send, board and off-board funds.
+//!
+//! ```
+//! use ark_client::prelude::*;
+//! ```
+//!
+//! Everything re-exported here, and the methods of [`Client`] and [`OfflineClient`] listed below,
+//! only change in a breaking way with a new major version. The rest of the crate may still change
+//! between minor versions while the protocol evolves. The stable methods are:
+//!
+//! - [`OfflineClient::new`], [`OfflineClient::connect`] and [`OfflineClient::connect_lazy`].
+//! - [`Client::get_offchain_address`] and [`Client::get_boarding_address`].
+//! - [`Client::offchain_balance`] and [`Client::transaction_history`].
+//! - [`Client::send_vtxo`], [`Client::send_vtxo_with_outcome`] and [`Client::send_on_chain`].
+//! - [`Client::board`] and [`Client::off_board`].
+//!
+//! The tests of this module fail if any of these change. Intentional changes to the list of
+//! re-exports must also be recorded in `api/prelude.txt`.
+
+pub use crate::operation::OperationId;
+pub use crate::wallet::BoardingWallet;
+pub use crate::wallet::OnchainWallet;
+pub use crate::wallet::Persistence;
+pub use crate::Blockchain;
+pub use crate::Client;
+pub use crate::DataFreshness;
+pub use crate::Error;
+pub use crate::ExplorerUtxo;
+pub use crate::OffChainBalance;
+pub use crate::OfflineClient;
+pub use crate::PaymentOutcome;
+pub use crate::SpendStatus;
+pub use ark_core::redeem::ChangeDecision;
+pub use ark_core::ArkAddress;
+pub use ark_core::ArkTransaction;
+
+#[cfg(test)]
+mod tests {
+    /// The re-exports of this module, as recorded in `api/prelude.txt`.
+    const PRELUDE_SNAPSHOT: &str = include_str!("../api/prelude.txt");
+
+    #[test]
+    fn re_exports_match_snapshot() {
+        let re_exports = include_str!("prelude.rs")
+            .lines()
+            .filter_map(|line| line.strip_prefix("pub use "))
+            .map(|path| path.trim_end_matches(';'))
+            .collect::<Vec<_>>();
+
+        let snapshot = PRELUDE_SNAPSHOT
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            re_exports, snapshot,
+            "the re-exports of `ark_client::prelude` changed: if this is intentional, update \
+             `api/prelude.txt` and make sure the change is compatible with the current version"
+        );
+    }
+
+    /// [`OfflineClient::new`] is only available with the `grpc` feature.
+    #[cfg(feature = "grpc")]
+    mod stable_methods {
+        use crate::prelude::*;
+        use bitcoin::key::Keypair;
+        use bitcoin::Address;
+        use bitcoin::Amount;
+        use bitcoin::Psbt;
+        use bitcoin::Txid;
+        use rand::rngs::StdRng;
+        use std::sync::Arc;
+
+        /// Only compiled, never run: it stops compiling if a stable method changes its signature.
+        #[allow(dead_code)]
+        async fn stable_methods<B, W>(
+            blockchain: Arc<B>,
+            wallet: Arc<W>,
+            kp: Keypair,
+            address: ArkAddress,
+            onchain_address: Address,
+            amount: Amount,
+            rng: &mut StdRng,
+        ) -> Result<(), Error>
+        where
+            B: Blockchain,
+            W: BoardingWallet + OnchainWallet,
+        {
+            let offline: OfflineClient<B, W> = OfflineClient::new(
+                "wallet".to_string(),
+                kp,
+                blockchain.clone(),
+                wallet.clone(),
+                String::new(),
+            );
+            let _: Client<B, W> = offline.connect_lazy().await?;
+
+            let offline =
+                OfflineClient::new("wallet".to_string(), kp, blockchain, wallet, String::new());
+            let client: Client<B, W> = offline.connect().await?;
+
+            let (_, _): (ArkAddress, _) = client.get_offchain_address();
+            let _: Address = client.get_boarding_address()?;
+
+            let balance: OffChainBalance = client.offchain_balance().await?;
+            let _: Amount = balance.total();
+            let _: DataFreshness = balance.freshness();
+            let _: Vec<ArkTransaction> = client.transaction_history().await?;
+
+            let _: Psbt = client.send_vtxo(address, amount).await?;
+            let PaymentOutcome {
+                operation_id,
+                redeem_psbt,
+                change,
+            } = client.send_vtxo_with_outcome(address, amount).await?;
+            let _: (OperationId, Psbt, ChangeDecision) = (operation_id, redeem_psbt, change);
+            let _: Txid = client
+                .send_on_chain(onchain_address.clone(), amount)
+                .await?;
+
+            let _: () = client.board(rng).await?;
+            let _: Txid = client.off_board(rng, onchain_address, amount).await?;
+
+            Ok(())
+        }
+
+        /// Only compiled, never run: it stops compiling if a variant is added to or removed from
+        /// [`ChangeDecision`] or [`DataFreshness`].
+        #[allow(dead_code)]
+        fn stable_enums(change: ChangeDecision, freshness: DataFreshness) {
+            match change {
+                ChangeDecision::NoChange
+                | ChangeDecision::Change(_)
+                | ChangeDecision::AbsorbedIntoFee(_)
+                | ChangeDecision::AddedToPayment(_) => {}
+            }
+
+            match freshness {
+                DataFreshness::Live | DataFreshness::Cached { updated_at: _ } => {}
+            }
+        }
+    }
+}
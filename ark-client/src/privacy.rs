@@ -0,0 +1,106 @@
+//! Redaction of sensitive values in logs.
+//!
+//! Addresses and amounts logged by the client can be enough to reconstruct a user's financial
+//! activity. A [`PrivacyConfig`] controls how these values are written to logs, so that debug logs
+//! can be kept in production without leaking them.
+
+use bitcoin::hashes::sha256;
+use bitcoin::hashes::Hash;
+use bitcoin::hex::DisplayHex;
+use bitcoin::Amount;
+use std::fmt;
+
+/// How addresses are written to logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AddressRedaction {
+    /// Log addresses in full.
+    #[default]
+    None,
+    /// Only log the first and last few characters of an address.
+    Truncate,
+    /// Log a short hash of an address instead of the address itself.
+    ///
+    /// The same address always maps to the same hash, so log lines about an address can still be
+    /// correlated.
+    Hash,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrivacyConfig {
+    pub address_redaction: AddressRedaction,
+    /// Whether amounts are written to logs.
+    pub log_amounts: bool,
+}
+
+impl PrivacyConfig {
+    /// Hash addresses and omit amounts.
+    pub fn strict() -> Self {
+        Self {
+            address_redaction: AddressRedaction::Hash,
+            log_amounts: false,
+        }
+    }
+
+    /// The value to log in place of `address`.
+    pub(crate) fn address(&self, address: impl fmt::Display) -> Redacted {
+        let address = address.to_string();
+
+        let redacted = match self.address_redaction {
+            AddressRedaction::None => address,
+            AddressRedaction::Truncate => truncate(&address),
+            AddressRedaction::Hash => {
+                let hash = sha256::Hash::hash(address.as_bytes());
+
+                format!("hash:{}", hash[..8].to_lower_hex_string())
+            }
+        };
+
+        Redacted(redacted)
+    }
+
+    /// The value to log in place of `amount`.
+    pub(crate) fn amount(&self, amount: Amount) -> Redacted {
+        if self.log_amounts {
+            Redacted(amount.to_string())
+        } else {
+            Redacted("<redacted>".to_string())
+        }
+    }
+}
+
+impl Default for PrivacyConfig {
+    /// Log addresses and amounts in full.
+    fn default() -> Self {
+        Self {
+            address_redaction: AddressRedaction::None,
+            log_amounts: true,
+        }
+    }
+}
+
+/// A value which may have been redacted according to a [`PrivacyConfig`].
+pub(crate) struct Redacted(String);
+
+impl fmt::Display for Redacted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+fn truncate(address: &str) -> String {
+    const PREFIX_LEN: usize = 8;
+    const SUFFIX_LEN: usize = 4;
+
+    let n_chars = address.chars().count();
+    if n_chars <= PREFIX_LEN + SUFFIX_LEN {
+        return address.to_string();
+    }
+
+    let prefix = address.chars().take(PREFIX_LEN).collect::<String>();
+    let suffix = address
+        .chars()
+        .skip(n_chars - SUFFIX_LEN)
+        .collect::<String>();
+
+    format!("{prefix}…{suffix}")
+}
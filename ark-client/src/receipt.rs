@@ -0,0 +1,91 @@
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::ClientStore;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use crate::PaymentOutcome;
+use ark_core::receipt::PaymentReceipt;
+use ark_core::ArkAddress;
+use bitcoin::OutPoint;
+use jiff::Timestamp;
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Issue a [`PaymentReceipt`] for the payment to `address` reported by `outcome`, to be
+    /// handed to the recipient.
+    ///
+    /// The receipt is signed with our key and kept, see [`Client::payment_receipts`].
+    pub fn issue_payment_receipt(
+        &self,
+        address: ArkAddress,
+        outcome: &PaymentOutcome,
+    ) -> Result<PaymentReceipt, Error> {
+        let tx = &outcome.redeem_psbt.unsigned_tx;
+        let script_pubkey = address.to_p2tr_script_pubkey();
+
+        let (vout, output) = tx
+            .output
+            .iter()
+            .enumerate()
+            .find(|(_, output)| output.script_pubkey == script_pubkey)
+            .ok_or_else(|| {
+                Error::ad_hoc(format!(
+                    "redeem transaction {} does not pay {address}",
+                    tx.compute_txid()
+                ))
+            })?;
+
+        let receipt = PaymentReceipt::new(
+            self.kp(),
+            address,
+            output.value,
+            OutPoint::new(tx.compute_txid(), vout as u32),
+            Timestamp::now().as_second(),
+        );
+
+        self.inner.wallet.store().save_receipt(receipt.clone())?;
+
+        Ok(receipt)
+    }
+
+    /// Verify and keep a [`PaymentReceipt`] handed to us by the sender of a payment.
+    ///
+    /// The receipt must be signed by its sender and pay one of our addresses.
+    pub fn accept_payment_receipt(&self, receipt: PaymentReceipt) -> Result<(), Error> {
+        receipt.verify()?;
+
+        let script_pubkey = receipt.recipient.to_p2tr_script_pubkey();
+        let is_ours = self
+            .get_offchain_addresses()
+            .iter()
+            .any(|(address, _)| address.to_p2tr_script_pubkey() == script_pubkey);
+        if !is_ours {
+            return Err(Error::ad_hoc(format!(
+                "payment receipt for VTXO {} does not pay us",
+                receipt.vtxo_outpoint
+            )));
+        }
+
+        tracing::info!(
+            vtxo_outpoint = %receipt.vtxo_outpoint,
+            amount = %self.inner.privacy.amount(receipt.amount),
+            "Accepted payment receipt"
+        );
+
+        self.inner.wallet.store().save_receipt(receipt)
+    }
+
+    /// The payment receipts we issued or accepted, oldest first.
+    pub fn payment_receipts(&self) -> Result<Vec<PaymentReceipt>, Error> {
+        let mut receipts = self.inner.wallet.store().load_receipts()?;
+        receipts.sort_by_key(|receipt| receipt.created_at);
+
+        Ok(receipts)
+    }
+}
@@ -0,0 +1,217 @@
+//! Compare our local view of the wallet with the Ark server's records and the chain.
+
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::ClientStore;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use ark_core::server::VtxoOutPoint;
+use ark_core::ArkAddress;
+use bitcoin::Amount;
+use bitcoin::OutPoint;
+use bitcoin::Txid;
+use jiff::Timestamp;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+/// Cached VTXOs older than this are reported as [`Discrepancy::StaleCache`].
+const STALE_CACHE_AGE_SECS: i64 = 24 * 60 * 60;
+
+/// The outcome of [`Client::reconcile`].
+#[derive(Debug, Clone)]
+pub struct ReconciliationReport {
+    /// UNIX timestamp in seconds.
+    pub checked_at: i64,
+    /// The value of our spendable VTXOs according to the cache, before reconciling.
+    pub cached_balance: Amount,
+    /// The value of our spendable VTXOs according to the Ark server.
+    pub live_balance: Amount,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl ReconciliationReport {
+    pub fn is_consistent(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Discrepancy {
+    /// A cached VTXO which the Ark server no longer reports.
+    MissingVtxo { outpoint: OutPoint, amount: Amount },
+    /// A VTXO which was spendable according to the cache, but was spent by a transaction that is
+    /// not linked to any operation recorded by this client.
+    ///
+    /// Payments without change are not recorded, so they are reported here too.
+    UnexpectedSpend {
+        outpoint: OutPoint,
+        amount: Amount,
+        spent_by: Option<Txid>,
+    },
+    /// The cached VTXOs of `address` were last updated at `updated_at`, which is more than a day
+    /// ago.
+    StaleCache {
+        address: ArkAddress,
+        updated_at: i64,
+    },
+    /// The round transaction backing some of our spendable VTXOs is not on chain.
+    MissingRoundTransaction {
+        round_txid: Txid,
+        vtxos: Vec<OutPoint>,
+    },
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Compare the cached VTXOs, and the history and balances derived from them, with the VTXOs
+    /// reported by the Ark server and with the chain.
+    ///
+    /// Afterwards, the cache is updated with the VTXOs reported by the Ark server, so that running
+    /// this periodically reports what changed since the previous run.
+    pub async fn reconcile(&self) -> Result<ReconciliationReport, Error> {
+        let checked_at = Timestamp::now().as_second();
+
+        let mut cached_balance = Amount::ZERO;
+        let mut cached = Vec::new();
+        let mut live = Vec::new();
+        let mut discrepancies = Vec::new();
+        for (address, _) in self.get_offchain_addresses().into_iter() {
+            let list = self.network_client().list_vtxos(&address).await?;
+
+            if let Some((cached_list, updated_at)) =
+                self.inner.wallet.store().load_vtxo_list(&address)?
+            {
+                if checked_at - updated_at > STALE_CACHE_AGE_SECS {
+                    discrepancies.push(Discrepancy::StaleCache {
+                        address,
+                        updated_at,
+                    });
+                }
+
+                cached_balance += cached_list
+                    .spendable
+                    .iter()
+                    .map(|vtxo| vtxo.amount)
+                    .sum::<Amount>();
+
+                cached.extend(cached_list.spendable);
+                cached.extend(cached_list.spent);
+            }
+
+            self.inner
+                .wallet
+                .store()
+                .save_vtxo_list(address, list.clone(), checked_at)?;
+
+            live.extend(list.spendable);
+            live.extend(list.spent);
+        }
+
+        let live_balance = live
+            .iter()
+            .filter(|vtxo| !vtxo.spent)
+            .map(|vtxo| vtxo.amount)
+            .sum::<Amount>();
+
+        let recorded_spends = self.recorded_spends(&live)?;
+        let live_by_outpoint = live
+            .iter()
+            .map(|vtxo| (vtxo.outpoint, vtxo))
+            .collect::<HashMap<_, _>>();
+        for vtxo in cached.iter() {
+            match live_by_outpoint.get(&vtxo.outpoint) {
+                None => discrepancies.push(Discrepancy::MissingVtxo {
+                    outpoint: vtxo.outpoint,
+                    amount: vtxo.amount,
+                }),
+                Some(live_vtxo) if !vtxo.spent && live_vtxo.spent => {
+                    let is_recorded = live_vtxo
+                        .spent_by
+                        .is_some_and(|txid| recorded_spends.contains(&(vtxo.outpoint, txid)));
+
+                    if !is_recorded {
+                        discrepancies.push(Discrepancy::UnexpectedSpend {
+                            outpoint: vtxo.outpoint,
+                            amount: vtxo.amount,
+                            spent_by: live_vtxo.spent_by,
+                        });
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+
+        let mut vtxos_by_round = HashMap::<Txid, Vec<OutPoint>>::new();
+        for vtxo in live.iter().filter(|vtxo| !vtxo.spent) {
+            vtxos_by_round
+                .entry(vtxo.round_txid)
+                .or_default()
+                .push(vtxo.outpoint);
+        }
+        for (round_txid, vtxos) in vtxos_by_round.into_iter() {
+            if self.blockchain().find_tx(&round_txid).await?.is_none() {
+                discrepancies.push(Discrepancy::MissingRoundTransaction { round_txid, vtxos });
+            }
+        }
+
+        for discrepancy in discrepancies.iter() {
+            tracing::warn!(?discrepancy, "Reconciliation found a discrepancy");
+        }
+
+        Ok(ReconciliationReport {
+            checked_at,
+            cached_balance,
+            live_balance,
+            discrepancies,
+        })
+    }
+
+    /// The spends of our VTXOs that we know about, as pairs of spent outpoint and spending TXID.
+    ///
+    /// A spend is known if we signed a forfeit transaction for it, if the spending transaction
+    /// created one of our VTXOs, or if it is part of a unilateral exit.
+    fn recorded_spends(&self, live: &[VtxoOutPoint]) -> Result<HashSet<(OutPoint, Txid)>, Error> {
+        let mut our_txids = live
+            .iter()
+            .map(|vtxo| match vtxo.redeem_tx {
+                Some(_) => vtxo.outpoint.txid,
+                None => vtxo.round_txid,
+            })
+            .collect::<HashSet<_>>();
+        our_txids.extend(
+            self.inner
+                .wallet
+                .store()
+                .load_exit_txs()?
+                .iter()
+                .map(|exit_tx| exit_tx.tx.compute_txid()),
+        );
+
+        let mut recorded = live
+            .iter()
+            .filter_map(|vtxo| {
+                let spent_by = vtxo.spent_by?;
+
+                our_txids
+                    .contains(&spent_by)
+                    .then_some((vtxo.outpoint, spent_by))
+            })
+            .collect::<HashSet<_>>();
+        recorded.extend(
+            self.inner
+                .wallet
+                .store()
+                .load_forfeits()?
+                .iter()
+                .map(|forfeit| (forfeit.vtxo_outpoint, forfeit.round_txid)),
+        );
+
+        Ok(recorded)
+    }
+}
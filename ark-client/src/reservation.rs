@@ -0,0 +1,961 @@
+//! Set VTXOs aside for a payment, so that concurrent operations cannot spend them.
+//!
+//! Operations which cannot be served right away may queue for VTXOs held by others with
+//! [`Client::reserve_with_priority`]. The queue is served by [`ReservationPriority`] and, for equal
+//! priority, in order of arrival.
+
+use crate::error::ErrorContext;
+use crate::operation::new_ulid;
+use crate::transport::NetworkTransport;
+use crate::utils::sleep;
+use crate::wallet::BoardingWallet;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+#[cfg(not(feature = "coin-select-trace"))]
+use ark_core::coin_select::select_vtxos_with_strategy;
+#[cfg(feature = "coin-select-trace")]
+use ark_core::coin_select::select_vtxos_with_trace;
+#[cfg(feature = "coin-select-trace")]
+use ark_core::coin_select::CandidateDecision;
+#[cfg(feature = "coin-select-trace")]
+use ark_core::coin_select::CandidateEvaluation;
+use ark_core::coin_select::CoinSelectionStrategy;
+use ark_core::coin_select::VtxoOutPoint;
+use bitcoin::Amount;
+use bitcoin::OutPoint;
+use futures::future;
+use jiff::Timestamp;
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::future::Future;
+use std::pin::pin;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::Notify;
+use ulid::Ulid;
+
+/// How often an operation queued with [`Client::reserve_with_priority`] tries again, in case we
+/// received new VTXOs in the meantime.
+const RESERVATION_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Identifies a [`Reservation`] made with [`Client::reserve`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ReservationId(Ulid);
+
+impl fmt::Display for ReservationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for ReservationId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ulid = Ulid::from_string(s)
+            .map_err(|e| Error::ad_hoc(format!("invalid reservation ID {s}: {e}")))?;
+
+        Ok(Self(ulid))
+    }
+}
+
+/// VTXOs set aside to pay `amount`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reservation {
+    pub id: ReservationId,
+    /// The amount that the reservation was made for.
+    pub amount: Amount,
+    /// The reserved VTXOs, worth at least `amount`.
+    pub vtxos: Vec<OutPoint>,
+    /// The value of the reserved VTXOs.
+    pub total: Amount,
+    /// How every spendable VTXO was treated when selecting the reserved VTXOs.
+    #[cfg(feature = "coin-select-trace")]
+    pub selection_trace: Vec<CandidateEvaluation>,
+}
+
+/// How urgently an operation queued with [`Client::reserve_with_priority`] needs VTXOs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ReservationPriority {
+    /// Housekeeping which can wait, e.g. consolidating small VTXOs.
+    Background,
+    #[default]
+    Normal,
+    /// Payments which must not be held up by anything else, e.g. payouts.
+    High,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Reservations {
+    inner: Mutex<HashMap<ReservationId, ReservationState>>,
+    queue: Mutex<ReservationQueue>,
+    /// Notified whenever reserved VTXOs are returned to the pool of spendable VTXOs or the queue
+    /// changes.
+    changed: Notify,
+}
+
+/// Operations waiting for VTXOs, see [`Client::reserve_with_priority`].
+#[derive(Debug, Default)]
+struct ReservationQueue {
+    next_ticket: u64,
+    /// Highest priority first and, for equal priority, first come first served.
+    waiting: BTreeSet<QueueKey>,
+}
+
+type QueueKey = (Reverse<ReservationPriority>, u64);
+
+#[derive(Debug)]
+struct ReservationState {
+    reservation: Reservation,
+    /// Whether a payment spending the reservation is in progress.
+    is_spending: bool,
+}
+
+impl Reservations {
+    /// Join the queue of operations waiting for VTXOs. The returned ticket leaves the queue when
+    /// dropped.
+    fn enqueue(&self, priority: ReservationPriority) -> QueueTicket<'_> {
+        let mut queue = self.queue.lock().expect("lock not poisoned");
+
+        let key = (Reverse(priority), queue.next_ticket);
+        queue.next_ticket += 1;
+        queue.waiting.insert(key);
+
+        QueueTicket {
+            reservations: self,
+            key,
+        }
+    }
+
+    /// Wait in the queue with `priority` until it is our turn, then call `try_reserve` until it
+    /// succeeds or `timeout` elapses, see [`Client::reserve_with_priority`].
+    async fn reserve_in_turn<F, Fut>(
+        &self,
+        priority: ReservationPriority,
+        timeout: Duration,
+        mut try_reserve: F,
+    ) -> Result<ReservationId, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<ReservationId, Error>>,
+    {
+        let deadline = Timestamp::now().as_millisecond() + timeout.as_millis() as i64;
+
+        let ticket = self.enqueue(priority);
+
+        loop {
+            // Listen before checking, so that we do not miss a release in between.
+            let mut changed = pin!(self.changed.notified());
+            changed.as_mut().enable();
+
+            let now = Timestamp::now().as_millisecond();
+
+            if ticket.is_next() {
+                match try_reserve().await {
+                    Ok(id) => return Ok(id),
+                    Err(e) => {
+                        if self.reserved_outpoints().is_empty() {
+                            return Err(e);
+                        }
+
+                        if now >= deadline {
+                            return Err(e.context(format!(
+                                "timed out after {timeout:?} waiting for reserved VTXOs"
+                            )));
+                        }
+
+                        tracing::debug!("Waiting for reserved VTXOs to be released: {e}");
+                    }
+                }
+            } else if now >= deadline {
+                return Err(Error::coin_select(format!(
+                    "timed out after {timeout:?} waiting behind other reservations"
+                )));
+            }
+
+            let remaining = Duration::from_millis((deadline - now).max(0) as u64);
+            let retry = pin!(sleep(remaining.min(RESERVATION_RETRY_INTERVAL)));
+
+            future::select(changed, retry).await;
+        }
+    }
+
+    /// Whether VTXOs are reserved, or an operation is waiting for some.
+    pub(crate) fn is_active(&self) -> bool {
+        let is_reserved = !self.inner.lock().expect("lock not poisoned").is_empty();
+
+        is_reserved
+            || !self
+                .queue
+                .lock()
+                .expect("lock not poisoned")
+                .waiting
+                .is_empty()
+    }
+
+    /// Whether an operation with a priority above `priority` is waiting for VTXOs.
+    pub(crate) fn is_queued_above(&self, priority: ReservationPriority) -> bool {
+        let queue = self.queue.lock().expect("lock not poisoned");
+
+        queue
+            .waiting
+            .first()
+            .is_some_and(|(Reverse(first), _)| *first > priority)
+    }
+
+    /// All the VTXOs that are currently reserved.
+    pub(crate) fn reserved_outpoints(&self) -> HashSet<OutPoint> {
+        let reservations = self.inner.lock().expect("lock not poisoned");
+
+        reservations
+            .values()
+            .flat_map(|state| state.reservation.vtxos.iter().copied())
+            .collect()
+    }
+
+    /// Mark the reservation with ID `id` as being spent, returning its VTXOs.
+    ///
+    /// Fails if the reservation does not exist or is already being spent.
+    pub(crate) fn start_spending(&self, id: ReservationId) -> Result<Vec<OutPoint>, Error> {
+        let mut reservations = self.inner.lock().expect("lock not poisoned");
+
+        let state = reservations
+            .get_mut(&id)
+            .ok_or_else(|| Error::ad_hoc(format!("reservation {id} not found")))?;
+
+        if state.is_spending {
+            return Err(Error::ad_hoc(format!(
+                "reservation {id} is already being spent"
+            )));
+        }
+
+        state.is_spending = true;
+
+        Ok(state.reservation.vtxos.clone())
+    }
+
+    /// Conclude a payment started with [`Reservations::start_spending`].
+    ///
+    /// The reservation is removed if the payment went through. Otherwise, its VTXOs stay
+    /// reserved.
+    pub(crate) fn finish_spending(&self, id: ReservationId, is_spent: bool) {
+        let mut reservations = self.inner.lock().expect("lock not poisoned");
+
+        if is_spent {
+            reservations.remove(&id);
+        } else if let Some(state) = reservations.get_mut(&id) {
+            state.is_spending = false;
+        }
+
+        // A payment may have produced change, so queued operations can try again either way.
+        self.changed.notify_waiters();
+    }
+
+    /// Reserve VTXOs worth at least `amount` among `spendable`, skipping those which are already
+    /// reserved.
+    pub(crate) fn reserve(
+        &self,
+        spendable: Vec<VtxoOutPoint>,
+        amount: Amount,
+        dust: Amount,
+        strategy: CoinSelectionStrategy,
+    ) -> Result<Reservation, Error> {
+        let mut reservations = self.inner.lock().expect("lock not poisoned");
+
+        // Another reservation may have been made while the caller was fetching our VTXOs.
+        let reserved = reservations
+            .values()
+            .flat_map(|state| state.reservation.vtxos.iter().copied())
+            .collect::<HashSet<_>>();
+
+        #[cfg(feature = "coin-select-trace")]
+        let excluded = spendable
+            .iter()
+            .filter(|vtxo| reserved.contains(&vtxo.outpoint))
+            .map(|vtxo| CandidateEvaluation {
+                outpoint: vtxo.outpoint,
+                amount: vtxo.amount,
+                expire_at: vtxo.expire_at,
+                rank: None,
+                decision: CandidateDecision::Excluded {
+                    reason: "reserved by another reservation".to_string(),
+                },
+            })
+            .collect::<Vec<_>>();
+
+        let candidates = spendable
+            .into_iter()
+            .filter(|vtxo| !reserved.contains(&vtxo.outpoint))
+            .collect::<Vec<_>>();
+
+        #[cfg(not(feature = "coin-select-trace"))]
+        let selected_coins = select_vtxos_with_strategy(candidates, amount, dust, strategy)
+            .map_err(Error::from)
+            .context("failed to select coins")?;
+
+        #[cfg(feature = "coin-select-trace")]
+        let (selected_coins, selection_trace) = {
+            let (selected_coins, evaluations) =
+                select_vtxos_with_trace(candidates, amount, dust, strategy);
+
+            let selection_trace = evaluations.into_iter().chain(excluded).collect::<Vec<_>>();
+
+            let selected_coins = selected_coins
+                .map_err(Error::from)
+                .context("failed to select coins")?;
+
+            (selected_coins, selection_trace)
+        };
+
+        let id = ReservationId(new_ulid());
+        let reservation = Reservation {
+            id,
+            amount,
+            vtxos: selected_coins.iter().map(|vtxo| vtxo.outpoint).collect(),
+            total: selected_coins.iter().map(|vtxo| vtxo.amount).sum(),
+            #[cfg(feature = "coin-select-trace")]
+            selection_trace,
+        };
+
+        reservations.insert(
+            id,
+            ReservationState {
+                reservation: reservation.clone(),
+                is_spending: false,
+            },
+        );
+
+        Ok(reservation)
+    }
+
+    /// Reserve exactly the VTXOs with outpoints in `vtxos`, each of which must be in `spendable`
+    /// and not reserved yet.
+    pub(crate) fn reserve_exact(
+        &self,
+        spendable: &[(OutPoint, Amount)],
+        vtxos: &[OutPoint],
+    ) -> Result<Reservation, Error> {
+        if vtxos.is_empty() {
+            return Err(Error::ad_hoc("cannot reserve an empty set of VTXOs"));
+        }
+
+        let mut reservations = self.inner.lock().expect("lock not poisoned");
+
+        // Another reservation may have been made while the caller was fetching our VTXOs.
+        let reserved = reservations
+            .values()
+            .flat_map(|state| state.reservation.vtxos.iter().copied())
+            .collect::<HashSet<_>>();
+
+        let mut selected = HashSet::new();
+        let mut total = Amount::ZERO;
+        for outpoint in vtxos.iter() {
+            if !selected.insert(*outpoint) {
+                return Err(Error::ad_hoc(format!("VTXO {outpoint} selected twice")));
+            }
+
+            if reserved.contains(outpoint) {
+                return Err(Error::ad_hoc(format!(
+                    "VTXO {outpoint} is already reserved"
+                )));
+            }
+
+            let (_, amount) = spendable
+                .iter()
+                .find(|(spendable, _)| spendable == outpoint)
+                .ok_or_else(|| {
+                    Error::ad_hoc(format!("VTXO {outpoint} is not one of our spendable VTXOs"))
+                })?;
+
+            total += *amount;
+        }
+
+        let id = ReservationId(new_ulid());
+        let reservation = Reservation {
+            id,
+            amount: total,
+            vtxos: vtxos.to_vec(),
+            total,
+            #[cfg(feature = "coin-select-trace")]
+            selection_trace: Vec::new(),
+        };
+
+        reservations.insert(
+            id,
+            ReservationState {
+                reservation: reservation.clone(),
+                is_spending: false,
+            },
+        );
+
+        Ok(reservation)
+    }
+
+    /// Return the VTXOs of the reservation with ID `id` to the pool of spendable VTXOs.
+    ///
+    /// Fails if the reservation does not exist or is being spent.
+    pub(crate) fn release(&self, id: ReservationId) -> Result<(), Error> {
+        let mut reservations = self.inner.lock().expect("lock not poisoned");
+
+        match reservations.get(&id) {
+            None => Err(Error::ad_hoc(format!("reservation {id} not found"))),
+            Some(state) if state.is_spending => Err(Error::ad_hoc(format!(
+                "cannot release reservation {id} while it is being spent"
+            ))),
+            Some(_) => {
+                reservations.remove(&id);
+
+                self.changed.notify_waiters();
+
+                Ok(())
+            }
+        }
+    }
+
+    /// All reservations, sorted by ID.
+    fn all(&self) -> Vec<Reservation> {
+        let reservations = self.inner.lock().expect("lock not poisoned");
+
+        let mut reservations = reservations
+            .values()
+            .map(|state| state.reservation.clone())
+            .collect::<Vec<_>>();
+        reservations.sort_by_key(|reservation| reservation.id);
+
+        reservations
+    }
+
+    /// Reserve the VTXOs in `vtxos`, worth `total`, while they are registered for a round.
+    ///
+    /// They are returned to the pool of spendable VTXOs when the returned guard is dropped, i.e.
+    /// once the round is over or our registration was withdrawn.
+    pub(crate) fn hold_round_inputs(
+        &self,
+        vtxos: Vec<OutPoint>,
+        total: Amount,
+    ) -> RoundInputsHold<'_> {
+        let id = ReservationId(new_ulid());
+        let reservation = Reservation {
+            id,
+            amount: total,
+            vtxos,
+            total,
+            #[cfg(feature = "coin-select-trace")]
+            selection_trace: Vec::new(),
+        };
+
+        let mut reservations = self.inner.lock().expect("lock not poisoned");
+        reservations.insert(
+            id,
+            ReservationState {
+                reservation,
+                // The round spends them, so they cannot be released by the caller.
+                is_spending: true,
+            },
+        );
+
+        RoundInputsHold {
+            reservations: self,
+            id,
+        }
+    }
+}
+
+/// VTXOs reserved for a round, see [`Reservations::hold_round_inputs`].
+pub(crate) struct RoundInputsHold<'a> {
+    reservations: &'a Reservations,
+    id: ReservationId,
+}
+
+impl Drop for RoundInputsHold<'_> {
+    fn drop(&mut self) {
+        let mut reservations = self.reservations.inner.lock().expect("lock not poisoned");
+        reservations.remove(&self.id);
+
+        self.reservations.changed.notify_waiters();
+
+        tracing::debug!(id = %self.id, "Released VTXOs registered for round");
+    }
+}
+
+/// A place in the queue of operations waiting for VTXOs, see [`Reservations::enqueue`].
+struct QueueTicket<'a> {
+    reservations: &'a Reservations,
+    key: QueueKey,
+}
+
+impl QueueTicket<'_> {
+    /// Whether no other queued operation comes before ours.
+    fn is_next(&self) -> bool {
+        let queue = self.reservations.queue.lock().expect("lock not poisoned");
+
+        queue.waiting.first() == Some(&self.key)
+    }
+}
+
+impl Drop for QueueTicket<'_> {
+    fn drop(&mut self) {
+        let mut queue = self.reservations.queue.lock().expect("lock not poisoned");
+        queue.waiting.remove(&self.key);
+
+        self.reservations.changed.notify_waiters();
+    }
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Set aside VTXOs worth at least `amount`, excluding them from the coin selection of every
+    /// other operation.
+    ///
+    /// VTXOs are selected as configured with
+    /// [`crate::OfflineClient::with_coin_selection_strategy`].
+    ///
+    /// The VTXOs stay reserved until they are spent with [`Client::send_vtxo_with_reservation`]
+    /// or the reservation is released with [`Client::release_reservation`]. Reservations are not
+    /// persisted, so they do not outlive the client.
+    pub async fn reserve(&self, amount: Amount) -> Result<ReservationId, Error> {
+        let spendable_vtxos = self
+            .spendable_vtxos()
+            .await
+            .context("failed to get spendable VTXOs")?;
+
+        let candidates = spendable_vtxos
+            .iter()
+            .flat_map(|(vtxos, _)| vtxos.iter())
+            .map(|vtxo| VtxoOutPoint {
+                outpoint: vtxo.outpoint,
+                expire_at: vtxo.expire_at,
+                amount: vtxo.amount,
+            })
+            .collect::<Vec<_>>();
+
+        let reservation = self.reservations.reserve(
+            candidates,
+            amount,
+            self.server_info.dust,
+            self.inner.coin_selection_strategy,
+        )?;
+
+        #[cfg(feature = "coin-select-trace")]
+        for evaluation in reservation.selection_trace.iter() {
+            tracing::debug!(
+                outpoint = %evaluation.outpoint,
+                amount = %self.inner.privacy.amount(evaluation.amount),
+                expire_at = evaluation.expire_at,
+                rank = ?evaluation.rank,
+                decision = ?evaluation.decision,
+                "Evaluated coin selection candidate"
+            );
+        }
+
+        tracing::debug!(
+            id = %reservation.id,
+            amount = %self.inner.privacy.amount(amount),
+            vtxos = ?reservation.vtxos,
+            "Reserved VTXOs"
+        );
+
+        Ok(reservation.id)
+    }
+
+    /// Like [`Client::reserve`], but if we cannot cover `amount` right away, wait up to `timeout`
+    /// for VTXOs held by other reservations or rounds to be released.
+    ///
+    /// Waiting operations are served by `priority` and, for equal priority, in order of arrival:
+    /// an operation only tries to reserve VTXOs once every operation ahead of it is served or
+    /// gave up. While an operation above [`ReservationPriority::Background`] is waiting,
+    /// [`Client::sweep_small_vtxos`] leaves our VTXOs alone.
+    ///
+    /// Fails with the last error of [`Client::reserve`] once `timeout` elapses, or right away if
+    /// none of our VTXOs are held by others.
+    #[tracing::instrument(skip_all, fields(?priority))]
+    pub async fn reserve_with_priority(
+        &self,
+        amount: Amount,
+        priority: ReservationPriority,
+        timeout: Duration,
+    ) -> Result<ReservationId, Error> {
+        self.reservations
+            .reserve_in_turn(priority, timeout, move || self.reserve(amount))
+            .await
+    }
+
+    /// Like [`Client::reserve`], but reserving exactly the VTXOs with outpoints in `vtxos`, e.g.
+    /// as picked by the user from [`Client::list_spendable_outpoints`].
+    ///
+    /// Fails if any of them is not one of our spendable VTXOs or is already reserved.
+    pub async fn reserve_vtxos(&self, vtxos: &[OutPoint]) -> Result<ReservationId, Error> {
+        let spendable_vtxos = self
+            .spendable_vtxos()
+            .await
+            .context("failed to get spendable VTXOs")?;
+
+        let spendable = spendable_vtxos
+            .iter()
+            .flat_map(|(vtxos, _)| vtxos.iter())
+            .map(|vtxo| (vtxo.outpoint, vtxo.amount))
+            .collect::<Vec<_>>();
+
+        let reservation = self.reservations.reserve_exact(&spendable, vtxos)?;
+
+        tracing::debug!(
+            id = %reservation.id,
+            vtxos = ?reservation.vtxos,
+            "Reserved selected VTXOs"
+        );
+
+        Ok(reservation.id)
+    }
+
+    /// Return the VTXOs of the reservation with ID `id` to the pool of spendable VTXOs.
+    ///
+    /// Fails if a payment spending the reservation is in progress.
+    pub fn release_reservation(&self, id: ReservationId) -> Result<(), Error> {
+        self.reservations.release(id)?;
+
+        tracing::debug!(%id, "Released reservation");
+
+        Ok(())
+    }
+
+    /// All reservations which have been neither spent nor released.
+    pub fn reservations(&self) -> Vec<Reservation> {
+        self.reservations.all()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use bitcoin::Txid;
+
+    const DUST: Amount = Amount::from_sat(330);
+
+    fn outpoint(vout: u32) -> OutPoint {
+        OutPoint::new(Txid::all_zeros(), vout)
+    }
+
+    /// `n` VTXOs worth 1_000 sats each.
+    fn vtxos(n: u32) -> Vec<VtxoOutPoint> {
+        (0..n)
+            .map(|vout| VtxoOutPoint {
+                outpoint: outpoint(vout),
+                expire_at: 1_000 + vout as i64,
+                amount: Amount::from_sat(1_000),
+            })
+            .collect()
+    }
+
+    /// Reserve enough for a payment that a single one of [`vtxos`] covers.
+    fn reserve(
+        reservations: &Reservations,
+        spendable: Vec<VtxoOutPoint>,
+    ) -> Result<Reservation, Error> {
+        reservations.reserve(
+            spendable,
+            Amount::from_sat(500),
+            DUST,
+            CoinSelectionStrategy::default(),
+        )
+    }
+
+    #[test]
+    fn reservations_do_not_overlap() {
+        let reservations = Reservations::default();
+
+        let first = reserve(&reservations, vtxos(2)).unwrap();
+        let second = reserve(&reservations, vtxos(2)).unwrap();
+
+        assert_eq!(first.vtxos.len(), 1);
+        assert_eq!(second.vtxos.len(), 1);
+        assert_ne!(first.vtxos, second.vtxos);
+        assert_eq!(
+            reservations.reserved_outpoints(),
+            HashSet::from([outpoint(0), outpoint(1)])
+        );
+
+        assert!(reserve(&reservations, vtxos(2)).is_err());
+    }
+
+    #[test]
+    fn concurrent_reservations_do_not_share_vtxos() {
+        let reservations = Reservations::default();
+
+        let (first, second) = std::thread::scope(|s| {
+            let first = s.spawn(|| reserve(&reservations, vtxos(1)));
+            let second = s.spawn(|| reserve(&reservations, vtxos(1)));
+
+            (first.join().unwrap(), second.join().unwrap())
+        });
+
+        assert!(first.is_ok() != second.is_ok());
+        assert_eq!(reservations.all().len(), 1);
+    }
+
+    #[test]
+    fn released_vtxos_can_be_reserved_again() {
+        let reservations = Reservations::default();
+
+        let reservation = reserve(&reservations, vtxos(1)).unwrap();
+        assert!(reserve(&reservations, vtxos(1)).is_err());
+
+        reservations.release(reservation.id).unwrap();
+
+        assert!(reservations.reserved_outpoints().is_empty());
+        assert!(reservations.release(reservation.id).is_err());
+
+        let reservation = reserve(&reservations, vtxos(1)).unwrap();
+        assert_eq!(reservation.vtxos, vec![outpoint(0)]);
+    }
+
+    #[test]
+    fn reservation_cannot_be_released_while_being_spent() {
+        let reservations = Reservations::default();
+
+        let reservation = reserve(&reservations, vtxos(1)).unwrap();
+        reservations.start_spending(reservation.id).unwrap();
+
+        assert!(reservations.release(reservation.id).is_err());
+        assert_eq!(
+            reservations.reserved_outpoints(),
+            HashSet::from([outpoint(0)])
+        );
+    }
+
+    #[test]
+    fn reservation_cannot_be_spent_by_two_payments() {
+        let reservations = Reservations::default();
+
+        let reservation = reserve(&reservations, vtxos(1)).unwrap();
+
+        assert_eq!(
+            reservations.start_spending(reservation.id).unwrap(),
+            vec![outpoint(0)]
+        );
+        assert!(reservations.start_spending(reservation.id).is_err());
+    }
+
+    #[test]
+    fn failed_payment_keeps_vtxos_reserved() {
+        let reservations = Reservations::default();
+
+        let reservation = reserve(&reservations, vtxos(1)).unwrap();
+        reservations.start_spending(reservation.id).unwrap();
+        reservations.finish_spending(reservation.id, false);
+
+        assert_eq!(
+            reservations.reserved_outpoints(),
+            HashSet::from([outpoint(0)])
+        );
+        assert!(reserve(&reservations, vtxos(1)).is_err());
+
+        // Once the payment is over, the reservation can be spent again or released.
+        reservations.start_spending(reservation.id).unwrap();
+        reservations.finish_spending(reservation.id, false);
+        reservations.release(reservation.id).unwrap();
+
+        assert!(reservations.reserved_outpoints().is_empty());
+    }
+
+    #[test]
+    fn spent_reservation_is_removed() {
+        let reservations = Reservations::default();
+
+        let reservation = reserve(&reservations, vtxos(1)).unwrap();
+        reservations.start_spending(reservation.id).unwrap();
+        reservations.finish_spending(reservation.id, true);
+
+        assert!(reservations.all().is_empty());
+        assert!(reservations.release(reservation.id).is_err());
+    }
+
+    #[test]
+    fn round_inputs_are_released_on_drop() {
+        let reservations = Reservations::default();
+
+        let hold = reservations.hold_round_inputs(vec![outpoint(0)], Amount::from_sat(1_000));
+
+        assert!(reserve(&reservations, vtxos(1)).is_err());
+        assert!(reservations.is_active());
+
+        drop(hold);
+
+        assert!(!reservations.is_active());
+        assert!(reserve(&reservations, vtxos(1)).is_ok());
+    }
+
+    #[test]
+    fn exact_reservation_rejects_conflicts() {
+        let reservations = Reservations::default();
+        let spendable = vtxos(3)
+            .into_iter()
+            .map(|vtxo| (vtxo.outpoint, vtxo.amount))
+            .collect::<Vec<_>>();
+
+        let reservation = reservations
+            .reserve_exact(&spendable, &[outpoint(0), outpoint(1)])
+            .unwrap();
+        assert_eq!(reservation.total, Amount::from_sat(2_000));
+
+        // Empty, already reserved, selected twice and not ours.
+        assert!(reservations.reserve_exact(&spendable, &[]).is_err());
+        assert!(reservations
+            .reserve_exact(&spendable, &[outpoint(1)])
+            .is_err());
+        assert!(reservations
+            .reserve_exact(&spendable, &[outpoint(2), outpoint(2)])
+            .is_err());
+        assert!(reservations
+            .reserve_exact(&spendable, &[outpoint(3)])
+            .is_err());
+
+        // A coin-selected reservation cannot take them either.
+        let reservation = reserve(&reservations, vtxos(3)).unwrap();
+        assert_eq!(reservation.vtxos, vec![outpoint(2)]);
+    }
+
+    /// Run `future` to completion on a runtime with timers, as [`Reservations::reserve_in_turn`]
+    /// needs.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    /// Queue for the single VTXO of [`vtxos`] with `priority`, recording `name` in `served` once
+    /// we get it and then handing it over to the next waiter.
+    async fn wait_for_vtxo(
+        reservations: &Reservations,
+        served: &Mutex<Vec<&'static str>>,
+        name: &'static str,
+        priority: ReservationPriority,
+    ) {
+        let id = reservations
+            .reserve_in_turn(priority, Duration::from_secs(5), move || async move {
+                reserve(reservations, vtxos(1)).map(|reservation| reservation.id)
+            })
+            .await
+            .unwrap();
+
+        served.lock().unwrap().push(name);
+
+        reservations.release(id).unwrap();
+    }
+
+    /// Release the reservation with ID `id` after a moment, once every waiter has joined the
+    /// queue.
+    async fn release_later(reservations: &Reservations, id: ReservationId) {
+        sleep(Duration::from_millis(20)).await;
+
+        reservations.release(id).unwrap();
+    }
+
+    #[test]
+    fn higher_priority_is_served_first() {
+        let reservations = Reservations::default();
+        let served = Mutex::new(Vec::new());
+
+        let blocker = reserve(&reservations, vtxos(1)).unwrap();
+
+        block_on(future::join3(
+            wait_for_vtxo(
+                &reservations,
+                &served,
+                "normal",
+                ReservationPriority::Normal,
+            ),
+            wait_for_vtxo(&reservations, &served, "high", ReservationPriority::High),
+            release_later(&reservations, blocker.id),
+        ));
+
+        assert_eq!(served.into_inner().unwrap(), vec!["high", "normal"]);
+    }
+
+    #[test]
+    fn equal_priority_is_served_in_order_of_arrival() {
+        let reservations = Reservations::default();
+        let served = Mutex::new(Vec::new());
+
+        let blocker = reserve(&reservations, vtxos(1)).unwrap();
+
+        block_on(future::join4(
+            wait_for_vtxo(&reservations, &served, "first", ReservationPriority::Normal),
+            wait_for_vtxo(
+                &reservations,
+                &served,
+                "second",
+                ReservationPriority::Normal,
+            ),
+            wait_for_vtxo(&reservations, &served, "third", ReservationPriority::Normal),
+            release_later(&reservations, blocker.id),
+        ));
+
+        assert_eq!(
+            served.into_inner().unwrap(),
+            vec!["first", "second", "third"]
+        );
+    }
+
+    #[test]
+    fn dropped_waiter_does_not_block_the_queue() {
+        let reservations = Reservations::default();
+        let served = Mutex::new(Vec::new());
+
+        let blocker = reserve(&reservations, vtxos(1)).unwrap();
+
+        // The high priority waiter gives up before the VTXO is released.
+        let impatient = async {
+            let waiter = pin!(wait_for_vtxo(
+                &reservations,
+                &served,
+                "high",
+                ReservationPriority::High
+            ));
+            let give_up = pin!(sleep(Duration::from_millis(10)));
+
+            future::select(waiter, give_up).await;
+        };
+
+        block_on(future::join3(
+            wait_for_vtxo(
+                &reservations,
+                &served,
+                "normal",
+                ReservationPriority::Normal,
+            ),
+            impatient,
+            release_later(&reservations, blocker.id),
+        ));
+
+        assert_eq!(served.into_inner().unwrap(), vec!["normal"]);
+        assert!(!reservations.is_active());
+    }
+
+    #[test]
+    fn waiter_times_out_if_nothing_is_released() {
+        let reservations = Reservations::default();
+
+        reserve(&reservations, vtxos(1)).unwrap();
+
+        let result = block_on(reservations.reserve_in_turn(
+            ReservationPriority::High,
+            Duration::from_millis(20),
+            || async { reserve(&reservations, vtxos(1)).map(|reservation| reservation.id) },
+        ));
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,488 @@
+//! Retrying requests to the Ark server which fail for transient reasons, e.g. a dropped
+//! connection.
+//!
+//! Every [`NetworkTransport`] request made by the client is retried according to the
+//! [`RetryPolicy`] set with [`crate::OfflineClient::with_retry_policy`]. Only errors for which
+//! [`Error::is_transient`] holds are retried: the Ark server rejecting a request is reported
+//! straight away. Custom transports mark their transient errors with
+//! [`Error::ark_server_transient`].
+
+use crate::transport::NetworkStream;
+use crate::transport::NetworkTransport;
+use crate::utils::sleep;
+use crate::Error;
+use ark_core::server::Info;
+use ark_core::server::ListVtxo;
+use ark_core::server::Round;
+use ark_core::server::RoundInput;
+use ark_core::server::RoundOutput;
+use ark_core::server::RoundStreamEvent;
+use ark_core::server::TransactionEvent;
+use ark_core::ArkAddress;
+use backon::ExponentialBuilder;
+use backon::Retryable;
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::Psbt;
+use futures::Future;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A request made to the Ark server, see [`NetworkTransport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NetworkCall {
+    GetInfo,
+    ListVtxos,
+    GetRound,
+    RegisterInputs,
+    RegisterOutputs,
+    SubmitRedeemTransaction,
+    Ping,
+    SubmitTreeNonces,
+    SubmitTreeSignatures,
+    SubmitSignedForfeitTxs,
+    GetEventStream,
+    GetTransactionStream,
+}
+
+/// How many times, and how often, a failed request is retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    max_retries: usize,
+    min_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+}
+
+impl Backoff {
+    /// Retry up to `max_retries` times, with an exponential backoff between `min_delay` and
+    /// `max_delay`.
+    ///
+    /// A random jitter is added to each delay, so that clients which lost their connection at the
+    /// same time do not all reconnect at once.
+    pub fn new(max_retries: usize, min_delay: Duration, max_delay: Duration) -> Self {
+        Self {
+            max_retries,
+            min_delay,
+            max_delay,
+            jitter: true,
+        }
+    }
+
+    /// Never retry.
+    pub fn never() -> Self {
+        Self::new(0, Duration::ZERO, Duration::ZERO)
+    }
+
+    /// Wait for exactly the exponential backoff between retries, without jitter.
+    pub fn without_jitter(mut self) -> Self {
+        self.jitter = false;
+        self
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if self.min_delay > self.max_delay {
+            return Err(Error::ad_hoc(format!(
+                "invalid retry policy: minimum delay {:?} exceeds maximum delay {:?}",
+                self.min_delay, self.max_delay
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn builder(&self) -> ExponentialBuilder {
+        let builder = ExponentialBuilder::default()
+            .with_max_times(self.max_retries)
+            .with_min_delay(self.min_delay)
+            .with_max_delay(self.max_delay);
+
+        if self.jitter {
+            builder.with_jitter()
+        } else {
+            builder
+        }
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(3, Duration::from_millis(200), Duration::from_secs(5))
+    }
+}
+
+/// How requests to the Ark server which fail for transient reasons are retried.
+///
+/// By default, every request is retried with [`Backoff::default`], except for:
+///
+/// - The requests made while signing a round ([`NetworkCall::SubmitTreeNonces`],
+///   [`NetworkCall::SubmitTreeSignatures`] and [`NetworkCall::SubmitSignedForfeitTxs`]) and
+///   [`NetworkCall::Ping`]. A round only waits for signatures for a few seconds, so these are never
+///   retried: the whole round is retried instead, see [`crate::round::RoundRetryPolicy`].
+/// - The requests which are not idempotent ([`NetworkCall::SubmitRedeemTransaction`] and
+///   [`NetworkCall::RegisterInputs`]). If the connection drops
+///   after the Ark server processed one of these, making it again fails because the VTXOs are
+///   already spent or registered. The caller must check what happened instead, e.g. by listing its
+///   VTXOs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    default: Backoff,
+    overrides: HashMap<NetworkCall, Backoff>,
+}
+
+impl RetryPolicy {
+    /// Retry every request with `default`.
+    pub fn new(default: Backoff) -> Self {
+        Self {
+            default,
+            overrides: HashMap::new(),
+        }
+    }
+
+    /// Never retry any request.
+    pub fn disabled() -> Self {
+        Self::new(Backoff::never())
+    }
+
+    /// Retry `call` with `backoff`, instead of the default backoff of this policy.
+    pub fn with_override(mut self, call: NetworkCall, backoff: Backoff) -> Self {
+        self.overrides.insert(call, backoff);
+        self
+    }
+
+    /// The backoff used to retry `call`.
+    pub fn backoff(&self, call: NetworkCall) -> Backoff {
+        self.overrides.get(&call).copied().unwrap_or(self.default)
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        self.default.validate()?;
+
+        for backoff in self.overrides.values() {
+            backoff.validate()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(Backoff::default())
+            .with_override(NetworkCall::Ping, Backoff::never())
+            .with_override(NetworkCall::SubmitTreeNonces, Backoff::never())
+            .with_override(NetworkCall::SubmitTreeSignatures, Backoff::never())
+            .with_override(NetworkCall::SubmitSignedForfeitTxs, Backoff::never())
+            .with_override(NetworkCall::SubmitRedeemTransaction, Backoff::never())
+            .with_override(NetworkCall::RegisterInputs, Backoff::never())
+    }
+}
+
+/// A [`NetworkTransport`] which retries the requests of `inner` according to a [`RetryPolicy`].
+#[derive(Clone)]
+pub(crate) struct RetryingTransport<T> {
+    inner: T,
+    policy: Arc<RetryPolicy>,
+}
+
+impl<T> RetryingTransport<T>
+where
+    T: NetworkTransport,
+{
+    pub(crate) fn new(inner: T, policy: RetryPolicy) -> Self {
+        Self {
+            inner,
+            policy: Arc::new(policy),
+        }
+    }
+
+    pub(crate) fn set_policy(&mut self, policy: RetryPolicy) {
+        self.policy = Arc::new(policy);
+    }
+
+    pub(crate) fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    async fn retry<R, F, Fut>(&self, call: NetworkCall, request: F) -> Result<R, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<R, Error>>,
+    {
+        retry(&self.policy, call, request).await
+    }
+}
+
+/// Make `request` until it succeeds, fails for a reason which is not transient, or `policy` gives
+/// up on `call`.
+async fn retry<R, F, Fut>(policy: &RetryPolicy, call: NetworkCall, request: F) -> Result<R, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<R, Error>>,
+{
+    request
+        .retry(policy.backoff(call).builder())
+        .sleep(sleep)
+        .when(Error::is_transient)
+        .notify(|err: &Error, dur: Duration| {
+            tracing::warn!(
+                ?call,
+                "Retrying request to Ark server after {dur:?}. Error: {err}"
+            );
+        })
+        .await
+}
+
+impl<T> NetworkTransport for RetryingTransport<T>
+where
+    T: NetworkTransport,
+{
+    fn url(&self) -> &str {
+        self.inner.url()
+    }
+
+    fn set_url(&mut self, url: String) {
+        self.inner.set_url(url);
+    }
+
+    async fn connect(&mut self) -> Result<(), Error> {
+        self.inner.connect().await
+    }
+
+    fn connect_lazy(&mut self) -> Result<(), Error> {
+        self.inner.connect_lazy()
+    }
+
+    async fn get_info(&self) -> Result<Info, Error> {
+        self.retry(NetworkCall::GetInfo, || self.inner.get_info())
+            .await
+    }
+
+    async fn list_vtxos(&self, address: &ArkAddress) -> Result<ListVtxo, Error> {
+        self.retry(NetworkCall::ListVtxos, || self.inner.list_vtxos(address))
+            .await
+    }
+
+    async fn get_round(&self, round_txid: String) -> Result<Option<Round>, Error> {
+        self.retry(NetworkCall::GetRound, || {
+            self.inner.get_round(round_txid.clone())
+        })
+        .await
+    }
+
+    async fn register_inputs_for_next_round(&self, inputs: &[RoundInput]) -> Result<String, Error> {
+        self.retry(NetworkCall::RegisterInputs, || {
+            self.inner.register_inputs_for_next_round(inputs)
+        })
+        .await
+    }
+
+    async fn register_outputs_for_next_round(
+        &self,
+        request_id: String,
+        outputs: &[RoundOutput],
+        cosigner_pks: &[PublicKey],
+        signing_all: bool,
+    ) -> Result<(), Error> {
+        self.retry(NetworkCall::RegisterOutputs, || {
+            self.inner.register_outputs_for_next_round(
+                request_id.clone(),
+                outputs,
+                cosigner_pks,
+                signing_all,
+            )
+        })
+        .await
+    }
+
+    async fn submit_redeem_transaction(&self, redeem_psbt: Psbt) -> Result<Psbt, Error> {
+        self.retry(NetworkCall::SubmitRedeemTransaction, || {
+            self.inner.submit_redeem_transaction(redeem_psbt.clone())
+        })
+        .await
+    }
+
+    async fn ping(&self, request_id: String) -> Result<(), Error> {
+        self.retry(NetworkCall::Ping, || self.inner.ping(request_id.clone()))
+            .await
+    }
+
+    async fn submit_tree_nonces(
+        &self,
+        round_id: &str,
+        cosigner_pk: PublicKey,
+        pub_nonce_tree: Vec<Vec<Option<zkp::MusigPubNonce>>>,
+    ) -> Result<(), Error> {
+        self.retry(NetworkCall::SubmitTreeNonces, || {
+            self.inner
+                .submit_tree_nonces(round_id, cosigner_pk, pub_nonce_tree.clone())
+        })
+        .await
+    }
+
+    async fn submit_tree_signatures(
+        &self,
+        round_id: &str,
+        cosigner_pk: PublicKey,
+        partial_sig_tree: Vec<Vec<Option<zkp::MusigPartialSignature>>>,
+    ) -> Result<(), Error> {
+        self.retry(NetworkCall::SubmitTreeSignatures, || {
+            self.inner
+                .submit_tree_signatures(round_id, cosigner_pk, partial_sig_tree.clone())
+        })
+        .await
+    }
+
+    async fn submit_signed_forfeit_txs(
+        &self,
+        signed_forfeit_txs: Vec<Psbt>,
+        signed_round_psbt: Option<Psbt>,
+    ) -> Result<(), Error> {
+        self.retry(NetworkCall::SubmitSignedForfeitTxs, || {
+            self.inner
+                .submit_signed_forfeit_txs(signed_forfeit_txs.clone(), signed_round_psbt.clone())
+        })
+        .await
+    }
+
+    async fn get_event_stream(
+        &self,
+    ) -> Result<NetworkStream<'static, Result<RoundStreamEvent, Error>>, Error> {
+        self.retry(NetworkCall::GetEventStream, || {
+            self.inner.get_event_stream()
+        })
+        .await
+    }
+
+    async fn get_transaction_stream(
+        &self,
+    ) -> Result<Option<NetworkStream<'static, Result<TransactionEvent, Error>>>, Error> {
+        self.retry(NetworkCall::GetTransactionStream, || {
+            self.inner.get_transaction_stream()
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorContext;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    /// Make a request with `policy`, failing with the first errors of `errors` before succeeding.
+    /// Returns the outcome and how many times the request was made.
+    fn make_request(
+        policy: &RetryPolicy,
+        call: NetworkCall,
+        errors: Vec<fn() -> Error>,
+    ) -> (Result<(), Error>, usize) {
+        let attempts = AtomicUsize::new(0);
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let result = runtime.block_on(retry(policy, call, || async {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+
+            match errors.get(attempt) {
+                Some(error) => Err(error()),
+                None => Ok(()),
+            }
+        }));
+
+        (result, attempts.into_inner())
+    }
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy::new(
+            Backoff::new(3, Duration::from_millis(1), Duration::from_millis(1)).without_jitter(),
+        )
+    }
+
+    fn transient() -> Error {
+        Error::ark_server_transient("connection reset")
+    }
+
+    fn rejected() -> Error {
+        Error::ark_server("invalid request")
+    }
+
+    #[test]
+    fn transient_errors_are_retried() {
+        let (result, attempts) = make_request(
+            &fast_policy(),
+            NetworkCall::GetInfo,
+            vec![transient, transient],
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn retries_are_bounded() {
+        let (result, attempts) = make_request(
+            &fast_policy(),
+            NetworkCall::GetInfo,
+            vec![transient, transient, transient, transient, transient],
+        );
+
+        assert!(result.unwrap_err().is_transient());
+        assert_eq!(attempts, 4);
+    }
+
+    #[test]
+    fn rejections_are_not_retried() {
+        let (result, attempts) = make_request(&fast_policy(), NetworkCall::GetInfo, vec![rejected]);
+
+        assert!(!result.unwrap_err().is_transient());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn non_idempotent_requests_are_not_retried_by_default() {
+        for call in [
+            NetworkCall::SubmitRedeemTransaction,
+            NetworkCall::RegisterInputs,
+            NetworkCall::SubmitTreeNonces,
+            NetworkCall::SubmitTreeSignatures,
+            NetworkCall::SubmitSignedForfeitTxs,
+            NetworkCall::Ping,
+        ] {
+            assert_eq!(
+                RetryPolicy::default().backoff(call),
+                Backoff::never(),
+                "{call:?}"
+            );
+        }
+
+        assert_eq!(
+            RetryPolicy::default().backoff(NetworkCall::ListVtxos),
+            Backoff::default()
+        );
+
+        let (result, attempts) = make_request(
+            &RetryPolicy::default(),
+            NetworkCall::SubmitRedeemTransaction,
+            vec![transient],
+        );
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn is_transient_looks_through_context() {
+        assert!(transient().is_transient());
+        assert!(transient().context("failed to get info").is_transient());
+
+        assert!(!rejected().is_transient());
+        assert!(!Error::wallet("disk full").is_transient());
+        assert!(!Error::wallet("disk full")
+            .context(rejected())
+            .is_transient());
+    }
+}
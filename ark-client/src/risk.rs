@@ -0,0 +1,332 @@
+use crate::notifications::ClientEvent;
+use crate::operation::OperationId;
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::ClientStore;
+use crate::wallet::OnchainWallet;
+use crate::wallet::VtxoOrigin;
+use crate::wallet::VtxoRiskStatus;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use crate::OffchainVtxos;
+use ark_core::redeem;
+use ark_core::redeem::refund_address;
+use ark_core::server::VtxoOutPoint;
+use ark_core::ArkAddress;
+use bitcoin::Amount;
+use bitcoin::OutPoint;
+use bitcoin::Txid;
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+/// A source of risk assessments for incoming VTXOs, e.g. a chain-analysis service.
+///
+/// The oracle is consulted once for every VTXO of ours which does not have a
+/// [`VtxoRiskStatus`] yet, except for the change of our own payments. The resulting status is
+/// persisted, so that the decision is stable and can be reviewed later via
+/// [`Client::flagged_vtxos`].
+pub trait RiskOracle: Send + Sync {
+    /// Assess `vtxo`. This is asynchronous so that the oracle can query a remote service.
+    fn assess<'a>(&'a self, vtxo: &'a VtxoOutPoint)
+        -> BoxFuture<'a, Result<RiskAssessment, Error>>;
+}
+
+/// Published as [`ClientEvent::VtxoRefunded`] for every rejected VTXO sent back to its sender by
+/// [`Client::refund_rejected_vtxos`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VtxoRefunded {
+    pub outpoint: OutPoint,
+    pub amount: Amount,
+    /// The address of the sender, see [`refund_address`].
+    pub refund_address: ArkAddress,
+    pub redeem_txid: Txid,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RiskAssessment {
+    Accept,
+    /// Exclude the VTXO from coin selection until it is released with
+    /// [`Client::release_flagged_vtxo`].
+    Flag {
+        reason: String,
+    },
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// All VTXOs that were flagged by the [`RiskOracle`] and have not been released yet, together
+    /// with the reason they were flagged for.
+    pub fn flagged_vtxos(&self) -> Result<Vec<(OutPoint, String)>, Error> {
+        let statuses = self.inner.wallet.store().load_vtxo_risk_statuses()?;
+
+        let flagged = statuses
+            .into_iter()
+            .filter_map(|(outpoint, status)| match status {
+                VtxoRiskStatus::Flagged { reason } => Some((outpoint, reason)),
+                VtxoRiskStatus::Accepted
+                | VtxoRiskStatus::Released { .. }
+                | VtxoRiskStatus::PendingReview
+                | VtxoRiskStatus::Rejected { .. } => None,
+            })
+            .collect();
+
+        Ok(flagged)
+    }
+
+    /// Allow a flagged VTXO to be spent again, after it has been reviewed.
+    pub fn release_flagged_vtxo(&self, outpoint: OutPoint) -> Result<(), Error> {
+        let statuses = self.inner.wallet.store().load_vtxo_risk_statuses()?;
+
+        let reason = statuses
+            .into_iter()
+            .find_map(|(o, status)| match status {
+                VtxoRiskStatus::Flagged { reason } if o == outpoint => Some(reason),
+                _ => None,
+            })
+            .ok_or_else(|| Error::ad_hoc(format!("VTXO {outpoint} is not flagged")))?;
+
+        tracing::info!(%outpoint, reason, "Releasing flagged VTXO");
+
+        self.inner
+            .wallet
+            .store()
+            .save_vtxo_risk_status(outpoint, VtxoRiskStatus::Released { reason })
+    }
+
+    /// All received VTXOs awaiting manual review, see [`crate::OfflineClient::with_manual_review`].
+    pub fn vtxos_pending_review(&self) -> Result<Vec<OutPoint>, Error> {
+        let statuses = self.inner.wallet.store().load_vtxo_risk_statuses()?;
+
+        let pending = statuses
+            .into_iter()
+            .filter_map(|(outpoint, status)| {
+                (status == VtxoRiskStatus::PendingReview).then_some(outpoint)
+            })
+            .collect();
+
+        Ok(pending)
+    }
+
+    /// Accept a VTXO awaiting manual review, allowing it to be spent.
+    pub fn accept_vtxo(&self, outpoint: OutPoint) -> Result<(), Error> {
+        self.ensure_pending_review(outpoint)?;
+
+        tracing::info!(%outpoint, "Accepting VTXO after review");
+
+        self.inner
+            .wallet
+            .store()
+            .save_vtxo_risk_status(outpoint, VtxoRiskStatus::Accepted)
+    }
+
+    /// Reject a VTXO awaiting manual review, so that it is never spent by us.
+    ///
+    /// With [`crate::OfflineClient::with_rejected_vtxo_refunds`], the VTXO is then sent back to
+    /// its sender by [`Client::refund_rejected_vtxos`].
+    pub fn reject_vtxo(&self, outpoint: OutPoint, reason: String) -> Result<(), Error> {
+        self.ensure_pending_review(outpoint)?;
+
+        tracing::info!(%outpoint, reason, "Rejecting VTXO after review");
+
+        self.inner
+            .wallet
+            .store()
+            .save_vtxo_risk_status(outpoint, VtxoRiskStatus::Rejected { reason })
+    }
+
+    /// Send the VTXOs we rejected with [`Client::reject_vtxo`] back to their senders, if enabled
+    /// with [`crate::OfflineClient::with_rejected_vtxo_refunds`].
+    ///
+    /// Call this periodically. Every refund is published as [`ClientEvent::VtxoRefunded`].
+    ///
+    /// Only VTXOs received out of round can be refunded: the sender is whoever owned the VTXO spent
+    /// by the redeem transaction which created ours, see [`refund_address`]. VTXOs received in a
+    /// round do not reveal their sender, so they stay rejected and returning the funds must be
+    /// arranged with the sender out of band. The fee of the refund is deducted from the refunded
+    /// amount.
+    pub async fn refund_rejected_vtxos(&self) -> Result<Vec<VtxoRefunded>, Error> {
+        if !self.inner.refund_rejected_vtxos {
+            return Ok(Vec::new());
+        }
+
+        let rejected = self
+            .inner
+            .wallet
+            .store()
+            .load_vtxo_risk_statuses()?
+            .into_iter()
+            .filter_map(|(outpoint, status)| {
+                matches!(status, VtxoRiskStatus::Rejected { .. }).then_some(outpoint)
+            })
+            .collect::<HashSet<_>>();
+        if rejected.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.check_server_info().await?;
+
+        let server = self.server_info.pk.x_only_public_key().0;
+
+        let OffchainVtxos { spendable, .. } = self.fetch_offchain_vtxos().await?;
+
+        let mut refunded = Vec::new();
+        for (vtxos, vtxo) in spendable.into_iter() {
+            for virtual_tx_outpoint in vtxos.into_iter().filter(|v| rejected.contains(&v.outpoint))
+            {
+                let outpoint = virtual_tx_outpoint.outpoint;
+
+                let Some(redeem_psbt) = virtual_tx_outpoint.redeem_tx.as_ref() else {
+                    tracing::debug!(%outpoint, "Cannot refund rejected VTXO received in a round");
+                    continue;
+                };
+
+                let refund_address =
+                    match refund_address(redeem_psbt, self.server_info.network, server) {
+                        Ok(refund_address) => refund_address,
+                        Err(e) => {
+                            tracing::warn!(%outpoint, "Cannot refund rejected VTXO: {e}");
+                            continue;
+                        }
+                    };
+
+                let amount = virtual_tx_outpoint.amount;
+                let vtxo_input = redeem::VtxoInput::new(vtxo.clone(), amount, outpoint);
+
+                let operation_id = OperationId::start();
+                let outcome = self
+                    .spend_vtxo_inputs(operation_id, &[vtxo_input], &[(refund_address, amount)])
+                    .await;
+                let outcome =
+                    match self.record_operation(operation_id, "refund_rejected_vtxo", outcome) {
+                        Ok(outcome) => outcome,
+                        Err(e) => {
+                            tracing::warn!(%outpoint, "Failed to refund rejected VTXO: {e}");
+                            continue;
+                        }
+                    };
+
+                let event = VtxoRefunded {
+                    outpoint,
+                    amount,
+                    refund_address,
+                    redeem_txid: outcome.redeem_psbt.unsigned_tx.compute_txid(),
+                };
+
+                tracing::info!(
+                    %outpoint,
+                    amount = %self.inner.privacy.amount(amount),
+                    refund_address = %self.inner.privacy.address(refund_address),
+                    redeem_txid = %event.redeem_txid,
+                    "Refunded rejected VTXO"
+                );
+
+                self.publish(ClientEvent::VtxoRefunded(Box::new(event)));
+
+                refunded.push(event);
+            }
+        }
+
+        Ok(refunded)
+    }
+
+    fn ensure_pending_review(&self, outpoint: OutPoint) -> Result<(), Error> {
+        let statuses = self.inner.wallet.store().load_vtxo_risk_statuses()?;
+
+        statuses
+            .into_iter()
+            .find(|(o, status)| *o == outpoint && *status == VtxoRiskStatus::PendingReview)
+            .map(|_| ())
+            .ok_or_else(|| Error::ad_hoc(format!("VTXO {outpoint} is not pending review")))
+    }
+
+    /// Split `vtxos` into those that may be spent and those that may not be spent yet, or ever.
+    ///
+    /// VTXOs without a persisted [`VtxoRiskStatus`] are assessed by the [`RiskOracle`], if one is
+    /// configured, unless they are the change of our own payments. If manual review is enabled,
+    /// VTXOs which the oracle did not flag and which did not originate from one of our own
+    /// operations are then held for review.
+    pub(crate) async fn screen_vtxos(
+        &self,
+        vtxos: Vec<VtxoOutPoint>,
+    ) -> Result<ScreenedVtxos, Error> {
+        let statuses = self
+            .inner
+            .wallet
+            .store()
+            .load_vtxo_risk_statuses()?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+
+        let mut screened = ScreenedVtxos::default();
+        for vtxo in vtxos.into_iter() {
+            let status = match statuses.get(&vtxo.outpoint) {
+                Some(status) => Some(status.clone()),
+                None => {
+                    let origin = self.inner.wallet.store().load_vtxo_origin(&vtxo.outpoint)?;
+
+                    let status = match (&self.inner.risk_oracle, &origin) {
+                        // Our change only ever held our own funds.
+                        (
+                            Some(_),
+                            Some(VtxoOrigin::SendChange { .. } | VtxoOrigin::OffBoardChange { .. }),
+                        ) => Some(VtxoRiskStatus::Accepted),
+                        (Some(risk_oracle), _) => match risk_oracle.assess(&vtxo).await? {
+                            RiskAssessment::Accept => Some(VtxoRiskStatus::Accepted),
+                            RiskAssessment::Flag { reason } => {
+                                tracing::warn!(outpoint = %vtxo.outpoint, reason, "VTXO flagged");
+
+                                Some(VtxoRiskStatus::Flagged { reason })
+                            }
+                        },
+                        (None, _) => None,
+                    };
+
+                    let status = match status {
+                        Some(VtxoRiskStatus::Flagged { reason }) => {
+                            Some(VtxoRiskStatus::Flagged { reason })
+                        }
+                        _ if self.inner.manual_review && origin.is_none() => {
+                            tracing::info!(outpoint = %vtxo.outpoint, "VTXO held for review");
+
+                            Some(VtxoRiskStatus::PendingReview)
+                        }
+                        status => status,
+                    };
+
+                    if let Some(status) = &status {
+                        self.inner
+                            .wallet
+                            .store()
+                            .save_vtxo_risk_status(vtxo.outpoint, status.clone())?;
+                    }
+
+                    status
+                }
+            };
+
+            match status {
+                Some(VtxoRiskStatus::Flagged { .. }) => screened.flagged.push(vtxo),
+                Some(VtxoRiskStatus::PendingReview) => screened.pending_review.push(vtxo),
+                Some(VtxoRiskStatus::Rejected { .. }) => screened.rejected.push(vtxo),
+                _ => screened.spendable.push(vtxo),
+            }
+        }
+
+        Ok(screened)
+    }
+}
+
+/// VTXOs split by whether they may be spent, see [`Client::screen_vtxos`].
+#[derive(Default)]
+pub(crate) struct ScreenedVtxos {
+    pub spendable: Vec<VtxoOutPoint>,
+    pub flagged: Vec<VtxoOutPoint>,
+    pub pending_review: Vec<VtxoOutPoint>,
+    pub rejected: Vec<VtxoOutPoint>,
+}
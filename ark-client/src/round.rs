@@ -1,62 +1,291 @@
 use crate::error::ErrorContext;
+use crate::fees::FeeOperation;
+use crate::notifications::ClientEvent;
+use crate::operation::OperationId;
+use crate::reservation::ReservationPriority;
+use crate::round_handle::RoundHandle;
+use crate::signer::ArkSigner;
+use crate::transport::NetworkTransport;
 use crate::utils::sleep;
 use crate::utils::spawn;
+use crate::utils::until;
 use crate::wallet::BoardingWallet;
+use crate::wallet::ClientStore;
+use crate::wallet::ForfeitRecord;
 use crate::wallet::OnchainWallet;
+use crate::wallet::VtxoOrigin;
 use crate::Blockchain;
 use crate::Client;
 use crate::Error;
-use crate::ExplorerUtxo;
+use ark_core::fees::charged_round_fee;
 use ark_core::round;
-use ark_core::round::create_and_sign_forfeit_txs;
-use ark_core::round::generate_nonce_tree;
+use ark_core::round::create_and_sign_forfeit_txs_with;
+use ark_core::round::forfeit_txs_sighashes;
+use ark_core::round::prepare_round_psbt;
+use ark_core::round::round_psbt_sighashes;
 use ark_core::round::sign_round_psbt;
-use ark_core::round::sign_vtxo_tree;
-use ark_core::round::NonceTree;
+use ark_core::round::verify_round_psbt_signatures;
+use ark_core::round::verify_vtxo_tree;
 use ark_core::round::PubNonceTree;
+use ark_core::round::VtxoTreeSigningSession;
 use ark_core::server::RoundInput;
 use ark_core::server::RoundOutput;
+use ark_core::server::RoundOutputAddress;
 use ark_core::server::RoundStreamEvent;
-use ark_core::server::TxTree;
 use ark_core::ArkAddress;
 use backon::ExponentialBuilder;
 use backon::Retryable;
 use bitcoin::key::Keypair;
 use bitcoin::secp256k1;
 use bitcoin::secp256k1::schnorr;
+use bitcoin::secp256k1::PublicKey;
 use bitcoin::Address;
 use bitcoin::Amount;
-use bitcoin::Psbt;
+use bitcoin::OutPoint;
 use bitcoin::Txid;
 use bitcoin::XOnlyPublicKey;
+use futures::future;
+use futures::future::Either;
 use futures::FutureExt;
 use futures::StreamExt;
 use jiff::Timestamp;
 use rand::CryptoRng;
 use rand::Rng;
 use std::collections::HashMap;
+use std::pin::pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How many times we resubscribe to the round event stream while taking part in a single round.
+const MAX_ROUND_EVENT_STREAM_RECONNECTS: usize = 5;
+
+/// How often, and how fast, to register again for the next round after a round we joined was
+/// aborted by the Ark server.
+///
+/// Other failures are not retried, since joining the next round would most likely fail in the same
+/// way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoundRetryPolicy {
+    max_retries: usize,
+    min_delay: Duration,
+    max_delay: Duration,
+}
+
+impl RoundRetryPolicy {
+    /// Retry up to `max_retries` times, with an exponential backoff between `min_delay` and
+    /// `max_delay`.
+    pub fn new(max_retries: usize, min_delay: Duration, max_delay: Duration) -> Self {
+        Self {
+            max_retries,
+            min_delay,
+            max_delay,
+        }
+    }
+
+    /// Never retry.
+    pub fn disabled() -> Self {
+        Self::new(0, Duration::ZERO, Duration::ZERO)
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        if self.min_delay > self.max_delay {
+            return Err(Error::ad_hoc(format!(
+                "invalid round retry policy: minimum delay {:?} exceeds maximum delay {:?}",
+                self.min_delay, self.max_delay
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn backoff(&self) -> ExponentialBuilder {
+        ExponentialBuilder::default()
+            .with_max_times(self.max_retries)
+            .with_min_delay(self.min_delay)
+            .with_max_delay(self.max_delay)
+    }
+}
+
+impl Default for RoundRetryPolicy {
+    fn default() -> Self {
+        Self::new(3, Duration::from_secs(1), Duration::from_secs(60))
+    }
+}
+
+/// How long each phase of a round may take before we give up on it, so that a stalled Ark server
+/// cannot make [`Client::board`] or [`Client::off_board`] hang forever, and what we register for a
+/// single round.
+///
+/// If the registration phase times out, we stop pinging the Ark server, which drops our inputs
+/// from the next round. The protocol does not let us leave a round once it started signing, so a
+/// timeout in a later phase only stops us from waiting for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoundConfig {
+    /// From registering our inputs until the round starts.
+    pub registration_timeout: Duration,
+    /// From submitting our nonces until the Ark server aggregates the nonces of every cosigner.
+    pub nonce_exchange_timeout: Duration,
+    /// From submitting our partial signatures until the VTXO tree is signed.
+    pub signing_timeout: Duration,
+    /// From submitting our signed forfeit transactions until the round transaction is broadcast.
+    pub finalization_timeout: Duration,
+    /// How much more than the quoted fee a round may charge before we refuse to sign it.
+    ///
+    /// Rounds are quoted with [`Client::round_fee_schedule`]: the Ark server's fee schedule if it
+    /// publishes one, or our own estimate otherwise.
+    pub fee_tolerance: Amount,
+    /// The most inputs we register for a single round. More inputs are settled over several
+    /// rounds.
+    ///
+    /// This is our own limit, not the Ark server's: the `ark.v1` API does not announce how many
+    /// inputs a participant may register, so [`ark_core::server::Info`] has no limit to read. If
+    /// `None`, the default, registrations are never split and the Ark server rejects them if they
+    /// exceed its limit. Set this to match the server if it enforces one.
+    pub max_inputs_per_round: Option<usize>,
+    /// The most outputs we register for a single round.
+    ///
+    /// Like [`RoundConfig::max_inputs_per_round`], this is our own limit and is not checked if
+    /// `None`, the default.
+    pub max_outputs_per_round: Option<usize>,
+}
+
+impl RoundConfig {
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        if [
+            self.registration_timeout,
+            self.nonce_exchange_timeout,
+            self.signing_timeout,
+            self.finalization_timeout,
+        ]
+        .contains(&Duration::ZERO)
+        {
+            return Err(Error::ad_hoc("round timeouts must be positive"));
+        }
+
+        if self.max_inputs_per_round == Some(0) || self.max_outputs_per_round == Some(0) {
+            return Err(Error::ad_hoc(
+                "round input and output limits must be positive",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for RoundConfig {
+    fn default() -> Self {
+        Self {
+            registration_timeout: Duration::from_secs(10 * 60),
+            nonce_exchange_timeout: Duration::from_secs(60),
+            signing_timeout: Duration::from_secs(60),
+            finalization_timeout: Duration::from_secs(2 * 60),
+            fee_tolerance: Amount::ZERO,
+            max_inputs_per_round: None,
+            max_outputs_per_round: None,
+        }
+    }
+}
 
-impl<B, W> Client<B, W>
+/// The key we cosign the VTXO tree of a round with.
+enum OwnCosigner {
+    /// An ephemeral keypair, generated for the round.
+    Keypair(Keypair),
+    /// The key of an [`ArkSigner`] which supports MuSig2.
+    Signer(Arc<dyn ArkSigner>, PublicKey),
+}
+
+impl OwnCosigner {
+    fn public_key(&self) -> PublicKey {
+        match self {
+            OwnCosigner::Keypair(kp) => kp.public_key(),
+            OwnCosigner::Signer(_, pk) => *pk,
+        }
+    }
+}
+
+/// When to board confirmed deposits with [`Client::auto_board`].
+///
+/// Boarding outputs are only settled into VTXOs if they are worth at least `min_amount`, and only
+/// in rounds which charge at most `max_fee`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoBoardPolicy {
+    /// Boarding outputs worth less than this amount are left for [`Client::board`].
+    pub min_amount: Amount,
+    /// The most we are willing to pay the Ark server to board a batch of boarding outputs, as
+    /// quoted by [`Client::quote_fees`].
+    pub max_fee: Amount,
+}
+
+/// Published as [`ClientEvent::AutoBoarded`] by [`Client::auto_board`] for every round in which
+/// confirmed deposits were boarded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutoBoarded {
+    pub round_txid: Txid,
+    pub boarding_outpoints: Vec<OutPoint>,
+    /// The value of the boarded outputs.
+    pub amount: Amount,
+    /// The fee quoted for boarding them, see [`Client::quote_fees`].
+    pub fee: Amount,
+}
+
+/// When to consolidate small VTXOs with [`Client::sweep_small_vtxos`].
+///
+/// VTXOs worth less than `threshold` are hard to spend on their own, but they can be merged into a
+/// single, larger VTXO by settling them together. This is only done during the Ark server's market
+/// hour, when joining a round is cheap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DustSweepPolicy {
+    /// VTXOs worth less than this amount are swept.
+    pub threshold: Amount,
+}
+
+impl<B, W, T> Client<B, W, T>
 where
     B: Blockchain,
     W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
 {
     /// Lift all pending VTXOs and boarding outputs into the Ark, converting them into new,
     /// confirmed VTXOs. We do this by "joining the next round".
     pub async fn board<R>(&self, rng: &mut R) -> Result<(), Error>
+    where
+        R: Rng + CryptoRng + Clone,
+    {
+        self.board_with_handle(rng, &RoundHandle::new()).await
+    }
+
+    /// Like [`Client::board`], but our round registration can be withdrawn with `handle` until
+    /// the round starts.
+    #[tracing::instrument(skip_all, fields(operation_id = tracing::field::Empty))]
+    pub async fn board_with_handle<R>(&self, rng: &mut R, handle: &RoundHandle) -> Result<(), Error>
+    where
+        R: Rng + CryptoRng + Clone,
+    {
+        let operation_id = OperationId::start();
+
+        let outcome = self.board_batches(operation_id, rng, handle).await;
+
+        self.record_operation(operation_id, "board", outcome)
+    }
+
+    async fn board_batches<R>(
+        &self,
+        operation_id: OperationId,
+        rng: &mut R,
+        handle: &RoundHandle,
+    ) -> Result<(), Error>
     where
         R: Rng + CryptoRng + Clone,
     {
         // Get off-chain address and send all funds to this address, no change output 🦄
         let (to_address, _) = self.get_offchain_address();
 
-        let (boarding_inputs, vtxo_inputs, total_amount) =
-            self.fetch_round_transaction_inputs().await?;
+        let (boarding_inputs, vtxo_inputs, _) = self.fetch_round_transaction_inputs().await?;
 
         tracing::debug!(
-            offchain_adress = %to_address.encode(),
-            ?boarding_inputs,
-            ?vtxo_inputs,
+            offchain_adress = %self.inner.privacy.address(to_address),
+            boarding_outpoints = ?boarding_inputs.iter().map(|i| i.outpoint()).collect::<Vec<_>>(),
+            vtxo_outpoints = ?vtxo_inputs.iter().map(|v| v.outpoint()).collect::<Vec<_>>(),
             "Attempting to board the ark"
         );
 
@@ -65,33 +294,526 @@ where
             return Ok(());
         }
 
+        // We may be limited in how many inputs we can register per round, so we may need to board
+        // over several rounds.
+        let batches = batch_round_inputs(
+            boarding_inputs,
+            vtxo_inputs,
+            self.inner.round_config.max_inputs_per_round,
+        );
+
+        if batches.len() > 1 {
+            tracing::info!(
+                n_batches = batches.len(),
+                max_inputs_per_round = self.inner.round_config.max_inputs_per_round,
+                plan = ?batches
+                    .iter()
+                    .map(|b| (
+                        b.boarding_inputs.len() + b.vtxo_inputs.len(),
+                        self.inner.privacy.amount(b.amount).to_string()
+                    ))
+                    .collect::<Vec<_>>(),
+                "Splitting boarding across several rounds"
+            );
+        }
+
+        for batch in batches.iter() {
+            let txid = self.settle_batch(rng, batch, handle).await?;
+
+            tracing::info!(%txid, "Boarding success");
+
+            if let Err(e) = self
+                .record_round_vtxo_origins(
+                    txid,
+                    VtxoOrigin::Board {
+                        round_txid: txid,
+                        operation_id,
+                    },
+                )
+                .await
+            {
+                tracing::warn!(%txid, "Failed to record origin of boarded VTXOs: {e}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reclaim the value of VTXOs which expired and were swept by the Ark server, by settling them
+    /// into new VTXOs.
+    ///
+    /// Returns the TXIDs of the rounds we joined, which is empty if there was nothing to recover or
+    /// the Ark server is under maintenance.
+    #[tracing::instrument(skip_all, fields(operation_id = tracing::field::Empty))]
+    pub async fn recover_swept_vtxos<R>(&self, rng: &mut R) -> Result<Vec<Txid>, Error>
+    where
+        R: Rng + CryptoRng + Clone,
+    {
+        let operation_id = OperationId::start();
+
+        let outcome = self.recover_swept_vtxo_batches(operation_id, rng).await;
+
+        // Called periodically, so we only record the runs which did something.
+        match outcome {
+            Ok(rounds) if rounds.is_empty() => Ok(rounds),
+            outcome => self.record_operation(operation_id, "recover_swept_vtxos", outcome),
+        }
+    }
+
+    async fn recover_swept_vtxo_batches<R>(
+        &self,
+        operation_id: OperationId,
+        rng: &mut R,
+    ) -> Result<Vec<Txid>, Error>
+    where
+        R: Rng + CryptoRng + Clone,
+    {
+        if self.is_paused_for_maintenance() {
+            return Ok(Vec::new());
+        }
+
+        let recoverable_vtxos = self.recoverable_vtxos().await?;
+
+        let vtxo_inputs = recoverable_vtxos
+            .into_iter()
+            .flat_map(|(vtxo_outpoints, vtxo)| {
+                vtxo_outpoints
+                    .into_iter()
+                    .map(|vtxo_outpoint| {
+                        round::VtxoInput::new_recoverable(
+                            vtxo.clone(),
+                            vtxo_outpoint.amount,
+                            vtxo_outpoint.outpoint,
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        if vtxo_inputs.is_empty() {
+            tracing::debug!("No swept VTXOs to recover");
+            return Ok(Vec::new());
+        }
+
+        let batches = batch_round_inputs(
+            Vec::new(),
+            vtxo_inputs,
+            self.inner.round_config.max_inputs_per_round,
+        );
+
+        let mut txids = Vec::new();
+        for batch in batches.iter() {
+            let txid = self.settle_batch(rng, batch, &RoundHandle::new()).await?;
+
+            tracing::info!(%txid, amount = %self.inner.privacy.amount(batch.amount), "Recovered swept VTXOs");
+
+            if let Err(e) = self
+                .record_round_vtxo_origins(
+                    txid,
+                    VtxoOrigin::Recovery {
+                        round_txid: txid,
+                        operation_id,
+                    },
+                )
+                .await
+            {
+                tracing::warn!(%txid, "Failed to record origin of recovered VTXOs: {e}");
+            }
+
+            txids.push(txid);
+        }
+
+        Ok(txids)
+    }
+
+    /// Refresh only those of our VTXOs which expire within `expiring_within`, by settling them
+    /// into new VTXOs.
+    ///
+    /// Rounds cannot renew individual leaves of a VTXO tree, so each selected VTXO is spent and
+    /// re-created as with [`Client::board`]. Only the selection differs: VTXOs with plenty of time
+    /// left and boarding outputs are left out, which keeps the rounds we join small.
+    ///
+    /// Returns the TXIDs of the rounds we joined, which is empty if no VTXO is about to expire or
+    /// the Ark server is under maintenance.
+    #[tracing::instrument(skip_all, fields(operation_id = tracing::field::Empty))]
+    pub async fn refresh_vtxos_near_expiry<R>(
+        &self,
+        rng: &mut R,
+        expiring_within: Duration,
+    ) -> Result<Vec<Txid>, Error>
+    where
+        R: Rng + CryptoRng + Clone,
+    {
+        let operation_id = OperationId::start();
+
+        let outcome = self
+            .settle_vtxos_near_expiry(operation_id, rng, expiring_within)
+            .await;
+
+        // Called periodically, so we only record the runs which did something.
+        match outcome {
+            Ok(rounds) if rounds.is_empty() => Ok(rounds),
+            outcome => self.record_operation(operation_id, "refresh_vtxos_near_expiry", outcome),
+        }
+    }
+
+    async fn settle_vtxos_near_expiry<R>(
+        &self,
+        operation_id: OperationId,
+        rng: &mut R,
+        expiring_within: Duration,
+    ) -> Result<Vec<Txid>, Error>
+    where
+        R: Rng + CryptoRng + Clone,
+    {
+        if self.is_paused_for_maintenance() {
+            return Ok(Vec::new());
+        }
+
+        let deadline = Timestamp::now().as_second() + expiring_within.as_secs() as i64;
+
+        let spendable_vtxos = self.available_vtxos().await?;
+
+        let vtxo_inputs = spendable_vtxos
+            .into_iter()
+            .flat_map(|(vtxo_outpoints, vtxo)| {
+                vtxo_outpoints
+                    .into_iter()
+                    // VTXOs without a known expiry are left alone.
+                    .filter(|vtxo_outpoint| {
+                        vtxo_outpoint.expire_at > 0 && vtxo_outpoint.expire_at <= deadline
+                    })
+                    .map(|vtxo_outpoint| {
+                        round::VtxoInput::new(
+                            vtxo.clone(),
+                            vtxo_outpoint.amount,
+                            vtxo_outpoint.outpoint,
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        if vtxo_inputs.is_empty() {
+            tracing::debug!(?expiring_within, "No VTXOs about to expire");
+            return Ok(Vec::new());
+        }
+
+        let batches = batch_round_inputs(
+            Vec::new(),
+            vtxo_inputs,
+            self.inner.round_config.max_inputs_per_round,
+        );
+
+        let mut txids = Vec::new();
+        for batch in batches.iter() {
+            let txid = self.settle_batch(rng, batch, &RoundHandle::new()).await?;
+
+            tracing::info!(
+                %txid,
+                n_vtxos = batch.vtxo_inputs.len(),
+                amount = %self.inner.privacy.amount(batch.amount),
+                "Refreshed expiring VTXOs"
+            );
+
+            if let Err(e) = self
+                .record_round_vtxo_origins(
+                    txid,
+                    VtxoOrigin::Board {
+                        round_txid: txid,
+                        operation_id,
+                    },
+                )
+                .await
+            {
+                tracing::warn!(%txid, "Failed to record origin of refreshed VTXOs: {e}");
+            }
+
+            txids.push(txid);
+        }
+
+        Ok(txids)
+    }
+
+    /// Board our confirmed boarding outputs according to the [`AutoBoardPolicy`], turning
+    /// on-chain deposits into spendable VTXOs. Unlike [`Client::board`], our VTXOs are left
+    /// untouched.
+    ///
+    /// Call this periodically. Every round joined is published as [`ClientEvent::AutoBoarded`].
+    ///
+    /// Returns the rounds we joined, which is empty if no [`AutoBoardPolicy`] is configured, the
+    /// Ark server is under maintenance, there is no deposit worth boarding or boarding would cost
+    /// more than the policy allows.
+    #[tracing::instrument(skip_all, fields(operation_id = tracing::field::Empty))]
+    pub async fn auto_board<R>(&self, rng: &mut R) -> Result<Vec<AutoBoarded>, Error>
+    where
+        R: Rng + CryptoRng + Clone,
+    {
+        let operation_id = OperationId::start();
+
+        let outcome = self.auto_board_deposits(operation_id, rng).await;
+
+        // Called periodically, so we only record the runs which did something.
+        match outcome {
+            Ok(rounds) if rounds.is_empty() => Ok(rounds),
+            outcome => self.record_operation(operation_id, "auto_board", outcome),
+        }
+    }
+
+    async fn auto_board_deposits<R>(
+        &self,
+        operation_id: OperationId,
+        rng: &mut R,
+    ) -> Result<Vec<AutoBoarded>, Error>
+    where
+        R: Rng + CryptoRng + Clone,
+    {
+        let policy = match self.inner.auto_board_policy {
+            Some(policy) => policy,
+            None => return Ok(Vec::new()),
+        };
+
+        if self.is_paused_for_maintenance() {
+            return Ok(Vec::new());
+        }
+
+        let boarding_inputs = self
+            .fetch_boarding_inputs()
+            .await?
+            .into_iter()
+            .filter(|input| input.amount() >= policy.min_amount)
+            .collect::<Vec<_>>();
+
+        if boarding_inputs.is_empty() {
+            tracing::debug!("No confirmed deposits to board");
+            return Ok(Vec::new());
+        }
+
+        let batches = batch_round_inputs(
+            boarding_inputs,
+            Vec::new(),
+            self.inner.round_config.max_inputs_per_round,
+        );
+
+        let mut boarded = Vec::new();
+        for batch in batches.iter() {
+            let fee = self
+                .quote_fees(FeeOperation::Board {
+                    n_inputs: batch.boarding_inputs.len(),
+                })
+                .await;
+            if fee > policy.max_fee {
+                tracing::info!(
+                    n_inputs = batch.boarding_inputs.len(),
+                    %fee,
+                    max_fee = %policy.max_fee,
+                    "Not boarding deposits: fee exceeds the limit"
+                );
+                continue;
+            }
+
+            let txid = self.settle_batch(rng, batch, &RoundHandle::new()).await?;
+
+            tracing::info!(
+                %txid,
+                n_inputs = batch.boarding_inputs.len(),
+                amount = %self.inner.privacy.amount(batch.amount),
+                %fee,
+                "Boarded confirmed deposits"
+            );
+
+            if let Err(e) = self
+                .record_round_vtxo_origins(
+                    txid,
+                    VtxoOrigin::Board {
+                        round_txid: txid,
+                        operation_id,
+                    },
+                )
+                .await
+            {
+                tracing::warn!(%txid, "Failed to record origin of boarded VTXOs: {e}");
+            }
+
+            let event = AutoBoarded {
+                round_txid: txid,
+                boarding_outpoints: batch
+                    .boarding_inputs
+                    .iter()
+                    .map(|input| input.outpoint())
+                    .collect(),
+                amount: batch.amount,
+                fee,
+            };
+
+            self.publish(ClientEvent::AutoBoarded(event.clone()));
+
+            boarded.push(event);
+        }
+
+        Ok(boarded)
+    }
+
+    /// Consolidate our VTXOs worth less than the [`DustSweepPolicy`] threshold into larger VTXOs,
+    /// if the Ark server's market hour is open.
+    ///
+    /// Returns the TXIDs of the rounds we joined, which is empty if no [`DustSweepPolicy`] is
+    /// configured, the market hour is closed, the Ark server is under maintenance, an operation is
+    /// queued for VTXOs with [`Client::reserve_with_priority`] or the small VTXOs are not worth
+    /// enough to be swept.
+    #[tracing::instrument(skip_all, fields(operation_id = tracing::field::Empty))]
+    pub async fn sweep_small_vtxos<R>(&self, rng: &mut R) -> Result<Vec<Txid>, Error>
+    where
+        R: Rng + CryptoRng + Clone,
+    {
+        let operation_id = OperationId::start();
+
+        let outcome = self.consolidate_small_vtxos(operation_id, rng).await;
+
+        // Called periodically, so we only record the runs which did something.
+        match outcome {
+            Ok(rounds) if rounds.is_empty() => Ok(rounds),
+            outcome => self.record_operation(operation_id, "sweep_small_vtxos", outcome),
+        }
+    }
+
+    async fn consolidate_small_vtxos<R>(
+        &self,
+        operation_id: OperationId,
+        rng: &mut R,
+    ) -> Result<Vec<Txid>, Error>
+    where
+        R: Rng + CryptoRng + Clone,
+    {
+        let policy = match self.inner.dust_sweep_policy {
+            Some(policy) => policy,
+            None => return Ok(Vec::new()),
+        };
+
+        if !self
+            .server_info
+            .is_market_hour_open(Timestamp::now().as_second())
+        {
+            tracing::debug!("Market hour closed, not sweeping small VTXOs");
+            return Ok(Vec::new());
+        }
+
+        if self.is_paused_for_maintenance() {
+            return Ok(Vec::new());
+        }
+
+        // Sweeping would hold our VTXOs for a whole round, so let waiting payments go first.
+        if self
+            .reservations
+            .is_queued_above(ReservationPriority::Background)
+        {
+            tracing::debug!("Operations waiting for VTXOs, not sweeping small VTXOs");
+            return Ok(Vec::new());
+        }
+
+        let spendable_vtxos = self.available_vtxos().await?;
+
+        let vtxo_inputs = spendable_vtxos
+            .into_iter()
+            .flat_map(|(vtxo_outpoints, vtxo)| {
+                vtxo_outpoints
+                    .into_iter()
+                    .filter(|vtxo_outpoint| vtxo_outpoint.amount < policy.threshold)
+                    .map(|vtxo_outpoint| {
+                        round::VtxoInput::new(
+                            vtxo.clone(),
+                            vtxo_outpoint.amount,
+                            vtxo_outpoint.outpoint,
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let batches = batch_round_inputs(
+            Vec::new(),
+            vtxo_inputs,
+            self.inner.round_config.max_inputs_per_round,
+        );
+
+        let mut txids = Vec::new();
+        for batch in batches.iter() {
+            // Merging a single VTXO gains nothing, and the server rejects outputs below dust.
+            if batch.vtxo_inputs.len() < 2 || batch.amount < self.server_info.dust {
+                tracing::debug!(
+                    n_vtxos = batch.vtxo_inputs.len(),
+                    amount = %self.inner.privacy.amount(batch.amount),
+                    "Not sweeping small VTXOs"
+                );
+                continue;
+            }
+
+            let txid = self.settle_batch(rng, batch, &RoundHandle::new()).await?;
+
+            tracing::info!(
+                %txid,
+                n_vtxos = batch.vtxo_inputs.len(),
+                amount = %self.inner.privacy.amount(batch.amount),
+                "Swept small VTXOs"
+            );
+
+            if let Err(e) = self
+                .record_round_vtxo_origins(
+                    txid,
+                    VtxoOrigin::Board {
+                        round_txid: txid,
+                        operation_id,
+                    },
+                )
+                .await
+            {
+                tracing::warn!(%txid, "Failed to record origin of swept VTXOs: {e}");
+            }
+
+            txids.push(txid);
+        }
+
+        Ok(txids)
+    }
+
+    /// Join the next round with the inputs in `batch`, sending everything to our own offchain
+    /// address.
+    async fn settle_batch<R>(
+        &self,
+        rng: &mut R,
+        batch: &RoundInputBatch,
+        handle: &RoundHandle,
+    ) -> Result<Txid, Error>
+    where
+        R: Rng + CryptoRng + Clone,
+    {
+        let (to_address, _) = self.get_offchain_address();
+
         let join_next_ark_round = || async {
             self.join_next_ark_round(
                 &mut rng.clone(),
-                boarding_inputs.clone(),
-                vtxo_inputs.clone(),
+                batch.boarding_inputs.clone(),
+                batch.vtxo_inputs.clone(),
                 RoundOutputType::Board {
                     to_address,
-                    to_amount: total_amount,
+                    to_amount: batch.amount,
                 },
+                handle,
             )
             .await
         };
 
-        // Joining a round can fail depending on the timing, so we try a few times.
-        let txid = join_next_ark_round
-            .retry(ExponentialBuilder::default().with_max_times(0))
+        // The round can be aborted because of other participants, in which case we register the
+        // same inputs for the next one.
+        join_next_ark_round
+            .retry(self.inner.round_retry_policy.backoff())
             .sleep(sleep)
-            // TODO: Use `when` to only retry certain errors.
-            .notify(|err: &Error, dur: std::time::Duration| {
+            .when(Error::is_round_failed)
+            .notify(|err: &Error, dur: Duration| {
                 tracing::warn!("Retrying joining next Ark round after {dur:?}. Error: {err}",);
             })
             .await
-            .context("Failed to join round")?;
-
-        tracing::info!(%txid, "Boarding success");
-
-        Ok(())
+            .context("Failed to join round")
     }
 
     // In go client: CollaborativeRedeem.
@@ -104,21 +826,83 @@ where
     where
         R: Rng + CryptoRng + Clone,
     {
-        let (change_address, _) = self.get_offchain_address();
+        self.off_board_with_handle(rng, to_address, to_amount, &RoundHandle::new())
+            .await
+    }
 
-        let (boarding_inputs, vtxo_inputs, total_amount) =
-            self.fetch_round_transaction_inputs().await?;
+    /// Like [`Client::off_board`], but our round registration can be withdrawn with `handle`
+    /// until the round starts.
+    #[tracing::instrument(skip_all, fields(operation_id = tracing::field::Empty))]
+    pub async fn off_board_with_handle<R>(
+        &self,
+        rng: &mut R,
+        to_address: Address,
+        to_amount: Amount,
+        handle: &RoundHandle,
+    ) -> Result<Txid, Error>
+    where
+        R: Rng + CryptoRng + Clone,
+    {
+        let operation_id = OperationId::start();
+
+        let outcome = self
+            .off_board_inputs(operation_id, rng, to_address, to_amount, handle)
+            .await;
+
+        self.record_operation(operation_id, "off_board", outcome)
+    }
+
+    async fn off_board_inputs<R>(
+        &self,
+        operation_id: OperationId,
+        rng: &mut R,
+        to_address: Address,
+        to_amount: Amount,
+        handle: &RoundHandle,
+    ) -> Result<Txid, Error>
+    where
+        R: Rng + CryptoRng + Clone,
+    {
+        let change_address = self.change_address()?;
+
+        let (boarding_inputs, vtxo_inputs, total_amount) = loop {
+            let (boarding_inputs, vtxo_inputs, total_amount) =
+                self.fetch_round_transaction_inputs().await?;
+
+            let n_inputs = boarding_inputs.len() + vtxo_inputs.len();
+            match self.inner.round_config.max_inputs_per_round {
+                Some(max_inputs) if n_inputs > max_inputs => {
+                    if max_inputs < 2 {
+                        return Err(Error::ad_hoc(format!(
+                            "cannot off-board {n_inputs} inputs: \
+                             only {max_inputs} input(s) allowed per round"
+                        )));
+                    }
+
+                    // Boarding merges our inputs into fewer VTXOs, which we can then off-board
+                    // in a single round.
+                    tracing::info!(
+                        n_inputs,
+                        max_inputs,
+                        "Too many inputs to off-board at once, consolidating first"
+                    );
+
+                    self.board_with_handle(rng, handle).await?;
+                }
+                _ => break (boarding_inputs, vtxo_inputs, total_amount),
+            }
+        };
 
         let change_amount = total_amount.checked_sub(to_amount).ok_or_else(|| {
             Error::coin_select("cannot afford to send {to_amount}, only have {total_amount}")
         })?;
 
         tracing::info!(
-            %to_address,
-            %to_amount,
-            change_address = %change_address.encode(),
-            %change_amount,
-            ?boarding_inputs,
+            to_address = %self.inner.privacy.address(&to_address),
+            to_amount = %self.inner.privacy.amount(to_amount),
+            change_address = %self.inner.privacy.address(change_address),
+            change_amount = %self.inner.privacy.amount(change_amount),
+            boarding_outpoints = ?boarding_inputs.iter().map(|i| i.outpoint()).collect::<Vec<_>>(),
             "Attempting to off-board the ark"
         );
 
@@ -133,16 +917,18 @@ where
                     change_address,
                     change_amount,
                 },
+                handle,
             )
             .await
         };
 
-        // Joining a round can fail depending on the timing, so we try a few times.
+        // The round can be aborted because of other participants, in which case we register the
+        // same inputs for the next one.
         let txid = join_next_ark_round
-            .retry(ExponentialBuilder::default().with_max_times(3))
+            .retry(self.inner.round_retry_policy.backoff())
             .sleep(sleep)
-            // TODO: Use `when` to only retry certain errors.
-            .notify(|err: &Error, dur: std::time::Duration| {
+            .when(Error::is_round_failed)
+            .notify(|err: &Error, dur: Duration| {
                 tracing::warn!("Retrying joining next Ark round after {dur:?}. Error: {err}");
             })
             .await
@@ -150,6 +936,19 @@ where
 
         tracing::info!(%txid, "Off-boarding success");
 
+        if let Err(e) = self
+            .record_round_vtxo_origins(
+                txid,
+                VtxoOrigin::OffBoardChange {
+                    round_txid: txid,
+                    operation_id,
+                },
+            )
+            .await
+        {
+            tracing::warn!(%txid, "Failed to record origin of off-boarding change VTXO: {e}");
+        }
+
         Ok(txid)
     }
 
@@ -158,43 +957,13 @@ where
     async fn fetch_round_transaction_inputs(
         &self,
     ) -> Result<(Vec<round::OnChainInput>, Vec<round::VtxoInput>, Amount), Error> {
-        // Get all known boarding outputs.
-        let boarding_outputs = self.inner.wallet.get_boarding_outputs()?;
-
-        let mut boarding_inputs: Vec<round::OnChainInput> = Vec::new();
-        let mut total_amount = Amount::ZERO;
-
-        let now = Timestamp::now();
-
-        // Find outpoints for each boarding output.
-        for boarding_output in boarding_outputs {
-            let outpoints = self
-                .blockchain()
-                .find_outpoints(boarding_output.address())
-                .await?;
+        let boarding_inputs = self.fetch_boarding_inputs().await?;
 
-            for o in outpoints.iter() {
-                if let ExplorerUtxo {
-                    outpoint,
-                    amount,
-                    confirmation_blocktime: Some(confirmation_blocktime),
-                    is_spent: false,
-                } = o
-                {
-                    // Only include confirmed boarding outputs with an _inactive_ exit path.
-                    if !boarding_output.can_be_claimed_unilaterally_by_owner(
-                        now.as_duration().try_into().map_err(Error::ad_hoc)?,
-                        std::time::Duration::from_secs(*confirmation_blocktime),
-                    ) {
-                        boarding_inputs
-                            .push(round::OnChainInput::new(boarding_output.clone(), *outpoint));
-                        total_amount += *amount;
-                    }
-                }
-            }
-        }
+        let mut total_amount = boarding_inputs
+            .iter()
+            .fold(Amount::ZERO, |acc, input| acc + input.amount());
 
-        let spendable_vtxos = self.spendable_vtxos().await?;
+        let spendable_vtxos = self.available_vtxos().await?;
 
         for (vtxo_outpoints, _) in spendable_vtxos.iter() {
             total_amount += vtxo_outpoints
@@ -221,12 +990,90 @@ where
         Ok((boarding_inputs, vtxo_inputs, total_amount))
     }
 
+    /// Our confirmed boarding outputs which can still be boarded.
+    async fn fetch_boarding_inputs(&self) -> Result<Vec<round::OnChainInput>, Error> {
+        // Get all known boarding outputs.
+        let boarding_outputs = self.inner.wallet.get_boarding_outputs()?;
+
+        let mut boarding_inputs: Vec<round::OnChainInput> = Vec::new();
+        let mut confirmed_boarding_outputs = Vec::new();
+
+        // Only fetched once we find a confirmed boarding output.
+        let mut chain_tip = None;
+
+        // Find outpoints for each boarding output.
+        for boarding_output in boarding_outputs {
+            let outpoints = self.find_our_outpoints(boarding_output.address()).await?;
+
+            for o in outpoints.iter() {
+                if o.confirmation_blocktime.is_some() {
+                    confirmed_boarding_outputs.push((o.outpoint, o.amount));
+                }
+
+                if o.is_spent {
+                    continue;
+                }
+
+                if let Some(confirmed) = self.confirmed_in(o).await? {
+                    let tip = match chain_tip {
+                        Some(tip) => tip,
+                        None => *chain_tip.insert(self.chain_tip().await?),
+                    };
+
+                    // Only include confirmed boarding outputs with an _inactive_ exit path.
+                    if !boarding_output.can_be_claimed_unilaterally_by_owner(confirmed, tip)? {
+                        boarding_inputs.push(round::OnChainInput::new(
+                            boarding_output.clone(),
+                            o.amount,
+                            o.outpoint,
+                        ));
+                    }
+                }
+            }
+        }
+
+        self.observe_confirmed_boarding_outputs(&confirmed_boarding_outputs);
+
+        Ok(boarding_inputs)
+    }
+
+    /// Check that none of `boarding_inputs` was spent since we found it, e.g. by another device
+    /// using the same boarding key.
+    ///
+    /// Otherwise the Ark server would reject our registration, or the round would fail.
+    async fn check_boarding_inputs_unspent(
+        &self,
+        boarding_inputs: &[round::OnChainInput],
+    ) -> Result<(), Error> {
+        for input in boarding_inputs.iter() {
+            let outpoint = input.outpoint();
+            let status = self
+                .blockchain()
+                .get_output_status(&outpoint.txid, outpoint.vout)
+                .await
+                .with_context(|| format!("failed to get status of boarding output {outpoint}"))?;
+
+            if let Some(spend_txid) = status.spend_txid {
+                tracing::warn!(
+                    %outpoint,
+                    %spend_txid,
+                    "Boarding output already spent, possibly by another device"
+                );
+
+                return Err(Error::boarding_output_spent(outpoint, spend_txid));
+            }
+        }
+
+        Ok(())
+    }
+
     async fn join_next_ark_round<R>(
         &self,
         rng: &mut R,
         onchain_inputs: Vec<round::OnChainInput>,
         vtxo_inputs: Vec<round::VtxoInput>,
         output_type: RoundOutputType,
+        handle: &RoundHandle,
     ) -> Result<Txid, Error>
     where
         R: Rng + CryptoRng,
@@ -235,10 +1082,21 @@ where
             return Err(Error::ad_hoc("cannot join round without inputs"));
         }
 
+        self.check_server_info().await?;
+
         let server_info = &self.server_info;
 
-        // Generate an (ephemeral) cosigner keypair.
-        let own_cosigner_kp = Keypair::new(self.secp(), rng);
+        // Cosign with the key of the Ark signer if it supports MuSig2, or else with an (ephemeral)
+        // cosigner keypair.
+        let own_cosigner = match self
+            .inner
+            .ark_signer
+            .as_ref()
+            .and_then(|signer| signer.musig2_cosigner_pk().map(|pk| (signer.clone(), pk)))
+        {
+            Some((signer, pk)) => OwnCosigner::Signer(signer, pk),
+            None => OwnCosigner::Keypair(Keypair::new(self.secp(), rng)),
+        };
 
         let inputs = {
             let boarding_inputs = onchain_inputs
@@ -254,14 +1112,15 @@ where
             boarding_inputs.chain(vtxo_inputs).collect::<Vec<_>>()
         };
 
-        let payment_id = self
-            .network_client()
-            .register_inputs_for_next_round(&inputs)
-            .await
-            .map_err(Error::from)
-            .context("failed to register round inputs")?;
-
-        tracing::debug!(payment_id, "Registered for round");
+        let round_config = self.inner.round_config;
+        if let Some(max_inputs) = round_config.max_inputs_per_round {
+            if inputs.len() > max_inputs {
+                return Err(Error::ad_hoc(format!(
+                    "cannot register {} inputs for round: at most {max_inputs} allowed",
+                    inputs.len()
+                )));
+            }
+        }
 
         let mut outputs = vec![];
 
@@ -281,14 +1140,84 @@ where
             }
         }
 
-        let own_cosigner_kps = [own_cosigner_kp];
-        let own_cosigner_pks = own_cosigner_kps
+        if let Some(max_outputs) = round_config.max_outputs_per_round {
+            if outputs.len() > max_outputs {
+                return Err(Error::ad_hoc(format!(
+                    "cannot register {} outputs for round: at most {max_outputs} allowed",
+                    outputs.len()
+                )));
+            }
+        }
+
+        let inputs_total = onchain_inputs
+            .iter()
+            .map(round::OnChainInput::amount)
+            .chain(vtxo_inputs.iter().map(round::VtxoInput::amount))
+            .sum::<Amount>();
+        let max_fee = self
+            .round_fee_schedule()
+            .await
+            .round_fee_for_outputs(inputs.len(), &outputs)
+            + round_config.fee_tolerance;
+        // The VTXOs we register for, which the VTXO tree must pay.
+        let our_vtxos = outputs
+            .iter()
+            .filter_map(|output| match output.address() {
+                RoundOutputAddress::Virtual(address) => {
+                    Some((address.to_p2tr_script_pubkey(), output.amount()))
+                }
+                RoundOutputAddress::OnChain(_) => None,
+            })
+            .collect::<Vec<_>>();
+
+        self.check_boarding_inputs_unspent(&onchain_inputs).await?;
+
+        for middleware in self.inner.round_middleware.iter() {
+            middleware
+                .before_registration(&inputs, &outputs)
+                .context("round middleware refused registration")?;
+        }
+
+        // Until the round is over, other operations must not select the VTXOs we register.
+        let _held_vtxos = self.reservations.hold_round_inputs(
+            vtxo_inputs.iter().map(round::VtxoInput::outpoint).collect(),
+            vtxo_inputs.iter().map(round::VtxoInput::amount).sum(),
+        );
+
+        let _registration = handle.register()?;
+
+        let mut step = RoundStep::Start;
+        let mut phase_timeout = Box::pin(sleep(step.timeout(&round_config)));
+
+        let payment_id = until(
+            self.network_client()
+                .register_inputs_for_next_round(&inputs),
+            phase_timeout.as_mut(),
+        )
+        .await
+        .ok_or_else(|| step.timed_out(&round_config))?
+        .context("failed to register round inputs")?;
+
+        tracing::debug!(payment_id, "Registered for round");
+
+        let own_cosigners = [own_cosigner];
+        let own_cosigner_pks = own_cosigners
             .iter()
-            .map(|k| k.public_key())
+            .map(|c| c.public_key())
             .collect::<Vec<_>>();
-        self.network_client()
-            .register_outputs_for_next_round(payment_id.clone(), &outputs, &own_cosigner_pks, false)
-            .await?;
+        until(
+            self.network_client().register_outputs_for_next_round(
+                payment_id.clone(),
+                &outputs,
+                &own_cosigner_pks,
+                false,
+            ),
+            phase_timeout.as_mut(),
+        )
+        .await
+        .ok_or_else(|| step.timed_out(&round_config))??;
+
+        self.publish(ClientEvent::RoundRegistered);
 
         let network_client = self.network_client();
 
@@ -305,7 +1234,7 @@ where
                         tracing::warn!("Error via ping: {e:?}");
                     }
 
-                    sleep(std::time::Duration::from_millis(5000)).await
+                    sleep(Duration::from_millis(5000)).await
                 }
             }
         }
@@ -315,17 +1244,112 @@ where
 
         let mut stream = network_client.get_event_stream().await?;
 
-        let mut step = RoundStep::Start;
-
         let (ark_server_pk, _) = server_info.pk.x_only_public_key();
 
         let mut round_id: Option<String> = None;
-        let mut unsigned_round_tx: Option<Psbt> = None;
-        let mut vtxo_tree: Option<TxTree> = None;
-        let mut our_nonce_trees: Option<HashMap<Keypair, NonceTree>> = None;
+        let mut our_signing_sessions: Option<Vec<VtxoTreeSigningSession>> = None;
+        let mut signed_forfeits: Vec<(OutPoint, OutPoint, Txid)> = Vec::new();
+        let mut reconnects = 0;
+        // Whether we resubscribed to the event stream while signing a round, and are yet to see
+        // the next event of that round.
+        let mut resumed = false;
+        let mut timed_step = step;
         loop {
-            match stream.next().await {
-                Some(Ok(event)) => match event {
+            if step != timed_step {
+                timed_step = step;
+                phase_timeout = Box::pin(sleep(step.timeout(&round_config)));
+            }
+
+            let next_event = until(stream.next(), phase_timeout.as_mut());
+
+            // Until we start signing, our registration can be withdrawn. We then stop pinging
+            // the Ark server, which drops our inputs from the next round.
+            let event = if step == RoundStep::Start {
+                match future::select(pin!(next_event), pin!(handle.withdrawn())).await {
+                    Either::Left((event, _)) => event,
+                    Either::Right(((), _)) => {
+                        tracing::info!("Withdrew round registration");
+
+                        return Err(Error::round_withdrawn("round registration withdrawn"));
+                    }
+                }
+            } else {
+                next_event.await
+            };
+
+            let Some(event) = event else {
+                tracing::info!(?step, "Giving up on round");
+
+                return Err(step.timed_out(&round_config));
+            };
+
+            let event = match event {
+                Some(Err(e)) if e.is_transient() => Err(e),
+                Some(event) => Ok(event),
+                None => Err(Error::ark_server("dropped round event stream")),
+            };
+
+            let event =
+                match event {
+                    Ok(event) => event,
+                    Err(dropped) => {
+                        reconnects += 1;
+                        if reconnects > MAX_ROUND_EVENT_STREAM_RECONNECTS {
+                            return Err(dropped.context(Error::round_abandoned(
+                                "round event stream dropped too many times",
+                            )));
+                        }
+
+                        tracing::warn!(?step, "Resubscribing to round event stream: {dropped}");
+
+                        stream = network_client.get_event_stream().await.context(
+                            Error::round_abandoned("failed to resubscribe to round event stream"),
+                        )?;
+
+                        // Before signing, our registration survives as long as we keep pinging.
+                        resumed = step != RoundStep::Start;
+
+                        continue;
+                    }
+                };
+
+            // Events sent while we were disconnected are lost, so we can only resume the round we
+            // are signing if we did not miss any of its events.
+            if let (true, Ok(event), Some(our_round_id)) = (resumed, &event, &round_id) {
+                let is_next_step = matches!(
+                    (event, &step),
+                    (RoundStreamEvent::RoundFailed(_), _)
+                        | (
+                            RoundStreamEvent::RoundSigningNoncesGenerated(_),
+                            RoundStep::RoundSigningStarted
+                        )
+                        | (
+                            RoundStreamEvent::RoundFinalization(_),
+                            RoundStep::RoundSigningNoncesGenerated
+                        )
+                        | (
+                            RoundStreamEvent::RoundFinalized(_),
+                            RoundStep::RoundFinalization
+                        )
+                );
+
+                if event.round_id() == our_round_id.as_str() {
+                    if !is_next_step {
+                        return Err(Error::round_abandoned(format!(
+                            "missed events of round {our_round_id} while disconnected"
+                        )));
+                    }
+
+                    resumed = false;
+                } else if let RoundStreamEvent::RoundSigning(_) = event {
+                    return Err(Error::round_abandoned(format!(
+                        "round {our_round_id} ended while disconnected"
+                    )));
+                }
+            }
+
+            match event {
+                Ok(event) => match event {
                     RoundStreamEvent::RoundSigning(e) => {
                         if step != RoundStep::Start {
                             continue;
@@ -335,9 +1359,45 @@ where
 
                         round_id = Some(e.id.clone());
 
+                        self.publish(ClientEvent::RoundJoined {
+                            round_id: e.id.clone(),
+                        });
+
                         let unsigned_vtxo_tree =
                             e.unsigned_vtxo_tree.expect("to have an unsigned vtxo tree");
 
+                        for middleware in self.inner.round_middleware.iter() {
+                            middleware
+                                .before_signing(&e.id, &e.unsigned_round_tx, &unsigned_vtxo_tree)
+                                .context("round middleware refused signing")?;
+                        }
+
+                        let charged_fee = charged_round_fee(
+                            &e.unsigned_round_tx,
+                            &unsigned_vtxo_tree,
+                            &outputs,
+                            inputs_total,
+                        )
+                        .map_err(Error::ark_server)
+                        .context("failed to check round fee")?;
+                        if charged_fee > max_fee {
+                            return Err(Error::ark_server(format!(
+                                "round charges a fee of {charged_fee}, more than the {max_fee} \
+                                 we accept"
+                            )));
+                        }
+
+                        verify_vtxo_tree(
+                            &e.unsigned_round_tx,
+                            &unsigned_vtxo_tree,
+                            server_info.vtxo_tree_expiry,
+                            ark_server_pk,
+                            &our_vtxos,
+                            self.inner.vtxo_tree_limits,
+                        )
+                        .map_err(Error::ark_server)
+                        .context("refusing to cosign VTXO tree")?;
+
                         for own_cosigner_pk in own_cosigner_pks.iter() {
                             if !&e.cosigners_pubkeys.iter().any(|p| p == own_cosigner_pk) {
                                 return Err(Error::ark_server(format!(
@@ -346,14 +1406,61 @@ where
                             }
                         }
 
-                        // We generate and submit a nonce tree for every cosigner key we provide.
-                        let mut our_nonce_tree_map = HashMap::new();
-                        for own_cosigner_kp in own_cosigner_kps {
-                            let own_cosigner_pk = own_cosigner_kp.public_key();
-                            let nonce_tree =
-                                generate_nonce_tree(rng, &unsigned_vtxo_tree, own_cosigner_pk)
+                        // We only keep what we need to sign our share of the VTXO tree, so that
+                        // every level of the tree is dropped as soon as it
+                        // has been processed.
+                        let mut signing_sessions = own_cosigners
+                            .iter()
+                            .map(|cosigner| match cosigner {
+                                OwnCosigner::Keypair(kp) => VtxoTreeSigningSession::new(
+                                    server_info.vtxo_tree_expiry,
+                                    ark_server_pk,
+                                    kp,
+                                    &e.unsigned_round_tx,
+                                ),
+                                OwnCosigner::Signer(_, pk) => VtxoTreeSigningSession::new_external(
+                                    server_info.vtxo_tree_expiry,
+                                    ark_server_pk,
+                                    *pk,
+                                    &e.unsigned_round_tx,
+                                ),
+                            })
+                            .collect::<Vec<_>>();
+                        for level in unsigned_vtxo_tree.levels.into_iter() {
+                            for session in signing_sessions.iter_mut() {
+                                session
+                                    .add_level(rng, &level)
                                     .map_err(Error::from)
                                     .context("failed to generate VTXO nonce tree")?;
+                            }
+                        }
+
+                        // The Ark signer generates the nonces for its key itself.
+                        for (session, cosigner) in
+                            signing_sessions.iter_mut().zip(own_cosigners.iter())
+                        {
+                            if let OwnCosigner::Signer(signer, _) = cosigner {
+                                for node in session.nodes() {
+                                    let pub_nonce = signer
+                                        .musig2_nonce(node.key_agg_cache, node.msg)
+                                        .await
+                                        .context(
+                                            "failed to generate VTXO tree nonce with signer",
+                                        )?;
+
+                                    session
+                                        .set_pub_nonce(node.level, node.branch, pub_nonce)
+                                        .map_err(Error::from)?;
+                                }
+                            }
+                        }
+
+                        // Past this point, leaving the round would make it fail.
+                        handle.start_signing(&e.id)?;
+
+                        // We submit a nonce tree for every cosigner key we provide.
+                        for session in signing_sessions.iter() {
+                            let own_cosigner_pk = session.own_cosigner_pk();
 
                             tracing::info!(
                                 cosigner_pk = %own_cosigner_pk,
@@ -364,20 +1471,13 @@ where
                                 .submit_tree_nonces(
                                     &e.id,
                                     own_cosigner_pk,
-                                    nonce_tree.to_pub_nonce_tree().into_inner(),
+                                    session.pub_nonce_tree().into_inner(),
                                 )
                                 .await
-                                .map_err(Error::ark_server)
                                 .context("failed to submit VTXO nonce tree")?;
-
-                            our_nonce_tree_map.insert(own_cosigner_kp, nonce_tree);
                         }
 
-                        our_nonce_trees = Some(our_nonce_tree_map);
-
-                        vtxo_tree = Some(unsigned_vtxo_tree);
-
-                        unsigned_round_tx = Some(e.unsigned_round_tx);
+                        our_signing_sessions = Some(signing_sessions);
 
                         step = step.next();
                         continue;
@@ -395,41 +1495,66 @@ where
                             "Round combined nonces generated"
                         );
 
-                        let unsigned_round_tx = unsigned_round_tx
-                            .as_ref()
-                            .ok_or(Error::ark_server("missing round TX during round protocol"))?;
-
-                        let vtxo_tree = vtxo_tree
-                            .as_ref()
-                            .ok_or(Error::ark_server("missing vtxo tree during round protocol"))?;
-                        let our_nonce_trees = our_nonce_trees.take().ok_or(Error::ark_server(
-                            "missing nonce tree during round protocol",
-                        ))?;
-
-                        for (cosigner_kp, our_nonce_tree) in our_nonce_trees {
-                            let partial_sig_tree = sign_vtxo_tree(
-                                server_info.vtxo_tree_expiry,
-                                ark_server_pk,
-                                &cosigner_kp,
-                                vtxo_tree,
-                                unsigned_round_tx,
-                                our_nonce_tree,
-                                &agg_pub_nonce_tree,
-                            )
+                        let signing_sessions = our_signing_sessions.take().ok_or(
+                            Error::ark_server("missing nonce tree during round protocol"),
+                        )?;
+
+                        for (session, cosigner) in
+                            signing_sessions.into_iter().zip(own_cosigners.iter())
+                        {
+                            let own_cosigner_pk = session.own_cosigner_pk();
+
+                            let partial_sig_tree = match cosigner {
+                                OwnCosigner::Keypair(_) => session.sign(&agg_pub_nonce_tree),
+                                OwnCosigner::Signer(signer, _) => {
+                                    let pub_nonce_tree = session.pub_nonce_tree();
+
+                                    let mut partial_sigs = HashMap::new();
+                                    for node in session.nodes() {
+                                        let (level, branch) = (node.level, node.branch);
+
+                                        let pub_nonce =
+                                            pub_nonce_tree.get(level, branch).ok_or_else(|| {
+                                                Error::ad_hoc(format!(
+                                                    "missing own nonce {level}, {branch}"
+                                                ))
+                                            })?;
+                                        let agg_nonce = session
+                                            .aggregate_nonce(&agg_pub_nonce_tree, level, branch)
+                                            .map_err(Error::from)?;
+
+                                        let partial_sig = signer
+                                            .musig2_partial_sign(
+                                                node.key_agg_cache,
+                                                node.msg,
+                                                pub_nonce,
+                                                agg_nonce,
+                                            )
+                                            .await
+                                            .context("failed to sign VTXO tree with signer")?;
+
+                                        partial_sigs.insert((level, branch), partial_sig);
+                                    }
+
+                                    // The partial signatures of the signer are verified here.
+                                    session.partial_sig_tree(&agg_pub_nonce_tree, &partial_sigs)
+                                }
+                            }
                             .map_err(Error::from)
                             .context("failed to sign VTXO tree")?;
 
                             network_client
                                 .submit_tree_signatures(
                                     &e.id,
-                                    cosigner_kp.public_key(),
+                                    own_cosigner_pk,
                                     partial_sig_tree.into_inner(),
                                 )
                                 .await
-                                .map_err(Error::ark_server)
                                 .context("failed to submit VTXO tree signatures")?;
                         }
 
+                        self.publish(ClientEvent::RoundSigned { round_id: e.id });
+
                         step = step.next();
                     }
                     RoundStreamEvent::RoundFinalization(e) => {
@@ -438,8 +1563,22 @@ where
                         }
                         tracing::debug!(round_id = e.id, "Round finalization started");
 
-                        let signed_forfeit_psbts = create_and_sign_forfeit_txs(
-                            self.kp(),
+                        let sighashes = forfeit_txs_sighashes(
+                            vtxo_inputs.as_slice(),
+                            &e.connector_tree,
+                            &e.connectors_index,
+                            e.min_relay_fee_rate,
+                            &server_info.forfeit_address,
+                            server_info.dust,
+                        )
+                        .map_err(Error::from)?;
+                        let sigs = self
+                            .sign_sighashes(sighashes)
+                            .await
+                            .context("failed to sign forfeit transactions")?;
+
+                        let signed_forfeit_psbts = create_and_sign_forfeit_txs_with(
+                            sigs.sign_for_pk_fn(),
                             vtxo_inputs.as_slice(),
                             e.connector_tree,
                             &e.connectors_index,
@@ -449,21 +1588,71 @@ where
                         )
                         .map_err(Error::from)?;
 
+                        // Every forfeit transaction spends a connector output first and the
+                        // forfeited VTXO second.
+                        signed_forfeits = signed_forfeit_psbts
+                            .iter()
+                            .filter_map(|psbt| {
+                                let tx = &psbt.unsigned_tx;
+                                let connector_outpoint = tx.input.first()?.previous_output;
+                                let vtxo_outpoint = tx.input.get(1)?.previous_output;
+
+                                Some((vtxo_outpoint, connector_outpoint, tx.compute_txid()))
+                            })
+                            .collect();
+
                         let round_psbt = if onchain_inputs.is_empty() {
                             None
+                        } else if let Some(external_signer) = &self.inner.external_signer {
+                            let mut round_psbt = e.round_tx;
+                            prepare_round_psbt(&mut round_psbt, &onchain_inputs)
+                                .map_err(Error::from)?;
+
+                            let unsigned_tx = round_psbt.unsigned_tx.clone();
+                            let round_psbt = until(
+                                external_signer.sign_psbt(round_psbt),
+                                Box::pin(sleep(round_config.finalization_timeout)),
+                            )
+                            .await
+                            .ok_or_else(|| {
+                                Error::round_timed_out(
+                                    "external signer did not sign the round transaction in time",
+                                )
+                            })?
+                            .context("failed to sign round TX externally")?;
+
+                            if round_psbt.unsigned_tx != unsigned_tx {
+                                return Err(Error::ad_hoc(
+                                    "external signer modified the round transaction",
+                                ));
+                            }
+
+                            verify_round_psbt_signatures(&round_psbt, &onchain_inputs)
+                                .map_err(Error::from)?;
+
+                            Some(round_psbt)
                         } else {
                             let mut round_psbt = e.round_tx;
 
+                            // The wallet signs asynchronously, so we collect its signatures
+                            // before signing the round transaction.
+                            let mut sigs = HashMap::new();
+                            for (pk, msg) in round_psbt_sighashes(&round_psbt, &onchain_inputs)
+                                .map_err(Error::from)?
+                            {
+                                let sig = self.inner.wallet.sign_for_pk(&pk, &msg).await?;
+                                sigs.insert((pk, msg), sig);
+                            }
+
                             let sign_for_pk_fn = |pk: &XOnlyPublicKey,
                                                   msg: &secp256k1::Message|
                              -> Result<
                                 schnorr::Signature,
                                 ark_core::Error,
                             > {
-                                self.inner
-                                    .wallet
-                                    .sign_for_pk(pk, msg)
-                                    .map_err(|e| ark_core::Error::ad_hoc(e.to_string()))
+                                sigs.get(&(*pk, *msg)).copied().ok_or_else(|| {
+                                    ark_core::Error::ad_hoc(format!("missing signature by {pk}"))
+                                })
                             };
 
                             sign_round_psbt(sign_for_pk_fn, &mut round_psbt, &onchain_inputs)
@@ -476,6 +1665,8 @@ where
                             .submit_signed_forfeit_txs(signed_forfeit_psbts, round_psbt)
                             .await?;
 
+                        self.publish(ClientEvent::RoundForfeitsSigned { round_id: e.id });
+
                         step = step.next();
                     }
                     RoundStreamEvent::RoundFinalized(e) => {
@@ -487,11 +1678,43 @@ where
 
                         tracing::info!(round_id = e.id, %round_txid, "Round finalized");
 
+                        for (vtxo_outpoint, connector_outpoint, forfeit_txid) in
+                            signed_forfeits.iter()
+                        {
+                            let forfeit = ForfeitRecord {
+                                vtxo_outpoint: *vtxo_outpoint,
+                                connector_outpoint: *connector_outpoint,
+                                forfeit_txid: *forfeit_txid,
+                                round_txid,
+                            };
+
+                            if let Err(e) = self.inner.wallet.store().save_forfeit(forfeit) {
+                                tracing::warn!(
+                                    %forfeit_txid,
+                                    "Failed to record signed forfeit transaction: {e}"
+                                );
+                            }
+                        }
+
+                        for middleware in self.inner.round_middleware.iter() {
+                            middleware.after_finalization(&e.id, round_txid);
+                        }
+
+                        self.publish(ClientEvent::RoundFinalized {
+                            round_id: e.id,
+                            round_txid,
+                        });
+
                         return Ok(round_txid);
                     }
                     RoundStreamEvent::RoundFailed(e) => {
                         if Some(&e.id) == round_id.as_ref() {
-                            return Err(Error::ark_server(format!(
+                            self.publish(ClientEvent::RoundFailed {
+                                round_id: e.id.clone(),
+                                reason: e.reason.clone(),
+                            });
+
+                            return Err(Error::round_failed(format!(
                                 "failed registering in round {}: {}",
                                 e.id, e.reason
                             )));
@@ -502,16 +1725,13 @@ where
                         continue;
                     }
                 },
-                Some(Err(e)) => {
-                    return Err(Error::ark_server(e));
-                }
-                None => {
-                    return Err(Error::ark_server("dropped round event stream"));
+                Err(e) => {
+                    return Err(e);
                 }
             }
         }
 
-        #[derive(Debug, PartialEq, Eq)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
         enum RoundStep {
             Start,
             RoundSigningStarted,
@@ -530,8 +1750,84 @@ where
                     RoundStep::Finalized => RoundStep::Finalized, // we can't go further
                 }
             }
+
+            /// How long we wait for the round to move past this step.
+            fn timeout(&self, round_config: &RoundConfig) -> Duration {
+                match self {
+                    RoundStep::Start => round_config.registration_timeout,
+                    RoundStep::RoundSigningStarted => round_config.nonce_exchange_timeout,
+                    RoundStep::RoundSigningNoncesGenerated => round_config.signing_timeout,
+                    RoundStep::RoundFinalization | RoundStep::Finalized => {
+                        round_config.finalization_timeout
+                    }
+                }
+            }
+
+            fn timed_out(&self, round_config: &RoundConfig) -> Error {
+                let phase = match self {
+                    RoundStep::Start => "registration",
+                    RoundStep::RoundSigningStarted => "nonce exchange",
+                    RoundStep::RoundSigningNoncesGenerated => "signing",
+                    RoundStep::RoundFinalization | RoundStep::Finalized => "finalization",
+                };
+
+                Error::round_timed_out(format!(
+                    "round {phase} did not complete within {:?}",
+                    self.timeout(round_config)
+                ))
+            }
+        }
+    }
+}
+
+/// A subset of our round inputs which can be registered in a single round.
+#[derive(Debug)]
+struct RoundInputBatch {
+    boarding_inputs: Vec<round::OnChainInput>,
+    vtxo_inputs: Vec<round::VtxoInput>,
+    amount: Amount,
+}
+
+/// Split the inputs into batches of at most `max_inputs` inputs each, preferring boarding inputs.
+fn batch_round_inputs(
+    boarding_inputs: Vec<round::OnChainInput>,
+    vtxo_inputs: Vec<round::VtxoInput>,
+    max_inputs: Option<usize>,
+) -> Vec<RoundInputBatch> {
+    let max_inputs = max_inputs.unwrap_or(usize::MAX).max(1);
+
+    let new_batch = || RoundInputBatch {
+        boarding_inputs: Vec::new(),
+        vtxo_inputs: Vec::new(),
+        amount: Amount::ZERO,
+    };
+
+    let mut batches = Vec::new();
+    let mut batch = new_batch();
+
+    for input in boarding_inputs.into_iter() {
+        if batch.boarding_inputs.len() + batch.vtxo_inputs.len() == max_inputs {
+            batches.push(std::mem::replace(&mut batch, new_batch()));
+        }
+
+        batch.amount += input.amount();
+        batch.boarding_inputs.push(input);
+    }
+
+    for input in vtxo_inputs.into_iter() {
+        if batch.boarding_inputs.len() + batch.vtxo_inputs.len() == max_inputs {
+            batches.push(std::mem::replace(&mut batch, new_batch()));
         }
+
+        batch.amount += input.amount();
+        batch.vtxo_inputs.push(input);
     }
+
+    if !batch.boarding_inputs.is_empty() || !batch.vtxo_inputs.is_empty() {
+        batches.push(batch);
+    }
+
+    batches
 }
 
 enum RoundOutputType {
@@ -546,3 +1842,106 @@ enum RoundOutputType {
         change_amount: Amount,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_core::BoardingOutput;
+    use ark_core::DefaultVtxo;
+    use bitcoin::hashes::Hash;
+    use bitcoin::key::Keypair;
+    use bitcoin::key::Secp256k1;
+    use bitcoin::secp256k1::SecretKey;
+    use bitcoin::Network;
+    use bitcoin::Sequence;
+
+    fn inputs(
+        n_boarding: usize,
+        n_vtxos: usize,
+    ) -> (Vec<round::OnChainInput>, Vec<round::VtxoInput>) {
+        let secp = Secp256k1::new();
+        let server = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[1; 32]).unwrap());
+        let owner = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[2; 32]).unwrap());
+        let (server, _) = server.x_only_public_key();
+        let (owner, _) = owner.x_only_public_key();
+
+        let boarding_output = BoardingOutput::new(
+            &secp,
+            server,
+            owner,
+            "tr(unspendable,{and(pk(SERVER),pk(USER)),and(older(144),pk(USER))})",
+            Sequence::from_height(144),
+            Network::Regtest,
+        );
+        let vtxo = DefaultVtxo::new(
+            &secp,
+            server,
+            owner,
+            Sequence::from_height(144),
+            Network::Regtest,
+        );
+
+        let outpoint = |vout: usize| OutPoint::new(Txid::all_zeros(), vout as u32);
+
+        let boarding_inputs = (0..n_boarding)
+            .map(|i| {
+                round::OnChainInput::new(
+                    boarding_output.clone(),
+                    Amount::from_sat(1_000),
+                    outpoint(i),
+                )
+            })
+            .collect();
+        let vtxo_inputs = (0..n_vtxos)
+            .map(|i| {
+                round::VtxoInput::new(
+                    vtxo.clone(),
+                    Amount::from_sat(100),
+                    outpoint(n_boarding + i),
+                )
+            })
+            .collect();
+
+        (boarding_inputs, vtxo_inputs)
+    }
+
+    #[test]
+    fn inputs_are_split_across_batches_boarding_first() {
+        let (boarding_inputs, vtxo_inputs) = inputs(3, 4);
+
+        let batches = batch_round_inputs(boarding_inputs, vtxo_inputs, Some(3));
+
+        let shapes = batches
+            .iter()
+            .map(|batch| {
+                (
+                    batch.boarding_inputs.len(),
+                    batch.vtxo_inputs.len(),
+                    batch.amount,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            shapes,
+            [
+                (3, 0, Amount::from_sat(3_000)),
+                (0, 3, Amount::from_sat(300)),
+                (0, 1, Amount::from_sat(100)),
+            ]
+        );
+    }
+
+    #[test]
+    fn inputs_share_a_batch_without_a_limit() {
+        let (boarding_inputs, vtxo_inputs) = inputs(2, 5);
+
+        let batches = batch_round_inputs(boarding_inputs, vtxo_inputs, None);
+
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].boarding_inputs.len(), 2);
+        assert_eq!(batches[0].vtxo_inputs.len(), 5);
+        assert_eq!(batches[0].amount, Amount::from_sat(2_500));
+
+        assert!(batch_round_inputs(Vec::new(), Vec::new(), Some(3)).is_empty());
+    }
+}
@@ -0,0 +1,142 @@
+//! Withdraw from a round that we registered for, as long as the round has not started.
+//!
+//! See [`RoundHandle`].
+
+use crate::Error;
+use std::sync::Arc;
+use std::sync::Mutex;
+use tokio::sync::Notify;
+
+/// Lets another task withdraw our registration for a round, e.g. to register again with different
+/// outputs. See [`crate::Client::board_with_handle`] and [`crate::Client::off_board_with_handle`].
+///
+/// The registration can be withdrawn until we start signing the VTXO tree of the round. The
+/// VTXOs that were registered are then released for other operations right away, and the
+/// operation fails with an error for which [`Error::is_round_withdrawn`] holds.
+///
+/// A handle is meant for a single operation: once withdrawn, every further registration made
+/// with it is refused.
+#[derive(Debug, Clone, Default)]
+pub struct RoundHandle {
+    inner: Arc<RoundHandleInner>,
+}
+
+#[derive(Debug, Default)]
+struct RoundHandleInner {
+    phase: Mutex<RoundPhase>,
+    withdrawn: Notify,
+}
+
+/// Where the operation of a [`RoundHandle`] stands.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum RoundPhase {
+    /// Not registered for a round, e.g. before registering or between two rounds.
+    #[default]
+    Idle,
+    /// Registered for the next round, which has not started yet.
+    Registered,
+    /// Signing the round with ID `round_id`: the registration can no longer be withdrawn.
+    Signing { round_id: String },
+    /// The registration was withdrawn.
+    Withdrawn,
+}
+
+impl RoundHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn phase(&self) -> RoundPhase {
+        self.inner.phase.lock().expect("lock not poisoned").clone()
+    }
+
+    /// Withdraw our registration, or prevent it if we have not registered yet.
+    ///
+    /// Fails if we are already signing a round, since the round can no longer be left without
+    /// making it fail.
+    pub fn withdraw(&self) -> Result<(), Error> {
+        let mut phase = self.inner.phase.lock().expect("lock not poisoned");
+
+        match &*phase {
+            RoundPhase::Signing { round_id } => {
+                return Err(Error::ad_hoc(format!(
+                    "cannot withdraw from round {round_id}: already signing it"
+                )))
+            }
+            RoundPhase::Withdrawn => return Ok(()),
+            RoundPhase::Idle | RoundPhase::Registered => {}
+        }
+
+        tracing::info!(phase = ?*phase, "Withdrawing round registration");
+
+        *phase = RoundPhase::Withdrawn;
+        drop(phase);
+
+        self.inner.withdrawn.notify_waiters();
+
+        Ok(())
+    }
+
+    /// Mark the handle as registered for the next round, until the returned guard is dropped.
+    ///
+    /// Fails if the handle was withdrawn.
+    pub(crate) fn register(&self) -> Result<RoundRegistration<'_>, Error> {
+        let mut phase = self.inner.phase.lock().expect("lock not poisoned");
+
+        if *phase == RoundPhase::Withdrawn {
+            return Err(Error::round_withdrawn("round registration withdrawn"));
+        }
+
+        *phase = RoundPhase::Registered;
+
+        Ok(RoundRegistration { handle: self })
+    }
+
+    /// Pass the point of no return: from now on, we sign the round with ID `round_id`.
+    ///
+    /// Fails if the handle was withdrawn before.
+    pub(crate) fn start_signing(&self, round_id: &str) -> Result<(), Error> {
+        let mut phase = self.inner.phase.lock().expect("lock not poisoned");
+
+        if *phase == RoundPhase::Withdrawn {
+            return Err(Error::round_withdrawn(format!(
+                "round registration withdrawn before signing round {round_id}"
+            )));
+        }
+
+        *phase = RoundPhase::Signing {
+            round_id: round_id.to_string(),
+        };
+
+        Ok(())
+    }
+
+    /// Resolve once the handle is withdrawn.
+    pub(crate) async fn withdrawn(&self) {
+        loop {
+            // Created before checking the phase, so that we cannot miss a notification.
+            let notified = self.inner.withdrawn.notified();
+
+            if *self.inner.phase.lock().expect("lock not poisoned") == RoundPhase::Withdrawn {
+                return;
+            }
+
+            notified.await;
+        }
+    }
+}
+
+/// Our registration for a round, see [`RoundHandle::register`].
+pub(crate) struct RoundRegistration<'a> {
+    handle: &'a RoundHandle,
+}
+
+impl Drop for RoundRegistration<'_> {
+    fn drop(&mut self) {
+        let mut phase = self.handle.inner.phase.lock().expect("lock not poisoned");
+
+        if *phase != RoundPhase::Withdrawn {
+            *phase = RoundPhase::Idle;
+        }
+    }
+}
@@ -1,82 +1,324 @@
 use crate::error::ErrorContext;
+use crate::operation::OperationId;
+use crate::reservation::ReservationId;
+use crate::transport::NetworkTransport;
 use crate::wallet::BoardingWallet;
 use crate::wallet::OnchainWallet;
+use crate::wallet::VtxoOrigin;
 use crate::Blockchain;
 use crate::Client;
 use crate::Error;
-use ark_core::coin_select::select_vtxos;
 use ark_core::redeem;
-use ark_core::redeem::create_and_sign_redeem_transaction;
+use ark_core::redeem::batch_redeem_transaction_sighashes;
+use ark_core::redeem::create_and_sign_batch_redeem_transaction_with;
+use ark_core::redeem::ChangeDecision;
+use ark_core::redeem::ChangePolicy;
 use ark_core::ArkAddress;
 use bitcoin::Amount;
+use bitcoin::OutPoint;
 use bitcoin::Psbt;
 
-impl<B, W> Client<B, W>
+/// The result of [`Client::send_vtxo_with_outcome`].
+#[derive(Debug, Clone)]
+pub struct PaymentOutcome {
+    pub operation_id: OperationId,
+    /// The redeem transaction, as submitted to the Ark server.
+    pub redeem_psbt: Psbt,
+    /// What was done with the change, according to the client's [`ChangePolicy`].
+    pub change: ChangeDecision,
+}
+
+impl<B, W, T> Client<B, W, T>
 where
     B: Blockchain,
     W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
 {
+    /// Send `amount` to `address` out of round.
+    ///
+    /// Fails with an error for which [`Error::is_server_mismatch`] holds if `address` belongs to
+    /// a different Ark server than ours.
     pub async fn send_vtxo(&self, address: ArkAddress, amount: Amount) -> Result<Psbt, Error> {
+        let PaymentOutcome { redeem_psbt, .. } =
+            self.send_vtxo_with_outcome(address, amount).await?;
+
+        Ok(redeem_psbt)
+    }
+
+    /// Like [`Client::send_vtxo`], but also reports whether a change VTXO was created.
+    ///
+    /// See [`OfflineClient::with_change_policy`](crate::OfflineClient::with_change_policy).
+    pub async fn send_vtxo_with_outcome(
+        &self,
+        address: ArkAddress,
+        amount: Amount,
+    ) -> Result<PaymentOutcome, Error> {
+        self.send_vtxos_with_outcome(vec![(address, amount)]).await
+    }
+
+    /// Pay every `(address, amount)` pair in `outputs` out of round, with a single redeem
+    /// transaction.
+    ///
+    /// The change, if any, goes to a single change output. Fails if any amount is below the dust
+    /// limit of the Ark server, or with an error for which [`Error::is_server_mismatch`] holds if
+    /// any address belongs to a different Ark server than ours.
+    pub async fn send_vtxos(&self, outputs: Vec<(ArkAddress, Amount)>) -> Result<Psbt, Error> {
+        let PaymentOutcome { redeem_psbt, .. } = self.send_vtxos_with_outcome(outputs).await?;
+
+        Ok(redeem_psbt)
+    }
+
+    /// Like [`Client::send_vtxos`], but also reports whether a change VTXO was created.
+    #[tracing::instrument(skip_all, fields(operation_id = tracing::field::Empty))]
+    pub async fn send_vtxos_with_outcome(
+        &self,
+        outputs: Vec<(ArkAddress, Amount)>,
+    ) -> Result<PaymentOutcome, Error> {
+        let operation_id = OperationId::start();
+
+        let outcome = async {
+            let amount = self.check_payment_outputs(&outputs)?;
+
+            // Reserve the coins we select, so that concurrent operations cannot select them too.
+            let reservation_id = self.reserve(amount).await?;
+
+            self.send_or_release_reserved_vtxos(operation_id, reservation_id, &outputs)
+                .await
+        }
+        .await;
+
+        self.record_operation(operation_id, "send_vtxos", outcome)
+    }
+
+    /// Like [`Client::send_vtxo_with_outcome`], but spending the VTXOs of the reservation with ID
+    /// `reservation_id`, made with [`Client::reserve`].
+    ///
+    /// The reservation is consumed if the payment goes through. Otherwise, its VTXOs stay
+    /// reserved.
+    #[tracing::instrument(skip_all, fields(operation_id = tracing::field::Empty, %reservation_id))]
+    pub async fn send_vtxo_with_reservation(
+        &self,
+        reservation_id: ReservationId,
+        address: ArkAddress,
+        amount: Amount,
+    ) -> Result<PaymentOutcome, Error> {
+        let operation_id = OperationId::start();
+
+        let outputs = [(address, amount)];
+        let outcome = async {
+            self.check_payment_outputs(&outputs)?;
+
+            self.send_reserved_vtxos(operation_id, reservation_id, &outputs)
+                .await
+        }
+        .await;
+
+        self.record_operation(operation_id, "send_vtxo_with_reservation", outcome)
+    }
+
+    /// Like [`Client::send_vtxo_with_outcome`], but spending exactly the VTXOs with outpoints in
+    /// `vtxos` instead of selecting them automatically.
+    ///
+    /// Any value in excess of `amount` and the fee is sent back to us as change, according to the
+    /// client's [`ChangePolicy`]. See [`Client::list_spendable_outpoints`] to build a coin
+    /// control interface.
+    #[tracing::instrument(skip_all, fields(operation_id = tracing::field::Empty))]
+    pub async fn send_vtxo_with_inputs(
+        &self,
+        vtxos: &[OutPoint],
+        address: ArkAddress,
+        amount: Amount,
+    ) -> Result<PaymentOutcome, Error> {
+        let operation_id = OperationId::start();
+
+        let outputs = [(address, amount)];
+        let outcome = async {
+            self.check_payment_outputs(&outputs)?;
+
+            let reservation_id = self.reserve_vtxos(vtxos).await?;
+
+            self.send_or_release_reserved_vtxos(operation_id, reservation_id, &outputs)
+                .await
+        }
+        .await;
+
+        self.record_operation(operation_id, "send_vtxo_with_inputs", outcome)
+    }
+
+    /// Check that we can pay every output of a payment, returning the total amount paid.
+    fn check_payment_outputs(&self, outputs: &[(ArkAddress, Amount)]) -> Result<Amount, Error> {
+        if outputs.is_empty() {
+            return Err(Error::ad_hoc("cannot send VTXOs without outputs"));
+        }
+
+        for (address, amount) in outputs.iter() {
+            self.check_recipient_server(address)?;
+
+            if *amount < self.server_info.dust {
+                return Err(Error::coin_select(format!(
+                    "cannot send {amount}, below the dust limit of {}",
+                    self.server_info.dust
+                )));
+            }
+        }
+
+        Ok(outputs.iter().map(|(_, amount)| *amount).sum())
+    }
+
+    pub(crate) fn check_recipient_server(&self, address: &ArkAddress) -> Result<(), Error> {
+        let server = self.server_info.pk.x_only_public_key().0;
+        if address.server() != server {
+            return Err(Error::server_mismatch(server, address.server()));
+        }
+
+        Ok(())
+    }
+
+    /// Like [`Client::send_reserved_vtxos`], but releasing the reservation if the payment fails.
+    async fn send_or_release_reserved_vtxos(
+        &self,
+        operation_id: OperationId,
+        reservation_id: ReservationId,
+        outputs: &[(ArkAddress, Amount)],
+    ) -> Result<PaymentOutcome, Error> {
+        let outcome = self
+            .send_reserved_vtxos(operation_id, reservation_id, outputs)
+            .await;
+
+        if outcome.is_err() {
+            if let Err(e) = self.release_reservation(reservation_id) {
+                tracing::warn!(%reservation_id, "Failed to release reservation: {e}");
+            }
+        }
+
+        outcome
+    }
+
+    async fn send_reserved_vtxos(
+        &self,
+        operation_id: OperationId,
+        reservation_id: ReservationId,
+        outputs: &[(ArkAddress, Amount)],
+    ) -> Result<PaymentOutcome, Error> {
+        let reserved_outpoints = self.reservations.start_spending(reservation_id)?;
+
+        let outcome = self
+            .spend_outpoints(operation_id, reserved_outpoints, outputs)
+            .await;
+
+        self.reservations
+            .finish_spending(reservation_id, outcome.is_ok());
+
+        outcome
+    }
+
+    async fn spend_outpoints(
+        &self,
+        operation_id: OperationId,
+        outpoints: Vec<OutPoint>,
+        outputs: &[(ArkAddress, Amount)],
+    ) -> Result<PaymentOutcome, Error> {
+        self.check_server_info().await?;
+
         let spendable_vtxos = self
             .spendable_vtxos()
             .await
             .context("failed to get spendable VTXOs")?;
 
-        // Run coin selection algorithm on candidate spendable VTXOs.
-        let spendable_vtxo_outpoints = spendable_vtxos
-            .iter()
-            .flat_map(|(vtxos, _)| vtxos.clone())
-            .map(|vtxo| ark_core::coin_select::VtxoOutPoint {
-                outpoint: vtxo.outpoint,
-                expire_at: vtxo.expire_at,
-                amount: vtxo.amount,
-            })
-            .collect::<Vec<_>>();
-
-        let selected_coins = select_vtxos(
-            spendable_vtxo_outpoints,
-            amount,
-            self.server_info.dust,
-            true,
-        )
-        .map_err(Error::from)
-        .context("failed to select coins")?;
-
-        let vtxo_inputs = selected_coins
+        let vtxo_inputs = outpoints
             .into_iter()
-            .map(|vtxo_outpoint| {
-                let vtxo = spendable_vtxos
-                    .clone()
-                    .into_iter()
+            .map(|outpoint| {
+                spendable_vtxos
+                    .iter()
                     .find_map(|(vtxo_outpoints, vtxo)| {
                         vtxo_outpoints
                             .iter()
-                            .any(|v| v.outpoint == vtxo_outpoint.outpoint)
-                            .then_some(vtxo)
+                            .find(|v| v.outpoint == outpoint)
+                            .map(|v| redeem::VtxoInput::new(vtxo.clone(), v.amount, v.outpoint))
+                    })
+                    .ok_or_else(|| {
+                        Error::ad_hoc(format!("reserved VTXO {outpoint} is no longer spendable"))
                     })
-                    .expect("to find matching default VTXO");
-
-                redeem::VtxoInput::new(vtxo, vtxo_outpoint.amount, vtxo_outpoint.outpoint)
             })
-            .collect::<Vec<_>>();
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.spend_vtxo_inputs(operation_id, &vtxo_inputs, outputs)
+            .await
+    }
+
+    /// Spend `vtxo_inputs` to `outputs` with a redeem transaction, sending the change back to us.
+    ///
+    /// The inputs are not screened: the caller must make sure that they may be spent.
+    pub(crate) async fn spend_vtxo_inputs(
+        &self,
+        operation_id: OperationId,
+        vtxo_inputs: &[redeem::VtxoInput],
+        outputs: &[(ArkAddress, Amount)],
+    ) -> Result<PaymentOutcome, Error> {
+        let change_address = self.change_address()?;
 
-        let (change_address, _) = self.get_offchain_address();
+        let change_policy = ChangePolicy {
+            min_change: self
+                .inner
+                .change_policy
+                .min_change
+                .max(self.server_info.dust),
+            ..self.inner.change_policy
+        };
 
-        let signed_redeem_psbt = create_and_sign_redeem_transaction(
-            self.kp(),
-            &address,
-            amount,
+        let sighashes = batch_redeem_transaction_sighashes(
+            outputs,
             &change_address,
-            &vtxo_inputs,
+            vtxo_inputs,
+            change_policy,
         )
         .map_err(Error::from)?;
+        let sigs = self
+            .sign_sighashes(sighashes)
+            .await
+            .context("failed to sign redeem transaction")?;
+
+        let (signed_redeem_psbt, change) = create_and_sign_batch_redeem_transaction_with(
+            sigs.sign_for_pk_fn(),
+            outputs,
+            &change_address,
+            vtxo_inputs,
+            change_policy,
+        )
+        .map_err(Error::from)?;
+
+        match change {
+            ChangeDecision::AbsorbedIntoFee(change) => tracing::info!(
+                change = %self.inner.privacy.amount(change),
+                "Change too small for a VTXO, absorbed into fee"
+            ),
+            ChangeDecision::AddedToPayment(change) => tracing::info!(
+                change = %self.inner.privacy.amount(change),
+                "Change too small for a VTXO, added to payment"
+            ),
+            ChangeDecision::NoChange | ChangeDecision::Change(_) => {}
+        }
 
         self.network_client()
             .submit_redeem_transaction(signed_redeem_psbt.clone())
             .await
-            .map_err(Error::ark_server)
             .context("failed to complete payment request")?;
 
-        Ok(signed_redeem_psbt)
+        let redeem_txid = signed_redeem_psbt.unsigned_tx.compute_txid();
+        if let Err(e) = self.record_redeem_vtxo_origins(
+            &signed_redeem_psbt,
+            VtxoOrigin::SendChange {
+                redeem_txid,
+                operation_id,
+            },
+        ) {
+            tracing::warn!(%redeem_txid, "Failed to record origin of change VTXO: {e}");
+        }
+
+        Ok(PaymentOutcome {
+            operation_id,
+            redeem_psbt: signed_redeem_psbt,
+            change,
+        })
     }
 }
@@ -0,0 +1,305 @@
+//! Sign for our VTXOs with a key held outside of the client, e.g. by a hardware wallet or a remote
+//! signing service.
+//!
+//! Set an [`ArkSigner`] with [`crate::OfflineClient::with_ark_signer`]. The default offchain
+//! address is then owned by the key of the signer, and every Schnorr signature spending one of its
+//! VTXOs is requested from it: forfeit transactions when joining a round,
+//! redeem transactions when sending VTXOs and on-chain transactions spending exited VTXOs.
+//!
+//! The VTXO tree of a round is cosigned with MuSig2. By default, the client generates a fresh key
+//! for every round, so the signer is not involved in it. A signer which supports MuSig2 can cosign
+//! with its own key instead, see [`ArkSigner::musig2_cosigner_pk`].
+//!
+//! The inputs of the round transaction spending our boarding outputs belong to the boarding
+//! wallet rather than to the key of the signer, and are signed by
+//! [`crate::wallet::ExternalSigner`] if one is set.
+
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use ark_core::musig;
+use ark_core::musig::MusigAggNonce;
+use ark_core::musig::MusigKeyAggCache;
+use ark_core::musig::MusigPartialSignature;
+use ark_core::musig::MusigPubNonce;
+use ark_core::musig::MusigSecNonce;
+use bitcoin::key::Keypair;
+use bitcoin::key::Secp256k1;
+use bitcoin::secp256k1;
+use bitcoin::secp256k1::schnorr;
+use bitcoin::secp256k1::All;
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::XOnlyPublicKey;
+use futures::future;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Produces Schnorr signatures for a single key, without exposing the secret key to the client.
+///
+/// The client checks every signature before using it.
+pub trait ArkSigner: Send + Sync {
+    /// The public key of the key this signer signs with.
+    fn x_only_public_key(&self) -> XOnlyPublicKey;
+
+    /// Sign the BIP340 `msg`, which is a Taproot script spend sighash.
+    fn sign_schnorr(
+        &self,
+        msg: secp256k1::Message,
+    ) -> BoxFuture<'_, Result<schnorr::Signature, Error>>;
+
+    /// The key to cosign the VTXO trees of our rounds with, if this signer supports MuSig2.
+    ///
+    /// By default `None`, and the client cosigns with a fresh key for every round. Otherwise, the
+    /// client asks the signer for a nonce for every node of the VTXO tree that it cosigns with
+    /// [`ArkSigner::musig2_nonce`], and for their partial signatures with
+    /// [`ArkSigner::musig2_partial_sign`]. Every partial signature is verified.
+    ///
+    /// The same key is then used in every round, which links our rounds together for the Ark
+    /// server.
+    fn musig2_cosigner_pk(&self) -> Option<PublicKey> {
+        None
+    }
+
+    /// Generate a MuSig2 nonce pair to sign `msg` for the aggregate key of `key_agg_cache`,
+    /// keeping the secret nonce for [`ArkSigner::musig2_partial_sign`].
+    ///
+    /// Returns the public nonce. Only called if [`ArkSigner::musig2_cosigner_pk`] is set.
+    fn musig2_nonce(
+        &self,
+        key_agg_cache: MusigKeyAggCache,
+        msg: secp256k1::Message,
+    ) -> BoxFuture<'_, Result<MusigPubNonce, Error>> {
+        let _ = (key_agg_cache, msg);
+
+        future::ready(Err(Error::ad_hoc("signer does not support MuSig2"))).boxed()
+    }
+
+    /// Partially sign `msg` for the aggregate key of `key_agg_cache`, with the secret nonce of
+    /// `pub_nonce` and the aggregate nonce of all cosigners `agg_nonce`.
+    ///
+    /// The secret nonce must be forgotten, so that it is never used again.
+    fn musig2_partial_sign(
+        &self,
+        key_agg_cache: MusigKeyAggCache,
+        msg: secp256k1::Message,
+        pub_nonce: MusigPubNonce,
+        agg_nonce: MusigAggNonce,
+    ) -> BoxFuture<'_, Result<MusigPartialSignature, Error>> {
+        let _ = (key_agg_cache, msg, pub_nonce, agg_nonce);
+
+        future::ready(Err(Error::ad_hoc("signer does not support MuSig2"))).boxed()
+    }
+}
+
+/// An [`ArkSigner`] backed by a [`Keypair`] held in process.
+pub struct KeypairSigner {
+    kp: Keypair,
+    secp: Secp256k1<All>,
+    secp_zkp: zkp::Secp256k1<zkp::All>,
+    /// Whether to cosign VTXO trees with `kp`, see [`KeypairSigner::with_musig2_cosigning`].
+    musig2_cosigning: bool,
+    /// The secret nonces handed out by [`ArkSigner::musig2_nonce`], by public nonce.
+    sec_nonces: Mutex<HashMap<[u8; 66], MusigSecNonce>>,
+}
+
+impl KeypairSigner {
+    pub fn new(kp: Keypair) -> Self {
+        Self {
+            kp,
+            secp: Secp256k1::new(),
+            secp_zkp: zkp::Secp256k1::new(),
+            musig2_cosigning: false,
+            sec_nonces: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Cosign the VTXO trees of our rounds with the keypair, see
+    /// [`ArkSigner::musig2_cosigner_pk`].
+    pub fn with_musig2_cosigning(mut self) -> Self {
+        self.musig2_cosigning = true;
+        self
+    }
+}
+
+impl ArkSigner for KeypairSigner {
+    fn x_only_public_key(&self) -> XOnlyPublicKey {
+        self.kp.x_only_public_key().0
+    }
+
+    fn sign_schnorr(
+        &self,
+        msg: secp256k1::Message,
+    ) -> BoxFuture<'_, Result<schnorr::Signature, Error>> {
+        future::ready(Ok(self.secp.sign_schnorr_no_aux_rand(&msg, &self.kp))).boxed()
+    }
+
+    fn musig2_cosigner_pk(&self) -> Option<PublicKey> {
+        self.musig2_cosigning.then(|| self.kp.public_key())
+    }
+
+    fn musig2_nonce(
+        &self,
+        _: MusigKeyAggCache,
+        _: secp256k1::Message,
+    ) -> BoxFuture<'_, Result<MusigPubNonce, Error>> {
+        let nonce_pair = musig::generate_nonce_pair(
+            &mut rand::thread_rng(),
+            &self.secp_zkp,
+            self.kp.public_key(),
+        );
+
+        let pub_nonce = nonce_pair
+            .map_err(Error::from)
+            .map(|(sec_nonce, pub_nonce)| {
+                self.sec_nonces
+                    .lock()
+                    .expect("lock not poisoned")
+                    .insert(pub_nonce.serialize(), sec_nonce);
+
+                pub_nonce
+            });
+
+        future::ready(pub_nonce).boxed()
+    }
+
+    fn musig2_partial_sign(
+        &self,
+        key_agg_cache: MusigKeyAggCache,
+        msg: secp256k1::Message,
+        pub_nonce: MusigPubNonce,
+        agg_nonce: MusigAggNonce,
+    ) -> BoxFuture<'_, Result<MusigPartialSignature, Error>> {
+        let sec_nonce = self
+            .sec_nonces
+            .lock()
+            .expect("lock not poisoned")
+            .remove(&pub_nonce.serialize());
+
+        let partial_sig = match sec_nonce {
+            Some(sec_nonce) => musig::partial_sign(
+                &self.secp_zkp,
+                &self.kp,
+                &key_agg_cache,
+                msg,
+                sec_nonce,
+                agg_nonce,
+            )
+            .map_err(Error::from),
+            None => Err(Error::ad_hoc("unknown or already used MuSig2 nonce")),
+        };
+
+        future::ready(partial_sig).boxed()
+    }
+}
+
+/// Signatures collected ahead of building a transaction, indexed by public key and message.
+pub(crate) struct Signatures(HashMap<(XOnlyPublicKey, secp256k1::Message), schnorr::Signature>);
+
+impl Signatures {
+    /// A `sign_for_pk_fn` for the `ark_core` functions which sign with a closure.
+    pub(crate) fn sign_for_pk_fn(
+        &self,
+    ) -> impl Fn(&XOnlyPublicKey, &secp256k1::Message) -> Result<schnorr::Signature, ark_core::Error> + '_
+    {
+        |pk, msg| {
+            self.0
+                .get(&(*pk, *msg))
+                .copied()
+                .ok_or_else(|| ark_core::Error::ad_hoc(format!("missing signature by {pk}")))
+        }
+    }
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Sign every message in `sighashes` with the key of its public key, asking the
+    /// [`ArkSigner`] for those of its key and using our keypairs for the rest.
+    pub(crate) async fn sign_sighashes(
+        &self,
+        sighashes: Vec<(XOnlyPublicKey, secp256k1::Message)>,
+    ) -> Result<Signatures, Error> {
+        let ark_signer = self.inner.ark_signer.as_ref();
+        let keypairs = self.keypairs();
+
+        let mut sigs = HashMap::with_capacity(sighashes.len());
+        for (pk, msg) in sighashes {
+            let sig = match ark_signer {
+                Some(ark_signer) if ark_signer.x_only_public_key() == pk => {
+                    ark_signer.sign_schnorr(msg).await?
+                }
+                _ => {
+                    let kp = keypairs
+                        .iter()
+                        .find(|kp| kp.x_only_public_key().0 == pk)
+                        .ok_or_else(|| Error::ad_hoc(format!("no key to sign for {pk}")))?;
+
+                    self.secp().sign_schnorr_no_aux_rand(&msg, kp)
+                }
+            };
+
+            sigs.insert((pk, msg), sig);
+        }
+
+        Ok(Signatures(sigs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_core::musig::aggregate_nonces;
+    use ark_core::musig::verify_partial_signature;
+    use futures::executor::block_on;
+
+    #[test]
+    fn keypair_signer_cosigns_with_musig2_once_per_nonce() {
+        let secp = zkp::Secp256k1::new();
+        let mut rng = rand::thread_rng();
+
+        let kp = Keypair::new(&Secp256k1::new(), &mut rng);
+        let other_kp = Keypair::new(&Secp256k1::new(), &mut rng);
+
+        let signer = KeypairSigner::new(kp);
+        assert_eq!(signer.musig2_cosigner_pk(), None);
+
+        let signer = signer.with_musig2_cosigning();
+        assert_eq!(signer.musig2_cosigner_pk(), Some(kp.public_key()));
+
+        let key_agg_cache = musig::key_agg_cache(&secp, &[kp.public_key(), other_kp.public_key()]);
+        let msg = secp256k1::Message::from_digest([7; 32]);
+
+        let pub_nonce = block_on(signer.musig2_nonce(key_agg_cache.clone(), msg)).unwrap();
+        let (_, other_pub_nonce) =
+            musig::generate_nonce_pair(&mut rng, &secp, other_kp.public_key()).unwrap();
+        let agg_nonce = aggregate_nonces(&secp, &[pub_nonce, other_pub_nonce]);
+
+        let partial_sig =
+            block_on(signer.musig2_partial_sign(key_agg_cache.clone(), msg, pub_nonce, agg_nonce))
+                .unwrap();
+
+        verify_partial_signature(
+            &secp,
+            &key_agg_cache,
+            agg_nonce,
+            msg,
+            partial_sig,
+            pub_nonce,
+            kp.public_key(),
+        )
+        .unwrap();
+
+        // A nonce must never be used twice.
+        assert!(
+            block_on(signer.musig2_partial_sign(key_agg_cache, msg, pub_nonce, agg_nonce)).is_err()
+        );
+    }
+}
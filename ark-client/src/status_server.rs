@@ -0,0 +1,283 @@
+//! A tiny HTTP server exposing the status of the client as JSON, so that headless deployments can
+//! be probed without linking against the client.
+//!
+//! Only available with the `status-server` feature. See [`Client::serve_status`].
+
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::ExitTxStatus;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::DataFreshness;
+use crate::Error;
+use jiff::Timestamp;
+use serde_json::json;
+use serde_json::Value;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+
+/// The largest request line and headers we read before giving up on a request.
+const MAX_REQUEST_HEAD_BYTES: u64 = 8 * 1024;
+
+/// How long a connection may take to send its request and receive the response.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Answer HTTP `GET` requests on `listener` with the status of the client as JSON, until
+    /// accepting a connection fails.
+    ///
+    /// The endpoints are:
+    ///
+    /// - `/health`: whether the server info is live and whether the Ark server is under
+    ///   maintenance. This never contacts the Ark server.
+    /// - `/balances`: our off-chain balance, see [`Client::offchain_balance`].
+    /// - `/operations`: active reservations and exit transactions which are not confirmed yet.
+    /// - `/metrics`: the number of VTXOs, reservations and exit transactions, and how long the
+    ///   status has been served for.
+    ///
+    /// The status is not authenticated, so `listener` must be bound to a loopback address.
+    /// Requests are answered one at a time.
+    pub async fn serve_status(&self, listener: TcpListener) -> Result<(), Error> {
+        let local_addr = listener
+            .local_addr()
+            .map_err(|e| Error::ad_hoc(format!("failed to get status server address: {e}")))?;
+        if !local_addr.ip().is_loopback() {
+            return Err(Error::ad_hoc(format!(
+                "refusing to serve client status on non-loopback address {local_addr}"
+            )));
+        }
+
+        tracing::info!(%local_addr, "Serving client status");
+
+        let started_at = Instant::now();
+        let mut requests_served = 0;
+        loop {
+            let (stream, peer) = listener
+                .accept()
+                .await
+                .map_err(|e| Error::ad_hoc(format!("failed to accept status request: {e}")))?;
+
+            let answered = tokio::time::timeout(
+                REQUEST_TIMEOUT,
+                self.answer_status_request(stream, started_at, requests_served),
+            )
+            .await;
+
+            match answered {
+                Ok(Ok(())) => requests_served += 1,
+                Ok(Err(e)) => tracing::warn!(%peer, "Failed to answer status request: {e}"),
+                Err(_) => tracing::warn!(%peer, "Status request timed out"),
+            }
+        }
+    }
+
+    async fn answer_status_request(
+        &self,
+        stream: TcpStream,
+        started_at: Instant,
+        requests_served: u64,
+    ) -> Result<(), Error> {
+        let mut stream = BufReader::new(stream);
+
+        // We only need the request line, but the headers must be read before responding.
+        let mut head = (&mut stream).take(MAX_REQUEST_HEAD_BYTES);
+        let mut request_line = String::new();
+        head.read_line(&mut request_line).await.map_err(io_error)?;
+        loop {
+            let mut header = String::new();
+            let read = head.read_line(&mut header).await.map_err(io_error)?;
+            if read == 0 || header.trim().is_empty() {
+                break;
+            }
+        }
+
+        let mut request_line = request_line.split_whitespace();
+        let method = request_line.next().unwrap_or_default();
+        let path = request_line.next().unwrap_or_default();
+        let path = path.split('?').next().unwrap_or_default();
+
+        let status = match (method, path) {
+            ("GET", "/health") => Ok(self.health_status()),
+            ("GET", "/balances") => self.balances_status().await,
+            ("GET", "/operations") => self.operations_status(),
+            ("GET", "/metrics") => self.metrics_status(started_at, requests_served).await,
+            ("GET", _) => {
+                let body = json!({ "error": "not found" });
+                return respond(stream.get_mut(), "404 Not Found", &body).await;
+            }
+            _ => {
+                let body = json!({ "error": "method not allowed" });
+                return respond(stream.get_mut(), "405 Method Not Allowed", &body).await;
+            }
+        };
+
+        let (status_line, body) = match status {
+            Ok(status) => ("200 OK", status),
+            Err(e) => (
+                "500 Internal Server Error",
+                json!({ "error": e.to_string() }),
+            ),
+        };
+
+        respond(stream.get_mut(), status_line, &body).await
+    }
+
+    fn health_status(&self) -> Value {
+        let is_under_maintenance = self.is_under_maintenance(Timestamp::now().as_second());
+
+        json!({
+            "status": if is_under_maintenance { "maintenance" } else { "ok" },
+            "ark_server_url": self.network_client().url(),
+            "server_info_is_live": self.server_info_is_live,
+            "network": self.server_info.network.to_string(),
+        })
+    }
+
+    async fn balances_status(&self) -> Result<Value, Error> {
+        let balance = self.offchain_balance().await?;
+
+        let freshness = match balance.freshness() {
+            DataFreshness::Live => json!("live"),
+            DataFreshness::Cached { updated_at } => json!({ "cached_at": updated_at }),
+        };
+        let sweep_warning = balance.sweep_warning().map(|warning| {
+            json!({
+                "amount": warning.amount.to_sat(),
+                "earliest_sweepable_after": warning.earliest_sweepable_after,
+            })
+        });
+
+        Ok(json!({
+            "offchain": {
+                "pending": balance.pending().to_sat(),
+                "awaiting_confirmations": balance.awaiting_confirmations().to_sat(),
+                "confirmed": balance.confirmed().to_sat(),
+                "flagged": balance.flagged().to_sat(),
+                "pending_review": balance.pending_review().to_sat(),
+                "rejected": balance.rejected().to_sat(),
+                "recoverable": balance.recoverable().to_sat(),
+                "total": balance.total().to_sat(),
+            },
+            "sweep_warning": sweep_warning,
+            "freshness": freshness,
+        }))
+    }
+
+    fn operations_status(&self) -> Result<Value, Error> {
+        let reservations = self
+            .reservations()
+            .into_iter()
+            .map(|reservation| {
+                json!({
+                    "id": reservation.id.to_string(),
+                    "amount": reservation.amount.to_sat(),
+                    "vtxos": reservation
+                        .vtxos
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let exits = self
+            .exit_progress()?
+            .into_iter()
+            .filter(|exit_tx| exit_tx.status != ExitTxStatus::Confirmed)
+            .map(|exit_tx| {
+                json!({
+                    "txid": exit_tx.tx.compute_txid().to_string(),
+                    "status": exit_tx_status(exit_tx.status),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(json!({
+            "reservations": reservations,
+            "exits": exits,
+        }))
+    }
+
+    async fn metrics_status(
+        &self,
+        started_at: Instant,
+        requests_served: u64,
+    ) -> Result<Value, Error> {
+        let spendable_vtxos = self
+            .spendable_vtxos()
+            .await?
+            .iter()
+            .map(|(vtxos, _)| vtxos.len())
+            .sum::<usize>();
+        let recoverable_vtxos = self
+            .recoverable_vtxos()
+            .await?
+            .iter()
+            .map(|(vtxos, _)| vtxos.len())
+            .sum::<usize>();
+
+        let exit_txs = self.exit_progress()?;
+        let count_exit_txs = |status: ExitTxStatus| {
+            exit_txs
+                .iter()
+                .filter(|exit_tx| exit_tx.status == status)
+                .count()
+        };
+
+        Ok(json!({
+            "uptime_seconds": started_at.elapsed().as_secs(),
+            "status_requests_served": requests_served,
+            "spendable_vtxos": spendable_vtxos,
+            "recoverable_vtxos": recoverable_vtxos,
+            "reservations": self.reservations().len(),
+            "exit_txs": {
+                "pending": count_exit_txs(ExitTxStatus::Pending),
+                "broadcast": count_exit_txs(ExitTxStatus::Broadcast),
+                "confirmed": count_exit_txs(ExitTxStatus::Confirmed),
+            },
+        }))
+    }
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, body: &Value) -> Result<(), Error> {
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\
+         \r\n\
+         {body}",
+        body.len()
+    );
+
+    stream
+        .write_all(response.as_bytes())
+        .await
+        .map_err(io_error)?;
+    stream.shutdown().await.map_err(io_error)
+}
+
+fn exit_tx_status(status: ExitTxStatus) -> &'static str {
+    match status {
+        ExitTxStatus::Pending => "pending",
+        ExitTxStatus::Broadcast => "broadcast",
+        ExitTxStatus::Confirmed => "confirmed",
+    }
+}
+
+fn io_error(error: std::io::Error) -> Error {
+    Error::ad_hoc(format!("status request failed: {error}"))
+}
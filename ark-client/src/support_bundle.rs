@@ -0,0 +1,226 @@
+//! A redacted snapshot of the state of the client, for users to attach to bug reports.
+//!
+//! Only available with the `support-bundle` feature. See [`Client::generate_support_bundle`].
+
+use crate::operation::OperationRecord;
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use jiff::Timestamp;
+use serde_json::json;
+use serde_json::Value;
+
+/// Written in place of an address or another long identifier found in an error message.
+const REDACTED_ADDRESS: &str = "<address>";
+
+/// Written in place of a transaction ID found in an error message.
+const REDACTED_TXID: &str = "<txid>";
+
+/// Written in place of an amount found in an error message.
+const REDACTED_AMOUNT: &str = "<amount>";
+
+/// The units which mark the number before them as an amount.
+const AMOUNT_UNITS: &[&str] = &["BTC", "btc", "sat", "sats", "SAT", "msat"];
+
+/// Identifiers at least this long are redacted, since they are likely addresses or keys.
+const MIN_REDACTED_IDENTIFIER_LEN: usize = 26;
+
+/// A redacted snapshot of the state of the client, see [`Client::generate_support_bundle`].
+#[derive(Debug, Clone)]
+pub struct SupportBundle(Value);
+
+impl SupportBundle {
+    /// The bundle as a JSON document.
+    pub fn as_json(&self) -> &Value {
+        &self.0
+    }
+
+    /// The bundle as pretty-printed JSON, ready to be written to a file.
+    pub fn to_json_string(&self) -> String {
+        serde_json::to_string_pretty(&self.0).expect("JSON value serializes")
+    }
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Collect the information needed to investigate a bug report: the version of this crate, the
+    /// configuration of the client, the server info in use, the [operation
+    /// journal](Client::operation_journal) and the errors of the operations which failed.
+    ///
+    /// The bundle never includes keys, addresses, VTXOs or balances. Error messages are scrubbed
+    /// of anything which looks like an address, a transaction ID or an amount, and credentials are
+    /// removed from the Ark server URL.
+    ///
+    /// This never contacts the Ark server, so it also works while it is unreachable.
+    pub fn generate_support_bundle(&self) -> SupportBundle {
+        let operations = self.operation_journal();
+
+        let errors = operations
+            .iter()
+            .filter_map(|record| {
+                record.error.as_ref().map(|chain| {
+                    json!({
+                        "operation_id": record.operation_id.to_string(),
+                        "name": record.name,
+                        "trace": chain.iter().map(|message| redact(message)).collect::<Vec<_>>(),
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+
+        SupportBundle(json!({
+            "version": self.version_info(),
+            "config": self.config_info(),
+            "server_info": self.server_info_snapshot(),
+            "operations": operations.iter().map(operation_info).collect::<Vec<_>>(),
+            "errors": errors,
+        }))
+    }
+
+    fn version_info(&self) -> Value {
+        json!({
+            "ark_client": env!("CARGO_PKG_VERSION"),
+            "os": std::env::consts::OS,
+            "arch": std::env::consts::ARCH,
+            "generated_at": Timestamp::now().to_string(),
+        })
+    }
+
+    fn config_info(&self) -> Value {
+        let config = self.config();
+
+        json!({
+            "server_url": strip_credentials(&config.server_url),
+            "min_round_confirmations": config.min_round_confirmations,
+            "onchain_fee_rate_sat_per_vb": config.onchain_fee_rate.to_sat_per_vb_ceil(),
+            "address_type_policy": format!("{:?}", config.address_type_policy),
+            "round_retry_policy": format!("{:?}", config.round_retry_policy),
+            "round_config": format!("{:?}", config.round_config),
+            "dust_sweep_policy": config.dust_sweep_policy.map(|policy| format!("{policy:?}")),
+            "auto_board_policy": config.auto_board_policy.map(|policy| format!("{policy:?}")),
+            "manual_review": config.manual_review,
+            "refund_rejected_vtxos": config.refund_rejected_vtxos,
+            "privacy": format!("{:?}", config.privacy),
+            "change_policy": format!("{:?}", config.change_policy),
+            "coin_selection_strategy": format!("{:?}", config.coin_selection_strategy),
+            "ark_signer": self.inner.ark_signer.is_some(),
+        })
+    }
+
+    fn server_info_snapshot(&self) -> Value {
+        let info = &self.server_info;
+
+        json!({
+            "is_live": self.server_info_is_live,
+            "network": info.network.to_string(),
+            "pk": info.pk.to_string(),
+            "vtxo_tree_expiry": info.vtxo_tree_expiry.to_consensus_u32(),
+            "unilateral_exit_delay": info.unilateral_exit_delay.to_consensus_u32(),
+            "round_interval": info.round_interval,
+            "dust": info.dust.to_sat(),
+            "market_hour": info.market_hour.map(|market_hour| format!("{market_hour:?}")),
+            "fees": format!("{:?}", info.fees),
+            "maintenance": self.maintenance.as_ref().map(|maintenance| format!("{maintenance:?}")),
+        })
+    }
+}
+
+fn operation_info(record: &OperationRecord) -> Value {
+    json!({
+        "operation_id": record.operation_id.to_string(),
+        "name": record.name,
+        "started_at_ms": record.operation_id.timestamp_ms(),
+        "finished_at_ms": record.finished_at_ms,
+        "outcome": if record.error.is_some() { "failed" } else { "ok" },
+    })
+}
+
+/// Remove the user info, query and fragment of `url`, which may hold credentials.
+fn strip_credentials(url: &str) -> String {
+    let (scheme, rest) = match url.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme), rest),
+        None => (None, url),
+    };
+
+    let rest = rest.split(['?', '#']).next().unwrap_or_default();
+    let rest = match rest.split_once('/') {
+        Some((authority, path)) => {
+            let host = authority.rsplit('@').next().unwrap_or_default();
+            format!("{host}/{path}")
+        }
+        None => rest.rsplit('@').next().unwrap_or_default().to_string(),
+    };
+
+    match scheme {
+        Some(scheme) => format!("{scheme}://{rest}"),
+        None => rest,
+    }
+}
+
+/// Scrub addresses, transaction IDs, keys and amounts from the error `message`.
+fn redact(message: &str) -> String {
+    // Split the message into alternating runs of alphanumeric and other characters.
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for (i, c) in message.char_indices().skip(1) {
+        let previous = message[..i].chars().next_back().expect("not at start");
+        if previous.is_alphanumeric() != c.is_alphanumeric() {
+            segments.push(&message[start..i]);
+            start = i;
+        }
+    }
+    if start < message.len() {
+        segments.push(&message[start..]);
+    }
+
+    let mut redacted = segments
+        .iter()
+        .map(|segment| redact_identifier(segment))
+        .collect::<Vec<_>>();
+
+    // An amount is a number, possibly with a decimal point, followed by a space and a unit.
+    for i in 0..segments.len() {
+        if !AMOUNT_UNITS.contains(&segments[i]) || i < 2 || segments[i - 1] != " " {
+            continue;
+        }
+
+        let mut j = i - 2;
+        if !is_number(segments[j]) {
+            continue;
+        }
+        if j >= 2 && segments[j - 1] == "." && is_number(segments[j - 2]) {
+            redacted[j - 1] = String::new();
+            redacted[j - 2] = String::new();
+            j -= 2;
+        }
+
+        redacted[j] = REDACTED_AMOUNT.to_string();
+        if j != i - 2 {
+            redacted[i - 2] = String::new();
+        }
+    }
+
+    redacted.concat()
+}
+
+fn redact_identifier(segment: &str) -> String {
+    if segment.len() == 64 && segment.chars().all(|c| c.is_ascii_hexdigit()) {
+        REDACTED_TXID.to_string()
+    } else if segment.len() >= MIN_REDACTED_IDENTIFIER_LEN
+        && segment.chars().all(|c| c.is_ascii_alphanumeric())
+    {
+        REDACTED_ADDRESS.to_string()
+    } else {
+        segment.to_string()
+    }
+}
+
+fn is_number(segment: &str) -> bool {
+    !segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit())
+}
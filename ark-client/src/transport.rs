@@ -0,0 +1,295 @@
+//! The interface to the Ark server, so that the client is not tied to a particular protocol.
+//!
+//! [`ark_grpc::Client`] is the default [`NetworkTransport`]. A REST implementation, or a mock for
+//! testing, can be plugged in with [`crate::OfflineClient::new_with_transport`].
+//!
+//! The gRPC transport is only available with the `grpc` feature, which is enabled by default, and
+//! not in WASM (`wasm32-unknown-unknown`), so browser wallets must always bring their own
+//! transport. Browser transports are built on types such as `JsValue`
+//! which cannot be sent to other threads, so transports, their futures and their streams only have
+//! to be `Send` outside of WASM, see [`MaybeSend`].
+
+use crate::Error;
+use ark_core::server::Info;
+use ark_core::server::ListVtxo;
+use ark_core::server::Round;
+use ark_core::server::RoundInput;
+use ark_core::server::RoundOutput;
+use ark_core::server::RoundStreamEvent;
+use ark_core::server::TransactionEvent;
+use ark_core::ArkAddress;
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::Psbt;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use futures::stream::BoxStream;
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+use futures::stream::LocalBoxStream;
+use futures::Future;
+use futures::Stream;
+use futures::StreamExt;
+
+/// `Send`, except in WASM where everything runs on a single thread.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub trait MaybeSend: Send {}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl<T> MaybeSend for T where T: Send + ?Sized {}
+
+/// `Send`, except in WASM where everything runs on a single thread.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub trait MaybeSend {}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+impl<T> MaybeSend for T where T: ?Sized {}
+
+/// `Sync`, except in WASM where everything runs on a single thread.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub trait MaybeSync: Sync {}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl<T> MaybeSync for T where T: Sync + ?Sized {}
+
+/// `Sync`, except in WASM where everything runs on a single thread.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub trait MaybeSync {}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+impl<T> MaybeSync for T where T: ?Sized {}
+
+/// A boxed stream of a [`NetworkTransport`], which is [`MaybeSend`].
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub type NetworkStream<'a, T> = BoxStream<'a, T>;
+
+/// A boxed stream of a [`NetworkTransport`], which is [`MaybeSend`].
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub type NetworkStream<'a, T> = LocalBoxStream<'a, T>;
+
+/// Box `stream` into a [`NetworkStream`].
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) fn box_stream<'a, S>(stream: S) -> NetworkStream<'a, S::Item>
+where
+    S: Stream + MaybeSend + 'a,
+{
+    stream.boxed()
+}
+
+/// Box `stream` into a [`NetworkStream`].
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub(crate) fn box_stream<'a, S>(stream: S) -> NetworkStream<'a, S::Item>
+where
+    S: Stream + MaybeSend + 'a,
+{
+    stream.boxed_local()
+}
+
+/// The requests that the client makes to the Ark server.
+///
+/// Cloning a transport must be cheap, since the client clones it for concurrent requests.
+pub trait NetworkTransport: Clone + MaybeSend + MaybeSync + 'static {
+    /// The URL of the Ark server.
+    fn url(&self) -> &str;
+
+    /// Point the transport at the Ark server at `url`, dropping the current connection.
+    fn set_url(&mut self, url: String);
+
+    /// Connect to the Ark server, failing if it is unreachable.
+    fn connect(&mut self) -> impl Future<Output = Result<(), Error>> + MaybeSend;
+
+    /// Set up the connection to the Ark server without waiting for it to be established.
+    fn connect_lazy(&mut self) -> Result<(), Error>;
+
+    fn get_info(&self) -> impl Future<Output = Result<Info, Error>> + MaybeSend;
+
+    fn list_vtxos(
+        &self,
+        address: &ArkAddress,
+    ) -> impl Future<Output = Result<ListVtxo, Error>> + MaybeSend;
+
+    /// Look up the round whose round transaction has TXID `round_txid`.
+    fn get_round(
+        &self,
+        round_txid: String,
+    ) -> impl Future<Output = Result<Option<Round>, Error>> + MaybeSend;
+
+    /// Register `inputs` for the next round, returning the ID of the registration.
+    fn register_inputs_for_next_round(
+        &self,
+        inputs: &[RoundInput],
+    ) -> impl Future<Output = Result<String, Error>> + MaybeSend;
+
+    fn register_outputs_for_next_round(
+        &self,
+        request_id: String,
+        outputs: &[RoundOutput],
+        cosigner_pks: &[PublicKey],
+        signing_all: bool,
+    ) -> impl Future<Output = Result<(), Error>> + MaybeSend;
+
+    /// Submit an out-of-round transaction, returning it cosigned by the Ark server.
+    fn submit_redeem_transaction(
+        &self,
+        redeem_psbt: Psbt,
+    ) -> impl Future<Output = Result<Psbt, Error>> + MaybeSend;
+
+    /// Let the Ark server know that the registration with ID `request_id` is still interested in
+    /// joining the round.
+    fn ping(&self, request_id: String) -> impl Future<Output = Result<(), Error>> + MaybeSend;
+
+    fn submit_tree_nonces(
+        &self,
+        round_id: &str,
+        cosigner_pk: PublicKey,
+        pub_nonce_tree: Vec<Vec<Option<zkp::MusigPubNonce>>>,
+    ) -> impl Future<Output = Result<(), Error>> + MaybeSend;
+
+    fn submit_tree_signatures(
+        &self,
+        round_id: &str,
+        cosigner_pk: PublicKey,
+        partial_sig_tree: Vec<Vec<Option<zkp::MusigPartialSignature>>>,
+    ) -> impl Future<Output = Result<(), Error>> + MaybeSend;
+
+    fn submit_signed_forfeit_txs(
+        &self,
+        signed_forfeit_txs: Vec<Psbt>,
+        signed_round_psbt: Option<Psbt>,
+    ) -> impl Future<Output = Result<(), Error>> + MaybeSend;
+
+    /// The events of the rounds of the Ark server, starting with the next one.
+    fn get_event_stream(
+        &self,
+    ) -> impl Future<Output = Result<NetworkStream<'static, Result<RoundStreamEvent, Error>>, Error>>
+           + MaybeSend;
+
+    /// The round and out-of-round transactions of the Ark server, starting with the next one.
+    ///
+    /// Returns `None` if the Ark server, or the transport, does not support streaming
+    /// transactions, in which case the client polls [`NetworkTransport::list_vtxos`] instead.
+    fn get_transaction_stream(
+        &self,
+    ) -> impl Future<
+        Output = Result<Option<NetworkStream<'static, Result<TransactionEvent, Error>>>, Error>,
+    > + MaybeSend;
+}
+
+#[cfg(all(
+    feature = "grpc",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+impl NetworkTransport for ark_grpc::Client {
+    fn url(&self) -> &str {
+        ark_grpc::Client::url(self)
+    }
+
+    fn set_url(&mut self, url: String) {
+        ark_grpc::Client::set_url(self, url);
+    }
+
+    async fn connect(&mut self) -> Result<(), Error> {
+        ark_grpc::Client::connect(self).await.map_err(Error::from)
+    }
+
+    fn connect_lazy(&mut self) -> Result<(), Error> {
+        ark_grpc::Client::connect_lazy(self).map_err(Error::from)
+    }
+
+    async fn get_info(&self) -> Result<Info, Error> {
+        ark_grpc::Client::get_info(self).await.map_err(Error::from)
+    }
+
+    async fn list_vtxos(&self, address: &ArkAddress) -> Result<ListVtxo, Error> {
+        ark_grpc::Client::list_vtxos(self, address)
+            .await
+            .map_err(Error::from)
+    }
+
+    async fn get_round(&self, round_txid: String) -> Result<Option<Round>, Error> {
+        ark_grpc::Client::get_round(self, round_txid)
+            .await
+            .map_err(Error::from)
+    }
+
+    async fn register_inputs_for_next_round(&self, inputs: &[RoundInput]) -> Result<String, Error> {
+        ark_grpc::Client::register_inputs_for_next_round(self, inputs)
+            .await
+            .map_err(Error::from)
+    }
+
+    async fn register_outputs_for_next_round(
+        &self,
+        request_id: String,
+        outputs: &[RoundOutput],
+        cosigner_pks: &[PublicKey],
+        signing_all: bool,
+    ) -> Result<(), Error> {
+        ark_grpc::Client::register_outputs_for_next_round(
+            self,
+            request_id,
+            outputs,
+            cosigner_pks,
+            signing_all,
+        )
+        .await
+        .map_err(Error::from)
+    }
+
+    async fn submit_redeem_transaction(&self, redeem_psbt: Psbt) -> Result<Psbt, Error> {
+        ark_grpc::Client::submit_redeem_transaction(self, redeem_psbt)
+            .await
+            .map_err(Error::from)
+    }
+
+    async fn ping(&self, request_id: String) -> Result<(), Error> {
+        ark_grpc::Client::ping(self, request_id)
+            .await
+            .map_err(Error::from)
+    }
+
+    async fn submit_tree_nonces(
+        &self,
+        round_id: &str,
+        cosigner_pk: PublicKey,
+        pub_nonce_tree: Vec<Vec<Option<zkp::MusigPubNonce>>>,
+    ) -> Result<(), Error> {
+        ark_grpc::Client::submit_tree_nonces(self, round_id, cosigner_pk, pub_nonce_tree)
+            .await
+            .map_err(Error::from)
+    }
+
+    async fn submit_tree_signatures(
+        &self,
+        round_id: &str,
+        cosigner_pk: PublicKey,
+        partial_sig_tree: Vec<Vec<Option<zkp::MusigPartialSignature>>>,
+    ) -> Result<(), Error> {
+        ark_grpc::Client::submit_tree_signatures(self, round_id, cosigner_pk, partial_sig_tree)
+            .await
+            .map_err(Error::from)
+    }
+
+    async fn submit_signed_forfeit_txs(
+        &self,
+        signed_forfeit_txs: Vec<Psbt>,
+        signed_round_psbt: Option<Psbt>,
+    ) -> Result<(), Error> {
+        ark_grpc::Client::submit_signed_forfeit_txs(self, signed_forfeit_txs, signed_round_psbt)
+            .await
+            .map_err(Error::from)
+    }
+
+    async fn get_event_stream(
+        &self,
+    ) -> Result<NetworkStream<'static, Result<RoundStreamEvent, Error>>, Error> {
+        let stream = ark_grpc::Client::get_event_stream(self).await?;
+
+        Ok(stream.map(|event| event.map_err(Error::from)).boxed())
+    }
+
+    async fn get_transaction_stream(
+        &self,
+    ) -> Result<Option<NetworkStream<'static, Result<TransactionEvent, Error>>>, Error> {
+        let stream = ark_grpc::Client::get_tx_stream(self).await?;
+
+        Ok(stream.map(|stream| stream.map(|event| event.map_err(Error::from)).boxed()))
+    }
+}
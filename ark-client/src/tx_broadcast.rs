@@ -0,0 +1,139 @@
+//! Classify and handle the reasons why a [`Blockchain`] backend rejects a transaction.
+
+use crate::error::ErrorContext;
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use bitcoin::Transaction;
+use std::error::Error as StdError;
+use std::fmt;
+
+/// Why a [`Blockchain`] backend rejected a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BroadcastError {
+    /// The transaction is already in the mempool of the backend.
+    AlreadyInMempool,
+    /// The transaction is already confirmed.
+    AlreadyInChain,
+    /// The transaction does not pay the minimum relay fee, the minimum fee of the mempool or
+    /// enough to replace the transactions it conflicts with.
+    InsufficientFee { reason: String },
+    /// The transaction spends an output which is also spent by a transaction in the mempool.
+    MempoolConflict { reason: String },
+    /// The transaction spends an output which is unknown or already spent, e.g. because its parent
+    /// has not reached the backend yet.
+    MissingOrSpentInputs { reason: String },
+    /// The transaction was rejected for any other reason.
+    Rejected { reason: String },
+}
+
+impl BroadcastError {
+    /// Classify the `reason` for a rejection reported by a backend.
+    ///
+    /// Backends such as Esplora and Electrum servers relay the reject reasons of Bitcoin Core,
+    /// which are recognised here regardless of the message around them.
+    pub fn from_reason(reason: impl Into<String>) -> Self {
+        let reason = reason.into();
+        let lowercase = reason.to_lowercase();
+
+        let contains_any =
+            |patterns: &[&str]| patterns.iter().any(|pattern| lowercase.contains(pattern));
+
+        if contains_any(&["txn-already-in-mempool", "txn-already-known"]) {
+            Self::AlreadyInMempool
+        } else if contains_any(&[
+            "already in block chain",
+            "already in blockchain",
+            "outputs already in utxo set",
+            "txn-already-confirmed",
+        ]) {
+            Self::AlreadyInChain
+        } else if contains_any(&[
+            "min relay fee not met",
+            "mempool min fee not met",
+            "insufficient fee",
+            "min-fee-not-met",
+            "fee not met",
+        ]) {
+            Self::InsufficientFee { reason }
+        } else if contains_any(&["txn-mempool-conflict"]) {
+            Self::MempoolConflict { reason }
+        } else if contains_any(&["missingorspent", "missing-inputs", "missing inputs"]) {
+            Self::MissingOrSpentInputs { reason }
+        } else {
+            Self::Rejected { reason }
+        }
+    }
+
+    /// Whether the transaction was already published, in which case broadcasting it succeeded
+    /// for all intents and purposes.
+    pub fn is_already_known(&self) -> bool {
+        matches!(self, Self::AlreadyInMempool | Self::AlreadyInChain)
+    }
+
+    /// Whether broadcasting the same transaction again later may succeed.
+    ///
+    /// Missing inputs may still be on their way to the backend, and conflicting transactions may
+    /// be evicted from the mempool.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::MissingOrSpentInputs { .. } | Self::MempoolConflict { .. }
+        )
+    }
+}
+
+impl fmt::Display for BroadcastError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AlreadyInMempool => write!(f, "transaction already in mempool"),
+            Self::AlreadyInChain => write!(f, "transaction already confirmed"),
+            Self::InsufficientFee { reason } => write!(f, "insufficient fee: {reason}"),
+            Self::MempoolConflict { reason } => write!(f, "mempool conflict: {reason}"),
+            Self::MissingOrSpentInputs { reason } => {
+                write!(f, "missing or spent inputs: {reason}")
+            }
+            Self::Rejected { reason } => write!(f, "transaction rejected: {reason}"),
+        }
+    }
+}
+
+impl StdError for BroadcastError {}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Broadcast `tx`, treating a transaction which was already published as broadcast.
+    ///
+    /// With the `consensus-verify` feature, `tx` is only broadcast if it passes
+    /// [`Client::verify_transaction`].
+    pub(crate) async fn broadcast_tx(&self, tx: &Transaction) -> Result<(), Error> {
+        let txid = tx.compute_txid();
+
+        #[cfg(feature = "consensus-verify")]
+        if let Err(e) = self.verify_transaction(tx).await {
+            tracing::error!(%txid, "Refusing to broadcast transaction: {e}");
+
+            return Err(e);
+        }
+
+        match self.blockchain().broadcast(tx).await {
+            Ok(()) => Ok(()),
+            Err(e)
+                if e.broadcast_error()
+                    .is_some_and(BroadcastError::is_already_known) =>
+            {
+                tracing::debug!(%txid, "Transaction was already published: {e}");
+
+                Ok(())
+            }
+            Err(e) => Err(e).with_context(|| format!("failed to broadcast transaction {txid}")),
+        }
+    }
+}
@@ -1,34 +1,141 @@
 use crate::coin_select::coin_select_for_onchain;
 use crate::error::Error;
 use crate::error::ErrorContext;
+use crate::notifications::ClientEvent;
+use crate::operation::OperationId;
+use crate::transport::NetworkTransport;
+use crate::tx_broadcast::BroadcastError;
 use crate::utils::sleep;
 use crate::wallet::BoardingWallet;
+use crate::wallet::ClientStore;
+use crate::wallet::ExitTx;
+use crate::wallet::ExitTxStatus;
 use crate::wallet::OnchainWallet;
 use crate::Blockchain;
 use crate::Client;
+use ark_core::exit_delay::BlockTime;
 use ark_core::unilateral_exit;
-use ark_core::unilateral_exit::create_unilateral_exit_transaction;
+use ark_core::unilateral_exit::batched_unilateral_exit_transaction_sighashes;
+use ark_core::unilateral_exit::create_batched_unilateral_exit_transaction_with;
+use ark_core::unilateral_exit::estimate_unilateral_exit_tx_fee;
 use ark_core::unilateral_exit::prepare_vtxo_tree_transactions;
 use backon::ExponentialBuilder;
 use backon::Retryable;
 use bitcoin::Address;
+use bitcoin::AddressType;
 use bitcoin::Amount;
+use bitcoin::FeeRate;
+use bitcoin::OutPoint;
 use bitcoin::Transaction;
 use bitcoin::TxOut;
 use bitcoin::Txid;
+use jiff::Timestamp;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// How many times [`Client::send_on_chain`] rebuilds a transaction with a higher fee rate if it
+/// does not pay enough fees to be accepted.
+const MAX_FEE_RATE_BUMPS: usize = 3;
+
+/// The on-chain address types which we are willing to send to, and to receive change on.
+///
+/// Addresses whose type is unknown (e.g. future witness versions) are always refused, since we
+/// cannot estimate the fee for them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressTypePolicy {
+    destination: Vec<AddressType>,
+    change: Vec<AddressType>,
+}
+
+impl AddressTypePolicy {
+    pub fn new(destination: Vec<AddressType>, change: Vec<AddressType>) -> Self {
+        Self {
+            destination,
+            change,
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        if self.destination.is_empty() || self.change.is_empty() {
+            return Err(Error::ad_hoc(
+                "invalid address type policy: no allowed destination or change address types",
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn check_destination(&self, address: &Address) -> Result<(), Error> {
+        check_address_type(&self.destination, address)
+            .context("destination address refused by policy")
+    }
+
+    fn check_change(&self, address: &Address) -> Result<(), Error> {
+        check_address_type(&self.change, address).context("change address refused by policy")
+    }
+}
+
+impl Default for AddressTypePolicy {
+    /// Send to any standard address type, but only receive change on segwit addresses.
+    fn default() -> Self {
+        Self {
+            destination: vec![
+                AddressType::P2pkh,
+                AddressType::P2sh,
+                AddressType::P2wpkh,
+                AddressType::P2wsh,
+                AddressType::P2tr,
+            ],
+            change: vec![AddressType::P2wpkh, AddressType::P2tr],
+        }
+    }
+}
+
+fn check_address_type(allowed: &[AddressType], address: &Address) -> Result<(), Error> {
+    match address.address_type() {
+        Some(address_type) if allowed.contains(&address_type) => Ok(()),
+        Some(address_type) => Err(Error::ad_hoc(format!(
+            "address {address} has disallowed type {address_type}"
+        ))),
+        None => Err(Error::ad_hoc(format!(
+            "address {address} has unknown script type"
+        ))),
+    }
+}
 
 // TODO: We should not _need_ to connect to the Ark server to perform unilateral exit. Currently we
 // do talk to the Ark server for simplicity.
-impl<B, W> Client<B, W>
+impl<B, W, T> Client<B, W, T>
 where
     B: Blockchain,
     W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
 {
     /// Publish all the relevant transactions in the VTXO tree to get our VTXOs on chain.
+    ///
+    /// The transactions and how far they got are persisted (see [`Client::exit_progress`]), so
+    /// that calling this again, e.g. after a restart, resumes the exit: confirmed transactions are
+    /// skipped and broadcast transactions are only broadcast again if they were dropped.
+    #[tracing::instrument(skip_all, fields(operation_id = tracing::field::Empty))]
     pub async fn commit_vtxos_on_chain(&self) -> Result<(), Error> {
-        let spendable_vtxos = self.spendable_vtxos().await?;
+        let operation_id = OperationId::start();
+
+        let outcome = self.commit_selected_vtxos_on_chain(None).await;
+
+        self.record_operation(operation_id, "commit_vtxos_on_chain", outcome)
+    }
+
+    /// Like [`Client::commit_vtxos_on_chain`], but only publishing the VTXOs in `selection`, if
+    /// given.
+    ///
+    /// Exits which were interrupted are resumed regardless of `selection`.
+    pub(crate) async fn commit_selected_vtxos_on_chain(
+        &self,
+        selection: Option<&HashSet<OutPoint>>,
+    ) -> Result<(), Error> {
+        let spendable_vtxos = self.available_vtxos().await?;
 
         let network_client = &self.network_client();
         let vtxos = spendable_vtxos
@@ -36,6 +143,11 @@ where
             .flat_map(|(vtxo_outpoints, _)| {
                 vtxo_outpoints
                     .into_iter()
+                    .filter(|vtxo_outpoint| {
+                        selection.map_or(true, |selection| {
+                            selection.contains(&vtxo_outpoint.outpoint)
+                        })
+                    })
                     .map(|vtxo_outpoint| match vtxo_outpoint.redeem_tx {
                         Some(redeem_transaction) => {
                             unilateral_exit::VtxoProvenance::new_unconfirmed(
@@ -59,8 +171,7 @@ where
             if let Entry::Vacant(e) = rounds.entry(round_txid) {
                 let round = network_client
                     .get_round(round_txid.to_string())
-                    .await
-                    .map_err(Error::ark_server)?
+                    .await?
                     .ok_or_else(|| Error::ad_hoc(format!("could not find round {round_txid}")))?;
 
                 e.insert(round);
@@ -70,65 +181,275 @@ where
         let off_board_txs =
             prepare_vtxo_tree_transactions(vtxos.as_slice(), rounds).map_err(Error::from)?;
 
+        let wanted_txids = off_board_txs
+            .iter()
+            .map(Transaction::compute_txid)
+            .collect::<HashSet<_>>();
+
+        // Resume the exits which were interrupted, e.g. by a restart, together with the new ones.
+        let mut exit_txs = self.inner.wallet.store().load_exit_txs()?;
+        let known_txids = exit_txs
+            .iter()
+            .map(|exit_tx| exit_tx.tx.compute_txid())
+            .collect::<HashSet<_>>();
+        for tx in off_board_txs.into_iter() {
+            if known_txids.contains(&tx.compute_txid()) {
+                continue;
+            }
+
+            let exit_tx = ExitTx {
+                tx,
+                status: ExitTxStatus::Pending,
+            };
+
+            self.inner.wallet.store().save_exit_tx(exit_tx.clone())?;
+            exit_txs.push(exit_tx);
+        }
+
+        let exit_txs = order_exit_txs(exit_txs);
+
+        let stale_txids = stale_exit_txs(&exit_txs, &wanted_txids);
+        for txid in stale_txids.iter() {
+            tracing::info!(%txid, "Dropping exit transaction which is no longer needed");
+
+            self.inner.wallet.store().delete_exit_tx(*txid)?;
+        }
+        let exit_txs = exit_txs
+            .into_iter()
+            .filter(|exit_tx| !stale_txids.contains(&exit_tx.tx.compute_txid()))
+            .collect::<Vec<_>>();
+
         let blockchain = &self.blockchain();
 
-        let off_board_txs_len = off_board_txs.len();
-        for (i, tx) in off_board_txs.iter().enumerate() {
+        // Transactions which were rejected for good, and those which could not be broadcast this
+        // time. Their descendants cannot be broadcast either.
+        let mut rejected = HashSet::new();
+        let mut failed = Vec::new();
+
+        let off_board_txs_len = exit_txs.len();
+        for (i, exit_tx) in exit_txs.iter().enumerate() {
+            let tx = &exit_tx.tx;
             let txid = tx.compute_txid();
 
+            let spends = |txids: &HashSet<Txid>| {
+                tx.input
+                    .iter()
+                    .any(|input| txids.contains(&input.previous_output.txid))
+            };
+            if spends(&rejected) {
+                tracing::warn!(%txid, "Dropping exit transaction spending a rejected one");
+
+                self.inner.wallet.store().delete_exit_tx(txid)?;
+                rejected.insert(txid);
+                continue;
+            }
+            let blocked = failed.iter().map(|(txid, _)| *txid).collect::<HashSet<_>>();
+            if spends(&blocked) {
+                tracing::debug!(%txid, "Not broadcasting exit transaction before its parent");
+                continue;
+            }
+
+            if exit_tx.status == ExitTxStatus::Confirmed {
+                tracing::debug!(%txid, "VTXO transaction already confirmed");
+                continue;
+            }
+
             let is_not_published = blockchain.find_tx(&txid).await?.is_none();
             if is_not_published {
                 tracing::info!(%txid, "Broadcasting VTXO transaction");
-                let broadcast = || async { blockchain.broadcast(tx).await };
+                let broadcast = || async { self.broadcast_tx(tx).await };
 
-                broadcast
+                let outcome = broadcast
                     .retry(ExponentialBuilder::default().with_max_times(5))
                     .sleep(sleep)
-                    // TODO: Use `when` to only retry certain errors.
-                    .notify(|err: &Error, dur: std::time::Duration| {
+                    // The parent of the transaction may not have reached the backend yet, but
+                    // other rejections will not go away by themselves.
+                    .when(|err: &Error| {
+                        err.broadcast_error()
+                            .map_or(true, BroadcastError::is_transient)
+                    })
+                    .notify(|err: &Error, dur: Duration| {
                         tracing::warn!(
                             "Retrying broadcasting VTXO transaction {txid} after {dur:?}. Error: {err}",
                         );
                     })
-                    .await
-                    .with_context(|| format!("Failed to broadcast VTXO transaction {txid}"))?;
+                    .await;
+
+                if let Err(e) = outcome {
+                    // Other exit transactions may still go through, so we keep going.
+                    if let Some(BroadcastError::Rejected { .. }) = e.broadcast_error() {
+                        tracing::warn!(%txid, "VTXO transaction was rejected, dropping it: {e}");
+
+                        self.inner.wallet.store().delete_exit_tx(txid)?;
+                        rejected.insert(txid);
+
+                        self.publish(ClientEvent::ExitTxRejected { txid });
+                    } else {
+                        tracing::warn!(%txid, "Failed to broadcast VTXO transaction: {e}");
+                    }
+
+                    failed.push((txid, e));
+                    continue;
+                }
 
                 tracing::info!(%txid, i, total_txs = off_board_txs_len, "Broadcasted VTXO transaction");
+
+                self.publish(ClientEvent::ExitBroadcast { txid });
+            }
+
+            let status = if blockchain.get_confirmations(&txid).await? > 0 {
+                ExitTxStatus::Confirmed
+            } else {
+                ExitTxStatus::Broadcast
+            };
+
+            if status != exit_tx.status {
+                self.inner.wallet.store().save_exit_tx(ExitTx {
+                    tx: tx.clone(),
+                    status,
+                })?;
             }
         }
 
-        Ok(())
+        let n_failed = failed.len();
+        match failed.into_iter().next() {
+            Some((txid, e)) => Err(e).with_context(|| {
+                format!(
+                    "failed to broadcast {n_failed} of {off_board_txs_len} VTXO transactions, \
+                     starting with {txid}"
+                )
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// The transactions published by [`Client::commit_vtxos_on_chain`] and how far they got.
+    pub fn exit_progress(&self) -> Result<Vec<ExitTx>, Error> {
+        let exit_txs = self.inner.wallet.store().load_exit_txs()?;
+
+        Ok(order_exit_txs(exit_txs))
+    }
+
+    /// The time from which the boarding output or published VTXO at `outpoint` can be spent with
+    /// [`Client::send_on_chain`], i.e. when its exit delay is over.
+    ///
+    /// Like BIP68, this is compared with the median time past of the tip of the blockchain, which
+    /// usually lags about an hour behind the current time.
+    ///
+    /// Returns `None` if the output is not confirmed yet, or if its exit delay counts blocks
+    /// rather than seconds.
+    pub async fn exit_claimable_at(&self, outpoint: OutPoint) -> Result<Option<Timestamp>, Error> {
+        for boarding_output in self.inner.wallet.get_boarding_outputs()?.iter() {
+            let outpoints = self.find_our_outpoints(boarding_output.address()).await?;
+            if let Some(utxo) = outpoints.iter().find(|utxo| utxo.outpoint == outpoint) {
+                return claimable_at(self.confirmed_in(utxo).await?, |confirmed| {
+                    Ok(boarding_output.exit_claimable_at(confirmed)?)
+                });
+            }
+        }
+
+        for (_, vtxo) in self.get_offchain_addresses() {
+            let outpoints = self.find_our_outpoints(vtxo.address()).await?;
+            if let Some(utxo) = outpoints.iter().find(|utxo| utxo.outpoint == outpoint) {
+                return claimable_at(self.confirmed_in(utxo).await?, |confirmed| {
+                    Ok(vtxo.exit_claimable_at(confirmed))
+                });
+            }
+        }
+
+        Err(Error::ad_hoc(format!(
+            "output {outpoint} is neither one of our boarding outputs nor a published VTXO"
+        )))
     }
 
     /// Spend boarding outputs and VTXOs to an _on-chain_ address.
     ///
     /// All these outputs are spent unilaterally.
     ///
+    /// The destination and change addresses must be allowed by the [`AddressTypePolicy`] set with
+    /// [`crate::OfflineClient::with_address_type_policy`].
+    ///
     /// To be able to spend a boarding output, we must wait for the exit delay to pass.
     ///
     /// To be able to spend a VTXO, the VTXO itself must be published on-chain (via something like
     /// `unilateral_off_board`), and then we must wait for the exit delay to pass.
+    #[tracing::instrument(skip_all, fields(operation_id = tracing::field::Empty))]
     pub async fn send_on_chain(
         &self,
         to_address: Address,
         to_amount: Amount,
     ) -> Result<Txid, Error> {
-        let (tx, _) = self
-            .create_send_on_chain_transaction(to_address, to_amount)
-            .await?;
-
-        let txid = tx.compute_txid();
-        tracing::info!(
-            %txid,
-            "Broadcasting transaction sending Ark outputs onchain"
-        );
+        let operation_id = OperationId::start();
 
-        self.blockchain()
-            .broadcast(&tx)
-            .await
-            .context("failed to broadcast transaction {tx}")?;
+        let outcome = self
+            .broadcast_send_on_chain_transaction(vec![(to_address, to_amount)])
+            .await;
+
+        self.record_operation(operation_id, "send_on_chain", outcome)
+    }
 
-        Ok(txid)
+    /// Like [`Client::send_on_chain`], but paying several on-chain addresses in a single
+    /// transaction.
+    #[tracing::instrument(skip_all, fields(operation_id = tracing::field::Empty))]
+    pub async fn send_on_chain_batch(
+        &self,
+        outputs: Vec<(Address, Amount)>,
+    ) -> Result<Txid, Error> {
+        let operation_id = OperationId::start();
+
+        let outcome = self.broadcast_send_on_chain_transaction(outputs).await;
+
+        self.record_operation(operation_id, "send_on_chain_batch", outcome)
+    }
+
+    /// Build and broadcast a transaction paying `outputs` with our boarding outputs and VTXOs.
+    ///
+    /// If the transaction does not pay enough fees to be accepted, it is rebuilt with twice the
+    /// fee rate, up to [`MAX_FEE_RATE_BUMPS`] times.
+    async fn broadcast_send_on_chain_transaction(
+        &self,
+        outputs: Vec<(Address, Amount)>,
+    ) -> Result<Txid, Error> {
+        let mut fee_rate = self.onchain_fee_rate().await;
+        let mut n_bumps = 0;
+        loop {
+            let (tx, _) = self
+                .build_send_on_chain_batch_transaction(outputs.clone(), fee_rate)
+                .await?;
+
+            let txid = tx.compute_txid();
+            tracing::info!(
+                %txid,
+                n_outputs = tx.output.len(),
+                %fee_rate,
+                "Broadcasting transaction sending Ark outputs onchain"
+            );
+
+            match self.broadcast_tx(&tx).await {
+                Ok(()) => return Ok(txid),
+                Err(e)
+                    if n_bumps < MAX_FEE_RATE_BUMPS
+                        && matches!(
+                            e.broadcast_error(),
+                            Some(BroadcastError::InsufficientFee { .. })
+                        ) =>
+                {
+                    let bumped_fee_rate =
+                        FeeRate::from_sat_per_kwu(fee_rate.to_sat_per_kwu().saturating_mul(2));
+
+                    tracing::warn!(
+                        %txid,
+                        %fee_rate,
+                        %bumped_fee_rate,
+                        "Transaction does not pay enough fees, rebuilding it: {e}"
+                    );
+
+                    fee_rate = bumped_fee_rate;
+                    n_bumps += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
     }
 
     /// Helper function to `send_on_chain`.
@@ -142,27 +463,97 @@ where
         to_address: Address,
         to_amount: Amount,
     ) -> Result<(Transaction, Vec<TxOut>), Error> {
-        if to_amount < self.server_info.dust {
-            return Err(Error::ad_hoc(format!(
-                "invalid amount {to_amount}, must be greater than dust: {}",
-                self.server_info.dust,
-            )));
+        self.create_send_on_chain_batch_transaction(vec![(to_address, to_amount)])
+            .await
+    }
+
+    /// Helper function to `send_on_chain_batch`.
+    ///
+    /// Also returns the previous outputs of the transaction inputs, to be able to verify it.
+    pub async fn create_send_on_chain_batch_transaction(
+        &self,
+        outputs: Vec<(Address, Amount)>,
+    ) -> Result<(Transaction, Vec<TxOut>), Error> {
+        let fee_rate = self.onchain_fee_rate().await;
+
+        self.build_send_on_chain_batch_transaction(outputs, fee_rate)
+            .await
+    }
+
+    async fn build_send_on_chain_batch_transaction(
+        &self,
+        outputs: Vec<(Address, Amount)>,
+        fee_rate: FeeRate,
+    ) -> Result<(Transaction, Vec<TxOut>), Error> {
+        if outputs.is_empty() {
+            return Err(Error::ad_hoc("cannot send on-chain without outputs"));
         }
 
-        // TODO: Do not use an arbitrary fee.
-        let fee = Amount::from_sat(1_000);
+        let address_type_policy = &self.inner.address_type_policy;
+        for (to_address, to_amount) in outputs.iter() {
+            if *to_amount < self.server_info.dust {
+                return Err(Error::ad_hoc(format!(
+                    "invalid amount {to_amount}, must be greater than dust: {}",
+                    self.server_info.dust,
+                )));
+            }
+
+            address_type_policy.check_destination(to_address)?;
+        }
 
-        let (onchain_inputs, vtxo_inputs) = coin_select_for_onchain(self, to_amount + fee).await?;
+        let change_address = self.inner.wallet.get_onchain_address().await?;
+        address_type_policy.check_change(&change_address)?;
 
-        let change_address = self.inner.wallet.get_onchain_address()?;
+        let to_amount: Amount = outputs.iter().map(|(_, amount)| *amount).sum();
 
-        let tx = create_unilateral_exit_transaction(
-            self.kp(),
-            to_address,
-            to_amount,
+        let output_scripts = outputs
+            .iter()
+            .map(|(address, _)| address.script_pubkey())
+            .chain(std::iter::once(change_address.script_pubkey()))
+            .collect::<Vec<_>>();
+
+        // The fee depends on the selected inputs, so we select again until the inputs cover the
+        // fee they imply.
+        let mut fee = Amount::ZERO;
+        let (onchain_inputs, vtxo_inputs) = loop {
+            let (onchain_inputs, vtxo_inputs) =
+                coin_select_for_onchain(self, to_amount + fee).await?;
+
+            let required_fee = estimate_unilateral_exit_tx_fee(
+                fee_rate,
+                &onchain_inputs,
+                &vtxo_inputs,
+                &output_scripts,
+            )
+            .map_err(Error::from)?;
+
+            if required_fee <= fee {
+                break (onchain_inputs, vtxo_inputs);
+            }
+
+            fee = required_fee;
+        };
+
+        let sighashes = batched_unilateral_exit_transaction_sighashes(
+            &outputs,
+            change_address.clone(),
+            &onchain_inputs,
+            &vtxo_inputs,
+            fee_rate,
+        )
+        .map_err(Error::from)?;
+        let sigs = self
+            .sign_sighashes(sighashes)
+            .await
+            .context("failed to sign on-chain transaction")?;
+
+        let tx = create_batched_unilateral_exit_transaction_with(
+            sigs.sign_for_pk_fn(),
+            &outputs,
             change_address,
             &onchain_inputs,
             &vtxo_inputs,
+            fee_rate,
         )
         .map_err(Error::from)?;
 
@@ -179,3 +570,186 @@ where
         Ok((tx, prevouts))
     }
 }
+
+/// When an output `confirmed` on chain can be claimed, given how `claimable_at` works it out from
+/// the median time past of the output.
+fn claimable_at(
+    confirmed: Option<BlockTime>,
+    claimable_at: impl FnOnce(Duration) -> Result<Option<Duration>, Error>,
+) -> Result<Option<Timestamp>, Error> {
+    let confirmed = match confirmed {
+        Some(confirmed) => confirmed,
+        None => return Ok(None),
+    };
+
+    claimable_at(confirmed.median_time_past)?
+        .map(|claimable_at| {
+            Timestamp::from_second(claimable_at.as_secs() as i64).map_err(Error::ad_hoc)
+        })
+        .transpose()
+}
+
+/// Order `exit_txs` so that every transaction comes after the transactions whose outputs it
+/// spends.
+fn order_exit_txs(mut exit_txs: Vec<ExitTx>) -> Vec<ExitTx> {
+    let mut ordered = Vec::with_capacity(exit_txs.len());
+    while !exit_txs.is_empty() {
+        let remaining = exit_txs
+            .iter()
+            .map(|exit_tx| exit_tx.tx.compute_txid())
+            .collect::<HashSet<_>>();
+
+        let (ready, blocked): (Vec<_>, Vec<_>) = exit_txs.into_iter().partition(|exit_tx| {
+            !exit_tx
+                .tx
+                .input
+                .iter()
+                .any(|input| remaining.contains(&input.previous_output.txid))
+        });
+
+        // Valid transactions cannot spend each other's outputs, but corrupt records could. We
+        // keep them in their current order rather than loop forever.
+        if ready.is_empty() {
+            ordered.extend(blocked);
+            break;
+        }
+
+        ordered.extend(ready);
+        exit_txs = blocked;
+    }
+
+    ordered
+}
+
+/// The pending transactions of `exit_txs`, ordered with [`order_exit_txs`], which are no longer
+/// needed.
+///
+/// A pending transaction is still needed if it is one of `wanted_txids`, i.e. part of the exit we
+/// are starting, or if it spends a transaction which is still needed, i.e. it continues an exit
+/// which already got some of its transactions on chain. Otherwise it belongs to the exit of a VTXO
+/// which is no longer ours, e.g. because it was spent after the exit was interrupted.
+fn stale_exit_txs(exit_txs: &[ExitTx], wanted_txids: &HashSet<Txid>) -> HashSet<Txid> {
+    let mut needed = wanted_txids.clone();
+    let mut stale = HashSet::new();
+
+    for exit_tx in exit_txs.iter() {
+        let txid = exit_tx.tx.compute_txid();
+
+        let continues_exit = exit_tx
+            .tx
+            .input
+            .iter()
+            .any(|input| needed.contains(&input.previous_output.txid));
+
+        if exit_tx.status != ExitTxStatus::Pending || continues_exit {
+            needed.insert(txid);
+        } else if !needed.contains(&txid) {
+            stale.insert(txid);
+        }
+    }
+
+    stale
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute;
+    use bitcoin::hashes::Hash;
+    use bitcoin::transaction;
+    use bitcoin::TxIn;
+
+    /// An exit transaction spending the first output of each of `parents`, distinguished by
+    /// `value`.
+    fn exit_tx(parents: &[&ExitTx], value: u64, status: ExitTxStatus) -> ExitTx {
+        let input = match parents {
+            [] => vec![TxIn {
+                previous_output: OutPoint::new(Txid::all_zeros(), value as u32),
+                ..TxIn::default()
+            }],
+            parents => parents
+                .iter()
+                .map(|parent| TxIn {
+                    previous_output: OutPoint::new(parent.tx.compute_txid(), 0),
+                    ..TxIn::default()
+                })
+                .collect(),
+        };
+
+        ExitTx {
+            tx: Transaction {
+                version: transaction::Version::non_standard(3),
+                lock_time: absolute::LockTime::ZERO,
+                input,
+                output: vec![TxOut {
+                    value: Amount::from_sat(value),
+                    script_pubkey: Default::default(),
+                }],
+            },
+            status,
+        }
+    }
+
+    fn txids(exit_txs: &[&ExitTx]) -> Vec<Txid> {
+        exit_txs
+            .iter()
+            .map(|exit_tx| exit_tx.tx.compute_txid())
+            .collect()
+    }
+
+    #[test]
+    fn exit_txs_are_ordered_after_their_parents() {
+        let root = exit_tx(&[], 1, ExitTxStatus::Pending);
+        let branch = exit_tx(&[&root], 2, ExitTxStatus::Pending);
+        let leaf = exit_tx(&[&branch], 3, ExitTxStatus::Pending);
+        let other = exit_tx(&[], 4, ExitTxStatus::Pending);
+
+        let ordered = order_exit_txs(vec![
+            leaf.clone(),
+            branch.clone(),
+            other.clone(),
+            root.clone(),
+        ]);
+
+        assert_eq!(
+            ordered,
+            [other.clone(), root.clone(), branch.clone(), leaf.clone()]
+        );
+    }
+
+    #[test]
+    fn pending_exit_txs_which_no_exit_needs_are_stale() {
+        let broadcast_root = exit_tx(&[], 1, ExitTxStatus::Broadcast);
+        let continued = exit_tx(&[&broadcast_root], 2, ExitTxStatus::Pending);
+        let continued_child = exit_tx(&[&continued], 3, ExitTxStatus::Pending);
+
+        let wanted = exit_tx(&[], 4, ExitTxStatus::Pending);
+        let wanted_child = exit_tx(&[&wanted], 5, ExitTxStatus::Pending);
+
+        let abandoned = exit_tx(&[], 6, ExitTxStatus::Pending);
+        let abandoned_child = exit_tx(&[&abandoned], 7, ExitTxStatus::Pending);
+
+        let exit_txs = order_exit_txs(
+            [
+                &broadcast_root,
+                &continued,
+                &continued_child,
+                &wanted,
+                &wanted_child,
+                &abandoned,
+                &abandoned_child,
+            ]
+            .into_iter()
+            .cloned()
+            .collect(),
+        );
+        let wanted_txids = txids(&[&wanted, &wanted_child]).into_iter().collect();
+
+        let stale = stale_exit_txs(&exit_txs, &wanted_txids);
+
+        assert_eq!(
+            stale,
+            txids(&[&abandoned, &abandoned_child]).into_iter().collect()
+        );
+    }
+}
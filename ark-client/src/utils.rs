@@ -1,4 +1,7 @@
+use futures::future;
+use futures::future::Either;
 use futures::Future;
+use std::pin::pin;
 
 #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
 pub(crate) fn spawn<F>(future: F)
@@ -26,3 +29,15 @@ pub(crate) async fn sleep(duration: std::time::Duration) {
         tokio::time::sleep(duration).await;
     }
 }
+
+/// Run `future` until `deadline` completes, returning `None` if the deadline completes first.
+pub(crate) async fn until<F, D>(future: F, deadline: D) -> Option<F::Output>
+where
+    F: Future,
+    D: Future<Output = ()> + Unpin,
+{
+    match future::select(pin!(future), deadline).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right(((), _)) => None,
+    }
+}
@@ -0,0 +1,76 @@
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::ClientStore;
+use crate::wallet::OnchainWallet;
+use crate::wallet::VtxoOrigin;
+use crate::Blockchain;
+use crate::Client;
+use crate::Error;
+use bitcoin::OutPoint;
+use bitcoin::Psbt;
+use bitcoin::Txid;
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Look up the operation which created the VTXO identified by `outpoint`.
+    ///
+    /// Returns `None` for VTXOs whose origin was never recorded, such as VTXOs received from other
+    /// parties without a call to [`Client::record_vtxo_origin`].
+    pub fn vtxo_origin(&self, outpoint: &OutPoint) -> Result<Option<VtxoOrigin>, Error> {
+        self.inner.wallet.store().load_vtxo_origin(outpoint)
+    }
+
+    /// Link the VTXO identified by `outpoint` to an operation, overwriting any previously recorded
+    /// origin.
+    pub fn record_vtxo_origin(&self, outpoint: OutPoint, origin: VtxoOrigin) -> Result<(), Error> {
+        self.inner.wallet.store().save_vtxo_origin(outpoint, origin)
+    }
+
+    /// Record `origin` for all our spendable VTXOs which were created in the round with TXID
+    /// `round_txid`.
+    pub(crate) async fn record_round_vtxo_origins(
+        &self,
+        round_txid: Txid,
+        origin: VtxoOrigin,
+    ) -> Result<(), Error> {
+        let vtxos = self.list_vtxos().await?;
+
+        for vtxo in vtxos
+            .spendable
+            .iter()
+            .filter(|v| v.round_txid == round_txid && !v.is_pending)
+        {
+            self.record_vtxo_origin(vtxo.outpoint, origin.clone())?;
+        }
+
+        Ok(())
+    }
+
+    /// Record `origin` for all outputs of `redeem_psbt` which belong to one of our offchain
+    /// addresses.
+    pub(crate) fn record_redeem_vtxo_origins(
+        &self,
+        redeem_psbt: &Psbt,
+        origin: VtxoOrigin,
+    ) -> Result<(), Error> {
+        let redeem_txid = redeem_psbt.unsigned_tx.compute_txid();
+        let own_scripts = self
+            .get_offchain_addresses()
+            .into_iter()
+            .map(|(address, _)| address.to_p2tr_script_pubkey())
+            .collect::<Vec<_>>();
+
+        for (vout, output) in redeem_psbt.unsigned_tx.output.iter().enumerate() {
+            if own_scripts.contains(&output.script_pubkey) {
+                let outpoint = OutPoint::new(redeem_txid, vout as u32);
+                self.record_vtxo_origin(outpoint, origin.clone())?;
+            }
+        }
+
+        Ok(())
+    }
+}
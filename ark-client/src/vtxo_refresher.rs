@@ -0,0 +1,71 @@
+//! Refresh our VTXOs in the background before the Ark server can sweep them.
+//!
+//! See [`Client::run_vtxo_refresher`].
+
+use crate::transport::NetworkTransport;
+use crate::utils::sleep;
+use crate::wallet::BoardingWallet;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::SWEEP_WARNING_WINDOW;
+use rand::CryptoRng;
+use rand::Rng;
+use std::time::Duration;
+
+/// How [`Client::run_vtxo_refresher`] keeps our VTXOs from expiring.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VtxoRefresherConfig {
+    /// How long to wait between two inspections of our VTXOs.
+    pub check_interval: Duration,
+    /// VTXOs which expire within this window are refreshed, see
+    /// [`Client::refresh_vtxos_near_expiry`].
+    pub refresh_within: Duration,
+}
+
+impl Default for VtxoRefresherConfig {
+    fn default() -> Self {
+        Self {
+            check_interval: Duration::from_secs(10 * 60),
+            refresh_within: SWEEP_WARNING_WINDOW,
+        }
+    }
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Periodically refresh our VTXOs which are about to expire, so that the Ark server never gets
+    /// to sweep them.
+    ///
+    /// Every [`VtxoRefresherConfig::check_interval`], the VTXOs expiring within
+    /// [`VtxoRefresherConfig::refresh_within`] are settled into new VTXOs with
+    /// [`Client::refresh_vtxos_near_expiry`]. Failures are logged and retried at the next check.
+    ///
+    /// This never returns: run it alongside the rest of the application, e.g. with
+    /// `tokio::task::spawn_local` or `tokio::select!`, and drop it to stop refreshing.
+    pub async fn run_vtxo_refresher<R>(&self, rng: &mut R, config: VtxoRefresherConfig)
+    where
+        R: Rng + CryptoRng + Clone,
+    {
+        tracing::info!(?config, "Refreshing expiring VTXOs in the background");
+
+        loop {
+            match self
+                .refresh_vtxos_near_expiry(rng, config.refresh_within)
+                .await
+            {
+                Ok(txids) if !txids.is_empty() => {
+                    tracing::info!(?txids, "Refreshed VTXOs in the background");
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Failed to refresh expiring VTXOs: {e}"),
+            }
+
+            sleep(config.check_interval).await;
+        }
+    }
+}
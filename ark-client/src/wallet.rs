@@ -1,4 +1,14 @@
+use crate::config::ClientConfig;
+use crate::contacts::Contact;
 use crate::error::Error;
+use crate::operation::OperationId;
+use crate::watch_only::WatchedAddress;
+use ark_core::receipt::PaymentReceipt;
+use ark_core::server;
+use ark_core::server::ListVtxo;
+use ark_core::server::VtxoOutPoint;
+use ark_core::ArkAddress;
+use ark_core::ArkTransaction;
 use ark_core::BoardingOutput;
 use bitcoin::secp256k1::schnorr::Signature;
 use bitcoin::secp256k1::Message;
@@ -7,10 +17,17 @@ use bitcoin::Address;
 use bitcoin::Amount;
 use bitcoin::FeeRate;
 use bitcoin::Network;
+use bitcoin::OutPoint;
 use bitcoin::Psbt;
+use bitcoin::Transaction;
+use bitcoin::Txid;
 use bitcoin::XOnlyPublicKey;
+use futures::future::BoxFuture;
 
 pub trait BoardingWallet {
+    /// Where the [`crate::Client`] keeps its state, besides our boarding outputs and keys.
+    type Store: ClientStore;
+
     fn new_boarding_output(
         &self,
         server_pubkey: XOnlyPublicKey,
@@ -21,24 +38,99 @@ pub trait BoardingWallet {
 
     fn get_boarding_outputs(&self) -> Result<Vec<BoardingOutput>, Error>;
 
-    fn sign_for_pk(&self, pk: &XOnlyPublicKey, msg: &Message) -> Result<Signature, Error>;
+    fn store(&self) -> &Self::Store;
+
+    /// Sign `msg` with the secret key of `pk`.
+    ///
+    /// This is asynchronous so that the key can be held by a remote signing service.
+    fn sign_for_pk(
+        &self,
+        pk: &XOnlyPublicKey,
+        msg: &Message,
+    ) -> impl std::future::Future<Output = Result<Signature, Error>> + Send;
+
+    /// Decrypt our secret keys with `passphrase`, so that we can sign again, see
+    /// [`Persistence::unlock`].
+    fn unlock_keys(&self, passphrase: &str) -> Result<(), Error> {
+        let _ = passphrase;
+
+        Err(Error::ad_hoc("secret keys are not encrypted at rest"))
+    }
+
+    /// Forget the passphrase given to [`BoardingWallet::unlock_keys`], see [`Persistence::lock`].
+    fn lock_keys(&self) -> Result<(), Error> {
+        Err(Error::ad_hoc("secret keys are not encrypted at rest"))
+    }
 }
 
+/// Signs the inputs spending our boarding outputs outside of the client, e.g. on an air-gapped
+/// device, as an alternative to [`BoardingWallet::sign_for_pk`].
+///
+/// The round transaction is only known once the round is being finalized, so it cannot be signed
+/// ahead of time. Signing is asynchronous instead: an implementation can export the PSBT, e.g. as
+/// a file or QR code, and resolve once the signed PSBT is imported back.
+///
+/// This is separate from [`crate::signer::ArkSigner`] because the two sign for different keys in
+/// different ways: the boarding outputs belong to the keys of the boarding wallet, which a PSBT
+/// signer can recognise and sign for in one go, whereas an [`crate::signer::ArkSigner`] holds the
+/// single key owning our VTXOs and signs individual sighashes and MuSig2 sessions.
+pub trait ExternalSigner: Send + Sync {
+    /// Sign the inputs of `psbt` which spend our boarding outputs, and return the signed PSBT.
+    ///
+    /// The inputs are populated with everything needed to sign them, see
+    /// [`ark_core::round::prepare_round_psbt`].
+    ///
+    /// The Ark server only waits so long for our signatures: the round fails if the PSBT is not
+    /// signed within [`crate::round::RoundConfig::finalization_timeout`].
+    fn sign_psbt(&self, psbt: Psbt) -> BoxFuture<'_, Result<Psbt, Error>>;
+}
+
+/// An on-chain wallet.
+///
+/// Every method is asynchronous, so that the wallet can be backed by a remote service.
 pub trait OnchainWallet {
-    fn get_onchain_address(&self) -> Result<Address, Error>;
+    fn get_onchain_address(
+        &self,
+    ) -> impl std::future::Future<Output = Result<Address, Error>> + Send;
 
-    fn sync(&self) -> impl std::future::Future<Output = Result<(), Error>>;
+    fn sync(&self) -> impl std::future::Future<Output = Result<(), Error>> + Send;
 
-    fn balance(&self) -> Result<Balance, Error>;
+    fn balance(&self) -> impl std::future::Future<Output = Result<Balance, Error>> + Send;
 
     fn prepare_send_to_address(
         &self,
         address: Address,
         amount: Amount,
         fee_rate: FeeRate,
-    ) -> Result<Psbt, Error>;
+    ) -> impl std::future::Future<Output = Result<Psbt, Error>> + Send;
+
+    fn sign(
+        &self,
+        psbt: &mut Psbt,
+    ) -> impl std::future::Future<Output = Result<bool, Error>> + Send;
+
+    /// Build a transaction spending every output of `parent` that belongs to this wallet back to
+    /// the wallet, paying `fee_rate`.
+    ///
+    /// Used to accelerate the confirmation of `parent` via child-pays-for-parent (CPFP).
+    fn prepare_cpfp(
+        &self,
+        parent: &Transaction,
+        fee_rate: FeeRate,
+    ) -> impl std::future::Future<Output = Result<Psbt, Error>> + Send;
 
-    fn sign(&self, psbt: &mut Psbt) -> Result<bool, Error>;
+    /// Build a transaction spending the pay-to-anchor output at index `anchor_vout` of `parent`,
+    /// funded by this wallet and paying `fee_rate`.
+    ///
+    /// Used to accelerate the confirmation of `parent` via CPFP when none of its outputs belong to
+    /// this wallet, e.g. for the transactions of a unilateral exit. The anchor input must be
+    /// finalized with an empty witness.
+    fn prepare_anchor_cpfp(
+        &self,
+        parent: &Transaction,
+        anchor_vout: u32,
+        fee_rate: FeeRate,
+    ) -> impl std::future::Future<Output = Result<Psbt, Error>> + Send;
 }
 
 pub trait Persistence {
@@ -51,6 +143,386 @@ pub trait Persistence {
     fn load_boarding_outputs(&self) -> Result<Vec<BoardingOutput>, Error>;
 
     fn sk_for_pk(&self, pk: &XOnlyPublicKey) -> Result<SecretKey, Error>;
+
+    /// Make our secret keys available to [`Persistence::sk_for_pk`] by decrypting them with
+    /// `passphrase`.
+    ///
+    /// Only stores which encrypt secret keys at rest implement this, e.g.
+    /// `key_encryption::EncryptedPersistence` with the `key-encryption` feature. Others fail.
+    fn unlock(&self, passphrase: &str) -> Result<(), Error> {
+        let _ = passphrase;
+
+        Err(Error::ad_hoc("secret keys are not encrypted at rest"))
+    }
+
+    /// Make our secret keys unavailable until [`Persistence::unlock`] is called again.
+    fn lock(&self) -> Result<(), Error> {
+        Err(Error::ad_hoc("secret keys are not encrypted at rest"))
+    }
+}
+
+/// The state of a [`crate::Client`] which must survive restarts, e.g. the server info, our
+/// forfeits and the progress of unilateral exits.
+///
+/// Only the methods which keep our funds safe are required. The others back optional features and
+/// by default save nothing: those features then forget their state when the client restarts, e.g.
+/// receipts, contacts or VTXO origins. Override them to persist it.
+pub trait ClientStore {
+    fn save_vtxo_origin(&self, outpoint: OutPoint, origin: VtxoOrigin) -> Result<(), Error> {
+        let _ = (outpoint, origin);
+
+        Ok(())
+    }
+
+    fn load_vtxo_origin(&self, outpoint: &OutPoint) -> Result<Option<VtxoOrigin>, Error> {
+        let _ = outpoint;
+
+        Ok(None)
+    }
+
+    fn save_server_info(&self, info: server::Info) -> Result<(), Error>;
+
+    fn load_server_info(&self) -> Result<Option<server::Info>, Error>;
+
+    /// Cache the VTXOs of `address`, as returned by the Ark server at UNIX timestamp
+    /// `updated_at`.
+    ///
+    /// By default, nothing is cached, so our VTXOs are unknown while the Ark server is
+    /// unreachable.
+    fn save_vtxo_list(
+        &self,
+        address: ArkAddress,
+        vtxos: ListVtxo,
+        updated_at: i64,
+    ) -> Result<(), Error> {
+        let _ = (address, vtxos, updated_at);
+
+        Ok(())
+    }
+
+    /// Get the cached VTXOs of `address`, together with the UNIX timestamp at which they were
+    /// cached.
+    fn load_vtxo_list(&self, address: &ArkAddress) -> Result<Option<(ListVtxo, i64)>, Error> {
+        let _ = address;
+
+        Ok(None)
+    }
+
+    fn save_vtxo_risk_status(
+        &self,
+        outpoint: OutPoint,
+        status: VtxoRiskStatus,
+    ) -> Result<(), Error>;
+
+    fn load_vtxo_risk_statuses(&self) -> Result<Vec<(OutPoint, VtxoRiskStatus)>, Error>;
+
+    fn save_forfeit(&self, forfeit: ForfeitRecord) -> Result<(), Error>;
+
+    fn load_forfeits(&self) -> Result<Vec<ForfeitRecord>, Error>;
+
+    /// By default, the birthday is not persisted, so it must be set with
+    /// [`crate::OfflineClient::with_birthday`] on every start.
+    fn save_birthday(&self, birthday: WalletBirthday) -> Result<(), Error> {
+        let _ = birthday;
+
+        Ok(())
+    }
+
+    fn load_birthday(&self) -> Result<Option<WalletBirthday>, Error> {
+        Ok(None)
+    }
+
+    /// By default, the configuration set with [`crate::Client::update_config`] is not persisted.
+    fn save_config(&self, config: ClientConfig) -> Result<(), Error> {
+        let _ = config;
+
+        Ok(())
+    }
+
+    fn load_config(&self) -> Result<Option<ClientConfig>, Error> {
+        Ok(None)
+    }
+
+    /// Remember that the out-of-round payment which created the VTXO with outpoint `outpoint` was
+    /// claimed, see [`crate::Client::claim_pending_deliveries`].
+    fn save_claimed_delivery(&self, outpoint: OutPoint) -> Result<(), Error> {
+        let _ = outpoint;
+
+        Ok(())
+    }
+
+    fn load_claimed_deliveries(&self) -> Result<Vec<OutPoint>, Error> {
+        Ok(Vec::new())
+    }
+
+    /// Remember that claimed deliveries are tracked from now on, see
+    /// [`crate::Client::claim_pending_deliveries`].
+    ///
+    /// By default, nothing is remembered, so no delivery is ever announced.
+    fn save_deliveries_initialized(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Whether [`ClientStore::save_deliveries_initialized`] was ever called.
+    fn load_deliveries_initialized(&self) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    /// Record a transaction of a unilateral exit, replacing the record with the same TXID, see
+    /// [`crate::Client::commit_vtxos_on_chain`].
+    fn save_exit_tx(&self, exit_tx: ExitTx) -> Result<(), Error>;
+
+    fn load_exit_txs(&self) -> Result<Vec<ExitTx>, Error>;
+
+    /// Forget the exit transaction with TXID `txid`, once it is no longer needed or can never be
+    /// published.
+    fn delete_exit_tx(&self, txid: Txid) -> Result<(), Error>;
+
+    /// Persist a VTXO of `address` which was handed to us out-of-band, see
+    /// [`crate::Client::import_vtxo`].
+    ///
+    /// Fails by default, since we would lose track of the VTXO on restart.
+    fn save_imported_vtxo(&self, address: ArkAddress, vtxo: VtxoOutPoint) -> Result<(), Error> {
+        let _ = (address, vtxo);
+
+        Err(Error::ad_hoc("this store cannot persist imported VTXOs"))
+    }
+
+    fn load_imported_vtxos(&self, address: &ArkAddress) -> Result<Vec<VtxoOutPoint>, Error> {
+        let _ = address;
+
+        Ok(Vec::new())
+    }
+
+    /// Forget a VTXO imported with [`crate::Client::import_vtxo`], once the Ark server reports it
+    /// or it expired.
+    fn delete_imported_vtxo(&self, address: &ArkAddress, outpoint: OutPoint) -> Result<(), Error> {
+        let _ = (address, outpoint);
+
+        Ok(())
+    }
+
+    /// Save a contact of the address book, replacing the contact with the same name, see
+    /// [`crate::Client::add_contact`].
+    fn save_contact(&self, contact: Contact) -> Result<(), Error> {
+        let _ = contact;
+
+        Ok(())
+    }
+
+    fn load_contacts(&self) -> Result<Vec<Contact>, Error> {
+        Ok(Vec::new())
+    }
+
+    fn delete_contact(&self, name: &str) -> Result<(), Error> {
+        let _ = name;
+
+        Ok(())
+    }
+
+    /// Save a payment receipt that we issued or accepted, replacing the receipt for the same
+    /// VTXO, see [`crate::Client::payment_receipts`].
+    fn save_receipt(&self, receipt: PaymentReceipt) -> Result<(), Error> {
+        let _ = receipt;
+
+        Ok(())
+    }
+
+    fn load_receipts(&self) -> Result<Vec<PaymentReceipt>, Error> {
+        Ok(Vec::new())
+    }
+
+    /// Remember that the event of the Ark server with ID `event_id` was handed to the application,
+    /// so that it is not handed over again when the event is replayed, see
+    /// [`crate::Client::transaction_events`].
+    ///
+    /// By default, nothing is remembered, so replayed events are handed over again.
+    fn save_processed_event(&self, event_id: String) -> Result<(), Error> {
+        let _ = event_id;
+
+        Ok(())
+    }
+
+    /// The IDs saved with [`ClientStore::save_processed_event`], oldest first.
+    fn load_processed_events(&self) -> Result<Vec<String>, Error> {
+        Ok(Vec::new())
+    }
+
+    /// Forget all but the `keep` most recently processed events.
+    fn prune_processed_events(&self, keep: usize) -> Result<(), Error> {
+        let _ = keep;
+
+        Ok(())
+    }
+
+    /// Record how far the exit of a VTXO got, replacing the record for the same VTXO, see
+    /// [`crate::exit_manager::ExitManager`].
+    fn save_vtxo_exit(&self, exit: VtxoExit) -> Result<(), Error> {
+        let _ = exit;
+
+        Ok(())
+    }
+
+    fn load_vtxo_exits(&self) -> Result<Vec<VtxoExit>, Error> {
+        Ok(Vec::new())
+    }
+
+    /// Save an address to watch, replacing the record for the same address, see
+    /// [`crate::Client::watch_address`].
+    fn save_watched_address(&self, watched: WatchedAddress) -> Result<(), Error> {
+        let _ = watched;
+
+        Ok(())
+    }
+
+    fn load_watched_addresses(&self) -> Result<Vec<WatchedAddress>, Error> {
+        Ok(Vec::new())
+    }
+
+    fn delete_watched_address(&self, address: &ArkAddress) -> Result<(), Error> {
+        let _ = address;
+
+        Ok(())
+    }
+
+    /// Save a transaction reconstructed by [`crate::Client::backfill_history`], replacing the
+    /// record with the same TXID and direction.
+    fn save_archived_transaction(&self, tx: ArkTransaction) -> Result<(), Error> {
+        let _ = tx;
+
+        Ok(())
+    }
+
+    fn load_archived_transactions(&self) -> Result<Vec<ArkTransaction>, Error> {
+        Ok(Vec::new())
+    }
+}
+
+/// When a wallet was created, so that its outputs need not be searched for from the genesis block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalletBirthday {
+    /// UNIX timestamp in seconds.
+    pub timestamp: u64,
+    /// The height of the chain tip when the wallet was created, if known.
+    pub height: Option<u32>,
+}
+
+impl WalletBirthday {
+    /// Block timestamps may be up to two hours off, so we look this far before the birthday.
+    const TIMESTAMP_MARGIN_SECS: u64 = 2 * 60 * 60;
+
+    /// The birthday of a wallet created right now.
+    pub fn now() -> Self {
+        Self {
+            timestamp: jiff::Timestamp::now().as_second().max(0) as u64,
+            height: None,
+        }
+    }
+
+    /// The earliest block timestamp of a block which may confirm an output of the wallet.
+    pub fn scan_start_timestamp(&self) -> u64 {
+        self.timestamp.saturating_sub(Self::TIMESTAMP_MARGIN_SECS)
+    }
+}
+
+/// The operation which created a VTXO.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VtxoOrigin {
+    /// The VTXO was created by boarding (or settling existing VTXOs) in the round with this TXID.
+    Board {
+        round_txid: Txid,
+        operation_id: OperationId,
+    },
+    /// The VTXO is the change output of an off-boarding in the round with this TXID.
+    OffBoardChange {
+        round_txid: Txid,
+        operation_id: OperationId,
+    },
+    /// The VTXO was created by recovering swept VTXOs in the round with this TXID.
+    Recovery {
+        round_txid: Txid,
+        operation_id: OperationId,
+    },
+    /// The VTXO is the change output of an out-of-round payment with this redeem TXID.
+    SendChange {
+        redeem_txid: Txid,
+        operation_id: OperationId,
+    },
+    /// The VTXO was linked to an operation by the caller, e.g. to an invoice which it paid.
+    External { id: String },
+}
+
+/// The outcome of screening a VTXO with a [`crate::risk::RiskOracle`], or of reviewing it manually.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VtxoRiskStatus {
+    /// The oracle or the reviewer accepted the VTXO.
+    Accepted,
+    /// The oracle flagged the VTXO. It will not be spent until it is released.
+    Flagged { reason: String },
+    /// The VTXO was flagged, but released after review.
+    Released { reason: String },
+    /// The VTXO was received while manual review is enabled. It will not be spent until it is
+    /// accepted.
+    PendingReview,
+    /// The VTXO was rejected after manual review. It will never be spent, except to refund it with
+    /// [`crate::Client::refund_rejected_vtxos`].
+    Rejected { reason: String },
+}
+
+/// A transaction of the VTXO tree which we publish to get a VTXO on chain, see
+/// [`crate::Client::commit_vtxos_on_chain`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExitTx {
+    pub tx: Transaction,
+    pub status: ExitTxStatus,
+}
+
+/// How far an [`ExitTx`] got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitTxStatus {
+    /// The transaction is part of an exit, but was not broadcast yet.
+    Pending,
+    /// The transaction was broadcast, but is not confirmed yet.
+    Broadcast,
+    /// The transaction is confirmed.
+    Confirmed,
+}
+
+/// The unilateral exit of a VTXO, driven by an [`crate::exit_manager::ExitManager`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VtxoExit {
+    pub vtxo_outpoint: OutPoint,
+    /// Where the VTXO is swept to once its exit delay is over.
+    pub destination: Address,
+    pub status: VtxoExitStatus,
+}
+
+/// How far a [`VtxoExit`] got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VtxoExitStatus {
+    /// The transactions of the VTXO tree leading to the VTXO are being published.
+    Committing,
+    /// The VTXO is confirmed on chain, and can be swept once its exit delay is over.
+    WaitingForExitDelay,
+    /// The VTXO was swept to the destination in the transaction with this TXID.
+    Swept { txid: Txid },
+    /// The sweeping transaction is confirmed: the exit is complete.
+    Completed { txid: Txid },
+}
+
+/// A forfeit transaction which we signed when settling a VTXO in a round.
+///
+/// The Ark server can only publish the forfeit transaction by spending the connector output, so
+/// we keep track of both to be able to monitor the connector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ForfeitRecord {
+    /// The VTXO that we forfeited.
+    pub vtxo_outpoint: OutPoint,
+    /// The connector output spent by the forfeit transaction.
+    pub connector_outpoint: OutPoint,
+    pub forfeit_txid: Txid,
+    /// The round in which we forfeited the VTXO.
+    pub round_txid: Txid,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -0,0 +1,199 @@
+//! Watch Ark addresses which are not ours, e.g. to monitor several wallets from a single
+//! dashboard.
+//!
+//! Watched addresses are read-only: their VTXOs, balance and history are queried from the Ark
+//! server, but they can never be spent by this client.
+
+use crate::transport::NetworkTransport;
+use crate::wallet::BoardingWallet;
+use crate::wallet::ClientStore;
+use crate::wallet::OnchainWallet;
+use crate::Blockchain;
+use crate::Client;
+use crate::DataFreshness;
+use crate::Error;
+use ark_core::generate_incoming_vtxo_transaction_history;
+use ark_core::generate_outgoing_vtxo_transaction_history;
+use ark_core::server::ListVtxo;
+use ark_core::ArkAddress;
+use ark_core::ArkTransaction;
+use bitcoin::Amount;
+use jiff::Timestamp;
+
+#[derive(Debug, Clone)]
+pub struct WatchedAddress {
+    pub address: ArkAddress,
+    /// A name for the address, e.g. the wallet it belongs to.
+    pub label: String,
+    /// The UNIX timestamp in seconds at which we started watching the address.
+    pub added_at: i64,
+}
+
+/// The balance of a [`WatchedAddress`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WatchedBalance {
+    /// VTXOs which were created in a round.
+    pub confirmed: Amount,
+    /// VTXOs which were created out of round and are yet to be settled.
+    pub pending: Amount,
+    /// VTXOs which expired or were swept by the Ark server, but can still be recovered by their
+    /// owner.
+    pub recoverable: Amount,
+    pub freshness: DataFreshness,
+}
+
+impl WatchedBalance {
+    pub fn total(&self) -> Amount {
+        self.confirmed + self.pending + self.recoverable
+    }
+}
+
+impl<B, W, T> Client<B, W, T>
+where
+    B: Blockchain,
+    W: BoardingWallet + OnchainWallet,
+    T: NetworkTransport,
+{
+    /// Start watching `address`, replacing the label of an address which is already watched.
+    ///
+    /// The address must belong to our Ark server and must not be one of our own addresses.
+    pub fn watch_address(
+        &self,
+        address: ArkAddress,
+        label: String,
+    ) -> Result<WatchedAddress, Error> {
+        self.check_recipient_server(&address)?;
+
+        let script_pubkey = address.to_p2tr_script_pubkey();
+        if self
+            .get_offchain_addresses()
+            .iter()
+            .any(|(ours, _)| ours.to_p2tr_script_pubkey() == script_pubkey)
+        {
+            return Err(Error::ad_hoc(format!(
+                "cannot watch address {address}: it is one of ours"
+            )));
+        }
+
+        let added_at = self
+            .watched_address(&address)?
+            .map_or_else(|| Timestamp::now().as_second(), |watched| watched.added_at);
+
+        let watched = WatchedAddress {
+            address,
+            label: label.trim().to_string(),
+            added_at,
+        };
+
+        self.inner
+            .wallet
+            .store()
+            .save_watched_address(watched.clone())?;
+
+        Ok(watched)
+    }
+
+    pub fn unwatch_address(&self, address: &ArkAddress) -> Result<(), Error> {
+        if self.watched_address(address)?.is_none() {
+            return Err(Error::ad_hoc(format!("address {address} is not watched")));
+        }
+
+        self.inner.wallet.store().delete_watched_address(address)
+    }
+
+    /// All watched addresses, sorted by label.
+    pub fn watched_addresses(&self) -> Result<Vec<WatchedAddress>, Error> {
+        let mut watched = self.inner.wallet.store().load_watched_addresses()?;
+        watched.sort_by(|a, b| a.label.cmp(&b.label));
+
+        Ok(watched)
+    }
+
+    /// The VTXOs of the watched address `address`.
+    ///
+    /// If the Ark server is unreachable, the last VTXOs we fetched are returned instead, which is
+    /// reflected in the returned [`DataFreshness`].
+    pub async fn watched_vtxos(
+        &self,
+        address: &ArkAddress,
+    ) -> Result<(ListVtxo, DataFreshness), Error> {
+        self.check_watched(address)?;
+
+        self.list_vtxos_or_cached(address).await
+    }
+
+    /// The balance of the watched address `address`.
+    pub async fn watched_balance(&self, address: &ArkAddress) -> Result<WatchedBalance, Error> {
+        let (vtxos, freshness) = self.watched_vtxos(address).await?;
+
+        let now = Timestamp::now().as_second();
+
+        let mut balance = WatchedBalance {
+            freshness,
+            ..WatchedBalance::default()
+        };
+        for vtxo in vtxos.spendable.iter() {
+            let is_expired = vtxo.expire_at > 0 && vtxo.expire_at <= now;
+            if vtxo.swept || is_expired {
+                balance.recoverable += vtxo.amount;
+            } else if vtxo.is_pending {
+                balance.pending += vtxo.amount;
+            } else {
+                balance.confirmed += vtxo.amount;
+            }
+        }
+
+        Ok(balance)
+    }
+
+    /// The balance of every watched address, sorted by label.
+    pub async fn watched_balances(&self) -> Result<Vec<(WatchedAddress, WatchedBalance)>, Error> {
+        let mut balances = Vec::new();
+        for watched in self.watched_addresses()? {
+            let balance = self.watched_balance(&watched.address).await?;
+            balances.push((watched, balance));
+        }
+
+        Ok(balances)
+    }
+
+    /// The transaction history of the watched address `address`, like
+    /// [`Client::transaction_history`] without boarding transactions.
+    pub async fn watched_transaction_history(
+        &self,
+        address: &ArkAddress,
+    ) -> Result<(Vec<ArkTransaction>, DataFreshness), Error> {
+        let (vtxos, freshness) = self.watched_vtxos(address).await?;
+
+        let incoming_transactions =
+            generate_incoming_vtxo_transaction_history(&vtxos.spent, &vtxos.spendable, &[])?;
+        let outgoing_transactions =
+            generate_outgoing_vtxo_transaction_history(&vtxos.spent, &vtxos.spendable)?;
+
+        let mut txs = [incoming_transactions, outgoing_transactions].concat();
+        txs.sort_by_key(|tx| tx.created_at());
+
+        Ok((txs, freshness))
+    }
+
+    fn watched_address(&self, address: &ArkAddress) -> Result<Option<WatchedAddress>, Error> {
+        let script_pubkey = address.to_p2tr_script_pubkey();
+
+        let watched = self
+            .inner
+            .wallet
+            .store()
+            .load_watched_addresses()?
+            .into_iter()
+            .find(|watched| watched.address.to_p2tr_script_pubkey() == script_pubkey);
+
+        Ok(watched)
+    }
+
+    fn check_watched(&self, address: &ArkAddress) -> Result<(), Error> {
+        match self.watched_address(address)? {
+            Some(_) => Ok(()),
+            None => Err(Error::ad_hoc(format!("address {address} is not watched"))),
+        }
+    }
+}
@@ -0,0 +1,286 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::print_stdout)]
+
+//! Compare the memory we hold on to between the two steps of signing a VTXO tree, when keeping the
+//! whole tree around (`generate_nonce_tree` and `sign_vtxo_tree`) and when processing it one level
+//! at a time (`VtxoTreeSigningSession`).
+//!
+//! Run with `cargo bench -p ark-core --bench vtxo_tree_signing`.
+
+use ark_core::round::generate_nonce_tree;
+use ark_core::round::sign_vtxo_tree;
+use ark_core::round::NodeCosigners;
+use ark_core::round::VtxoTreeSigningSession;
+use ark_core::server::TxTree;
+use ark_core::server::TxTreeLevel;
+use ark_core::server::TxTreeNode;
+use bitcoin::absolute::LockTime;
+use bitcoin::key::Keypair;
+use bitcoin::key::Secp256k1;
+use bitcoin::key::TweakedPublicKey;
+use bitcoin::psbt::raw;
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::transaction;
+use bitcoin::Amount;
+use bitcoin::OutPoint;
+use bitcoin::Psbt;
+use bitcoin::ScriptBuf;
+use bitcoin::Sequence;
+use bitcoin::Transaction;
+use bitcoin::TxIn;
+use bitcoin::TxOut;
+use bitcoin::XOnlyPublicKey;
+use rand::thread_rng;
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+/// The prefix of the PSBT input keys which hold the cosigner PKs of a VTXO tree node.
+const COSIGNER_PSBT_KEY_PREFIX: [u8; 8] = [111, 115, 105, 103, 110, 101, 114, 0];
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// Keeps track of the number of bytes currently allocated and of the peak since the last reset.
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::Relaxed);
+        }
+
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+fn allocated() -> usize {
+    ALLOCATED.load(Ordering::Relaxed)
+}
+
+/// Reset the peak to the current allocation and return it.
+fn reset_peak() -> usize {
+    let allocated = allocated();
+    PEAK.store(allocated, Ordering::Relaxed);
+
+    allocated
+}
+
+fn peak() -> usize {
+    PEAK.load(Ordering::Relaxed)
+}
+
+fn main() {
+    let secp = Secp256k1::new();
+    let mut rng = thread_rng();
+
+    let server_pk = Keypair::new(&secp, &mut rng).x_only_public_key().0;
+    let expiry = Sequence::from_seconds_ceil(604_672).unwrap();
+
+    println!(
+        "{:>8} {:>12} | {:>14} {:>14} {:>10} | {:>14} {:>14} {:>10}",
+        "leaves",
+        "tree (KiB)",
+        "tree held",
+        "tree peak",
+        "tree ms",
+        "session held",
+        "session peak",
+        "session ms"
+    );
+
+    for depth in [4, 6, 8, 10, 12] {
+        let n_leaves = 1 << depth;
+
+        let kps = (0..n_leaves)
+            .map(|_| Keypair::new(&secp, &mut rng))
+            .collect::<Vec<_>>();
+        let own_kp = kps[0];
+        let pks = kps.iter().map(|kp| kp.public_key()).collect::<Vec<_>>();
+
+        // Whole tree: the tree, the round transaction and the nonce tree are held until the
+        // aggregate nonces arrive.
+        let before = reset_peak();
+        let (round_tx, vtxo_tree) = binary_vtxo_tree(&pks, expiry, server_pk);
+        let tree_size = allocated() - before;
+
+        let start = Instant::now();
+        let nonce_tree = generate_nonce_tree(&mut rng, &vtxo_tree, own_kp.public_key()).unwrap();
+        let pub_nonce_tree = nonce_tree.to_pub_nonce_tree();
+        let tree_held = allocated() - before;
+        let partial_sig_tree = sign_vtxo_tree(
+            expiry,
+            server_pk,
+            &own_kp,
+            &vtxo_tree,
+            &round_tx,
+            nonce_tree,
+            &pub_nonce_tree,
+        )
+        .unwrap();
+        let tree_time = start.elapsed();
+        let tree_peak = peak() - before;
+
+        drop((round_tx, vtxo_tree, pub_nonce_tree, partial_sig_tree));
+
+        // Session: every level of the tree is dropped once processed, so only the session is held
+        // until the aggregate nonces arrive.
+        let before = reset_peak();
+        let (round_tx, vtxo_tree) = binary_vtxo_tree(&pks, expiry, server_pk);
+
+        let start = Instant::now();
+        let mut session = VtxoTreeSigningSession::new(expiry, server_pk, &own_kp, &round_tx);
+        drop(round_tx);
+        for level in vtxo_tree.levels.into_iter() {
+            session.add_level(&mut rng, &level).unwrap();
+        }
+        let pub_nonce_tree = session.pub_nonce_tree();
+        let session_held = allocated() - before;
+        let partial_sig_tree = session.sign(&pub_nonce_tree).unwrap();
+        let session_time = start.elapsed();
+        let session_peak = peak() - before;
+
+        drop((pub_nonce_tree, partial_sig_tree));
+
+        println!(
+            "{:>8} {:>12} | {:>14} {:>14} {:>10} | {:>14} {:>14} {:>10}",
+            n_leaves,
+            tree_size / 1024,
+            tree_held / 1024,
+            tree_peak / 1024,
+            tree_time.as_millis(),
+            session_held / 1024,
+            session_peak / 1024,
+            session_time.as_millis()
+        );
+    }
+}
+
+/// A VTXO tree with one leaf per cosigner, where every node is cosigned by the owners of the leaves
+/// below it.
+fn binary_vtxo_tree(
+    cosigner_pks: &[PublicKey],
+    vtxo_tree_expiry: Sequence,
+    server_pk: XOnlyPublicKey,
+) -> (Psbt, TxTree) {
+    let leaf_script =
+        ScriptBuf::new_p2tr_tweaked(TweakedPublicKey::dangerous_assume_tweaked(server_pk));
+    let amount = Amount::from_sat(1_000);
+
+    let round_tx = Psbt::from_unsigned_tx(Transaction {
+        version: transaction::Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: Vec::new(),
+        output: vec![TxOut {
+            value: amount,
+            script_pubkey: node_script(cosigner_pks, vtxo_tree_expiry, server_pk),
+        }],
+    })
+    .unwrap();
+
+    let mut levels = Vec::new();
+    let mut parents = vec![(
+        round_tx.unsigned_tx.compute_txid(),
+        0,
+        cosigner_pks.to_vec(),
+    )];
+    while !parents.is_empty() {
+        let mut nodes = Vec::new();
+        let mut children = Vec::new();
+        for (parent_txid, vout, pks) in parents {
+            let halves = if pks.len() > 1 {
+                let (left, right) = pks.split_at(pks.len() / 2);
+                vec![left.to_vec(), right.to_vec()]
+            } else {
+                Vec::new()
+            };
+
+            let output = if halves.is_empty() {
+                vec![TxOut {
+                    value: amount,
+                    script_pubkey: leaf_script.clone(),
+                }]
+            } else {
+                halves
+                    .iter()
+                    .map(|half| TxOut {
+                        value: amount,
+                        script_pubkey: node_script(half, vtxo_tree_expiry, server_pk),
+                    })
+                    .collect()
+            };
+
+            let tx = node_tx(OutPoint::new(parent_txid, vout), output, &pks);
+
+            let txid = tx.unsigned_tx.compute_txid();
+            for (vout, half) in halves.into_iter().enumerate() {
+                children.push((txid, vout as u32, half));
+            }
+
+            nodes.push(TxTreeNode {
+                txid,
+                tx,
+                parent_txid,
+            });
+        }
+
+        levels.push(TxTreeLevel { nodes });
+        parents = children;
+    }
+
+    (round_tx, TxTree { levels })
+}
+
+/// The script of the output spent by a node cosigned by `pks`.
+fn node_script(
+    pks: &[PublicKey],
+    vtxo_tree_expiry: Sequence,
+    server_pk: XOnlyPublicKey,
+) -> ScriptBuf {
+    let tx = node_tx(OutPoint::null(), Vec::new(), pks);
+
+    NodeCosigners::from_psbt(&tx)
+        .unwrap()
+        .output_script(vtxo_tree_expiry, server_pk)
+}
+
+fn node_tx(previous_output: OutPoint, output: Vec<TxOut>, pks: &[PublicKey]) -> Psbt {
+    let mut tx = Psbt::from_unsigned_tx(Transaction {
+        version: transaction::Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output,
+            ..Default::default()
+        }],
+        output,
+    })
+    .unwrap();
+
+    for (k, pk) in pks.iter().enumerate() {
+        let mut key = COSIGNER_PSBT_KEY_PREFIX.to_vec();
+        key.extend((k as u32).to_le_bytes());
+
+        tx.inputs[0].unknown.insert(
+            raw::Key {
+                type_value: b'c',
+                key,
+            },
+            pk.serialize().to_vec(),
+        );
+    }
+
+    tx
+}
@@ -0,0 +1,266 @@
+//! Double-entry bookkeeping of the balance changes in a transaction history.
+//!
+//! Every [`ArkTransaction`] is turned into a [`JournalEntry`] whose debits and credits add up to
+//! the same amount, so the books always balance.
+
+use crate::ArkTransaction;
+use crate::Direction;
+use crate::Error;
+use bitcoin::Amount;
+use bitcoin::SignedAmount;
+use bitcoin::Txid;
+use std::fmt;
+use std::fmt::Write;
+
+/// An account of the [`Ledger`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Account {
+    /// Our funds: boarding outputs and VTXOs.
+    Wallet,
+    /// Fees paid to the Ark server and to miners.
+    Fees,
+    /// Everyone we receive funds from or send funds to.
+    Counterparties,
+}
+
+impl fmt::Display for Account {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Account::Wallet => "assets:wallet",
+            Account::Fees => "expenses:fees",
+            Account::Counterparties => "equity:counterparties",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// What caused a [`JournalEntry`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EntryKind {
+    /// Funds were sent to one of our boarding addresses.
+    Board,
+    /// We received VTXOs.
+    Receive,
+    /// We sent VTXOs.
+    Send,
+}
+
+/// A debit or credit of an [`Account`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Posting {
+    pub account: Account,
+    pub debit: Amount,
+    pub credit: Amount,
+    /// The balance of `account` after this posting, i.e. all its debits minus all its credits so
+    /// far.
+    #[cfg_attr(feature = "serde", serde(with = "bitcoin::amount::serde::as_sat"))]
+    pub running_balance: SignedAmount,
+}
+
+/// The postings of a single transaction, whose debits and credits add up to the same amount.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JournalEntry {
+    pub txid: Txid,
+    /// See [`ArkTransaction::created_at`].
+    pub created_at: i64,
+    pub kind: EntryKind,
+    pub postings: Vec<Posting>,
+}
+
+/// The books of a wallet, built from its transaction history with [`Ledger::from_history`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ledger {
+    entries: Vec<JournalEntry>,
+}
+
+impl Ledger {
+    /// Record every transaction of `history`, oldest first.
+    ///
+    /// Settlements, e.g. boarding or refreshing VTXOs, are not part of the history, so they leave
+    /// the books unchanged.
+    pub fn from_history(history: &[ArkTransaction]) -> Result<Self, Error> {
+        let mut history = history.to_vec();
+        history.sort_by_key(|tx| tx.created_at());
+
+        let mut ledger = Self::default();
+        for tx in history.iter() {
+            ledger.record(tx)?;
+        }
+
+        Ok(ledger)
+    }
+
+    pub fn entries(&self) -> &[JournalEntry] {
+        &self.entries
+    }
+
+    /// The current balance of `account`, i.e. all its debits minus all its credits.
+    pub fn balance(&self, account: Account) -> SignedAmount {
+        self.entries
+            .iter()
+            .rev()
+            .flat_map(|entry| entry.postings.iter())
+            .find(|posting| posting.account == account)
+            .map_or(SignedAmount::ZERO, |posting| posting.running_balance)
+    }
+
+    /// Export every posting as CSV, one posting per line, with amounts in sats.
+    pub fn to_csv(&self) -> String {
+        let mut csv = "created_at,txid,kind,account,debit,credit,running_balance\n".to_string();
+
+        for entry in self.entries.iter() {
+            for posting in entry.postings.iter() {
+                let _ = writeln!(
+                    csv,
+                    "{},{},{:?},{},{},{},{}",
+                    entry.created_at,
+                    entry.txid,
+                    entry.kind,
+                    posting.account,
+                    posting.debit.to_sat(),
+                    posting.credit.to_sat(),
+                    posting.running_balance.to_sat(),
+                );
+            }
+        }
+
+        csv
+    }
+
+    fn record(&mut self, tx: &ArkTransaction) -> Result<(), Error> {
+        let amount = tx.amount();
+
+        // (account, debit, credit)
+        let movements = match amount.direction {
+            Direction::Incoming => vec![
+                (Account::Wallet, amount.net, Amount::ZERO),
+                (Account::Counterparties, Amount::ZERO, amount.net),
+            ],
+            Direction::Outgoing => {
+                let fee = amount.fee.unwrap_or(Amount::ZERO);
+                let sent = amount.net.checked_sub(fee).ok_or_else(|| {
+                    Error::ad_hoc(format!(
+                        "fee {fee} of transaction {} exceeds its net amount {}",
+                        tx.txid(),
+                        amount.net
+                    ))
+                })?;
+
+                let mut movements = vec![(Account::Counterparties, sent, Amount::ZERO)];
+                if fee > Amount::ZERO {
+                    movements.push((Account::Fees, fee, Amount::ZERO));
+                }
+                movements.push((Account::Wallet, Amount::ZERO, amount.net));
+
+                movements
+            }
+        };
+
+        let kind = match (tx, amount.direction) {
+            (ArkTransaction::Boarding { .. }, _) => EntryKind::Board,
+            (_, Direction::Incoming) => EntryKind::Receive,
+            (_, Direction::Outgoing) => EntryKind::Send,
+        };
+
+        let mut postings = Vec::with_capacity(movements.len());
+        for (account, debit, credit) in movements {
+            let change = debit.to_signed().map_err(Error::ad_hoc)?
+                - credit.to_signed().map_err(Error::ad_hoc)?;
+
+            postings.push(Posting {
+                account,
+                debit,
+                credit,
+                running_balance: self.balance(account) + change,
+            });
+        }
+
+        self.entries.push(JournalEntry {
+            txid: tx.txid(),
+            created_at: tx.created_at(),
+            kind,
+            postings,
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TransactionAmount;
+    use bitcoin::hashes::Hash;
+
+    fn txid(n: u8) -> Txid {
+        Txid::from_byte_array([n; 32])
+    }
+
+    #[test]
+    fn books_balance() {
+        let history = [
+            ArkTransaction::Redeem {
+                txid: txid(2),
+                amount: TransactionAmount {
+                    direction: Direction::Outgoing,
+                    gross: Amount::from_sat(50_000),
+                    net: Amount::from_sat(30_000),
+                    fee: Some(Amount::from_sat(1_000)),
+                },
+                counterparty: None,
+                is_settled: false,
+                created_at: 20,
+            },
+            ArkTransaction::Boarding {
+                txid: txid(1),
+                amount: TransactionAmount::incoming(Amount::from_sat(100_000)),
+                confirmed_at: Some(10),
+            },
+            ArkTransaction::Round {
+                txid: txid(3),
+                amount: TransactionAmount::incoming(Amount::from_sat(5_000)),
+                counterparty: None,
+                created_at: 30,
+            },
+        ];
+
+        let ledger = Ledger::from_history(&history).unwrap();
+
+        let kinds = ledger
+            .entries()
+            .iter()
+            .map(|entry| entry.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [EntryKind::Board, EntryKind::Send, EntryKind::Receive]
+        );
+
+        for entry in ledger.entries() {
+            let debits = entry.postings.iter().map(|p| p.debit).sum::<Amount>();
+            let credits = entry.postings.iter().map(|p| p.credit).sum::<Amount>();
+
+            assert_eq!(debits, credits);
+        }
+
+        assert_eq!(
+            ledger.balance(Account::Wallet),
+            SignedAmount::from_sat(75_000)
+        );
+        assert_eq!(ledger.balance(Account::Fees), SignedAmount::from_sat(1_000));
+        assert_eq!(
+            ledger.balance(Account::Counterparties),
+            SignedAmount::from_sat(-76_000)
+        );
+
+        let csv = ledger.to_csv();
+        assert_eq!(csv.lines().count(), 1 + 2 + 3 + 2);
+        assert!(csv.contains(&format!("20,{},Send,expenses:fees,1000,0,1000", txid(2))));
+    }
+}
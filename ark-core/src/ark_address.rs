@@ -6,7 +6,7 @@ use bitcoin::Network;
 use bitcoin::ScriptBuf;
 use bitcoin::XOnlyPublicKey;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ArkAddress {
     hrp: Hrp,
     server: XOnlyPublicKey,
@@ -17,6 +17,11 @@ impl ArkAddress {
     pub fn to_p2tr_script_pubkey(&self) -> ScriptBuf {
         ScriptBuf::new_p2tr_tweaked(self.vtxo_tap_key)
     }
+
+    /// The public key of the Ark server that the address belongs to.
+    pub fn server(&self) -> XOnlyPublicKey {
+        self.server
+    }
 }
 
 impl ArkAddress {
@@ -46,6 +51,12 @@ impl ArkAddress {
 
     pub fn decode(value: &str) -> Result<Self, Error> {
         let (hrp, bytes) = bech32::decode(value).map_err(Error::address_format)?;
+        if bytes.len() != 64 {
+            return Err(Error::address_format(format!(
+                "invalid Ark address length: {} bytes",
+                bytes.len()
+            )));
+        }
 
         let server = XOnlyPublicKey::from_slice(&bytes[..32]).map_err(Error::address_format)?;
         let vtxo_tap_key =
@@ -69,6 +80,29 @@ impl std::fmt::Display for ArkAddress {
     }
 }
 
+/// Serialized as its encoding, see [`ArkAddress::encode`].
+#[cfg(feature = "serde")]
+impl serde::Serialize for ArkAddress {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.encode())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ArkAddress {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+
+        ArkAddress::decode(&value).map_err(serde::de::Error::custom)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,386 @@
+//! Verify the data of a round published by an Ark server, without trusting the server.
+//!
+//! See [`verify_round`].
+
+use crate::internal_node::VtxoTreeInternalNodeScript;
+use crate::payment_proof::verify_vtxo_tree_node;
+use crate::server::Round;
+use crate::server::TxTree;
+use crate::Error;
+use bitcoin::key::Secp256k1;
+use bitcoin::Amount;
+use bitcoin::OutPoint;
+use bitcoin::Transaction;
+use bitcoin::Txid;
+use bitcoin::XOnlyPublicKey;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+/// What [`verify_round`] checked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedRound {
+    pub round_txid: Txid,
+    /// The number of transactions in the VTXO tree, all of them cosigned by the Ark server.
+    pub n_vtxo_tree_txs: usize,
+    /// The outputs of the leaves of the VTXO tree, i.e. the VTXOs created by the round.
+    pub vtxos: Vec<OutPoint>,
+    /// The number of transactions in the connector tree.
+    pub n_connector_tree_txs: usize,
+}
+
+/// Verify the data of `round` against `round_tx`, the round transaction as confirmed on the
+/// blockchain.
+///
+/// The following is checked:
+///
+/// - `round` describes `round_tx`.
+/// - Every transaction of the VTXO tree and of the connector tree has the TXID it is listed with,
+///   and spends a single output of its parent, which is either the round transaction or a
+///   transaction of the level above.
+/// - No output is spent by more than one transaction of a tree.
+/// - No transaction of a tree spends more than the output it spends.
+/// - Every transaction of the VTXO tree lists the Ark server `server_pk` among its cosigners, and
+///   the output it spends is locked by the aggregate key of its cosigners, tweaked with the sweep
+///   leaf of `server_pk` after `vtxo_tree_expiry`.
+/// - Every transaction of the VTXO tree carries a valid key-spend signature for that key.
+///
+/// Only the confirmation of `round_tx` itself is left to the caller, who is expected to fetch it
+/// from a source they trust.
+pub fn verify_round(
+    round: &Round,
+    round_tx: &Transaction,
+    server_pk: XOnlyPublicKey,
+    vtxo_tree_expiry: bitcoin::Sequence,
+) -> Result<VerifiedRound, Error> {
+    let round_txid = round_tx.compute_txid();
+
+    let listed_round_txid = round.round_tx.unsigned_tx.compute_txid();
+    if listed_round_txid != round_txid {
+        return Err(Error::ad_hoc(format!(
+            "round {} lists round transaction {listed_round_txid}, not {round_txid}",
+            round.id
+        )));
+    }
+
+    let vtxos = verify_tree(
+        &round.vtxo_tree,
+        round_tx,
+        Some((server_pk, vtxo_tree_expiry)),
+    )
+    .map_err(|e| Error::ad_hoc(format!("invalid VTXO tree in round {round_txid}: {e}")))?;
+
+    verify_tree(&round.connector_tree, round_tx, None)
+        .map_err(|e| Error::ad_hoc(format!("invalid connector tree in round {round_txid}: {e}")))?;
+
+    Ok(VerifiedRound {
+        round_txid,
+        n_vtxo_tree_txs: count_txs(&round.vtxo_tree),
+        vtxos,
+        n_connector_tree_txs: count_txs(&round.connector_tree),
+    })
+}
+
+/// Check the links between the transactions of `tree`, rooted in `round_tx`, returning the outputs
+/// of its leaves.
+///
+/// If `server` is set, to the PK of the Ark server and the VTXO tree expiry, every transaction must
+/// also be cosigned by the Ark server, see [`verify_vtxo_tree_node`].
+pub(crate) fn verify_tree(
+    tree: &TxTree,
+    round_tx: &Transaction,
+    server: Option<(XOnlyPublicKey, bitcoin::Sequence)>,
+) -> Result<Vec<OutPoint>, Error> {
+    let secp = Secp256k1::verification_only();
+    let server = server.map(|(server_pk, vtxo_tree_expiry)| {
+        (
+            server_pk,
+            VtxoTreeInternalNodeScript::new(vtxo_tree_expiry, server_pk),
+        )
+    });
+
+    let mut parents = HashMap::from([(round_tx.compute_txid(), round_tx.clone())]);
+    let mut spent = HashSet::new();
+    let mut leaves = Vec::new();
+
+    for (i, level) in tree.levels.iter().enumerate() {
+        let mut level_txs = HashMap::with_capacity(level.nodes.len());
+        for node in level.nodes.iter() {
+            let tx = &node.tx.unsigned_tx;
+            let txid = tx.compute_txid();
+
+            if txid != node.txid {
+                return Err(Error::ad_hoc(format!(
+                    "transaction listed as {} at level {i} has TXID {txid}",
+                    node.txid
+                )));
+            }
+
+            let previous_output = match tx.input.as_slice() {
+                [input] => input.previous_output,
+                _ => {
+                    return Err(Error::ad_hoc(format!(
+                        "transaction {txid} must have exactly one input"
+                    )))
+                }
+            };
+
+            if previous_output.txid != node.parent_txid {
+                return Err(Error::ad_hoc(format!(
+                    "transaction {txid} does not spend its listed parent {}",
+                    node.parent_txid
+                )));
+            }
+
+            let prevout = parents
+                .get(&previous_output.txid)
+                .and_then(|parent| parent.output.get(previous_output.vout as usize))
+                .cloned()
+                .ok_or_else(|| {
+                    Error::ad_hoc(format!(
+                        "transaction {txid} spends {previous_output}, which is not an output of \
+                         the level above"
+                    ))
+                })?;
+
+            if !spent.insert(previous_output) {
+                return Err(Error::ad_hoc(format!(
+                    "output {previous_output} is spent more than once"
+                )));
+            }
+
+            let output_value = tx.output.iter().map(|output| output.value).sum::<Amount>();
+            if output_value > prevout.value {
+                return Err(Error::ad_hoc(format!(
+                    "transaction {txid} spends {output_value}, more than the {} it receives",
+                    prevout.value
+                )));
+            }
+
+            if let Some((server_pk, internal_node_script)) = server.as_ref() {
+                verify_vtxo_tree_node(&secp, internal_node_script, *server_pk, &node.tx, prevout)?;
+            }
+
+            level_txs.insert(txid, tx.clone());
+        }
+
+        // Outputs of the level above which are not spent by this level are leaves.
+        leaves.extend(unspent_outputs(&parents, &spent).filter(|_| i > 0));
+
+        parents = level_txs;
+    }
+
+    if !tree.levels.is_empty() {
+        leaves.extend(unspent_outputs(&parents, &spent));
+    }
+
+    leaves.sort();
+
+    Ok(leaves)
+}
+
+fn unspent_outputs<'a>(
+    txs: &'a HashMap<Txid, Transaction>,
+    spent: &'a HashSet<OutPoint>,
+) -> impl Iterator<Item = OutPoint> + 'a {
+    txs.iter()
+        .flat_map(|(txid, tx)| {
+            (0..tx.output.len()).map(move |vout| OutPoint::new(*txid, vout as u32))
+        })
+        .filter(move |outpoint| !spent.contains(outpoint))
+}
+
+fn count_txs(tree: &TxTree) -> usize {
+    tree.levels.iter().map(|level| level.nodes.len()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::TxTreeLevel;
+    use crate::test_utils::cosigned_child;
+    use crate::test_utils::keypair;
+    use crate::test_utils::node_output;
+    use crate::test_utils::p2tr_output;
+    use crate::test_utils::signed_child;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::transaction;
+    use bitcoin::Psbt;
+    use bitcoin::Sequence;
+    use bitcoin::TxIn;
+
+    #[test]
+    fn round_is_verified() {
+        let server = keypair(1);
+        let alice = keypair(2);
+        let bob = keypair(3);
+        let (server_pk, _) = server.x_only_public_key();
+        let expiry = Sequence::from_seconds_ceil(604_672).unwrap();
+
+        let amount = Amount::from_sat(100_000);
+        let half = Amount::from_sat(50_000);
+
+        let everyone = [&server, &alice, &bob];
+        let alice_and_server = [&server, &alice];
+        let bob_and_server = [&server, &bob];
+
+        let round_tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: vec![node_output(&everyone, expiry, server_pk, amount)],
+        };
+
+        let root = cosigned_child(
+            &round_tx,
+            0,
+            &everyone,
+            expiry,
+            server_pk,
+            vec![
+                node_output(&alice_and_server, expiry, server_pk, half),
+                node_output(&bob_and_server, expiry, server_pk, half),
+            ],
+        );
+        let alice_leaf = cosigned_child(
+            &root.tx.unsigned_tx,
+            0,
+            &alice_and_server,
+            expiry,
+            server_pk,
+            vec![p2tr_output(&alice, half)],
+        );
+        let bob_leaf = cosigned_child(
+            &root.tx.unsigned_tx,
+            1,
+            &bob_and_server,
+            expiry,
+            server_pk,
+            vec![p2tr_output(&bob, half)],
+        );
+
+        let round = Round {
+            id: "round".to_string(),
+            start: 0,
+            end: 0,
+            round_tx: Psbt::from_unsigned_tx(round_tx.clone()).unwrap(),
+            vtxo_tree: TxTree {
+                levels: vec![
+                    TxTreeLevel {
+                        nodes: vec![root.clone()],
+                    },
+                    TxTreeLevel {
+                        nodes: vec![alice_leaf.clone(), bob_leaf.clone()],
+                    },
+                ],
+            },
+            forfeit_txs: Vec::new(),
+            connector_tree: TxTree { levels: Vec::new() },
+            stage: 0,
+        };
+
+        let verified = verify_round(&round, &round_tx, server_pk, expiry).unwrap();
+
+        let mut vtxos = vec![
+            OutPoint::new(alice_leaf.txid, 0),
+            OutPoint::new(bob_leaf.txid, 0),
+        ];
+        vtxos.sort();
+        assert_eq!(
+            verified,
+            VerifiedRound {
+                round_txid: round_tx.compute_txid(),
+                n_vtxo_tree_txs: 3,
+                vtxos,
+                n_connector_tree_txs: 0,
+            }
+        );
+
+        // The round must match the confirmed round transaction.
+        let mut other_round_tx = round_tx.clone();
+        other_round_tx.lock_time = LockTime::from_consensus(1);
+        assert!(verify_round(&round, &other_round_tx, server_pk, expiry).is_err());
+
+        // The VTXO tree must be cosigned by the expected Ark server, with the expected expiry.
+        let (other_server_pk, _) = alice.x_only_public_key();
+        assert!(verify_round(&round, &round_tx, other_server_pk, expiry).is_err());
+        let other_expiry = Sequence::from_seconds_ceil(1_024).unwrap();
+        assert!(verify_round(&round, &round_tx, server_pk, other_expiry).is_err());
+
+        // Every transaction must spend its listed parent.
+        let mut relinked = round.clone();
+        relinked.vtxo_tree.levels[1].nodes[1].parent_txid = round_tx.compute_txid();
+        assert!(verify_round(&relinked, &round_tx, server_pk, expiry).is_err());
+
+        // An output cannot be spent twice.
+        let mut double_spent = round.clone();
+        double_spent.vtxo_tree.levels[1].nodes[1] = cosigned_child(
+            &root.tx.unsigned_tx,
+            0,
+            &alice_and_server,
+            expiry,
+            server_pk,
+            vec![p2tr_output(&bob, half)],
+        );
+        assert!(verify_round(&double_spent, &round_tx, server_pk, expiry).is_err());
+
+        // Every transaction of the VTXO tree must be signed by its cosigners.
+        let mut forged = round;
+        forged.vtxo_tree.levels[1].nodes[1] =
+            signed_child(&root.tx.unsigned_tx, 1, &bob, vec![p2tr_output(&bob, half)]);
+        assert!(verify_round(&forged, &round_tx, server_pk, expiry).is_err());
+    }
+
+    #[test]
+    fn vtxo_tree_not_cosigned_by_server_is_rejected() {
+        let server = keypair(1);
+        let alice = keypair(2);
+        let (server_pk, _) = server.x_only_public_key();
+        let expiry = Sequence::from_seconds_ceil(604_672).unwrap();
+
+        let amount = Amount::from_sat(100_000);
+
+        // A tree whose outputs commit to the sweep leaf of the Ark server, but which the Ark server
+        // did not cosign.
+        let round_tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: vec![node_output(&[&alice], expiry, server_pk, amount)],
+        };
+        let leaf = cosigned_child(
+            &round_tx,
+            0,
+            &[&alice],
+            expiry,
+            server_pk,
+            vec![p2tr_output(&alice, amount)],
+        );
+
+        let round = Round {
+            id: "round".to_string(),
+            start: 0,
+            end: 0,
+            round_tx: Psbt::from_unsigned_tx(round_tx.clone()).unwrap(),
+            vtxo_tree: TxTree {
+                levels: vec![TxTreeLevel { nodes: vec![leaf] }],
+            },
+            forfeit_txs: Vec::new(),
+            connector_tree: TxTree { levels: Vec::new() },
+            stage: 0,
+        };
+
+        assert!(verify_round(&round, &round_tx, server_pk, expiry).is_err());
+
+        // Nor is a tree signed by the holder of a plain key.
+        let mut self_signed = round;
+        self_signed.round_tx.unsigned_tx.output[0] = p2tr_output(&alice, amount);
+        let self_signed_round_tx = self_signed.round_tx.unsigned_tx.clone();
+        self_signed.vtxo_tree.levels[0].nodes[0] = signed_child(
+            &self_signed_round_tx,
+            0,
+            &alice,
+            vec![p2tr_output(&alice, amount)],
+        );
+        assert!(verify_round(&self_signed, &self_signed_round_tx, server_pk, expiry).is_err());
+    }
+}
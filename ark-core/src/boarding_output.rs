@@ -1,11 +1,13 @@
+use crate::exit_delay::BlockTime;
+use crate::exit_delay::ExitDelay;
 use crate::script::csv_sig_script;
 use crate::script::multisig_script;
 use crate::script::tr_script_pubkey;
+use crate::Error;
 use crate::UNSPENDABLE_KEY;
 use bitcoin::key::PublicKey;
 use bitcoin::key::Secp256k1;
 use bitcoin::key::Verification;
-use bitcoin::relative;
 use bitcoin::taproot;
 use bitcoin::taproot::LeafVersion;
 use bitcoin::taproot::TaprootBuilder;
@@ -72,6 +74,10 @@ impl BoardingOutput {
         &self.address
     }
 
+    pub fn server_pk(&self) -> XOnlyPublicKey {
+        self.server
+    }
+
     pub fn owner_pk(&self) -> XOnlyPublicKey {
         self.owner
     }
@@ -114,18 +120,17 @@ impl BoardingOutput {
         self.exit_delay
     }
 
-    pub fn exit_delay_duration(&self) -> Duration {
-        let exit_delay = self
-            .exit_delay
-            .to_relative_lock_time()
-            .expect("relative lock time");
+    /// Whether the exit delay counts blocks or seconds.
+    ///
+    /// Fails if the exit delay is not a relative time lock, e.g. for a boarding output persisted
+    /// with a corrupted exit delay.
+    pub fn typed_exit_delay(&self) -> Result<ExitDelay, Error> {
+        ExitDelay::from_sequence(self.exit_delay)
+    }
 
-        match exit_delay {
-            relative::LockTime::Time(time) => Duration::from_secs(time.value() as u64 * 512),
-            relative::LockTime::Blocks(_) => {
-                unreachable!("Only seconds timelock is supported");
-            }
-        }
+    /// How long the exit path stays locked for, see [`ExitDelay::duration`].
+    pub fn exit_delay_duration(&self) -> Result<Duration, Error> {
+        Ok(self.typed_exit_delay()?.duration())
     }
 
     pub fn tapscripts(&self) -> Vec<ScriptBuf> {
@@ -135,16 +140,30 @@ impl BoardingOutput {
         vec![exit_script, forfeit_script]
     }
 
-    /// Whether the boarding output can be claimed unilaterally by the owner or not, given the
-    /// `confirmation_blocktime` of the transaction that included this boarding output as an output.
-    pub fn can_be_claimed_unilaterally_by_owner(
+    /// The earliest median time past of the tip of the blockchain at which the boarding output
+    /// can be claimed unilaterally by the owner, given the `confirmed_median_time_past` of the
+    /// output.
+    ///
+    /// See [`ExitDelay::claimable_at`].
+    pub fn exit_claimable_at(
         &self,
-        now: Duration,
-        confirmation_blocktime: Duration,
-    ) -> bool {
-        let exit_path_time = confirmation_blocktime + self.exit_delay_duration();
+        confirmed_median_time_past: Duration,
+    ) -> Result<Option<Duration>, Error> {
+        Ok(self
+            .typed_exit_delay()?
+            .claimable_at(confirmed_median_time_past))
+    }
 
-        now > exit_path_time
+    /// Whether the boarding output can be claimed unilaterally by the owner or not, given when it
+    /// was `confirmed` and the `tip` of the blockchain, see [`BlockTime`].
+    ///
+    /// See [`ExitDelay::has_elapsed`].
+    pub fn can_be_claimed_unilaterally_by_owner(
+        &self,
+        confirmed: BlockTime,
+        tip: BlockTime,
+    ) -> Result<bool, Error> {
+        Ok(self.typed_exit_delay()?.has_elapsed(confirmed, tip))
     }
 
     fn forfeit_script(&self) -> ScriptBuf {
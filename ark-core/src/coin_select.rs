@@ -9,27 +9,158 @@ pub struct VtxoOutPoint {
     pub amount: Amount,
 }
 
+/// The order in which candidate VTXOs are considered during coin selection.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CoinSelectionStrategy {
+    /// Spend the VTXOs which expire soonest first, so that they do not have to be refreshed.
+    #[default]
+    OldestExpiryFirst,
+    /// Spend the largest VTXOs first, keeping the number of inputs low.
+    LargestFirst,
+    /// Spend as few VTXOs as possible, each of them being a branch of a VTXO tree that would have
+    /// to be published to exit unilaterally.
+    ///
+    /// The smallest VTXO which covers the amount on its own is preferred. Otherwise, the largest
+    /// VTXOs are spent first.
+    BranchMinimizing,
+}
+
+impl CoinSelectionStrategy {
+    /// Sort `vtxo_outpoints` in the order in which they should be considered to pay `amount`.
+    fn sort(&self, vtxo_outpoints: &mut [VtxoOutPoint], amount: Amount) {
+        match self {
+            CoinSelectionStrategy::OldestExpiryFirst => {
+                vtxo_outpoints.sort_by_key(|vtxo| vtxo.expire_at);
+            }
+            CoinSelectionStrategy::LargestFirst => {
+                vtxo_outpoints.sort_by_key(|vtxo| std::cmp::Reverse(vtxo.amount));
+            }
+            CoinSelectionStrategy::BranchMinimizing => {
+                vtxo_outpoints.sort_by_key(|vtxo| std::cmp::Reverse(vtxo.amount));
+
+                // The last VTXO which covers the amount on its own is the smallest one.
+                let covering = vtxo_outpoints
+                    .iter()
+                    .rposition(|vtxo| vtxo.amount >= amount);
+                if let Some(i) = covering {
+                    vtxo_outpoints[..=i].rotate_right(1);
+                }
+            }
+        }
+    }
+}
+
+/// How a candidate VTXO was treated during coin selection.
+///
+/// Only reported with the `coin-select-trace` feature, to debug surprising input choices.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CandidateEvaluation {
+    pub outpoint: OutPoint,
+    pub amount: Amount,
+    pub expire_at: i64,
+    /// The position of the candidate in the order in which candidates are considered, starting
+    /// from 0. Candidates with a lower rank are preferred. Excluded candidates have no rank.
+    pub rank: Option<usize>,
+    pub decision: CandidateDecision,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CandidateDecision {
+    /// Selected to reach the target amount.
+    Selected,
+    /// Selected on top of the target amount, so that the change is not dust.
+    SelectedToAvoidDust,
+    /// Eligible, but not needed to reach the target amount.
+    NotNeeded,
+    /// Not eligible for selection.
+    Excluded { reason: String },
+}
+
 /// Select VTXOs to be used as inputs in redeem (out-of-round) transactions.
 pub fn select_vtxos(
-    mut vtxo_outpoints: Vec<VtxoOutPoint>,
+    vtxo_outpoints: Vec<VtxoOutPoint>,
     amount: Amount,
     dust: Amount,
     sort_by_expiration_time: bool,
+) -> Result<Vec<VtxoOutPoint>, Error> {
+    let strategy = sort_by_expiration_time.then_some(CoinSelectionStrategy::OldestExpiryFirst);
+
+    select(vtxo_outpoints, amount, dust, strategy, |_, _, _| {})
+}
+
+/// Like [`select_vtxos`], considering candidates in the order given by `strategy`.
+pub fn select_vtxos_with_strategy(
+    vtxo_outpoints: Vec<VtxoOutPoint>,
+    amount: Amount,
+    dust: Amount,
+    strategy: CoinSelectionStrategy,
+) -> Result<Vec<VtxoOutPoint>, Error> {
+    select(vtxo_outpoints, amount, dust, Some(strategy), |_, _, _| {})
+}
+
+/// Like [`select_vtxos_with_strategy`], but also reporting how every candidate was treated, even if
+/// selection fails.
+#[cfg(feature = "coin-select-trace")]
+pub fn select_vtxos_with_trace(
+    vtxo_outpoints: Vec<VtxoOutPoint>,
+    amount: Amount,
+    dust: Amount,
+    strategy: CoinSelectionStrategy,
+) -> (Result<Vec<VtxoOutPoint>, Error>, Vec<CandidateEvaluation>) {
+    let mut evaluations = Vec::new();
+
+    let selected = select(
+        vtxo_outpoints,
+        amount,
+        dust,
+        Some(strategy),
+        |rank, vtxo, decision| {
+            // A candidate selected to avoid dust was first found not to be needed.
+            evaluations
+                .retain(|evaluation: &CandidateEvaluation| evaluation.outpoint != vtxo.outpoint);
+
+            evaluations.push(CandidateEvaluation {
+                outpoint: vtxo.outpoint,
+                amount: vtxo.amount,
+                expire_at: vtxo.expire_at,
+                rank: Some(rank),
+                decision,
+            });
+        },
+    );
+
+    evaluations.sort_by_key(|evaluation| evaluation.rank);
+
+    (selected, evaluations)
+}
+
+/// Select VTXOs, calling `on_evaluated` with the rank of every candidate and what was decided
+/// about it.
+///
+/// Without a `strategy`, candidates are considered in the order in which they are given.
+fn select(
+    mut vtxo_outpoints: Vec<VtxoOutPoint>,
+    amount: Amount,
+    dust: Amount,
+    strategy: Option<CoinSelectionStrategy>,
+    mut on_evaluated: impl FnMut(usize, &VtxoOutPoint, CandidateDecision),
 ) -> Result<Vec<VtxoOutPoint>, Error> {
     let mut selected = Vec::new();
     let mut not_selected = Vec::new();
     let mut selected_amount = Amount::ZERO;
 
-    if sort_by_expiration_time {
-        // Sort vtxos by expiration (older first)
-        vtxo_outpoints.sort_by(|a, b| a.expire_at.cmp(&b.expire_at));
+    if let Some(strategy) = strategy {
+        strategy.sort(&mut vtxo_outpoints, amount);
     }
 
     // Process VTXOs
-    for vtxo_outpoint in vtxo_outpoints {
+    for (rank, vtxo_outpoint) in vtxo_outpoints.into_iter().enumerate() {
         if selected_amount >= amount {
-            not_selected.push(vtxo_outpoint);
+            on_evaluated(rank, &vtxo_outpoint, CandidateDecision::NotNeeded);
+            not_selected.push((rank, vtxo_outpoint));
         } else {
+            on_evaluated(rank, &vtxo_outpoint, CandidateDecision::Selected);
             selected.push(vtxo_outpoint.clone());
             selected_amount += vtxo_outpoint.amount;
         }
@@ -44,7 +175,8 @@ pub fn select_vtxos(
     // Try to avoid generating dust.
     let change_amount = selected_amount - amount;
     if change_amount < dust {
-        if let Some(vtxo) = not_selected.first() {
+        if let Some((rank, vtxo)) = not_selected.first() {
+            on_evaluated(*rank, vtxo, CandidateDecision::SelectedToAvoidDust);
             selected.push(vtxo.clone());
         }
     }
@@ -83,4 +215,104 @@ mod tests {
         let result = select_vtxos(vtxos, Amount::from_sat(1000), Amount::from_sat(50), true);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn strategies_order_candidates() {
+        let vtxo = |vout, expire_at, amount| VtxoOutPoint {
+            outpoint: OutPoint {
+                vout,
+                ..OutPoint::default()
+            },
+            expire_at,
+            amount: Amount::from_sat(amount),
+        };
+        let vtxos = vec![
+            vtxo(0, 300, 5_000),
+            vtxo(1, 100, 1_000),
+            vtxo(2, 200, 3_000),
+            vtxo(3, 400, 2_000),
+        ];
+        let dust = Amount::from_sat(330);
+
+        let select = |amount, strategy| {
+            select_vtxos_with_strategy(vtxos.clone(), Amount::from_sat(amount), dust, strategy)
+                .unwrap()
+                .iter()
+                .map(|vtxo| vtxo.outpoint.vout)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            select(3_500, CoinSelectionStrategy::OldestExpiryFirst),
+            vec![1, 2]
+        );
+        assert_eq!(select(3_500, CoinSelectionStrategy::LargestFirst), vec![0]);
+        assert_eq!(
+            select(5_500, CoinSelectionStrategy::LargestFirst),
+            vec![0, 2]
+        );
+
+        // A single VTXO suffices, and the smallest one is picked.
+        assert_eq!(
+            select(2_500, CoinSelectionStrategy::BranchMinimizing),
+            vec![2]
+        );
+        assert_eq!(
+            select(5_500, CoinSelectionStrategy::BranchMinimizing),
+            vec![0, 2]
+        );
+    }
+
+    #[cfg(feature = "coin-select-trace")]
+    #[test]
+    fn trace_reports_every_candidate() {
+        let outpoint = |vout| OutPoint {
+            vout,
+            ..OutPoint::default()
+        };
+        let vtxos = vec![
+            VtxoOutPoint {
+                outpoint: outpoint(0),
+                ..vtxo(300, Amount::from_sat(1_000))
+            },
+            VtxoOutPoint {
+                outpoint: outpoint(1),
+                ..vtxo(100, Amount::from_sat(2_000))
+            },
+            VtxoOutPoint {
+                outpoint: outpoint(2),
+                ..vtxo(200, Amount::from_sat(500))
+            },
+        ];
+
+        let (selected, trace) = select_vtxos_with_trace(
+            vtxos,
+            Amount::from_sat(2_400),
+            Amount::from_sat(330),
+            CoinSelectionStrategy::OldestExpiryFirst,
+        );
+
+        // 2_000 + 500 leaves 100 of change, so the last candidate is selected to avoid dust.
+        assert_eq!(selected.unwrap().len(), 3);
+        assert_eq!(
+            trace
+                .iter()
+                .map(|evaluation| (evaluation.outpoint.vout, evaluation.decision.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (1, CandidateDecision::Selected),
+                (2, CandidateDecision::Selected),
+                (0, CandidateDecision::SelectedToAvoidDust),
+            ]
+        );
+
+        let (selected, trace) = select_vtxos_with_trace(
+            vec![vtxo(100, Amount::from_sat(100))],
+            Amount::from_sat(1_000),
+            Amount::from_sat(50),
+            CoinSelectionStrategy::OldestExpiryFirst,
+        );
+        assert!(selected.is_err());
+        assert_eq!(trace.len(), 1);
+    }
 }
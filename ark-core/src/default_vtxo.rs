@@ -1,4 +1,6 @@
 use crate::ark_address::ArkAddress;
+use crate::exit_delay::BlockTime;
+use crate::exit_delay::ExitDelay;
 use crate::script::csv_sig_script;
 use crate::script::multisig_script;
 use crate::script::tr_script_pubkey;
@@ -6,7 +8,6 @@ use crate::UNSPENDABLE_KEY;
 use bitcoin::key::PublicKey;
 use bitcoin::key::Secp256k1;
 use bitcoin::key::Verification;
-use bitcoin::relative;
 use bitcoin::taproot;
 use bitcoin::taproot::LeafVersion;
 use bitcoin::taproot::TaprootBuilder;
@@ -29,7 +30,7 @@ pub struct DefaultVtxo {
     ark_descriptor: String,
     address: Address,
     exit_delay: bitcoin::Sequence,
-    exit_delay_seconds: u64,
+    typed_exit_delay: ExitDelay,
     network: Network,
 }
 
@@ -37,6 +38,9 @@ impl DefaultVtxo {
     /// 64 bytes per pubkey. In the default VTXO we have 2 pubkeys
     pub const FORFEIT_WITNESS_SIZE: usize = 64 * 2;
 
+    /// 64 bytes for the owner's signature.
+    pub const EXIT_WITNESS_SIZE: usize = 64;
+
     /// Build a default VTXO.
     pub fn new<C>(
         secp: &Secp256k1<C>,
@@ -62,15 +66,16 @@ impl DefaultVtxo {
             .finalize(secp, unspendable_key)
             .expect("can be finalized");
 
-        let exit_delay_seconds = match exit_delay.to_relative_lock_time() {
-            Some(relative::LockTime::Time(time)) => time.value() * 512,
-            _ => unreachable!("default VTXO redeem script must use relative lock time in seconds"),
-        };
+        let typed_exit_delay = ExitDelay::from_sequence(exit_delay)
+            .expect("default VTXO redeem script must use a relative lock time");
         let ark_descriptor = DEFAULT_VTXO_DESCRIPTOR_TEMPLATE
             .replace("UNSPENDABLE_KEY", unspendable_key.to_string().as_str())
             .replace("USER", owner.to_string().as_str())
             .replace("SERVER", server.to_string().as_str())
-            .replace("TIMEOUT", exit_delay_seconds.to_string().as_str());
+            .replace(
+                "TIMEOUT",
+                typed_exit_delay.descriptor_value().to_string().as_str(),
+            );
 
         let script_pubkey = tr_script_pubkey(&spend_info);
         let address = Address::from_script(&script_pubkey, network).expect("valid script");
@@ -82,11 +87,15 @@ impl DefaultVtxo {
             ark_descriptor,
             address,
             exit_delay,
-            exit_delay_seconds: exit_delay_seconds as u64,
+            typed_exit_delay,
             network,
         }
     }
 
+    pub fn owner_pk(&self) -> XOnlyPublicKey {
+        self.owner
+    }
+
     pub fn spend_info(&self) -> &TaprootSpendInfo {
         &self.spend_info
     }
@@ -107,8 +116,14 @@ impl DefaultVtxo {
         self.exit_delay
     }
 
+    /// Whether the exit delay counts blocks or seconds.
+    pub fn typed_exit_delay(&self) -> ExitDelay {
+        self.typed_exit_delay
+    }
+
+    /// How long the exit path stays locked for, see [`ExitDelay::duration`].
     pub fn exit_delay_duration(&self) -> Duration {
-        Duration::from_secs(self.exit_delay_seconds)
+        self.typed_exit_delay.duration()
     }
 
     pub fn to_ark_address(&self) -> ArkAddress {
@@ -150,16 +165,25 @@ impl DefaultVtxo {
         vec![exit_script, forfeit_script]
     }
 
-    /// Whether the VTXO can be claimed unilaterally by the owner or not, given the
-    /// `confirmation_blocktime` of the transaction that included this VTXO as an output.
+    /// The earliest median time past of the tip of the blockchain at which the VTXO can be claimed
+    /// unilaterally by the owner, given the `confirmed_median_time_past` of the output.
+    ///
+    /// See [`ExitDelay::claimable_at`].
+    pub fn exit_claimable_at(&self, confirmed_median_time_past: Duration) -> Option<Duration> {
+        self.typed_exit_delay
+            .claimable_at(confirmed_median_time_past)
+    }
+
+    /// Whether the VTXO can be claimed unilaterally by the owner or not, given when it was
+    /// `confirmed` on chain and the `tip` of the blockchain, see [`BlockTime`].
+    ///
+    /// See [`ExitDelay::has_elapsed`].
     pub fn can_be_claimed_unilaterally_by_owner(
         &self,
-        now: Duration,
-        confirmation_blocktime: Duration,
+        confirmed: BlockTime,
+        tip: BlockTime,
     ) -> bool {
-        let exit_path_time = confirmation_blocktime + self.exit_delay_duration();
-
-        now > exit_path_time
+        self.typed_exit_delay.has_elapsed(confirmed, tip)
     }
 
     fn forfeit_script(&self) -> ScriptBuf {
@@ -170,3 +194,26 @@ impl DefaultVtxo {
         csv_sig_script(self.exit_delay, self.owner)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::secp256k1::SecretKey;
+
+    #[test]
+    fn exit_delay_of_more_than_u16_seconds() {
+        let secp = Secp256k1::new();
+        let pk = |byte| {
+            SecretKey::from_slice(&[byte; 32])
+                .unwrap()
+                .x_only_public_key(&secp)
+                .0
+        };
+        let exit_delay = bitcoin::Sequence::from_seconds_ceil(86_528).unwrap();
+
+        let vtxo = DefaultVtxo::new(&secp, pk(1), pk(2), exit_delay, Network::Regtest);
+
+        assert!(vtxo.ark_descriptor().contains("older(86528)"));
+        assert_eq!(vtxo.exit_delay_duration(), Duration::from_secs(86_528));
+    }
+}
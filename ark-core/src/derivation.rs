@@ -0,0 +1,229 @@
+//! Derive many offchain addresses from a single extended private key, so that a fresh Ark address
+//! can be used for every payment.
+
+use crate::DefaultVtxo;
+use crate::Error;
+use bitcoin::bip32::ChildNumber;
+use bitcoin::bip32::Xpriv;
+use bitcoin::key::Keypair;
+use bitcoin::key::Secp256k1;
+use bitcoin::secp256k1::Signing;
+use bitcoin::secp256k1::Verification;
+use bitcoin::Network;
+use bitcoin::Sequence;
+use bitcoin::XOnlyPublicKey;
+
+/// The default number of consecutive unused addresses after which a [`GapLimitScan`] stops.
+pub const DEFAULT_GAP_LIMIT: u32 = 20;
+
+/// The hardened child of the account-level key under which internal keys are derived.
+const INTERNAL_BRANCH: u32 = 1;
+
+/// Derives the keys owning our VTXOs from an extended private key.
+///
+/// The keys are split into two branches (see [`KeychainKind`]). The external key for index `i` is
+/// derived at `<xpriv>/i` and the internal one at `<xpriv>/1'/i`, so that the two branches can
+/// never overlap. Callers are expected to pass an account-level key, e.g. derived at a path
+/// reserved for Ark.
+#[derive(Clone, Debug)]
+pub struct OffchainKeychain {
+    xpriv: Xpriv,
+}
+
+/// A branch of an [`OffchainKeychain`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KeychainKind {
+    /// Keys whose addresses are handed out to receive payments.
+    External,
+    /// Keys whose addresses only ever receive our own change, and are never shown to anyone.
+    Internal,
+}
+
+impl OffchainKeychain {
+    pub fn new(xpriv: Xpriv) -> Self {
+        Self { xpriv }
+    }
+
+    /// The keypair at `index` of the `kind` branch.
+    pub fn keypair<C>(
+        &self,
+        secp: &Secp256k1<C>,
+        kind: KeychainKind,
+        index: u32,
+    ) -> Result<Keypair, Error>
+    where
+        C: Signing,
+    {
+        let child = ChildNumber::from_normal_idx(index).map_err(Error::crypto)?;
+        let path = match kind {
+            KeychainKind::External => vec![child],
+            KeychainKind::Internal => vec![
+                ChildNumber::from_hardened_idx(INTERNAL_BRANCH).expect("valid index"),
+                child,
+            ],
+        };
+
+        let xpriv = self.xpriv.derive_priv(secp, &path).map_err(Error::crypto)?;
+
+        Ok(xpriv.to_keypair(secp))
+    }
+
+    /// The [`DefaultVtxo`] owned by the keypair at `index` of the `kind` branch, together with
+    /// that keypair.
+    pub fn default_vtxo<C>(
+        &self,
+        secp: &Secp256k1<C>,
+        kind: KeychainKind,
+        index: u32,
+        server: XOnlyPublicKey,
+        exit_delay: Sequence,
+        network: Network,
+    ) -> Result<(Keypair, DefaultVtxo), Error>
+    where
+        C: Signing + Verification,
+    {
+        let kp = self.keypair(secp, kind, index)?;
+        let (owner, _) = kp.x_only_public_key();
+
+        let vtxo = DefaultVtxo::new(secp, server, owner, exit_delay, network);
+
+        Ok((kp, vtxo))
+    }
+}
+
+/// Walks through derivation indices, stopping after `gap_limit` consecutive unused addresses.
+///
+/// ```
+/// # use ark_core::derivation::GapLimitScan;
+/// let used = [true, false, true];
+///
+/// let mut scan = GapLimitScan::new(2);
+/// while let Some(index) = scan.next_index() {
+///     scan.record(used.get(index as usize).copied().unwrap_or(false));
+/// }
+///
+/// assert_eq!(scan.last_used(), Some(2));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct GapLimitScan {
+    gap_limit: u32,
+    next_index: u32,
+    last_used: Option<u32>,
+}
+
+impl GapLimitScan {
+    pub fn new(gap_limit: u32) -> Self {
+        Self {
+            gap_limit,
+            next_index: 0,
+            last_used: None,
+        }
+    }
+
+    /// The index to check next, or `None` if the scan is over.
+    pub fn next_index(&self) -> Option<u32> {
+        let first_unused = self.last_used.map_or(0, |index| index + 1);
+        let gap = self.next_index - first_unused;
+
+        if gap >= self.gap_limit || ChildNumber::from_normal_idx(self.next_index).is_err() {
+            return None;
+        }
+
+        Some(self.next_index)
+    }
+
+    /// Record whether the address at [`GapLimitScan::next_index`] has been used.
+    pub fn record(&mut self, is_used: bool) {
+        if is_used {
+            self.last_used = Some(self.next_index);
+        }
+
+        self.next_index += 1;
+    }
+
+    /// The highest index found to be used so far.
+    pub fn last_used(&self) -> Option<u32> {
+        self.last_used
+    }
+}
+
+/// The keypair in `kps` which can sign for `pk`.
+pub(crate) fn keypair_for(kps: &[Keypair], pk: XOnlyPublicKey) -> Result<&Keypair, Error> {
+    kps.iter()
+        .find(|kp| kp.x_only_public_key().0 == pk)
+        .ok_or_else(|| Error::crypto(format!("no keypair for public key {pk}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::secp256k1::SecretKey;
+    use bitcoin::NetworkKind;
+
+    fn keychain() -> OffchainKeychain {
+        OffchainKeychain::new(Xpriv::new_master(NetworkKind::Test, &[7; 32]).unwrap())
+    }
+
+    #[test]
+    fn derives_distinct_keys_per_index() {
+        let secp = Secp256k1::new();
+        let keychain = keychain();
+
+        let first = keychain.keypair(&secp, KeychainKind::External, 0).unwrap();
+        let second = keychain.keypair(&secp, KeychainKind::External, 1).unwrap();
+
+        assert_ne!(first.public_key(), second.public_key());
+        assert_eq!(
+            keychain.keypair(&secp, KeychainKind::External, 0).unwrap(),
+            first
+        );
+        assert!(keychain
+            .keypair(&secp, KeychainKind::External, 1 << 31)
+            .is_err());
+
+        // The internal branch shares no key with the external one.
+        let internal = (0..4)
+            .map(|i| keychain.keypair(&secp, KeychainKind::Internal, i).unwrap())
+            .collect::<Vec<_>>();
+        assert!(!internal.contains(&first));
+        assert!(!internal.contains(&second));
+        assert_ne!(internal[0], internal[1]);
+
+        let server = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[1; 32]).unwrap());
+        let (kp, vtxo) = keychain
+            .default_vtxo(
+                &secp,
+                KeychainKind::External,
+                1,
+                server.x_only_public_key().0,
+                Sequence::from_seconds_ceil(86_400).unwrap(),
+                Network::Regtest,
+            )
+            .unwrap();
+
+        assert_eq!(kp, second);
+        assert_eq!(vtxo.owner_pk(), second.x_only_public_key().0);
+    }
+
+    #[test]
+    fn gap_limit_scan_stops_after_gap() {
+        let used = [false, true, false, false, true];
+
+        let mut scan = GapLimitScan::new(3);
+        let mut checked = Vec::new();
+        while let Some(index) = scan.next_index() {
+            checked.push(index);
+            scan.record(used.get(index as usize).copied().unwrap_or(false));
+        }
+
+        assert_eq!(scan.last_used(), Some(4));
+        assert_eq!(checked, (0..8).collect::<Vec<_>>());
+
+        let mut scan = GapLimitScan::new(3);
+        while scan.next_index().is_some() {
+            scan.record(false);
+        }
+
+        assert_eq!(scan.last_used(), None);
+    }
+}
@@ -0,0 +1,184 @@
+//! Specification of how this crate derives keys and addresses from a seed.
+//!
+//! A wallet restored from the same seed must find the same VTXOs and boarding outputs. This module
+//! documents how this crate derives them and pins the derivation with regression vectors taken
+//! from its own output, so that a change breaking the restore of existing wallets is caught.
+//!
+//! It describes this crate only: other Ark SDKs may derive keys differently, e.g. not use the seed
+//! directly as the secret key. See [`crate::derivation`] for deriving many addresses from an
+//! extended private key.
+//!
+//! # Derivation
+//!
+//! 1. The 32-byte seed is used directly as the secp256k1 secret key of the Ark identity. No BIP32
+//!    derivation is applied. The owner key `USER` is the x-only public key of that secret key.
+//!
+//! 2. A default VTXO is a taproot output with the unspendable internal key [`UNSPENDABLE_KEY`] and
+//!    two leaves at depth 1, in this order:
+//!
+//!    - forfeit: `<SERVER> OP_CHECKSIGVERIFY <USER> OP_CHECKSIG`.
+//!    - exit: `<exit_delay> OP_CHECKSEQUENCEVERIFY OP_DROP <USER> OP_CHECKSIG`, where `exit_delay`
+//!      is the server's `unilateral_exit_delay`, encoded as a BIP68 relative time lock.
+//!
+//! 3. The offchain address is the bech32m encoding of `SERVER || VTXO_TAP_KEY`, where
+//!    `VTXO_TAP_KEY` is the tweaked output key of the default VTXO. The HRP is `ark` on mainnet and
+//!    `tark` everywhere else.
+//!
+//! 4. A boarding output uses the same tapscript as the default VTXO, with the boarding exit delay.
+//!    The boarding address is the P2TR address of its output key.
+//!
+//! [`UNSPENDABLE_KEY`]: crate::UNSPENDABLE_KEY
+
+use crate::ArkAddress;
+use crate::BoardingOutput;
+use crate::DefaultVtxo;
+use crate::Error;
+use bitcoin::key::Keypair;
+use bitcoin::key::Secp256k1;
+use bitcoin::key::Verification;
+use bitcoin::secp256k1::SecretKey;
+use bitcoin::secp256k1::Signing;
+use bitcoin::Address;
+use bitcoin::Network;
+use bitcoin::XOnlyPublicKey;
+
+/// The keypair of the Ark identity derived from `seed`.
+pub fn identity_keypair<C>(secp: &Secp256k1<C>, seed: &[u8; 32]) -> Result<Keypair, Error>
+where
+    C: Signing,
+{
+    let sk = SecretKey::from_slice(seed).map_err(Error::crypto)?;
+
+    Ok(Keypair::from_secret_key(secp, &sk))
+}
+
+/// The offchain address of the Ark identity derived from `seed`.
+pub fn offchain_address<C>(
+    secp: &Secp256k1<C>,
+    seed: &[u8; 32],
+    server: XOnlyPublicKey,
+    exit_delay: bitcoin::Sequence,
+    network: Network,
+) -> Result<ArkAddress, Error>
+where
+    C: Signing + Verification,
+{
+    let (owner, _) = identity_keypair(secp, seed)?.x_only_public_key();
+
+    let vtxo = DefaultVtxo::new(secp, server, owner, exit_delay, network);
+
+    Ok(vtxo.to_ark_address())
+}
+
+/// The boarding address of the Ark identity derived from `seed`.
+pub fn boarding_address<C>(
+    secp: &Secp256k1<C>,
+    seed: &[u8; 32],
+    server: XOnlyPublicKey,
+    boarding_descriptor_template: &str,
+    exit_delay: bitcoin::Sequence,
+    network: Network,
+) -> Result<Address, Error>
+where
+    C: Signing + Verification,
+{
+    let (owner, _) = identity_keypair(secp, seed)?.x_only_public_key();
+
+    let boarding_output = BoardingOutput::new(
+        secp,
+        server,
+        owner,
+        boarding_descriptor_template,
+        exit_delay,
+        network,
+    );
+
+    Ok(boarding_output.address().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hex::DisplayHex;
+    use bitcoin::hex::FromHex;
+    use std::str::FromStr;
+
+    // Server key of the address in
+    // https://github.com/ark-network/ark/blob/b536a9e65252573aaa48110ef5d0c90894eb550c/common/fixtures/encoding.json.
+    const SERVER: &str = "33ffb3dee353b1a9ebe4ced64b946238d0a4ac364f275d771da6ad2445d07ae0";
+
+    // An address from the same fixture, as encoded by the upstream Go implementation.
+    const UPSTREAM_ADDRESS: &str = "tark1x0lm8hhr2wc6n6lyemtyh9rz8rg2ftpkfun46aca56kjg3ws0tsztfpuanaquxc6faedvjk3tax0575y6perapg3e95654pk8r4fjecs5fyd2";
+
+    // The address vectors below are pinned from this crate, so that any change to the derivation
+    // is caught. Such a change breaks restoring wallets created by earlier versions.
+
+    const SEED: &str = "0101010101010101010101010101010101010101010101010101010101010101";
+
+    fn seed() -> [u8; 32] {
+        <[u8; 32]>::from_hex(SEED).unwrap()
+    }
+
+    fn server() -> XOnlyPublicKey {
+        XOnlyPublicKey::from_str(SERVER).unwrap()
+    }
+
+    #[test]
+    fn upstream_address_layout() {
+        let address = ArkAddress::decode(UPSTREAM_ADDRESS).unwrap();
+
+        assert_eq!(address.server(), server());
+        assert_eq!(address.encode(), UPSTREAM_ADDRESS);
+    }
+
+    #[test]
+    fn identity_key_is_seed() {
+        let secp = Secp256k1::new();
+
+        let kp = identity_keypair(&secp, &seed()).unwrap();
+
+        assert_eq!(kp.secret_bytes().to_lower_hex_string(), SEED);
+        assert_eq!(
+            kp.x_only_public_key().0.to_string(),
+            "1b84c5567b126440995d3ed5aaba0565d71e1834604819ff9c17f5e9d5dd078f"
+        );
+    }
+
+    #[test]
+    fn offchain_address_vector() {
+        let secp = Secp256k1::new();
+        let exit_delay = bitcoin::Sequence::from_seconds_ceil(86_528).unwrap();
+
+        let address =
+            offchain_address(&secp, &seed(), server(), exit_delay, Network::Regtest).unwrap();
+
+        assert_eq!(
+            address.encode(),
+            "tark1x0lm8hhr2wc6n6lyemtyh9rz8rg2ftpkfun46aca56kjg3ws0ts03mkx9tksf30rxrrgsa6kauch43x8u5unmwzagz8sz6muvhx66lsnvae8n"
+        );
+
+        let decoded = ArkAddress::decode(&address.encode()).unwrap();
+        assert_eq!(decoded.encode(), address.encode());
+    }
+
+    #[test]
+    fn boarding_address_vector() {
+        let secp = Secp256k1::new();
+        let exit_delay = bitcoin::Sequence::from_seconds_ceil(604_672).unwrap();
+
+        let address = boarding_address(
+            &secp,
+            &seed(),
+            server(),
+            "tr(0250929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0,{and(pk(USER),pk(33ffb3dee353b1a9ebe4ced64b946238d0a4ac364f275d771da6ad2445d07ae0)),and(older(604672),pk(USER))})",
+            exit_delay,
+            Network::Regtest,
+        )
+        .unwrap();
+
+        assert_eq!(
+            address.to_string(),
+            "bcrt1pe7dtwfhdeyrnxx4cscwyg4hzhjtjgrarjw8zqcggkrmx9ngq5zdqe2d6nw"
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! Relative time locks guarding the exit paths of boarding outputs and VTXOs.
+//!
+//! A BIP68 relative time lock counts either blocks or seconds, in units of 512 seconds. The two
+//! cannot be compared with each other, so we keep track of which one we are dealing with.
+//!
+//! Seconds are measured in median time past (MTP), the median of the timestamps of a block and of
+//! the 10 blocks before it, rather than in block timestamps, which miners can set up to two hours
+//! in the future.
+
+use crate::Error;
+use bitcoin::relative;
+use bitcoin::Sequence;
+use std::time::Duration;
+
+/// Values reported by the Ark server below this threshold are interpreted as a number of blocks,
+/// and as a number of seconds otherwise.
+const SERVER_SECONDS_THRESHOLD: i64 = 512;
+
+/// The number of blocks whose timestamps make up the median time past of a block: the block
+/// itself and the 10 blocks before it.
+pub const MEDIAN_TIME_SPAN: usize = 11;
+
+/// A point of the blockchain, to check whether an [`ExitDelay`] has elapsed: a block height and a
+/// median time past.
+///
+/// For the tip of the blockchain, this is the height of the tip and its own median time past. For
+/// a confirmed output, this is the height of the block confirming it and the median time past of
+/// the block _before_ it, from which BIP68 counts time-based delays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockTime {
+    pub height: u32,
+    /// The median time past, as a duration since the UNIX epoch.
+    pub median_time_past: Duration,
+}
+
+impl BlockTime {
+    pub fn new(height: u32, median_time_past: Duration) -> Self {
+        Self {
+            height,
+            median_time_past,
+        }
+    }
+}
+
+/// The median time past of a block, given the `timestamps` of the block and of the blocks before
+/// it, in UNIX seconds and in any order.
+///
+/// Only the [`MEDIAN_TIME_SPAN`] last blocks count, but fewer are enough close to the genesis
+/// block. Returns `None` if `timestamps` is empty.
+pub fn median_time_past(timestamps: &[u64]) -> Option<Duration> {
+    let mut timestamps = timestamps.to_vec();
+    timestamps.sort_unstable();
+
+    timestamps
+        .get(timestamps.len() / 2)
+        .map(|median| Duration::from_secs(*median))
+}
+
+/// The exit delay of a boarding output or VTXO.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitDelay {
+    /// The exit path can be used once this many blocks have been mined on top of the block
+    /// confirming the output.
+    Blocks(u16),
+    /// The exit path can be used once this many seconds have passed since the output was
+    /// confirmed. Always a multiple of 512.
+    Seconds(u32),
+}
+
+impl ExitDelay {
+    /// Parse the exit delay reported by the Ark server, rejecting values which cannot be encoded
+    /// as a relative time lock.
+    ///
+    /// Values below 512 are a number of blocks. Larger values are a number of seconds, rounded up
+    /// to the next multiple of 512.
+    pub fn from_server_value(value: i64) -> Result<Self, Error> {
+        if value <= 0 {
+            return Err(Error::ad_hoc(format!(
+                "invalid exit delay {value}: must be positive"
+            )));
+        }
+
+        if value < SERVER_SECONDS_THRESHOLD {
+            return Ok(Self::Blocks(value as u16));
+        }
+
+        let seconds = u32::try_from(value)
+            .map_err(|_| Error::ad_hoc(format!("invalid exit delay {value}: too large")))?;
+
+        let sequence = Sequence::from_seconds_ceil(seconds)
+            .map_err(|e| Error::ad_hoc(format!("invalid exit delay {value}: {e}")))?;
+
+        Self::from_sequence(sequence)
+    }
+
+    /// Interpret a `sequence`, as used in a CSV script or transaction input.
+    pub fn from_sequence(sequence: Sequence) -> Result<Self, Error> {
+        match sequence.to_relative_lock_time() {
+            Some(relative::LockTime::Blocks(height)) => Ok(Self::Blocks(height.value())),
+            Some(relative::LockTime::Time(time)) => Ok(Self::Seconds(time.value() as u32 * 512)),
+            None => Err(Error::ad_hoc(format!(
+                "sequence {sequence} is not a relative time lock"
+            ))),
+        }
+    }
+
+    pub fn to_sequence(&self) -> Sequence {
+        match self {
+            Self::Blocks(blocks) => Sequence::from_height(*blocks),
+            Self::Seconds(seconds) => Sequence::from_512_second_intervals((seconds / 512) as u16),
+        }
+    }
+
+    /// The value of the time lock, as used in the `older` fragment of a descriptor.
+    pub fn descriptor_value(&self) -> u32 {
+        match self {
+            Self::Blocks(blocks) => *blocks as u32,
+            Self::Seconds(seconds) => *seconds,
+        }
+    }
+
+    /// The earliest median time past of the tip of the blockchain from which an output can be
+    /// spent via an exit path with this delay, given the `confirmed_median_time_past` of the
+    /// output, i.e. that of the block before the one confirming it.
+    ///
+    /// Block-based delays cannot be converted to timestamps, so this is `None` for them.
+    pub fn claimable_at(&self, confirmed_median_time_past: Duration) -> Option<Duration> {
+        match self {
+            Self::Blocks(_) => None,
+            Self::Seconds(seconds) => {
+                Some(confirmed_median_time_past + Duration::from_secs(*seconds as u64))
+            }
+        }
+    }
+
+    /// Whether an output `confirmed` at some point of the blockchain can be spent via an exit path
+    /// with this delay, now that the blockchain is at `tip`.
+    ///
+    /// In both cases, the delay has elapsed once a transaction spending the output can be included
+    /// in the next block. Block-based delays compare block heights. Time-based delays compare the
+    /// median time past of `tip` with [`ExitDelay::claimable_at`], like BIP68.
+    pub fn has_elapsed(&self, confirmed: BlockTime, tip: BlockTime) -> bool {
+        match self {
+            Self::Blocks(blocks) => {
+                let next_height = tip.height.saturating_add(1);
+
+                next_height.saturating_sub(confirmed.height) >= *blocks as u32
+            }
+            Self::Seconds(seconds) => {
+                tip.median_time_past
+                    >= confirmed.median_time_past + Duration::from_secs(*seconds as u64)
+            }
+        }
+    }
+
+    /// How long the exit path stays locked for.
+    ///
+    /// For block-based delays, this is an estimate assuming 10 minutes per block.
+    pub fn duration(&self) -> Duration {
+        match self {
+            Self::Blocks(blocks) => Duration::from_secs(*blocks as u64 * 600),
+            Self::Seconds(seconds) => Duration::from_secs(*seconds as u64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn server_value_units() {
+        assert_eq!(
+            ExitDelay::from_server_value(144).unwrap(),
+            ExitDelay::Blocks(144)
+        );
+        assert_eq!(
+            ExitDelay::from_server_value(86_400).unwrap(),
+            ExitDelay::Seconds(86_528)
+        );
+
+        assert!(ExitDelay::from_server_value(0).is_err());
+        assert!(ExitDelay::from_server_value(-1).is_err());
+        assert!(ExitDelay::from_server_value(512 * 65_536).is_err());
+    }
+
+    #[test]
+    fn sequence_roundtrip() {
+        for exit_delay in [ExitDelay::Blocks(144), ExitDelay::Seconds(86_528)] {
+            assert_eq!(
+                ExitDelay::from_sequence(exit_delay.to_sequence()).unwrap(),
+                exit_delay
+            );
+        }
+
+        assert!(ExitDelay::from_sequence(Sequence::MAX).is_err());
+    }
+
+    #[test]
+    fn has_elapsed_compares_like_units() {
+        let confirmed = BlockTime::new(100, Duration::from_secs(1_000_000));
+        // A single block, but long after the output was confirmed.
+        let later = BlockTime::new(
+            101,
+            confirmed.median_time_past + Duration::from_secs(100_000),
+        );
+
+        assert!(ExitDelay::Seconds(86_528).has_elapsed(confirmed, later));
+        assert!(!ExitDelay::Seconds(86_528).has_elapsed(confirmed, confirmed));
+
+        // 144 blocks are not 144 seconds.
+        assert!(!ExitDelay::Blocks(144).has_elapsed(confirmed, later));
+    }
+
+    #[test]
+    fn block_delay_elapses_with_blocks() {
+        let confirmed = BlockTime::new(100, Duration::from_secs(1_000_000));
+        let tip = |height| BlockTime::new(height, confirmed.median_time_past);
+
+        // With the output confirmed at height 100, a spend with a delay of 144 blocks can be
+        // included at height 244 at the earliest, i.e. once the tip is at height 243.
+        assert!(!ExitDelay::Blocks(144).has_elapsed(confirmed, tip(242)));
+        assert!(ExitDelay::Blocks(144).has_elapsed(confirmed, tip(243)));
+        assert!(ExitDelay::Blocks(144).has_elapsed(confirmed, tip(1_000)));
+    }
+
+    #[test]
+    fn claimable_from_the_boundary() {
+        let confirmed = Duration::from_secs(1_000_000);
+        let exit_delay = ExitDelay::Seconds(1_024);
+
+        let claimable_at = exit_delay.claimable_at(confirmed).unwrap();
+        assert_eq!(claimable_at, Duration::from_secs(1_001_024));
+
+        let confirmed = BlockTime::new(100, confirmed);
+        let tip = |time| BlockTime::new(101, time);
+
+        assert!(exit_delay.has_elapsed(confirmed, tip(claimable_at)));
+        assert!(!exit_delay.has_elapsed(confirmed, tip(claimable_at - Duration::from_secs(1))));
+
+        assert_eq!(
+            ExitDelay::Blocks(144).claimable_at(confirmed.median_time_past),
+            None
+        );
+    }
+
+    #[test]
+    fn median_time_past_of_unordered_timestamps() {
+        // Block timestamps need not increase: the last block claims to be far in the future.
+        let timestamps = [
+            1_000, 1_600, 1_200, 2_000, 1_800, 2_400, 2_200, 2_600, 3_000, 2_800, 100_000,
+        ];
+
+        assert_eq!(
+            median_time_past(&timestamps),
+            Some(Duration::from_secs(2_200))
+        );
+
+        // Close to the genesis block, with an even number of blocks.
+        assert_eq!(
+            median_time_past(&[1_000, 3_000]),
+            Some(Duration::from_secs(3_000))
+        );
+        assert_eq!(median_time_past(&[]), None);
+    }
+}
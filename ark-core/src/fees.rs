@@ -0,0 +1,210 @@
+//! The fees charged by the Ark server for taking part in a round.
+
+use crate::server::RoundOutput;
+use crate::server::RoundOutputAddress;
+use crate::server::TxTree;
+use crate::Error;
+use bitcoin::Amount;
+use bitcoin::FeeRate;
+use bitcoin::Psbt;
+use bitcoin::ScriptBuf;
+
+/// The virtual size of a taproot script-path spend of a boarding output or VTXO, with a single
+/// signature.
+const SCRIPT_PATH_INPUT_VBYTES: u64 = 91;
+
+/// The virtual size of a P2TR output.
+const P2TR_OUTPUT_VBYTES: u64 = 43;
+
+/// How much the Ark server charges for a round registration, depending on its size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FeeSchedule {
+    /// Charged for every boarding output or VTXO registered as a round input.
+    pub per_input: Amount,
+    /// Charged for every VTXO created by the round.
+    pub per_vtxo_output: Amount,
+    /// Charged for every on-chain output of the round transaction, e.g. when off-boarding.
+    pub per_onchain_output: Amount,
+}
+
+impl FeeSchedule {
+    /// Our own estimate of what a round registration should cost: the on-chain weight that every
+    /// input and output adds to the round, paid at `fee_rate`.
+    ///
+    /// Used in place of a schedule published by the Ark server, so that the fee charged by a round
+    /// can be checked even if the server does not publish one.
+    pub fn estimate(fee_rate: FeeRate) -> Self {
+        let fee_for_vbytes = |vbytes: u64| {
+            fee_rate
+                .fee_vb(vbytes)
+                .expect("fee for a single input or output to fit in an amount")
+        };
+
+        Self {
+            per_input: fee_for_vbytes(SCRIPT_PATH_INPUT_VBYTES),
+            per_vtxo_output: fee_for_vbytes(P2TR_OUTPUT_VBYTES),
+            per_onchain_output: fee_for_vbytes(P2TR_OUTPUT_VBYTES),
+        }
+    }
+
+    /// The fee for registering `n_inputs` inputs, `n_vtxo_outputs` VTXO outputs and
+    /// `n_onchain_outputs` on-chain outputs for a round.
+    pub fn round_fee(
+        &self,
+        n_inputs: usize,
+        n_vtxo_outputs: usize,
+        n_onchain_outputs: usize,
+    ) -> Amount {
+        self.per_input * n_inputs as u64
+            + self.per_vtxo_output * n_vtxo_outputs as u64
+            + self.per_onchain_output * n_onchain_outputs as u64
+    }
+
+    /// The fee for registering `outputs` together with `n_inputs` inputs.
+    pub fn round_fee_for_outputs(&self, n_inputs: usize, outputs: &[RoundOutput]) -> Amount {
+        let n_onchain_outputs = outputs
+            .iter()
+            .filter(|output| matches!(output.address(), RoundOutputAddress::OnChain(_)))
+            .count();
+        let n_vtxo_outputs = outputs.len() - n_onchain_outputs;
+
+        self.round_fee(n_inputs, n_vtxo_outputs, n_onchain_outputs)
+    }
+}
+
+/// The fee actually charged by a round, given the value of the inputs we registered
+/// (`inputs_total`) and the `outputs` we registered.
+///
+/// Every registered output is looked up in the leaves of `vtxo_tree` or in the outputs of
+/// `round_tx`, depending on its type. The fee is whatever the round does not pay back to us.
+pub fn charged_round_fee(
+    round_tx: &Psbt,
+    vtxo_tree: &TxTree,
+    outputs: &[RoundOutput],
+    inputs_total: Amount,
+) -> Result<Amount, Error> {
+    let leaves = vtxo_tree.leaves();
+
+    let mut vtxo_candidates = leaves
+        .iter()
+        .flat_map(|leaf| leaf.tx.unsigned_tx.output.iter())
+        .map(|output| Some((output.script_pubkey.clone(), output.value)))
+        .collect::<Vec<_>>();
+    let mut onchain_candidates = round_tx
+        .unsigned_tx
+        .output
+        .iter()
+        .map(|output| Some((output.script_pubkey.clone(), output.value)))
+        .collect::<Vec<_>>();
+
+    let mut received = Amount::ZERO;
+    for output in outputs.iter() {
+        let (script_pubkey, candidates) = match output.address() {
+            RoundOutputAddress::Virtual(address) => {
+                (address.to_p2tr_script_pubkey(), &mut vtxo_candidates)
+            }
+            RoundOutputAddress::OnChain(address) => {
+                (address.script_pubkey(), &mut onchain_candidates)
+            }
+        };
+
+        received += take_output(candidates, &script_pubkey).ok_or_else(|| {
+            Error::ad_hoc(format!(
+                "round does not include our output to {}",
+                output.address().serialize()
+            ))
+        })?;
+    }
+
+    inputs_total.checked_sub(received).ok_or_else(|| {
+        Error::ad_hoc(format!(
+            "round pays us {received}, more than our inputs are worth: {inputs_total}"
+        ))
+    })
+}
+
+/// Remove the first output locked by `script_pubkey` from `candidates`, returning its value.
+fn take_output(
+    candidates: &mut [Option<(ScriptBuf, Amount)>],
+    script_pubkey: &ScriptBuf,
+) -> Option<Amount> {
+    let candidate = candidates.iter_mut().find(|candidate| {
+        candidate
+            .as_ref()
+            .is_some_and(|(candidate, _)| candidate == script_pubkey)
+    })?;
+
+    candidate.take().map(|(_, amount)| amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute;
+    use bitcoin::transaction;
+    use bitcoin::Transaction;
+    use bitcoin::TxOut;
+    use std::str::FromStr;
+
+    #[test]
+    fn round_fee_depends_on_registration_size() {
+        let fees = FeeSchedule {
+            per_input: Amount::from_sat(100),
+            per_vtxo_output: Amount::from_sat(10),
+            per_onchain_output: Amount::from_sat(1_000),
+        };
+
+        assert_eq!(fees.round_fee(2, 1, 1), Amount::from_sat(1_210));
+        assert_eq!(FeeSchedule::default().round_fee(2, 1, 1), Amount::ZERO);
+    }
+
+    #[test]
+    fn estimate_pays_the_weight_of_the_registration() {
+        let fees = FeeSchedule::estimate(FeeRate::from_sat_per_vb_u32(2));
+
+        assert_eq!(fees.per_input, Amount::from_sat(182));
+        assert_eq!(fees.per_vtxo_output, Amount::from_sat(86));
+        assert_eq!(fees.round_fee(1, 1, 0), Amount::from_sat(268));
+        assert_eq!(
+            FeeSchedule::estimate(FeeRate::ZERO).round_fee(2, 1, 1),
+            Amount::ZERO
+        );
+    }
+
+    #[test]
+    fn charged_round_fee_is_what_the_round_does_not_pay_back() {
+        let address = bitcoin::Address::from_str("bcrt1q8frde3yn78tl9ecgq4anlz909jh0clefhucdur")
+            .unwrap()
+            .assume_checked();
+
+        let round_tx = Psbt::from_unsigned_tx(Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: Vec::new(),
+            output: vec![TxOut {
+                value: Amount::from_sat(9_000),
+                script_pubkey: address.script_pubkey(),
+            }],
+        })
+        .unwrap();
+        let vtxo_tree = TxTree { levels: Vec::new() };
+
+        let outputs = [RoundOutput::new_on_chain(
+            address.clone(),
+            Amount::from_sat(10_000),
+        )];
+
+        let charged =
+            charged_round_fee(&round_tx, &vtxo_tree, &outputs, Amount::from_sat(10_000)).unwrap();
+        assert_eq!(charged, Amount::from_sat(1_000));
+
+        // Both of our outputs must be in the round, even if they pay the same address.
+        let outputs = [
+            RoundOutput::new_on_chain(address.clone(), Amount::from_sat(5_000)),
+            RoundOutput::new_on_chain(address, Amount::from_sat(5_000)),
+        ];
+        assert!(
+            charged_round_fee(&round_tx, &vtxo_tree, &outputs, Amount::from_sat(10_000)).is_err()
+        );
+    }
+}
@@ -1,42 +1,150 @@
 use crate::server::VtxoOutPoint;
+use crate::ArkAddress;
 use crate::Error;
+use bitcoin::key::TweakedPublicKey;
 use bitcoin::Amount;
+use bitcoin::Network;
+use bitcoin::Psbt;
+use bitcoin::ScriptBuf;
 use bitcoin::SignedAmount;
 use bitcoin::Txid;
+use bitcoin::XOnlyPublicKey;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ArkTransaction {
     /// A transaction that transforms a UTXO into a boarding output.
+    ///
+    /// Boarding transactions are always incoming i.e. we receive a boarding output.
     Boarding {
         txid: Txid,
-        /// We use [`Amount`] because boarding transactions are always incoming i.e. we receive a
-        /// boarding output.
-        amount: Amount,
+        amount: TransactionAmount,
         confirmed_at: Option<i64>,
     },
     /// A transaction that confirms VTXOs.
     Round {
         txid: Txid,
-        /// We use [`SignedAmount`] because round transactions may be incoming or outgoing i.e. we
-        /// can send or receive VTXOs.
-        amount: SignedAmount,
+        amount: TransactionAmount,
+        /// The script of the other party's VTXO, if known.
+        counterparty: Option<ScriptBuf>,
         created_at: i64,
     },
     /// A transaction that sends VTXOs.
     Redeem {
         txid: Txid,
-        /// We use [`SignedAmount`] because redeem transactions may be incoming or outgoing
-        /// i.e. we can send or receive VTXOs.
-        amount: SignedAmount,
+        amount: TransactionAmount,
+        /// The script of the other party's VTXO, if known.
+        counterparty: Option<ScriptBuf>,
         /// A redeem transaction is settled if our outputs in it have been spent.
         is_settled: bool,
         created_at: i64,
     },
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Direction {
+    Incoming,
+    Outgoing,
+}
+
+/// How an [`ArkTransaction`] affected our balance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TransactionAmount {
+    pub direction: Direction,
+    /// The value of the outputs we received if incoming, or of the inputs we spent if outgoing.
+    pub gross: Amount,
+    /// How much our balance changed by: `gross` minus whatever came back to us in the same
+    /// transaction.
+    pub net: Amount,
+    /// The part of `net` paid in fees, if known. The counterparty received `net - fee`.
+    pub fee: Option<Amount>,
+}
+
+impl TransactionAmount {
+    pub fn incoming(amount: Amount) -> Self {
+        Self {
+            direction: Direction::Incoming,
+            gross: amount,
+            net: amount,
+            fee: None,
+        }
+    }
+
+    pub fn outgoing(amount: Amount) -> Self {
+        Self {
+            direction: Direction::Outgoing,
+            gross: amount,
+            net: amount,
+            fee: None,
+        }
+    }
+
+    /// The net amount, negative if outgoing.
+    pub fn to_signed(&self) -> Result<SignedAmount, Error> {
+        let net = self.net.to_signed().map_err(Error::ad_hoc)?;
+
+        match self.direction {
+            Direction::Incoming => Ok(net),
+            Direction::Outgoing => Ok(-net),
+        }
+    }
+}
+
+/// Conversion from the signed amounts previously used by [`ArkTransaction`], where negative
+/// amounts were outgoing.
+impl From<SignedAmount> for TransactionAmount {
+    fn from(amount: SignedAmount) -> Self {
+        if amount.is_negative() {
+            Self::outgoing(amount.unsigned_abs())
+        } else {
+            Self::incoming(amount.unsigned_abs())
+        }
+    }
+}
+
 impl ArkTransaction {
+    pub fn txid(&self) -> Txid {
+        match self {
+            ArkTransaction::Boarding { txid, .. }
+            | ArkTransaction::Round { txid, .. }
+            | ArkTransaction::Redeem { txid, .. } => *txid,
+        }
+    }
+
+    pub fn amount(&self) -> TransactionAmount {
+        match self {
+            ArkTransaction::Boarding { amount, .. }
+            | ArkTransaction::Round { amount, .. }
+            | ArkTransaction::Redeem { amount, .. } => *amount,
+        }
+    }
+
+    /// The [`ArkAddress`] of the other party, if known.
+    pub fn counterparty_address(
+        &self,
+        network: Network,
+        server: XOnlyPublicKey,
+    ) -> Option<ArkAddress> {
+        let script = match self {
+            ArkTransaction::Boarding { .. } => None,
+            ArkTransaction::Round { counterparty, .. }
+            | ArkTransaction::Redeem { counterparty, .. } => counterparty.as_ref(),
+        }?;
+
+        if !script.is_p2tr() {
+            return None;
+        }
+
+        let vtxo_tap_key = XOnlyPublicKey::from_slice(&script.as_bytes()[2..]).ok()?;
+        let vtxo_tap_key = TweakedPublicKey::dangerous_assume_tweaked(vtxo_tap_key);
+
+        Some(ArkAddress::new(network, server, vtxo_tap_key))
+    }
+
     /// The creation time of the [`ArkTransaction`]. This value can be used for sorting.
     ///
     /// - The creation time of a boarding transaction is based on its confirmation time. If it is
@@ -104,18 +212,24 @@ pub fn generate_incoming_vtxo_transaction_history(
                 spent_amount
             };
 
-            let receive_amount = vtxo.amount.to_signed().map_err(Error::ad_hoc)?;
-            let spent_amount = spent_amount.to_signed().map_err(Error::ad_hoc)?;
-
-            let net_amount = receive_amount - spent_amount;
-
             // If net amount is zero, it's a VTXO being settled (OOR, weird) => IGNORED.
             //
             // If net amount is negative, it's a change VTXO => IGNORED.
-            if net_amount.is_positive() {
+            if vtxo.amount > spent_amount {
+                let counterparty = vtxo
+                    .redeem_tx
+                    .as_ref()
+                    .and_then(|redeem_tx| redeem_sender(redeem_tx, vtxo));
+
                 txs.push(ArkTransaction::Redeem {
                     txid: vtxo.outpoint.txid,
-                    amount: net_amount,
+                    amount: TransactionAmount {
+                        direction: Direction::Incoming,
+                        gross: vtxo.amount,
+                        net: vtxo.amount - spent_amount,
+                        fee: None,
+                    },
+                    counterparty,
                     is_settled: vtxo.spent_by.is_some(),
                     created_at: vtxo.created_at,
                 })
@@ -138,18 +252,19 @@ pub fn generate_incoming_vtxo_transaction_history(
                 spent_amount
             };
 
-            let receive_amount = vtxo.amount.to_signed().map_err(Error::ad_hoc)?;
-            let spent_amount = spent_amount.to_signed().map_err(Error::ad_hoc)?;
-
-            let net_amount = receive_amount - spent_amount;
-
             // If net amount received is zero, it's a VTXO being settled => IGNORED.
             //
             // If net amount received is negative, it's a change VTXO => IGNORED.
-            if net_amount.is_positive() {
+            if vtxo.amount > spent_amount {
                 txs.push(ArkTransaction::Round {
                     txid: vtxo.outpoint.txid,
-                    amount: receive_amount,
+                    amount: TransactionAmount {
+                        direction: Direction::Incoming,
+                        gross: vtxo.amount,
+                        net: vtxo.amount - spent_amount,
+                        fee: None,
+                    },
+                    counterparty: None,
                     created_at: vtxo.created_at,
                 })
             }
@@ -196,9 +311,7 @@ pub fn generate_outgoing_vtxo_transaction_history(
     for (spend_txid, spent_vtxos) in vtxos_by_spent_by.iter() {
         let spent_amount = spent_vtxos
             .iter()
-            .fold(Amount::ZERO, |acc, x| acc + x.amount)
-            .to_signed()
-            .map_err(Error::ad_hoc)?;
+            .fold(Amount::ZERO, |acc, x| acc + x.amount);
 
         // We figure out the transaction type based on the produced VTXOs.
         let tx_type = all_vtxos.iter().find_map(|vtxo| {
@@ -220,21 +333,23 @@ pub fn generate_outgoing_vtxo_transaction_history(
 
                 let produced_amount = produced_vtxos
                     .iter()
-                    .fold(Amount::ZERO, |acc, x| acc + x.amount)
-                    .to_signed()
-                    .map_err(Error::ad_hoc)?;
+                    .fold(Amount::ZERO, |acc, x| acc + x.amount);
 
                 // TODO: Sending own VTXO to own address is not handled correctly.
 
-                let net_amount = produced_amount - spent_amount;
-
                 // If net amount is zero, it's a VTXO being settled => IGNORED.
                 //
                 // If net amount is positive, it's a change VTXO => IGNORED.
-                if net_amount.is_negative() {
+                if spent_amount > produced_amount {
                     txs.push(ArkTransaction::Round {
                         txid: *spend_txid,
-                        amount: net_amount,
+                        amount: TransactionAmount {
+                            direction: Direction::Outgoing,
+                            gross: spent_amount,
+                            net: spent_amount - produced_amount,
+                            fee: None,
+                        },
+                        counterparty: None,
                         created_at: produced_vtxos[0].created_at,
                     })
                 }
@@ -247,21 +362,28 @@ pub fn generate_outgoing_vtxo_transaction_history(
 
                 let produced_amount = produced_vtxos
                     .iter()
-                    .fold(Amount::ZERO, |acc, x| acc + x.amount)
-                    .to_signed()
-                    .map_err(Error::ad_hoc)?;
+                    .fold(Amount::ZERO, |acc, x| acc + x.amount);
 
                 // TODO: Sending own VTXO to own address is not handled correctly.
 
-                let net_amount = produced_amount - spent_amount;
-
                 // If net amount is zero, it's a VTXO being settled (OOR, weird) => IGNORED.
                 //
                 // If net amount is positive, it's a change VTXO => IGNORED.
-                if net_amount.is_negative() {
+                if spent_amount > produced_amount {
+                    let redeem_tx = produced_vtxos
+                        .iter()
+                        .find_map(|vtxo| vtxo.redeem_tx.as_ref());
+
                     txs.push(ArkTransaction::Redeem {
                         txid: *spend_txid,
-                        amount: net_amount,
+                        amount: TransactionAmount {
+                            direction: Direction::Outgoing,
+                            gross: spent_amount,
+                            net: spent_amount - produced_amount,
+                            fee: redeem_tx.and_then(redeem_fee),
+                        },
+                        counterparty: redeem_tx
+                            .and_then(|redeem_tx| redeem_recipient(redeem_tx, &produced_vtxos)),
                         is_settled: true,
                         created_at: produced_vtxos[0].created_at,
                     })
@@ -274,7 +396,8 @@ pub fn generate_outgoing_vtxo_transaction_history(
                 if spent_vtxos[0].is_pending {
                     txs.push(ArkTransaction::Redeem {
                         txid: *spend_txid,
-                        amount: -spent_amount,
+                        amount: TransactionAmount::outgoing(spent_amount),
+                        counterparty: None,
                         is_settled: true,
                         created_at,
                     });
@@ -286,7 +409,8 @@ pub fn generate_outgoing_vtxo_transaction_history(
 
                     txs.push(ArkTransaction::Round {
                         txid: *spend_txid,
-                        amount: -spent_amount,
+                        amount: TransactionAmount::outgoing(spent_amount),
+                        counterparty: None,
                         created_at,
                     });
                 }
@@ -297,11 +421,96 @@ pub fn generate_outgoing_vtxo_transaction_history(
     Ok(txs)
 }
 
+/// The fee paid by `redeem_tx`, if the values of all its inputs are known.
+fn redeem_fee(redeem_tx: &Psbt) -> Option<Amount> {
+    let input_amount = redeem_tx
+        .inputs
+        .iter()
+        .map(|input| input.witness_utxo.as_ref().map(|prevout| prevout.value))
+        .sum::<Option<Amount>>()?;
+
+    let output_amount = redeem_tx
+        .unsigned_tx
+        .output
+        .iter()
+        .map(|output| output.value)
+        .sum::<Amount>();
+
+    input_amount.checked_sub(output_amount)
+}
+
+/// The script of the only output of `redeem_tx` which is neither one of `produced_vtxos` nor a
+/// zero-value output.
+fn redeem_recipient(redeem_tx: &Psbt, produced_vtxos: &[&VtxoOutPoint]) -> Option<ScriptBuf> {
+    let txid = redeem_tx.unsigned_tx.compute_txid();
+
+    let mut recipients = redeem_tx
+        .unsigned_tx
+        .output
+        .iter()
+        .enumerate()
+        .filter(|(vout, output)| {
+            output.value > Amount::ZERO
+                && !produced_vtxos
+                    .iter()
+                    .any(|v| v.outpoint.txid == txid && v.outpoint.vout == *vout as u32)
+        })
+        .map(|(_, output)| output.script_pubkey.clone());
+
+    match (recipients.next(), recipients.next()) {
+        (Some(recipient), None) => Some(recipient),
+        _ => None,
+    }
+}
+
+/// The script of the VTXOs spent by `redeem_tx` to pay us `vtxo`, if they all share one.
+fn redeem_sender(redeem_tx: &Psbt, vtxo: &VtxoOutPoint) -> Option<ScriptBuf> {
+    let our_script = &redeem_tx
+        .unsigned_tx
+        .output
+        .get(vtxo.outpoint.vout as usize)?
+        .script_pubkey;
+
+    let mut senders = redeem_tx
+        .inputs
+        .iter()
+        .filter_map(|input| input.witness_utxo.as_ref())
+        .map(|prevout| &prevout.script_pubkey)
+        .filter(|script| *script != our_script);
+
+    let sender = senders.next()?;
+    if senders.any(|script| script != sender) {
+        return None;
+    }
+
+    Some(sender.clone())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use bitcoin::OutPoint;
 
+    fn p2tr(output_key: &str) -> ScriptBuf {
+        let output_key = output_key.parse().unwrap();
+
+        ScriptBuf::new_p2tr_tweaked(TweakedPublicKey::dangerous_assume_tweaked(output_key))
+    }
+
+    #[test]
+    fn signed_amount_conversion() {
+        for sats in [-1_216, 0, 2_000] {
+            let amount = SignedAmount::from_sat(sats);
+
+            assert_eq!(TransactionAmount::from(amount).to_signed().unwrap(), amount);
+        }
+
+        assert_eq!(
+            TransactionAmount::from(SignedAmount::from_sat(-1_216)).direction,
+            Direction::Outgoing
+        );
+    }
+
     // These tests are taken straight from the Go client.
 
     #[test]
@@ -325,6 +534,7 @@ mod tests {
                 .unwrap(),
             spent_by: None,
             expire_at: 1730934927,
+            sweepable_after: None,
             swept: false,
             is_pending: false,
             redeem_tx: None,
@@ -370,6 +580,7 @@ mod tests {
                     .unwrap(),
             ),
             expire_at: 1730934927,
+            sweepable_after: None,
             swept: false,
             is_pending: true,
             redeem_tx: Some("cHNidP8BAIkCAAAAAX5SNz6Ces7ONgkhFk5+zIziPh99YTqjOReeOIKmrkYmAAAAAAD/////AugDAAAAAAAAIlEgqlhusFLr8eZ29t9OrzWRyQ1WqXmHGu+ptn5WGMt8DyVgSQAAAAAAACJRIPw+1IIkAbx1hYxqfgipdMaKd3vPh+a6U11Ir6t9AM9fAAAAAAABASsgTgAAAAAAACJRIPw+1IIkAbx1hYxqfgipdMaKd3vPh+a6U11Ir6t9AM9fQRRzba+JiJXvEqldJ7Squ3RSy3Tygj+kNcbZ9sdwtY13kLn0kC6mChU9UVqsaZC/ptUXmqKnA1BqwBIdRoXN3UIQQPXRlndrrsGBbJZ0P1+K+UWTytCU+O1AZbfg/APqOYkI+e7ne0BRtQVTKu2V8mEMydDRmpy5UCGcr9ZhfRRr6d1BFL+NVPnBYGBLoEcv5/9swwUPUfu0KQNTf5WWzvL0XQGlufSQLqYKFT1RWqxpkL+m1ReaoqcDUGrAEh1Ghc3dQhBANfxQ5evXPI16w88zj7VkcarvA6MUPKjTKmOTzAnYGHUb2GkQa6Ixdg+s/z+Nt5jMyL+KUsiLsuoFeat5dn6r3UIVwVCSm3TBoElUt4tLYDXpel4HiloPKOyW1Ue/7prOgDrASdfU5FE/s1XZ2TstJV7kIKp/CP0Z2eTjaXuvl4qCX/NFIHNtr4mIle8SqV0ntKq7dFLLdPKCP6Q1xtn2x3C1jXeQrSC/jVT5wWBgS6BHL+f/bMMFD1H7tCkDU3+Vls7y9F0BpazAAAAA".parse().unwrap()),
@@ -397,6 +608,7 @@ mod tests {
                     .unwrap(),
             ),
             expire_at: 1730934927,
+            sweepable_after: None,
             swept: false,
             is_pending: false,
             redeem_tx: None,
@@ -425,7 +637,15 @@ mod tests {
                 txid: "33fd8ca9ea9cfb53802c42be10ae428573e19fb89484dfe536d06d43efa82034"
                     .parse()
                     .unwrap(),
-                amount: SignedAmount::from_sat(-1_216),
+                amount: TransactionAmount {
+                    direction: Direction::Outgoing,
+                    gross: Amount::from_sat(20_000),
+                    net: Amount::from_sat(1_216),
+                    fee: Some(Amount::from_sat(216)),
+                },
+                counterparty: Some(p2tr(
+                    "aa586eb052ebf1e676f6df4eaf3591c90d56a979871aefa9b67e5618cb7c0f25"
+                )),
                 is_settled: true,
                 created_at: 1730330256,
             }]
@@ -447,6 +667,7 @@ mod tests {
                 .unwrap(),
             spent_by: None,
             expire_at: 1730934927,
+            sweepable_after: None,
             swept: false,
             is_pending: true,
             redeem_tx: Some("cHNidP8BAIkCAAAAAX5SNz6Ces7ONgkhFk5+zIziPh99YTqjOReeOIKmrkYmAAAAAAD/////AugDAAAAAAAAIlEgqlhusFLr8eZ29t9OrzWRyQ1WqXmHGu+ptn5WGMt8DyVgSQAAAAAAACJRIPw+1IIkAbx1hYxqfgipdMaKd3vPh+a6U11Ir6t9AM9fAAAAAAABASsgTgAAAAAAACJRIPw+1IIkAbx1hYxqfgipdMaKd3vPh+a6U11Ir6t9AM9fQRRzba+JiJXvEqldJ7Squ3RSy3Tygj+kNcbZ9sdwtY13kLn0kC6mChU9UVqsaZC/ptUXmqKnA1BqwBIdRoXN3UIQQPXRlndrrsGBbJZ0P1+K+UWTytCU+O1AZbfg/APqOYkI+e7ne0BRtQVTKu2V8mEMydDRmpy5UCGcr9ZhfRRr6d1BFL+NVPnBYGBLoEcv5/9swwUPUfu0KQNTf5WWzvL0XQGlufSQLqYKFT1RWqxpkL+m1ReaoqcDUGrAEh1Ghc3dQhBANfxQ5evXPI16w88zj7VkcarvA6MUPKjTKmOTzAnYGHUb2GkQa6Ixdg+s/z+Nt5jMyL+KUsiLsuoFeat5dn6r3UIVwVCSm3TBoElUt4tLYDXpel4HiloPKOyW1Ue/7prOgDrASdfU5FE/s1XZ2TstJV7kIKp/CP0Z2eTjaXuvl4qCX/NFIHNtr4mIle8SqV0ntKq7dFLLdPKCP6Q1xtn2x3C1jXeQrSC/jVT5wWBgS6BHL+f/bMMFD1H7tCkDU3+Vls7y9F0BpazAAAAA".parse().unwrap()),
@@ -468,6 +689,7 @@ mod tests {
                 .unwrap(),
             spent_by: None,
             expire_at: 1730935548,
+            sweepable_after: None,
             swept: false,
             is_pending: true,
             redeem_tx: Some("cHNidP8BAIkCAAAAAT7y41Cb5k0SMpEYaB/3NLlJ8leksHt08k6sK2gRlx3/AAAAAAD/////AtAHAAAAAAAAIlEgqlhusFLr8eZ29t9OrzWRyQ1WqXmHGu+ptn5WGMt8DyVoHgAAAAAAACJRIHnxG3UzOEn7H8oZrxQCVXCjoUVhUEBBgzDLu8ARqongAAAAAAABASsQJwAAAAAAACJRIHnxG3UzOEn7H8oZrxQCVXCjoUVhUEBBgzDLu8ARqongQRRzba+JiJXvEqldJ7Squ3RSy3Tygj+kNcbZ9sdwtY13kH1QsQK/Pk7/PqAmUThCuTCfbTo69ePAgzsvSuR97VgUQBSvpq/lJ7+uc8nyWwV5sCRukn5TnOybRHCjCOUPviykP6C+ue768mRDK6PxQ5FpNJhHmNLpfdTbIQwGCNIJr7pBFOchVKNhwXJqAhCx+u7ObLBb4YqW5vA1iW45rGgxtmP7fVCxAr8+Tv8+oCZROEK5MJ9tOjr148CDOy9K5H3tWBRALxMyiBhy6eGAHjj0OJ+LRFYI8PCIplSLl+SqfMLoSHZzsXkDIyDcLdV6w4Vvq4oBQN+lfKAX2IKZGB0WUGavn0IVwVCSm3TBoElUt4tLYDXpel4HiloPKOyW1Ue/7prOgDrAL6zdBmWt8+odVYaSKdWl60i5qQGel8jvirsvt2ageslFIHNtr4mIle8SqV0ntKq7dFLLdPKCP6Q1xtn2x3C1jXeQrSDnIVSjYcFyagIQsfruzmywW+GKlubwNYluOaxoMbZj+6zAAAAA".parse().unwrap()),
@@ -496,7 +718,15 @@ mod tests {
                     txid: "884d85c0db6b52139c39337d54c1f20cd8c5c0d2e83109d69246a345ccc9d169"
                         .parse()
                         .unwrap(),
-                    amount: SignedAmount::from_sat(2_000),
+                    amount: TransactionAmount {
+                        direction: Direction::Incoming,
+                        gross: Amount::from_sat(2_000),
+                        net: Amount::from_sat(2_000),
+                        fee: None,
+                    },
+                    counterparty: Some(p2tr(
+                        "79f11b75333849fb1fca19af14025570a3a145615040418330cbbbc011aa89e0"
+                    )),
                     is_settled: false,
                     created_at: 1730330748,
                 },
@@ -504,7 +734,15 @@ mod tests {
                     txid: "33fd8ca9ea9cfb53802c42be10ae428573e19fb89484dfe536d06d43efa82034"
                         .parse()
                         .unwrap(),
-                    amount: SignedAmount::from_sat(1_000),
+                    amount: TransactionAmount {
+                        direction: Direction::Incoming,
+                        gross: Amount::from_sat(1_000),
+                        net: Amount::from_sat(1_000),
+                        fee: None,
+                    },
+                    counterparty: Some(p2tr(
+                        "fc3ed4822401bc75858c6a7e08a974c68a777bcf87e6ba535d48afab7d00cf5f"
+                    )),
                     is_settled: false,
                     created_at: 1730330256,
                 }
@@ -529,6 +767,7 @@ mod tests {
                 .unwrap(),
             spent_by: None,
             expire_at: 1730935835,
+            sweepable_after: None,
             swept: false,
             is_pending: false,
             redeem_tx: None,
@@ -552,6 +791,7 @@ mod tests {
                 .unwrap(),
             spent_by: Some("7fd65ce87e0f9a7af583593d5b0124aabd65c97e05159525d0a98201d6ae95a4".parse().unwrap()),
             expire_at: 1730934927,
+            sweepable_after: None,
             swept: false,
             is_pending: true,
             redeem_tx: Some("cHNidP8BAIkCAAAAAX5SNz6Ces7ONgkhFk5+zIziPh99YTqjOReeOIKmrkYmAAAAAAD/////AugDAAAAAAAAIlEgqlhusFLr8eZ29t9OrzWRyQ1WqXmHGu+ptn5WGMt8DyVgSQAAAAAAACJRIPw+1IIkAbx1hYxqfgipdMaKd3vPh+a6U11Ir6t9AM9fAAAAAAABASsgTgAAAAAAACJRIPw+1IIkAbx1hYxqfgipdMaKd3vPh+a6U11Ir6t9AM9fQRRzba+JiJXvEqldJ7Squ3RSy3Tygj+kNcbZ9sdwtY13kLn0kC6mChU9UVqsaZC/ptUXmqKnA1BqwBIdRoXN3UIQQPXRlndrrsGBbJZ0P1+K+UWTytCU+O1AZbfg/APqOYkI+e7ne0BRtQVTKu2V8mEMydDRmpy5UCGcr9ZhfRRr6d1BFL+NVPnBYGBLoEcv5/9swwUPUfu0KQNTf5WWzvL0XQGlufSQLqYKFT1RWqxpkL+m1ReaoqcDUGrAEh1Ghc3dQhBANfxQ5evXPI16w88zj7VkcarvA6MUPKjTKmOTzAnYGHUb2GkQa6Ixdg+s/z+Nt5jMyL+KUsiLsuoFeat5dn6r3UIVwVCSm3TBoElUt4tLYDXpel4HiloPKOyW1Ue/7prOgDrASdfU5FE/s1XZ2TstJV7kIKp/CP0Z2eTjaXuvl4qCX/NFIHNtr4mIle8SqV0ntKq7dFLLdPKCP6Q1xtn2x3C1jXeQrSC/jVT5wWBgS6BHL+f/bMMFD1H7tCkDU3+Vls7y9F0BpazAAAAA".parse().unwrap()),
@@ -573,6 +813,7 @@ mod tests {
                 .unwrap(),
             spent_by: Some("7fd65ce87e0f9a7af583593d5b0124aabd65c97e05159525d0a98201d6ae95a4".parse().unwrap()),
             expire_at: 1730935548,
+            sweepable_after: None,
             swept: false,
             is_pending: true,
             redeem_tx: Some("cHNidP8BAIkCAAAAAT7y41Cb5k0SMpEYaB/3NLlJ8leksHt08k6sK2gRlx3/AAAAAAD/////AtAHAAAAAAAAIlEgqlhusFLr8eZ29t9OrzWRyQ1WqXmHGu+ptn5WGMt8DyVoHgAAAAAAACJRIHnxG3UzOEn7H8oZrxQCVXCjoUVhUEBBgzDLu8ARqongAAAAAAABASsQJwAAAAAAACJRIHnxG3UzOEn7H8oZrxQCVXCjoUVhUEBBgzDLu8ARqongQRRzba+JiJXvEqldJ7Squ3RSy3Tygj+kNcbZ9sdwtY13kH1QsQK/Pk7/PqAmUThCuTCfbTo69ePAgzsvSuR97VgUQBSvpq/lJ7+uc8nyWwV5sCRukn5TnOybRHCjCOUPviykP6C+ue768mRDK6PxQ5FpNJhHmNLpfdTbIQwGCNIJr7pBFOchVKNhwXJqAhCx+u7ObLBb4YqW5vA1iW45rGgxtmP7fVCxAr8+Tv8+oCZROEK5MJ9tOjr148CDOy9K5H3tWBRALxMyiBhy6eGAHjj0OJ+LRFYI8PCIplSLl+SqfMLoSHZzsXkDIyDcLdV6w4Vvq4oBQN+lfKAX2IKZGB0WUGavn0IVwVCSm3TBoElUt4tLYDXpel4HiloPKOyW1Ue/7prOgDrAL6zdBmWt8+odVYaSKdWl60i5qQGel8jvirsvt2ageslFIHNtr4mIle8SqV0ntKq7dFLLdPKCP6Q1xtn2x3C1jXeQrSDnIVSjYcFyagIQsfruzmywW+GKlubwNYluOaxoMbZj+6zAAAAA".parse().unwrap()),
@@ -599,7 +840,15 @@ mod tests {
                     txid: "884d85c0db6b52139c39337d54c1f20cd8c5c0d2e83109d69246a345ccc9d169"
                         .parse()
                         .unwrap(),
-                    amount: SignedAmount::from_sat(2_000),
+                    amount: TransactionAmount {
+                        direction: Direction::Incoming,
+                        gross: Amount::from_sat(2_000),
+                        net: Amount::from_sat(2_000),
+                        fee: None,
+                    },
+                    counterparty: Some(p2tr(
+                        "79f11b75333849fb1fca19af14025570a3a145615040418330cbbbc011aa89e0"
+                    )),
                     is_settled: true,
                     created_at: 1730330748,
                 },
@@ -607,7 +856,15 @@ mod tests {
                     txid: "33fd8ca9ea9cfb53802c42be10ae428573e19fb89484dfe536d06d43efa82034"
                         .parse()
                         .unwrap(),
-                    amount: SignedAmount::from_sat(1_000),
+                    amount: TransactionAmount {
+                        direction: Direction::Incoming,
+                        gross: Amount::from_sat(1_000),
+                        net: Amount::from_sat(1_000),
+                        fee: None,
+                    },
+                    counterparty: Some(p2tr(
+                        "fc3ed4822401bc75858c6a7e08a974c68a777bcf87e6ba535d48afab7d00cf5f"
+                    )),
                     is_settled: true,
                     created_at: 1730330256,
                 }
@@ -632,6 +889,7 @@ mod tests {
                 .unwrap(),
             spent_by: None,
             expire_at: 1730935835,
+            sweepable_after: None,
             swept: false,
             is_pending: true,
             redeem_tx: Some("cHNidP8BAIkCAAAAAfk6SF8evBPDF1/8N1o3wLraIU7V7QVwAP0ZxMByU8nZAAAAAAD/////AjQIAAAAAAAAIlEgefEbdTM4SfsfyhmvFAJVcKOhRWFQQEGDMMu7wBGqieCsAgAAAAAAACJRIKpYbrBS6/HmdvbfTq81kckNVql5hxrvqbZ+VhjLfA8lAAAAAAABASu4CwAAAAAAACJRIKpYbrBS6/HmdvbfTq81kckNVql5hxrvqbZ+VhjLfA8lQRRzba+JiJXvEqldJ7Squ3RSy3Tygj+kNcbZ9sdwtY13kMkuxL2rXufbKxVtT1EaM9Vz7X2fpReM0c3VdBGWLTt6QOQzMhmzDjjLlb76u3ZS/xfu4DdmpxClsIAtAjvKhMycpjoPpLqdFMZkfRR3hM6rSUHpED+NY2UdqCyyh4EhZKVBFIdwPOfQj0BBcg2i+i3lRh1pA4SOHkW+q0rabgNGEdfiyS7Evate59srFW1PURoz1XPtfZ+lF4zRzdV0EZYtO3pAVlbcq0Z0Fh/BHSNd6IDksw8RC0fitTYPdnaWOmAlUHmH9d343v25QSc6q/2HdE8VoQi3+sQ6cS3Xm+EWBClZAUIVwFCSm3TBoElUt4tLYDXpel4HiloPKOyW1Ue/7prOgDrAUNUCehfUMhBKXlquGl4TQ7nsvjlxxe9WfEPi4eN3DDtFIHNtr4mIle8SqV0ntKq7dFLLdPKCP6Q1xtn2x3C1jXeQrSCHcDzn0I9AQXINovot5UYdaQOEjh5FvqtK2m4DRhHX4qzAAAAA".parse().unwrap()),
@@ -656,6 +914,7 @@ mod tests {
                     .unwrap(),
                 spent_by: Some("7fd65ce87e0f9a7af583593d5b0124aabd65c97e05159525d0a98201d6ae95a4".parse().unwrap()),
                 expire_at: 1730934927,
+                sweepable_after: None,
                 swept: false,
                 is_pending: true,
                 redeem_tx: Some("cHNidP8BAIkCAAAAAX5SNz6Ces7ONgkhFk5+zIziPh99YTqjOReeOIKmrkYmAAAAAAD/////AugDAAAAAAAAIlEgqlhusFLr8eZ29t9OrzWRyQ1WqXmHGu+ptn5WGMt8DyVgSQAAAAAAACJRIPw+1IIkAbx1hYxqfgipdMaKd3vPh+a6U11Ir6t9AM9fAAAAAAABASsgTgAAAAAAACJRIPw+1IIkAbx1hYxqfgipdMaKd3vPh+a6U11Ir6t9AM9fQRRzba+JiJXvEqldJ7Squ3RSy3Tygj+kNcbZ9sdwtY13kLn0kC6mChU9UVqsaZC/ptUXmqKnA1BqwBIdRoXN3UIQQPXRlndrrsGBbJZ0P1+K+UWTytCU+O1AZbfg/APqOYkI+e7ne0BRtQVTKu2V8mEMydDRmpy5UCGcr9ZhfRRr6d1BFL+NVPnBYGBLoEcv5/9swwUPUfu0KQNTf5WWzvL0XQGlufSQLqYKFT1RWqxpkL+m1ReaoqcDUGrAEh1Ghc3dQhBANfxQ5evXPI16w88zj7VkcarvA6MUPKjTKmOTzAnYGHUb2GkQa6Ixdg+s/z+Nt5jMyL+KUsiLsuoFeat5dn6r3UIVwVCSm3TBoElUt4tLYDXpel4HiloPKOyW1Ue/7prOgDrASdfU5FE/s1XZ2TstJV7kIKp/CP0Z2eTjaXuvl4qCX/NFIHNtr4mIle8SqV0ntKq7dFLLdPKCP6Q1xtn2x3C1jXeQrSC/jVT5wWBgS6BHL+f/bMMFD1H7tCkDU3+Vls7y9F0BpazAAAAA".parse().unwrap()),
@@ -678,6 +937,7 @@ mod tests {
                     .unwrap(),
                 spent_by: Some("7fd65ce87e0f9a7af583593d5b0124aabd65c97e05159525d0a98201d6ae95a4".parse().unwrap()),
                 expire_at: 1730935548,
+                sweepable_after: None,
                 swept: false,
                 is_pending: true,
                 redeem_tx: Some("cHNidP8BAIkCAAAAAT7y41Cb5k0SMpEYaB/3NLlJ8leksHt08k6sK2gRlx3/AAAAAAD/////AtAHAAAAAAAAIlEgqlhusFLr8eZ29t9OrzWRyQ1WqXmHGu+ptn5WGMt8DyVoHgAAAAAAACJRIHnxG3UzOEn7H8oZrxQCVXCjoUVhUEBBgzDLu8ARqongAAAAAAABASsQJwAAAAAAACJRIHnxG3UzOEn7H8oZrxQCVXCjoUVhUEBBgzDLu8ARqongQRRzba+JiJXvEqldJ7Squ3RSy3Tygj+kNcbZ9sdwtY13kH1QsQK/Pk7/PqAmUThCuTCfbTo69ePAgzsvSuR97VgUQBSvpq/lJ7+uc8nyWwV5sCRukn5TnOybRHCjCOUPviykP6C+ue768mRDK6PxQ5FpNJhHmNLpfdTbIQwGCNIJr7pBFOchVKNhwXJqAhCx+u7ObLBb4YqW5vA1iW45rGgxtmP7fVCxAr8+Tv8+oCZROEK5MJ9tOjr148CDOy9K5H3tWBRALxMyiBhy6eGAHjj0OJ+LRFYI8PCIplSLl+SqfMLoSHZzsXkDIyDcLdV6w4Vvq4oBQN+lfKAX2IKZGB0WUGavn0IVwVCSm3TBoElUt4tLYDXpel4HiloPKOyW1Ue/7prOgDrAL6zdBmWt8+odVYaSKdWl60i5qQGel8jvirsvt2ageslFIHNtr4mIle8SqV0ntKq7dFLLdPKCP6Q1xtn2x3C1jXeQrSDnIVSjYcFyagIQsfruzmywW+GKlubwNYluOaxoMbZj+6zAAAAA".parse().unwrap()),
@@ -700,6 +960,7 @@ mod tests {
                     .unwrap(),
                 spent_by: Some("c59004f8c468a922216f513ec7d63d9b6a13571af0bacd51910709351d27fe55".parse().unwrap()),
                 expire_at: 1730935835,
+                sweepable_after: None,
                 swept: false,
                 is_pending: false,
                 redeem_tx: None,
@@ -728,7 +989,15 @@ mod tests {
                     txid: "c59004f8c468a922216f513ec7d63d9b6a13571af0bacd51910709351d27fe55"
                         .parse()
                         .unwrap(),
-                    amount: SignedAmount::from_sat(-2_316),
+                    amount: TransactionAmount {
+                        direction: Direction::Outgoing,
+                        gross: Amount::from_sat(3_000),
+                        net: Amount::from_sat(2_316),
+                        fee: Some(Amount::from_sat(216)),
+                    },
+                    counterparty: Some(p2tr(
+                        "79f11b75333849fb1fca19af14025570a3a145615040418330cbbbc011aa89e0"
+                    )),
                     is_settled: true,
                     created_at: 1730331198,
                 },
@@ -736,7 +1005,15 @@ mod tests {
                     txid: "884d85c0db6b52139c39337d54c1f20cd8c5c0d2e83109d69246a345ccc9d169"
                         .parse()
                         .unwrap(),
-                    amount: SignedAmount::from_sat(2_000),
+                    amount: TransactionAmount {
+                        direction: Direction::Incoming,
+                        gross: Amount::from_sat(2_000),
+                        net: Amount::from_sat(2_000),
+                        fee: None,
+                    },
+                    counterparty: Some(p2tr(
+                        "79f11b75333849fb1fca19af14025570a3a145615040418330cbbbc011aa89e0"
+                    )),
                     is_settled: true,
                     created_at: 1730330748,
                 },
@@ -744,7 +1021,15 @@ mod tests {
                     txid: "33fd8ca9ea9cfb53802c42be10ae428573e19fb89484dfe536d06d43efa82034"
                         .parse()
                         .unwrap(),
-                    amount: SignedAmount::from_sat(1_000),
+                    amount: TransactionAmount {
+                        direction: Direction::Incoming,
+                        gross: Amount::from_sat(1_000),
+                        net: Amount::from_sat(1_000),
+                        fee: None,
+                    },
+                    counterparty: Some(p2tr(
+                        "fc3ed4822401bc75858c6a7e08a974c68a777bcf87e6ba535d48afab7d00cf5f"
+                    )),
                     is_settled: true,
                     created_at: 1730330256,
                 }
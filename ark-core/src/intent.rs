@@ -0,0 +1,398 @@
+//! Registration intents, which prove that we own the inputs we register for a round.
+//!
+//! The proof follows the "proof of funds" construction of BIP322. A virtual `to_spend` transaction
+//! commits to the intent message, and a `to_sign` transaction spends it together with every
+//! registered input. The `to_sign` transaction can never be published, because `to_spend` is not a
+//! valid transaction.
+//!
+//! Every input is signed by the owner using the forfeit leaf of the boarding output or VTXO. The
+//! Ark server does not need to sign for the proof to be verified, since it only checks the owner's
+//! signatures.
+//!
+//! The `ark.v1` API of the Ark server has no way to register an intent, so `ark-client` does not
+//! use this module yet: it registers round inputs without a proof of funds.
+
+use crate::round::OnChainInput;
+use crate::round::VtxoInput;
+use crate::signing::sign_with_keypairs;
+use crate::signing::InputToSign;
+use crate::Error;
+use crate::ErrorContext;
+use bitcoin::absolute::LockTime;
+use bitcoin::hashes::sha256;
+use bitcoin::hashes::Hash;
+use bitcoin::hashes::HashEngine;
+use bitcoin::hex::DisplayHex;
+use bitcoin::key::Keypair;
+use bitcoin::opcodes::all::OP_RETURN;
+use bitcoin::script;
+use bitcoin::secp256k1;
+use bitcoin::secp256k1::schnorr;
+use bitcoin::transaction;
+use bitcoin::Amount;
+use bitcoin::OutPoint;
+use bitcoin::Psbt;
+use bitcoin::ScriptBuf;
+use bitcoin::Sequence;
+use bitcoin::TapLeafHash;
+use bitcoin::Transaction;
+use bitcoin::TxIn;
+use bitcoin::TxOut;
+use bitcoin::Txid;
+use bitcoin::Witness;
+use bitcoin::XOnlyPublicKey;
+
+/// The tag of the BIP322 message hash.
+const BIP322_TAG: &[u8] = b"BIP0322-signed-message";
+
+/// A signed intent to register inputs for the next round.
+#[derive(Debug, Clone)]
+pub struct Intent {
+    /// The BIP322 `to_sign` transaction, with a signature for every input.
+    proof: Psbt,
+    /// The message committed to by the `to_spend` transaction.
+    message: String,
+}
+
+impl Intent {
+    pub fn proof(&self) -> &Psbt {
+        &self.proof
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// Build and sign an [`Intent`] to register `onchain_inputs` and `vtxo_inputs` for a round.
+///
+/// The intent is only valid between the UNIX timestamps `valid_at` and `expire_at`, so that it
+/// cannot be replayed for a later round.
+///
+/// Every input is signed with the keypair in `kps` which owns it.
+pub fn create_and_sign_intent(
+    kps: &[Keypair],
+    onchain_inputs: &[OnChainInput],
+    vtxo_inputs: &[VtxoInput],
+    valid_at: u64,
+    expire_at: u64,
+) -> Result<Intent, Error> {
+    create_and_sign_intent_with(
+        sign_with_keypairs(kps),
+        onchain_inputs,
+        vtxo_inputs,
+        valid_at,
+        expire_at,
+    )
+}
+
+/// The messages to be signed by the owners of the inputs of the [`Intent`] built by
+/// [`create_and_sign_intent`], together with the public key that must sign each of them.
+///
+/// Signatures for these messages can be produced ahead of calling
+/// [`create_and_sign_intent_with`], e.g. when signing is asynchronous.
+pub fn intent_sighashes(
+    onchain_inputs: &[OnChainInput],
+    vtxo_inputs: &[VtxoInput],
+    valid_at: u64,
+    expire_at: u64,
+) -> Result<Vec<(XOnlyPublicKey, secp256k1::Message)>, Error> {
+    let (_, _, inputs_to_sign) = build_intent(onchain_inputs, vtxo_inputs, valid_at, expire_at)?;
+
+    Ok(inputs_to_sign.iter().map(InputToSign::sighash).collect())
+}
+
+/// Like [`create_and_sign_intent`], but every input is signed with `sign_for_pk_fn` instead of a
+/// keypair.
+pub fn create_and_sign_intent_with<F>(
+    sign_for_pk_fn: F,
+    onchain_inputs: &[OnChainInput],
+    vtxo_inputs: &[VtxoInput],
+    valid_at: u64,
+    expire_at: u64,
+) -> Result<Intent, Error>
+where
+    F: Fn(&XOnlyPublicKey, &secp256k1::Message) -> Result<schnorr::Signature, Error>,
+{
+    let (mut proof, message, inputs_to_sign) =
+        build_intent(onchain_inputs, vtxo_inputs, valid_at, expire_at)?;
+
+    for input in inputs_to_sign {
+        let sig = input
+            .sign(&sign_for_pk_fn)
+            .context("failed to sign intent")?;
+
+        proof.inputs[input.index]
+            .tap_script_sigs
+            .insert((input.pk, input.leaf_hash), sig);
+    }
+
+    Ok(Intent { proof, message })
+}
+
+/// The unsigned intent proof and its message, with every input ready to be signed.
+fn build_intent(
+    onchain_inputs: &[OnChainInput],
+    vtxo_inputs: &[VtxoInput],
+    valid_at: u64,
+    expire_at: u64,
+) -> Result<(Psbt, String, Vec<InputToSign>), Error> {
+    // The owner, outpoint, previous output and forfeit spend info of every input.
+    let inputs = onchain_inputs
+        .iter()
+        .map(|o| {
+            let boarding_output = o.boarding_output();
+
+            (
+                boarding_output.owner_pk(),
+                o.outpoint(),
+                TxOut {
+                    value: o.amount(),
+                    script_pubkey: boarding_output.script_pubkey(),
+                },
+                boarding_output.tapscripts(),
+                boarding_output.forfeit_spend_info(),
+            )
+        })
+        .chain(vtxo_inputs.iter().map(|v| {
+            let vtxo = v.vtxo();
+
+            (
+                vtxo.owner_pk(),
+                v.outpoint(),
+                TxOut {
+                    value: v.amount(),
+                    script_pubkey: vtxo.script_pubkey(),
+                },
+                vtxo.tapscripts(),
+                vtxo.forfeit_spend_info(),
+            )
+        }))
+        .collect::<Vec<_>>();
+
+    let (first_owner, _, first_prevout, _, first_spend_info) = inputs
+        .first()
+        .cloned()
+        .ok_or_else(|| Error::ad_hoc("cannot create intent without inputs"))?;
+
+    let input_tap_trees = inputs
+        .iter()
+        .map(|(_, _, _, tapscripts, _)| {
+            let scripts = tapscripts
+                .iter()
+                .map(|s| format!("\"{}\"", s.as_bytes().to_lower_hex_string()))
+                .collect::<Vec<_>>();
+
+            format!("[{}]", scripts.join(","))
+        })
+        .collect::<Vec<_>>();
+
+    let message = format!(
+        "{{\"type\":\"register\",\"input_tap_trees\":[{}],\"valid_at\":{valid_at},\"expire_at\":{expire_at}}}",
+        input_tap_trees.join(",")
+    );
+
+    let to_spend = to_spend_transaction(message.as_bytes(), first_prevout.script_pubkey.clone());
+    let to_spend_prevout = to_spend.output[0].clone();
+
+    let to_sign = Transaction {
+        version: transaction::Version(0),
+        lock_time: LockTime::ZERO,
+        input: std::iter::once(OutPoint::new(to_spend.compute_txid(), 0))
+            .chain(inputs.iter().map(|(_, outpoint, _, _, _)| *outpoint))
+            .map(|previous_output| TxIn {
+                previous_output,
+                sequence: Sequence::ZERO,
+                ..Default::default()
+            })
+            .collect(),
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: script::Builder::new().push_opcode(OP_RETURN).into_script(),
+        }],
+    };
+
+    let mut proof = Psbt::from_unsigned_tx(to_sign).map_err(Error::transaction)?;
+
+    // The `to_spend` output is locked by the same script as the first input, so it is signed using
+    // the same leaf.
+    let spend_infos = std::iter::once((first_owner, to_spend_prevout, first_spend_info))
+        .chain(
+            inputs
+                .into_iter()
+                .map(|(owner, _, prevout, _, spend_info)| (owner, prevout, spend_info)),
+        )
+        .collect::<Vec<_>>();
+
+    let prevouts = spend_infos
+        .iter()
+        .map(|(_, prevout, _)| prevout.clone())
+        .collect::<Vec<_>>();
+
+    let mut inputs_to_sign = Vec::with_capacity(spend_infos.len());
+    for (i, (owner, prevout, (script, control_block))) in spend_infos.into_iter().enumerate() {
+        let leaf_version = control_block.leaf_version;
+        let leaf_hash = TapLeafHash::from_script(&script, leaf_version);
+
+        inputs_to_sign.push(InputToSign::new(
+            &proof.unsigned_tx,
+            i,
+            &prevouts,
+            owner,
+            leaf_hash,
+        )?);
+
+        let input = &mut proof.inputs[i];
+        input.witness_utxo = Some(prevout);
+        input
+            .tap_scripts
+            .insert(control_block, (script, leaf_version));
+    }
+
+    Ok((proof, message, inputs_to_sign))
+}
+
+/// The BIP322 hash of `message`.
+fn message_hash(message: &[u8]) -> sha256::Hash {
+    let tag = sha256::Hash::hash(BIP322_TAG);
+
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag.as_ref());
+    engine.input(tag.as_ref());
+    engine.input(message);
+
+    sha256::Hash::from_engine(engine)
+}
+
+/// The BIP322 `to_spend` transaction, committing to `message` and paying to `script_pubkey`.
+fn to_spend_transaction(message: &[u8], script_pubkey: ScriptBuf) -> Transaction {
+    let script_sig = script::Builder::new()
+        .push_int(0)
+        .push_slice(message_hash(message).to_byte_array())
+        .into_script();
+
+    Transaction {
+        version: transaction::Version(0),
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(Txid::all_zeros(), 0xFFFFFFFF),
+            script_sig,
+            sequence: Sequence::ZERO,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey,
+        }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoardingOutput;
+    use crate::DefaultVtxo;
+    use bitcoin::key::Secp256k1;
+    use bitcoin::secp256k1::SecretKey;
+    use bitcoin::sighash::Prevouts;
+    use bitcoin::sighash::SighashCache;
+    use bitcoin::Network;
+    use bitcoin::TapSighashType;
+    use std::str::FromStr;
+
+    const SERVER: &str = "33ffb3dee353b1a9ebe4ced64b946238d0a4ac364f275d771da6ad2445d07ae0";
+
+    // Test vectors from BIP322.
+    #[test]
+    fn bip322_message_hash() {
+        assert_eq!(
+            message_hash(b"").to_string(),
+            "c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1"
+        );
+        assert_eq!(
+            message_hash(b"Hello World").to_string(),
+            "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a"
+        );
+    }
+
+    #[test]
+    fn intent_signs_every_input() {
+        let secp = Secp256k1::new();
+        let kp = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[1; 32]).unwrap());
+        let (owner, _) = kp.x_only_public_key();
+        // The VTXO belongs to a different key, e.g. one derived for another offchain address.
+        let vtxo_kp = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[2; 32]).unwrap());
+        let (vtxo_owner, _) = vtxo_kp.x_only_public_key();
+        let server = XOnlyPublicKey::from_str(SERVER).unwrap();
+
+        let boarding_output = BoardingOutput::new(
+            &secp,
+            server,
+            owner,
+            "",
+            Sequence::from_seconds_ceil(604_672).unwrap(),
+            Network::Regtest,
+        );
+        let vtxo = DefaultVtxo::new(
+            &secp,
+            server,
+            vtxo_owner,
+            Sequence::from_seconds_ceil(86_528).unwrap(),
+            Network::Regtest,
+        );
+
+        let intent = create_and_sign_intent(
+            &[kp, vtxo_kp],
+            &[OnChainInput::new(
+                boarding_output,
+                Amount::from_sat(100_000),
+                OutPoint::new(Txid::all_zeros(), 0),
+            )],
+            &[VtxoInput::new(
+                vtxo,
+                Amount::from_sat(50_000),
+                OutPoint::new(Txid::all_zeros(), 1),
+            )],
+            1_700_000_000,
+            1_700_000_120,
+        )
+        .unwrap();
+
+        let proof = intent.proof();
+        assert_eq!(proof.inputs.len(), 3);
+
+        // The first input spends the `to_spend` transaction committing to the message.
+        let to_spend = to_spend_transaction(
+            intent.message().as_bytes(),
+            proof.inputs[0].witness_utxo.clone().unwrap().script_pubkey,
+        );
+        assert_eq!(
+            proof.unsigned_tx.input[0].previous_output,
+            OutPoint::new(to_spend.compute_txid(), 0)
+        );
+
+        let prevouts = proof
+            .inputs
+            .iter()
+            .map(|i| i.witness_utxo.clone().unwrap())
+            .collect::<Vec<_>>();
+
+        for (i, input) in proof.inputs.iter().enumerate() {
+            let ((pk, leaf_hash), sig) = input.tap_script_sigs.iter().next().unwrap();
+            let expected_owner = if i == 2 { vtxo_owner } else { owner };
+            assert_eq!(*pk, expected_owner);
+
+            let sighash = SighashCache::new(&proof.unsigned_tx)
+                .taproot_script_spend_signature_hash(
+                    i,
+                    &Prevouts::All(&prevouts),
+                    *leaf_hash,
+                    TapSighashType::Default,
+                )
+                .unwrap();
+            let msg = secp256k1::Message::from_digest(sighash.to_raw_hash().to_byte_array());
+
+            secp.verify_schnorr(&sig.signature, &msg, pk).unwrap();
+        }
+    }
+}
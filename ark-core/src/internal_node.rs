@@ -1,6 +1,6 @@
 use crate::script::csv_sig_script;
 use bitcoin::key::Secp256k1;
-use bitcoin::secp256k1::All;
+use bitcoin::secp256k1::Verification;
 use bitcoin::taproot::LeafVersion;
 use bitcoin::taproot::TaprootBuilder;
 use bitcoin::taproot::TaprootSpendInfo;
@@ -22,11 +22,14 @@ impl VtxoTreeInternalNodeScript {
         Self { script }
     }
 
-    pub fn sweep_spend_leaf(
+    pub fn sweep_spend_leaf<C>(
         &self,
-        secp: &Secp256k1<All>,
+        secp: &Secp256k1<C>,
         aggregate_pk: XOnlyPublicKey,
-    ) -> TaprootSpendInfo {
+    ) -> TaprootSpendInfo
+    where
+        C: Verification,
+    {
         TaprootBuilder::new()
             .add_leaf_with_ver(0, self.script.clone(), LeafVersion::TapScript)
             .expect("valid sweep leaf")
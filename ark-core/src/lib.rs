@@ -1,10 +1,23 @@
+pub mod accounting;
+pub mod audit;
 pub mod coin_select;
 pub mod default_vtxo;
+pub mod derivation;
+pub mod derivation_spec;
+pub mod exit_delay;
+pub mod fees;
+pub mod intent;
+pub mod musig;
+pub mod payment_proof;
+pub mod payment_request;
+pub mod receipt;
 pub mod redeem;
 pub mod round;
 pub mod server;
+pub mod topology;
 pub mod tx_weight_estimator;
 pub mod unilateral_exit;
+pub mod uri;
 
 mod ark_address;
 mod boarding_output;
@@ -14,8 +27,13 @@ mod forfeit_fee;
 mod history;
 mod internal_node;
 mod script;
+mod signing;
+
+#[cfg(test)]
+mod test_utils;
 
 pub use ark_address::ArkAddress;
+pub use audit::verify_round;
 pub use boarding_output::BoardingOutput;
 pub use default_vtxo::DefaultVtxo;
 pub use error::Error;
@@ -23,6 +41,8 @@ pub use error::ErrorContext;
 pub use history::generate_incoming_vtxo_transaction_history;
 pub use history::generate_outgoing_vtxo_transaction_history;
 pub use history::ArkTransaction;
+pub use history::Direction;
+pub use history::TransactionAmount;
 pub use script::extract_sequence_from_csv_sig_script;
 
 pub const UNSPENDABLE_KEY: &str =
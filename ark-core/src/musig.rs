@@ -0,0 +1,394 @@
+//! The MuSig2 primitives used to cosign the VTXO tree of a round.
+//!
+//! Every internal node of the VTXO tree is spent with a key path spend by the aggregate key of its
+//! cosigners, tweaked with the sweep leaf of the Ark server. Signing a round goes as follows:
+//!
+//! 1. Every cosigner generates a nonce pair per node it cosigns with [`generate_nonce_pair`] and
+//!    submits the public nonces to the Ark server.
+//! 2. The Ark server aggregates the public nonces of each node with [`aggregate_nonces`] and sends
+//!    the aggregate nonces back.
+//! 3. Every cosigner signs each of its nodes with [`partial_sign`] and submits the partial
+//!    signatures.
+//! 4. The Ark server checks them with [`verify_partial_signature`] and combines them into the
+//!    signature of the node with [`aggregate_partial_signatures`].
+//!
+//! Cosigner keys are aggregated with [`key_agg_cache`], sorted by their serialization. See
+//! [`crate::round::VtxoTreeSigningSession`] for the whole client side of the protocol.
+//!
+//! The tests of this module include deterministic test vectors, so that other implementations
+//! can check that they agree with this one.
+
+use crate::conversions::from_zkp_xonly;
+use crate::conversions::to_zkp_pk;
+use crate::Error;
+use bitcoin::hashes::Hash;
+use bitcoin::key::Keypair;
+use bitcoin::secp256k1;
+use bitcoin::secp256k1::schnorr;
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::TapTweakHash;
+use bitcoin::XOnlyPublicKey;
+use rand::CryptoRng;
+use rand::Rng;
+use zkp::new_musig_nonce_pair;
+pub use zkp::MusigAggNonce;
+pub use zkp::MusigKeyAggCache;
+pub use zkp::MusigPartialSignature;
+pub use zkp::MusigPubNonce;
+pub use zkp::MusigSecNonce;
+use zkp::MusigSession;
+use zkp::MusigSessionId;
+
+/// The key aggregation cache of `cosigner_pks`, aggregated in the given order.
+///
+/// The Ark server aggregates the cosigners of a node sorted by their serialization, without
+/// duplicates, see [`crate::round::NodeCosigners`].
+pub fn key_agg_cache<C>(
+    secp_zkp: &zkp::Secp256k1<C>,
+    cosigner_pks: &[PublicKey],
+) -> MusigKeyAggCache
+where
+    C: zkp::Verification,
+{
+    let cosigner_pks = cosigner_pks
+        .iter()
+        .map(|pk| to_zkp_pk(*pk))
+        .collect::<Vec<_>>();
+
+    MusigKeyAggCache::new(secp_zkp, &cosigner_pks)
+}
+
+/// The aggregate key of `key_agg_cache`, including any tweak applied to it.
+pub fn aggregate_pk(key_agg_cache: &MusigKeyAggCache) -> XOnlyPublicKey {
+    from_zkp_xonly(key_agg_cache.agg_pk_full().x_only_public_key().0)
+}
+
+/// Tweak the aggregate key of `key_agg_cache` with `tap_tweak`, so that the cosigners sign for the
+/// Taproot output key instead of the internal key.
+pub fn apply_taproot_tweak<C>(
+    secp_zkp: &zkp::Secp256k1<C>,
+    key_agg_cache: &mut MusigKeyAggCache,
+    tap_tweak: TapTweakHash,
+) -> Result<(), Error>
+where
+    C: zkp::Verification,
+{
+    let tweak = zkp::SecretKey::from_slice(tap_tweak.as_byte_array()).map_err(Error::crypto)?;
+
+    key_agg_cache
+        .pubkey_xonly_tweak_add(secp_zkp, tweak)
+        .map_err(Error::crypto)?;
+
+    Ok(())
+}
+
+/// Generate a fresh nonce pair for the cosigner `pk`.
+///
+/// The secret nonce can only be used once, by [`partial_sign`].
+pub fn generate_nonce_pair<R, C>(
+    rng: &mut R,
+    secp_zkp: &zkp::Secp256k1<C>,
+    pk: PublicKey,
+) -> Result<(MusigSecNonce, MusigPubNonce), Error>
+where
+    R: Rng + CryptoRng,
+    C: zkp::Signing,
+{
+    let session_id = MusigSessionId::new(rng);
+    let extra_rand = rng.gen();
+
+    new_musig_nonce_pair(
+        secp_zkp,
+        session_id,
+        None,
+        None,
+        to_zkp_pk(pk),
+        None,
+        Some(extra_rand),
+    )
+    .map_err(Error::crypto)
+}
+
+/// Derive the nonce pair of the cosigner `pk` from `session_id` alone.
+///
+/// Only use this to reproduce test vectors. Signing two different messages with nonces derived
+/// from the same `session_id` reveals the secret key of `pk`: use [`generate_nonce_pair`] instead.
+pub fn nonce_pair_from_session_id<C>(
+    secp_zkp: &zkp::Secp256k1<C>,
+    session_id: [u8; 32],
+    pk: PublicKey,
+) -> Result<(MusigSecNonce, MusigPubNonce), Error>
+where
+    C: zkp::Signing,
+{
+    let session_id = MusigSessionId::assume_unique_per_nonce_gen(session_id);
+
+    new_musig_nonce_pair(secp_zkp, session_id, None, None, to_zkp_pk(pk), None, None)
+        .map_err(Error::crypto)
+}
+
+/// Combine the public nonces of all the cosigners of a message.
+pub fn aggregate_nonces<C>(
+    secp_zkp: &zkp::Secp256k1<C>,
+    pub_nonces: &[MusigPubNonce],
+) -> MusigAggNonce
+where
+    C: zkp::Signing,
+{
+    MusigAggNonce::new(secp_zkp, pub_nonces)
+}
+
+/// Our partial signature of `msg` with `kp`, for the aggregate key of `key_agg_cache` and the
+/// aggregate nonce `agg_nonce`.
+///
+/// `sec_nonce` must be the secret nonce whose public nonce went into `agg_nonce`. It is consumed,
+/// so that it cannot be reused.
+pub fn partial_sign<C>(
+    secp_zkp: &zkp::Secp256k1<C>,
+    kp: &Keypair,
+    key_agg_cache: &MusigKeyAggCache,
+    msg: secp256k1::Message,
+    sec_nonce: MusigSecNonce,
+    agg_nonce: MusigAggNonce,
+) -> Result<MusigPartialSignature, Error>
+where
+    C: zkp::Signing,
+{
+    let kp =
+        zkp::Keypair::from_seckey_slice(secp_zkp, &kp.secret_bytes()).map_err(Error::crypto)?;
+
+    session(secp_zkp, key_agg_cache, agg_nonce, msg)
+        .partial_sign(secp_zkp, sec_nonce, &kp, key_agg_cache)
+        .map_err(Error::crypto)
+}
+
+/// Check that `partial_sig` is the partial signature of `msg` by the cosigner `pk`, whose public
+/// nonce is `pub_nonce`.
+pub fn verify_partial_signature<C>(
+    secp_zkp: &zkp::Secp256k1<C>,
+    key_agg_cache: &MusigKeyAggCache,
+    agg_nonce: MusigAggNonce,
+    msg: secp256k1::Message,
+    partial_sig: MusigPartialSignature,
+    pub_nonce: MusigPubNonce,
+    pk: PublicKey,
+) -> Result<(), Error>
+where
+    C: zkp::Signing,
+{
+    let is_valid = session(secp_zkp, key_agg_cache, agg_nonce, msg).partial_verify(
+        secp_zkp,
+        key_agg_cache,
+        partial_sig,
+        pub_nonce,
+        to_zkp_pk(pk),
+    );
+
+    if !is_valid {
+        return Err(Error::crypto(format!("invalid partial signature by {pk}")));
+    }
+
+    Ok(())
+}
+
+/// Combine the partial signatures of all the cosigners of `msg` into a BIP340 signature for the
+/// aggregate key of `key_agg_cache`.
+///
+/// The partial signatures are not checked: use [`verify_partial_signature`] first, or verify the
+/// resulting signature.
+pub fn aggregate_partial_signatures<C>(
+    secp_zkp: &zkp::Secp256k1<C>,
+    key_agg_cache: &MusigKeyAggCache,
+    agg_nonce: MusigAggNonce,
+    msg: secp256k1::Message,
+    partial_sigs: &[MusigPartialSignature],
+) -> schnorr::Signature
+where
+    C: zkp::Signing,
+{
+    let sig = session(secp_zkp, key_agg_cache, agg_nonce, msg).partial_sig_agg(partial_sigs);
+
+    schnorr::Signature::from_slice(sig.as_ref()).expect("valid conversion")
+}
+
+fn session<C>(
+    secp_zkp: &zkp::Secp256k1<C>,
+    key_agg_cache: &MusigKeyAggCache,
+    agg_nonce: MusigAggNonce,
+    msg: secp256k1::Message,
+) -> MusigSession
+where
+    C: zkp::Signing,
+{
+    let msg = zkp::Message::from_digest(*msg.as_ref());
+
+    MusigSession::new(secp_zkp, key_agg_cache, agg_nonce, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hex::DisplayHex;
+    use bitcoin::key::Secp256k1;
+    use bitcoin::key::TapTweak;
+    use bitcoin::secp256k1::SecretKey;
+    use std::str::FromStr;
+
+    /// Two cosigners sign a message with nonces derived from fixed session IDs.
+    #[test]
+    fn two_cosigners_test_vector() {
+        let secp = Secp256k1::new();
+        let secp_zkp = zkp::Secp256k1::new();
+
+        let kp1 = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[0x11; 32]).unwrap());
+        let kp2 = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[0x22; 32]).unwrap());
+        let msg = secp256k1::Message::from_digest([0x55; 32]);
+
+        let mut cosigner_pks = vec![kp1.public_key(), kp2.public_key()];
+        cosigner_pks.sort_by_key(|pk| pk.serialize());
+
+        let key_agg_cache = key_agg_cache(&secp_zkp, &cosigner_pks);
+        assert_eq!(
+            aggregate_pk(&key_agg_cache).to_string(),
+            "fc23182b2ee6d25438f148c2836a5ca041b4caca3d99202294ae89c790332fb3"
+        );
+
+        let (sec_nonce1, pub_nonce1) =
+            nonce_pair_from_session_id(&secp_zkp, [0x33; 32], kp1.public_key()).unwrap();
+        let (sec_nonce2, pub_nonce2) =
+            nonce_pair_from_session_id(&secp_zkp, [0x44; 32], kp2.public_key()).unwrap();
+        assert_eq!(
+            pub_nonce1.serialize().to_lower_hex_string(),
+            "02ca984c6a983621b01dd9980fbf95f4888c902634bf431bf3ee8c612c694e7f20\
+             021f68b3e4d3a9a3a9978cdc1e3bf3da862070bb5afb5d9baebbaadbf06c6368b5"
+        );
+        assert_eq!(
+            pub_nonce2.serialize().to_lower_hex_string(),
+            "0363eea1d853f399c10e86e8858ca791e413763f6d018f9235c79e7fb6cbbe9648\
+             0278d9842c3d8a5293c47d8a66606d40f4b705c3862ff22977bd76dd90ec4fc210"
+        );
+
+        let agg_nonce = aggregate_nonces(&secp_zkp, &[pub_nonce1, pub_nonce2]);
+        assert_eq!(
+            agg_nonce.serialize().to_lower_hex_string(),
+            "030335f7f930f5ccf830c3166a913400d3a3d94c4cf40fe8963f0cf2b292e49e97\
+             02c730dfe8202fabb4ee8a6d5cb4fb2252b762d8d83275e19423b16becd8cce833"
+        );
+
+        let partial_sig1 =
+            partial_sign(&secp_zkp, &kp1, &key_agg_cache, msg, sec_nonce1, agg_nonce).unwrap();
+        let partial_sig2 =
+            partial_sign(&secp_zkp, &kp2, &key_agg_cache, msg, sec_nonce2, agg_nonce).unwrap();
+        assert_eq!(
+            partial_sig1.serialize().to_lower_hex_string(),
+            "d84c8ad80dd5336af2b734eea5b80d04571b5212e22544fb107c24be9ca31467"
+        );
+        assert_eq!(
+            partial_sig2.serialize().to_lower_hex_string(),
+            "5cdba702f52d1284ebbb09a6a5b855d035ab0f9dab478e3b916a67a9a831d46e"
+        );
+
+        verify_partial_signature(
+            &secp_zkp,
+            &key_agg_cache,
+            agg_nonce,
+            msg,
+            partial_sig1,
+            pub_nonce1,
+            kp1.public_key(),
+        )
+        .unwrap();
+        verify_partial_signature(
+            &secp_zkp,
+            &key_agg_cache,
+            agg_nonce,
+            msg,
+            partial_sig2,
+            pub_nonce2,
+            kp2.public_key(),
+        )
+        .unwrap();
+
+        // A partial signature does not verify for the other cosigner.
+        assert!(verify_partial_signature(
+            &secp_zkp,
+            &key_agg_cache,
+            agg_nonce,
+            msg,
+            partial_sig1,
+            pub_nonce2,
+            kp2.public_key(),
+        )
+        .is_err());
+
+        let sig = aggregate_partial_signatures(
+            &secp_zkp,
+            &key_agg_cache,
+            agg_nonce,
+            msg,
+            &[partial_sig1, partial_sig2],
+        );
+        assert_eq!(
+            sig,
+            schnorr::Signature::from_str(
+                "0650dc455a72c7e402e73c4e2910d2c3d2c10f4439fdffea187f52ee3e7f4103\
+                 352831db030245efde723e954b7062d5d21784c9de2432fae2142ddb749ea794"
+            )
+            .unwrap()
+        );
+
+        secp.verify_schnorr(&sig, &msg, &aggregate_pk(&key_agg_cache))
+            .unwrap();
+    }
+
+    #[test]
+    fn tweaked_aggregate_key_signature_verifies() {
+        let secp = Secp256k1::new();
+        let secp_zkp = zkp::Secp256k1::new();
+        let mut rng = rand::thread_rng();
+
+        let kps = (1..=3)
+            .map(|i| Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[i; 32]).unwrap()))
+            .collect::<Vec<_>>();
+        let msg = secp256k1::Message::from_digest([7; 32]);
+
+        let mut key_agg_cache = key_agg_cache(
+            &secp_zkp,
+            &kps.iter().map(|kp| kp.public_key()).collect::<Vec<_>>(),
+        );
+        let internal_pk = aggregate_pk(&key_agg_cache);
+        let tap_tweak = TapTweakHash::from_key_and_tweak(internal_pk, None);
+        apply_taproot_tweak(&secp_zkp, &mut key_agg_cache, tap_tweak).unwrap();
+
+        let nonces = kps
+            .iter()
+            .map(|kp| generate_nonce_pair(&mut rng, &secp_zkp, kp.public_key()).unwrap())
+            .collect::<Vec<_>>();
+        let agg_nonce = aggregate_nonces(
+            &secp_zkp,
+            &nonces
+                .iter()
+                .map(|(_, pub_nonce)| *pub_nonce)
+                .collect::<Vec<_>>(),
+        );
+
+        let partial_sigs = kps
+            .iter()
+            .zip(nonces)
+            .map(|(kp, (sec_nonce, _))| {
+                partial_sign(&secp_zkp, kp, &key_agg_cache, msg, sec_nonce, agg_nonce).unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let sig =
+            aggregate_partial_signatures(&secp_zkp, &key_agg_cache, agg_nonce, msg, &partial_sigs);
+
+        let (output_key, _) = internal_pk.tap_tweak(&secp, None);
+        assert_eq!(
+            aggregate_pk(&key_agg_cache),
+            output_key.to_x_only_public_key()
+        );
+        secp.verify_schnorr(&sig, &msg, &output_key.to_x_only_public_key())
+            .unwrap();
+    }
+}
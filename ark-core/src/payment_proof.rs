@@ -0,0 +1,610 @@
+//! Proofs that a VTXO was created in a round, which can be handed to a third party as a receipt.
+//!
+//! A [`PaymentProof`] contains the round transaction and the branch of the VTXO tree leading from
+//! it to the VTXO. Every transaction in the branch announces its cosigners, among them the Ark
+//! server, and carries the key-spend signature of their aggregate key. Anyone holding the proof can
+//! check the signatures and the links between the transactions, and then look up the round
+//! transaction on the blockchain.
+
+use crate::internal_node::VtxoTreeInternalNodeScript;
+use crate::redeem::verify_redeem_transaction;
+use crate::round::NodeCosigners;
+use crate::server::Round;
+use crate::server::VtxoOutPoint;
+use crate::Error;
+use crate::ErrorContext;
+use bitcoin::hashes::Hash;
+use bitcoin::key::Secp256k1;
+use bitcoin::secp256k1;
+use bitcoin::sighash::Prevouts;
+use bitcoin::sighash::SighashCache;
+use bitcoin::OutPoint;
+use bitcoin::Psbt;
+use bitcoin::Transaction;
+use bitcoin::TxOut;
+use bitcoin::Txid;
+use bitcoin::XOnlyPublicKey;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PaymentProof {
+    pub vtxo_outpoint: OutPoint,
+    pub round_tx: Transaction,
+    /// The transactions of the VTXO tree leading from the round transaction to the VTXO, ordered
+    /// from the root to the leaf.
+    pub branch: Vec<Psbt>,
+    /// The out-of-round transaction which created the VTXO, spending an output of the last
+    /// transaction in the branch.
+    pub redeem_tx: Option<Psbt>,
+}
+
+impl PaymentProof {
+    pub fn round_txid(&self) -> Txid {
+        self.round_tx.compute_txid()
+    }
+
+    /// Check that the proof is internally consistent and fully signed, returning the output of
+    /// the VTXO.
+    ///
+    /// `server` is the public key of the Ark server, which must have cosigned every transaction of
+    /// the branch and the redeem transaction, if any. Every output spent by the branch, starting
+    /// with the shared output of the round transaction, must be locked by the aggregate key of the
+    /// cosigners, tweaked with the sweep leaf of `server` after `vtxo_tree_expiry`.
+    ///
+    /// A successful verification does not mean that the payment happened: the verifier must also
+    /// check that the transaction with TXID [`PaymentProof::round_txid`] was confirmed on the
+    /// blockchain.
+    pub fn verify(
+        &self,
+        server: XOnlyPublicKey,
+        vtxo_tree_expiry: bitcoin::Sequence,
+    ) -> Result<TxOut, Error> {
+        if self.branch.is_empty() {
+            return Err(Error::ad_hoc(
+                "proof does not include a branch of the VTXO tree",
+            ));
+        }
+
+        let secp = Secp256k1::verification_only();
+        let internal_node_script = VtxoTreeInternalNodeScript::new(vtxo_tree_expiry, server);
+
+        let mut parent = self.round_tx.clone();
+        for (i, node) in self.branch.iter().enumerate() {
+            let tx = &node.unsigned_tx;
+
+            let previous_output = match tx.input.as_slice() {
+                [input] => input.previous_output,
+                _ => {
+                    return Err(Error::ad_hoc(format!(
+                        "VTXO tree transaction {} must have exactly one input",
+                        tx.compute_txid()
+                    )))
+                }
+            };
+
+            let prevout = spent_output(&parent, previous_output).ok_or_else(|| {
+                Error::ad_hoc(format!(
+                    "transaction {i} of the branch does not spend its parent"
+                ))
+            })?;
+
+            verify_vtxo_tree_node(&secp, &internal_node_script, server, node, prevout)?;
+
+            parent = tx.clone();
+        }
+
+        if let Some(redeem_psbt) = self.redeem_tx.as_ref() {
+            verify_redeem_tx(redeem_psbt, &parent, server)?;
+
+            parent = redeem_psbt.unsigned_tx.clone();
+        }
+
+        if parent.compute_txid() != self.vtxo_outpoint.txid {
+            return Err(Error::ad_hoc(format!(
+                "proof does not lead to VTXO {}",
+                self.vtxo_outpoint
+            )));
+        }
+
+        parent
+            .output
+            .get(self.vtxo_outpoint.vout as usize)
+            .cloned()
+            .ok_or_else(|| Error::ad_hoc(format!("VTXO {} not found", self.vtxo_outpoint)))
+    }
+}
+
+/// Build the [`PaymentProof`] for `vtxo`, which must have been created in `round`.
+///
+/// VTXOs created out of round are supported as long as the redeem transaction spends a VTXO of
+/// `round` directly. Longer chains of out-of-round transactions cannot be proven yet.
+pub fn payment_proof(round: &Round, vtxo: &VtxoOutPoint) -> Result<PaymentProof, Error> {
+    let round_txid = round.round_tx.unsigned_tx.compute_txid();
+
+    let nodes = round
+        .vtxo_tree
+        .levels
+        .iter()
+        .flat_map(|level| level.nodes.iter())
+        .collect::<Vec<_>>();
+
+    let leaf_txid = match vtxo.redeem_tx.as_ref() {
+        Some(redeem_tx) => redeem_tx
+            .unsigned_tx
+            .input
+            .iter()
+            .map(|input| input.previous_output.txid)
+            .find(|txid| nodes.iter().any(|node| node.txid == *txid))
+            .ok_or_else(|| {
+                Error::ad_hoc(format!(
+                    "redeem transaction of VTXO {} does not spend a VTXO of round {round_txid}",
+                    vtxo.outpoint
+                ))
+            })?,
+        None => vtxo.outpoint.txid,
+    };
+
+    let mut branch = Vec::new();
+    let mut txid = leaf_txid;
+    while txid != round_txid {
+        let node = nodes.iter().find(|node| node.txid == txid).ok_or_else(|| {
+            Error::ad_hoc(format!(
+                "transaction {txid} not found in VTXO tree of round {round_txid}"
+            ))
+        })?;
+
+        branch.push(node.tx.clone());
+        txid = node.parent_txid;
+    }
+
+    branch.reverse();
+
+    Ok(PaymentProof {
+        vtxo_outpoint: vtxo.outpoint,
+        round_tx: round.round_tx.unsigned_tx.clone(),
+        branch,
+        redeem_tx: vtxo.redeem_tx.clone(),
+    })
+}
+
+/// Check that `redeem_psbt` spends an output of `parent` and that it is signed by the Ark server,
+/// with public key `server`, and the owners of the VTXOs it spends.
+fn verify_redeem_tx(
+    redeem_psbt: &Psbt,
+    parent: &Transaction,
+    server: XOnlyPublicKey,
+) -> Result<(), Error> {
+    let spends_parent = redeem_psbt
+        .unsigned_tx
+        .input
+        .iter()
+        .zip(redeem_psbt.inputs.iter())
+        .any(|(input, psbt_input)| {
+            psbt_input.witness_utxo.is_some()
+                && spent_output(parent, input.previous_output) == psbt_input.witness_utxo
+        });
+    if !spends_parent {
+        return Err(Error::ad_hoc(
+            "redeem transaction does not spend the last transaction of the branch",
+        ));
+    }
+
+    verify_redeem_transaction(redeem_psbt, server)
+}
+
+/// Check that the VTXO tree transaction `node`, which spends `prevout`, was cosigned by the Ark
+/// server `server_pk`.
+///
+/// `prevout` must be locked by the aggregate key of the cosigners listed in `node`, among them
+/// `server_pk`, tweaked with `internal_node_script`. The key-spend signature of `node` must be valid
+/// for that key.
+pub(crate) fn verify_vtxo_tree_node(
+    secp: &Secp256k1<secp256k1::VerifyOnly>,
+    internal_node_script: &VtxoTreeInternalNodeScript,
+    server_pk: XOnlyPublicKey,
+    node: &Psbt,
+    prevout: TxOut,
+) -> Result<(), Error> {
+    let tx = &node.unsigned_tx;
+
+    NodeCosigners::from_psbt(node)?
+        .verify_cosigned_by_server(internal_node_script, server_pk, &prevout)
+        .with_context(|| format!("invalid VTXO tree transaction {}", tx.compute_txid()))?;
+
+    verify_vtxo_tree_tx_signature(secp, node, prevout)
+}
+
+/// Check the key-spend signature on the VTXO tree transaction `node`, which spends `prevout`.
+///
+/// This only proves that the holder of the key of `prevout` signed `node`, see
+/// [`verify_vtxo_tree_node`].
+fn verify_vtxo_tree_tx_signature(
+    secp: &Secp256k1<secp256k1::VerifyOnly>,
+    node: &Psbt,
+    prevout: TxOut,
+) -> Result<(), Error> {
+    let tx = &node.unsigned_tx;
+
+    let sig = node
+        .inputs
+        .first()
+        .and_then(|input| input.tap_key_sig)
+        .ok_or_else(|| {
+            Error::ad_hoc(format!(
+                "VTXO tree transaction {} is not signed",
+                tx.compute_txid()
+            ))
+        })?;
+
+    let output_key = taproot_output_key(&prevout)?;
+
+    let sighash = SighashCache::new(tx)
+        .taproot_key_spend_signature_hash(0, &Prevouts::All(&[prevout]), sig.sighash_type)
+        .map_err(Error::crypto)?;
+    let msg = secp256k1::Message::from_digest(sighash.to_raw_hash().to_byte_array());
+
+    secp.verify_schnorr(&sig.signature, &msg, &output_key)
+        .map_err(|e| {
+            Error::crypto(format!(
+                "invalid signature on VTXO tree transaction {}: {e}",
+                tx.compute_txid()
+            ))
+        })
+}
+
+fn spent_output(parent: &Transaction, outpoint: OutPoint) -> Option<TxOut> {
+    if outpoint.txid != parent.compute_txid() {
+        return None;
+    }
+
+    parent.output.get(outpoint.vout as usize).cloned()
+}
+
+pub(crate) fn taproot_output_key(output: &TxOut) -> Result<XOnlyPublicKey, Error> {
+    let script = &output.script_pubkey;
+    if !script.is_p2tr() {
+        return Err(Error::ad_hoc(format!("output {script} is not P2TR")));
+    }
+
+    XOnlyPublicKey::from_slice(&script.as_bytes()[2..]).map_err(Error::crypto)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::multisig_script;
+    use crate::server::TxTree;
+    use crate::server::TxTreeLevel;
+    use crate::test_utils::cosigned_child;
+    use crate::test_utils::keypair;
+    use crate::test_utils::node_output;
+    use crate::test_utils::p2tr_output;
+    use crate::test_utils::signed_child;
+    use crate::DefaultVtxo;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::key::Keypair;
+    use bitcoin::taproot;
+    use bitcoin::transaction;
+    use bitcoin::Amount;
+    use bitcoin::Network;
+    use bitcoin::ScriptBuf;
+    use bitcoin::Sequence;
+    use bitcoin::TapLeafHash;
+    use bitcoin::TapSighashType;
+    use bitcoin::TxIn;
+    use std::collections::BTreeMap;
+
+    fn expiry() -> Sequence {
+        Sequence::from_seconds_ceil(604_672).unwrap()
+    }
+
+    /// A round transaction with a single shared output of `amount`, locked by `cosigners`.
+    fn round_tx(cosigners: &[&Keypair], server_pk: XOnlyPublicKey, amount: Amount) -> Transaction {
+        Transaction {
+            version: transaction::Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: vec![node_output(cosigners, expiry(), server_pk, amount)],
+        }
+    }
+
+    #[test]
+    fn signed_branch_is_verified() {
+        let server = keypair(1);
+        let owner = keypair(2);
+        let (server_pk, _) = server.x_only_public_key();
+        let cosigners = [&server, &owner];
+
+        let amount = Amount::from_sat(100_000);
+
+        let round_tx = round_tx(&cosigners, server_pk, amount);
+        let round_txid = round_tx.compute_txid();
+
+        let root = cosigned_child(
+            &round_tx,
+            0,
+            &cosigners,
+            expiry(),
+            server_pk,
+            vec![node_output(&cosigners, expiry(), server_pk, amount)],
+        );
+        let leaf = cosigned_child(
+            &root.tx.unsigned_tx,
+            0,
+            &cosigners,
+            expiry(),
+            server_pk,
+            vec![p2tr_output(&owner, amount)],
+        );
+
+        let round = Round {
+            id: "round".to_string(),
+            start: 0,
+            end: 0,
+            round_tx: Psbt::from_unsigned_tx(round_tx).unwrap(),
+            vtxo_tree: TxTree {
+                levels: vec![
+                    TxTreeLevel {
+                        nodes: vec![root.clone()],
+                    },
+                    TxTreeLevel {
+                        nodes: vec![leaf.clone()],
+                    },
+                ],
+            },
+            forfeit_txs: Vec::new(),
+            connector_tree: TxTree { levels: Vec::new() },
+            stage: 0,
+        };
+
+        let vtxo = VtxoOutPoint {
+            outpoint: OutPoint::new(leaf.txid, 0),
+            spent: false,
+            round_txid,
+            spent_by: None,
+            expire_at: 0,
+            sweepable_after: None,
+            swept: false,
+            is_pending: false,
+            redeem_tx: None,
+            amount,
+            pubkey: String::new(),
+            created_at: 0,
+        };
+
+        let proof = payment_proof(&round, &vtxo).unwrap();
+
+        assert_eq!(proof.round_txid(), round_txid);
+        assert_eq!(proof.branch, vec![root.tx, leaf.tx.clone()]);
+        assert_eq!(
+            proof.verify(server_pk, expiry()).unwrap(),
+            p2tr_output(&owner, amount)
+        );
+
+        // The outputs of the branch must be sweepable by the Ark server after the VTXO tree
+        // expiry.
+        let other_expiry = Sequence::from_seconds_ceil(1_024).unwrap();
+        assert!(proof.verify(server_pk, other_expiry).is_err());
+
+        // A leaf signed by someone other than the cosigners is rejected.
+        let mut forged = proof.clone();
+        let forged_leaf = signed_child(
+            &forged.branch[0].unsigned_tx,
+            0,
+            &owner,
+            vec![p2tr_output(&owner, amount)],
+        );
+        forged.vtxo_outpoint = OutPoint::new(forged_leaf.txid, 0);
+        forged.branch[1] = forged_leaf.tx;
+        assert!(forged.verify(server_pk, expiry()).is_err());
+
+        // So is a leaf without signature.
+        let mut unsigned = proof;
+        unsigned.branch[1].inputs[0].tap_key_sig = None;
+        assert!(unsigned.verify(server_pk, expiry()).is_err());
+    }
+
+    #[test]
+    fn branch_not_cosigned_by_server_is_rejected() {
+        let server = keypair(1);
+        let attacker = keypair(3);
+        let (server_pk, _) = server.x_only_public_key();
+
+        let amount = Amount::from_sat(100_000);
+
+        // The attacker confirms a transaction paying to their own key and signs a branch spending
+        // it themselves.
+        let attacker_tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn::default()],
+            output: vec![p2tr_output(&attacker, amount)],
+        };
+        let self_signed = signed_child(
+            &attacker_tx,
+            0,
+            &attacker,
+            vec![p2tr_output(&attacker, amount)],
+        );
+        let proof = PaymentProof {
+            vtxo_outpoint: OutPoint::new(self_signed.txid, 0),
+            round_tx: attacker_tx.clone(),
+            branch: vec![self_signed.tx],
+            redeem_tx: None,
+        };
+        assert!(proof.verify(server_pk, expiry()).is_err());
+
+        // Nor can they pass off an output of that transaction as a VTXO without a branch.
+        let proof = PaymentProof {
+            vtxo_outpoint: OutPoint::new(attacker_tx.compute_txid(), 0),
+            round_tx: attacker_tx,
+            branch: Vec::new(),
+            redeem_tx: None,
+        };
+        assert!(proof.verify(server_pk, expiry()).is_err());
+
+        // A branch which commits to the sweep leaf of the Ark server, but is only cosigned by the
+        // attacker, is rejected too.
+        let round_tx = round_tx(&[&attacker], server_pk, amount);
+        let leaf = cosigned_child(
+            &round_tx,
+            0,
+            &[&attacker],
+            expiry(),
+            server_pk,
+            vec![p2tr_output(&attacker, amount)],
+        );
+        let proof = PaymentProof {
+            vtxo_outpoint: OutPoint::new(leaf.txid, 0),
+            round_tx,
+            branch: vec![leaf.tx],
+            redeem_tx: None,
+        };
+        assert!(proof.verify(server_pk, expiry()).is_err());
+    }
+
+    /// A redeem transaction spending `vtxo`, the only output of `parent`, signed by `signers` over
+    /// the leaf `script` with `control_block`.
+    fn signed_redeem(
+        parent: &Transaction,
+        vtxo: &DefaultVtxo,
+        (script, control_block): (ScriptBuf, taproot::ControlBlock),
+        signers: &[&Keypair],
+    ) -> Psbt {
+        let secp = Secp256k1::new();
+
+        let prevout = parent.output[0].clone();
+        let tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(parent.compute_txid(), 0),
+                ..Default::default()
+            }],
+            output: vec![TxOut {
+                value: prevout.value,
+                script_pubkey: vtxo.script_pubkey(),
+            }],
+        };
+
+        let leaf_version = control_block.leaf_version;
+        let leaf_hash = TapLeafHash::from_script(&script, leaf_version);
+        let sighash = SighashCache::new(&tx)
+            .taproot_script_spend_signature_hash(
+                0,
+                &Prevouts::All(&[prevout.clone()]),
+                leaf_hash,
+                TapSighashType::Default,
+            )
+            .unwrap();
+        let msg = secp256k1::Message::from_digest(sighash.to_raw_hash().to_byte_array());
+
+        let mut psbt = Psbt::from_unsigned_tx(tx).unwrap();
+        psbt.inputs[0].witness_utxo = Some(prevout);
+        psbt.inputs[0].tap_scripts = BTreeMap::from_iter([(control_block, (script, leaf_version))]);
+        for signer in signers {
+            psbt.inputs[0].tap_script_sigs.insert(
+                (signer.x_only_public_key().0, leaf_hash),
+                taproot::Signature {
+                    signature: secp.sign_schnorr_no_aux_rand(&msg, signer),
+                    sighash_type: TapSighashType::Default,
+                },
+            );
+        }
+
+        psbt
+    }
+
+    #[test]
+    fn redeem_tx_must_be_cosigned_by_server_and_owner() {
+        let secp = Secp256k1::new();
+        let server = keypair(1);
+        let owner = keypair(2);
+        let attacker = keypair(3);
+        let (server_pk, _) = server.x_only_public_key();
+
+        let amount = Amount::from_sat(100_000);
+
+        let vtxo = DefaultVtxo::new(
+            &secp,
+            server_pk,
+            owner.x_only_public_key().0,
+            Sequence::from_seconds_ceil(86_400).unwrap(),
+            Network::Regtest,
+        );
+
+        let cosigners = [&server, &owner];
+        let round_tx = round_tx(&cosigners, server_pk, amount);
+        let leaf = cosigned_child(
+            &round_tx,
+            0,
+            &cosigners,
+            expiry(),
+            server_pk,
+            vec![TxOut {
+                value: amount,
+                script_pubkey: vtxo.script_pubkey(),
+            }],
+        )
+        .tx;
+
+        let proof_for = |redeem_tx: Psbt| PaymentProof {
+            vtxo_outpoint: OutPoint::new(redeem_tx.unsigned_tx.compute_txid(), 0),
+            round_tx: round_tx.clone(),
+            branch: vec![leaf.clone()],
+            redeem_tx: Some(redeem_tx),
+        };
+
+        let redeem_tx = signed_redeem(
+            &leaf.unsigned_tx,
+            &vtxo,
+            vtxo.forfeit_spend_info(),
+            &[&owner, &server],
+        );
+        proof_for(redeem_tx).verify(server_pk, expiry()).unwrap();
+
+        // Without the signature of the Ark server, the redeem transaction is not final.
+        let self_signed = signed_redeem(
+            &leaf.unsigned_tx,
+            &vtxo,
+            vtxo.forfeit_spend_info(),
+            &[&owner],
+        );
+        assert!(proof_for(self_signed).verify(server_pk, expiry()).is_err());
+
+        // Nor is it if the Ark server signed, but not the owner of the VTXO.
+        let server_signed = signed_redeem(
+            &leaf.unsigned_tx,
+            &vtxo,
+            vtxo.forfeit_spend_info(),
+            &[&server],
+        );
+        assert!(proof_for(server_signed)
+            .verify(server_pk, expiry())
+            .is_err());
+
+        // A leaf made up by the attacker is not in the script tree of the VTXO.
+        let (_, control_block) = vtxo.forfeit_spend_info();
+        let forged = signed_redeem(
+            &leaf.unsigned_tx,
+            &vtxo,
+            (
+                multisig_script(attacker.x_only_public_key().0, server_pk),
+                control_block,
+            ),
+            &[&attacker],
+        );
+        assert!(proof_for(forged).verify(server_pk, expiry()).is_err());
+
+        // Signatures by another key posing as the Ark server are rejected.
+        let impersonated = signed_redeem(
+            &leaf.unsigned_tx,
+            &vtxo,
+            vtxo.forfeit_spend_info(),
+            &[&owner, &attacker],
+        );
+        assert!(proof_for(impersonated)
+            .verify(attacker.x_only_public_key().0, expiry())
+            .is_err());
+    }
+}
@@ -0,0 +1,182 @@
+//! Signed requests for payment, which a payee hands to the payer before being paid.
+//!
+//! An [`ArkPaymentRequest`] asks for `amount` to be paid to `address` before `expires_at`. Since
+//! it is signed by the payee, the payer can check that the request was not tampered with, e.g. by
+//! someone replacing the address, before paying it. The signature only proves that the holder of
+//! the `payee` key issued the request: the payer must still check that `payee` is the key of whom
+//! they mean to pay, e.g. the published key of a merchant.
+
+use crate::ArkAddress;
+use crate::Error;
+use bitcoin::hashes::sha256;
+use bitcoin::hashes::Hash;
+use bitcoin::hashes::HashEngine;
+use bitcoin::key::Keypair;
+use bitcoin::key::Secp256k1;
+use bitcoin::secp256k1;
+use bitcoin::secp256k1::schnorr;
+use bitcoin::Amount;
+use bitcoin::XOnlyPublicKey;
+
+/// The tag of the hash signed by the payee of an [`ArkPaymentRequest`].
+const REQUEST_TAG: &[u8] = b"ark/payment-request";
+
+/// The version of the statement signed by the payee of an [`ArkPaymentRequest`].
+const REQUEST_VERSION: u8 = 0;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArkPaymentRequest {
+    /// The key of the payee, which signs the request.
+    pub payee: XOnlyPublicKey,
+    /// Where to send the payment.
+    pub address: ArkAddress,
+    #[cfg_attr(feature = "serde", serde(with = "bitcoin::amount::serde::as_sat"))]
+    pub amount: Amount,
+    /// The UNIX timestamp in seconds from which the request must no longer be paid.
+    pub expires_at: i64,
+    /// A description of the payment for the payer, e.g. what is being paid for.
+    pub memo: String,
+    /// The signature of the payee over all other fields.
+    pub signature: schnorr::Signature,
+}
+
+impl ArkPaymentRequest {
+    /// Request `amount` to be paid to `address` before `expires_at`, signed with the payee's `kp`.
+    pub fn new(
+        kp: &Keypair,
+        address: ArkAddress,
+        amount: Amount,
+        expires_at: i64,
+        memo: impl Into<String>,
+    ) -> Self {
+        let secp = Secp256k1::new();
+        let (payee, _) = kp.x_only_public_key();
+        let memo = memo.into();
+
+        let msg = message(&statement(payee, &address, amount, expires_at, &memo));
+        let signature = secp.sign_schnorr_no_aux_rand(&msg, kp);
+
+        Self {
+            payee,
+            address,
+            amount,
+            expires_at,
+            memo,
+            signature,
+        }
+    }
+
+    /// Check that the request was signed by [`ArkPaymentRequest::payee`], and that it has not
+    /// expired at `now`, a UNIX timestamp in seconds.
+    pub fn verify(&self, now: i64) -> Result<(), Error> {
+        let secp = Secp256k1::verification_only();
+
+        let msg = message(&statement(
+            self.payee,
+            &self.address,
+            self.amount,
+            self.expires_at,
+            &self.memo,
+        ));
+
+        secp.verify_schnorr(&self.signature, &msg, &self.payee)
+            .map_err(|e| Error::crypto(format!("invalid payment request signature: {e}")))?;
+
+        if self.is_expired(now) {
+            return Err(Error::ad_hoc(format!(
+                "payment request expired at {}",
+                self.expires_at
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        now >= self.expires_at
+    }
+}
+
+/// The serialization of everything the payee signs.
+fn statement(
+    payee: XOnlyPublicKey,
+    address: &ArkAddress,
+    amount: Amount,
+    expires_at: i64,
+    memo: &str,
+) -> Vec<u8> {
+    let address = address.encode();
+
+    let mut bytes = vec![REQUEST_VERSION];
+    bytes.extend_from_slice(&payee.serialize());
+    bytes.extend_from_slice(&(address.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(address.as_bytes());
+    bytes.extend_from_slice(&amount.to_sat().to_le_bytes());
+    bytes.extend_from_slice(&expires_at.to_le_bytes());
+    bytes.extend_from_slice(&(memo.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(memo.as_bytes());
+
+    bytes
+}
+
+/// The tagged hash of `statement`.
+fn message(statement: &[u8]) -> secp256k1::Message {
+    let tag = sha256::Hash::hash(REQUEST_TAG);
+
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag.as_ref());
+    engine.input(tag.as_ref());
+    engine.input(statement);
+
+    secp256k1::Message::from_digest(sha256::Hash::from_engine(engine).to_byte_array())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::key::TweakedPublicKey;
+    use bitcoin::secp256k1::SecretKey;
+    use bitcoin::Network;
+    use std::str::FromStr;
+
+    #[test]
+    fn payment_request_verification() {
+        let secp = Secp256k1::new();
+        let kp = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[1; 32]).unwrap());
+
+        let server = XOnlyPublicKey::from_str(
+            "33ffb3dee353b1a9ebe4ced64b946238d0a4ac364f275d771da6ad2445d07ae0",
+        )
+        .unwrap();
+        let address = ArkAddress::new(
+            Network::Regtest,
+            server,
+            TweakedPublicKey::dangerous_assume_tweaked(server),
+        );
+
+        let request = ArkPaymentRequest::new(
+            &kp,
+            address,
+            Amount::from_sat(21_000),
+            1_700_000_600,
+            "Order #42",
+        );
+        request.verify(1_700_000_000).unwrap();
+
+        assert!(request.verify(1_700_000_600).is_err());
+
+        let tampered = ArkPaymentRequest {
+            memo: "Order #43".to_string(),
+            ..request.clone()
+        };
+        assert!(tampered.verify(1_700_000_000).is_err());
+
+        let other_kp = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[2; 32]).unwrap());
+        let impersonated = ArkPaymentRequest {
+            payee: other_kp.x_only_public_key().0,
+            ..request
+        };
+        assert!(impersonated.verify(1_700_000_000).is_err());
+    }
+}
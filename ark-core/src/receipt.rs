@@ -0,0 +1,251 @@
+//! Signed statements of payment, which the sender of a VTXO hands to the recipient.
+//!
+//! A [`PaymentReceipt`] states that the owner of the `sender` key paid `amount` to `recipient` with
+//! the VTXO at `vtxo_outpoint`. Since it is signed by the sender, both sides can keep it as a
+//! record of what was paid, to settle disputes. It does not prove that the payment happened: that
+//! is what a [`PaymentProof`](crate::payment_proof::PaymentProof) is for.
+
+use crate::ArkAddress;
+use crate::Error;
+use bitcoin::consensus::Encodable;
+use bitcoin::hashes::sha256;
+use bitcoin::hashes::Hash;
+use bitcoin::hashes::HashEngine;
+use bitcoin::key::Keypair;
+use bitcoin::key::Secp256k1;
+use bitcoin::secp256k1;
+use bitcoin::secp256k1::schnorr;
+use bitcoin::Amount;
+use bitcoin::OutPoint;
+use bitcoin::XOnlyPublicKey;
+
+/// The tag of the hash signed by the sender of a [`PaymentReceipt`].
+const RECEIPT_TAG: &[u8] = b"ark/payment-receipt";
+
+/// The version of the serialization of a [`PaymentReceipt`].
+const RECEIPT_VERSION: u8 = 0;
+
+#[derive(Debug, Clone)]
+pub struct PaymentReceipt {
+    pub sender: XOnlyPublicKey,
+    pub recipient: ArkAddress,
+    pub amount: Amount,
+    /// The VTXO paying `amount` to `recipient`.
+    ///
+    /// Its TXID is that of the round transaction or the out-of-round transaction which created
+    /// the VTXO.
+    pub vtxo_outpoint: OutPoint,
+    /// The UNIX timestamp in seconds at which the receipt was issued.
+    pub created_at: i64,
+    /// The signature of the sender over all other fields.
+    pub signature: schnorr::Signature,
+}
+
+impl PaymentReceipt {
+    /// Issue a receipt for the payment of `amount` to `recipient` with the VTXO at
+    /// `vtxo_outpoint`, signed with the sender's `kp`.
+    pub fn new(
+        kp: &Keypair,
+        recipient: ArkAddress,
+        amount: Amount,
+        vtxo_outpoint: OutPoint,
+        created_at: i64,
+    ) -> Self {
+        let secp = Secp256k1::new();
+        let (sender, _) = kp.x_only_public_key();
+
+        let msg = message(&statement(
+            sender,
+            &recipient,
+            amount,
+            vtxo_outpoint,
+            created_at,
+        ));
+        let signature = secp.sign_schnorr_no_aux_rand(&msg, kp);
+
+        Self {
+            sender,
+            recipient,
+            amount,
+            vtxo_outpoint,
+            created_at,
+            signature,
+        }
+    }
+
+    /// Check that the receipt was signed by [`PaymentReceipt::sender`].
+    pub fn verify(&self) -> Result<(), Error> {
+        let secp = Secp256k1::verification_only();
+
+        let msg = message(&self.statement());
+
+        secp.verify_schnorr(&self.signature, &msg, &self.sender)
+            .map_err(|e| Error::crypto(format!("invalid payment receipt signature: {e}")))
+    }
+
+    /// Encode the receipt, e.g. to hand it to the recipient.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = self.statement();
+        bytes.extend_from_slice(self.signature.as_ref());
+
+        bytes
+    }
+
+    /// Decode a receipt encoded with [`PaymentReceipt::serialize`].
+    ///
+    /// The signature is not verified, see [`PaymentReceipt::verify`].
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader(bytes);
+
+        let version = reader.take(1)?[0];
+        if version != RECEIPT_VERSION {
+            return Err(Error::ad_hoc(format!(
+                "unsupported payment receipt version {version}"
+            )));
+        }
+
+        let sender = XOnlyPublicKey::from_slice(reader.take(32)?).map_err(Error::crypto)?;
+
+        let recipient_len = u16::from_le_bytes(reader.array()?) as usize;
+        let recipient = std::str::from_utf8(reader.take(recipient_len)?)
+            .map_err(|e| Error::ad_hoc(format!("invalid payment receipt recipient: {e}")))?;
+        let recipient = ArkAddress::decode(recipient)?;
+
+        let amount = Amount::from_sat(u64::from_le_bytes(reader.array()?));
+
+        let vtxo_outpoint = bitcoin::consensus::deserialize(reader.take(36)?)
+            .map_err(|e| Error::ad_hoc(format!("invalid payment receipt outpoint: {e}")))?;
+
+        let created_at = i64::from_le_bytes(reader.array()?);
+
+        let signature = schnorr::Signature::from_slice(reader.take(64)?).map_err(Error::crypto)?;
+
+        if !reader.0.is_empty() {
+            return Err(Error::ad_hoc("trailing bytes after payment receipt"));
+        }
+
+        Ok(Self {
+            sender,
+            recipient,
+            amount,
+            vtxo_outpoint,
+            created_at,
+            signature,
+        })
+    }
+
+    fn statement(&self) -> Vec<u8> {
+        statement(
+            self.sender,
+            &self.recipient,
+            self.amount,
+            self.vtxo_outpoint,
+            self.created_at,
+        )
+    }
+}
+
+/// The serialization of everything the sender signs.
+fn statement(
+    sender: XOnlyPublicKey,
+    recipient: &ArkAddress,
+    amount: Amount,
+    vtxo_outpoint: OutPoint,
+    created_at: i64,
+) -> Vec<u8> {
+    let recipient = recipient.encode();
+
+    let mut bytes = vec![RECEIPT_VERSION];
+    bytes.extend_from_slice(&sender.serialize());
+    bytes.extend_from_slice(&(recipient.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(recipient.as_bytes());
+    bytes.extend_from_slice(&amount.to_sat().to_le_bytes());
+    vtxo_outpoint
+        .consensus_encode(&mut bytes)
+        .expect("writing to a vector cannot fail");
+    bytes.extend_from_slice(&created_at.to_le_bytes());
+
+    bytes
+}
+
+/// The tagged hash of `statement`.
+fn message(statement: &[u8]) -> secp256k1::Message {
+    let tag = sha256::Hash::hash(RECEIPT_TAG);
+
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag.as_ref());
+    engine.input(tag.as_ref());
+    engine.input(statement);
+
+    secp256k1::Message::from_digest(sha256::Hash::from_engine(engine).to_byte_array())
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < n {
+            return Err(Error::ad_hoc("payment receipt is too short"));
+        }
+
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        Ok(self.take(N)?.try_into().expect("N bytes"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::key::TweakedPublicKey;
+    use bitcoin::secp256k1::SecretKey;
+    use bitcoin::Network;
+    use bitcoin::Txid;
+    use std::str::FromStr;
+
+    #[test]
+    fn payment_receipt_roundtrip_and_tampering() {
+        let secp = Secp256k1::new();
+        let kp = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[1; 32]).unwrap());
+
+        let server = XOnlyPublicKey::from_str(
+            "33ffb3dee353b1a9ebe4ced64b946238d0a4ac364f275d771da6ad2445d07ae0",
+        )
+        .unwrap();
+        let recipient = ArkAddress::new(
+            Network::Regtest,
+            server,
+            TweakedPublicKey::dangerous_assume_tweaked(server),
+        );
+
+        let receipt = PaymentReceipt::new(
+            &kp,
+            recipient,
+            Amount::from_sat(21_000),
+            OutPoint::new(Txid::all_zeros(), 1),
+            1_700_000_000,
+        );
+        receipt.verify().unwrap();
+
+        let decoded = PaymentReceipt::deserialize(&receipt.serialize()).unwrap();
+        decoded.verify().unwrap();
+        assert_eq!(decoded.serialize(), receipt.serialize());
+        assert_eq!(decoded.amount, receipt.amount);
+        assert_eq!(decoded.vtxo_outpoint, receipt.vtxo_outpoint);
+
+        let tampered = PaymentReceipt {
+            amount: Amount::from_sat(210_000),
+            ..receipt.clone()
+        };
+        assert!(tampered.verify().is_err());
+
+        let mut truncated = receipt.serialize();
+        truncated.pop();
+        assert!(PaymentReceipt::deserialize(&truncated).is_err());
+    }
+}
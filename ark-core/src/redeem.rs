@@ -1,4 +1,7 @@
 use crate::default_vtxo::DefaultVtxo;
+use crate::payment_proof::taproot_output_key;
+use crate::signing::sign_with_keypairs;
+use crate::signing::InputToSign;
 use crate::tx_weight_estimator;
 use crate::tx_weight_estimator::compute_redeem_tx_fee;
 use crate::ArkAddress;
@@ -8,20 +11,23 @@ use bitcoin::absolute::LockTime;
 use bitcoin::hashes::Hash;
 use bitcoin::key::Keypair;
 use bitcoin::key::Secp256k1;
+use bitcoin::key::TweakedPublicKey;
+use bitcoin::script::Instruction;
 use bitcoin::secp256k1;
+use bitcoin::secp256k1::schnorr;
 use bitcoin::sighash::Prevouts;
 use bitcoin::sighash::SighashCache;
-use bitcoin::taproot;
 use bitcoin::transaction;
 use bitcoin::Amount;
 use bitcoin::FeeRate;
+use bitcoin::Network;
 use bitcoin::OutPoint;
 use bitcoin::Psbt;
 use bitcoin::TapLeafHash;
-use bitcoin::TapSighashType;
 use bitcoin::Transaction;
 use bitcoin::TxIn;
 use bitcoin::TxOut;
+use bitcoin::XOnlyPublicKey;
 use std::collections::BTreeMap;
 
 /// A VTXO to be spent into an unconfirmed VTXO.
@@ -47,31 +53,179 @@ impl VtxoInput {
     }
 }
 
+/// What to do with change which would fall below [`ChangePolicy::min_change`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmallChangeHandling {
+    /// Leave the change out of the transaction, so that it goes towards the fee.
+    AbsorbIntoFee,
+    /// Add the change to the recipient's output.
+    AddToPayment,
+}
+
+/// Decides whether a redeem transaction gets a change output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChangePolicy {
+    /// The smallest change output worth creating, after fees.
+    pub min_change: Amount,
+    pub small_change: SmallChangeHandling,
+}
+
+impl Default for ChangePolicy {
+    fn default() -> Self {
+        Self {
+            min_change: Amount::ZERO,
+            small_change: SmallChangeHandling::AbsorbIntoFee,
+        }
+    }
+}
+
+/// What happened to the change of a redeem transaction, according to its [`ChangePolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeDecision {
+    /// The inputs matched the amount sent, so there was no change.
+    NoChange,
+    /// A change output with this amount was created.
+    Change(Amount),
+    /// This amount of change was too small and went towards the fee.
+    AbsorbedIntoFee(Amount),
+    /// This amount of change was too small and was added to the recipient's output.
+    AddedToPayment(Amount),
+}
+
 /// Build and sign a transaction to send VTXOs to another [`ArkAddress`].
 ///
 /// The inputs will be signed using the forfeit (multisignature) branch of the Taproot tree. Thus,
 /// the inputs will still need a signature from the Ark server.
+///
+/// Every input is signed with the keypair in `kps` which owns its VTXO.
 pub fn create_and_sign_redeem_transaction(
-    kp: &Keypair,
+    kps: &[Keypair],
     to_address: &ArkAddress,
     to_amount: Amount,
     change_address: &ArkAddress,
     vtxo_inputs: &[VtxoInput],
 ) -> Result<Psbt, Error> {
+    let (psbt, _) = create_and_sign_redeem_transaction_with_change_policy(
+        kps,
+        to_address,
+        to_amount,
+        change_address,
+        vtxo_inputs,
+        ChangePolicy::default(),
+    )?;
+
+    Ok(psbt)
+}
+
+/// Like [`create_and_sign_redeem_transaction`], but change below [`ChangePolicy::min_change`] is
+/// handled as instructed by `change_policy` instead of becoming a VTXO of its own.
+///
+/// Returns the signed transaction together with what was done with the change.
+pub fn create_and_sign_redeem_transaction_with_change_policy(
+    kps: &[Keypair],
+    to_address: &ArkAddress,
+    to_amount: Amount,
+    change_address: &ArkAddress,
+    vtxo_inputs: &[VtxoInput],
+    change_policy: ChangePolicy,
+) -> Result<(Psbt, ChangeDecision), Error> {
+    create_and_sign_batch_redeem_transaction(
+        kps,
+        &[(*to_address, to_amount)],
+        change_address,
+        vtxo_inputs,
+        change_policy,
+    )
+}
+
+/// Like [`create_and_sign_redeem_transaction_with_change_policy`], but paying every recipient in
+/// `outputs` with a single transaction.
+///
+/// The outputs are created in the order of `outputs`, followed by the change output, if any.
+/// Without change, the fee is paid by the last recipient, who also gets any small change added to
+/// the payment (see [`SmallChangeHandling::AddToPayment`]).
+pub fn create_and_sign_batch_redeem_transaction(
+    kps: &[Keypair],
+    outputs: &[(ArkAddress, Amount)],
+    change_address: &ArkAddress,
+    vtxo_inputs: &[VtxoInput],
+    change_policy: ChangePolicy,
+) -> Result<(Psbt, ChangeDecision), Error> {
+    create_and_sign_batch_redeem_transaction_with(
+        sign_with_keypairs(kps),
+        outputs,
+        change_address,
+        vtxo_inputs,
+        change_policy,
+    )
+}
+
+/// The messages to be signed by the owners of the `vtxo_inputs` of the transaction built by
+/// [`create_and_sign_batch_redeem_transaction`], together with the public key that must sign each
+/// of them.
+///
+/// Signatures for these messages can be produced ahead of calling
+/// [`create_and_sign_batch_redeem_transaction_with`], e.g. when signing is asynchronous.
+pub fn batch_redeem_transaction_sighashes(
+    outputs: &[(ArkAddress, Amount)],
+    change_address: &ArkAddress,
+    vtxo_inputs: &[VtxoInput],
+    change_policy: ChangePolicy,
+) -> Result<Vec<(XOnlyPublicKey, secp256k1::Message)>, Error> {
+    let (_, _, inputs_to_sign) =
+        build_batch_redeem_transaction(outputs, change_address, vtxo_inputs, change_policy)?;
+
+    Ok(inputs_to_sign.iter().map(InputToSign::sighash).collect())
+}
+
+/// Like [`create_and_sign_batch_redeem_transaction`], but every input is signed with
+/// `sign_for_pk_fn` instead of a keypair.
+pub fn create_and_sign_batch_redeem_transaction_with<F>(
+    sign_for_pk_fn: F,
+    outputs: &[(ArkAddress, Amount)],
+    change_address: &ArkAddress,
+    vtxo_inputs: &[VtxoInput],
+    change_policy: ChangePolicy,
+) -> Result<(Psbt, ChangeDecision), Error>
+where
+    F: Fn(&XOnlyPublicKey, &secp256k1::Message) -> Result<schnorr::Signature, Error>,
+{
+    let (mut redeem_psbt, change_decision, inputs_to_sign) =
+        build_batch_redeem_transaction(outputs, change_address, vtxo_inputs, change_policy)?;
+
+    for input in inputs_to_sign {
+        let sig = input
+            .sign(&sign_for_pk_fn)
+            .context("failed to sign redeem transaction")?;
+
+        redeem_psbt.inputs[input.index].tap_script_sigs =
+            BTreeMap::from_iter([((input.pk, input.leaf_hash), sig)]);
+    }
+
+    Ok((redeem_psbt, change_decision))
+}
+
+/// The unsigned redeem transaction, with every input ready to be signed.
+fn build_batch_redeem_transaction(
+    outputs: &[(ArkAddress, Amount)],
+    change_address: &ArkAddress,
+    vtxo_inputs: &[VtxoInput],
+    change_policy: ChangePolicy,
+) -> Result<(Psbt, ChangeDecision, Vec<InputToSign>), Error> {
     if vtxo_inputs.is_empty() {
         return Err(Error::transaction(
             "cannot create redeem transaction without inputs",
         ));
     }
 
-    let secp = Secp256k1::new();
-
-    let mut outputs = vec![TxOut {
-        value: to_amount,
-        script_pubkey: to_address.to_p2tr_script_pubkey(),
-    }];
+    if outputs.is_empty() {
+        return Err(Error::transaction(
+            "cannot create redeem transaction without outputs",
+        ));
+    }
 
     let total_amount: Amount = vtxo_inputs.iter().map(|v| v.amount).sum();
+    let to_amount: Amount = outputs.iter().map(|(_, amount)| *amount).sum();
 
     let change_amount = total_amount.checked_sub(to_amount).ok_or_else(|| {
         Error::transaction(format!(
@@ -79,49 +233,95 @@ pub fn create_and_sign_redeem_transaction(
         ))
     })?;
 
-    if change_amount > Amount::ZERO {
-        outputs.push(TxOut {
-            value: change_amount,
-            script_pubkey: change_address.to_p2tr_script_pubkey(),
-        })
-    }
-
-    let fee = {
-        let vtxos = vtxo_inputs
-            .iter()
-            .map(
-                |VtxoInput {
-                     vtxo,
-                     amount,
-                     outpoint,
-                 }| {
-                    let (script, control_block) = vtxo.forfeit_spend_info();
-
-                    tx_weight_estimator::VtxoInput {
-                        outpoint: *outpoint,
-                        amount: *amount,
-                        revealed_script: Some(script),
-                        control_block,
-                        witness_size: DefaultVtxo::FORFEIT_WITNESS_SIZE,
-                    }
-                },
-            )
-            .collect::<Vec<_>>();
+    let vtxos = vtxo_inputs
+        .iter()
+        .map(
+            |VtxoInput {
+                 vtxo,
+                 amount,
+                 outpoint,
+             }| {
+                let (script, control_block) = vtxo.forfeit_spend_info();
 
-        compute_redeem_tx_fee(
-            FeeRate::from_sat_per_kwu(253),
-            vtxos.as_slice(),
-            outputs.len(),
+                tx_weight_estimator::VtxoInput {
+                    outpoint: *outpoint,
+                    amount: *amount,
+                    revealed_script: Some(script),
+                    control_block,
+                    witness_size: DefaultVtxo::FORFEIT_WITNESS_SIZE,
+                }
+            },
         )
-        .map_err(Error::from)?
+        .collect::<Vec<_>>();
+
+    let fee = |n_outputs: usize| {
+        compute_redeem_tx_fee(FeeRate::from_sat_per_kwu(253), vtxos.as_slice(), n_outputs)
+            .map_err(Error::from)
     };
 
-    if let Some(change) = outputs.last_mut() {
-        let change_amount = change.value;
+    let n_recipients = outputs.len();
+
+    // The outputs paying every recipient, with `adjust` applied to the last one.
+    let to_outputs = |adjust: &dyn Fn(Amount) -> Result<Amount, Error>| {
+        outputs
+            .iter()
+            .enumerate()
+            .map(|(i, (address, amount))| {
+                let value = if i == n_recipients - 1 {
+                    adjust(*amount)?
+                } else {
+                    *amount
+                };
+
+                Ok(TxOut {
+                    value,
+                    script_pubkey: address.to_p2tr_script_pubkey(),
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()
+    };
 
-        change.value = change_amount.checked_sub(fee).ok_or_else(|| {
-            Error::coin_select("fee ({fee}) greater than change ({change_amount})")
+    let (outputs, change_decision) = if change_amount == Amount::ZERO {
+        // Without change, the (last) recipient pays the fee.
+        let fee = fee(n_recipients)?;
+        let outputs = to_outputs(&|amount| {
+            amount.checked_sub(fee).ok_or_else(|| {
+                Error::coin_select(format!("fee ({fee}) greater than amount ({amount})"))
+            })
         })?;
+
+        (outputs, ChangeDecision::NoChange)
+    } else {
+        match change_amount.checked_sub(fee(n_recipients + 1)?) {
+            Some(change) if change > Amount::ZERO && change >= change_policy.min_change => {
+                let change_output = TxOut {
+                    value: change,
+                    script_pubkey: change_address.to_p2tr_script_pubkey(),
+                };
+
+                let mut outputs = to_outputs(&Ok)?;
+                outputs.push(change_output);
+
+                (outputs, ChangeDecision::Change(change))
+            }
+            _ => {
+                let fee = fee(n_recipients)?;
+                let leftover = change_amount.checked_sub(fee).ok_or_else(|| {
+                    Error::coin_select(format!("fee ({fee}) greater than change ({change_amount})"))
+                })?;
+
+                match change_policy.small_change {
+                    _ if leftover == Amount::ZERO => (to_outputs(&Ok)?, ChangeDecision::NoChange),
+                    SmallChangeHandling::AbsorbIntoFee => {
+                        (to_outputs(&Ok)?, ChangeDecision::AbsorbedIntoFee(leftover))
+                    }
+                    SmallChangeHandling::AddToPayment => (
+                        to_outputs(&|amount| Ok(amount + leftover))?,
+                        ChangeDecision::AddedToPayment(leftover),
+                    ),
+                }
+            }
+        }
     };
 
     // TODO: Use a different locktime if we have CLTV multisig script.
@@ -142,8 +342,7 @@ pub fn create_and_sign_redeem_transaction(
             .collect(),
         output: outputs,
     };
-    let unsigned_psbt = Psbt::from_unsigned_tx(unsigned_tx).map_err(Error::transaction)?;
-    let mut signed_redeem_psbt = unsigned_psbt;
+    let mut redeem_psbt = Psbt::from_unsigned_tx(unsigned_tx).map_err(Error::transaction)?;
 
     let prevouts = vtxo_inputs
         .iter()
@@ -153,7 +352,8 @@ pub fn create_and_sign_redeem_transaction(
         })
         .collect::<Vec<_>>();
 
-    // Sign all redeem transaction inputs (could be multiple VTXOs!).
+    // Prepare all redeem transaction inputs (could be multiple VTXOs!) for signing.
+    let mut inputs_to_sign = Vec::new();
     for VtxoInput {
         vtxo,
         amount,
@@ -167,8 +367,8 @@ pub fn create_and_sign_redeem_transaction(
             "Attempting to sign selected VTXO for redeem transaction"
         );
 
-        for (i, psbt_input) in signed_redeem_psbt.inputs.iter_mut().enumerate() {
-            let psbt_input_outpoint = signed_redeem_psbt.unsigned_tx.input[i].previous_output;
+        for (i, psbt_input) in redeem_psbt.inputs.iter_mut().enumerate() {
+            let psbt_input_outpoint = redeem_psbt.unsigned_tx.input[i].previous_output;
 
             if psbt_input_outpoint == *outpoint {
                 tracing::debug!(
@@ -189,39 +389,514 @@ pub fn create_and_sign_redeem_transaction(
                     (forfeit_script.clone(), leaf_version),
                 )]);
 
-                let prevouts = Prevouts::All(&prevouts);
-
                 let leaf_hash = TapLeafHash::from_script(&forfeit_script, leaf_version);
 
-                let tap_sighash = SighashCache::new(&signed_redeem_psbt.unsigned_tx)
-                    .taproot_script_spend_signature_hash(
-                        i,
-                        &prevouts,
-                        leaf_hash,
-                        TapSighashType::Default,
-                    )
-                    .map_err(Error::crypto)
-                    .context("failed to generate sighash")?;
-
-                let msg =
-                    secp256k1::Message::from_digest(tap_sighash.to_raw_hash().to_byte_array());
-
-                let sig = secp.sign_schnorr_no_aux_rand(&msg, kp);
-                let pk = kp.x_only_public_key().0;
-
-                secp.verify_schnorr(&sig, &msg, &pk)
-                    .map_err(Error::crypto)
-                    .context("failed to verify own redeem signature")?;
-
-                let sig = taproot::Signature {
-                    signature: sig,
-                    sighash_type: TapSighashType::Default,
-                };
+                inputs_to_sign.push(InputToSign::new(
+                    &redeem_psbt.unsigned_tx,
+                    i,
+                    &prevouts,
+                    vtxo.owner_pk(),
+                    leaf_hash,
+                )?);
+            }
+        }
+    }
+
+    Ok((redeem_psbt, change_decision, inputs_to_sign))
+}
+
+/// Check that every input of `redeem_psbt` is signed by the Ark server with public key `server`
+/// and by the owner of the VTXO it spends, and that all the signatures are valid.
+///
+/// The signatures must be for a spend path of the VTXO, i.e. a leaf of the script tree of the
+/// spent output, proven with the control block in [`bitcoin::psbt::Input::tap_scripts`]. Every key
+/// in that leaf must have signed, and the leaf must require the Ark server's signature. Otherwise
+/// anyone could sign over a script of their choosing.
+///
+/// A redeem transaction is only final once the Ark server has cosigned it, so this is what the
+/// recipient of an out-of-round payment should check before considering the payment received.
+pub fn verify_redeem_transaction(redeem_psbt: &Psbt, server: XOnlyPublicKey) -> Result<(), Error> {
+    let secp = Secp256k1::verification_only();
+
+    let tx = &redeem_psbt.unsigned_tx;
+
+    let prevouts = redeem_psbt
+        .inputs
+        .iter()
+        .enumerate()
+        .map(|(i, input)| {
+            input.witness_utxo.clone().ok_or_else(|| {
+                Error::transaction(format!(
+                    "missing witness UTXO for input {i} of redeem transaction"
+                ))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
 
-                psbt_input.tap_script_sigs = BTreeMap::from_iter([((pk, leaf_hash), sig)]);
+    for (i, (input, prevout)) in redeem_psbt.inputs.iter().zip(prevouts.iter()).enumerate() {
+        let (leaf_hash, keys) = signed_spend_path(&secp, input, prevout)
+            .with_context(|| format!("invalid spend path for input {i} of redeem transaction"))?;
+
+        if !keys.contains(&server) {
+            return Err(Error::transaction(format!(
+                "input {i} of redeem transaction is not signed by the Ark server"
+            )));
+        }
+
+        for pk in keys.iter() {
+            let sig = input
+                .tap_script_sigs
+                .get(&(*pk, leaf_hash))
+                .ok_or_else(|| {
+                    Error::transaction(format!(
+                        "input {i} of redeem transaction is missing a signature by {pk}"
+                    ))
+                })?;
+
+            let sighash = SighashCache::new(tx)
+                .taproot_script_spend_signature_hash(
+                    i,
+                    &Prevouts::All(&prevouts),
+                    leaf_hash,
+                    sig.sighash_type,
+                )
+                .map_err(Error::crypto)?;
+            let msg = secp256k1::Message::from_digest(sighash.to_raw_hash().to_byte_array());
+
+            secp.verify_schnorr(&sig.signature, &msg, pk).map_err(|e| {
+                Error::crypto(format!(
+                    "invalid signature by {pk} on input {i} of redeem transaction: {e}"
+                ))
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The address of the VTXO spent by the first input of `redeem_psbt`, which belongs to the sender
+/// of the out-of-round payment it makes.
+///
+/// Paying this address returns the funds to the sender, e.g. to refund a payment that we do not
+/// want to accept. `server` is the public key of the Ark server, which is part of every
+/// [`ArkAddress`].
+pub fn refund_address(
+    redeem_psbt: &Psbt,
+    network: Network,
+    server: XOnlyPublicKey,
+) -> Result<ArkAddress, Error> {
+    let prevout = redeem_psbt
+        .inputs
+        .first()
+        .and_then(|input| input.witness_utxo.as_ref())
+        .ok_or_else(|| {
+            Error::transaction("missing witness UTXO for input 0 of redeem transaction")
+        })?;
+
+    if !prevout.script_pubkey.is_p2tr() {
+        return Err(Error::transaction(
+            "input 0 of redeem transaction does not spend a VTXO",
+        ));
+    }
+
+    // A P2TR script is `OP_1 <32-byte output key>`.
+    let output_key = XOnlyPublicKey::from_slice(&prevout.script_pubkey.as_bytes()[2..])
+        .map_err(Error::crypto)?;
+
+    Ok(ArkAddress::new(
+        network,
+        server,
+        TweakedPublicKey::dangerous_assume_tweaked(output_key),
+    ))
+}
+
+/// The leaf of the script tree of `prevout` which the signatures of `input` are for, and the keys
+/// in its script.
+///
+/// The leaf must be committed to by the output key of `prevout`, so that signers cannot make up
+/// their own spend path.
+fn signed_spend_path(
+    secp: &Secp256k1<secp256k1::VerifyOnly>,
+    input: &bitcoin::psbt::Input,
+    prevout: &TxOut,
+) -> Result<(TapLeafHash, Vec<XOnlyPublicKey>), Error> {
+    let output_key = taproot_output_key(prevout)?;
+
+    let mut leaf_hashes = input
+        .tap_script_sigs
+        .keys()
+        .map(|(_, leaf_hash)| *leaf_hash);
+    let leaf_hash = leaf_hashes
+        .next()
+        .ok_or_else(|| Error::transaction("input is not signed"))?;
+    if leaf_hashes.any(|other| other != leaf_hash) {
+        return Err(Error::transaction(
+            "signatures are for different spend paths",
+        ));
+    }
+
+    let script = input
+        .tap_scripts
+        .iter()
+        .find(|(_, (script, leaf_version))| {
+            TapLeafHash::from_script(script, *leaf_version) == leaf_hash
+        })
+        .filter(|(control_block, (script, _))| {
+            control_block.verify_taproot_commitment(secp, output_key, script)
+        })
+        .map(|(_, (script, _))| script)
+        .ok_or_else(|| {
+            Error::transaction(format!(
+                "signed leaf {leaf_hash} is not in the script tree of the spent output"
+            ))
+        })?;
+
+    let keys = script
+        .instructions()
+        .filter_map(|instruction| match instruction {
+            Ok(Instruction::PushBytes(bytes)) if bytes.len() == 32 => {
+                XOnlyPublicKey::from_slice(bytes.as_bytes()).ok()
             }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    Ok((leaf_hash, keys))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::secp256k1::SecretKey;
+    use bitcoin::taproot;
+    use bitcoin::Sequence;
+    use bitcoin::TapSighashType;
+    use bitcoin::Txid;
+    use std::collections::HashMap;
+
+    fn server_kp() -> Keypair {
+        Keypair::from_secret_key(&Secp256k1::new(), &SecretKey::from_slice(&[1; 32]).unwrap())
+    }
+
+    fn redeem(
+        input_amount: Amount,
+        to_amount: Amount,
+        change_policy: ChangePolicy,
+    ) -> (Psbt, ChangeDecision) {
+        let secp = Secp256k1::new();
+        let server = server_kp();
+        let owner = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[2; 32]).unwrap());
+
+        let vtxo = DefaultVtxo::new(
+            &secp,
+            server.x_only_public_key().0,
+            owner.x_only_public_key().0,
+            Sequence::from_seconds_ceil(86_400).unwrap(),
+            Network::Regtest,
+        );
+        let address = vtxo.to_ark_address();
+
+        let input = VtxoInput::new(vtxo, input_amount, OutPoint::new(Txid::all_zeros(), 0));
+
+        create_and_sign_redeem_transaction_with_change_policy(
+            &[owner],
+            &address,
+            to_amount,
+            &address,
+            &[input],
+            change_policy,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn small_change_is_not_turned_into_a_vtxo() {
+        let input_amount = Amount::from_sat(100_000);
+        let to_amount = Amount::from_sat(99_000);
+
+        let (psbt, decision) = redeem(input_amount, to_amount, ChangePolicy::default());
+        let change = match decision {
+            ChangeDecision::Change(change) => change,
+            decision => panic!("unexpected change decision: {decision:?}"),
+        };
+        assert_eq!(psbt.unsigned_tx.output.len(), 2);
+        assert_eq!(psbt.unsigned_tx.output[1].value, change);
+
+        let min_change = change + Amount::ONE_SAT;
+
+        let (psbt, decision) = redeem(
+            input_amount,
+            to_amount,
+            ChangePolicy {
+                min_change,
+                small_change: SmallChangeHandling::AbsorbIntoFee,
+            },
+        );
+        let absorbed = match decision {
+            ChangeDecision::AbsorbedIntoFee(absorbed) => absorbed,
+            decision => panic!("unexpected change decision: {decision:?}"),
+        };
+        assert_eq!(psbt.unsigned_tx.output.len(), 1);
+        assert_eq!(psbt.unsigned_tx.output[0].value, to_amount);
+        assert_eq!(psbt.fee().unwrap(), input_amount - to_amount);
+        assert!(absorbed > change);
+
+        let (psbt, decision) = redeem(
+            input_amount,
+            to_amount,
+            ChangePolicy {
+                min_change,
+                small_change: SmallChangeHandling::AddToPayment,
+            },
+        );
+        assert_eq!(decision, ChangeDecision::AddedToPayment(absorbed));
+        assert_eq!(psbt.unsigned_tx.output.len(), 1);
+        assert_eq!(psbt.unsigned_tx.output[0].value, to_amount + absorbed);
+    }
+
+    #[test]
+    fn batch_redeem_pays_every_recipient() {
+        let secp = Secp256k1::new();
+        let server = server_kp();
+        let owner = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[2; 32]).unwrap());
+
+        let address = |kp: &Keypair| {
+            DefaultVtxo::new(
+                &secp,
+                server.x_only_public_key().0,
+                kp.x_only_public_key().0,
+                Sequence::from_seconds_ceil(86_400).unwrap(),
+                Network::Regtest,
+            )
+        };
+
+        let vtxo = address(&owner);
+        let change_address = vtxo.to_ark_address();
+        let input = VtxoInput::new(
+            vtxo,
+            Amount::from_sat(100_000),
+            OutPoint::new(Txid::all_zeros(), 0),
+        );
+
+        let alice = address(&Keypair::from_secret_key(
+            &secp,
+            &SecretKey::from_slice(&[3; 32]).unwrap(),
+        ))
+        .to_ark_address();
+        let bob = address(&Keypair::from_secret_key(
+            &secp,
+            &SecretKey::from_slice(&[4; 32]).unwrap(),
+        ))
+        .to_ark_address();
+
+        let outputs = [
+            (alice, Amount::from_sat(30_000)),
+            (bob, Amount::from_sat(20_000)),
+        ];
+
+        let (psbt, decision) = create_and_sign_batch_redeem_transaction(
+            &[owner],
+            &outputs,
+            &change_address,
+            std::slice::from_ref(&input),
+            ChangePolicy::default(),
+        )
+        .unwrap();
+
+        let change = match decision {
+            ChangeDecision::Change(change) => change,
+            decision => panic!("unexpected change decision: {decision:?}"),
+        };
+
+        let tx = &psbt.unsigned_tx;
+        assert_eq!(tx.output.len(), 3);
+        for (output, (address, amount)) in tx.output.iter().zip(outputs.iter()) {
+            assert_eq!(output.script_pubkey, address.to_p2tr_script_pubkey());
+            assert_eq!(output.value, *amount);
         }
+        assert_eq!(
+            tx.output[2].script_pubkey,
+            change_address.to_p2tr_script_pubkey()
+        );
+        assert_eq!(tx.output[2].value, change);
+
+        // Spending everything leaves no change, and the last recipient pays the fee.
+        let outputs = [
+            (alice, Amount::from_sat(60_000)),
+            (bob, Amount::from_sat(40_000)),
+        ];
+
+        let (psbt, decision) = create_and_sign_batch_redeem_transaction(
+            &[owner],
+            &outputs,
+            &change_address,
+            &[input],
+            ChangePolicy::default(),
+        )
+        .unwrap();
+
+        assert_eq!(decision, ChangeDecision::NoChange);
+
+        let tx = &psbt.unsigned_tx;
+        assert_eq!(tx.output.len(), 2);
+        assert_eq!(tx.output[0].value, Amount::from_sat(60_000));
+        assert_eq!(
+            tx.output[1].value,
+            Amount::from_sat(40_000) - psbt.fee().unwrap()
+        );
     }
 
-    Ok(signed_redeem_psbt)
+    #[test]
+    fn redeem_transaction_can_be_signed_ahead_of_time() {
+        let secp = Secp256k1::new();
+        let server = server_kp();
+        let owner = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[2; 32]).unwrap());
+
+        let vtxo = DefaultVtxo::new(
+            &secp,
+            server.x_only_public_key().0,
+            owner.x_only_public_key().0,
+            Sequence::from_seconds_ceil(86_400).unwrap(),
+            Network::Regtest,
+        );
+        let address = vtxo.to_ark_address();
+        let inputs = [VtxoInput::new(
+            vtxo,
+            Amount::from_sat(100_000),
+            OutPoint::new(Txid::all_zeros(), 0),
+        )];
+        let outputs = [(address, Amount::from_sat(50_000))];
+
+        let sighashes = batch_redeem_transaction_sighashes(
+            &outputs,
+            &address,
+            &inputs,
+            ChangePolicy::default(),
+        )
+        .unwrap();
+        assert_eq!(sighashes.len(), 1);
+        assert_eq!(sighashes[0].0, owner.x_only_public_key().0);
+
+        let sigs = sighashes
+            .iter()
+            .map(|(pk, msg)| ((*pk, *msg), secp.sign_schnorr_no_aux_rand(msg, &owner)))
+            .collect::<HashMap<_, _>>();
+
+        let (psbt, _) = create_and_sign_batch_redeem_transaction_with(
+            |pk, msg| {
+                sigs.get(&(*pk, *msg))
+                    .copied()
+                    .ok_or_else(|| Error::ad_hoc("missing signature"))
+            },
+            &outputs,
+            &address,
+            &inputs,
+            ChangePolicy::default(),
+        )
+        .unwrap();
+
+        let (expected, _) = create_and_sign_batch_redeem_transaction(
+            &[owner],
+            &outputs,
+            &address,
+            &inputs,
+            ChangePolicy::default(),
+        )
+        .unwrap();
+        assert_eq!(psbt, expected);
+
+        // A signature by the wrong key is rejected.
+        let other = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[3; 32]).unwrap());
+        let res = create_and_sign_batch_redeem_transaction_with(
+            |_, msg| Ok(secp.sign_schnorr_no_aux_rand(msg, &other)),
+            &outputs,
+            &address,
+            &inputs,
+            ChangePolicy::default(),
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn refund_address_pays_the_spent_vtxo() {
+        let secp = Secp256k1::new();
+        let server = server_kp();
+        let sender = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[2; 32]).unwrap());
+
+        let vtxo = DefaultVtxo::new(
+            &secp,
+            server.x_only_public_key().0,
+            sender.x_only_public_key().0,
+            Sequence::from_seconds_ceil(86_400).unwrap(),
+            Network::Regtest,
+        );
+        let sender_address = vtxo.to_ark_address();
+
+        let (psbt, _) = redeem(
+            Amount::from_sat(100_000),
+            Amount::from_sat(50_000),
+            ChangePolicy::default(),
+        );
+
+        let address =
+            refund_address(&psbt, Network::Regtest, server.x_only_public_key().0).unwrap();
+        assert_eq!(address.encode(), sender_address.encode());
+
+        let mut psbt = psbt;
+        psbt.inputs[0].witness_utxo = None;
+        assert!(refund_address(&psbt, Network::Regtest, server.x_only_public_key().0).is_err());
+    }
+
+    #[test]
+    fn redeem_transaction_must_be_cosigned_by_server() {
+        let server = server_kp();
+        let (xonly_server, _) = server.x_only_public_key();
+
+        let (mut psbt, _) = redeem(
+            Amount::from_sat(100_000),
+            Amount::from_sat(50_000),
+            ChangePolicy::default(),
+        );
+
+        assert!(verify_redeem_transaction(&psbt, xonly_server).is_err());
+
+        let secp = Secp256k1::new();
+        let prevouts = psbt
+            .inputs
+            .iter()
+            .map(|input| input.witness_utxo.clone().unwrap())
+            .collect::<Vec<_>>();
+        let leaf_hash = *psbt.inputs[0]
+            .tap_script_sigs
+            .keys()
+            .next()
+            .map(|(_, leaf_hash)| leaf_hash)
+            .unwrap();
+
+        let sighash = SighashCache::new(&psbt.unsigned_tx)
+            .taproot_script_spend_signature_hash(
+                0,
+                &Prevouts::All(&prevouts),
+                leaf_hash,
+                TapSighashType::Default,
+            )
+            .unwrap();
+        let msg = secp256k1::Message::from_digest(sighash.to_raw_hash().to_byte_array());
+        let sig = secp.sign_schnorr_no_aux_rand(&msg, &server);
+
+        psbt.inputs[0].tap_script_sigs.insert(
+            (xonly_server, leaf_hash),
+            taproot::Signature {
+                signature: sig,
+                sighash_type: TapSighashType::Default,
+            },
+        );
+
+        verify_redeem_transaction(&psbt, xonly_server).unwrap();
+
+        // Tampering with the transaction invalidates the signatures.
+        psbt.unsigned_tx.output[0].value = Amount::from_sat(60_000);
+
+        assert!(verify_redeem_transaction(&psbt, xonly_server).is_err());
+    }
 }
@@ -1,9 +1,11 @@
-use crate::conversions::from_zkp_xonly;
-use crate::conversions::to_zkp_pk;
 use crate::forfeit_fee::compute_forfeit_min_relay_fee;
 use crate::internal_node::VtxoTreeInternalNodeScript;
+use crate::musig;
 use crate::server::TxTree;
+use crate::server::TxTreeLevel;
 use crate::server::TxTreeNode;
+use crate::signing::sign_with_keypairs;
+use crate::signing::InputToSign;
 use crate::BoardingOutput;
 use crate::DefaultVtxo;
 use crate::Error;
@@ -24,31 +26,30 @@ use bitcoin::Address;
 use bitcoin::Amount;
 use bitcoin::OutPoint;
 use bitcoin::Psbt;
+use bitcoin::ScriptBuf;
 use bitcoin::TapLeafHash;
 use bitcoin::TapSighashType;
 use bitcoin::Transaction;
 use bitcoin::TxIn;
 use bitcoin::TxOut;
+use bitcoin::Txid;
 use bitcoin::XOnlyPublicKey;
 use rand::CryptoRng;
 use rand::Rng;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
-use zkp::new_musig_nonce_pair;
 use zkp::MusigAggNonce;
 use zkp::MusigKeyAggCache;
 use zkp::MusigPartialSignature;
 use zkp::MusigPubNonce;
 use zkp::MusigSecNonce;
-use zkp::MusigSession;
-use zkp::MusigSessionId;
 
 /// The cosigner PKs that sign a VTXO TX input are included in the `unknown` key-value map field of
 /// that input in the VTXO PSBT. Since the `unknown` field can be used for any purpose, we know that
 /// a value is a cosigner PK if the corresponding key starts with this prefix.
 ///
 /// The byte value corresponds to the string "cosigner".
-const COSIGNER_PSBT_KEY_PREFIX: [u8; 8] = [111, 115, 105, 103, 110, 101, 114, 0];
+pub(crate) const COSIGNER_PSBT_KEY_PREFIX: [u8; 8] = [111, 115, 105, 103, 110, 101, 114, 0];
 
 /// A UTXO that is primed to become a VTXO. Alternatively, the owner of this UTXO may decide to
 /// spend it into a vanilla UTXO.
@@ -56,19 +57,19 @@ const COSIGNER_PSBT_KEY_PREFIX: [u8; 8] = [111, 115, 105, 103, 110, 101, 114, 0]
 /// Only UTXOs with a particular script (involving an Ark server) can become VTXOs.
 #[derive(Debug, Clone)]
 pub struct OnChainInput {
-    /// The information needed to spend the UTXO.
-    ///
-    /// This does not include the amount, because the Ark server will provide that during the
-    /// process of signing the round transaction where this UTXO is used as an input.
+    /// The information needed to spend the UTXO, besides the amount.
     boarding_output: BoardingOutput,
+    /// The amount of coins locked in the UTXO.
+    amount: Amount,
     /// The location of this UTXO in the blockchain.
     outpoint: OutPoint,
 }
 
 impl OnChainInput {
-    pub fn new(boarding_output: BoardingOutput, outpoint: OutPoint) -> Self {
+    pub fn new(boarding_output: BoardingOutput, amount: Amount, outpoint: OutPoint) -> Self {
         Self {
             boarding_output,
+            amount,
             outpoint,
         }
     }
@@ -77,6 +78,10 @@ impl OnChainInput {
         &self.boarding_output
     }
 
+    pub fn amount(&self) -> Amount {
+        self.amount
+    }
+
     pub fn outpoint(&self) -> OutPoint {
         self.outpoint
     }
@@ -96,6 +101,11 @@ pub struct VtxoInput {
     amount: Amount,
     /// Where the VTXO would end up on the blockchain if it were to become a UTXO.
     outpoint: OutPoint,
+    /// Whether the VTXO was swept by the Ark server after expiring.
+    ///
+    /// A recoverable VTXO is settled without a forfeit transaction, since the Ark server already
+    /// controls the corresponding coins on the blockchain.
+    is_recoverable: bool,
 }
 
 impl VtxoInput {
@@ -104,13 +114,32 @@ impl VtxoInput {
             vtxo,
             amount,
             outpoint,
+            is_recoverable: false,
+        }
+    }
+
+    /// Build a [`VtxoInput`] for a VTXO which was swept by the Ark server.
+    pub fn new_recoverable(vtxo: DefaultVtxo, amount: Amount, outpoint: OutPoint) -> Self {
+        Self {
+            vtxo,
+            amount,
+            outpoint,
+            is_recoverable: true,
         }
     }
 
+    pub fn is_recoverable(&self) -> bool {
+        self.is_recoverable
+    }
+
     pub fn outpoint(&self) -> OutPoint {
         self.outpoint
     }
 
+    pub fn amount(&self) -> Amount {
+        self.amount
+    }
+
     pub fn vtxo(&self) -> &DefaultVtxo {
         &self.vtxo
     }
@@ -183,6 +212,257 @@ impl From<Vec<Vec<Option<MusigPubNonce>>>> for PubNonceTree {
     }
 }
 
+/// The keys that cosign a node of the VTXO tree, as announced by the Ark server in the PSBT of the
+/// node.
+///
+/// The output spent by the node is locked by the MuSig2 aggregate of these keys, tweaked with the
+/// sweep leaf of the Ark server (see [`NodeCosigners::output_script`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeCosigners {
+    /// Sorted by their serialization, which is the order in which they are aggregated.
+    cosigner_pks: Vec<PublicKey>,
+    aggregate_pk: XOnlyPublicKey,
+}
+
+impl NodeCosigners {
+    /// Parse the cosigner PKs of the VTXO tree node `psbt`.
+    ///
+    /// Fails if the node has no cosigners.
+    pub fn from_psbt(psbt: &Psbt) -> Result<Self, Error> {
+        let mut cosigner_pks = extract_cosigner_pks_from_vtxo_psbt(psbt)?;
+        if cosigner_pks.is_empty() {
+            return Err(Error::crypto(format!(
+                "VTXO tree node {} has no cosigners",
+                psbt.unsigned_tx.compute_txid()
+            )));
+        }
+
+        cosigner_pks.sort_by_key(|k| k.serialize());
+        cosigner_pks.dedup();
+
+        let secp_zkp = zkp::Secp256k1::verification_only();
+        let aggregate_pk = musig::aggregate_pk(&musig::key_agg_cache(&secp_zkp, &cosigner_pks));
+
+        Ok(Self {
+            cosigner_pks,
+            aggregate_pk,
+        })
+    }
+
+    pub fn cosigner_pks(&self) -> &[PublicKey] {
+        &self.cosigner_pks
+    }
+
+    /// The MuSig2 aggregate of the cosigner PKs, before the taproot tweak.
+    pub fn aggregate_pk(&self) -> XOnlyPublicKey {
+        self.aggregate_pk
+    }
+
+    pub fn contains(&self, pk: &PublicKey) -> bool {
+        self.cosigner_pks.contains(pk)
+    }
+
+    /// The script of the output spent by the node: the aggregate PK, tweaked with the leaf that
+    /// lets the Ark server sweep the output once `vtxo_tree_expiry` has passed.
+    pub fn output_script(
+        &self,
+        vtxo_tree_expiry: bitcoin::Sequence,
+        server_pk: XOnlyPublicKey,
+    ) -> ScriptBuf {
+        let internal_node_script = VtxoTreeInternalNodeScript::new(vtxo_tree_expiry, server_pk);
+
+        self.internal_node_output_script(&internal_node_script)
+    }
+
+    /// Check that `prevout`, the output spent by the node, is locked by the tweaked aggregate key
+    /// of the cosigners.
+    ///
+    /// If it is not, the partial signatures of the cosigners cannot be aggregated into a valid
+    /// signature for the node.
+    fn verify_prevout(
+        &self,
+        internal_node_script: &VtxoTreeInternalNodeScript,
+        prevout: &TxOut,
+    ) -> Result<(), Error> {
+        if prevout.script_pubkey != self.internal_node_output_script(internal_node_script) {
+            return Err(Error::crypto(format!(
+                "output spent by VTXO tree node is not locked by the aggregate key {} of its \
+                 cosigners",
+                self.aggregate_pk
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Check that the node, which spends `prevout`, cannot be signed without the Ark server:
+    /// `server_pk` must be one of its cosigners, and `prevout` must be locked by their aggregate
+    /// key, tweaked with the sweep leaf of the Ark server.
+    ///
+    /// A valid key-spend signature on such a node proves that the Ark server cosigned it.
+    pub(crate) fn verify_cosigned_by_server(
+        &self,
+        internal_node_script: &VtxoTreeInternalNodeScript,
+        server_pk: XOnlyPublicKey,
+        prevout: &TxOut,
+    ) -> Result<(), Error> {
+        let is_cosigner = self
+            .cosigner_pks
+            .iter()
+            .any(|pk| pk.x_only_public_key().0 == server_pk);
+        if !is_cosigner {
+            return Err(Error::crypto(format!(
+                "Ark server {server_pk} is not a cosigner of VTXO tree node"
+            )));
+        }
+
+        self.verify_prevout(internal_node_script, prevout)
+    }
+
+    fn internal_node_output_script(
+        &self,
+        internal_node_script: &VtxoTreeInternalNodeScript,
+    ) -> ScriptBuf {
+        let secp = Secp256k1::verification_only();
+        let output_key = internal_node_script
+            .sweep_spend_leaf(&secp, self.aggregate_pk)
+            .output_key();
+
+        ScriptBuf::new_p2tr_tweaked(output_key)
+    }
+}
+
+/// The [`NodeCosigners`] of every node of `vtxo_tree`, level by level.
+pub fn vtxo_tree_cosigners(vtxo_tree: &TxTree) -> Result<Vec<Vec<NodeCosigners>>, Error> {
+    vtxo_tree
+        .levels
+        .iter()
+        .map(|level| {
+            level
+                .nodes
+                .iter()
+                .map(|node| {
+                    NodeCosigners::from_psbt(&node.tx)
+                        .with_context(|| format!("invalid cosigners for node {}", node.txid))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Limits on the VTXO trees that we are willing to cosign, see [`verify_vtxo_tree`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VtxoTreeLimits {
+    /// The maximum number of levels of the tree, i.e. of transactions to broadcast to exit with
+    /// one of its VTXOs.
+    pub max_depth: usize,
+    /// The maximum fee paid by a single transaction of the tree.
+    pub max_fee_per_tx: Amount,
+}
+
+impl Default for VtxoTreeLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 32,
+            max_fee_per_tx: Amount::from_sat(10_000),
+        }
+    }
+}
+
+/// Check the VTXO tree proposed by the Ark server for `round_tx` before cosigning it.
+///
+/// The following is checked:
+///
+/// - The tree is rooted in `round_tx`, every transaction spends a single output of its parent, no
+///   output is spent twice and no transaction spends more than it receives.
+/// - Every output spent by a transaction of the tree is locked by the aggregate key of the
+///   cosigners of that transaction, tweaked with the sweep leaf of `server_pk` after
+///   `vtxo_tree_expiry`.
+/// - The tree is no deeper, and none of its transactions pays a higher fee, than allowed by
+///   `limits`.
+/// - Every one of `our_vtxos`, given as script and amount, is paid by a distinct leaf output.
+///
+/// Our VTXO scripts commit to the server key and the exit delay, so a leaf paying to them
+/// encodes the expected exit path.
+pub fn verify_vtxo_tree(
+    round_tx: &Psbt,
+    vtxo_tree: &TxTree,
+    vtxo_tree_expiry: bitcoin::Sequence,
+    server_pk: XOnlyPublicKey,
+    our_vtxos: &[(ScriptBuf, Amount)],
+    limits: VtxoTreeLimits,
+) -> Result<(), Error> {
+    let depth = vtxo_tree.levels.len();
+    if depth == 0 {
+        return Err(Error::ad_hoc("VTXO tree is empty"));
+    }
+    if depth > limits.max_depth {
+        return Err(Error::ad_hoc(format!(
+            "VTXO tree has {depth} levels, more than the allowed {}",
+            limits.max_depth
+        )));
+    }
+
+    let round_tx = &round_tx.unsigned_tx;
+    let leaves = crate::audit::verify_tree(vtxo_tree, round_tx, None)?;
+
+    let internal_node_script = VtxoTreeInternalNodeScript::new(vtxo_tree_expiry, server_pk);
+
+    let mut txs = HashMap::from([(round_tx.compute_txid(), round_tx)]);
+    for level in vtxo_tree.levels.iter() {
+        for node in level.nodes.iter() {
+            let tx = &node.tx.unsigned_tx;
+
+            // The links between the transactions were checked above.
+            let previous_output = tx.input[VTXO_INPUT_INDEX].previous_output;
+            let prevout = &txs[&previous_output.txid].output[previous_output.vout as usize];
+
+            NodeCosigners::from_psbt(&node.tx)?
+                .verify_prevout(&internal_node_script, prevout)
+                .with_context(|| format!("invalid VTXO tree node {}", node.txid))?;
+
+            let output_value = tx.output.iter().map(|output| output.value).sum::<Amount>();
+            let fee = prevout.value - output_value;
+            if fee > limits.max_fee_per_tx {
+                return Err(Error::ad_hoc(format!(
+                    "VTXO tree node {} pays a fee of {fee}, more than the allowed {}",
+                    node.txid, limits.max_fee_per_tx
+                )));
+            }
+        }
+
+        txs.extend(
+            level
+                .nodes
+                .iter()
+                .map(|node| (node.txid, &node.tx.unsigned_tx)),
+        );
+    }
+
+    let mut leaf_outputs = leaves
+        .iter()
+        .map(|leaf| Some(&txs[&leaf.txid].output[leaf.vout as usize]))
+        .collect::<Vec<_>>();
+    for (script_pubkey, amount) in our_vtxos {
+        let leaf_output = leaf_outputs.iter_mut().find(|output| {
+            output.is_some_and(|output| {
+                output.script_pubkey == *script_pubkey && output.value == *amount
+            })
+        });
+
+        match leaf_output {
+            Some(leaf_output) => *leaf_output = None,
+            None => {
+                return Err(Error::ad_hoc(format!(
+                    "VTXO tree does not pay {amount} to our output {script_pubkey}"
+                )))
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /// Generate a nonce pair for each internal (non-leaf) node in the VTXO tree.
 pub fn generate_nonce_tree<R>(
     rng: &mut R,
@@ -202,27 +482,14 @@ where
                 .nodes
                 .iter()
                 .map(|node| {
-                    let cosigner_pks = extract_cosigner_pks_from_vtxo_psbt(&node.tx)?;
+                    let cosigners = NodeCosigners::from_psbt(&node.tx)?;
 
-                    if !cosigner_pks.contains(&own_cosigner_pk) {
+                    if !cosigners.contains(&own_cosigner_pk) {
                         return Ok(None);
                     }
 
-                    let session_id = MusigSessionId::new(rng);
-                    let extra_rand = rng.gen();
-
-                    // TODO: Revisit nonce generation, because this is something
-                    // that we could mess up in a non-obvious way.
-                    let (nonce, pub_nonce) = new_musig_nonce_pair(
-                        &secp_zkp,
-                        session_id,
-                        None,
-                        None,
-                        to_zkp_pk(own_cosigner_pk),
-                        None,
-                        Some(extra_rand),
-                    )
-                    .map_err(Error::crypto)?;
+                    let (nonce, pub_nonce) =
+                        musig::generate_nonce_pair(rng, &secp_zkp, own_cosigner_pk)?;
 
                     Ok(Some((Some(nonce), pub_nonce)))
                 })
@@ -262,110 +529,472 @@ pub fn sign_vtxo_tree(
     let secp = Secp256k1::new();
     let secp_zkp = zkp::Secp256k1::new();
 
-    let own_cosigner_kp =
-        zkp::Keypair::from_seckey_slice(&secp_zkp, &own_cosigner_kp.secret_bytes())
-            .expect("valid keypair");
-
     let mut partial_sig_tree: Vec<Vec<Option<MusigPartialSignature>>> = Vec::new();
     for (i, level) in vtxo_tree.levels.iter().enumerate() {
         let mut sigs_level = Vec::new();
         for (j, node) in level.nodes.iter().enumerate() {
-            let mut cosigner_pks = extract_cosigner_pks_from_vtxo_psbt(&node.tx)?;
-            cosigner_pks.sort_by_key(|k| k.serialize());
+            let cosigners = NodeCosigners::from_psbt(&node.tx)?;
 
-            if !cosigner_pks.contains(&own_cosigner_pk) {
+            if !cosigners.contains(&own_cosigner_pk) {
                 sigs_level.push(None);
                 continue;
             }
 
             tracing::debug!(i, j, ?node, "Generating partial signature");
 
-            let mut key_agg_cache = {
-                let cosigner_pks = cosigner_pks
-                    .iter()
-                    .map(|pk| to_zkp_pk(*pk))
-                    .collect::<Vec<_>>();
-                MusigKeyAggCache::new(&secp_zkp, &cosigner_pks)
-            };
+            let tx = &node.tx.unsigned_tx;
 
-            let sweep_tap_tree = internal_node_script
-                .sweep_spend_leaf(&secp, from_zkp_xonly(key_agg_cache.agg_pk()));
+            // We expect a single input to a VTXO.
+            let parent_txid = node.parent_txid;
 
-            let tweak = zkp::SecretKey::from_slice(sweep_tap_tree.tap_tweak().as_byte_array())
-                .expect("valid conversion");
+            let input_vout = tx.input[VTXO_INPUT_INDEX].previous_output.vout as usize;
 
-            key_agg_cache
-                .pubkey_xonly_tweak_add(&secp_zkp, tweak)
-                .map_err(Error::crypto)?;
+            let prevout = if i == 0 {
+                round_tx.unsigned_tx.output.get(input_vout)
+            } else {
+                vtxo_tree.levels[i - 1]
+                    .nodes
+                    .iter()
+                    .find(|node| node.txid == parent_txid)
+                    .and_then(|node| node.tx.unsigned_tx.output.get(input_vout))
+            }
+            .ok_or_else(|| Error::crypto(format!("missing parent for VTXO {i}, {j}")))?;
+
+            let (key_agg_cache, msg) = vtxo_tree_node_sighash(
+                &secp,
+                &secp_zkp,
+                &internal_node_script,
+                &cosigners,
+                tx,
+                prevout.clone(),
+            )
+            .with_context(|| format!("failed to sign VTXO tree node {}", node.txid))?;
 
             let agg_pub_nonce = aggregate_pub_nonce_tree
                 .get(i, j)
                 .ok_or_else(|| Error::crypto(format!("missing pub nonce {i}, {j}")))?;
 
-            // Equivalent to parsing the individual `MusigAggNonce` from a slice.
-            let agg_nonce = MusigAggNonce::new(&secp_zkp, &[agg_pub_nonce]);
+            let nonce_sk = our_nonce_tree
+                .take_sk(i, j)
+                .ok_or(Error::crypto("missing nonce {i}, {j}"))?;
+
+            let sig = partial_sign(
+                &secp_zkp,
+                own_cosigner_kp,
+                &key_agg_cache,
+                msg,
+                nonce_sk,
+                agg_pub_nonce,
+            )?;
+
+            sigs_level.push(Some(sig));
+        }
+
+        partial_sig_tree.push(sigs_level);
+    }
+
+    Ok(PartialSigTree(partial_sig_tree))
+}
+
+/// Our part in signing the VTXO tree, built up one level of the tree at a time.
+///
+/// Using [`generate_nonce_tree`] and [`sign_vtxo_tree`] requires keeping the whole VTXO tree and
+/// the round transaction in memory between the two signing steps of a round. Instead, a
+/// [`VtxoTreeSigningSession`] computes the message to sign as soon as it sees a node that we
+/// cosign, and only keeps that message, the aggregated key and our nonce. Each level of the tree
+/// can be dropped after passing it to [`VtxoTreeSigningSession::add_level`], since only the
+/// outputs of the last level are needed to process the next one.
+///
+/// Memory use between the two signing steps is therefore proportional to the number of nodes we
+/// cosign, usually the depth of the tree, rather than to the size of the tree. The
+/// `vtxo_tree_signing` benchmark of this crate measures both approaches.
+///
+/// The session signs with our cosigner keypair, unless it was created with
+/// [`VtxoTreeSigningSession::new_external`] for a key held elsewhere, e.g. by a hardware wallet.
+/// The nonces and partial signatures of such a key are then produced outside the session for every
+/// node in [`VtxoTreeSigningSession::nodes`], and handed back with
+/// [`VtxoTreeSigningSession::set_pub_nonce`] and [`VtxoTreeSigningSession::partial_sig_tree`].
+pub struct VtxoTreeSigningSession {
+    own_cosigner_pk: PublicKey,
+    /// `None` if the cosigner key is held outside the session.
+    own_cosigner_kp: Option<Keypair>,
+    internal_node_script: VtxoTreeInternalNodeScript,
+    secp: Secp256k1<secp256k1::All>,
+    secp_zkp: zkp::Secp256k1<zkp::All>,
+    /// The outputs of the round transaction, spent by the first level of the tree.
+    round_outputs: Vec<TxOut>,
+    /// The outputs of every node in the last level added, spent by the next level of the tree.
+    parent_outputs: HashMap<Txid, Vec<TxOut>>,
+    /// The number of nodes in every level added so far.
+    level_sizes: Vec<usize>,
+    nodes: Vec<SigningNode>,
+}
+
+/// A node of the VTXO tree that we cosign, ready to be signed once the aggregate nonce is known.
+struct SigningNode {
+    level: usize,
+    branch: usize,
+    key_agg_cache: MusigKeyAggCache,
+    msg: secp256k1::Message,
+    /// `None` if the cosigner key is held outside the session.
+    sec_nonce: Option<MusigSecNonce>,
+    /// `None` until set with [`VtxoTreeSigningSession::set_pub_nonce`] if the cosigner key is held
+    /// outside the session.
+    pub_nonce: Option<MusigPubNonce>,
+}
+
+/// A node of the VTXO tree that we cosign, see [`VtxoTreeSigningSession::nodes`].
+#[derive(Debug, Clone)]
+pub struct CosignedNode {
+    pub level: usize,
+    pub branch: usize,
+    /// The key aggregation cache of the cosigners of the node, tweaked for the key spend.
+    pub key_agg_cache: MusigKeyAggCache,
+    /// The key spend sighash of the node.
+    pub msg: secp256k1::Message,
+}
+
+impl VtxoTreeSigningSession {
+    pub fn new(
+        vtxo_tree_expiry: bitcoin::Sequence,
+        server_pk: XOnlyPublicKey,
+        own_cosigner_kp: &Keypair,
+        round_tx: &Psbt,
+    ) -> Self {
+        Self {
+            own_cosigner_pk: own_cosigner_kp.public_key(),
+            own_cosigner_kp: Some(*own_cosigner_kp),
+            internal_node_script: VtxoTreeInternalNodeScript::new(vtxo_tree_expiry, server_pk),
+            secp: Secp256k1::new(),
+            secp_zkp: zkp::Secp256k1::new(),
+            round_outputs: round_tx.unsigned_tx.output.clone(),
+            parent_outputs: HashMap::new(),
+            level_sizes: Vec::new(),
+            nodes: Vec::new(),
+        }
+    }
+
+    /// A session for the cosigner key `own_cosigner_pk`, whose secret key is held elsewhere.
+    ///
+    /// No nonces are generated: they must be set with [`VtxoTreeSigningSession::set_pub_nonce`].
+    pub fn new_external(
+        vtxo_tree_expiry: bitcoin::Sequence,
+        server_pk: XOnlyPublicKey,
+        own_cosigner_pk: PublicKey,
+        round_tx: &Psbt,
+    ) -> Self {
+        Self {
+            own_cosigner_pk,
+            own_cosigner_kp: None,
+            internal_node_script: VtxoTreeInternalNodeScript::new(vtxo_tree_expiry, server_pk),
+            secp: Secp256k1::new(),
+            secp_zkp: zkp::Secp256k1::new(),
+            round_outputs: round_tx.unsigned_tx.output.clone(),
+            parent_outputs: HashMap::new(),
+            level_sizes: Vec::new(),
+            nodes: Vec::new(),
+        }
+    }
+
+    pub fn own_cosigner_pk(&self) -> PublicKey {
+        self.own_cosigner_pk
+    }
+
+    /// Process the next level of the VTXO tree, starting from the root, generating a nonce pair
+    /// for every node that we cosign, unless the cosigner key is held outside the session.
+    pub fn add_level<R>(&mut self, rng: &mut R, level: &TxTreeLevel) -> Result<(), Error>
+    where
+        R: Rng + CryptoRng,
+    {
+        let i = self.level_sizes.len();
 
+        let mut level_outputs = HashMap::with_capacity(level.nodes.len());
+        for (j, node) in level.nodes.iter().enumerate() {
             let tx = &node.tx.unsigned_tx;
 
-            // We expect a single input to a VTXO.
-            let parent_txid = node.parent_txid;
+            level_outputs.insert(node.txid, tx.output.clone());
+
+            let cosigners = NodeCosigners::from_psbt(&node.tx)?;
+            if !cosigners.contains(&self.own_cosigner_pk) {
+                continue;
+            }
 
             let input_vout = tx.input[VTXO_INPUT_INDEX].previous_output.vout as usize;
 
             let prevout = if i == 0 {
-                round_tx.clone().unsigned_tx.output[input_vout].clone()
+                self.round_outputs.get(input_vout)
             } else {
-                let parent_level = &vtxo_tree.levels[i - 1];
-                let parent_tx = parent_level
-                    .nodes
-                    .iter()
-                    .find_map(|node| {
-                        (node.txid == parent_txid).then_some(node.tx.unsigned_tx.clone())
-                    })
-                    .ok_or(Error::crypto("missing parent for VTXO {i}, {j}"))?;
+                self.parent_outputs
+                    .get(&node.parent_txid)
+                    .and_then(|outputs| outputs.get(input_vout))
+            }
+            .ok_or_else(|| Error::crypto(format!("missing parent for VTXO {i}, {j}")))?;
+
+            let (key_agg_cache, msg) = vtxo_tree_node_sighash(
+                &self.secp,
+                &self.secp_zkp,
+                &self.internal_node_script,
+                &cosigners,
+                tx,
+                prevout.clone(),
+            )
+            .with_context(|| format!("failed to sign VTXO tree node {}", node.txid))?;
 
-                parent_tx.output[input_vout].clone()
+            let (sec_nonce, pub_nonce) = match self.own_cosigner_kp {
+                Some(_) => {
+                    let (sec_nonce, pub_nonce) =
+                        musig::generate_nonce_pair(rng, &self.secp_zkp, self.own_cosigner_pk)?;
+
+                    (Some(sec_nonce), Some(pub_nonce))
+                }
+                None => (None, None),
             };
 
-            let prevouts = [prevout];
-            let prevouts = Prevouts::All(&prevouts);
-
-            // Here we are generating a key spend sighash, because the VTXO tree outputs are signed
-            // by all parties with a VTXO in this new round, so we use a musig key spend to
-            // efficiently coordinate all the parties.
-            let tap_sighash = SighashCache::new(tx)
-                .taproot_key_spend_signature_hash(
-                    VTXO_INPUT_INDEX,
-                    &prevouts,
-                    TapSighashType::Default,
-                )
-                .map_err(Error::crypto)?;
+            self.nodes.push(SigningNode {
+                level: i,
+                branch: j,
+                key_agg_cache,
+                msg,
+                sec_nonce,
+                pub_nonce,
+            });
+        }
 
-            let msg = zkp::Message::from_digest(tap_sighash.to_raw_hash().to_byte_array());
+        // Every one of our VTXOs is below the root of the tree, so the root must be ours to
+        // cosign.
+        if i == 0 && self.nodes.is_empty() {
+            return Err(Error::crypto(format!(
+                "own cosigner PK {} is not a cosigner of the VTXO tree root",
+                self.own_cosigner_pk
+            )));
+        }
 
-            let nonce_sk = our_nonce_tree
-                .take_sk(i, j)
-                .ok_or(Error::crypto("missing nonce {i}, {j}"))?;
+        self.round_outputs = Vec::new();
+        self.parent_outputs = level_outputs;
+        self.level_sizes.push(level.nodes.len());
 
-            let sig = MusigSession::new(&secp_zkp, &key_agg_cache, agg_nonce, msg)
-                .partial_sign(&secp_zkp, nonce_sk, &own_cosigner_kp, &key_agg_cache)
-                .map_err(Error::crypto)?;
+        Ok(())
+    }
 
-            sigs_level.push(Some(sig));
+    /// Our public nonces for the levels added so far, to be submitted to the Ark server.
+    pub fn pub_nonce_tree(&self) -> PubNonceTree {
+        let mut pub_nonce_tree = self
+            .level_sizes
+            .iter()
+            .map(|size| vec![None; *size])
+            .collect::<Vec<_>>();
+
+        for node in self.nodes.iter() {
+            pub_nonce_tree[node.level][node.branch] = node.pub_nonce;
         }
 
-        partial_sig_tree.push(sigs_level);
+        PubNonceTree(pub_nonce_tree)
     }
 
-    Ok(PartialSigTree(partial_sig_tree))
+    /// The nodes that we cosign in the levels added so far.
+    pub fn nodes(&self) -> Vec<CosignedNode> {
+        self.nodes
+            .iter()
+            .map(|node| CosignedNode {
+                level: node.level,
+                branch: node.branch,
+                key_agg_cache: node.key_agg_cache.clone(),
+                msg: node.msg,
+            })
+            .collect()
+    }
+
+    /// Set the public nonce generated outside the session for the node at `level` and `branch`.
+    pub fn set_pub_nonce(
+        &mut self,
+        level: usize,
+        branch: usize,
+        pub_nonce: MusigPubNonce,
+    ) -> Result<(), Error> {
+        let node = self.node_mut(level, branch)?;
+
+        if node.sec_nonce.is_some() {
+            return Err(Error::crypto(format!(
+                "nonce of VTXO tree node {level}, {branch} was generated by the session"
+            )));
+        }
+
+        node.pub_nonce = Some(pub_nonce);
+
+        Ok(())
+    }
+
+    /// The aggregate nonce of the node at `level` and `branch`, to sign it outside the session.
+    pub fn aggregate_nonce(
+        &self,
+        aggregate_pub_nonce_tree: &PubNonceTree,
+        level: usize,
+        branch: usize,
+    ) -> Result<MusigAggNonce, Error> {
+        let agg_pub_nonce = aggregate_pub_nonce_tree
+            .get(level, branch)
+            .ok_or_else(|| Error::crypto(format!("missing pub nonce {level}, {branch}")))?;
+
+        // Equivalent to parsing the individual `MusigAggNonce` from a slice.
+        Ok(musig::aggregate_nonces(&self.secp_zkp, &[agg_pub_nonce]))
+    }
+
+    /// Check the partial signatures produced outside the session for every node that we cosign,
+    /// and arrange them into a [`PartialSigTree`].
+    ///
+    /// `partial_sigs` maps the level and branch of every node to its partial signature.
+    pub fn partial_sig_tree(
+        self,
+        aggregate_pub_nonce_tree: &PubNonceTree,
+        partial_sigs: &HashMap<(usize, usize), MusigPartialSignature>,
+    ) -> Result<PartialSigTree, Error> {
+        let mut partial_sig_tree = self
+            .level_sizes
+            .iter()
+            .map(|size| vec![None; *size])
+            .collect::<Vec<_>>();
+
+        for node in self.nodes.iter() {
+            let (i, j) = (node.level, node.branch);
+
+            let partial_sig = partial_sigs
+                .get(&(i, j))
+                .copied()
+                .ok_or_else(|| Error::crypto(format!("missing partial signature {i}, {j}")))?;
+            let pub_nonce = node
+                .pub_nonce
+                .ok_or_else(|| Error::crypto(format!("missing own pub nonce {i}, {j}")))?;
+
+            musig::verify_partial_signature(
+                &self.secp_zkp,
+                &node.key_agg_cache,
+                self.aggregate_nonce(aggregate_pub_nonce_tree, i, j)?,
+                node.msg,
+                partial_sig,
+                pub_nonce,
+                self.own_cosigner_pk,
+            )
+            .with_context(|| format!("invalid partial signature for VTXO tree node {i}, {j}"))?;
+
+            partial_sig_tree[i][j] = Some(partial_sig);
+        }
+
+        Ok(PartialSigTree(partial_sig_tree))
+    }
+
+    fn node_mut(&mut self, level: usize, branch: usize) -> Result<&mut SigningNode, Error> {
+        self.nodes
+            .iter_mut()
+            .find(|node| node.level == level && node.branch == branch)
+            .ok_or_else(|| {
+                Error::crypto(format!("we do not cosign VTXO tree node {level}, {branch}"))
+            })
+    }
+
+    /// Sign every node that we cosign, using the aggregate nonces of all cosigners.
+    ///
+    /// Fails if the cosigner key is held outside the session, see
+    /// [`VtxoTreeSigningSession::partial_sig_tree`].
+    pub fn sign(self, aggregate_pub_nonce_tree: &PubNonceTree) -> Result<PartialSigTree, Error> {
+        let own_cosigner_kp = self.own_cosigner_kp.ok_or_else(|| {
+            Error::crypto("cannot sign VTXO tree without the secret cosigner key")
+        })?;
+
+        let mut partial_sig_tree = self
+            .level_sizes
+            .iter()
+            .map(|size| vec![None; *size])
+            .collect::<Vec<_>>();
+
+        for node in self.nodes {
+            let (i, j) = (node.level, node.branch);
+
+            let agg_pub_nonce = aggregate_pub_nonce_tree
+                .get(i, j)
+                .ok_or_else(|| Error::crypto(format!("missing pub nonce {i}, {j}")))?;
+
+            let sec_nonce = node
+                .sec_nonce
+                .ok_or_else(|| Error::crypto(format!("missing own secret nonce {i}, {j}")))?;
+
+            let sig = partial_sign(
+                &self.secp_zkp,
+                &own_cosigner_kp,
+                &node.key_agg_cache,
+                node.msg,
+                sec_nonce,
+                agg_pub_nonce,
+            )?;
+
+            partial_sig_tree[i][j] = Some(sig);
+        }
+
+        Ok(PartialSigTree(partial_sig_tree))
+    }
+}
+
+/// The tweaked key aggregation cache of `cosigners` and the message they must sign for the VTXO
+/// tree transaction `tx`, which spends `prevout`.
+pub(crate) fn vtxo_tree_node_sighash(
+    secp: &Secp256k1<secp256k1::All>,
+    secp_zkp: &zkp::Secp256k1<zkp::All>,
+    internal_node_script: &VtxoTreeInternalNodeScript,
+    cosigners: &NodeCosigners,
+    tx: &Transaction,
+    prevout: TxOut,
+) -> Result<(MusigKeyAggCache, secp256k1::Message), Error> {
+    cosigners.verify_prevout(internal_node_script, &prevout)?;
+
+    let mut key_agg_cache = musig::key_agg_cache(secp_zkp, cosigners.cosigner_pks());
+
+    let sweep_tap_tree = internal_node_script.sweep_spend_leaf(secp, cosigners.aggregate_pk());
+
+    musig::apply_taproot_tweak(secp_zkp, &mut key_agg_cache, sweep_tap_tree.tap_tweak())?;
+
+    let prevouts = [prevout];
+    let prevouts = Prevouts::All(&prevouts);
+
+    // Here we are generating a key spend sighash, because the VTXO tree outputs are signed by all
+    // parties with a VTXO in this new round, so we use a musig key spend to efficiently coordinate
+    // all the parties.
+    let tap_sighash = SighashCache::new(tx)
+        .taproot_key_spend_signature_hash(VTXO_INPUT_INDEX, &prevouts, TapSighashType::Default)
+        .map_err(Error::crypto)?;
+
+    let msg = secp256k1::Message::from_digest(tap_sighash.to_raw_hash().to_byte_array());
+
+    Ok((key_agg_cache, msg))
+}
+
+/// Sign `msg` with the aggregate nonce `agg_pub_nonce`, which the Ark server sends us encoded as a
+/// public nonce.
+fn partial_sign(
+    secp_zkp: &zkp::Secp256k1<zkp::All>,
+    own_cosigner_kp: &Keypair,
+    key_agg_cache: &MusigKeyAggCache,
+    msg: secp256k1::Message,
+    nonce_sk: MusigSecNonce,
+    agg_pub_nonce: MusigPubNonce,
+) -> Result<MusigPartialSignature, Error> {
+    // Equivalent to parsing the individual `MusigAggNonce` from a slice.
+    let agg_nonce = musig::aggregate_nonces(secp_zkp, &[agg_pub_nonce]);
+
+    musig::partial_sign(
+        secp_zkp,
+        own_cosigner_kp,
+        key_agg_cache,
+        msg,
+        nonce_sk,
+        agg_nonce,
+    )
 }
 
 /// Build and sign a forfeit transaction per [`VtxoInput`] to be used in an upcoming round
 /// transaction.
+///
+/// Every forfeit transaction is signed with the keypair in `kps` which owns the forfeited VTXO.
 pub fn create_and_sign_forfeit_txs(
-    // For now we only support a single keypair. Eventually we may need to provide something like a
-    // `Sign` trait, so that the caller can find the secret key for the given `VtxoInput`.
-    kp: &Keypair,
+    kps: &[Keypair],
     vtxo_inputs: &[VtxoInput],
     connector_tree: TxTree,
     connector_index: &HashMap<OutPoint, OutPoint>,
@@ -374,23 +1003,113 @@ pub fn create_and_sign_forfeit_txs(
     // As defined by the server.
     dust: Amount,
 ) -> Result<Vec<Psbt>, Error> {
+    create_and_sign_forfeit_txs_with(
+        sign_with_keypairs(kps),
+        vtxo_inputs,
+        connector_tree,
+        connector_index,
+        min_relay_fee_rate_sats_per_kvb,
+        server_forfeit_address,
+        dust,
+    )
+}
+
+/// The messages to be signed by the owners of the `vtxo_inputs` for the forfeit transactions
+/// built by [`create_and_sign_forfeit_txs`], together with the public key that must sign each of
+/// them.
+///
+/// Signatures for these messages can be produced ahead of calling
+/// [`create_and_sign_forfeit_txs_with`], e.g. when signing is asynchronous.
+pub fn forfeit_txs_sighashes(
+    vtxo_inputs: &[VtxoInput],
+    connector_tree: &TxTree,
+    connector_index: &HashMap<OutPoint, OutPoint>,
+    min_relay_fee_rate_sats_per_kvb: i64,
+    server_forfeit_address: &Address,
+    dust: Amount,
+) -> Result<Vec<(XOnlyPublicKey, secp256k1::Message)>, Error> {
+    let forfeit_txs = build_forfeit_txs(
+        vtxo_inputs,
+        connector_tree,
+        connector_index,
+        min_relay_fee_rate_sats_per_kvb,
+        server_forfeit_address,
+        dust,
+    )?;
+
+    Ok(forfeit_txs
+        .iter()
+        .map(|(_, input)| input.sighash())
+        .collect())
+}
+
+/// Like [`create_and_sign_forfeit_txs`], but every forfeit transaction is signed with
+/// `sign_for_pk_fn` instead of a keypair.
+pub fn create_and_sign_forfeit_txs_with<F>(
+    sign_for_pk_fn: F,
+    vtxo_inputs: &[VtxoInput],
+    connector_tree: TxTree,
+    connector_index: &HashMap<OutPoint, OutPoint>,
+    min_relay_fee_rate_sats_per_kvb: i64,
+    server_forfeit_address: &Address,
+    // As defined by the server.
+    dust: Amount,
+) -> Result<Vec<Psbt>, Error>
+where
+    F: Fn(&XOnlyPublicKey, &secp256k1::Message) -> Result<schnorr::Signature, Error>,
+{
+    build_forfeit_txs(
+        vtxo_inputs,
+        &connector_tree,
+        connector_index,
+        min_relay_fee_rate_sats_per_kvb,
+        server_forfeit_address,
+        dust,
+    )?
+    .into_iter()
+    .map(|(mut forfeit_psbt, input)| {
+        let sig = input
+            .sign(&sign_for_pk_fn)
+            .context("failed to sign forfeit transaction")?;
+
+        forfeit_psbt.inputs[input.index].tap_script_sigs =
+            BTreeMap::from_iter([((input.pk, input.leaf_hash), sig)]);
+
+        Ok(forfeit_psbt)
+    })
+    .collect()
+}
+
+/// The unsigned forfeit transactions, each with the VTXO input ready to be signed.
+fn build_forfeit_txs(
+    vtxo_inputs: &[VtxoInput],
+    connector_tree: &TxTree,
+    connector_index: &HashMap<OutPoint, OutPoint>,
+    min_relay_fee_rate_sats_per_kvb: i64,
+    server_forfeit_address: &Address,
+    dust: Amount,
+) -> Result<Vec<(Psbt, InputToSign)>, Error> {
     const FORFEIT_TX_CONNECTOR_INDEX: usize = 0;
     const FORFEIT_TX_VTXO_INDEX: usize = 1;
 
-    let secp = Secp256k1::new();
-
     let fee_rate_sats_per_kvb = min_relay_fee_rate_sats_per_kvb as u64;
     let connector_amount = dust;
 
     let connector_psbts = connector_tree.leaves();
 
-    let mut signed_forfeit_psbts = Vec::new();
+    let mut forfeit_psbts = Vec::new();
     for VtxoInput {
         vtxo,
         amount: vtxo_amount,
         outpoint: vtxo_outpoint,
+        is_recoverable,
     } in vtxo_inputs.iter()
     {
+        // The server already owns the coins of swept VTXOs, so there is nothing to forfeit.
+        if *is_recoverable {
+            continue;
+        }
+
         let min_relay_fee =
             compute_forfeit_min_relay_fee(fee_rate_sats_per_kvb, vtxo, server_forfeit_address);
 
@@ -479,40 +1198,50 @@ pub fn create_and_sign_forfeit_txs(
             .iter()
             .filter_map(|i| i.witness_utxo.clone())
             .collect::<Vec<_>>();
-        let prevouts = Prevouts::All(&prevouts);
 
         let leaf_hash = TapLeafHash::from_script(&forfeit_script, leaf_version);
 
-        let tap_sighash = SighashCache::new(&forfeit_psbt.unsigned_tx)
-            .taproot_script_spend_signature_hash(
-                FORFEIT_TX_VTXO_INDEX,
-                &prevouts,
-                leaf_hash,
-                TapSighashType::Default,
-            )
-            .map_err(Error::crypto)?;
+        let input = InputToSign::new(
+            &forfeit_psbt.unsigned_tx,
+            FORFEIT_TX_VTXO_INDEX,
+            &prevouts,
+            vtxo.owner_pk(),
+            leaf_hash,
+        )?;
 
-        let msg = secp256k1::Message::from_digest(tap_sighash.to_raw_hash().to_byte_array());
+        forfeit_psbts.push((forfeit_psbt, input));
+    }
 
-        let sig = secp.sign_schnorr_no_aux_rand(&msg, kp);
-        let pk = kp.x_only_public_key().0;
+    Ok(forfeit_psbts)
+}
 
-        secp.verify_schnorr(&sig, &msg, &pk)
-            .map_err(Error::crypto)
-            .context("failed to verify own forfeit signature")?;
+/// The messages to be signed by the owners of the inputs of the `round_psbt` which are in the
+/// provided `onchain_inputs` list, together with the public key that must sign each of them.
+///
+/// Signatures for these messages can be produced ahead of calling [`sign_round_psbt`], e.g. when
+/// signing is asynchronous.
+pub fn round_psbt_sighashes(
+    round_psbt: &Psbt,
+    onchain_inputs: &[OnChainInput],
+) -> Result<Vec<(XOnlyPublicKey, secp256k1::Message)>, Error> {
+    let prevouts = round_psbt
+        .inputs
+        .iter()
+        .filter_map(|i| i.witness_utxo.clone())
+        .collect::<Vec<_>>();
 
-        let sig = taproot::Signature {
-            signature: sig,
-            sighash_type: TapSighashType::Default,
-        };
+    our_round_inputs(round_psbt, onchain_inputs)
+        .into_iter()
+        .map(|(i, boarding_output)| {
+            let (forfeit_script, forfeit_control_block) = boarding_output.forfeit_spend_info();
+            let leaf_hash =
+                TapLeafHash::from_script(&forfeit_script, forfeit_control_block.leaf_version);
 
-        forfeit_psbt.inputs[FORFEIT_TX_VTXO_INDEX].tap_script_sigs =
-            BTreeMap::from_iter([((pk, leaf_hash), sig)]);
+            let msg = round_psbt_sighash(round_psbt, i, &prevouts, leaf_hash)?;
 
-        signed_forfeit_psbts.push(forfeit_psbt.clone());
-    }
-
-    Ok(signed_forfeit_psbts)
+            Ok((boarding_output.owner_pk(), msg))
+        })
+        .collect()
 }
 
 /// Sign every input of the `round_psbt` which is in the provided `onchain_inputs` list.
@@ -526,6 +1255,8 @@ where
 {
     let secp = Secp256k1::new();
 
+    prepare_round_psbt(round_psbt, onchain_inputs)?;
+
     let prevouts = round_psbt
         .inputs
         .iter()
@@ -535,62 +1266,149 @@ where
     // Sign round transaction inputs that belong to us. For every output we
     // are boarding, we look through the round transaction inputs to find a
     // matching input.
-    for OnChainInput {
-        boarding_output,
-        outpoint: boarding_outpoint,
-    } in onchain_inputs.iter()
-    {
+    for (i, boarding_output) in our_round_inputs(round_psbt, onchain_inputs) {
+        // In the case of a boarding output, we are actually using a script spend path.
         let (forfeit_script, forfeit_control_block) = boarding_output.forfeit_spend_info();
+        let leaf_hash =
+            TapLeafHash::from_script(&forfeit_script, forfeit_control_block.leaf_version);
 
-        for (i, input) in round_psbt.inputs.iter_mut().enumerate() {
-            let previous_outpoint = round_psbt.unsigned_tx.input[i].previous_output;
+        let msg = round_psbt_sighash(round_psbt, i, &prevouts, leaf_hash)?;
+        let pk = boarding_output.owner_pk();
 
-            if previous_outpoint == *boarding_outpoint {
-                // In the case of a boarding output, we are actually using a
-                // script spend path.
+        let sig = sign_for_pk_fn(&pk, &msg)?;
 
-                let leaf_version = forfeit_control_block.leaf_version;
-                input.tap_scripts = BTreeMap::from_iter([(
-                    forfeit_control_block.clone(),
-                    (forfeit_script.clone(), leaf_version),
-                )]);
+        secp.verify_schnorr(&sig, &msg, &pk)
+            .map_err(Error::crypto)
+            .context("failed to verify own round TX signature")?;
 
-                let prevouts = Prevouts::All(&prevouts);
+        let sig = taproot::Signature {
+            signature: sig,
+            sighash_type: TapSighashType::Default,
+        };
 
-                let leaf_hash = TapLeafHash::from_script(&forfeit_script, leaf_version);
+        round_psbt.inputs[i].tap_script_sigs = BTreeMap::from_iter([((pk, leaf_hash), sig)]);
+    }
 
-                let tap_sighash = SighashCache::new(&round_psbt.unsigned_tx)
-                    .taproot_script_spend_signature_hash(
-                        i,
-                        &prevouts,
-                        leaf_hash,
-                        TapSighashType::Default,
-                    )
-                    .map_err(Error::crypto)?;
+    Ok(())
+}
 
-                let msg =
-                    secp256k1::Message::from_digest(tap_sighash.to_raw_hash().to_byte_array());
-                let pk = boarding_output.owner_pk();
+/// Populate every input of the `round_psbt` which is in the provided `onchain_inputs` list with
+/// everything needed to sign it externally, e.g. on an air-gapped device.
+///
+/// Each input gets the forfeit leaf script and its control block, the internal key, the owner's
+/// key with the leaf hash it signs for, and the sighash type. The key origin of the owner's key is
+/// left empty, since we do not know how the signer derives it.
+///
+/// Once signed, the PSBT can be checked with [`verify_round_psbt_signatures`].
+pub fn prepare_round_psbt(
+    round_psbt: &mut Psbt,
+    onchain_inputs: &[OnChainInput],
+) -> Result<(), Error> {
+    for (i, boarding_output) in our_round_inputs(round_psbt, onchain_inputs) {
+        let (forfeit_script, forfeit_control_block) = boarding_output.forfeit_spend_info();
+        let leaf_version = forfeit_control_block.leaf_version;
+        let leaf_hash = TapLeafHash::from_script(&forfeit_script, leaf_version);
 
-                let sig = sign_for_pk_fn(&pk, &msg)?;
+        let input = &mut round_psbt.inputs[i];
 
-                secp.verify_schnorr(&sig, &msg, &pk)
-                    .map_err(Error::crypto)
-                    .context("failed to verify own round TX signature")?;
+        input.tap_internal_key = Some(forfeit_control_block.internal_key);
+        input.tap_scripts =
+            BTreeMap::from_iter([(forfeit_control_block, (forfeit_script, leaf_version))]);
+        input.tap_key_origins = BTreeMap::from_iter([(
+            boarding_output.owner_pk(),
+            (vec![leaf_hash], Default::default()),
+        )]);
+        input.sighash_type = Some(TapSighashType::Default.into());
+    }
 
-                let sig = taproot::Signature {
-                    signature: sig,
-                    sighash_type: TapSighashType::Default,
-                };
+    Ok(())
+}
 
-                input.tap_script_sigs = BTreeMap::from_iter([((pk, leaf_hash), sig)]);
-            }
+/// Check that every input of the `round_psbt` which is in the provided `onchain_inputs` list
+/// carries a valid signature by the owner of the boarding output.
+///
+/// Use this after importing a PSBT which was prepared with [`prepare_round_psbt`] and signed
+/// externally.
+pub fn verify_round_psbt_signatures(
+    round_psbt: &Psbt,
+    onchain_inputs: &[OnChainInput],
+) -> Result<(), Error> {
+    let secp = Secp256k1::verification_only();
+
+    let prevouts = round_psbt
+        .inputs
+        .iter()
+        .filter_map(|i| i.witness_utxo.clone())
+        .collect::<Vec<_>>();
+
+    for (i, boarding_output) in our_round_inputs(round_psbt, onchain_inputs) {
+        let (forfeit_script, forfeit_control_block) = boarding_output.forfeit_spend_info();
+        let leaf_hash =
+            TapLeafHash::from_script(&forfeit_script, forfeit_control_block.leaf_version);
+        let pk = boarding_output.owner_pk();
+
+        let sig = round_psbt.inputs[i]
+            .tap_script_sigs
+            .get(&(pk, leaf_hash))
+            .ok_or_else(|| Error::crypto(format!("missing signature for round TX input {i}")))?;
+
+        if sig.sighash_type != TapSighashType::Default {
+            return Err(Error::crypto(format!(
+                "unexpected sighash type {} for round TX input {i}",
+                sig.sighash_type
+            )));
         }
+
+        let msg = round_psbt_sighash(round_psbt, i, &prevouts, leaf_hash)?;
+
+        secp.verify_schnorr(&sig.signature, &msg, &pk)
+            .map_err(Error::crypto)
+            .with_context(|| format!("invalid signature for round TX input {i}"))?;
     }
 
     Ok(())
 }
 
+/// The indices of the `round_psbt` inputs which spend one of our `onchain_inputs`, together with
+/// the corresponding boarding output.
+fn our_round_inputs<'a>(
+    round_psbt: &Psbt,
+    onchain_inputs: &'a [OnChainInput],
+) -> Vec<(usize, &'a BoardingOutput)> {
+    onchain_inputs
+        .iter()
+        .flat_map(|o| {
+            round_psbt
+                .unsigned_tx
+                .input
+                .iter()
+                .enumerate()
+                .filter(move |(_, input)| input.previous_output == o.outpoint)
+                .map(move |(i, _)| (i, &o.boarding_output))
+        })
+        .collect()
+}
+
+fn round_psbt_sighash(
+    round_psbt: &Psbt,
+    i: usize,
+    prevouts: &[TxOut],
+    leaf_hash: TapLeafHash,
+) -> Result<secp256k1::Message, Error> {
+    let tap_sighash = SighashCache::new(&round_psbt.unsigned_tx)
+        .taproot_script_spend_signature_hash(
+            i,
+            &Prevouts::All(prevouts),
+            leaf_hash,
+            TapSighashType::Default,
+        )
+        .map_err(Error::crypto)?;
+
+    Ok(secp256k1::Message::from_digest(
+        tap_sighash.to_raw_hash().to_byte_array(),
+    ))
+}
+
 fn extract_cosigner_pks_from_vtxo_psbt(psbt: &Psbt) -> Result<Vec<PublicKey>, Error> {
     let vtxo_input = &psbt.inputs[VTXO_INPUT_INDEX];
 
@@ -607,3 +1425,482 @@ fn extract_cosigner_pks_from_vtxo_psbt(psbt: &Psbt) -> Result<Vec<PublicKey>, Er
     }
     Ok(cosigner_pks)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::key::TweakedPublicKey;
+    use bitcoin::psbt::raw;
+    use bitcoin::secp256k1::SecretKey;
+    use bitcoin::Network;
+    use bitcoin::ScriptBuf;
+    use bitcoin::Sequence;
+    use bitcoin::Txid;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::str::FromStr;
+
+    const SERVER: &str = "33ffb3dee353b1a9ebe4ced64b946238d0a4ac364f275d771da6ad2445d07ae0";
+
+    #[test]
+    fn externally_signed_round_psbt_is_verified() {
+        let secp = Secp256k1::new();
+        let kp = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[1; 32]).unwrap());
+        let (owner, _) = kp.x_only_public_key();
+
+        let boarding_output = BoardingOutput::new(
+            &secp,
+            XOnlyPublicKey::from_str(SERVER).unwrap(),
+            owner,
+            "",
+            Sequence::from_seconds_ceil(604_672).unwrap(),
+            Network::Regtest,
+        );
+        let outpoint = OutPoint::new(Txid::all_zeros(), 0);
+        let amount = Amount::from_sat(100_000);
+
+        let mut round_psbt = Psbt::from_unsigned_tx(Transaction {
+            version: transaction::Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: outpoint,
+                ..Default::default()
+            }],
+            output: vec![TxOut {
+                value: amount,
+                script_pubkey: boarding_output.script_pubkey(),
+            }],
+        })
+        .unwrap();
+        round_psbt.inputs[0].witness_utxo = Some(TxOut {
+            value: amount,
+            script_pubkey: boarding_output.script_pubkey(),
+        });
+
+        let onchain_inputs = [OnChainInput::new(boarding_output, amount, outpoint)];
+
+        prepare_round_psbt(&mut round_psbt, &onchain_inputs).unwrap();
+
+        let input = &round_psbt.inputs[0];
+        assert_eq!(input.tap_scripts.len(), 1);
+        assert!(input.tap_key_origins.contains_key(&owner));
+        assert!(verify_round_psbt_signatures(&round_psbt, &onchain_inputs).is_err());
+
+        sign_round_psbt(
+            |_, msg| Ok(secp.sign_schnorr_no_aux_rand(msg, &kp)),
+            &mut round_psbt,
+            &onchain_inputs,
+        )
+        .unwrap();
+
+        verify_round_psbt_signatures(&round_psbt, &onchain_inputs).unwrap();
+
+        // A signature by anyone else is rejected.
+        let other_kp = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[2; 32]).unwrap());
+        for sig in round_psbt.inputs[0].tap_script_sigs.values_mut() {
+            let msg = secp256k1::Message::from_digest([0; 32]);
+            sig.signature = secp.sign_schnorr_no_aux_rand(&msg, &other_kp);
+        }
+
+        assert!(verify_round_psbt_signatures(&round_psbt, &onchain_inputs).is_err());
+    }
+
+    #[test]
+    fn vtxo_tree_signing_session_matches_whole_tree_signing() {
+        let secp = Secp256k1::new();
+        let server_pk = XOnlyPublicKey::from_str(SERVER).unwrap();
+        let expiry = Sequence::from_seconds_ceil(604_672).unwrap();
+
+        let kps = (1..=4)
+            .map(|i| Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[i; 32]).unwrap()))
+            .collect::<Vec<_>>();
+        let own_kp = kps[1];
+
+        let (round_tx, vtxo_tree) = binary_vtxo_tree(
+            &kps.iter().map(|kp| kp.public_key()).collect::<Vec<_>>(),
+            expiry,
+            server_pk,
+        );
+
+        let nonce_tree = generate_nonce_tree(
+            &mut StdRng::seed_from_u64(42),
+            &vtxo_tree,
+            own_kp.public_key(),
+        )
+        .unwrap();
+        let pub_nonce_tree = nonce_tree.to_pub_nonce_tree();
+
+        let mut session = VtxoTreeSigningSession::new(expiry, server_pk, &own_kp, &round_tx);
+        let mut rng = StdRng::seed_from_u64(42);
+        for level in vtxo_tree.levels.iter() {
+            session.add_level(&mut rng, level).unwrap();
+        }
+
+        let session_pub_nonce_tree = session.pub_nonce_tree();
+        assert_eq!(
+            session_pub_nonce_tree.0, pub_nonce_tree.0,
+            "nonces generated from the same randomness must match"
+        );
+
+        // We only cosign the root, our branch and our leaf.
+        let n_cosigned = session_pub_nonce_tree
+            .0
+            .iter()
+            .flatten()
+            .filter(|nonce| nonce.is_some())
+            .count();
+        assert_eq!(n_cosigned, 3);
+
+        // Our own nonces stand in for the aggregate nonces of all cosigners.
+        let partial_sig_tree = sign_vtxo_tree(
+            expiry,
+            server_pk,
+            &own_kp,
+            &vtxo_tree,
+            &round_tx,
+            nonce_tree,
+            &pub_nonce_tree,
+        )
+        .unwrap();
+        let session_partial_sig_tree = session.sign(&pub_nonce_tree).unwrap();
+
+        assert_eq!(
+            session_partial_sig_tree.into_inner(),
+            partial_sig_tree.into_inner()
+        );
+    }
+
+    #[test]
+    fn external_vtxo_tree_signing_session_checks_partial_signatures() {
+        let secp = Secp256k1::new();
+        let secp_zkp = zkp::Secp256k1::new();
+        let server_pk = XOnlyPublicKey::from_str(SERVER).unwrap();
+        let expiry = Sequence::from_seconds_ceil(604_672).unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let kps = (1..=4)
+            .map(|i| Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[i; 32]).unwrap()))
+            .collect::<Vec<_>>();
+        let own_kp = kps[1];
+
+        let (round_tx, vtxo_tree) = binary_vtxo_tree(
+            &kps.iter().map(|kp| kp.public_key()).collect::<Vec<_>>(),
+            expiry,
+            server_pk,
+        );
+
+        // Sign every node that we cosign as an external signer would, swapping the signatures of
+        // two nodes if `swap` is set.
+        let mut sign_externally = |swap: bool| {
+            let mut session = VtxoTreeSigningSession::new_external(
+                expiry,
+                server_pk,
+                own_kp.public_key(),
+                &round_tx,
+            );
+            for level in vtxo_tree.levels.iter() {
+                session
+                    .add_level(&mut StdRng::seed_from_u64(0), level)
+                    .unwrap();
+            }
+
+            let mut sec_nonces = HashMap::new();
+            for node in session.nodes() {
+                let (sec_nonce, pub_nonce) =
+                    musig::generate_nonce_pair(&mut rng, &secp_zkp, own_kp.public_key()).unwrap();
+
+                session
+                    .set_pub_nonce(node.level, node.branch, pub_nonce)
+                    .unwrap();
+                sec_nonces.insert((node.level, node.branch), sec_nonce);
+            }
+
+            // Our own nonces stand in for the aggregate nonces of all cosigners.
+            let agg_pub_nonce_tree = session.pub_nonce_tree();
+
+            let mut partial_sigs = HashMap::new();
+            for node in session.nodes() {
+                let agg_nonce = session
+                    .aggregate_nonce(&agg_pub_nonce_tree, node.level, node.branch)
+                    .unwrap();
+                let sec_nonce = sec_nonces.remove(&(node.level, node.branch)).unwrap();
+
+                let partial_sig = musig::partial_sign(
+                    &secp_zkp,
+                    &own_kp,
+                    &node.key_agg_cache,
+                    node.msg,
+                    sec_nonce,
+                    agg_nonce,
+                )
+                .unwrap();
+                partial_sigs.insert((node.level, node.branch), partial_sig);
+            }
+
+            if swap {
+                let root = partial_sigs[&(0, 0)];
+                let leaf = partial_sigs.insert((2, 1), root).unwrap();
+                partial_sigs.insert((0, 0), leaf);
+            }
+
+            session.partial_sig_tree(&agg_pub_nonce_tree, &partial_sigs)
+        };
+
+        let partial_sig_tree = sign_externally(false).unwrap();
+        let n_signed = partial_sig_tree
+            .into_inner()
+            .iter()
+            .flatten()
+            .filter(|sig| sig.is_some())
+            .count();
+        assert_eq!(n_signed, 3);
+
+        assert!(sign_externally(true).is_err());
+    }
+
+    #[test]
+    fn vtxo_tree_cosigners_are_verified() {
+        let secp = Secp256k1::new();
+        let server_pk = XOnlyPublicKey::from_str(SERVER).unwrap();
+        let expiry = Sequence::from_seconds_ceil(604_672).unwrap();
+
+        let kps = (1..=4)
+            .map(|i| Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[i; 32]).unwrap()))
+            .collect::<Vec<_>>();
+        let pks = kps.iter().map(|kp| kp.public_key()).collect::<Vec<_>>();
+
+        let (round_tx, vtxo_tree) = binary_vtxo_tree(&pks, expiry, server_pk);
+
+        let cosigners = vtxo_tree_cosigners(&vtxo_tree).unwrap();
+        let sizes = cosigners
+            .iter()
+            .map(|level| level.len())
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, [1, 2, 4]);
+
+        let root = &cosigners[0][0];
+        assert!(pks.iter().all(|pk| root.contains(pk)));
+        assert_eq!(
+            root.output_script(expiry, server_pk),
+            round_tx.unsigned_tx.output[0].script_pubkey
+        );
+
+        // A tree whose outputs are not locked by its cosigners cannot be signed.
+        let mut bad_round_tx = round_tx.clone();
+        bad_round_tx.unsigned_tx.output[0].script_pubkey =
+            ScriptBuf::new_p2tr_tweaked(TweakedPublicKey::dangerous_assume_tweaked(server_pk));
+
+        let mut session = VtxoTreeSigningSession::new(expiry, server_pk, &kps[0], &bad_round_tx);
+        assert!(session
+            .add_level(&mut StdRng::seed_from_u64(42), &vtxo_tree.levels[0])
+            .is_err());
+
+        // Nor can a tree whose root we do not cosign.
+        let other_kp = Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[5; 32]).unwrap());
+        let mut session = VtxoTreeSigningSession::new(expiry, server_pk, &other_kp, &round_tx);
+        assert!(session
+            .add_level(&mut StdRng::seed_from_u64(42), &vtxo_tree.levels[0])
+            .is_err());
+    }
+
+    #[test]
+    fn vtxo_tree_is_verified_before_cosigning() {
+        let secp = Secp256k1::new();
+        let server_pk = XOnlyPublicKey::from_str(SERVER).unwrap();
+        let expiry = Sequence::from_seconds_ceil(604_672).unwrap();
+
+        let pks = (1..=4)
+            .map(|i| {
+                Keypair::from_secret_key(&secp, &SecretKey::from_slice(&[i; 32]).unwrap())
+                    .public_key()
+            })
+            .collect::<Vec<_>>();
+
+        let (round_tx, vtxo_tree) = binary_vtxo_tree(&pks, expiry, server_pk);
+
+        let leaf_script =
+            ScriptBuf::new_p2tr_tweaked(TweakedPublicKey::dangerous_assume_tweaked(server_pk));
+        let our_vtxos = vec![(leaf_script.clone(), Amount::from_sat(1_000)); 2];
+        let limits = VtxoTreeLimits::default();
+
+        verify_vtxo_tree(&round_tx, &vtxo_tree, expiry, server_pk, &our_vtxos, limits).unwrap();
+
+        // The tree does not have a leaf for every one of our VTXOs.
+        let too_many_vtxos = vec![(leaf_script.clone(), Amount::from_sat(1_000)); 5];
+        assert!(verify_vtxo_tree(
+            &round_tx,
+            &vtxo_tree,
+            expiry,
+            server_pk,
+            &too_many_vtxos,
+            limits
+        )
+        .is_err());
+
+        // The leaves pay less than we registered for.
+        let underpaid_vtxos = vec![(leaf_script, Amount::from_sat(1_001))];
+        assert!(verify_vtxo_tree(
+            &round_tx,
+            &vtxo_tree,
+            expiry,
+            server_pk,
+            &underpaid_vtxos,
+            limits
+        )
+        .is_err());
+
+        // The internal nodes must be sweepable by the server after the expected expiry.
+        let other_expiry = Sequence::from_seconds_ceil(1_024).unwrap();
+        assert!(verify_vtxo_tree(
+            &round_tx,
+            &vtxo_tree,
+            other_expiry,
+            server_pk,
+            &our_vtxos,
+            limits
+        )
+        .is_err());
+
+        // The tree must be within the limits.
+        let shallow = VtxoTreeLimits {
+            max_depth: 2,
+            ..limits
+        };
+        assert!(
+            verify_vtxo_tree(&round_tx, &vtxo_tree, expiry, server_pk, &our_vtxos, shallow)
+                .is_err()
+        );
+
+        let mut expensive_tree = vtxo_tree.clone();
+        let expensive_node = &mut expensive_tree.levels[2].nodes[3];
+        expensive_node.tx.unsigned_tx.output[0].value = Amount::ZERO;
+        expensive_node.txid = expensive_node.tx.unsigned_tx.compute_txid();
+        let cheap = VtxoTreeLimits {
+            max_fee_per_tx: Amount::from_sat(999),
+            ..limits
+        };
+        assert!(verify_vtxo_tree(
+            &round_tx,
+            &expensive_tree,
+            expiry,
+            server_pk,
+            &our_vtxos,
+            cheap
+        )
+        .is_err());
+    }
+
+    /// A VTXO tree with one leaf per cosigner, where every node is cosigned by the owners of the
+    /// leaves below it.
+    fn binary_vtxo_tree(
+        cosigner_pks: &[PublicKey],
+        vtxo_tree_expiry: Sequence,
+        server_pk: XOnlyPublicKey,
+    ) -> (Psbt, TxTree) {
+        // The output spent by a node is locked by the cosigners of that node.
+        let node_script = |pks: &[PublicKey]| {
+            let mut cosigner_pks = pks.to_vec();
+            cosigner_pks.sort_by_key(|k| k.serialize());
+
+            let secp_zkp = zkp::Secp256k1::new();
+            let aggregate_pk = musig::aggregate_pk(&musig::key_agg_cache(&secp_zkp, &cosigner_pks));
+
+            NodeCosigners {
+                cosigner_pks,
+                aggregate_pk,
+            }
+            .output_script(vtxo_tree_expiry, server_pk)
+        };
+        let leaf_script =
+            ScriptBuf::new_p2tr_tweaked(TweakedPublicKey::dangerous_assume_tweaked(server_pk));
+        // Every leaf is worth the same, and the tree pays no fees.
+        let amount = |pks: &[PublicKey]| Amount::from_sat(1_000) * pks.len() as u64;
+
+        let round_tx = Psbt::from_unsigned_tx(Transaction {
+            version: transaction::Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: Vec::new(),
+            output: vec![TxOut {
+                value: amount(cosigner_pks),
+                script_pubkey: node_script(cosigner_pks),
+            }],
+        })
+        .unwrap();
+
+        // Every node spends one output of its parent and splits its cosigners between its two
+        // children, until a single cosigner is left.
+        let mut levels = Vec::new();
+        let mut parents = vec![(
+            round_tx.unsigned_tx.compute_txid(),
+            0,
+            cosigner_pks.to_vec(),
+        )];
+        while !parents.is_empty() {
+            let mut nodes = Vec::new();
+            let mut children = Vec::new();
+            for (parent_txid, vout, pks) in parents {
+                let halves = if pks.len() > 1 {
+                    let (left, right) = pks.split_at(pks.len() / 2);
+                    vec![left.to_vec(), right.to_vec()]
+                } else {
+                    Vec::new()
+                };
+
+                let output = if halves.is_empty() {
+                    vec![TxOut {
+                        value: amount(&pks),
+                        script_pubkey: leaf_script.clone(),
+                    }]
+                } else {
+                    halves
+                        .iter()
+                        .map(|half| TxOut {
+                            value: amount(half),
+                            script_pubkey: node_script(half),
+                        })
+                        .collect()
+                };
+
+                let mut tx = Psbt::from_unsigned_tx(Transaction {
+                    version: transaction::Version::TWO,
+                    lock_time: LockTime::ZERO,
+                    input: vec![TxIn {
+                        previous_output: OutPoint::new(parent_txid, vout),
+                        ..Default::default()
+                    }],
+                    output,
+                })
+                .unwrap();
+
+                for (k, pk) in pks.iter().enumerate() {
+                    let mut key = COSIGNER_PSBT_KEY_PREFIX.to_vec();
+                    key.extend((k as u32).to_le_bytes());
+
+                    tx.inputs[VTXO_INPUT_INDEX].unknown.insert(
+                        raw::Key {
+                            type_value: b'c',
+                            key,
+                        },
+                        pk.serialize().to_vec(),
+                    );
+                }
+
+                let txid = tx.unsigned_tx.compute_txid();
+                for (vout, half) in halves.into_iter().enumerate() {
+                    children.push((txid, vout as u32, half));
+                }
+
+                nodes.push(TxTreeNode {
+                    txid,
+                    tx,
+                    parent_txid,
+                });
+            }
+
+            levels.push(TxTreeLevel { nodes });
+            parents = children;
+        }
+
+        (round_tx, TxTree { levels })
+    }
+}
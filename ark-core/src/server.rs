@@ -1,6 +1,11 @@
 //! Messages exchanged between the client and the Ark server.
 
 use crate::ark_address::ArkAddress;
+use crate::exit_delay::ExitDelay;
+use crate::fees::FeeSchedule;
+use crate::round::vtxo_tree_cosigners;
+use crate::round::NodeCosigners;
+use crate::Error;
 use bitcoin::secp256k1::PublicKey;
 use bitcoin::Amount;
 use bitcoin::Network;
@@ -9,6 +14,7 @@ use bitcoin::Psbt;
 use bitcoin::ScriptBuf;
 use bitcoin::Txid;
 use std::collections::HashMap;
+use std::time::Duration;
 
 #[derive(Clone, Debug)]
 pub struct RoundInput {
@@ -137,6 +143,24 @@ pub struct Round {
     pub stage: i32,
 }
 
+impl Round {
+    /// The UNIX timestamp after which the Ark server can sweep the VTXO tree of this round, given
+    /// the `vtxo_tree_expiry` advertised in [`Info`].
+    ///
+    /// Block-based expiries are converted assuming 10 minutes per block, so the result is only an
+    /// estimate for them.
+    pub fn sweepable_after(&self, vtxo_tree_expiry: bitcoin::Sequence) -> Result<i64, Error> {
+        let expiry = ExitDelay::from_sequence(vtxo_tree_expiry)?;
+
+        Ok(self.end + expiry.duration().as_secs() as i64)
+    }
+
+    /// The keys that cosign every node of the VTXO tree of this round, level by level.
+    pub fn vtxo_tree_cosigners(&self) -> Result<Vec<Vec<NodeCosigners>>, Error> {
+        vtxo_tree_cosigners(&self.vtxo_tree)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct VtxoOutPoint {
     pub outpoint: OutPoint,
@@ -144,6 +168,12 @@ pub struct VtxoOutPoint {
     pub round_txid: Txid,
     pub spent_by: Option<Txid>,
     pub expire_at: i64,
+    /// The UNIX timestamp after which the Ark server can sweep the VTXO tree containing this
+    /// VTXO, taking back its value unless the VTXO was refreshed by then.
+    ///
+    /// This is unrelated to the exit delay, which controls when the _owner_ can claim the VTXO
+    /// on-chain.
+    pub sweepable_after: Option<i64>,
     pub swept: bool,
     pub is_pending: bool,
     /// The redeem transaction which has this [`VtxoOutPoint`] as an output. The txid matches the
@@ -165,6 +195,210 @@ pub struct Info {
     pub boarding_descriptor_template: String,
     pub vtxo_descriptor_templates: Vec<String>,
     pub forfeit_address: bitcoin::Address,
+    pub market_hour: Option<MarketHour>,
+    /// The fees charged for taking part in a round, if the server publishes them.
+    pub fees: Option<FeeSchedule>,
+}
+
+impl Info {
+    /// Estimate how long it will take for the next round to start, given the current UNIX
+    /// timestamp `now` in seconds.
+    ///
+    /// The server does not tell us exactly when the next round starts, so this is an upper bound
+    /// based on the round interval. If the server only runs rounds during market hours and we are
+    /// currently outside of them, the estimate is the time until the next market hour starts.
+    pub fn next_round_eta(&self, now: i64) -> Duration {
+        let round_interval = match self.market_hour {
+            Some(market_hour) => match market_hour.time_until_next_start(now) {
+                Some(time_until_next_start) if time_until_next_start > 0 => {
+                    return Duration::from_secs(time_until_next_start as u64);
+                }
+                _ if market_hour.round_interval > 0 => market_hour.round_interval,
+                _ => self.round_interval,
+            },
+            None => self.round_interval,
+        };
+
+        Duration::from_secs(round_interval.max(0) as u64)
+    }
+
+    /// Check that the parameters needed to derive boarding outputs and VTXOs make sense, so that
+    /// they can be cached and used to derive addresses without contacting the server.
+    pub fn validate(&self) -> Result<(), Error> {
+        ExitDelay::from_sequence(self.unilateral_exit_delay)
+            .map_err(|e| Error::ad_hoc(format!("invalid unilateral exit delay: {e}")))?;
+
+        if !self.boarding_descriptor_template.contains("USER") {
+            return Err(Error::ad_hoc(format!(
+                "boarding descriptor template {} does not include the USER placeholder",
+                self.boarding_descriptor_template
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Whether the server's market hour is ongoing at the UNIX timestamp `now` in seconds.
+    ///
+    /// Rounds held during market hours are cheaper to join, so this can be used to decide when to
+    /// perform optional operations.
+    pub fn is_market_hour_open(&self, now: i64) -> bool {
+        self.market_hour
+            .and_then(|market_hour| market_hour.time_until_next_start(now))
+            == Some(0)
+    }
+}
+
+/// What we expect of the Ark server, checked against its [`Info`] before using it.
+///
+/// Every check is optional: the default policy accepts any valid server info.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ServerPolicy {
+    /// The network the server must operate on.
+    pub network: Option<Network>,
+    /// The longest acceptable time between rounds.
+    pub max_round_interval: Option<Duration>,
+    /// The shortest acceptable unilateral exit delay. Shorter delays leave little time to react
+    /// if the server publishes an old state.
+    pub min_exit_delay: Option<Duration>,
+    /// The longest acceptable unilateral exit delay, i.e. how long our funds may stay locked if
+    /// we have to exit unilaterally.
+    pub max_exit_delay: Option<Duration>,
+    /// The public key the server must use. Protects against talking to a different server than
+    /// the one we trust, e.g. after a DNS hijack.
+    pub server_pk: Option<PublicKey>,
+}
+
+impl ServerPolicy {
+    /// Fail with a description of the first expectation that `info` does not meet.
+    ///
+    /// Block-based exit delays are compared assuming 10 minutes per block.
+    pub fn check(&self, info: &Info) -> Result<(), Error> {
+        if let Some(network) = self.network {
+            if info.network != network {
+                return Err(Error::ad_hoc(format!(
+                    "Ark server operates on {}, expected {network}",
+                    info.network
+                )));
+            }
+        }
+
+        if let Some(server_pk) = self.server_pk {
+            if info.pk != server_pk {
+                return Err(Error::ad_hoc(format!(
+                    "Ark server public key {} does not match the pinned key {server_pk}",
+                    info.pk
+                )));
+            }
+        }
+
+        if let Some(max_round_interval) = self.max_round_interval {
+            let round_interval = Duration::from_secs(info.round_interval.max(0) as u64);
+            if round_interval > max_round_interval {
+                return Err(Error::ad_hoc(format!(
+                    "Ark server round interval of {}s exceeds the maximum of {}s",
+                    round_interval.as_secs(),
+                    max_round_interval.as_secs()
+                )));
+            }
+        }
+
+        if self.min_exit_delay.is_some() || self.max_exit_delay.is_some() {
+            let exit_delay = ExitDelay::from_sequence(info.unilateral_exit_delay)?.duration();
+
+            if let Some(min_exit_delay) = self.min_exit_delay {
+                if exit_delay < min_exit_delay {
+                    return Err(Error::ad_hoc(format!(
+                        "Ark server unilateral exit delay of {}s is below the minimum of {}s",
+                        exit_delay.as_secs(),
+                        min_exit_delay.as_secs()
+                    )));
+                }
+            }
+
+            if let Some(max_exit_delay) = self.max_exit_delay {
+                if exit_delay > max_exit_delay {
+                    return Err(Error::ad_hoc(format!(
+                        "Ark server unilateral exit delay of {}s exceeds the maximum of {}s",
+                        exit_delay.as_secs(),
+                        max_exit_delay.as_secs()
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A period announced by the operator of the Ark server during which it halts rounds. All times
+/// are UNIX timestamps in seconds.
+///
+/// The Ark server does not announce maintenance through its API, so this comes from wherever the
+/// operator publishes it, e.g. a status page.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Maintenance {
+    pub start_time: i64,
+    /// When rounds are expected to resume, if the operator announced it.
+    pub end_time: Option<i64>,
+    pub reason: Option<String>,
+}
+
+impl Maintenance {
+    /// Whether rounds are halted at `now`.
+    pub fn is_active(&self, now: i64) -> bool {
+        self.start_time <= now && self.end_time.map_or(true, |end_time| now < end_time)
+    }
+
+    /// How long until rounds are expected to resume at `now`, if the maintenance is ongoing and
+    /// its end was announced.
+    pub fn time_until_end(&self, now: i64) -> Option<Duration> {
+        if !self.is_active(now) {
+            return None;
+        }
+
+        self.end_time
+            .map(|end_time| Duration::from_secs((end_time - now) as u64))
+    }
+}
+
+/// A recurring time window during which the Ark server runs rounds, potentially at a different
+/// interval. All times are UNIX timestamps in seconds; `period`, `round_interval` and durations are
+/// in seconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MarketHour {
+    pub next_start_time: i64,
+    pub next_end_time: i64,
+    pub period: i64,
+    pub round_interval: i64,
+}
+
+impl MarketHour {
+    /// The number of seconds until the market hour starts, or 0 if we are currently in it.
+    ///
+    /// Returns `None` if the server did not configure market hours.
+    fn time_until_next_start(&self, now: i64) -> Option<i64> {
+        if self.next_start_time <= 0 || self.next_end_time <= self.next_start_time {
+            return None;
+        }
+
+        let (mut start, mut end) = (self.next_start_time, self.next_end_time);
+
+        // The values reported by the server may be stale, so we roll the window forward until it
+        // ends in the future.
+        if now >= end && self.period > 0 {
+            let elapsed_periods = (now - end) / self.period + 1;
+
+            start += elapsed_periods * self.period;
+            end += elapsed_periods * self.period;
+        }
+
+        if now >= end {
+            return None;
+        }
+
+        Some((start - now).max(0))
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -219,6 +453,19 @@ pub enum RoundStreamEvent {
     RoundSigningNoncesGenerated(RoundSigningNoncesGeneratedEvent),
 }
 
+impl RoundStreamEvent {
+    /// The ID of the round this event belongs to.
+    pub fn round_id(&self) -> &str {
+        match self {
+            RoundStreamEvent::RoundFinalization(e) => &e.id,
+            RoundStreamEvent::RoundFinalized(e) => &e.id,
+            RoundStreamEvent::RoundFailed(e) => &e.id,
+            RoundStreamEvent::RoundSigning(e) => &e.id,
+            RoundStreamEvent::RoundSigningNoncesGenerated(e) => &e.id,
+        }
+    }
+}
+
 pub enum TransactionEvent {
     Round(RoundTransaction),
     Redeem(RedeemTransaction),
@@ -236,3 +483,212 @@ pub struct RoundTransaction {
     pub spendable_vtxos: Vec<VtxoOutPoint>,
     pub claimed_boarding_utxos: Vec<OutPoint>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn info(round_interval: i64, market_hour: Option<MarketHour>) -> Info {
+        Info {
+            pk: PublicKey::from_str(
+                "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            )
+            .unwrap(),
+            vtxo_tree_expiry: bitcoin::Sequence::MAX,
+            unilateral_exit_delay: bitcoin::Sequence::MAX,
+            round_interval,
+            network: Network::Regtest,
+            dust: Amount::from_sat(330),
+            boarding_descriptor_template: String::new(),
+            vtxo_descriptor_templates: Vec::new(),
+            forfeit_address: bitcoin::Address::from_str(
+                "bcrt1q8frde3yn78tl9ecgq4anlz909jh0clefhucdur",
+            )
+            .unwrap()
+            .assume_checked(),
+            market_hour,
+            fees: None,
+        }
+    }
+
+    #[test]
+    fn validate_rejects_unusable_server_info() {
+        let valid = Info {
+            unilateral_exit_delay: bitcoin::Sequence::from_height(144),
+            boarding_descriptor_template: "tr(unspendable,{and(pk(SERVER),pk(USER)),\
+                                           and(older(144),pk(USER))})"
+                .to_string(),
+            ..info(10, None)
+        };
+        assert!(valid.validate().is_ok());
+
+        let no_relative_lock_time = Info {
+            unilateral_exit_delay: bitcoin::Sequence::MAX,
+            ..valid.clone()
+        };
+        assert!(no_relative_lock_time.validate().is_err());
+
+        let no_user_placeholder = Info {
+            boarding_descriptor_template: String::new(),
+            ..valid
+        };
+        assert!(no_user_placeholder.validate().is_err());
+    }
+
+    #[test]
+    fn server_policy_rejects_unexpected_server_info() {
+        let info = Info {
+            unilateral_exit_delay: bitcoin::Sequence::from_height(144),
+            ..info(10, None)
+        };
+        assert!(ServerPolicy::default().check(&info).is_ok());
+
+        let policy = ServerPolicy {
+            network: Some(Network::Regtest),
+            max_round_interval: Some(Duration::from_secs(10)),
+            min_exit_delay: Some(Duration::from_secs(144 * 600)),
+            max_exit_delay: Some(Duration::from_secs(144 * 600)),
+            server_pk: Some(info.pk),
+        };
+        assert!(policy.check(&info).is_ok());
+
+        let wrong_network = ServerPolicy {
+            network: Some(Network::Bitcoin),
+            ..policy.clone()
+        };
+        assert!(wrong_network.check(&info).is_err());
+
+        let slow_rounds = ServerPolicy {
+            max_round_interval: Some(Duration::from_secs(5)),
+            ..policy.clone()
+        };
+        assert!(slow_rounds.check(&info).is_err());
+
+        let short_exit = ServerPolicy {
+            min_exit_delay: Some(Duration::from_secs(145 * 600)),
+            ..policy.clone()
+        };
+        assert!(short_exit.check(&info).is_err());
+
+        let long_exit = ServerPolicy {
+            max_exit_delay: Some(Duration::from_secs(143 * 600)),
+            ..policy.clone()
+        };
+        assert!(long_exit.check(&info).is_err());
+
+        let other_server = ServerPolicy {
+            server_pk: Some(
+                PublicKey::from_str(
+                    "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5",
+                )
+                .unwrap(),
+            ),
+            ..policy
+        };
+        assert!(other_server.check(&info).is_err());
+    }
+
+    #[test]
+    fn next_round_eta_without_market_hour() {
+        let info = info(10, None);
+
+        assert_eq!(info.next_round_eta(1_000), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn next_round_eta_before_market_hour() {
+        let info = info(
+            10,
+            Some(MarketHour {
+                next_start_time: 1_100,
+                next_end_time: 1_200,
+                period: 1_000,
+                round_interval: 5,
+            }),
+        );
+
+        assert_eq!(info.next_round_eta(1_000), Duration::from_secs(100));
+    }
+
+    #[test]
+    fn next_round_eta_during_market_hour() {
+        let info = info(
+            10,
+            Some(MarketHour {
+                next_start_time: 1_100,
+                next_end_time: 1_200,
+                period: 1_000,
+                round_interval: 5,
+            }),
+        );
+
+        assert_eq!(info.next_round_eta(1_150), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn next_round_eta_after_stale_market_hour() {
+        let info = info(
+            10,
+            Some(MarketHour {
+                next_start_time: 1_100,
+                next_end_time: 1_200,
+                period: 1_000,
+                round_interval: 5,
+            }),
+        );
+
+        assert_eq!(info.next_round_eta(2_500), Duration::from_secs(600));
+    }
+
+    #[test]
+    fn market_hour_open() {
+        let info = info(
+            10,
+            Some(MarketHour {
+                next_start_time: 1_100,
+                next_end_time: 1_200,
+                period: 1_000,
+                round_interval: 5,
+            }),
+        );
+
+        assert!(!info.is_market_hour_open(1_000));
+        assert!(info.is_market_hour_open(1_150));
+        assert!(info.is_market_hour_open(2_150));
+        assert!(!info.is_market_hour_open(2_500));
+
+        assert!(!self::info(10, None).is_market_hour_open(1_150));
+    }
+
+    #[test]
+    fn maintenance_is_active_until_its_end() {
+        let maintenance = Maintenance {
+            start_time: 1_000,
+            end_time: Some(1_600),
+            reason: None,
+        };
+
+        assert!(!maintenance.is_active(900));
+        assert_eq!(maintenance.time_until_end(900), None);
+
+        assert!(maintenance.is_active(1_000));
+        assert_eq!(
+            maintenance.time_until_end(1_000),
+            Some(Duration::from_secs(600))
+        );
+
+        assert!(!maintenance.is_active(1_600));
+        assert_eq!(maintenance.time_until_end(1_600), None);
+
+        // Without an announced end, rounds are halted until the announcement is withdrawn.
+        let maintenance = Maintenance {
+            end_time: None,
+            reason: Some("upgrade".to_string()),
+            ..maintenance
+        };
+
+        assert!(maintenance.is_active(1_000_000));
+        assert_eq!(maintenance.time_until_end(1_000_000), None);
+    }
+}
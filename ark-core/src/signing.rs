@@ -0,0 +1,89 @@
+use crate::derivation::keypair_for;
+use crate::Error;
+use crate::ErrorContext;
+use bitcoin::hashes::Hash;
+use bitcoin::key::Keypair;
+use bitcoin::key::Secp256k1;
+use bitcoin::secp256k1;
+use bitcoin::secp256k1::schnorr;
+use bitcoin::sighash::Prevouts;
+use bitcoin::sighash::SighashCache;
+use bitcoin::taproot;
+use bitcoin::TapLeafHash;
+use bitcoin::TapSighashType;
+use bitcoin::Transaction;
+use bitcoin::TxOut;
+use bitcoin::XOnlyPublicKey;
+
+/// An input of a transaction to be signed by `pk`, spending the leaf with hash `leaf_hash`.
+pub(crate) struct InputToSign {
+    pub index: usize,
+    pub pk: XOnlyPublicKey,
+    pub leaf_hash: TapLeafHash,
+    pub msg: secp256k1::Message,
+}
+
+impl InputToSign {
+    pub fn new(
+        tx: &Transaction,
+        index: usize,
+        prevouts: &[TxOut],
+        pk: XOnlyPublicKey,
+        leaf_hash: TapLeafHash,
+    ) -> Result<Self, Error> {
+        let tap_sighash = SighashCache::new(tx)
+            .taproot_script_spend_signature_hash(
+                index,
+                &Prevouts::All(prevouts),
+                leaf_hash,
+                TapSighashType::Default,
+            )
+            .map_err(Error::crypto)
+            .context("failed to generate sighash")?;
+
+        let msg = secp256k1::Message::from_digest(tap_sighash.to_raw_hash().to_byte_array());
+
+        Ok(Self {
+            index,
+            pk,
+            leaf_hash,
+            msg,
+        })
+    }
+
+    /// Sign the input with `sign_for_pk_fn`, checking that the signature is valid.
+    pub fn sign<F>(&self, sign_for_pk_fn: &F) -> Result<taproot::Signature, Error>
+    where
+        F: Fn(&XOnlyPublicKey, &secp256k1::Message) -> Result<schnorr::Signature, Error>,
+    {
+        let secp = Secp256k1::verification_only();
+
+        let sig = sign_for_pk_fn(&self.pk, &self.msg)?;
+
+        secp.verify_schnorr(&sig, &self.msg, &self.pk)
+            .map_err(Error::crypto)
+            .with_context(|| format!("failed to verify own signature for input {}", self.index))?;
+
+        Ok(taproot::Signature {
+            signature: sig,
+            sighash_type: TapSighashType::Default,
+        })
+    }
+
+    pub fn sighash(&self) -> (XOnlyPublicKey, secp256k1::Message) {
+        (self.pk, self.msg)
+    }
+}
+
+/// A `sign_for_pk_fn` which signs with the keypair in `kps` for the requested public key.
+pub(crate) fn sign_with_keypairs(
+    kps: &[Keypair],
+) -> impl Fn(&XOnlyPublicKey, &secp256k1::Message) -> Result<schnorr::Signature, Error> + '_ {
+    let secp = Secp256k1::new();
+
+    move |pk, msg| {
+        let kp = keypair_for(kps, *pk)?;
+
+        Ok(secp.sign_schnorr_no_aux_rand(msg, kp))
+    }
+}
@@ -0,0 +1,204 @@
+//! Fixtures shared by the tests of this crate.
+
+use crate::internal_node::VtxoTreeInternalNodeScript;
+use crate::musig;
+use crate::round::vtxo_tree_node_sighash;
+use crate::round::NodeCosigners;
+use crate::round::COSIGNER_PSBT_KEY_PREFIX;
+use crate::server::TxTreeNode;
+use crate::VTXO_INPUT_INDEX;
+use bitcoin::absolute::LockTime;
+use bitcoin::hashes::Hash;
+use bitcoin::key::Keypair;
+use bitcoin::key::Secp256k1;
+use bitcoin::key::TapTweak;
+use bitcoin::psbt::raw;
+use bitcoin::secp256k1;
+use bitcoin::secp256k1::SecretKey;
+use bitcoin::sighash::Prevouts;
+use bitcoin::sighash::SighashCache;
+use bitcoin::taproot;
+use bitcoin::transaction;
+use bitcoin::Amount;
+use bitcoin::OutPoint;
+use bitcoin::Psbt;
+use bitcoin::ScriptBuf;
+use bitcoin::Sequence;
+use bitcoin::TapSighashType;
+use bitcoin::Transaction;
+use bitcoin::TxIn;
+use bitcoin::TxOut;
+use bitcoin::XOnlyPublicKey;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+pub(crate) fn keypair(byte: u8) -> Keypair {
+    Keypair::from_secret_key(
+        &Secp256k1::new(),
+        &SecretKey::from_slice(&[byte; 32]).unwrap(),
+    )
+}
+
+/// An output that `kp` alone can spend with a key-spend signature.
+pub(crate) fn p2tr_output(kp: &Keypair, value: Amount) -> TxOut {
+    TxOut {
+        value,
+        script_pubkey: ScriptBuf::new_p2tr(&Secp256k1::new(), kp.x_only_public_key().0, None),
+    }
+}
+
+/// An output of a VTXO tree, locked by the aggregate key of `cosigners`, tweaked with the sweep
+/// leaf of `server_pk` after `vtxo_tree_expiry`.
+pub(crate) fn node_output(
+    cosigners: &[&Keypair],
+    vtxo_tree_expiry: Sequence,
+    server_pk: XOnlyPublicKey,
+    value: Amount,
+) -> TxOut {
+    let secp = Secp256k1::new();
+    let secp_zkp = zkp::Secp256k1::new();
+
+    let mut cosigner_pks = cosigners
+        .iter()
+        .map(|kp| kp.public_key())
+        .collect::<Vec<_>>();
+    cosigner_pks.sort_by_key(|pk| pk.serialize());
+
+    let aggregate_pk = musig::aggregate_pk(&musig::key_agg_cache(&secp_zkp, &cosigner_pks));
+    let output_key = VtxoTreeInternalNodeScript::new(vtxo_tree_expiry, server_pk)
+        .sweep_spend_leaf(&secp, aggregate_pk)
+        .output_key();
+
+    TxOut {
+        value,
+        script_pubkey: ScriptBuf::new_p2tr_tweaked(output_key),
+    }
+}
+
+/// A child of `parent`, spending output `vout` with a key-spend signature by `kp` alone.
+pub(crate) fn signed_child(
+    parent: &Transaction,
+    vout: u32,
+    kp: &Keypair,
+    output: Vec<TxOut>,
+) -> TxTreeNode {
+    let secp = Secp256k1::new();
+
+    let mut psbt = child_psbt(parent, vout, output);
+
+    let msg = key_spend_sighash(&psbt.unsigned_tx, parent.output[vout as usize].clone());
+    let tweaked = kp.tap_tweak(&secp, None).to_keypair();
+    psbt.inputs[VTXO_INPUT_INDEX].tap_key_sig = Some(taproot::Signature {
+        signature: secp.sign_schnorr_no_aux_rand(&msg, &tweaked),
+        sighash_type: TapSighashType::Default,
+    });
+
+    tree_node(parent, psbt)
+}
+
+/// A VTXO tree node spending output `vout` of `parent`, which lists `cosigners` in its PSBT and is
+/// signed by all of them with MuSig2, for the sweep leaf of `server_pk` after `vtxo_tree_expiry`.
+///
+/// The spent output must be a [`node_output`] of the same cosigners for the signature to be valid.
+pub(crate) fn cosigned_child(
+    parent: &Transaction,
+    vout: u32,
+    cosigners: &[&Keypair],
+    vtxo_tree_expiry: Sequence,
+    server_pk: XOnlyPublicKey,
+    output: Vec<TxOut>,
+) -> TxTreeNode {
+    let secp = Secp256k1::new();
+    let secp_zkp = zkp::Secp256k1::new();
+    let mut rng = StdRng::seed_from_u64(42);
+
+    let mut psbt = child_psbt(parent, vout, output);
+    for (k, kp) in cosigners.iter().enumerate() {
+        let mut key = COSIGNER_PSBT_KEY_PREFIX.to_vec();
+        key.extend((k as u32).to_le_bytes());
+
+        psbt.inputs[VTXO_INPUT_INDEX].unknown.insert(
+            raw::Key {
+                type_value: b'c',
+                key,
+            },
+            kp.public_key().serialize().to_vec(),
+        );
+    }
+
+    let (key_agg_cache, msg) = vtxo_tree_node_sighash(
+        &secp,
+        &secp_zkp,
+        &VtxoTreeInternalNodeScript::new(vtxo_tree_expiry, server_pk),
+        &NodeCosigners::from_psbt(&psbt).unwrap(),
+        &psbt.unsigned_tx,
+        parent.output[vout as usize].clone(),
+    )
+    .unwrap();
+
+    let nonces = cosigners
+        .iter()
+        .map(|kp| musig::generate_nonce_pair(&mut rng, &secp_zkp, kp.public_key()).unwrap())
+        .collect::<Vec<_>>();
+    let agg_nonce = musig::aggregate_nonces(
+        &secp_zkp,
+        &nonces
+            .iter()
+            .map(|(_, pub_nonce)| *pub_nonce)
+            .collect::<Vec<_>>(),
+    );
+
+    let partial_sigs = cosigners
+        .iter()
+        .zip(nonces)
+        .map(|(kp, (sec_nonce, _))| {
+            musig::partial_sign(&secp_zkp, kp, &key_agg_cache, msg, sec_nonce, agg_nonce).unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    psbt.inputs[VTXO_INPUT_INDEX].tap_key_sig = Some(taproot::Signature {
+        signature: musig::aggregate_partial_signatures(
+            &secp_zkp,
+            &key_agg_cache,
+            agg_nonce,
+            msg,
+            &partial_sigs,
+        ),
+        sighash_type: TapSighashType::Default,
+    });
+
+    tree_node(parent, psbt)
+}
+
+fn child_psbt(parent: &Transaction, vout: u32, output: Vec<TxOut>) -> Psbt {
+    Psbt::from_unsigned_tx(Transaction {
+        version: transaction::Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(parent.compute_txid(), vout),
+            ..Default::default()
+        }],
+        output,
+    })
+    .unwrap()
+}
+
+fn key_spend_sighash(tx: &Transaction, prevout: TxOut) -> secp256k1::Message {
+    let sighash = SighashCache::new(tx)
+        .taproot_key_spend_signature_hash(
+            VTXO_INPUT_INDEX,
+            &Prevouts::All(&[prevout]),
+            TapSighashType::Default,
+        )
+        .unwrap();
+
+    secp256k1::Message::from_digest(sighash.to_raw_hash().to_byte_array())
+}
+
+fn tree_node(parent: &Transaction, psbt: Psbt) -> TxTreeNode {
+    TxTreeNode {
+        txid: psbt.unsigned_tx.compute_txid(),
+        tx: psbt,
+        parent_txid: parent.compute_txid(),
+    }
+}
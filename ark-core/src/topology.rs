@@ -0,0 +1,96 @@
+//! A flat, serializable view of the VTXO tree of a round, for explorers and debugging tools.
+
+use crate::server::Round;
+use bitcoin::Amount;
+use bitcoin::ScriptBuf;
+use bitcoin::Txid;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RoundTopology {
+    pub round_txid: Txid,
+    /// All the transactions in the VTXO tree, ordered from the root to the leaves.
+    pub nodes: Vec<TopologyNode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TopologyNode {
+    pub txid: Txid,
+    /// The round transaction, for the root of the tree.
+    pub parent_txid: Txid,
+    /// The depth of the node in the tree, starting at 0 for the root.
+    pub level: usize,
+    /// Whether the outputs of this node are VTXOs.
+    pub is_leaf: bool,
+    pub outputs: Vec<TopologyOutput>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TopologyOutput {
+    pub vout: u32,
+    pub amount: Amount,
+    pub script_pubkey: ScriptBuf,
+    /// Whether the output is locked by one of the scripts given to [`round_topology`].
+    pub is_ours: bool,
+}
+
+impl RoundTopology {
+    /// The outputs of the tree which belong to us.
+    pub fn our_outputs(&self) -> impl Iterator<Item = (&TopologyNode, &TopologyOutput)> {
+        self.nodes
+            .iter()
+            .flat_map(|node| node.outputs.iter().map(move |output| (node, output)))
+            .filter(|(_, output)| output.is_ours)
+    }
+}
+
+/// Build the [`RoundTopology`] of `round`, marking the outputs locked by any of `our_scripts`.
+pub fn round_topology(round: &Round, our_scripts: &[ScriptBuf]) -> RoundTopology {
+    let round_txid = round.round_tx.unsigned_tx.compute_txid();
+
+    let parents = round
+        .vtxo_tree
+        .levels
+        .iter()
+        .flat_map(|level| level.nodes.iter().map(|node| node.parent_txid))
+        .collect::<HashSet<_>>();
+
+    let nodes = round
+        .vtxo_tree
+        .levels
+        .iter()
+        .enumerate()
+        .flat_map(|(level, tree_level)| {
+            let parents = &parents;
+
+            tree_level.nodes.iter().map(move |node| {
+                let outputs = node
+                    .tx
+                    .unsigned_tx
+                    .output
+                    .iter()
+                    .enumerate()
+                    .map(|(vout, output)| TopologyOutput {
+                        vout: vout as u32,
+                        amount: output.value,
+                        script_pubkey: output.script_pubkey.clone(),
+                        is_ours: our_scripts.contains(&output.script_pubkey),
+                    })
+                    .collect();
+
+                TopologyNode {
+                    txid: node.txid,
+                    parent_txid: node.parent_txid,
+                    level,
+                    is_leaf: !parents.contains(&node.txid),
+                    outputs,
+                }
+            })
+        })
+        .collect();
+
+    RoundTopology { round_txid, nodes }
+}
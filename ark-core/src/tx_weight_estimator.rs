@@ -1,8 +1,12 @@
+use crate::BoardingOutput;
+use crate::DefaultVtxo;
 use crate::Error;
 use bitcoin::taproot::ControlBlock;
+use bitcoin::AddressType;
 use bitcoin::Amount;
 use bitcoin::FeeRate;
 use bitcoin::OutPoint;
+use bitcoin::Script;
 use bitcoin::ScriptBuf;
 use bitcoin::VarInt;
 
@@ -46,14 +50,60 @@ const BASE_OUTPUT_SIZE: usize = 8 + 1;
 /// P2PKHSIZE 25 bytes.
 const P2PKH_SIZE: usize = 25;
 
+/// P2SHSize 23 bytes.
+const P2SH_SIZE: usize = 23;
+
+/// P2WPKHSize 22 bytes
+///      - OP_0: 1 byte
+///      - OP_DATA: 1 byte (PublicKeyHASH160 length)
+///      - PublicKeyHASH160: 20 bytes
+const P2WPKH_SIZE: usize = 1 + 1 + 20;
+
+/// P2WSHSize 34 bytes
+///      - OP_0: 1 byte
+///      - OP_DATA: 1 byte (WitnessScriptSHA256 length)
+///      - WitnessScriptSHA256: 32 bytes
+const P2WSH_SIZE: usize = 1 + 1 + 32;
+
+/// P2TRSize 34 bytes
+///      - OP_1: 1 byte
+///      - OP_DATA: 1 byte (x-only public key length)
+///      - x-only public key: 32 bytes
+const P2TR_SIZE: usize = 1 + 1 + 32;
+
 /// P2TROutputSize 43 bytes
 ///      - value: 8 bytes
 ///      - var_int: 1 byte (pkscript_length)
 ///      - pkscript (p2tr): 34 bytes
-const P2TR_OUTPUT_SIZE: usize = BASE_OUTPUT_SIZE + P2PKH_SIZE;
+const P2TR_OUTPUT_SIZE: usize = BASE_OUTPUT_SIZE + P2TR_SIZE;
+
+/// TaprootSignatureWitnessSize 65 bytes
+///      - OP_DATA: 1 byte (signature length)
+///      - signature: 64 bytes
+const TAPROOT_SIGNATURE_WITNESS_SIZE: usize = 1 + 64;
+
+/// TaprootKeyPathWitnessSize 66 bytes
+///      - NumberOfWitnessElements: 1 byte
+///      - signature: 65 bytes
+const TAPROOT_KEY_PATH_WITNESS_SIZE: usize = 1 + TAPROOT_SIGNATURE_WITNESS_SIZE;
 
+/// The size of the script pubkey of an output of type `address_type`.
+fn script_pubkey_size(address_type: AddressType) -> Result<usize, Error> {
+    match address_type {
+        AddressType::P2pkh => Ok(P2PKH_SIZE),
+        AddressType::P2sh => Ok(P2SH_SIZE),
+        AddressType::P2wpkh => Ok(P2WPKH_SIZE),
+        AddressType::P2wsh => Ok(P2WSH_SIZE),
+        AddressType::P2tr => Ok(P2TR_SIZE),
+        address_type => Err(Error::ad_hoc(format!(
+            "cannot estimate size of output type {address_type}"
+        ))),
+    }
+}
+
+/// Estimates the weight of a transaction, from the inputs and outputs added to it.
 #[derive(Default)]
-struct TxWeightEstimator {
+pub struct TxWeightEstimator {
     has_witness: bool,
     input_count: u32,
     output_count: u32,
@@ -104,6 +154,24 @@ impl TxWeightEstimator {
         self
     }
 
+    /// Updates the weight estimate to account for an additional output of type `address_type`.
+    pub fn add_output_of_type(&mut self, address_type: AddressType) -> Result<&mut Self, Error> {
+        self.output_size += BASE_OUTPUT_SIZE + script_pubkey_size(address_type)?;
+        self.output_count += 1;
+
+        Ok(self)
+    }
+
+    /// Updates the weight estimate to account for an additional output locked by `script_pubkey`.
+    ///
+    /// Unlike [`TxWeightEstimator::add_p2tr_output`], this supports any output type, including
+    /// P2WSH and legacy outputs.
+    pub fn add_output(&mut self, script_pubkey: &Script) -> &mut Self {
+        self.output_size += 8 + VarInt(script_pubkey.len() as u64).size() + script_pubkey.len();
+        self.output_count += 1;
+        self
+    }
+
     /// Weight gets the estimated weight of the transaction.
     pub fn weight(&self) -> usize {
         let input_count_size = VarInt(self.input_count as u64).size();
@@ -139,6 +207,138 @@ pub struct VtxoInput {
     pub witness_size: usize,
 }
 
+/// Estimate the size of a transaction spending `num_inputs` boarding outputs like
+/// `boarding_output` via their exit path, to outputs of `output_types`.
+pub fn boarding_exit_tx_vsize(
+    boarding_output: &BoardingOutput,
+    num_inputs: usize,
+    output_types: &[AddressType],
+) -> Result<usize, Error> {
+    let (exit_script, control_block) = boarding_output.exit_spend_info();
+
+    exit_tx_vsize(&exit_script, &control_block, num_inputs, output_types)
+}
+
+/// Estimate the size of a transaction spending `num_inputs` VTXOs like `vtxo` via their exit path,
+/// once they have been published on-chain, to outputs of `output_types`.
+pub fn vtxo_exit_tx_vsize(
+    vtxo: &DefaultVtxo,
+    num_inputs: usize,
+    output_types: &[AddressType],
+) -> Result<usize, Error> {
+    let (exit_script, control_block) = vtxo.exit_spend_info();
+
+    exit_tx_vsize(&exit_script, &control_block, num_inputs, output_types)
+}
+
+fn exit_tx_vsize(
+    exit_script: &ScriptBuf,
+    control_block: &ControlBlock,
+    num_inputs: usize,
+    output_types: &[AddressType],
+) -> Result<usize, Error> {
+    let mut estimator = TxWeightEstimator::default();
+
+    for _ in 0..num_inputs {
+        estimator.add_tapscript_input(DefaultVtxo::EXIT_WITNESS_SIZE, exit_script, control_block);
+    }
+
+    for output_type in output_types {
+        estimator.add_output_of_type(*output_type)?;
+    }
+
+    Ok(estimator.vsize())
+}
+
+/// Estimate the size of a forfeit transaction for `vtxo`, paying to a forfeit address of type
+/// `forfeit_address_type`.
+///
+/// A forfeit transaction spends a connector output (via the key path) and the VTXO (via its
+/// largest tapscript), to a single output.
+pub fn forfeit_tx_vsize(
+    vtxo: &DefaultVtxo,
+    forfeit_address_type: AddressType,
+) -> Result<usize, Error> {
+    let n_inputs = 2;
+    let n_outputs = 1;
+    let mut input_size = 0;
+    let mut witness_size = 0;
+
+    // 1 connector input.
+    input_size += INPUT_SIZE;
+    witness_size += TAPROOT_KEY_PATH_WITNESS_SIZE;
+
+    // 1 VTXO input.
+    input_size += INPUT_SIZE;
+
+    let spend_info = &vtxo.spend_info();
+    let ((biggest_script, leaf_version), _) = spend_info
+        .script_map()
+        .iter()
+        .max_by_key(|((script, leaf_version), _)| {
+            let control_block = spend_info
+                .control_block(&(script.clone(), *leaf_version))
+                .expect("control block");
+
+            control_block.size() + script.len()
+        })
+        .expect("at least one");
+
+    let control_block = spend_info
+        .control_block(&(biggest_script.clone(), *leaf_version))
+        .expect("control block");
+
+    // We add 1 byte for the total number of witness elements.
+    //
+    // 1 byte for the length of the element plus the element itself.
+    let control_block_witness_size = 1
+        + TAPROOT_BASE_CONTROL_BLOCK_WITNESS_SIZE
+        + 1
+        + biggest_script.len()
+        + 1
+        + control_block.merkle_branch.concat().len();
+
+    witness_size += DefaultVtxo::FORFEIT_WITNESS_SIZE + control_block_witness_size;
+
+    let output_size = BASE_OUTPUT_SIZE + script_pubkey_size(forfeit_address_type)?;
+
+    let input_count = VarInt(n_inputs).size();
+    let output_count = VarInt(n_outputs).size();
+    let tx_size_stripped = BASE_TX_SIZE + input_count + input_size + output_count + output_size;
+
+    let weight = tx_size_stripped * WITNESS_SCALE_FACTOR + witness_size;
+
+    Ok(weight.div_ceil(WITNESS_SCALE_FACTOR))
+}
+
+/// Compute the fee for a transaction spending `inputs` via tapscript to outputs locked by
+/// `output_scripts`.
+pub fn compute_tx_fee(
+    fee_rate: FeeRate,
+    inputs: &[VtxoInput],
+    output_scripts: &[ScriptBuf],
+) -> Result<Amount, Error> {
+    let mut estimator = TxWeightEstimator::default();
+
+    for input in inputs {
+        let revealed_script = input.revealed_script.as_ref().ok_or_else(|| {
+            Error::ad_hoc(format!("missing tapscript for input {}", input.outpoint))
+        })?;
+
+        estimator.add_tapscript_input(input.witness_size, revealed_script, &input.control_block);
+    }
+
+    for script_pubkey in output_scripts {
+        estimator.add_output(script_pubkey);
+    }
+
+    let vsize = estimator.vsize();
+
+    fee_rate
+        .fee_vb(vsize as u64)
+        .ok_or(Error::ad_hoc("failed calculating fee rate".to_string()))
+}
+
 /// Compute the fee for a redeem transaction.
 pub fn compute_redeem_tx_fee(
     fee_rate: FeeRate,
@@ -180,3 +380,213 @@ pub fn compute_redeem_tx_fee(
 
     Ok(fee)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unilateral_exit::create_batched_unilateral_exit_transaction;
+    use crate::unilateral_exit::create_unilateral_exit_transaction;
+    use crate::unilateral_exit::OnChainInput;
+    use crate::unilateral_exit::VtxoInput;
+    use bitcoin::hashes::Hash;
+    use bitcoin::key::Keypair;
+    use bitcoin::key::Secp256k1;
+    use bitcoin::secp256k1::SecretKey;
+    use bitcoin::Address;
+    use bitcoin::CompressedPublicKey;
+    use bitcoin::Network;
+    use bitcoin::OutPoint;
+    use bitcoin::Txid;
+    use bitcoin::WScriptHash;
+    use bitcoin::XOnlyPublicKey;
+    use std::str::FromStr;
+
+    const SERVER: &str = "33ffb3dee353b1a9ebe4ced64b946238d0a4ac364f275d771da6ad2445d07ae0";
+
+    fn keypair() -> Keypair {
+        let secp = Secp256k1::new();
+        let sk = SecretKey::from_slice(&[1; 32]).unwrap();
+
+        Keypair::from_secret_key(&secp, &sk)
+    }
+
+    fn outpoint(vout: u32) -> OutPoint {
+        OutPoint::new(Txid::all_zeros(), vout)
+    }
+
+    fn addresses() -> Vec<Address> {
+        let secp = Secp256k1::new();
+        let kp = keypair();
+        let pk = CompressedPublicKey(kp.public_key());
+        let (x_only_pk, _) = kp.x_only_public_key();
+
+        vec![
+            Address::p2pkh(pk, Network::Regtest),
+            Address::p2shwpkh(&pk, Network::Regtest),
+            Address::p2wpkh(&pk, Network::Regtest),
+            Address::p2wsh(
+                &ScriptBuf::new_p2wsh(&WScriptHash::all_zeros()),
+                Network::Regtest,
+            ),
+            Address::p2tr(&secp, x_only_pk, None, Network::Regtest),
+        ]
+    }
+
+    #[test]
+    fn output_type_sizes_match_scripts() {
+        for address in addresses() {
+            let mut by_type = TxWeightEstimator::default();
+            by_type
+                .add_output_of_type(address.address_type().unwrap())
+                .unwrap();
+
+            let mut by_script = TxWeightEstimator::default();
+            by_script.add_output(&address.script_pubkey());
+
+            assert_eq!(by_type.weight(), by_script.weight(), "{address}");
+        }
+    }
+
+    #[test]
+    fn exit_tx_vsize_covers_signed_tx() {
+        let secp = Secp256k1::new();
+        let kp = keypair();
+        let server = XOnlyPublicKey::from_str(SERVER).unwrap();
+        let (owner, _) = kp.x_only_public_key();
+
+        let boarding_output = BoardingOutput::new(
+            &secp,
+            server,
+            owner,
+            "",
+            bitcoin::Sequence::from_seconds_ceil(604_672).unwrap(),
+            Network::Regtest,
+        );
+        let vtxo = DefaultVtxo::new(
+            &secp,
+            server,
+            owner,
+            bitcoin::Sequence::from_seconds_ceil(86_528).unwrap(),
+            Network::Regtest,
+        );
+
+        let change_address = addresses()[2].clone();
+        for to_address in addresses() {
+            let output_types = [
+                to_address.address_type().unwrap(),
+                change_address.address_type().unwrap(),
+            ];
+
+            let tx = create_unilateral_exit_transaction(
+                &[kp],
+                to_address.clone(),
+                Amount::from_sat(100_000),
+                change_address.clone(),
+                &[
+                    OnChainInput::new(
+                        boarding_output.clone(),
+                        Amount::from_sat(100_000),
+                        outpoint(0),
+                    ),
+                    OnChainInput::new(
+                        boarding_output.clone(),
+                        Amount::from_sat(100_000),
+                        outpoint(1),
+                    ),
+                ],
+                &[],
+                FeeRate::BROADCAST_MIN,
+            )
+            .unwrap();
+
+            let estimate = boarding_exit_tx_vsize(&boarding_output, 2, &output_types).unwrap();
+            let actual = tx.vsize();
+            assert!(estimate >= actual, "{to_address}: {estimate} < {actual}");
+            assert!(
+                estimate - actual <= 20,
+                "{to_address}: {estimate} vs {actual}"
+            );
+
+            let tx = create_unilateral_exit_transaction(
+                &[kp],
+                to_address.clone(),
+                Amount::from_sat(100_000),
+                change_address.clone(),
+                &[],
+                &[VtxoInput::new(
+                    vtxo.clone(),
+                    Amount::from_sat(200_000),
+                    outpoint(2),
+                )],
+                FeeRate::BROADCAST_MIN,
+            )
+            .unwrap();
+
+            let estimate = vtxo_exit_tx_vsize(&vtxo, 1, &output_types).unwrap();
+            let actual = tx.vsize();
+            assert!(estimate >= actual, "{to_address}: {estimate} < {actual}");
+            assert!(
+                estimate - actual <= 10,
+                "{to_address}: {estimate} vs {actual}"
+            );
+        }
+    }
+
+    #[test]
+    fn batched_exit_tx_pays_fee_for_every_output() {
+        let secp = Secp256k1::new();
+        let kp = keypair();
+        let (owner, _) = kp.x_only_public_key();
+        let vtxo = DefaultVtxo::new(
+            &secp,
+            XOnlyPublicKey::from_str(SERVER).unwrap(),
+            owner,
+            bitcoin::Sequence::from_seconds_ceil(86_528).unwrap(),
+            Network::Regtest,
+        );
+
+        let input_amount = Amount::from_sat(1_000_000);
+        let outputs = addresses()
+            .into_iter()
+            .map(|address| (address, Amount::from_sat(100_000)))
+            .collect::<Vec<_>>();
+
+        let tx = create_batched_unilateral_exit_transaction(
+            &[kp],
+            &outputs,
+            addresses()[2].clone(),
+            &[],
+            &[VtxoInput::new(vtxo, input_amount, outpoint(0))],
+            FeeRate::from_sat_per_vb_u32(2),
+        )
+        .unwrap();
+
+        assert_eq!(tx.output.len(), outputs.len() + 1);
+        for ((address, amount), output) in outputs.iter().zip(tx.output.iter()) {
+            assert_eq!(output.script_pubkey, address.script_pubkey());
+            assert_eq!(output.value, *amount);
+        }
+
+        let output_amount: Amount = tx.output.iter().map(|o| o.value).sum();
+        let fee = input_amount - output_amount;
+        assert!(fee >= Amount::from_sat(2 * tx.vsize() as u64), "{fee}");
+    }
+
+    #[test]
+    fn forfeit_tx_vsize_depends_on_output_type() {
+        let secp = Secp256k1::new();
+        let (owner, _) = keypair().x_only_public_key();
+        let vtxo = DefaultVtxo::new(
+            &secp,
+            XOnlyPublicKey::from_str(SERVER).unwrap(),
+            owner,
+            bitcoin::Sequence::from_seconds_ceil(86_528).unwrap(),
+            Network::Regtest,
+        );
+
+        let p2wpkh = forfeit_tx_vsize(&vtxo, AddressType::P2wpkh).unwrap();
+        let p2tr = forfeit_tx_vsize(&vtxo, AddressType::P2tr).unwrap();
+
+        assert_eq!(p2tr - p2wpkh, P2TR_SIZE - P2WPKH_SIZE);
+    }
+}
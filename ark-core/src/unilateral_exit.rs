@@ -1,29 +1,32 @@
 use crate::server::Round;
+use crate::signing::sign_with_keypairs;
+use crate::signing::InputToSign;
+use crate::tx_weight_estimator;
+use crate::tx_weight_estimator::compute_tx_fee;
 use crate::BoardingOutput;
 use crate::DefaultVtxo;
 use crate::Error;
-use crate::ErrorContext;
 use crate::VTXO_INPUT_INDEX;
 use bitcoin::absolute::LockTime;
-use bitcoin::hashes::Hash;
 use bitcoin::hex::DisplayHex;
 use bitcoin::key::Keypair;
-use bitcoin::key::Secp256k1;
 use bitcoin::secp256k1;
-use bitcoin::sighash::Prevouts;
-use bitcoin::sighash::SighashCache;
+use bitcoin::secp256k1::schnorr;
+use bitcoin::taproot::ControlBlock;
 use bitcoin::transaction;
 use bitcoin::Address;
 use bitcoin::Amount;
+use bitcoin::FeeRate;
 use bitcoin::OutPoint;
 use bitcoin::Psbt;
+use bitcoin::ScriptBuf;
 use bitcoin::TapLeafHash;
-use bitcoin::TapSighashType;
 use bitcoin::Transaction;
 use bitcoin::TxIn;
 use bitcoin::TxOut;
 use bitcoin::Txid;
 use bitcoin::Witness;
+use bitcoin::XOnlyPublicKey;
 use std::collections::HashMap;
 use std::collections::HashSet;
 
@@ -83,8 +86,58 @@ impl VtxoInput {
     }
 }
 
+/// The script of a pay-to-anchor (P2A) output: `OP_1 <0x4e73>`.
+///
+/// Anyone can spend such an output with an empty witness, e.g. to bump the fee of its transaction
+/// via child-pays-for-parent (CPFP).
+const ANCHOR_SCRIPT: [u8; 4] = [0x51, 0x02, 0x4e, 0x73];
+
+/// The index of the pay-to-anchor output of `tx`, if it has one.
+pub fn find_anchor_output(tx: &Transaction) -> Option<u32> {
+    tx.output
+        .iter()
+        .position(|output| output.script_pubkey.as_bytes() == ANCHOR_SCRIPT)
+        .map(|vout| vout as u32)
+}
+
+/// Estimate the fee of a transaction built with [`create_unilateral_exit_transaction`], paying
+/// `fee_rate`.
+///
+/// The weight of each output is derived from its script, so that P2TR, P2WSH and legacy outputs
+/// are all accounted for correctly.
+pub fn estimate_unilateral_exit_tx_fee(
+    fee_rate: FeeRate,
+    onchain_inputs: &[OnChainInput],
+    vtxo_inputs: &[VtxoInput],
+    output_scripts: &[ScriptBuf],
+) -> Result<Amount, Error> {
+    let inputs = onchain_inputs
+        .iter()
+        .map(|o| (o.outpoint, o.amount, o.boarding_output.exit_spend_info()))
+        .chain(
+            vtxo_inputs
+                .iter()
+                .map(|v| (v.outpoint, v.amount, v.vtxo.exit_spend_info())),
+        )
+        .map(
+            |(outpoint, amount, (script, control_block))| tx_weight_estimator::VtxoInput {
+                outpoint,
+                amount,
+                revealed_script: Some(script),
+                control_block,
+                witness_size: DefaultVtxo::EXIT_WITNESS_SIZE,
+            },
+        )
+        .collect::<Vec<_>>();
+
+    compute_tx_fee(fee_rate, inputs.as_slice(), output_scripts)
+}
+
 /// Build a transaction that spends boarding outputs and VTXOs to an _on-chain_ `to_address`. Any
-/// coins left over after covering the `to_amount` are sent to an on-chain change address.
+/// coins left over after covering the `to_amount` and the fee are sent to an on-chain change
+/// address.
+///
+/// If the change would be dust, it is added to the fee instead.
 ///
 /// All these outputs are spent unilaterally i.e. without the collaboration of the Ark server.
 ///
@@ -93,25 +146,151 @@ impl VtxoInput {
 /// To be able to spend a VTXO, the VTXO itself must be published on-chain, and then we must wait
 /// for the exit delay to pass.
 pub fn create_unilateral_exit_transaction(
-    kp: &Keypair,
+    kps: &[Keypair],
     to_address: Address,
     to_amount: Amount,
     change_address: Address,
     onchain_inputs: &[OnChainInput],
     vtxo_inputs: &[VtxoInput],
+    fee_rate: FeeRate,
+) -> Result<Transaction, Error> {
+    create_batched_unilateral_exit_transaction(
+        kps,
+        &[(to_address, to_amount)],
+        change_address,
+        onchain_inputs,
+        vtxo_inputs,
+        fee_rate,
+    )
+}
+
+/// Like [`create_unilateral_exit_transaction`], but paying several on-chain `outputs` at once.
+///
+/// The fee covers every output and is paid entirely from the change, so each recipient receives
+/// exactly the amount given in `outputs`.
+pub fn create_batched_unilateral_exit_transaction(
+    kps: &[Keypair],
+    outputs: &[(Address, Amount)],
+    change_address: Address,
+    onchain_inputs: &[OnChainInput],
+    vtxo_inputs: &[VtxoInput],
+    fee_rate: FeeRate,
 ) -> Result<Transaction, Error> {
+    create_batched_unilateral_exit_transaction_with(
+        sign_with_keypairs(kps),
+        outputs,
+        change_address,
+        onchain_inputs,
+        vtxo_inputs,
+        fee_rate,
+    )
+}
+
+/// The messages to be signed by the owners of the inputs of the transaction built by
+/// [`create_batched_unilateral_exit_transaction`], together with the public key that must sign
+/// each of them.
+///
+/// Signatures for these messages can be produced ahead of calling
+/// [`create_batched_unilateral_exit_transaction_with`], e.g. when signing is asynchronous.
+pub fn batched_unilateral_exit_transaction_sighashes(
+    outputs: &[(Address, Amount)],
+    change_address: Address,
+    onchain_inputs: &[OnChainInput],
+    vtxo_inputs: &[VtxoInput],
+    fee_rate: FeeRate,
+) -> Result<Vec<(XOnlyPublicKey, secp256k1::Message)>, Error> {
+    let (_, inputs_to_sign) = build_batched_unilateral_exit_transaction(
+        outputs,
+        change_address,
+        onchain_inputs,
+        vtxo_inputs,
+        fee_rate,
+    )?;
+
+    Ok(inputs_to_sign
+        .iter()
+        .map(|(input, _, _)| input.sighash())
+        .collect())
+}
+
+/// Like [`create_batched_unilateral_exit_transaction`], but every input is signed with
+/// `sign_for_pk_fn` instead of a keypair.
+pub fn create_batched_unilateral_exit_transaction_with<F>(
+    sign_for_pk_fn: F,
+    outputs: &[(Address, Amount)],
+    change_address: Address,
+    onchain_inputs: &[OnChainInput],
+    vtxo_inputs: &[VtxoInput],
+    fee_rate: FeeRate,
+) -> Result<Transaction, Error>
+where
+    F: Fn(&XOnlyPublicKey, &secp256k1::Message) -> Result<schnorr::Signature, Error>,
+{
+    let (mut psbt, inputs_to_sign) = build_batched_unilateral_exit_transaction(
+        outputs,
+        change_address,
+        onchain_inputs,
+        vtxo_inputs,
+        fee_rate,
+    )?;
+
+    // Sign each input.
+    for (input, exit_script, exit_control_block) in inputs_to_sign {
+        let sig = input.sign(&sign_for_pk_fn)?;
+
+        let witness = Witness::from_slice(&[
+            &sig.signature[..],
+            exit_script.as_bytes(),
+            &exit_control_block.serialize(),
+        ]);
+
+        psbt.inputs[input.index].final_script_witness = Some(witness);
+    }
+
+    let tx = psbt.extract_tx().map_err(Error::transaction)?;
+
+    tracing::debug!(
+        ?onchain_inputs,
+        ?vtxo_inputs,
+        raw_tx = %bitcoin::consensus::serialize(&tx).as_hex(),
+        "Built transaction sending inputs to on-chain address"
+    );
+
+    Ok(tx)
+}
+
+/// An input to be signed, with the exit leaf script and control block needed for its witness.
+type ExitInputToSign = (InputToSign, ScriptBuf, ControlBlock);
+
+/// The unsigned transaction, with every input ready to be signed via its exit leaf.
+fn build_batched_unilateral_exit_transaction(
+    outputs: &[(Address, Amount)],
+    change_address: Address,
+    onchain_inputs: &[OnChainInput],
+    vtxo_inputs: &[VtxoInput],
+    fee_rate: FeeRate,
+) -> Result<(Psbt, Vec<ExitInputToSign>), Error> {
     if onchain_inputs.is_empty() && vtxo_inputs.is_empty() {
         return Err(Error::transaction(
             "cannot create transaction without inputs",
         ));
     }
 
-    let secp = Secp256k1::new();
+    if outputs.is_empty() {
+        return Err(Error::transaction(
+            "cannot create transaction without outputs",
+        ));
+    }
+
+    let mut output = outputs
+        .iter()
+        .map(|(address, amount)| TxOut {
+            value: *amount,
+            script_pubkey: address.script_pubkey(),
+        })
+        .collect::<Vec<_>>();
 
-    let mut output = vec![TxOut {
-        value: to_amount,
-        script_pubkey: to_address.script_pubkey(),
-    }];
+    let to_amount: Amount = outputs.iter().map(|(_, amount)| *amount).sum();
 
     let total_amount: Amount = onchain_inputs
         .iter()
@@ -119,13 +298,23 @@ pub fn create_unilateral_exit_transaction(
         .chain(vtxo_inputs.iter().map(|v| v.amount))
         .sum();
 
-    let change_amount = total_amount.checked_sub(to_amount).ok_or_else(|| {
+    let output_scripts = outputs
+        .iter()
+        .map(|(address, _)| address.script_pubkey())
+        .chain(std::iter::once(change_address.script_pubkey()))
+        .collect::<Vec<_>>();
+
+    let fee =
+        estimate_unilateral_exit_tx_fee(fee_rate, onchain_inputs, vtxo_inputs, &output_scripts)?;
+
+    let change_amount = total_amount.checked_sub(to_amount + fee).ok_or_else(|| {
         Error::transaction(format!(
-            "cannot cover to_amount ({to_amount}) with total input amount ({total_amount})"
+            "cannot cover to_amount ({to_amount}) and fee ({fee}) with total input amount \
+                 ({total_amount})"
         ))
     })?;
 
-    if change_amount > Amount::ZERO {
+    if change_amount >= change_address.script_pubkey().minimal_non_dust() {
         output.push(TxOut {
             value: change_amount,
             script_pubkey: change_address.script_pubkey(),
@@ -188,60 +377,36 @@ pub fn create_unilateral_exit_transaction(
         .filter_map(|i| i.witness_utxo.clone())
         .collect::<Vec<_>>();
 
-    // Sign each input.
-    for (i, input) in psbt.inputs.iter_mut().enumerate() {
+    let mut inputs_to_sign = Vec::with_capacity(psbt.inputs.len());
+    for i in 0..psbt.inputs.len() {
         let outpoint = psbt.unsigned_tx.input[i].previous_output;
 
-        let (exit_script, exit_control_block) = onchain_inputs
+        let (owner, (exit_script, exit_control_block)) = onchain_inputs
             .iter()
-            .find_map(|b| (b.outpoint == outpoint).then(|| b.boarding_output.exit_spend_info()))
+            .find_map(|b| {
+                (b.outpoint == outpoint).then(|| {
+                    (
+                        b.boarding_output.owner_pk(),
+                        b.boarding_output.exit_spend_info(),
+                    )
+                })
+            })
             .or_else(|| {
-                vtxo_inputs
-                    .iter()
-                    .find_map(|v| (v.outpoint == outpoint).then(|| v.vtxo.exit_spend_info()))
+                vtxo_inputs.iter().find_map(|v| {
+                    (v.outpoint == outpoint).then(|| (v.vtxo.owner_pk(), v.vtxo.exit_spend_info()))
+                })
             })
             .expect("spend info for input");
 
         let leaf_version = exit_control_block.leaf_version;
         let leaf_hash = TapLeafHash::from_script(&exit_script, leaf_version);
 
-        let tap_sighash = SighashCache::new(&psbt.unsigned_tx)
-            .taproot_script_spend_signature_hash(
-                i,
-                &Prevouts::All(&prevouts),
-                leaf_hash,
-                TapSighashType::Default,
-            )
-            .map_err(Error::crypto)?;
+        let input = InputToSign::new(&psbt.unsigned_tx, i, &prevouts, owner, leaf_hash)?;
 
-        let msg = secp256k1::Message::from_digest(tap_sighash.to_raw_hash().to_byte_array());
-
-        let sig = secp.sign_schnorr_no_aux_rand(&msg, kp);
-        let pk = kp.x_only_public_key().0;
-
-        secp.verify_schnorr(&sig, &msg, &pk)
-            .map_err(Error::crypto)
-            .with_context(|| format!("failed to verify own signature for input {i}"))?;
-
-        let witness = Witness::from_slice(&[
-            &sig[..],
-            exit_script.as_bytes(),
-            &exit_control_block.serialize(),
-        ]);
-
-        input.final_script_witness = Some(witness);
+        inputs_to_sign.push((input, exit_script, exit_control_block));
     }
 
-    let tx = psbt.clone().extract_tx().map_err(Error::transaction)?;
-
-    tracing::debug!(
-        ?onchain_inputs,
-        ?vtxo_inputs,
-        raw_tx = %bitcoin::consensus::serialize(&tx).as_hex(),
-        "Built transaction sending inputs to on-chain address"
-    );
-
-    Ok(tx)
+    Ok((psbt, inputs_to_sign))
 }
 
 pub struct VtxoProvenance {
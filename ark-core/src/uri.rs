@@ -0,0 +1,315 @@
+//! [BIP21] payment URIs which carry an Ark address next to the on-chain address, so that a single
+//! QR code can be paid both on chain and off chain:
+//!
+//! ```text
+//! bitcoin:bc1p...?amount=0.0005&label=Coffee&ark=ark1q...
+//! ```
+//!
+//! Senders which do not know about Ark ignore the `ark` parameter and pay the on-chain address,
+//! while Ark wallets pay the Ark address instead. Either address may be left out, in which case
+//! the path of the URI is empty, e.g. `bitcoin:?ark=ark1q...`.
+//!
+//! [BIP21]: https://github.com/bitcoin/bips/blob/master/bip-0021.mediawiki
+
+use crate::ArkAddress;
+use crate::Error;
+use crate::ErrorContext;
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::Address;
+use bitcoin::Amount;
+use bitcoin::Denomination;
+use bitcoin::Network;
+use std::fmt;
+
+/// The scheme of BIP21 URIs.
+const SCHEME: &str = "bitcoin";
+
+/// The query parameter holding the Ark address.
+const ARK_PARAM: &str = "ark";
+
+/// A BIP21 payment URI, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct PaymentUri {
+    pub onchain_address: Option<Address>,
+    pub ark_address: Option<ArkAddress>,
+    pub amount: Option<Amount>,
+    /// A label for the payee, e.g. the name of a merchant.
+    pub label: Option<String>,
+    /// A description of the payment, e.g. what is being paid for.
+    pub message: Option<String>,
+}
+
+impl PaymentUri {
+    /// A URI paying `onchain_address` or `ark_address`, without an amount.
+    pub fn new(onchain_address: Address, ark_address: ArkAddress) -> Self {
+        Self {
+            onchain_address: Some(onchain_address),
+            ark_address: Some(ark_address),
+            amount: None,
+            label: None,
+            message: None,
+        }
+    }
+
+    /// A URI which can only be paid off chain, to `ark_address`.
+    pub fn ark_only(ark_address: ArkAddress) -> Self {
+        Self {
+            onchain_address: None,
+            ark_address: Some(ark_address),
+            amount: None,
+            label: None,
+            message: None,
+        }
+    }
+
+    pub fn with_amount(mut self, amount: Amount) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Parse `uri`, whose on-chain address must belong to `network`.
+    ///
+    /// Unknown parameters are ignored, unless they are prefixed with `req-`: as required by
+    /// BIP21, a URI with a required parameter that we do not understand is rejected.
+    pub fn parse(uri: &str, network: Network) -> Result<Self, Error> {
+        let (scheme, rest) = uri
+            .split_once(':')
+            .ok_or_else(|| Error::ad_hoc(format!("payment URI without scheme: {uri}")))?;
+        if !scheme.eq_ignore_ascii_case(SCHEME) {
+            return Err(Error::ad_hoc(format!(
+                "unexpected payment URI scheme: {scheme}"
+            )));
+        }
+
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+        let onchain_address = match path {
+            "" => None,
+            path => {
+                let address = path
+                    .parse::<Address<NetworkUnchecked>>()
+                    .map_err(Error::address_format)
+                    .context("invalid on-chain address in payment URI")?;
+                let address = address
+                    .require_network(network)
+                    .map_err(Error::address_format)
+                    .context("invalid on-chain address in payment URI")?;
+
+                Some(address)
+            }
+        };
+
+        let mut ark_address = None;
+        let mut amount = None;
+        let mut label = None;
+        let mut message = None;
+
+        for param in query.split('&').filter(|param| !param.is_empty()) {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            let value = percent_decode(value)
+                .with_context(|| format!("invalid value of payment URI parameter {key}"))?;
+
+            match key {
+                ARK_PARAM => {
+                    let address =
+                        ArkAddress::decode(&value).context("invalid Ark address in payment URI")?;
+                    set_once(&mut ark_address, key, address)?;
+                }
+                "amount" => {
+                    let value = Amount::from_str_in(&value, Denomination::Bitcoin)
+                        .map_err(Error::ad_hoc)
+                        .context("invalid amount in payment URI")?;
+                    set_once(&mut amount, key, value)?;
+                }
+                "label" => set_once(&mut label, key, value)?,
+                "message" => set_once(&mut message, key, value)?,
+                key if key.starts_with("req-") => {
+                    return Err(Error::ad_hoc(format!(
+                        "unsupported required payment URI parameter: {key}"
+                    )));
+                }
+                _ => {}
+            }
+        }
+
+        if onchain_address.is_none() && ark_address.is_none() {
+            return Err(Error::ad_hoc("payment URI without an address"));
+        }
+
+        Ok(Self {
+            onchain_address,
+            ark_address,
+            amount,
+            label,
+            message,
+        })
+    }
+}
+
+impl fmt::Display for PaymentUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{SCHEME}:")?;
+
+        if let Some(address) = &self.onchain_address {
+            write!(f, "{address}")?;
+        }
+
+        let mut params = Vec::new();
+        if let Some(amount) = self.amount {
+            params.push(format!(
+                "amount={}",
+                amount.display_in(Denomination::Bitcoin)
+            ));
+        }
+        if let Some(label) = &self.label {
+            params.push(format!("label={}", percent_encode(label)));
+        }
+        if let Some(message) = &self.message {
+            params.push(format!("message={}", percent_encode(message)));
+        }
+        if let Some(address) = &self.ark_address {
+            params.push(format!("{ARK_PARAM}={address}"));
+        }
+
+        if !params.is_empty() {
+            write!(f, "?{}", params.join("&"))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn set_once<T>(slot: &mut Option<T>, key: &str, value: T) -> Result<(), Error> {
+    if slot.is_some() {
+        return Err(Error::ad_hoc(format!(
+            "duplicate payment URI parameter: {key}"
+        )));
+    }
+
+    *slot = Some(value);
+
+    Ok(())
+}
+
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            byte => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+
+    encoded
+}
+
+fn percent_decode(value: &str) -> Result<String, Error> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte != b'%' {
+            bytes.push(byte);
+            rest = tail;
+            continue;
+        }
+
+        let hex = tail
+            .get(..2)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .ok_or_else(|| Error::ad_hoc("truncated percent-encoding"))?;
+        let byte = u8::from_str_radix(hex, 16)
+            .map_err(|_| Error::ad_hoc(format!("invalid percent-encoding: %{hex}")))?;
+
+        bytes.push(byte);
+        rest = &tail[2..];
+    }
+
+    String::from_utf8(bytes).map_err(Error::ad_hoc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARK_ADDRESS: &str = "tark1x0lm8hhr2wc6n6lyemtyh9rz8rg2ftpkfun46aca56kjg3ws0tsztfpuanaquxc6faedvjk3tax0575y6perapg3e95654pk8r4fjecs5fyd2";
+
+    const ONCHAIN_ADDRESS: &str = "bcrt1q8frde3yn78tl9ecgq4anlz909jh0clefhucdur";
+
+    #[test]
+    fn payment_uri_roundtrip() {
+        let onchain_address = ONCHAIN_ADDRESS
+            .parse::<Address<NetworkUnchecked>>()
+            .unwrap()
+            .assume_checked();
+        let ark_address = ArkAddress::decode(ARK_ADDRESS).unwrap();
+
+        let uri = PaymentUri::new(onchain_address, ark_address)
+            .with_amount(Amount::from_sat(50_000))
+            .with_label("Coffee & cake")
+            .with_message("Table 4");
+
+        let encoded = uri.to_string();
+        assert_eq!(
+            encoded,
+            format!(
+                "bitcoin:{ONCHAIN_ADDRESS}?amount=0.0005&label=Coffee%20%26%20cake\
+                 &message=Table%204&ark={ARK_ADDRESS}"
+            )
+        );
+
+        let decoded = PaymentUri::parse(&encoded, Network::Regtest).unwrap();
+        assert_eq!(decoded.onchain_address, uri.onchain_address);
+        assert_eq!(decoded.ark_address.unwrap().encode(), ARK_ADDRESS);
+        assert_eq!(decoded.amount, Some(Amount::from_sat(50_000)));
+        assert_eq!(decoded.label.as_deref(), Some("Coffee & cake"));
+        assert_eq!(decoded.message.as_deref(), Some("Table 4"));
+    }
+
+    #[test]
+    fn parse_payment_uri_without_onchain_address() {
+        let uri = PaymentUri::parse(
+            &format!("BITCOIN:?ark={ARK_ADDRESS}&amount=1&foo=bar"),
+            Network::Regtest,
+        )
+        .unwrap();
+
+        assert!(uri.onchain_address.is_none());
+        assert_eq!(uri.ark_address.unwrap().encode(), ARK_ADDRESS);
+        assert_eq!(uri.amount, Some(Amount::ONE_BTC));
+    }
+
+    #[test]
+    fn reject_invalid_payment_uris() {
+        let invalid = [
+            format!("lightning:?ark={ARK_ADDRESS}"),
+            "bitcoin:?amount=1".to_string(),
+            format!("bitcoin:{ONCHAIN_ADDRESS}?req-expiry=100"),
+            format!("bitcoin:{ONCHAIN_ADDRESS}?amount=1&amount=2"),
+            format!("bitcoin:{ONCHAIN_ADDRESS}?label=%E"),
+            format!("bitcoin:?ark={ONCHAIN_ADDRESS}"),
+        ];
+
+        for uri in invalid {
+            assert!(
+                PaymentUri::parse(&uri, Network::Regtest).is_err(),
+                "{uri} should be rejected"
+            );
+        }
+
+        // The on-chain address belongs to another network.
+        let uri = format!("bitcoin:{ONCHAIN_ADDRESS}");
+        assert!(PaymentUri::parse(&uri, Network::Bitcoin).is_err());
+    }
+}
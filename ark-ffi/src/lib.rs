@@ -0,0 +1,257 @@
+//! UniFFI bindings to the Ark client, so that iOS and Android wallets can embed it from Swift and
+//! Kotlin instead of implementing the protocol again.
+//!
+//! The bindings expose a single [`ArkWallet`], which holds an [`ark_client::Client`] backed by
+//! Esplora and a BDK wallet, keeping its state in an SQLite database supplied by the host. See the
+//! README of this crate for how to generate the Kotlin and Swift sources.
+
+use crate::db::SqliteDb;
+use ark_client::esplora::EsploraBlockchain;
+use ark_client::Client;
+use ark_client::OfflineClient;
+use ark_core::ArkAddress;
+use ark_core::ArkTransaction;
+use ark_core::Direction;
+use bitcoin::key::Keypair;
+use bitcoin::key::Secp256k1;
+use bitcoin::secp256k1::SecretKey;
+use bitcoin::Amount;
+use bitcoin::Network;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod db;
+
+uniffi::setup_scaffolding!();
+
+type WalletClient = Client<EsploraBlockchain, ark_bdk_wallet::Wallet<SqliteDb>>;
+
+/// An error thrown by the methods of [`ArkWallet`].
+#[derive(Debug, uniffi::Error)]
+pub enum ArkError {
+    /// An argument could not be parsed, e.g. an invalid address or secret key.
+    InvalidArgument { message: String },
+    /// The client failed to carry out the operation.
+    Client { message: String },
+}
+
+impl ArkError {
+    fn invalid_argument(message: impl fmt::Display) -> Self {
+        Self::InvalidArgument {
+            message: message.to_string(),
+        }
+    }
+
+    fn client(message: impl fmt::Display) -> Self {
+        Self::Client {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ArkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArkError::InvalidArgument { message } => write!(f, "invalid argument: {message}"),
+            ArkError::Client { message } => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for ArkError {}
+
+impl From<ark_client::Error> for ArkError {
+    fn from(value: ark_client::Error) -> Self {
+        Self::client(value)
+    }
+}
+
+/// The off-chain balance of an [`ArkWallet`], in satoshis.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct Balance {
+    /// VTXOs settled in a round with enough on-chain confirmations.
+    pub confirmed_sat: u64,
+    /// VTXOs received in a redeem transaction, which still have to be settled in a round.
+    pub pending_sat: u64,
+    /// Every VTXO, including those which cannot be spent yet, e.g. because their round is still
+    /// awaiting confirmations.
+    pub total_sat: u64,
+}
+
+/// The kind of an [`HistoryEntry`].
+#[derive(Debug, Clone, Copy, uniffi::Enum)]
+pub enum TransactionKind {
+    /// An on-chain transaction funding a boarding output.
+    Boarding,
+    /// A round transaction, which settled VTXOs.
+    Round,
+    /// An off-chain transaction, which sent VTXOs.
+    Redeem,
+}
+
+/// A transaction in the history of an [`ArkWallet`].
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct HistoryEntry {
+    pub txid: String,
+    pub kind: TransactionKind,
+    pub is_incoming: bool,
+    /// How much our balance changed by, in satoshis.
+    pub amount_sat: u64,
+    /// Only false for redeem transactions whose outputs we have not spent yet.
+    pub is_settled: bool,
+    /// In seconds since the Unix epoch. Unknown for unconfirmed boarding transactions.
+    pub created_at: Option<i64>,
+}
+
+impl From<ArkTransaction> for HistoryEntry {
+    fn from(tx: ArkTransaction) -> Self {
+        let (kind, is_settled, created_at) = match tx {
+            ArkTransaction::Boarding { confirmed_at, .. } => {
+                (TransactionKind::Boarding, true, confirmed_at)
+            }
+            ArkTransaction::Round { created_at, .. } => {
+                (TransactionKind::Round, true, Some(created_at))
+            }
+            ArkTransaction::Redeem {
+                is_settled,
+                created_at,
+                ..
+            } => (TransactionKind::Redeem, is_settled, Some(created_at)),
+        };
+
+        let amount = tx.amount();
+
+        Self {
+            txid: tx.txid().to_string(),
+            kind,
+            is_incoming: amount.direction == Direction::Incoming,
+            amount_sat: amount.net.to_sat(),
+            is_settled,
+            created_at,
+        }
+    }
+}
+
+/// A wallet connected to an Ark server, holding both off-chain VTXOs and on-chain boarding
+/// outputs.
+#[derive(uniffi::Object)]
+pub struct ArkWallet {
+    client: WalletClient,
+}
+
+#[uniffi::export(async_runtime = "tokio")]
+impl ArkWallet {
+    /// Connect to the Ark server at `ark_server_url` with the wallet of `secret_key_hex`, watching
+    /// the blockchain via the Esplora server at `esplora_url`.
+    ///
+    /// `network` is one of `bitcoin`, `testnet`, `signet` or `regtest`.
+    ///
+    /// The state of the wallet is kept in the SQLite database at `db_path`, which is created if it
+    /// does not exist. It must be in a directory private to the app, since it holds the secret keys
+    /// of the boarding outputs, and must not be shared between wallets.
+    #[uniffi::constructor]
+    pub async fn connect(
+        secret_key_hex: String,
+        network: String,
+        esplora_url: String,
+        ark_server_url: String,
+        db_path: String,
+    ) -> Result<Arc<Self>, ArkError> {
+        let secp = Secp256k1::new();
+
+        let sk = SecretKey::from_str(&secret_key_hex).map_err(ArkError::invalid_argument)?;
+        let kp = Keypair::from_secret_key(&secp, &sk);
+        let network = Network::from_str(&network).map_err(ArkError::invalid_argument)?;
+
+        let db = SqliteDb::open(&db_path, network)?;
+
+        let blockchain = EsploraBlockchain::new(&esplora_url)?;
+        let wallet = ark_bdk_wallet::Wallet::new(kp, secp, network, &esplora_url, db)
+            .map_err(ArkError::client)?;
+
+        let client = OfflineClient::new(
+            "ark-ffi".to_string(),
+            kp,
+            Arc::new(blockchain),
+            Arc::new(wallet),
+            ark_server_url,
+        )
+        .connect()
+        .await?;
+
+        Ok(Arc::new(Self { client }))
+    }
+
+    /// The address to receive VTXOs at.
+    pub fn offchain_address(&self) -> String {
+        let (address, _) = self.client.get_offchain_address();
+
+        address.encode()
+    }
+
+    /// The on-chain address to send funds to, before lifting them into the Ark with
+    /// [`ArkWallet::board`].
+    pub fn boarding_address(&self) -> Result<String, ArkError> {
+        let address = self.client.get_boarding_address()?;
+
+        Ok(address.to_string())
+    }
+
+    pub async fn balance(&self) -> Result<Balance, ArkError> {
+        let balance = self.client.offchain_balance().await?;
+
+        Ok(Balance {
+            confirmed_sat: balance.confirmed().to_sat(),
+            pending_sat: balance.pending().to_sat(),
+            total_sat: balance.total().to_sat(),
+        })
+    }
+
+    /// Send `amount_sat` to the Ark `address` off-chain, returning the TXID of the redeem
+    /// transaction.
+    pub async fn send(&self, address: String, amount_sat: u64) -> Result<String, ArkError> {
+        let address = ArkAddress::decode(&address).map_err(ArkError::invalid_argument)?;
+
+        let psbt = self
+            .client
+            .send_vtxo(address, Amount::from_sat(amount_sat))
+            .await?;
+
+        Ok(psbt.unsigned_tx.compute_txid().to_string())
+    }
+
+    /// Lift our confirmed boarding outputs and pending VTXOs into the Ark by joining the next
+    /// round.
+    pub async fn board(&self) -> Result<(), ArkError> {
+        let mut rng = StdRng::from_entropy();
+
+        self.client.board(&mut rng).await?;
+
+        Ok(())
+    }
+
+    /// Settle the VTXOs which expire within `expiring_within_secs` into new VTXOs, before the Ark
+    /// server can sweep them. Returns the TXIDs of the rounds joined, which is empty if no VTXO is
+    /// about to expire.
+    pub async fn settle(&self, expiring_within_secs: u64) -> Result<Vec<String>, ArkError> {
+        let mut rng = StdRng::from_entropy();
+
+        let txids = self
+            .client
+            .refresh_vtxos_near_expiry(&mut rng, Duration::from_secs(expiring_within_secs))
+            .await?;
+
+        Ok(txids.iter().map(ToString::to_string).collect())
+    }
+
+    /// Every transaction which changed our balance, oldest first.
+    pub async fn history(&self) -> Result<Vec<HistoryEntry>, ArkError> {
+        let history = self.client.transaction_history().await?;
+
+        Ok(history.into_iter().map(HistoryEntry::from).collect())
+    }
+}
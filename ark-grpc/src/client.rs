@@ -56,6 +56,8 @@ use std::str::FromStr;
 #[derive(Debug, Clone)]
 pub struct Client {
     url: String,
+    #[cfg(feature = "socks5")]
+    socks5_proxy: Option<String>,
     ark_client: Option<ArkServiceClient<tonic::transport::Channel>>,
     explorer_client: Option<ExplorerServiceClient<tonic::transport::Channel>>,
 }
@@ -64,25 +66,89 @@ impl Client {
     pub fn new(url: String) -> Self {
         Self {
             url,
+            #[cfg(feature = "socks5")]
+            socks5_proxy: None,
             ark_client: None,
             explorer_client: None,
         }
     }
 
+    /// Connect through the SOCKS5 proxy at `proxy`, e.g. `127.0.0.1:9050` for a local Tor daemon.
+    /// A `socks5h://` scheme is accepted too.
+    ///
+    /// The host of the Ark server is resolved by the proxy, so `.onion` servers can be used. Both
+    /// requests and event streams go through the proxy.
+    #[cfg(feature = "socks5")]
+    pub fn with_socks5_proxy(mut self, proxy: impl Into<String>) -> Self {
+        let proxy = proxy.into();
+        let proxy = match proxy.strip_prefix("socks5h://") {
+            Some(address) => address.trim_end_matches('/').to_string(),
+            None => proxy,
+        };
+
+        self.socks5_proxy = Some(proxy);
+        self
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Point the client at the Ark server at `url`, dropping the current connection.
+    ///
+    /// Any proxy stays in use.
+    pub fn set_url(&mut self, url: String) {
+        self.url = url;
+        self.ark_client = None;
+        self.explorer_client = None;
+    }
+
     pub async fn connect(&mut self) -> Result<(), Error> {
-        let ark_service_client = ArkServiceClient::connect(self.url.clone())
-            .await
-            .map_err(Error::connect)?;
-        let explorer_client = ExplorerServiceClient::connect(self.url.clone())
-            .await
-            .map_err(Error::connect)?;
+        let endpoint =
+            tonic::transport::Endpoint::from_shared(self.url.clone()).map_err(Error::connect)?;
+
+        #[cfg(feature = "socks5")]
+        let channel = match &self.socks5_proxy {
+            Some(proxy) => {
+                endpoint
+                    .connect_with_connector(crate::socks5::Socks5Connector::new(proxy.clone()))
+                    .await
+            }
+            None => endpoint.connect().await,
+        };
+        #[cfg(not(feature = "socks5"))]
+        let channel = endpoint.connect().await;
 
-        self.ark_client = Some(ark_service_client);
-        self.explorer_client = Some(explorer_client);
+        let channel = channel.map_err(Error::connect)?;
+
+        self.ark_client = Some(ArkServiceClient::new(channel.clone()));
+        self.explorer_client = Some(ExplorerServiceClient::new(channel));
+        Ok(())
+    }
+
+    /// Set up the connection to the Ark server without waiting for it to be established.
+    ///
+    /// The connection is only established on the first request, and it is re-established if it
+    /// drops.
+    pub fn connect_lazy(&mut self) -> Result<(), Error> {
+        let endpoint =
+            tonic::transport::Endpoint::from_shared(self.url.clone()).map_err(Error::connect)?;
+
+        #[cfg(feature = "socks5")]
+        let channel = match &self.socks5_proxy {
+            Some(proxy) => endpoint
+                .connect_with_connector_lazy(crate::socks5::Socks5Connector::new(proxy.clone())),
+            None => endpoint.connect_lazy(),
+        };
+        #[cfg(not(feature = "socks5"))]
+        let channel = endpoint.connect_lazy();
+
+        self.ark_client = Some(ArkServiceClient::new(channel.clone()));
+        self.explorer_client = Some(ExplorerServiceClient::new(channel));
         Ok(())
     }
 
-    pub async fn get_info(&mut self) -> Result<Info, Error> {
+    pub async fn get_info(&self) -> Result<Info, Error> {
         let mut client = self.inner_ark_client()?;
 
         let response = client
@@ -332,17 +398,23 @@ impl Client {
         Ok(stream.boxed())
     }
 
+    /// The round and out-of-round transactions of the Ark server, starting with the next one.
+    ///
+    /// Returns `None` if the Ark server does not support streaming transactions.
     pub async fn get_tx_stream(
         &self,
-    ) -> Result<impl Stream<Item = Result<TransactionEvent, Error>> + Unpin, Error> {
+    ) -> Result<Option<impl Stream<Item = Result<TransactionEvent, Error>> + Unpin>, Error> {
         let mut client = self.inner_ark_client()?;
 
         let response = client
             .get_transactions_stream(GetTransactionsStreamRequest {})
-            .await
-            .map_err(Error::request)?;
+            .await;
 
-        let mut stream = response.into_inner();
+        let mut stream = match response {
+            Ok(response) => response.into_inner(),
+            Err(status) if status.code() == tonic::Code::Unimplemented => return Ok(None),
+            Err(status) => return Err(Error::request(status)),
+        };
 
         let stream = stream! {
             loop {
@@ -365,7 +437,7 @@ impl Client {
             }
         };
 
-        Ok(stream.boxed())
+        Ok(Some(stream.boxed()))
     }
 
     pub async fn get_round(&self, round_txid: String) -> Result<Option<Round>, Error> {
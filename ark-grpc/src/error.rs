@@ -63,6 +63,26 @@ impl Error {
         Error::new(Kind::EventStream).with(source)
     }
 
+    /// Whether the request may succeed if it is made again, because it failed due to a dropped
+    /// connection or an Ark server which is temporarily unavailable.
+    pub fn is_transient(&self) -> bool {
+        match self.inner.kind {
+            Kind::Connect | Kind::EventStreamDisconnect => true,
+            Kind::Request | Kind::EventStream => self
+                .inner
+                .source
+                .as_ref()
+                .and_then(|source| source.downcast_ref::<tonic::Status>())
+                .is_some_and(|status| {
+                    matches!(
+                        status.code(),
+                        tonic::Code::Unavailable | tonic::Code::DeadlineExceeded
+                    )
+                }),
+            Kind::NotConnected | Kind::Conversion | Kind::Ping => false,
+        }
+    }
+
     fn description(&self) -> &str {
         match &self.inner.kind {
             Kind::Connect => "failed to connect to Ark server",
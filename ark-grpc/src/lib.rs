@@ -11,6 +11,8 @@ mod generated {
 pub mod client;
 
 mod error;
+#[cfg(feature = "socks5")]
+mod socks5;
 mod tree;
 mod types;
 
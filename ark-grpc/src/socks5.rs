@@ -0,0 +1,59 @@
+//! Connecting to the Ark server through a SOCKS5 proxy, e.g. to reach `.onion` servers via Tor.
+
+use hyper_util::rt::TokioIo;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+use tokio::net::TcpStream;
+use tokio_socks::tcp::Socks5Stream;
+use tonic::transport::Uri;
+use tower_service::Service;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Opens TCP connections to the Ark server through the SOCKS5 proxy at `proxy`.
+///
+/// The host of the Ark server is resolved by the proxy (as with a `socks5h` URL), so it never
+/// leaks to the local DNS resolver.
+#[derive(Debug, Clone)]
+pub(crate) struct Socks5Connector {
+    proxy: String,
+}
+
+impl Socks5Connector {
+    pub(crate) fn new(proxy: String) -> Self {
+        Self { proxy }
+    }
+}
+
+impl Service<Uri> for Socks5Connector {
+    type Response = TokioIo<Socks5Stream<TcpStream>>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let proxy = self.proxy.clone();
+
+        Box::pin(async move {
+            let host = uri
+                .host()
+                .ok_or_else(|| format!("Ark server URL {uri} has no host"))?;
+            let port = match (uri.port_u16(), uri.scheme_str()) {
+                (Some(port), _) => port,
+                (None, Some("https")) => 443,
+                (None, _) => 80,
+            };
+
+            let stream = Socks5Stream::connect(proxy.as_str(), (host, port))
+                .await
+                .map_err(|e| format!("failed to connect to {host}:{port} through {proxy}: {e}"))?;
+
+            Ok(TokioIo::new(stream))
+        })
+    }
+}
@@ -1,5 +1,6 @@
 use crate::generated;
 use crate::Error;
+use ark_core::exit_delay::ExitDelay;
 use ark_core::server;
 use base64::Engine;
 use bitcoin::address::NetworkUnchecked;
@@ -14,12 +15,13 @@ impl TryFrom<generated::ark::v1::GetInfoResponse> for server::Info {
     fn try_from(value: generated::ark::v1::GetInfoResponse) -> Result<Self, Self::Error> {
         let pk = value.pubkey.parse().map_err(Error::conversion)?;
 
-        let vtxo_tree_expiry = bitcoin::Sequence::from_seconds_ceil(value.vtxo_tree_expiry as u32)
-            .map_err(Error::conversion)?;
+        let vtxo_tree_expiry = ExitDelay::from_server_value(value.vtxo_tree_expiry)
+            .map_err(Error::conversion)?
+            .to_sequence();
 
-        let unilateral_exit_delay =
-            bitcoin::Sequence::from_seconds_ceil(value.unilateral_exit_delay as u32)
-                .map_err(Error::conversion)?;
+        let unilateral_exit_delay = ExitDelay::from_server_value(value.unilateral_exit_delay)
+            .map_err(Error::conversion)?
+            .to_sequence();
 
         let network = value.network.parse().map_err(Error::conversion)?;
 
@@ -39,6 +41,14 @@ impl TryFrom<generated::ark::v1::GetInfoResponse> for server::Info {
             boarding_descriptor_template: value.boarding_descriptor_template,
             vtxo_descriptor_templates: value.vtxo_descriptor_templates,
             forfeit_address,
+            market_hour: value.market_hour.map(|market_hour| server::MarketHour {
+                next_start_time: market_hour.next_start_time,
+                next_end_time: market_hour.next_end_time,
+                period: market_hour.period,
+                round_interval: market_hour.round_interval,
+            }),
+            // The server does not publish a fee schedule.
+            fees: None,
         })
     }
 }
@@ -80,6 +90,8 @@ impl TryFrom<&generated::ark::v1::Vtxo> for server::VtxoOutPoint {
             round_txid: value.round_txid.parse().map_err(Error::conversion)?,
             spent_by,
             expire_at: value.expire_at,
+            // The server reports the expiry of the VTXO tree, which is when it can sweep it.
+            sweepable_after: (value.expire_at > 0).then_some(value.expire_at),
             swept: value.swept,
             is_pending: value.is_pending,
             redeem_tx,
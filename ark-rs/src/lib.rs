@@ -1,5 +1,9 @@
+#[cfg(feature = "bdk-wallet")]
+pub use ark_bdk_wallet as bdk_wallet;
 #[cfg(feature = "client")]
 pub use ark_client as client;
 pub use ark_core as core;
 #[cfg(feature = "grpc")]
 pub use ark_grpc as grpc;
+#[cfg(feature = "rest")]
+pub use ark_rest as rest;
@@ -4,6 +4,10 @@
 use anyhow::bail;
 use anyhow::Result;
 use ark_core::coin_select::select_vtxos;
+use ark_core::exit_delay::median_time_past;
+use ark_core::exit_delay::BlockTime;
+use ark_core::exit_delay::ExitDelay;
+use ark_core::exit_delay::MEDIAN_TIME_SPAN;
 use ark_core::generate_incoming_vtxo_transaction_history;
 use ark_core::generate_outgoing_vtxo_transaction_history;
 use ark_core::redeem;
@@ -21,6 +25,8 @@ use ark_core::ArkAddress;
 use ark_core::ArkTransaction;
 use ark_core::BoardingOutput;
 use ark_core::DefaultVtxo;
+use ark_core::Direction;
+use ark_core::TransactionAmount;
 use bitcoin::key::Keypair;
 use bitcoin::key::Secp256k1;
 use bitcoin::secp256k1;
@@ -176,9 +182,12 @@ async fn main() -> Result<()> {
             let boarding_address = boarding_output.address();
 
             println!("Send coins to this on-chain address: {boarding_address}\n");
+            let exit_delay = match boarding_output.typed_exit_delay()? {
+                ExitDelay::Blocks(blocks) => format!("{blocks} blocks"),
+                ExitDelay::Seconds(seconds) => format!("{seconds} seconds"),
+            };
             println!(
-                "Once confirmed, you will have {} seconds to exchange the boarding output for a VTXO.",
-                boarding_output.exit_delay_duration().as_secs()
+                "Once confirmed, you will have {exit_delay} to exchange the boarding output for a VTXO."
             );
         }
         Commands::OffchainAddress => {
@@ -222,7 +231,7 @@ async fn main() -> Result<()> {
             let kp = Keypair::from_secret_key(&secp, &sk);
 
             let signed_redeem_psbt = create_and_sign_redeem_transaction(
-                &kp,
+                &[kp],
                 &address.0,
                 amount,
                 &change_address,
@@ -307,7 +316,6 @@ async fn list_vtxos(
         let onchain_vtxos = onchain_explorer.find_outpoints(vtxo.address()).await?;
 
         for vtxo_outpoint in vtxo_outpoints.spendable {
-            let now = std::time::UNIX_EPOCH.elapsed()?;
             match onchain_vtxos
                 .iter()
                 .find(|onchain_utxo| onchain_utxo.outpoint == vtxo_outpoint.outpoint)
@@ -315,11 +323,11 @@ async fn list_vtxos(
                 // VTXOs that have been confirmed on the blockchain, but whose
                 // exit path is now _active_, have expired.
                 Some(ExplorerUtxo {
-                    confirmation_blocktime: Some(confirmation_blocktime),
+                    confirmation_height: Some(confirmation_height),
                     ..
                 }) if vtxo.can_be_claimed_unilaterally_by_owner(
-                    now,
-                    Duration::from_secs(*confirmation_blocktime),
+                    onchain_explorer.confirmed_at(*confirmation_height).await?,
+                    onchain_explorer.tip().await?,
                 ) =>
                 {
                     expired.push((vtxo_outpoint, vtxo.clone()));
@@ -379,18 +387,19 @@ async fn list_boarding_outputs(
             match *boarding_utxo {
                 // The boarding output can be found on-chain.
                 ExplorerUtxo {
-                    confirmation_blocktime: Some(confirmation_blocktime),
+                    confirmation_blocktime: Some(_),
+                    confirmation_height: Some(confirmation_height),
                     outpoint,
                     amount,
                     is_spent: false,
                 } => {
-                    let now = std::time::UNIX_EPOCH.elapsed()?;
+                    let confirmed = onchain_explorer.confirmed_at(confirmation_height).await?;
 
                     // If the boarding output is on-chain can be spent unilaterally, it has expired.
                     if boarding_output.can_be_claimed_unilaterally_by_owner(
-                        now,
-                        Duration::from_secs(confirmation_blocktime),
-                    ) {
+                        confirmed,
+                        onchain_explorer.tip().await?,
+                    )? {
                         expired.push((outpoint, amount, boarding_output.clone()));
                     }
                     // If the boarding output is on-chain and cannot be spent unilaterally, it is
@@ -401,10 +410,10 @@ async fn list_boarding_outputs(
                 }
                 // The boarding output is still pending confirmation.
                 ExplorerUtxo {
-                    confirmation_blocktime: None,
                     outpoint,
                     amount,
                     is_spent: false,
+                    ..
                 } => {
                     pending.push((outpoint, amount, boarding_output.clone()));
                 }
@@ -560,7 +569,7 @@ async fn settle(
 
     let keypair = Keypair::from_secret_key(&secp, &sk);
     let signed_forfeit_psbts = create_and_sign_forfeit_txs(
-        &keypair,
+        &[keypair],
         vtxo_inputs.as_slice(),
         round_finalization_event.connector_tree,
         &round_finalization_event.connectors_index,
@@ -572,7 +581,9 @@ async fn settle(
     let onchain_inputs = boarding_outputs
         .spendable
         .into_iter()
-        .map(|(outpoint, _, boarding_output)| round::OnChainInput::new(boarding_output, outpoint))
+        .map(|(outpoint, amount, boarding_output)| {
+            round::OnChainInput::new(boarding_output, amount, outpoint)
+        })
         .collect::<Vec<_>>();
 
     let round_psbt = if round_inputs.is_empty() {
@@ -616,6 +627,7 @@ pub struct ExplorerUtxo {
     pub outpoint: OutPoint,
     pub amount: Amount,
     pub confirmation_blocktime: Option<u64>,
+    pub confirmation_height: Option<u32>,
     pub is_spent: bool,
 }
 
@@ -654,6 +666,7 @@ impl EsploraClient {
                         },
                         amount: Amount::from_sat(v.value),
                         confirmation_blocktime: tx.status.block_time,
+                        confirmation_height: tx.status.block_height,
                         // Assume the output is unspent until we dig deeper, further down.
                         is_spent: false,
                     })
@@ -685,6 +698,51 @@ impl EsploraClient {
         Ok(utxos)
     }
 
+    /// The tip of the blockchain, with its median time past.
+    async fn tip(&self) -> Result<BlockTime> {
+        let height = self.esplora_client.get_height().await?;
+
+        Ok(BlockTime::new(height, self.median_time_past(height).await?))
+    }
+
+    /// An output confirmed at `height`, with the median time past of the block before it, from
+    /// which BIP68 counts time-based exit delays.
+    async fn confirmed_at(&self, height: u32) -> Result<BlockTime> {
+        let median_time_past = self.median_time_past(height.saturating_sub(1)).await?;
+
+        Ok(BlockTime::new(height, median_time_past))
+    }
+
+    async fn median_time_past(&self, height: u32) -> Result<Duration> {
+        let lowest_height = height.saturating_sub(MEDIAN_TIME_SPAN as u32 - 1);
+
+        // Esplora returns the summaries of a few blocks at a time, from the requested height down.
+        let mut timestamps = Vec::new();
+        let mut next_height = Some(height);
+        while let Some(from_height) = next_height {
+            let blocks = self.esplora_client.get_blocks(Some(from_height)).await?;
+
+            timestamps.extend(
+                blocks
+                    .iter()
+                    .filter(|block| (lowest_height..=height).contains(&block.time.height))
+                    .map(|block| block.time.timestamp),
+            );
+
+            next_height = blocks
+                .iter()
+                .map(|block| block.time.height)
+                .min()
+                .filter(|min_height| *min_height > lowest_height && *min_height <= from_height)
+                .map(|min_height| min_height - 1);
+        }
+
+        match median_time_past(&timestamps) {
+            Some(median_time_past) => Ok(median_time_past),
+            None => bail!("no block at height {height}"),
+        }
+    }
+
     async fn get_output_status(&self, txid: &Txid, vout: u32) -> Result<SpendStatus> {
         let status = self
             .esplora_client
@@ -720,7 +778,7 @@ async fn transaction_history(
 
             boarding_transactions.push(ArkTransaction::Boarding {
                 txid: outpoint.txid,
-                amount: *amount,
+                amount: TransactionAmount::incoming(*amount),
                 confirmed_at,
             });
 
@@ -777,6 +835,8 @@ fn pretty_print_transaction(tx: &ArkTransaction) -> Result<String> {
                 None => "Pending confirmation".to_string(),
             };
 
+            let amount = amount.net;
+
             format!(
                 "Type: Boarding\n\
                  TXID: {txid}\n\
@@ -789,13 +849,14 @@ fn pretty_print_transaction(tx: &ArkTransaction) -> Result<String> {
             txid,
             amount,
             created_at,
+            ..
         } => {
-            let status = match amount.is_positive() {
-                true => "Received",
-                false => "Sent",
+            let status = match amount.direction {
+                Direction::Incoming => "Received",
+                Direction::Outgoing => "Sent",
             };
 
-            let amount = amount.abs();
+            let amount = amount.net;
 
             let time = Timestamp::from_second(*created_at)?;
 
@@ -812,10 +873,11 @@ fn pretty_print_transaction(tx: &ArkTransaction) -> Result<String> {
             amount,
             is_settled,
             created_at,
+            ..
         } => {
-            let status = match amount.is_positive() {
-                true => "Received",
-                false => "Sent",
+            let status = match amount.direction {
+                Direction::Incoming => "Received",
+                Direction::Outgoing => "Sent",
             };
 
             let settlement = match is_settled {
@@ -823,7 +885,7 @@ fn pretty_print_transaction(tx: &ArkTransaction) -> Result<String> {
                 false => "Pending",
             };
 
-            let amount = amount.abs();
+            let amount = amount.net;
 
             let time = Timestamp::from_second(*created_at)?;
 
@@ -0,0 +1,189 @@
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::print_stdout)]
+
+//! Run many concurrent clients through boarding, payments and refreshes against an Ark server, and
+//! report latency and failure statistics per operation.
+//!
+//! Requires a running Nigiri instance to fund the boarding outputs of the clients.
+
+use bitcoin::key::Secp256k1;
+use bitcoin::Amount;
+use clap::Parser;
+use common::init_tracing;
+use common::set_up_client_with_server_url;
+use common::Nigiri;
+use futures::future::join_all;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+#[path = "../../tests/common.rs"]
+mod common;
+
+#[derive(Parser)]
+#[command(name = "load-test")]
+#[command(about = "Simulate concurrent round participation against an Ark server")]
+struct Cli {
+    /// URL of the Ark server under test.
+    #[arg(long, default_value = "http://localhost:7070")]
+    ark_server_url: String,
+
+    /// Number of concurrent clients.
+    #[arg(short, long, default_value_t = 10)]
+    clients: usize,
+
+    /// Number of send-and-refresh iterations per client.
+    #[arg(short, long, default_value_t = 3)]
+    iterations: usize,
+
+    /// How many sats to fund each client's boarding output with.
+    #[arg(long, default_value_t = 1_000_000)]
+    fund_amount: u64,
+
+    /// How many sats each client sends to its neighbour per iteration.
+    #[arg(long, default_value_t = 10_000)]
+    send_amount: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Operation {
+    Board,
+    Send,
+    Refresh,
+}
+
+#[derive(Default)]
+struct Stats {
+    latencies: Vec<Duration>,
+    failures: usize,
+}
+
+impl Stats {
+    fn percentile(&self, p: f64) -> Option<Duration> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+
+        let mut latencies = self.latencies.clone();
+        latencies.sort();
+
+        let i = ((latencies.len() - 1) as f64 * p).round() as usize;
+
+        latencies.get(i).copied()
+    }
+}
+
+type Samples = Vec<(Operation, Result<Duration, String>)>;
+
+async fn measure<F, T, E>(samples: &mut Samples, operation: Operation, fut: F)
+where
+    F: Future<Output = Result<T, E>>,
+    E: std::fmt::Display,
+{
+    let start = Instant::now();
+    match fut.await {
+        Ok(_) => {
+            samples.push((operation, Ok(start.elapsed())));
+        }
+        Err(e) => {
+            tracing::warn!(?operation, "Operation failed: {e}");
+            samples.push((operation, Err(e.to_string())));
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    init_tracing();
+
+    let cli = Cli::parse();
+
+    let nigiri = Arc::new(Nigiri::new());
+    let secp = Secp256k1::new();
+
+    let clients = join_all((0..cli.clients).map(|i| {
+        set_up_client_with_server_url(
+            format!("client-{i}"),
+            nigiri.clone(),
+            secp.clone(),
+            &cli.ark_server_url,
+        )
+    }))
+    .await;
+
+    for client in clients.iter() {
+        let boarding_address = client.get_boarding_address().unwrap();
+        nigiri
+            .faucet_fund(&boarding_address, Amount::from_sat(cli.fund_amount))
+            .await;
+    }
+
+    tracing::info!(clients = cli.clients, "Funded all boarding outputs");
+
+    let send_amount = Amount::from_sat(cli.send_amount);
+    let iterations = cli.iterations;
+    let start = Instant::now();
+
+    let samples = join_all(clients.iter().enumerate().map(|(i, client)| {
+        let (to_address, _) = clients[(i + 1) % clients.len()].get_offchain_address();
+
+        async move {
+            let mut rng = StdRng::from_entropy();
+            let mut samples = Samples::new();
+
+            measure(&mut samples, Operation::Board, client.board(&mut rng)).await;
+
+            for _ in 0..iterations {
+                measure(
+                    &mut samples,
+                    Operation::Send,
+                    client.send_vtxo(to_address, send_amount),
+                )
+                .await;
+
+                measure(&mut samples, Operation::Refresh, client.board(&mut rng)).await;
+            }
+
+            samples
+        }
+    }))
+    .await;
+
+    let total_duration = start.elapsed();
+
+    let mut stats = BTreeMap::<Operation, Stats>::new();
+    for (operation, result) in samples.into_iter().flatten() {
+        let stats = stats.entry(operation).or_default();
+        match result {
+            Ok(latency) => stats.latencies.push(latency),
+            Err(_) => stats.failures += 1,
+        }
+    }
+
+    println!(
+        "{} clients, {} iterations, finished in {total_duration:?}\n",
+        cli.clients, cli.iterations
+    );
+    println!(
+        "{:<10} {:>6} {:>8} {:>12} {:>12} {:>12}",
+        "operation", "ok", "failed", "p50", "p95", "max"
+    );
+    for (operation, stats) in stats.iter() {
+        println!(
+            "{:<10} {:>6} {:>8} {:>12} {:>12} {:>12}",
+            format!("{operation:?}"),
+            stats.latencies.len(),
+            stats.failures,
+            format!("{:.2?}", stats.percentile(0.5).unwrap_or_default()),
+            format!("{:.2?}", stats.percentile(0.95).unwrap_or_default()),
+            format!(
+                "{:.2?}",
+                stats.latencies.iter().max().copied().unwrap_or_default()
+            ),
+        );
+    }
+}
@@ -1,13 +1,31 @@
 #![allow(clippy::unwrap_used)]
 
+use ark_client::config::ClientConfig;
+use ark_client::contacts::Contact;
 use ark_client::error::Error;
+use ark_client::tx_broadcast::BroadcastError;
+use ark_client::wallet::ClientStore;
+use ark_client::wallet::ExitTx;
+use ark_client::wallet::ForfeitRecord;
 use ark_client::wallet::Persistence;
+use ark_client::wallet::VtxoExit;
+use ark_client::wallet::VtxoOrigin;
+use ark_client::wallet::VtxoRiskStatus;
+use ark_client::wallet::WalletBirthday;
+use ark_client::watch_only::WatchedAddress;
 use ark_client::Blockchain;
 use ark_client::Client;
 use ark_client::ExplorerUtxo;
 use ark_client::OfflineClient;
 use ark_client::SpendStatus;
+use ark_core::receipt::PaymentReceipt;
+use ark_core::server;
+use ark_core::server::ListVtxo;
+use ark_core::server::VtxoOutPoint;
+use ark_core::ArkAddress;
+use ark_core::ArkTransaction;
 use ark_core::BoardingOutput;
+use ark_core::Direction;
 use bitcoin::hex::FromHex;
 use bitcoin::key::Keypair;
 use bitcoin::key::Secp256k1;
@@ -20,22 +38,27 @@ use bitcoin::OutPoint;
 use bitcoin::Transaction;
 use bitcoin::Txid;
 use bitcoin::XOnlyPublicKey;
+use jiff::Timestamp;
 use rand::thread_rng;
 use regex::Regex;
+use std::collections::HashMap;
 use std::process::Command;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::Once;
 use std::sync::RwLock;
+use std::time::Duration;
 
 pub struct Nigiri {
     esplora_client: esplora_client::BlockingClient,
-    /// By how much we _reduce_ the block time of outpoints. A lower block time indicates that an
-    /// outpoint was confirmed longer ago.
+    /// How far the chain time was moved forward with [`Nigiri::advance_chain_time`].
     ///
-    /// This can be used to ensure that certain outpoints are considered spendable, which is useful
-    /// for testing scripts with opcodes such as `OP_CSV`.
-    outpoint_blocktime_offset: RwLock<u64>,
+    /// We _reduce_ the block time of outpoints, and the median time past of every block but the
+    /// tip, by this much. A lower block time indicates that an outpoint was confirmed longer ago,
+    /// which is useful for testing scripts with opcodes such as `OP_CSV` without waiting.
+    chain_time_offset: RwLock<u64>,
+    /// The transactions broadcast through this client, in order.
+    broadcast_txids: RwLock<Vec<Txid>>,
 }
 
 impl Nigiri {
@@ -45,7 +68,8 @@ impl Nigiri {
 
         Self {
             esplora_client,
-            outpoint_blocktime_offset: RwLock::new(0),
+            chain_time_offset: RwLock::new(0),
+            broadcast_txids: RwLock::new(Vec::new()),
         }
     }
 
@@ -90,7 +114,7 @@ impl Nigiri {
             .unwrap();
 
         // Wait for output to be confirmed.
-        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+        tokio::time::sleep(Duration::from_secs(5)).await;
 
         OutPoint {
             txid,
@@ -98,10 +122,104 @@ impl Nigiri {
         }
     }
 
+    /// Move the chain time forward by `duration`: outpoints look like they were confirmed
+    /// `duration` earlier.
     #[allow(unused)]
-    pub fn set_outpoint_blocktime_offset(&self, outpoint_blocktime_offset: u64) {
-        let mut guard = self.outpoint_blocktime_offset.write().unwrap();
-        *guard = outpoint_blocktime_offset;
+    pub fn advance_chain_time(&self, duration: Duration) {
+        let mut guard = self.chain_time_offset.write().unwrap();
+        *guard += duration.as_secs();
+
+        tracing::debug!(offset = *guard, "Advanced chain time");
+    }
+
+    /// Move the chain time forward until `timestamp`, as reported by the client before this call,
+    /// has been reached by the median time past of the tip.
+    ///
+    /// Use this with [`Client::exit_claimable_at`] to open an exit path exactly.
+    #[allow(unused)]
+    pub fn advance_chain_time_to(&self, timestamp: Timestamp) {
+        let tip = self.esplora_client.get_height().unwrap();
+        let remaining = timestamp.as_second() - self.node_median_time_past(tip) as i64;
+
+        self.advance_chain_time(Duration::from_secs(remaining.max(0) as u64));
+    }
+
+    /// The median time past of the block at `height`, as reported by the node.
+    fn node_median_time_past(&self, height: u32) -> u64 {
+        let rpc = |args: &[&str]| {
+            let res = Command::new("nigiri")
+                .arg("rpc")
+                .args(args)
+                .output()
+                .unwrap();
+
+            assert!(res.status.success(), "nigiri rpc {args:?} failed: {res:?}");
+
+            String::from_utf8(res.stdout).unwrap()
+        };
+
+        let block_hash = rpc(&["getblockhash", &height.to_string()]);
+        let header: serde_json::Value =
+            serde_json::from_str(&rpc(&["getblockheader", block_hash.trim()])).unwrap();
+
+        header["mediantime"].as_u64().unwrap()
+    }
+
+    /// Mine `n` blocks with a block time of `timestamp`, in UNIX seconds.
+    ///
+    /// The node only accepts a `timestamp` after the median time of the last 11 blocks.
+    #[allow(unused)]
+    pub async fn mine_at(&self, n: u32, timestamp: i64) {
+        let rpc = |args: &[&str]| {
+            let res = Command::new("nigiri")
+                .arg("rpc")
+                .args(args)
+                .output()
+                .unwrap();
+
+            assert!(res.status.success(), "nigiri rpc {args:?} failed: {res:?}");
+        };
+
+        rpc(&["setmocktime", &timestamp.to_string()]);
+        rpc(&[
+            "generatetoaddress",
+            &n.to_string(),
+            "bcrt1q8frde3yn78tl9ecgq4anlz909jh0clefhucdur",
+        ]);
+        rpc(&["setmocktime", "0"]);
+
+        // Wait for the blocks to be indexed.
+        tokio::time::sleep(Duration::from_secs(5)).await;
+
+        tracing::debug!(n, timestamp, "Mined blocks at chosen time");
+    }
+
+    /// Send `amount` to `address` without mining a block, e.g. to confirm it with
+    /// [`Nigiri::mine_at`].
+    #[allow(unused)]
+    pub fn send_unconfirmed(&self, address: &Address, amount: Amount) -> Txid {
+        let res = Command::new("nigiri")
+            .args([
+                "rpc",
+                "sendtoaddress",
+                &address.to_string(),
+                &amount.to_btc().to_string(),
+            ])
+            .output()
+            .unwrap();
+
+        assert!(res.status.success());
+
+        String::from_utf8(res.stdout)
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap()
+    }
+
+    #[allow(unused)]
+    pub fn broadcast_txids(&self) -> Vec<Txid> {
+        self.broadcast_txids.read().unwrap().clone()
     }
 
     #[allow(unused)]
@@ -142,7 +260,8 @@ impl Blockchain for Nigiri {
                 let confirmation_blocktime = tx
                     .status
                     .block_time
-                    .map(|t| t - *self.outpoint_blocktime_offset.read().unwrap());
+                    .map(|t| t - *self.chain_time_offset.read().unwrap());
+                let confirmation_height = tx.status.block_height;
 
                 tx.vout
                     .iter()
@@ -155,6 +274,7 @@ impl Blockchain for Nigiri {
                         },
                         amount: Amount::from_sat(v.value),
                         confirmation_blocktime,
+                        confirmation_height,
                         // Assume the output is unspent until we dig deeper, further down.
                         is_spent: false,
                     })
@@ -203,18 +323,69 @@ impl Blockchain for Nigiri {
         })
     }
 
-    // TODO: Make sure we return a proper error here, so that we can retry if we encounter a
-    // `bad-txns-inputs-missingorspent` error.
     async fn broadcast(&self, tx: &Transaction) -> Result<(), Error> {
-        self.esplora_client.broadcast(tx).unwrap();
+        self.esplora_client
+            .broadcast(tx)
+            .map_err(|e| Error::broadcast(BroadcastError::from_reason(e.to_string())))?;
+
+        self.broadcast_txids
+            .write()
+            .unwrap()
+            .push(tx.compute_txid());
 
         Ok(())
     }
+
+    async fn get_confirmations(&self, txid: &Txid) -> Result<u32, Error> {
+        let status = self.esplora_client.get_tx_status(txid).unwrap();
+
+        let confirmations = match status.block_height {
+            Some(block_height) => {
+                let tip = self.esplora_client.get_height().unwrap();
+                tip.saturating_sub(block_height) + 1
+            }
+            None => 0,
+        };
+
+        Ok(confirmations)
+    }
+
+    async fn get_tip_height(&self) -> Result<u32, Error> {
+        Ok(self.esplora_client.get_height().unwrap())
+    }
+
+    async fn get_median_time_past(&self, height: u32) -> Result<u64, Error> {
+        let median_time_past = self.node_median_time_past(height);
+
+        // Like outpoints, older blocks look like they were mined earlier.
+        if height < self.esplora_client.get_height().unwrap() {
+            return Ok(median_time_past - *self.chain_time_offset.read().unwrap());
+        }
+
+        Ok(median_time_past)
+    }
 }
 
 #[derive(Default)]
 pub struct InMemoryDb {
     boarding_outputs: RwLock<Vec<(SecretKey, BoardingOutput)>>,
+    vtxo_origins: RwLock<HashMap<OutPoint, VtxoOrigin>>,
+    server_info: RwLock<Option<server::Info>>,
+    vtxo_lists: RwLock<HashMap<String, (ListVtxo, i64)>>,
+    vtxo_risk_statuses: RwLock<HashMap<OutPoint, VtxoRiskStatus>>,
+    forfeits: RwLock<Vec<ForfeitRecord>>,
+    birthday: RwLock<Option<WalletBirthday>>,
+    config: RwLock<Option<ClientConfig>>,
+    claimed_deliveries: RwLock<Vec<OutPoint>>,
+    deliveries_initialized: RwLock<bool>,
+    exit_txs: RwLock<HashMap<Txid, ExitTx>>,
+    imported_vtxos: RwLock<HashMap<String, Vec<VtxoOutPoint>>>,
+    contacts: RwLock<HashMap<String, Contact>>,
+    receipts: RwLock<HashMap<OutPoint, PaymentReceipt>>,
+    processed_events: RwLock<Vec<String>>,
+    vtxo_exits: RwLock<HashMap<OutPoint, VtxoExit>>,
+    watched_addresses: RwLock<HashMap<String, WatchedAddress>>,
+    archived_transactions: RwLock<HashMap<(Txid, bool), ArkTransaction>>,
 }
 
 impl Persistence for InMemoryDb {
@@ -254,10 +425,304 @@ impl Persistence for InMemoryDb {
     }
 }
 
+impl ClientStore for InMemoryDb {
+    fn save_vtxo_origin(&self, outpoint: OutPoint, origin: VtxoOrigin) -> Result<(), Error> {
+        self.vtxo_origins.write().unwrap().insert(outpoint, origin);
+
+        Ok(())
+    }
+
+    fn load_vtxo_origin(&self, outpoint: &OutPoint) -> Result<Option<VtxoOrigin>, Error> {
+        Ok(self.vtxo_origins.read().unwrap().get(outpoint).cloned())
+    }
+
+    fn save_server_info(&self, info: server::Info) -> Result<(), Error> {
+        *self.server_info.write().unwrap() = Some(info);
+
+        Ok(())
+    }
+
+    fn load_server_info(&self) -> Result<Option<server::Info>, Error> {
+        Ok(self.server_info.read().unwrap().clone())
+    }
+
+    fn save_vtxo_list(
+        &self,
+        address: ArkAddress,
+        vtxos: ListVtxo,
+        updated_at: i64,
+    ) -> Result<(), Error> {
+        self.vtxo_lists
+            .write()
+            .unwrap()
+            .insert(address.encode(), (vtxos, updated_at));
+
+        Ok(())
+    }
+
+    fn load_vtxo_list(&self, address: &ArkAddress) -> Result<Option<(ListVtxo, i64)>, Error> {
+        Ok(self
+            .vtxo_lists
+            .read()
+            .unwrap()
+            .get(&address.encode())
+            .cloned())
+    }
+
+    fn save_vtxo_risk_status(
+        &self,
+        outpoint: OutPoint,
+        status: VtxoRiskStatus,
+    ) -> Result<(), Error> {
+        self.vtxo_risk_statuses
+            .write()
+            .unwrap()
+            .insert(outpoint, status);
+
+        Ok(())
+    }
+
+    fn load_vtxo_risk_statuses(&self) -> Result<Vec<(OutPoint, VtxoRiskStatus)>, Error> {
+        Ok(self
+            .vtxo_risk_statuses
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(outpoint, status)| (*outpoint, status.clone()))
+            .collect())
+    }
+
+    fn save_forfeit(&self, forfeit: ForfeitRecord) -> Result<(), Error> {
+        self.forfeits.write().unwrap().push(forfeit);
+
+        Ok(())
+    }
+
+    fn load_forfeits(&self) -> Result<Vec<ForfeitRecord>, Error> {
+        Ok(self.forfeits.read().unwrap().clone())
+    }
+
+    fn save_birthday(&self, birthday: WalletBirthday) -> Result<(), Error> {
+        *self.birthday.write().unwrap() = Some(birthday);
+
+        Ok(())
+    }
+
+    fn load_birthday(&self) -> Result<Option<WalletBirthday>, Error> {
+        Ok(*self.birthday.read().unwrap())
+    }
+
+    fn save_config(&self, config: ClientConfig) -> Result<(), Error> {
+        *self.config.write().unwrap() = Some(config);
+
+        Ok(())
+    }
+
+    fn load_config(&self) -> Result<Option<ClientConfig>, Error> {
+        Ok(self.config.read().unwrap().clone())
+    }
+
+    fn save_claimed_delivery(&self, outpoint: OutPoint) -> Result<(), Error> {
+        self.claimed_deliveries.write().unwrap().push(outpoint);
+
+        Ok(())
+    }
+
+    fn load_claimed_deliveries(&self) -> Result<Vec<OutPoint>, Error> {
+        Ok(self.claimed_deliveries.read().unwrap().clone())
+    }
+
+    fn save_deliveries_initialized(&self) -> Result<(), Error> {
+        *self.deliveries_initialized.write().unwrap() = true;
+
+        Ok(())
+    }
+
+    fn load_deliveries_initialized(&self) -> Result<bool, Error> {
+        Ok(*self.deliveries_initialized.read().unwrap())
+    }
+
+    fn save_exit_tx(&self, exit_tx: ExitTx) -> Result<(), Error> {
+        self.exit_txs
+            .write()
+            .unwrap()
+            .insert(exit_tx.tx.compute_txid(), exit_tx);
+
+        Ok(())
+    }
+
+    fn load_exit_txs(&self) -> Result<Vec<ExitTx>, Error> {
+        Ok(self.exit_txs.read().unwrap().values().cloned().collect())
+    }
+
+    fn delete_exit_tx(&self, txid: Txid) -> Result<(), Error> {
+        self.exit_txs.write().unwrap().remove(&txid);
+
+        Ok(())
+    }
+
+    fn save_imported_vtxo(&self, address: ArkAddress, vtxo: VtxoOutPoint) -> Result<(), Error> {
+        self.imported_vtxos
+            .write()
+            .unwrap()
+            .entry(address.encode())
+            .or_default()
+            .push(vtxo);
+
+        Ok(())
+    }
+
+    fn load_imported_vtxos(&self, address: &ArkAddress) -> Result<Vec<VtxoOutPoint>, Error> {
+        Ok(self
+            .imported_vtxos
+            .read()
+            .unwrap()
+            .get(&address.encode())
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn delete_imported_vtxo(&self, address: &ArkAddress, outpoint: OutPoint) -> Result<(), Error> {
+        if let Some(vtxos) = self
+            .imported_vtxos
+            .write()
+            .unwrap()
+            .get_mut(&address.encode())
+        {
+            vtxos.retain(|vtxo| vtxo.outpoint != outpoint);
+        }
+
+        Ok(())
+    }
+
+    fn save_contact(&self, contact: Contact) -> Result<(), Error> {
+        self.contacts
+            .write()
+            .unwrap()
+            .insert(contact.name.clone(), contact);
+
+        Ok(())
+    }
+
+    fn load_contacts(&self) -> Result<Vec<Contact>, Error> {
+        Ok(self.contacts.read().unwrap().values().cloned().collect())
+    }
+
+    fn delete_contact(&self, name: &str) -> Result<(), Error> {
+        self.contacts.write().unwrap().remove(name);
+
+        Ok(())
+    }
+
+    fn save_receipt(&self, receipt: PaymentReceipt) -> Result<(), Error> {
+        self.receipts
+            .write()
+            .unwrap()
+            .insert(receipt.vtxo_outpoint, receipt);
+
+        Ok(())
+    }
+
+    fn load_receipts(&self) -> Result<Vec<PaymentReceipt>, Error> {
+        Ok(self.receipts.read().unwrap().values().cloned().collect())
+    }
+
+    fn save_processed_event(&self, event_id: String) -> Result<(), Error> {
+        self.processed_events.write().unwrap().push(event_id);
+
+        Ok(())
+    }
+
+    fn load_processed_events(&self) -> Result<Vec<String>, Error> {
+        Ok(self.processed_events.read().unwrap().clone())
+    }
+
+    fn prune_processed_events(&self, keep: usize) -> Result<(), Error> {
+        let mut processed_events = self.processed_events.write().unwrap();
+
+        let n_pruned = processed_events.len().saturating_sub(keep);
+        processed_events.drain(..n_pruned);
+
+        Ok(())
+    }
+
+    fn save_vtxo_exit(&self, exit: VtxoExit) -> Result<(), Error> {
+        self.vtxo_exits
+            .write()
+            .unwrap()
+            .insert(exit.vtxo_outpoint, exit);
+
+        Ok(())
+    }
+
+    fn load_vtxo_exits(&self) -> Result<Vec<VtxoExit>, Error> {
+        Ok(self.vtxo_exits.read().unwrap().values().cloned().collect())
+    }
+
+    fn save_watched_address(&self, watched: WatchedAddress) -> Result<(), Error> {
+        self.watched_addresses
+            .write()
+            .unwrap()
+            .insert(watched.address.encode(), watched);
+
+        Ok(())
+    }
+
+    fn load_watched_addresses(&self) -> Result<Vec<WatchedAddress>, Error> {
+        Ok(self
+            .watched_addresses
+            .read()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    fn delete_watched_address(&self, address: &ArkAddress) -> Result<(), Error> {
+        self.watched_addresses
+            .write()
+            .unwrap()
+            .remove(&address.encode());
+
+        Ok(())
+    }
+
+    fn save_archived_transaction(&self, tx: ArkTransaction) -> Result<(), Error> {
+        let is_incoming = tx.amount().direction == Direction::Incoming;
+
+        self.archived_transactions
+            .write()
+            .unwrap()
+            .insert((tx.txid(), is_incoming), tx);
+
+        Ok(())
+    }
+
+    fn load_archived_transactions(&self) -> Result<Vec<ArkTransaction>, Error> {
+        Ok(self
+            .archived_transactions
+            .read()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect())
+    }
+}
+
+#[allow(unused)]
 pub async fn set_up_client(
     name: String,
     nigiri: Arc<Nigiri>,
     secp: Secp256k1<All>,
+) -> Client<Nigiri, ark_bdk_wallet::Wallet<InMemoryDb>> {
+    set_up_client_with_server_url(name, nigiri, secp, "http://localhost:7070").await
+}
+
+pub async fn set_up_client_with_server_url(
+    name: String,
+    nigiri: Arc<Nigiri>,
+    secp: Secp256k1<All>,
+    ark_server_url: &str,
 ) -> Client<Nigiri, ark_bdk_wallet::Wallet<InMemoryDb>> {
     let mut rng = thread_rng();
 
@@ -270,16 +735,49 @@ pub async fn set_up_client(
             .unwrap();
     let wallet = Arc::new(wallet);
 
-    OfflineClient::new(
-        name,
-        kp,
-        nigiri,
-        wallet.clone(),
-        "http://localhost:7070".to_string(),
-    )
-    .connect()
-    .await
-    .unwrap()
+    OfflineClient::new(name, kp, nigiri, wallet.clone(), ark_server_url.to_string())
+        .connect()
+        .await
+        .unwrap()
+}
+
+/// The outpoints of the VTXOs of `client` which can currently be spent.
+#[allow(unused)]
+pub async fn spendable_vtxo_outpoints(
+    client: &Client<Nigiri, ark_bdk_wallet::Wallet<InMemoryDb>>,
+) -> Vec<OutPoint> {
+    client
+        .spendable_vtxos()
+        .await
+        .unwrap()
+        .into_iter()
+        .flat_map(|(vtxo_outpoints, _)| vtxo_outpoints)
+        .map(|vtxo_outpoint| vtxo_outpoint.outpoint)
+        .collect()
+}
+
+/// Advance the chain time of `nigiri` just enough for the exit paths of all of `outpoints` to be
+/// open.
+#[allow(unused)]
+pub async fn skip_exit_delays(
+    nigiri: &Nigiri,
+    client: &Client<Nigiri, ark_bdk_wallet::Wallet<InMemoryDb>>,
+    outpoints: &[OutPoint],
+) {
+    let mut claimable_at = Vec::new();
+    for outpoint in outpoints {
+        let timestamp = client
+            .exit_claimable_at(*outpoint)
+            .await
+            .unwrap()
+            .expect("confirmed output with time-based exit delay");
+
+        claimable_at.push(timestamp);
+    }
+
+    if let Some(latest) = claimable_at.into_iter().max() {
+        nigiri.advance_chain_time_to(latest);
+    }
 }
 
 pub fn init_tracing() {
@@ -0,0 +1,110 @@
+#![allow(clippy::unwrap_used)]
+
+use ark_client::Blockchain;
+use ark_core::exit_delay::ExitDelay;
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::key::Secp256k1;
+use bitcoin::Amount;
+use common::init_tracing;
+use common::set_up_client;
+use common::Nigiri;
+use jiff::SignedDuration;
+use jiff::Timestamp;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod common;
+
+/// The exit path of a boarding output opens when the median time past of the tip is its exit delay
+/// past that of the block before the one which confirmed it, and not before.
+#[tokio::test]
+#[ignore]
+pub async fn boarding_output_exit_delay_boundary() {
+    init_tracing();
+
+    let nigiri = Arc::new(Nigiri::new());
+
+    let secp = Secp256k1::new();
+
+    let alice = set_up_client("alice".to_string(), nigiri.clone(), secp.clone()).await;
+
+    let alice_boarding_address = alice.get_boarding_address().unwrap();
+
+    // Confirm the boarding output in a block with a time of our choosing.
+    let txid = nigiri.send_unconfirmed(&alice_boarding_address, Amount::ONE_BTC);
+    let confirmed_at = Timestamp::now().as_second() + 60;
+    nigiri.mine_at(1, confirmed_at).await;
+
+    let boarding_outpoint = nigiri
+        .find_outpoints(&alice_boarding_address)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|utxo| utxo.outpoint.txid == txid)
+        .unwrap();
+    assert_eq!(
+        boarding_outpoint.confirmation_blocktime,
+        Some(confirmed_at as u64)
+    );
+    // BIP68 ignores the timestamp of the confirming block, which miners can choose freely.
+    let confirmed_median_time_past = nigiri
+        .get_median_time_past(boarding_outpoint.confirmation_height.unwrap() - 1)
+        .await
+        .unwrap();
+    let boarding_outpoint = boarding_outpoint.outpoint;
+
+    let exit_delay = ExitDelay::from_sequence(alice.server_info.unilateral_exit_delay).unwrap();
+    let claimable_at = alice
+        .exit_claimable_at(boarding_outpoint)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        claimable_at.as_second(),
+        (confirmed_median_time_past + exit_delay.duration().as_secs()) as i64
+    );
+
+    let to_address = bitcoin::Address::<NetworkUnchecked>::from_str(
+        "bcrt1q8df4sx3hz63tq44ve3q6tr4qz0q30usk5sntpt",
+    )
+    .unwrap()
+    .assume_checked();
+
+    // Advancing the chain time moves the boundary back by exactly as much.
+    nigiri.advance_chain_time(Duration::from_secs(512));
+    let claimable_at = {
+        let shifted = alice
+            .exit_claimable_at(boarding_outpoint)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(shifted, claimable_at - SignedDuration::from_secs(512));
+
+        shifted
+    };
+
+    // The client checks the exit delay against the median time past of the tip rather than the
+    // wall clock, so we can stop just short of the boundary.
+    nigiri.advance_chain_time_to(claimable_at - SignedDuration::from_secs(1));
+
+    assert!(alice
+        .create_send_on_chain_transaction(to_address.clone(), Amount::from_btc(0.7).unwrap())
+        .await
+        .is_err());
+
+    let claimable_at = alice
+        .exit_claimable_at(boarding_outpoint)
+        .await
+        .unwrap()
+        .unwrap();
+    nigiri.advance_chain_time_to(claimable_at);
+
+    let (tx, prevouts) = alice
+        .create_send_on_chain_transaction(to_address, Amount::from_btc(0.7).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(tx.input.len(), 1);
+    assert_eq!(prevouts.len(), 1);
+}
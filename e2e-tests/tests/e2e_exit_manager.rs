@@ -0,0 +1,100 @@
+#![allow(clippy::unwrap_used)]
+
+use ark_client::wallet::VtxoExitStatus;
+use ark_client::Blockchain;
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::key::Secp256k1;
+use bitcoin::Amount;
+use common::init_tracing;
+use common::set_up_client;
+use common::skip_exit_delays;
+use common::spendable_vtxo_outpoints;
+use common::Nigiri;
+use rand::thread_rng;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod common;
+
+/// The exit manager takes a VTXO through every step of a unilateral exit, until the VTXO is swept
+/// to the destination.
+#[tokio::test]
+#[ignore]
+pub async fn exit_manager_sweeps_vtxo() {
+    init_tracing();
+
+    let nigiri = Arc::new(Nigiri::new());
+    let mut rng = thread_rng();
+
+    let alice = set_up_client("alice".to_string(), nigiri.clone(), Secp256k1::new()).await;
+
+    nigiri
+        .faucet_fund(&alice.get_boarding_address().unwrap(), Amount::ONE_BTC)
+        .await;
+
+    alice.board(&mut rng).await.unwrap();
+
+    let vtxo_outpoints = tokio::time::timeout(Duration::from_secs(30), async {
+        loop {
+            let vtxo_outpoints = spendable_vtxo_outpoints(&alice).await;
+            if !vtxo_outpoints.is_empty() {
+                return vtxo_outpoints;
+            }
+
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    let destination = bitcoin::Address::<NetworkUnchecked>::from_str(
+        "bcrt1q8df4sx3hz63tq44ve3q6tr4qz0q30usk5sntpt",
+    )
+    .unwrap()
+    .assume_checked();
+
+    let exit_manager = alice.exit_manager();
+    exit_manager
+        .start(&vtxo_outpoints, destination.clone())
+        .await
+        .unwrap();
+
+    // The same VTXOs cannot be exited twice.
+    assert!(exit_manager
+        .start(&vtxo_outpoints, destination)
+        .await
+        .is_err());
+
+    let exits = exit_manager.advance().await.unwrap();
+    assert!(exits
+        .iter()
+        .all(|exit| exit.status == VtxoExitStatus::Committing));
+
+    nigiri.mine(1).await;
+
+    let exits = exit_manager.advance().await.unwrap();
+    assert!(exits
+        .iter()
+        .all(|exit| exit.status == VtxoExitStatus::WaitingForExitDelay));
+
+    skip_exit_delays(&nigiri, &alice, &vtxo_outpoints).await;
+
+    let exits = exit_manager.advance().await.unwrap();
+    let sweep_txids = exits
+        .iter()
+        .map(|exit| match exit.status {
+            VtxoExitStatus::Swept { txid } => txid,
+            status => panic!("unexpected exit status {status:?}"),
+        })
+        .collect::<Vec<_>>();
+
+    nigiri.mine(1).await;
+
+    let exits = exit_manager.run(Duration::from_secs(1)).await;
+    assert_eq!(exits.len(), vtxo_outpoints.len());
+
+    for txid in sweep_txids {
+        assert!(nigiri.find_tx(&txid).await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,196 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::common::InMemoryDb;
+use ark_bdk_wallet::Wallet;
+use ark_client::Blockchain;
+use ark_core::ArkTransaction;
+use ark_core::Direction;
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::key::Secp256k1;
+use bitcoin::Amount;
+use common::init_tracing;
+use common::set_up_client;
+use common::skip_exit_delays;
+use common::spendable_vtxo_outpoints;
+use common::Nigiri;
+use rand::thread_rng;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod common;
+
+/// Walk a coin through every state we support, across two clients: boarding, sending, receiving,
+/// refreshing and finally exiting on-chain.
+#[tokio::test]
+#[ignore]
+pub async fn full_lifecycle() {
+    init_tracing();
+    let nigiri = Arc::new(Nigiri::new());
+
+    let secp = Secp256k1::new();
+    let mut rng = thread_rng();
+
+    let alice = set_up_client("alice".to_string(), nigiri.clone(), secp.clone()).await;
+    let bob = set_up_client("bob".to_string(), nigiri.clone(), secp).await;
+
+    // Board.
+
+    let fund_amount = Amount::ONE_BTC;
+    let alice_boarding_address = alice.get_boarding_address().unwrap();
+    let boarding_outpoint = nigiri
+        .faucet_fund(&alice_boarding_address, fund_amount)
+        .await;
+
+    assert_eq!(
+        alice.offchain_balance().await.unwrap().total(),
+        Amount::ZERO
+    );
+
+    alice.board(&mut rng).await.unwrap();
+    wait_until_balance(&alice, fund_amount, Amount::ZERO).await;
+
+    let alice_history = alice.transaction_history().await.unwrap();
+    tracing::info!(?alice_history, "Alice boarded");
+
+    // Settling the boarding output does not add an entry of its own.
+    assert_eq!(alice_history.len(), 1);
+    assert!(matches!(
+        alice_history[0],
+        ArkTransaction::Boarding { txid, .. } if txid == boarding_outpoint.txid
+    ));
+    assert_eq!(alice_history[0].amount().direction, Direction::Incoming);
+    assert_eq!(alice_history[0].amount().net, fund_amount);
+
+    // Send and receive.
+
+    let send_amount = Amount::from_sat(100_000);
+    let (bob_offchain_address, _) = bob.get_offchain_address();
+
+    let redeem_tx = alice
+        .send_vtxo(bob_offchain_address, send_amount)
+        .await
+        .unwrap();
+    let redeem_txid = redeem_tx.unsigned_tx.compute_txid();
+    let redeem_fee = redeem_tx.fee().unwrap();
+
+    let alice_change = fund_amount - send_amount - redeem_fee;
+    wait_until_balance(&alice, Amount::ZERO, alice_change).await;
+    wait_until_balance(&bob, Amount::ZERO, send_amount).await;
+
+    let alice_history = alice.transaction_history().await.unwrap();
+    let bob_history = bob.transaction_history().await.unwrap();
+    tracing::info!(?alice_history, ?bob_history, "Alice sent VTXO to Bob");
+
+    let sent = alice_history
+        .iter()
+        .find(|tx| tx.txid() == redeem_txid)
+        .unwrap();
+    assert!(matches!(sent, ArkTransaction::Redeem { .. }));
+    assert_eq!(sent.amount().direction, Direction::Outgoing);
+    assert_eq!(sent.amount().gross, fund_amount);
+    assert_eq!(sent.amount().net, send_amount + redeem_fee);
+    assert_eq!(
+        sent.counterparty_address(alice.server_info.network, alice.server_info.pk.into())
+            .map(|address| address.encode()),
+        Some(bob_offchain_address.encode())
+    );
+
+    assert_eq!(bob_history.len(), 1);
+    assert!(matches!(
+        bob_history[0],
+        ArkTransaction::Redeem {
+            txid,
+            is_settled: false,
+            ..
+        } if txid == redeem_txid
+    ));
+    assert_eq!(bob_history[0].amount().direction, Direction::Incoming);
+    assert_eq!(bob_history[0].amount().net, send_amount);
+
+    // Refresh.
+
+    let refresh_txids = bob
+        .refresh_vtxos_near_expiry(&mut rng, Duration::from_secs(365 * 24 * 60 * 60))
+        .await
+        .unwrap();
+    assert_eq!(refresh_txids.len(), 1);
+
+    wait_until_balance(&bob, send_amount, Amount::ZERO).await;
+
+    let bob_history = bob.transaction_history().await.unwrap();
+    tracing::info!(?bob_history, "Bob refreshed his VTXO");
+
+    // Refreshing a VTXO is not a payment, but it settles the one we received.
+    assert_eq!(bob_history.len(), 1);
+    assert!(matches!(
+        bob_history[0],
+        ArkTransaction::Redeem {
+            is_settled: true,
+            ..
+        }
+    ));
+
+    // Alice's change is unaffected by Bob's activity.
+    wait_until_balance(&alice, Amount::ZERO, alice_change).await;
+
+    // Exit.
+
+    let vtxo_outpoints = spendable_vtxo_outpoints(&bob).await;
+
+    bob.commit_vtxos_on_chain().await.unwrap();
+    wait_until_balance(&bob, Amount::ZERO, Amount::ZERO).await;
+
+    nigiri.mine(1).await;
+
+    // The exit path only opens once the VTXO has been confirmed for long enough.
+    skip_exit_delays(&nigiri, &bob, &vtxo_outpoints).await;
+
+    let exit_amount = Amount::from_sat(50_000);
+    let exit_txid = bob
+        .send_on_chain(
+            bitcoin::Address::<NetworkUnchecked>::from_str(
+                "bcrt1q8df4sx3hz63tq44ve3q6tr4qz0q30usk5sntpt",
+            )
+            .unwrap()
+            .assume_checked(),
+            exit_amount,
+        )
+        .await
+        .unwrap();
+
+    nigiri.mine(1).await;
+
+    let exit_tx = nigiri.find_tx(&exit_txid).await.unwrap().unwrap();
+    assert!(exit_tx
+        .output
+        .iter()
+        .any(|output| output.value == exit_amount));
+
+    let bob_offchain_balance = bob.offchain_balance().await.unwrap();
+    tracing::info!(%exit_txid, ?bob_offchain_balance, "Bob exited on-chain");
+
+    assert_eq!(bob_offchain_balance.total(), Amount::ZERO);
+}
+
+async fn wait_until_balance(
+    client: &ark_client::Client<Nigiri, Wallet<InMemoryDb>>,
+    confirmed_target: Amount,
+    pending_target: Amount,
+) {
+    tokio::time::timeout(Duration::from_secs(30), async {
+        loop {
+            let offchain_balance = client.offchain_balance().await.unwrap();
+
+            if offchain_balance.confirmed() == confirmed_target
+                && offchain_balance.pending() == pending_target
+            {
+                return;
+            }
+
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
@@ -0,0 +1,237 @@
+//! Payments between `ark-rs` and the reference Go client of Ark, against the same Ark server.
+//!
+//! Only built with the `interop` feature. The Go client CLI is run from `$ARK_GO_CLIENT`, or `ark`
+//! if unset, e.g.:
+//!
+//! ```sh
+//! ARK_GO_CLIENT=ark-go/ark/client/build/ark just interop-tests
+//! ```
+
+#![cfg(feature = "interop")]
+#![allow(clippy::unwrap_used)]
+
+use ark_bdk_wallet::Wallet;
+use ark_client::Client;
+use ark_core::ArkAddress;
+use bitcoin::key::Secp256k1;
+use bitcoin::Address;
+use bitcoin::Amount;
+use common::init_tracing;
+use common::set_up_client;
+use common::InMemoryDb;
+use common::Nigiri;
+use rand::thread_rng;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod common;
+
+/// The password protecting the wallet of the Go client.
+const GO_CLIENT_PASSWORD: &str = "password";
+
+/// Both clients can spend what the other sends, starting with the Go client boarding.
+#[tokio::test]
+#[ignore]
+pub async fn go_client_pays_rust_client() {
+    init_tracing();
+
+    let nigiri = Arc::new(Nigiri::new());
+    let mut rng = thread_rng();
+
+    let alice = set_up_client("alice".to_string(), nigiri.clone(), Secp256k1::new()).await;
+    let go = GoClient::init("go-bob");
+
+    nigiri
+        .faucet_fund(&go.boarding_address(), Amount::ONE_BTC)
+        .await;
+    go.settle();
+    go.wait_until_offchain_balance(Amount::ONE_BTC).await;
+
+    // The Go client pays us...
+    let (alice_address, _) = alice.get_offchain_address();
+    let go_payment = Amount::from_sat(100_000);
+    go.send(&alice_address.to_string(), go_payment);
+    wait_until_offchain_total(&alice, go_payment).await;
+
+    // ... and we can spend what it sent, back to the Go client.
+    let alice_payment = Amount::from_sat(40_000);
+    alice
+        .send_vtxo(go.offchain_address(), alice_payment)
+        .await
+        .unwrap();
+    go.wait_until_offchain_balance(Amount::ONE_BTC - go_payment + alice_payment)
+        .await;
+
+    // The Go client can settle what we sent in a round.
+    go.settle();
+
+    // Settling our change proves the VTXOs built from the Go client's payment are valid too.
+    alice.board(&mut rng).await.unwrap();
+    wait_until_offchain_total(&alice, go_payment - alice_payment).await;
+}
+
+/// Both clients can spend what the other sends, starting with us boarding.
+#[tokio::test]
+#[ignore]
+pub async fn rust_client_pays_go_client() {
+    init_tracing();
+
+    let nigiri = Arc::new(Nigiri::new());
+    let mut rng = thread_rng();
+
+    let alice = set_up_client("alice".to_string(), nigiri.clone(), Secp256k1::new()).await;
+    let go = GoClient::init("go-carol");
+
+    nigiri
+        .faucet_fund(&alice.get_boarding_address().unwrap(), Amount::ONE_BTC)
+        .await;
+    alice.board(&mut rng).await.unwrap();
+    wait_until_offchain_total(&alice, Amount::ONE_BTC).await;
+
+    // We pay the Go client...
+    let alice_payment = Amount::from_sat(100_000);
+    alice
+        .send_vtxo(go.offchain_address(), alice_payment)
+        .await
+        .unwrap();
+    go.wait_until_offchain_balance(alice_payment).await;
+
+    // ... and it can spend what we sent, back to us.
+    let (alice_address, _) = alice.get_offchain_address();
+    let go_payment = Amount::from_sat(40_000);
+    go.send(&alice_address.to_string(), go_payment);
+    wait_until_offchain_total(&alice, Amount::ONE_BTC - alice_payment + go_payment).await;
+
+    go.settle();
+    go.wait_until_offchain_balance(alice_payment - go_payment)
+        .await;
+}
+
+/// The reference Go client of Ark, driven through its CLI.
+struct GoClient {
+    binary: String,
+    datadir: PathBuf,
+}
+
+impl GoClient {
+    /// Create a wallet for the Go client in a fresh data directory, connected to the same Ark
+    /// server and explorer as our clients.
+    fn init(name: &str) -> Self {
+        let binary = std::env::var("ARK_GO_CLIENT").unwrap_or_else(|_| "ark".to_string());
+        let datadir = std::env::temp_dir().join(format!(
+            "ark-interop-{name}-{}",
+            jiff::Timestamp::now().as_millisecond()
+        ));
+
+        let client = Self { binary, datadir };
+        client.run(&[
+            "init",
+            "--network",
+            "regtest",
+            "--password",
+            GO_CLIENT_PASSWORD,
+            "--server-url",
+            "localhost:7070",
+            "--explorer",
+            "http://localhost:3000",
+        ]);
+
+        client
+    }
+
+    fn boarding_address(&self) -> Address {
+        let addresses = self.run(&["receive"]);
+
+        Address::from_str(addresses["boarding_address"].as_str().unwrap())
+            .unwrap()
+            .assume_checked()
+    }
+
+    fn offchain_address(&self) -> ArkAddress {
+        let addresses = self.run(&["receive"]);
+
+        ArkAddress::decode(addresses["offchain_address"].as_str().unwrap()).unwrap()
+    }
+
+    fn offchain_balance(&self) -> Amount {
+        let balance = self.run(&["balance"]);
+
+        Amount::from_sat(balance["offchain_balance"]["total"].as_u64().unwrap())
+    }
+
+    /// Board our boarding outputs and settle our VTXOs in the next round.
+    fn settle(&self) {
+        self.run(&["settle", "--password", GO_CLIENT_PASSWORD]);
+    }
+
+    fn send(&self, address: &str, amount: Amount) {
+        self.run(&[
+            "send",
+            "--to",
+            address,
+            "--amount",
+            &amount.to_sat().to_string(),
+            "--password",
+            GO_CLIENT_PASSWORD,
+        ]);
+    }
+
+    async fn wait_until_offchain_balance(&self, target: Amount) {
+        tokio::time::timeout(Duration::from_secs(60), async {
+            while self.offchain_balance() != target {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        })
+        .await
+        .unwrap_or_else(|_| {
+            panic!(
+                "Go client balance is {} instead of {target}",
+                self.offchain_balance()
+            )
+        });
+    }
+
+    /// Run the CLI with `args`, returning its JSON output.
+    fn run(&self, args: &[&str]) -> Value {
+        let output = Command::new(&self.binary)
+            .arg("--datadir")
+            .arg(&self.datadir)
+            .args(args)
+            .output()
+            .unwrap_or_else(|e| panic!("failed to run Go client {}: {e}", self.binary));
+
+        assert!(
+            output.status.success(),
+            "Go client {args:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        if stdout.trim().is_empty() {
+            return Value::Null;
+        }
+
+        serde_json::from_str(&stdout)
+            .unwrap_or_else(|e| panic!("invalid output of Go client {args:?}: {e}: {stdout}"))
+    }
+}
+
+async fn wait_until_offchain_total(client: &Client<Nigiri, Wallet<InMemoryDb>>, target: Amount) {
+    tokio::time::timeout(Duration::from_secs(60), async {
+        loop {
+            let offchain_balance = client.offchain_balance().await.unwrap();
+
+            if offchain_balance.total() == target {
+                return;
+            }
+
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
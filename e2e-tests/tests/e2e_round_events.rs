@@ -0,0 +1,72 @@
+#![allow(clippy::unwrap_used)]
+
+use ark_client::events::RoundEvent;
+use bitcoin::key::Secp256k1;
+use bitcoin::Amount;
+use common::init_tracing;
+use common::set_up_client;
+use common::Nigiri;
+use futures::StreamExt;
+use rand::thread_rng;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod common;
+
+/// The round we join goes through every step, and each step is reported once even if the round
+/// events are streamed again.
+#[tokio::test]
+#[ignore]
+pub async fn round_events() {
+    init_tracing();
+
+    let nigiri = Arc::new(Nigiri::new());
+    let mut rng = thread_rng();
+
+    let alice = set_up_client("alice".to_string(), nigiri.clone(), Secp256k1::new()).await;
+
+    nigiri
+        .faucet_fund(&alice.get_boarding_address().unwrap(), Amount::ONE_BTC)
+        .await;
+
+    let mut events = alice.round_events().await.unwrap();
+
+    alice.board(&mut rng).await.unwrap();
+
+    // We do not know which round we were part of, but every round which finalizes goes through
+    // all the steps.
+    let mut steps = Vec::new();
+    tokio::time::timeout(Duration::from_secs(30), async {
+        while let Some(event) = events.next().await {
+            let event = event.unwrap();
+            let is_finalized = matches!(event, RoundEvent::Finalized { .. });
+
+            steps.push(event);
+
+            if is_finalized {
+                break;
+            }
+        }
+    })
+    .await
+    .unwrap();
+
+    let finalized = steps.last().unwrap();
+    let round_steps = steps
+        .iter()
+        .filter(|event| event.round_id() == finalized.round_id())
+        .collect::<Vec<_>>();
+
+    assert!(matches!(round_steps[0], RoundEvent::Started { .. }));
+    assert!(matches!(round_steps[1], RoundEvent::Signing { .. }));
+    assert!(matches!(round_steps[2], RoundEvent::Finalization { .. }));
+    assert!(matches!(round_steps[3], RoundEvent::Finalized { .. }));
+
+    // Reopening the stream does not report the same events again.
+    let mut events = alice.round_events().await.unwrap();
+    if let Ok(Some(event)) = tokio::time::timeout(Duration::from_secs(5), events.next()).await {
+        let event = event.unwrap();
+
+        assert!(!steps.contains(&event), "replayed event {event:?}");
+    }
+}
@@ -0,0 +1,57 @@
+#![allow(clippy::unwrap_used)]
+
+use ark_client::round_handle::RoundHandle;
+use ark_client::round_handle::RoundPhase;
+use bitcoin::key::Secp256k1;
+use bitcoin::Amount;
+use common::init_tracing;
+use common::set_up_client;
+use common::Nigiri;
+use rand::thread_rng;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod common;
+
+/// Withdrawing a queued registration makes boarding fail, and the same funds can be boarded again
+/// right after.
+#[tokio::test]
+#[ignore]
+pub async fn withdraw_round_registration() {
+    init_tracing();
+
+    let nigiri = Arc::new(Nigiri::new());
+    let mut rng = thread_rng();
+
+    let alice = set_up_client("alice".to_string(), nigiri.clone(), Secp256k1::new()).await;
+
+    nigiri
+        .faucet_fund(&alice.get_boarding_address().unwrap(), Amount::ONE_BTC)
+        .await;
+
+    let handle = RoundHandle::new();
+
+    let (res, ()) = tokio::join!(alice.board_with_handle(&mut rng, &handle), async {
+        while handle.phase() != RoundPhase::Registered {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        handle.withdraw().unwrap();
+    });
+
+    let err = res.unwrap_err();
+    assert!(err.is_round_withdrawn(), "unexpected error: {err}");
+    assert_eq!(handle.phase(), RoundPhase::Withdrawn);
+
+    // The handle refuses any further registration.
+    assert!(alice
+        .board_with_handle(&mut rng, &handle)
+        .await
+        .unwrap_err()
+        .is_round_withdrawn());
+
+    alice.board(&mut rng).await.unwrap();
+
+    let balance = alice.offchain_balance().await.unwrap();
+    assert_eq!(balance.confirmed(), Amount::ONE_BTC);
+}
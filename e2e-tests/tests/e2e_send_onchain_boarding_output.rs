@@ -1,10 +1,12 @@
 #![allow(clippy::unwrap_used)]
 
+use ark_client::consensus_verify::verify_transaction;
 use bitcoin::address::NetworkUnchecked;
 use bitcoin::key::Secp256k1;
 use bitcoin::Amount;
 use common::init_tracing;
 use common::set_up_client;
+use common::skip_exit_delays;
 use common::Nigiri;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -18,20 +20,19 @@ pub async fn send_onchain_boarding_output() {
 
     let nigiri = Arc::new(Nigiri::new());
 
-    // To be able to spend a boarding output it needs to have been confirmed for at least 1_024
-    // seconds.
-    nigiri.set_outpoint_blocktime_offset(1_024);
-
     let secp = Secp256k1::new();
 
     let alice = set_up_client("alice".to_string(), nigiri.clone(), secp.clone()).await;
 
     let alice_boarding_address = alice.get_boarding_address().unwrap();
 
-    nigiri
+    let boarding_outpoint = nigiri
         .faucet_fund(&alice_boarding_address, Amount::ONE_BTC)
         .await;
 
+    // A boarding output can only be spent once its exit delay has passed since it was confirmed.
+    skip_exit_delays(&nigiri, &alice, &[boarding_outpoint]).await;
+
     let (tx, prevouts) = alice
         .create_send_on_chain_transaction(
             bitcoin::Address::<NetworkUnchecked>::from_str(
@@ -47,25 +48,5 @@ pub async fn send_onchain_boarding_output() {
     assert_eq!(tx.input.len(), 1);
     assert_eq!(prevouts.len(), 1);
 
-    for (i, prevout) in prevouts.iter().enumerate() {
-        let script_pubkey = prevout.script_pubkey.clone();
-        let amount = prevout.value;
-        let spent_outputs = prevouts
-            .iter()
-            .map(|o| bitcoinconsensus::Utxo {
-                script_pubkey: o.script_pubkey.as_bytes().as_ptr(),
-                script_pubkey_len: o.script_pubkey.len() as u32,
-                value: o.value.to_sat() as i64,
-            })
-            .collect::<Vec<_>>();
-
-        bitcoinconsensus::verify(
-            script_pubkey.as_bytes(),
-            amount.to_sat(),
-            bitcoin::consensus::serialize(&tx).as_slice(),
-            Some(&spent_outputs),
-            i,
-        )
-        .expect("valid input");
-    }
+    verify_transaction(&tx, &prevouts).expect("valid transaction");
 }
@@ -2,11 +2,14 @@
 
 use crate::common::InMemoryDb;
 use ark_bdk_wallet::Wallet;
+use ark_client::consensus_verify::verify_transaction;
 use bitcoin::address::NetworkUnchecked;
 use bitcoin::key::Secp256k1;
 use bitcoin::Amount;
 use common::init_tracing;
 use common::set_up_client;
+use common::skip_exit_delays;
+use common::spendable_vtxo_outpoints;
 use common::Nigiri;
 use rand::thread_rng;
 use std::str::FromStr;
@@ -46,6 +49,8 @@ pub async fn send_onchain_vtxo_and_boarding_output() {
     alice.board(&mut rng).await.unwrap();
     wait_until_balance(&alice, fund_amount, Amount::ZERO).await;
 
+    let vtxo_outpoints = spendable_vtxo_outpoints(&alice).await;
+
     alice.commit_vtxos_on_chain().await.unwrap();
     wait_until_balance(&alice, Amount::ZERO, Amount::ZERO).await;
 
@@ -53,7 +58,7 @@ pub async fn send_onchain_vtxo_and_boarding_output() {
     nigiri.mine(1).await;
 
     let alice_boarding_address = alice.get_boarding_address().unwrap();
-    nigiri
+    let boarding_outpoint = nigiri
         .faucet_fund(&alice_boarding_address, Amount::ONE_BTC)
         .await;
 
@@ -62,9 +67,10 @@ pub async fn send_onchain_vtxo_and_boarding_output() {
     assert_eq!(offchain_balance.confirmed(), Amount::ZERO);
     assert_eq!(offchain_balance.pending(), Amount::ZERO);
 
-    // To be able to spend a VTXO or a boarding output it needs to have been confirmed for at least
-    // 1024 seconds.
-    nigiri.set_outpoint_blocktime_offset(1024);
+    // To be able to spend a VTXO or a boarding output, its exit delay must have passed since it was
+    // confirmed.
+    let outpoints = [vtxo_outpoints, vec![boarding_outpoint]].concat();
+    skip_exit_delays(&nigiri, &alice, &outpoints).await;
 
     let (tx, prevouts) = alice
         .create_send_on_chain_transaction(
@@ -81,27 +87,7 @@ pub async fn send_onchain_vtxo_and_boarding_output() {
     assert_eq!(tx.input.len(), 2);
     assert_eq!(prevouts.len(), 2);
 
-    for (i, prevout) in prevouts.iter().enumerate() {
-        let script_pubkey = prevout.script_pubkey.clone();
-        let amount = prevout.value;
-        let spent_outputs = prevouts
-            .iter()
-            .map(|o| bitcoinconsensus::Utxo {
-                script_pubkey: o.script_pubkey.as_bytes().as_ptr(),
-                script_pubkey_len: o.script_pubkey.len() as u32,
-                value: o.value.to_sat() as i64,
-            })
-            .collect::<Vec<_>>();
-
-        bitcoinconsensus::verify(
-            script_pubkey.as_bytes(),
-            amount.to_sat(),
-            bitcoin::consensus::serialize(&tx).as_slice(),
-            Some(&spent_outputs),
-            i,
-        )
-        .expect("valid input");
-    }
+    verify_transaction(&tx, &prevouts).expect("valid transaction");
 }
 
 async fn wait_until_balance(
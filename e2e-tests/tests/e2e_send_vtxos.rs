@@ -0,0 +1,129 @@
+#![allow(clippy::unwrap_used)]
+
+use bitcoin::key::Secp256k1;
+use bitcoin::Amount;
+use common::init_tracing;
+use common::set_up_client;
+use common::Nigiri;
+use rand::thread_rng;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod common;
+
+/// A single offchain payment pays several recipients at once, with change going back to the
+/// sender.
+#[tokio::test]
+#[ignore]
+pub async fn send_vtxos_to_several_recipients() {
+    init_tracing();
+
+    let nigiri = Arc::new(Nigiri::new());
+    let secp = Secp256k1::new();
+    let mut rng = thread_rng();
+
+    let alice = set_up_client("alice".to_string(), nigiri.clone(), secp.clone()).await;
+    let bob = set_up_client("bob".to_string(), nigiri.clone(), secp.clone()).await;
+    let carol = set_up_client("carol".to_string(), nigiri.clone(), secp).await;
+
+    nigiri
+        .faucet_fund(&alice.get_boarding_address().unwrap(), Amount::ONE_BTC)
+        .await;
+
+    alice.board(&mut rng).await.unwrap();
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    let (bob_address, _) = bob.get_offchain_address();
+    let (carol_address, _) = carol.get_offchain_address();
+
+    let to_bob = Amount::from_sat(100_000);
+    let to_carol = Amount::from_sat(200_000);
+
+    // Every amount must be above dust.
+    assert!(alice
+        .send_vtxos(vec![
+            (bob_address, to_bob),
+            (carol_address, Amount::ONE_SAT)
+        ])
+        .await
+        .is_err());
+
+    let redeem_psbt = alice
+        .send_vtxos(vec![(bob_address, to_bob), (carol_address, to_carol)])
+        .await
+        .unwrap();
+
+    // Bob, Carol and Alice's change.
+    assert_eq!(redeem_psbt.unsigned_tx.output.len(), 3);
+
+    let fee = redeem_psbt.fee().unwrap();
+
+    let alice_balance = alice.offchain_balance().await.unwrap();
+    let bob_balance = bob.offchain_balance().await.unwrap();
+    let carol_balance = carol.offchain_balance().await.unwrap();
+
+    assert_eq!(
+        alice_balance.pending(),
+        Amount::ONE_BTC - to_bob - to_carol - fee
+    );
+    assert_eq!(bob_balance.pending(), to_bob);
+    assert_eq!(carol_balance.pending(), to_carol);
+}
+
+/// A payment spends exactly the VTXOs picked by the caller.
+#[tokio::test]
+#[ignore]
+pub async fn send_vtxo_with_selected_inputs() {
+    init_tracing();
+
+    let nigiri = Arc::new(Nigiri::new());
+    let secp = Secp256k1::new();
+    let mut rng = thread_rng();
+
+    let alice = set_up_client("alice".to_string(), nigiri.clone(), secp.clone()).await;
+    let bob = set_up_client("bob".to_string(), nigiri.clone(), secp).await;
+
+    nigiri
+        .faucet_fund(&alice.get_boarding_address().unwrap(), Amount::ONE_BTC)
+        .await;
+
+    alice.board(&mut rng).await.unwrap();
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    let spendable = alice.list_spendable_outpoints().await.unwrap();
+    assert_eq!(spendable.len(), 1);
+    assert_eq!(spendable[0].amount, Amount::ONE_BTC);
+    assert!(!spendable[0].is_reserved);
+
+    let outpoint = spendable[0].outpoint;
+    let (bob_address, _) = bob.get_offchain_address();
+    let to_bob = Amount::from_sat(100_000);
+
+    // Reserved VTXOs cannot be selected.
+    let reservation_id = alice.reserve_vtxos(&[outpoint]).await.unwrap();
+    let spendable = alice.list_spendable_outpoints().await.unwrap();
+    assert!(spendable[0].is_reserved);
+    assert!(alice
+        .send_vtxo_with_inputs(&[outpoint], bob_address, to_bob)
+        .await
+        .is_err());
+
+    alice.release_reservation(reservation_id).unwrap();
+
+    let outcome = alice
+        .send_vtxo_with_inputs(&[outpoint], bob_address, to_bob)
+        .await
+        .unwrap();
+
+    let inputs = outcome
+        .redeem_psbt
+        .unsigned_tx
+        .input
+        .iter()
+        .map(|input| input.previous_output)
+        .collect::<Vec<_>>();
+    assert_eq!(inputs, vec![outpoint]);
+
+    let bob_balance = bob.offchain_balance().await.unwrap();
+    assert_eq!(bob_balance.pending(), to_bob);
+}
@@ -0,0 +1,154 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::common::InMemoryDb;
+use ark_bdk_wallet::Wallet;
+use ark_client::wallet::ExitTxStatus;
+use ark_client::Blockchain;
+use ark_client::Client;
+use ark_client::OfflineClient;
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::key::Keypair;
+use bitcoin::key::Secp256k1;
+use bitcoin::secp256k1::SecretKey;
+use bitcoin::Amount;
+use bitcoin::Network;
+use common::init_tracing;
+use common::skip_exit_delays;
+use common::spendable_vtxo_outpoints;
+use common::Nigiri;
+use rand::thread_rng;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod common;
+
+/// Restart the client in the middle of a unilateral exit and check that the exit is resumed from
+/// the persisted progress.
+#[tokio::test]
+#[ignore]
+pub async fn resume_unilateral_exit_after_restart() {
+    init_tracing();
+
+    let nigiri = Arc::new(Nigiri::new());
+
+    let secp = Secp256k1::new();
+    let mut rng = thread_rng();
+
+    let sk = SecretKey::new(&mut rng);
+    let kp = Keypair::from_secret_key(&secp, &sk);
+
+    let db = InMemoryDb::default();
+    let wallet = Wallet::new(kp, secp, Network::Regtest, "http://localhost:3000", db).unwrap();
+    let wallet = Arc::new(wallet);
+
+    let alice = connect(kp, nigiri.clone(), wallet.clone()).await;
+
+    let fund_amount = Amount::ONE_BTC;
+    let alice_boarding_address = alice.get_boarding_address().unwrap();
+    nigiri
+        .faucet_fund(&alice_boarding_address, fund_amount)
+        .await;
+
+    alice.board(&mut rng).await.unwrap();
+    wait_until_balance(&alice, fund_amount, Amount::ZERO).await;
+
+    let vtxo_outpoints = spendable_vtxo_outpoints(&alice).await;
+
+    alice.commit_vtxos_on_chain().await.unwrap();
+
+    let exit_progress = alice.exit_progress().unwrap();
+    assert!(!exit_progress.is_empty());
+    assert!(exit_progress
+        .iter()
+        .all(|exit_tx| exit_tx.status == ExitTxStatus::Broadcast));
+
+    let broadcast_txids = nigiri.broadcast_txids();
+
+    // Restart before the exit transactions are confirmed.
+    drop(alice);
+    let alice = connect(kp, nigiri.clone(), wallet.clone()).await;
+
+    alice.commit_vtxos_on_chain().await.unwrap();
+
+    // Nothing is broadcast twice.
+    assert_eq!(nigiri.broadcast_txids(), broadcast_txids);
+
+    nigiri.mine(1).await;
+
+    alice.commit_vtxos_on_chain().await.unwrap();
+
+    assert_eq!(nigiri.broadcast_txids(), broadcast_txids);
+    assert!(alice
+        .exit_progress()
+        .unwrap()
+        .iter()
+        .all(|exit_tx| exit_tx.status == ExitTxStatus::Confirmed));
+
+    // Restart again, before the exit delay has passed.
+    drop(alice);
+    let alice = connect(kp, nigiri.clone(), wallet).await;
+
+    let exit_address = bitcoin::Address::<NetworkUnchecked>::from_str(
+        "bcrt1q8df4sx3hz63tq44ve3q6tr4qz0q30usk5sntpt",
+    )
+    .unwrap()
+    .assume_checked();
+    let exit_amount = Amount::from_sat(50_000);
+
+    // The persisted progress does not let us skip the exit delay.
+    assert!(alice
+        .send_on_chain(exit_address.clone(), exit_amount)
+        .await
+        .is_err());
+
+    skip_exit_delays(&nigiri, &alice, &vtxo_outpoints).await;
+
+    let exit_txid = alice
+        .send_on_chain(exit_address, exit_amount)
+        .await
+        .unwrap();
+
+    nigiri.mine(1).await;
+
+    assert!(nigiri.find_tx(&exit_txid).await.unwrap().is_some());
+}
+
+async fn connect(
+    kp: Keypair,
+    nigiri: Arc<Nigiri>,
+    wallet: Arc<Wallet<InMemoryDb>>,
+) -> Client<Nigiri, Wallet<InMemoryDb>> {
+    OfflineClient::new(
+        "alice".to_string(),
+        kp,
+        nigiri,
+        wallet,
+        "http://localhost:7070".to_string(),
+    )
+    .connect()
+    .await
+    .unwrap()
+}
+
+async fn wait_until_balance(
+    client: &Client<Nigiri, Wallet<InMemoryDb>>,
+    confirmed_target: Amount,
+    pending_target: Amount,
+) {
+    tokio::time::timeout(Duration::from_secs(30), async {
+        loop {
+            let offchain_balance = client.offchain_balance().await.unwrap();
+
+            if offchain_balance.confirmed() == confirmed_target
+                && offchain_balance.pending() == pending_target
+            {
+                return;
+            }
+
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    })
+    .await
+    .unwrap();
+}